@@ -25,11 +25,11 @@ cp -r $REPO_ROOT/noir/target/* $REPO_ROOT/fixtures/programs/
 mkdir -p $REPO_ROOT/fixtures/keys
 
 # Get all program names from the workspace - the ordering of these is important,
-# as the hash from utxo is used in agg_utxo, and agg_utxo used in agg_agg
-PROGRAMS=("utxo" "agg_utxo" "agg_agg" "signature" "points" "migrate")
+# as the hashes from utxo, utxo4 and swap are used in agg_utxo, and agg_utxo used in agg_agg
+PROGRAMS=("utxo" "utxo4" "swap" "agg_utxo" "agg_agg" "signature" "points" "migrate")
 
 # Define which programs should use the recursive flag
-RECURSIVE_PROGRAMS=("agg_utxo" "utxo")
+RECURSIVE_PROGRAMS=("agg_utxo" "utxo" "utxo4" "swap")
 
 # Function to check if a program should use recursive flag
 is_recursive() {
@@ -77,6 +77,22 @@ for NAME in "${PROGRAMS[@]}"; do
     rm $REPO_ROOT/noir/agg_utxo/src/main.nr.bak
   fi
 
+  # Update agg_utxo/src/main.nr with the UTXO4 verification key hash
+  if [ "$NAME" == "utxo4" ]; then
+    UTXO4_VK_HASH=$(echo "$VK_HASH_OUTPUT" | grep "u256:" | cut -d' ' -f2)
+    echo "Updating agg_utxo/src/main.nr with UTXO4 verification key hash: $UTXO4_VK_HASH"
+    sed -i.bak "s/global UTXO4_VERIFICATION_KEY_HASH: Field = [0-9]*;/global UTXO4_VERIFICATION_KEY_HASH: Field = $UTXO4_VK_HASH;/" $REPO_ROOT/noir/agg_utxo/src/main.nr
+    rm $REPO_ROOT/noir/agg_utxo/src/main.nr.bak
+  fi
+
+  # Update agg_utxo/src/main.nr with the swap verification key hash
+  if [ "$NAME" == "swap" ]; then
+    SWAP_VK_HASH=$(echo "$VK_HASH_OUTPUT" | grep "u256:" | cut -d' ' -f2)
+    echo "Updating agg_utxo/src/main.nr with swap verification key hash: $SWAP_VK_HASH"
+    sed -i.bak "s/global SWAP_VERIFICATION_KEY_HASH: Field = [0-9]*;/global SWAP_VERIFICATION_KEY_HASH: Field = $SWAP_VK_HASH;/" $REPO_ROOT/noir/agg_utxo/src/main.nr
+    rm $REPO_ROOT/noir/agg_utxo/src/main.nr.bak
+  fi
+
   # Update agg_agg/src/main.nr with the agg_utxo verification key hash
   if [ "$NAME" == "agg_utxo" ]; then
     AGG_UTXO_VK_HASH=$(echo "$VK_HASH_OUTPUT" | grep "u256:" | cut -d' ' -f2)
@@ -1,6 +1,5 @@
 use std::{
     io::{Read, Write},
-    path::PathBuf,
     process::Command,
 };
 
@@ -8,7 +7,7 @@ use flate2::{Compression, read::GzEncoder};
 use tempfile::{NamedTempFile, TempDir};
 use tracing::{error, info};
 
-use super::Backend;
+use super::{Backend, BackendError, registry::bb_path};
 use crate::Result;
 
 pub struct CliBackend;
@@ -41,7 +40,7 @@ impl Backend for CliBackend {
 
         let output_dir = TempDir::new()?;
 
-        let mut cmd = Command::new(PathBuf::from("bb"));
+        let mut cmd = Command::new(bb_path());
         cmd.arg("prove")
             .arg("-v")
             .arg("--scheme")
@@ -65,9 +64,15 @@ impl Backend for CliBackend {
             cmd.arg("--oracle_hash").arg("keccak");
         }
 
-        let output = cmd.output()?;
+        let output = cmd
+            .output()
+            .map_err(|err| BackendError(format!("failed to run {}: {err}", bb_path().display())))?;
         if !output.status.success() {
             let stderr = String::from_utf8(output.stderr)?;
+            // Killed by a signal, e.g. out of memory, rather than exiting with an error
+            if output.status.code().is_none() {
+                return Err(BackendError(format!("bb was killed: {stderr}")).into());
+            }
             return Err(stderr.into());
         }
 
@@ -96,7 +101,7 @@ impl Backend for CliBackend {
         public_inputs_file.write_all(&proof[..public_inputs_len])?;
         public_inputs_file.flush()?;
 
-        let mut cmd = Command::new(PathBuf::from("bb"));
+        let mut cmd = Command::new(bb_path());
         cmd.arg("verify")
             .arg("-v")
             .arg("--scheme")
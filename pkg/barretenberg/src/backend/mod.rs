@@ -1,6 +1,9 @@
 pub mod bb_cli;
 #[cfg(feature = "bb_rs")]
 mod bb_rs;
+mod registry;
+
+pub use registry::*;
 
 use crate::Result;
 
@@ -16,7 +19,10 @@ pub trait Backend {
     fn verify(proof: &[u8], key: &[u8], oracle_hash_keccak: bool) -> Result<()>;
 }
 
-#[cfg(feature = "bb_rs")]
-pub type DefaultBackend = bb_rs::BindingBackend;
-#[cfg(not(feature = "bb_rs"))]
-pub type DefaultBackend = bb_cli::CliBackend;
+pub type DefaultBackend = RegistryBackend;
+
+/// The backend itself failed, e.g. the `bb` binary is missing or was killed, rather than
+/// rejecting the witness. [`RegistryBackend`] only falls back to the next backend on these
+#[derive(Debug, thiserror::Error)]
+#[error("proving backend failed: {0}")]
+pub struct BackendError(pub String);
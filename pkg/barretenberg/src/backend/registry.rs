@@ -0,0 +1,386 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::PathBuf,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use super::{Backend, BackendError, bb_cli::CliBackend};
+use crate::{Result, circuits::prove_health_check};
+
+#[cfg(feature = "bb_rs")]
+type NativeBackend = super::bb_rs::BindingBackend;
+#[cfg(not(feature = "bb_rs"))]
+type NativeBackend = UnavailableBackend;
+
+lazy_static! {
+    static ref CONFIG: RwLock<BackendConfig> = RwLock::new(BackendConfig::default());
+    static ref STATS: Mutex<HashMap<BackendKind, BackendStats>> = Mutex::new(HashMap::new());
+}
+
+/// A proving backend that can be selected at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Barretenberg linked into the binary, needs the `bb_rs` feature
+    Native,
+    /// The `bb` binary, run as a subprocess with its inputs in temp files
+    Cli,
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendKind::Native => write!(f, "native"),
+            BackendKind::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(BackendKind::Native),
+            "cli" => Ok(BackendKind::Cli),
+            other => Err(format!(
+                "unknown proving backend '{other}', expected 'native' or 'cli'"
+            )),
+        }
+    }
+}
+
+impl BackendKind {
+    /// Whether the backend was compiled in
+    pub fn is_available(self) -> bool {
+        match self {
+            BackendKind::Native => cfg!(feature = "bb_rs"),
+            BackendKind::Cli => true,
+        }
+    }
+
+    pub(crate) fn prove(
+        self,
+        program: &[u8],
+        bytecode: &[u8],
+        key: &[u8],
+        witness: &[u8],
+        recursive: bool,
+        oracle_hash_keccak: bool,
+    ) -> Result<Vec<u8>> {
+        match self {
+            BackendKind::Native => NativeBackend::prove(
+                program,
+                bytecode,
+                key,
+                witness,
+                recursive,
+                oracle_hash_keccak,
+            ),
+            BackendKind::Cli => CliBackend::prove(
+                program,
+                bytecode,
+                key,
+                witness,
+                recursive,
+                oracle_hash_keccak,
+            ),
+        }
+    }
+
+    pub(crate) fn verify(self, proof: &[u8], key: &[u8], oracle_hash_keccak: bool) -> Result<()> {
+        match self {
+            BackendKind::Native => NativeBackend::verify(proof, key, oracle_hash_keccak),
+            BackendKind::Cli => CliBackend::verify(proof, key, oracle_hash_keccak),
+        }
+    }
+}
+
+/// Which backends prove and verify, see [`configure`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct BackendConfig {
+    /// Backends to prove with, in order. If a backend fails to run, the next one is tried
+    pub backends: Vec<BackendKind>,
+    /// Path to the `bb` binary used by [`BackendKind::Cli`]
+    pub bb_path: PathBuf,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self {
+            backends: vec![BackendKind::Native, BackendKind::Cli],
+            bb_path: PathBuf::from("bb"),
+        }
+    }
+}
+
+impl BackendConfig {
+    /// Skip the backends that weren't compiled in, failing if none are left
+    fn available(mut self) -> Result<Self> {
+        self.backends.retain(|kind| {
+            let available = kind.is_available();
+            if !available {
+                warn!(backend = %kind, "Proving backend is not available, skipping it");
+            }
+            available
+        });
+
+        if self.backends.is_empty() {
+            return Err("no available proving backend configured".into());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Proving and verification timings of a backend since startup
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackendStats {
+    /// Number of successful proofs
+    pub proofs: u64,
+    /// Number of failed proofs
+    pub failures: u64,
+    /// Total time spent proving, including failed proofs
+    pub prove_time: Duration,
+    /// Number of verifications, successful or not
+    pub verifications: u64,
+    /// Total time spent verifying
+    pub verify_time: Duration,
+}
+
+/// Select the backends used by every circuit
+///
+/// Backends that weren't compiled in are skipped with a warning, so the default config works
+/// with or without the `bb_rs` feature
+pub fn configure(config: BackendConfig) -> Result<()> {
+    let config = config.available()?;
+
+    info!(backends = ?config.backends, bb_path = ?config.bb_path, "Configured proving backends");
+    *CONFIG.write().unwrap() = config;
+
+    Ok(())
+}
+
+/// Prove and verify a fixture proof with every configured backend
+///
+/// Backends that fail are removed from the configuration, so provers don't pay for a failing
+/// backend on every proof. Fails if no backend is healthy
+pub fn health_check() -> Result<()> {
+    let backends = backends();
+    let mut healthy = Vec::with_capacity(backends.len());
+
+    for kind in backends {
+        let start = Instant::now();
+        match prove_health_check(kind) {
+            Ok(()) => {
+                info!(backend = %kind, elapsed = ?start.elapsed(), "Proving backend is healthy");
+                healthy.push(kind);
+            }
+            Err(err) => {
+                error!(backend = %kind, ?err, "Proving backend failed its health check");
+            }
+        }
+    }
+
+    if healthy.is_empty() {
+        return Err("no proving backend passed its health check".into());
+    }
+
+    CONFIG.write().unwrap().backends = healthy;
+
+    Ok(())
+}
+
+/// The stats of every backend that has been used
+pub fn backend_stats() -> Vec<(BackendKind, BackendStats)> {
+    let mut stats = STATS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(kind, stats)| (*kind, *stats))
+        .collect::<Vec<_>>();
+    stats.sort_by_key(|(kind, _)| *kind);
+    stats
+}
+
+/// The configured backends, without those that weren't compiled in, in case [`configure`] was
+/// never called
+fn backends() -> Vec<BackendKind> {
+    let mut backends = CONFIG.read().unwrap().backends.clone();
+    backends.retain(|kind| kind.is_available());
+    backends
+}
+
+pub(crate) fn bb_path() -> PathBuf {
+    CONFIG.read().unwrap().bb_path.clone()
+}
+
+/// Prove with each of `backends` in order until one succeeds
+///
+/// Only a [`BackendError`] falls back to the next backend. Any other error means the backend
+/// rejected the witness or the circuit, which every backend would, so it's returned as is
+fn prove_with_fallback(
+    backends: &[BackendKind],
+    prove: impl Fn(BackendKind) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let mut last_err = None;
+
+    for &kind in backends {
+        let start = Instant::now();
+        let result = prove(kind);
+        let elapsed = start.elapsed();
+
+        info!(histogram.prove_ms = elapsed.as_millis() as u64, backend = %kind, ok = result.is_ok());
+        let mut stats = STATS.lock().unwrap();
+        let stats = stats.entry(kind).or_default();
+        stats.prove_time += elapsed;
+
+        match result {
+            Ok(proof) => {
+                stats.proofs += 1;
+                return Ok(proof);
+            }
+            Err(err) if err.is::<BackendError>() => {
+                stats.failures += 1;
+                warn!(backend = %kind, ?err, "Proving failed, falling back to the next backend");
+                last_err = Some(err);
+            }
+            Err(err) => {
+                stats.failures += 1;
+                return Err(err);
+            }
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| "no proving backend configured".into()))
+}
+
+/// Dispatches to the [configured](configure) backends
+pub struct RegistryBackend;
+
+impl Backend for RegistryBackend {
+    fn prove(
+        program: &[u8],
+        bytecode: &[u8],
+        key: &[u8],
+        witness: &[u8],
+        recursive: bool,
+        oracle_hash_keccak: bool,
+    ) -> Result<Vec<u8>> {
+        prove_with_fallback(&backends(), |kind| {
+            kind.prove(
+                program,
+                bytecode,
+                key,
+                witness,
+                recursive,
+                oracle_hash_keccak,
+            )
+        })
+    }
+
+    // Verification doesn't fall back, as an invalid proof fails on every backend
+    fn verify(proof: &[u8], key: &[u8], oracle_hash_keccak: bool) -> Result<()> {
+        let Some(kind) = backends().first().copied() else {
+            return Err("no proving backend configured".into());
+        };
+
+        let start = Instant::now();
+        let result = kind.verify(proof, key, oracle_hash_keccak);
+        let elapsed = start.elapsed();
+
+        info!(histogram.verify_ms = elapsed.as_millis() as u64, backend = %kind, ok = result.is_ok());
+        let mut stats = STATS.lock().unwrap();
+        let stats = stats.entry(kind).or_default();
+        stats.verifications += 1;
+        stats.verify_time += elapsed;
+
+        result
+    }
+}
+
+/// Stands in for [`BackendKind::Native`] without the `bb_rs` feature
+#[cfg(not(feature = "bb_rs"))]
+pub struct UnavailableBackend;
+
+#[cfg(not(feature = "bb_rs"))]
+impl Backend for UnavailableBackend {
+    fn prove(
+        _program: &[u8],
+        _bytecode: &[u8],
+        _key: &[u8],
+        _witness: &[u8],
+        _recursive: bool,
+        _oracle_hash_keccak: bool,
+    ) -> Result<Vec<u8>> {
+        Err(BackendError("the native backend needs the bb_rs feature".to_owned()).into())
+    }
+
+    fn verify(_proof: &[u8], _key: &[u8], _oracle_hash_keccak: bool) -> Result<()> {
+        Err("the native backend needs the bb_rs feature".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configure_skips_unavailable_backends() {
+        let backends = BackendConfig::default().available().unwrap().backends;
+        match cfg!(feature = "bb_rs") {
+            true => assert_eq!(backends, [BackendKind::Native, BackendKind::Cli]),
+            false => assert_eq!(backends, [BackendKind::Cli]),
+        }
+
+        let native_only = BackendConfig {
+            backends: vec![BackendKind::Native],
+            bb_path: PathBuf::from("bb"),
+        };
+        assert_eq!(native_only.available().is_ok(), cfg!(feature = "bb_rs"));
+    }
+
+    #[test]
+    fn falls_back_only_on_backend_errors() {
+        let backends = [BackendKind::Native, BackendKind::Cli];
+
+        let proof = prove_with_fallback(&backends, |kind| match kind {
+            BackendKind::Native => Err(BackendError("crashed".to_owned()).into()),
+            BackendKind::Cli => Ok(vec![1, 2, 3]),
+        })
+        .unwrap();
+        assert_eq!(proof, [1, 2, 3]);
+
+        let err = prove_with_fallback(&backends, |kind| match kind {
+            BackendKind::Native => Err("circuit is not satisfied".into()),
+            BackendKind::Cli => panic!("a rejected witness must not fall back"),
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "circuit is not satisfied");
+    }
+
+    #[test]
+    fn config_from_json() {
+        let config: BackendConfig =
+            serde_json::from_str(r#"{"backends": ["cli"], "bb-path": "/usr/local/bin/bb"}"#)
+                .unwrap();
+
+        assert_eq!(config.backends, [BackendKind::Cli]);
+        assert_eq!(config.bb_path, PathBuf::from("/usr/local/bin/bb"));
+    }
+
+    #[test]
+    fn kind_from_str_matches_display() {
+        for kind in [BackendKind::Native, BackendKind::Cli] {
+            assert_eq!(kind.to_string().parse::<BackendKind>(), Ok(kind));
+        }
+        assert!("gpu".parse::<BackendKind>().is_err());
+    }
+}
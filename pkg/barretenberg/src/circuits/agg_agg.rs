@@ -2,6 +2,8 @@ use super::{AGG_UTXO_VERIFICATION_KEY, AGG_UTXO_VERIFICATION_KEY_HASH};
 use crate::Result;
 use crate::backend::DefaultBackend;
 use crate::circuits::get_bytecode_from_program;
+use crate::dry_run::{DryRun, WitnessError};
+use crate::execute::check_witness;
 use crate::util::write_to_temp_file;
 use crate::verify::{VerificationKey, VerificationKeyHash, verify};
 use crate::{
@@ -95,6 +97,12 @@ impl Verify for AggAggProof {
     }
 }
 
+impl DryRun for AggAgg {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(&PROGRAM_COMPILED, &InputMap::from(AggAggInput::from(self)))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AggAggInput {
     pub proofs: [UtxoAggProof; 2],
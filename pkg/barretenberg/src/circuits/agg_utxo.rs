@@ -1,13 +1,21 @@
-use super::{UTXO_VERIFICATION_KEY, UTXO_VERIFICATION_KEY_HASH};
+#[cfg(feature = "swap")]
+use super::{SWAP_VERIFICATION_KEY, SWAP_VERIFICATION_KEY_HASH};
+use super::{
+    UTXO_VERIFICATION_KEY, UTXO_VERIFICATION_KEY_HASH, merkle_path_fields, merkle_paths_input_value,
+};
+#[cfg(feature = "utxo4")]
+use super::{UTXO4_VERIFICATION_KEY, UTXO4_VERIFICATION_KEY_HASH};
 use crate::Result;
 use crate::backend::DefaultBackend;
 use crate::circuits::get_bytecode_from_program;
+use crate::dry_run::{DryRun, WitnessError};
+use crate::execute::check_witness;
 use crate::prove::prove;
 use crate::traits::{Prove, Verify};
 use crate::util::write_to_temp_file;
 use crate::verify::{VerificationKey, VerificationKeyHash, verify};
 use core::iter::Iterator;
-use element::Base;
+use element::{Base, Element};
 use lazy_static::lazy_static;
 use noirc_abi::{InputMap, input_parser::InputValue};
 use noirc_artifacts::program::ProgramArtifact;
@@ -15,7 +23,7 @@ use noirc_driver::CompiledProgram;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use zk_primitives::{
-    AggUtxo, AggUtxoProof, AggUtxoProofBytes, AggUtxoPublicInput, MerklePath, ToBytes,
+    AggUtxo, AggUtxoProof, AggUtxoProofBytes, AggUtxoPublicInput, SWAP_KIND, ToBytes, TxnProof,
     UtxoProofBundleWithMerkleProofs, bytes_to_elements,
 };
 
@@ -37,6 +45,28 @@ lazy_static! {
 
 const AGG_UTXO_PUBLIC_INPUTS_COUNT: usize = 18;
 
+/// The circuits an aggregated proof can be from, indexes into the circuit's verification keys
+const UTXO_CIRCUIT: u32 = 0;
+const UTXO4_CIRCUIT: u32 = 1;
+const SWAP_CIRCUIT: u32 = 2;
+
+/// The verification keys and key hashes of the circuits, in the order of the circuit indexes.
+/// Circuits without their fixtures take the utxo key, the aggregation only checks the key hash
+/// of the circuits its proofs are from
+fn verification_keys() -> [(&'static VerificationKey, &'static VerificationKeyHash); 3] {
+    let utxo = (&*UTXO_VERIFICATION_KEY, &*UTXO_VERIFICATION_KEY_HASH);
+    #[cfg(feature = "utxo4")]
+    let utxo4 = (&*UTXO4_VERIFICATION_KEY, &*UTXO4_VERIFICATION_KEY_HASH);
+    #[cfg(not(feature = "utxo4"))]
+    let utxo4 = utxo;
+    #[cfg(feature = "swap")]
+    let swap = (&*SWAP_VERIFICATION_KEY, &*SWAP_VERIFICATION_KEY_HASH);
+    #[cfg(not(feature = "swap"))]
+    let swap = utxo;
+
+    [utxo, utxo4, swap]
+}
+
 impl Prove for AggUtxo {
     type Proof = AggUtxoProof;
     type Result<Proof> = Result<Proof>;
@@ -110,9 +140,17 @@ impl Verify for AggUtxoProof {
     }
 }
 
+impl DryRun for AggUtxo {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(&PROGRAM_COMPILED, &InputMap::from(AggUtxoInput::from(self)))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AggUtxoInput {
     pub proofs: [AggUtxoProofInput; 3],
+    pub record_old_root: bool,
+    pub old_root_merkle_path: [Base; 160],
     pub messages: [Base; 15],
     pub old_root: Base,
     pub new_root: Base,
@@ -129,6 +167,11 @@ impl From<&AggUtxo> for AggUtxoInput {
         let messages: [Base; 15] = agg_utxo.messages().map(|e| e.to_base());
         AggUtxoInput {
             proofs: proofs.try_into().unwrap(),
+            record_old_root: agg_utxo.old_root_merkle_path.is_some(),
+            old_root_merkle_path: agg_utxo
+                .old_root_merkle_path
+                .as_ref()
+                .map_or([Base::default(); 160], merkle_path_fields),
             messages,
             old_root: agg_utxo.old_root.to_base(),
             new_root: agg_utxo.new_root.to_base(),
@@ -141,27 +184,39 @@ impl From<AggUtxoInput> for InputMap {
     fn from(value: AggUtxoInput) -> Self {
         let mut map = InputMap::new();
 
-        // Should be static
+        // Should be static, in the order of the circuit indexes
+        let verification_keys = verification_keys();
         map.insert(
-            "verification_key".to_owned(),
+            "verification_keys".to_owned(),
             InputValue::Vec(
-                UTXO_VERIFICATION_KEY
-                    .0
-                    .iter()
-                    .cloned()
-                    .map(InputValue::Field)
-                    .collect(),
+                verification_keys
+                    .map(|(key, _)| {
+                        InputValue::Vec(key.0.iter().cloned().map(InputValue::Field).collect())
+                    })
+                    .to_vec(),
             ),
         );
         map.insert(
-            "verification_key_hash".to_owned(),
-            InputValue::Field(UTXO_VERIFICATION_KEY_HASH.0),
+            "verification_key_hashes".to_owned(),
+            InputValue::Vec(
+                verification_keys
+                    .map(|(_, hash)| InputValue::Field(hash.0))
+                    .to_vec(),
+            ),
         );
 
         map.insert(
             "proofs".to_owned(),
             InputValue::Vec(value.proofs.map(InputValue::from).to_vec()),
         );
+        map.insert(
+            "record_old_root".to_owned(),
+            InputValue::Field(Base::from(value.record_old_root)),
+        );
+        map.insert(
+            "old_root_merkle_path".to_owned(),
+            InputValue::Vec(value.old_root_merkle_path.map(InputValue::Field).to_vec()),
+        );
         map.insert(
             "messages".to_owned(),
             InputValue::Vec(value.messages.map(InputValue::Field).to_vec()),
@@ -180,48 +235,71 @@ impl From<AggUtxoInput> for InputMap {
 #[derive(Debug, Clone)]
 pub struct AggUtxoProofInput {
     pub proof: [Base; 508],
-    pub input_merkle_paths: [[Base; 160]; 2],
-    pub output_merkle_paths: [[Base; 160]; 2],
-    pub input_commitments: [Base; 2],
-    pub output_commitments: [Base; 2],
+    pub circuit: u32,
+    pub root: Base,
+    pub root_merkle_path: [Base; 160],
+    pub nullifier_merkle_paths: [[Base; 160]; 4],
+    pub output_merkle_paths: [[Base; 160]; 4],
+    pub nullifiers: [Base; 4],
+    pub output_commitments: [Base; 4],
     pub utxo_kind: Base,
+    pub swap_terms: [Base; 4],
 }
 
 impl From<&UtxoProofBundleWithMerkleProofs> for AggUtxoProofInput {
     fn from(value: &UtxoProofBundleWithMerkleProofs) -> Self {
-        let convert_merkle_paths = |merkle_paths: &[MerklePath<161>; 2]| -> [[Base; 160]; 2] {
-            let mut paths = [[Base::default(); 160]; 2];
-            for (i, mp) in merkle_paths.iter().enumerate() {
-                for (j, s) in mp.siblings.iter().enumerate() {
-                    paths[i][j] = s.to_base();
-                }
+        let utxo_proof = &value.utxo_proof;
+
+        // Proofs with fewer nullifiers or outputs are padded with zeros
+        let mut nullifiers = [Base::default(); 4];
+        for (field, nullifier) in nullifiers.iter_mut().zip(utxo_proof.nullifiers()) {
+            *field = nullifier.to_base();
+        }
+        let mut output_commitments = [Base::default(); 4];
+        for (field, commitment) in output_commitments
+            .iter_mut()
+            .zip(utxo_proof.output_commitments())
+        {
+            *field = commitment.to_base();
+        }
+
+        let (circuit, proof) = match utxo_proof {
+            TxnProof::Utxo(proof) => (UTXO_CIRCUIT, &proof.proof),
+            TxnProof::Utxo4(proof) => (UTXO4_CIRCUIT, &proof.proof),
+            TxnProof::SwapLeg(proof) => (SWAP_CIRCUIT, &proof.proof),
+            TxnProof::Swap(_) => unreachable!("a swap fills a slot per leg"),
+            TxnProof::UtxoV1(_) => unreachable!("legacy utxo proofs are never aggregated"),
+        };
+
+        // Swap legs have their kind and terms in their messages
+        let (utxo_kind, swap_terms) = match utxo_proof {
+            TxnProof::SwapLeg(proof) => {
+                let [_, terms @ ..] = proof.public_inputs.0.messages;
+                (Element::new(SWAP_KIND), terms)
             }
-            paths
+            _ => (utxo_proof.kind().to_element(), [Element::ZERO; 4]),
         };
 
         AggUtxoProofInput {
-            proof: value
-                .utxo_proof
-                .proof
+            proof: proof
                 .to_fields()
                 .iter()
                 .map(|e| e.to_base())
                 .collect::<Vec<_>>()
                 .try_into()
                 .unwrap(),
-            input_merkle_paths: convert_merkle_paths(&value.input_merkle_paths),
-            output_merkle_paths: convert_merkle_paths(&value.output_merkle_paths),
-            input_commitments: value
-                .utxo_proof
-                .public_inputs
-                .input_commitments
-                .map(|e| e.to_base()),
-            output_commitments: value
-                .utxo_proof
-                .public_inputs
-                .output_commitments
-                .map(|e| e.to_base()),
-            utxo_kind: value.utxo_proof.kind().to_element().to_base(),
+            circuit,
+            root: utxo_proof.root().to_base(),
+            root_merkle_path: merkle_path_fields(&value.root_merkle_path),
+            nullifier_merkle_paths: value
+                .nullifier_merkle_paths
+                .each_ref()
+                .map(merkle_path_fields),
+            output_merkle_paths: value.output_merkle_paths.each_ref().map(merkle_path_fields),
+            nullifiers,
+            output_commitments,
+            utxo_kind: utxo_kind.to_base(),
+            swap_terms: swap_terms.map(|e| e.to_base()),
         }
     }
 }
@@ -234,28 +312,26 @@ impl From<AggUtxoProofInput> for InputValue {
             InputValue::Vec(value.proof.map(InputValue::Field).to_vec()),
         );
         struct_.insert(
-            "input_merkle_paths".to_owned(),
-            InputValue::Vec(
-                value
-                    .input_merkle_paths
-                    .map(|mp| InputValue::Vec(mp.map(InputValue::Field).to_vec()))
-                    .to_vec(),
-            ),
+            "circuit".to_owned(),
+            InputValue::Field(Base::from(u128::from(value.circuit))),
+        );
+        struct_.insert("root".to_owned(), InputValue::Field(value.root));
+        struct_.insert(
+            "root_merkle_path".to_owned(),
+            InputValue::Vec(value.root_merkle_path.map(InputValue::Field).to_vec()),
+        );
+        struct_.insert(
+            "nullifier_merkle_paths".to_owned(),
+            merkle_paths_input_value(value.nullifier_merkle_paths),
         );
-
         struct_.insert(
             "output_merkle_paths".to_owned(),
-            InputValue::Vec(
-                value
-                    .output_merkle_paths
-                    .map(|mp| InputValue::Vec(mp.map(InputValue::Field).to_vec()))
-                    .to_vec(),
-            ),
+            merkle_paths_input_value(value.output_merkle_paths),
         );
 
         struct_.insert(
-            "input_commitments".to_owned(),
-            InputValue::Vec(value.input_commitments.map(InputValue::Field).to_vec()),
+            "nullifiers".to_owned(),
+            InputValue::Vec(value.nullifiers.map(InputValue::Field).to_vec()),
         );
 
         struct_.insert(
@@ -264,6 +340,10 @@ impl From<AggUtxoProofInput> for InputValue {
         );
 
         struct_.insert("utxo_kind".to_owned(), InputValue::Field(value.utxo_kind));
+        struct_.insert(
+            "swap_terms".to_owned(),
+            InputValue::Vec(value.swap_terms.map(InputValue::Field).to_vec()),
+        );
 
         InputValue::Struct(struct_)
     }
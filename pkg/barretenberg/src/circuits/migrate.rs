@@ -2,6 +2,8 @@ use crate::{
     Result,
     backend::DefaultBackend,
     circuits::get_bytecode_from_program,
+    dry_run::{DryRun, WitnessError},
+    execute::check_witness,
     prove::prove,
     traits::{Prove, Verify},
     util::write_to_temp_file,
@@ -111,6 +113,12 @@ impl Verify for MigrateProof {
     }
 }
 
+impl DryRun for Migrate {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(&PROGRAM_COMPILED, &InputMap::from(MigrateInput::from(self)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,17 +4,29 @@ mod migrate;
 mod note;
 mod points;
 mod signature;
+#[cfg(feature = "swap")]
+mod swap;
 #[cfg(test)]
 mod tests;
+mod txn_proof;
 mod utxo;
+#[cfg(feature = "utxo4")]
+mod utxo4;
 
 use std::io::Read;
 
 pub use agg_utxo::*;
 use base64::Engine;
+use element::Base;
 use flate2::read::GzDecoder;
 // pub use migrate::*;
+pub(crate) use signature::prove_health_check;
+#[cfg(feature = "swap")]
+pub use swap::*;
 pub use utxo::*;
+#[cfg(feature = "utxo4")]
+pub use utxo4::*;
+use zk_primitives::MerklePath;
 
 fn get_bytecode_from_program(program_json: &str) -> Vec<u8> {
     let mut program = serde_json::from_str::<serde_json::Value>(program_json).unwrap();
@@ -28,3 +40,25 @@ fn get_bytecode_from_program(program_json: &str) -> Vec<u8> {
 
     bytecode
 }
+
+/// The siblings of a merkle path, as a circuit input
+fn merkle_path_fields(merkle_path: &MerklePath<161>) -> [Base; 160] {
+    let mut fields = [Base::default(); 160];
+    for (field, sibling) in fields.iter_mut().zip(&merkle_path.siblings) {
+        *field = sibling.to_base();
+    }
+    fields
+}
+
+/// Merkle path siblings as an [`InputValue`](noirc_abi::input_parser::InputValue)
+fn merkle_paths_input_value<const N: usize>(
+    merkle_paths: [[Base; 160]; N],
+) -> noirc_abi::input_parser::InputValue {
+    use noirc_abi::input_parser::InputValue;
+
+    InputValue::Vec(
+        merkle_paths
+            .map(|mp| InputValue::Vec(mp.map(InputValue::Field).to_vec()))
+            .to_vec(),
+    )
+}
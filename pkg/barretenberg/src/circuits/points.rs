@@ -2,6 +2,8 @@ use crate::{
     Result,
     backend::DefaultBackend,
     circuits::get_bytecode_from_program,
+    dry_run::{DryRun, WitnessError},
+    execute::check_witness,
     prove::prove,
     traits::{Prove, Verify},
     util::write_to_temp_file,
@@ -81,6 +83,12 @@ impl Verify for PointsProof {
     }
 }
 
+impl DryRun for Points {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(&PROGRAM_COMPILED, &InputMap::from(PointsInput::from(self)))
+    }
+}
+
 struct PointsInput {
     secret_keys: [Base; 10],
     notes: [BNote; 10],
@@ -1,13 +1,15 @@
 use crate::{
     Result,
-    backend::DefaultBackend,
+    backend::{BackendKind, DefaultBackend},
     circuits::get_bytecode_from_program,
-    prove::prove,
+    dry_run::{DryRun, WitnessError},
+    execute::check_witness,
+    prove::{prove, solve_witness},
     traits::{Prove, Verify},
     util::write_to_temp_file,
     verify::verify,
 };
-use element::Base;
+use element::{Base, Element};
 use lazy_static::lazy_static;
 use noirc_abi::InputMap;
 use noirc_artifacts::program::ProgramArtifact;
@@ -79,6 +81,30 @@ impl Verify for SignatureProof {
     }
 }
 
+impl DryRun for Signature {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(
+            &PROGRAM_COMPILED,
+            &InputMap::from(SignatureInput::from(self)),
+        )
+    }
+}
+
+/// Prove and verify a signature with `backend` alone, as signatures are the smallest circuit
+pub(crate) fn prove_health_check(backend: BackendKind) -> Result<()> {
+    let signature = Signature {
+        secret_key: Element::new(101),
+        message: Element::new(100),
+    };
+    let witness = solve_witness(
+        &PROGRAM_COMPILED,
+        &InputMap::from(SignatureInput::from(&signature)),
+    )?;
+
+    let proof = backend.prove(PROGRAM.as_bytes(), &BYTECODE, KEY, &witness, false, false)?;
+    backend.verify(&proof, KEY, false)
+}
+
 #[derive(Debug, Clone)]
 struct SignatureInput {
     secret_key: Base,
@@ -126,7 +152,6 @@ impl From<SignatureInput> for InputMap {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use element::Element;
 
     #[test]
     fn test_signature_proof_generation_and_verification() {
@@ -0,0 +1,165 @@
+use super::{
+    merkle_path_fields, merkle_paths_input_value,
+    note::{BInputNote, BNote},
+};
+use crate::{
+    Result,
+    backend::DefaultBackend,
+    circuits::get_bytecode_from_program,
+    dry_run::{DryRun, WitnessError},
+    execute::check_witness,
+    prove::prove,
+    traits::{Prove, Verify},
+    verify::{VerificationKey, VerificationKeyHash, verify},
+};
+use element::Base;
+use lazy_static::lazy_static;
+use noirc_abi::{InputMap, input_parser::InputValue};
+use noirc_artifacts::program::ProgramArtifact;
+use noirc_driver::CompiledProgram;
+use zk_primitives::{
+    SwapLeg, SwapLegProof, SwapLegPublicInput, ToBytes, UTXO_PROOF_SIZE, UTXO_PUBLIC_INPUTS_COUNT,
+    UtxoProofBytes, UtxoPublicInput, bytes_to_elements,
+};
+
+const PROGRAM: &str = include_str!("../../../../fixtures/programs/swap.json");
+const KEY: &[u8] = include_bytes!("../../../../fixtures/keys/swap_key");
+const KEY_FIELDS: &[u8] = include_bytes!("../../../../fixtures/keys/swap_key_fields.json");
+
+/// Number of public input fields for a swap leg proof, the same as a utxo proof
+const PUBLIC_INPUTS_COUNT: usize = UTXO_PUBLIC_INPUTS_COUNT;
+
+lazy_static! {
+    static ref PROGRAM_ARTIFACT: ProgramArtifact = serde_json::from_str(PROGRAM).unwrap();
+    static ref PROGRAM_COMPILED: CompiledProgram = CompiledProgram::from(PROGRAM_ARTIFACT.clone());
+    static ref BYTECODE: Vec<u8> = get_bytecode_from_program(PROGRAM);
+    pub static ref SWAP_VERIFICATION_KEY: VerificationKey = {
+        let fields = serde_json::from_slice::<Vec<Base>>(KEY_FIELDS).unwrap();
+        VerificationKey(fields)
+    };
+    pub static ref SWAP_VERIFICATION_KEY_HASH: VerificationKeyHash = VerificationKeyHash(
+        bn254_blackbox_solver::poseidon_hash(&SWAP_VERIFICATION_KEY.0, false).unwrap()
+    );
+}
+
+impl Prove for SwapLeg {
+    type Proof = SwapLegProof;
+    type Result<Proof> = Result<Proof>;
+
+    fn prove(&self) -> Self::Result<Self::Proof> {
+        let inputs = InputMap::from(SwapLegInput::from(self));
+
+        let proof_bytes = prove::<DefaultBackend>(
+            &PROGRAM_COMPILED,
+            PROGRAM.as_bytes(),
+            &BYTECODE,
+            KEY,
+            &inputs,
+            true,
+            false,
+        )?;
+
+        // The public inputs are the first 10, 32 byte chunks
+        let public_inputs = proof_bytes[..PUBLIC_INPUTS_COUNT * 32].to_vec();
+        let public_inputs = bytes_to_elements(&public_inputs);
+        let raw_proof = proof_bytes[PUBLIC_INPUTS_COUNT * 32..].to_vec();
+
+        assert_eq!(
+            raw_proof.len(),
+            UTXO_PROOF_SIZE * 32,
+            "Proof must be {UTXO_PROOF_SIZE} elements of 32 bytes"
+        );
+
+        Ok(SwapLegProof {
+            proof: UtxoProofBytes(raw_proof),
+            public_inputs: SwapLegPublicInput(UtxoPublicInput {
+                root: public_inputs[0],
+                nullifiers: [public_inputs[1], public_inputs[2]],
+                output_commitments: [public_inputs[3], public_inputs[4]],
+                messages: [
+                    public_inputs[5],
+                    public_inputs[6],
+                    public_inputs[7],
+                    public_inputs[8],
+                    public_inputs[9],
+                ],
+            }),
+        })
+    }
+}
+
+impl Verify for SwapLegProof {
+    fn verify(&self) -> Result<()> {
+        let bytes = self.to_bytes();
+        verify::<DefaultBackend>(KEY, &bytes, false)
+    }
+}
+
+impl DryRun for SwapLeg {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(&PROGRAM_COMPILED, &InputMap::from(SwapLegInput::from(self)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SwapLegInput {
+    input_notes: [BInputNote; 2],
+    input_merkle_paths: [[Base; 160]; 2],
+    output_notes: [BNote; 2],
+    root: Base,
+    nullifiers: [Base; 2],
+    output_commitments: [Base; 2],
+    messages: [Base; 5],
+}
+
+impl From<&SwapLeg> for SwapLegInput {
+    fn from(leg: &SwapLeg) -> Self {
+        let public_inputs = leg.public_inputs().0;
+
+        SwapLegInput {
+            input_notes: leg.input_notes.each_ref().map(BInputNote::from),
+            input_merkle_paths: leg.input_merkle_paths.each_ref().map(merkle_path_fields),
+            output_notes: leg.output_notes().each_ref().map(BNote::from),
+            root: public_inputs.root.to_base(),
+            nullifiers: public_inputs.nullifiers.map(|e| e.to_base()),
+            output_commitments: public_inputs.output_commitments.map(|e| e.to_base()),
+            messages: public_inputs.messages.map(|e| e.to_base()),
+        }
+    }
+}
+
+impl From<SwapLegInput> for InputMap {
+    fn from(leg: SwapLegInput) -> Self {
+        let mut map = InputMap::new();
+
+        map.insert(
+            "input_notes".to_owned(),
+            InputValue::Vec(leg.input_notes.map(InputValue::from).to_vec()),
+        );
+        map.insert(
+            "input_merkle_paths".to_owned(),
+            merkle_paths_input_value(leg.input_merkle_paths),
+        );
+        map.insert(
+            "output_notes".to_owned(),
+            InputValue::Vec(leg.output_notes.map(InputValue::from).to_vec()),
+        );
+
+        map.insert("root".to_owned(), InputValue::Field(leg.root));
+        map.insert(
+            "nullifiers".to_owned(),
+            InputValue::Vec(leg.nullifiers.map(InputValue::Field).to_vec()),
+        );
+        map.insert(
+            "output_commitments".to_owned(),
+            InputValue::Vec(leg.output_commitments.map(InputValue::Field).to_vec()),
+        );
+
+        map.insert(
+            "messages".to_owned(),
+            InputValue::Vec(leg.messages.map(InputValue::Field).to_vec()),
+        );
+
+        map
+    }
+}
@@ -2,11 +2,16 @@ use element::Element;
 use flate2::{Compression, write::GzEncoder};
 use std::io::Write;
 use zk_primitives::{
-    AggAgg, AggUtxo, InputNote, MerklePath, Note, ToBytes, Utxo, UtxoKind, UtxoProof,
-    UtxoProofBundleWithMerkleProofs, bridged_polygon_usdc_note_kind, get_address_for_private_key,
+    AggAgg, AggUtxo, InputNote, MerklePath, Note, ToBytes, TxnProof, Utxo, UtxoProof,
+    UtxoProofBundleWithMerkleProofs, UtxoProofBytes, bridged_polygon_usdc_note_kind,
+    get_address_for_private_key, root_leaf,
 };
+#[cfg(feature = "swap")]
+use zk_primitives::{SwapLeg, SwapLegProof, SwapProof};
+#[cfg(feature = "utxo4")]
+use zk_primitives::{Utxo4, Utxo4Proof};
 
-use crate::{Prove, Result, Verify};
+use crate::{DryRun, Prove, Result, Verify, WitnessError};
 
 pub fn get_keypair(key: u64) -> (Element, Element) {
     let secret_key = Element::new(key);
@@ -70,6 +75,38 @@ pub fn prove_and_verify<P: Prove>(proof_input: &P) -> Result<P::Proof> {
     Ok(proof)
 }
 
+fn merkle_path(tree: &smirk::Tree<161, ()>, leaf: Element) -> MerklePath<161> {
+    MerklePath::new(tree.path_for(leaf).siblings)
+}
+
+/// A tree holding the commitments of `input_notes`
+fn tree_with(input_notes: &[InputNote]) -> smirk::Tree<161, ()> {
+    let mut tree = smirk::Tree::new();
+    for input_note in input_notes {
+        tree.insert(input_note.note.commitment(), ()).unwrap();
+    }
+    tree
+}
+
+/// The merkle paths of `input_notes` in `tree`
+fn input_merkle_paths<const N: usize>(
+    tree: &smirk::Tree<161, ()>,
+    input_notes: &[InputNote; N],
+) -> [MerklePath<161>; N] {
+    input_notes
+        .each_ref()
+        .map(|input_note| match input_note.note.value == Element::ZERO {
+            true => MerklePath::default(),
+            false => merkle_path(tree, input_note.note.commitment()),
+        })
+}
+
+/// Spend the inputs of `utxo` from the current root of `tree`
+fn spend_from(tree: &smirk::Tree<161, ()>, utxo: Utxo) -> Utxo {
+    let paths = input_merkle_paths(tree, &utxo.input_notes);
+    utxo.with_merkle_paths(tree.root_hash(), paths)
+}
+
 #[test]
 fn test_utxo() {
     let (secret_key, address) = get_keypair(101);
@@ -87,191 +124,520 @@ fn test_utxo() {
     let output_note1 = send_note(40, address, 3);
     let output_note2 = send_note(40, address, 4);
 
-    let utxo = Utxo {
-        input_notes: [input_note1, input_note2],
-        output_notes: [output_note1, output_note2],
-        kind: UtxoKind::Send,
-        burn_address: None,
-    };
+    let tree = tree_with(&[input_note1.clone(), input_note2.clone()]);
+    let utxo = spend_from(
+        &tree,
+        Utxo::new_send([input_note1, input_note2], [output_note1, output_note2]),
+    );
 
     prove_and_verify(&utxo).unwrap();
 }
 
-fn process_utxo_for_agg(
-    tree: &mut smirk::Tree<161, ()>,
-    utxo: &Utxo,
-) -> Result<(
-    UtxoProof,
-    MerklePath<161>,
-    MerklePath<161>,
-    MerklePath<161>,
-    MerklePath<161>,
-    Element,
-)> {
-    let utxo_proof = utxo.prove()?;
-
-    let p1: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.input_notes[0].note.commitment())
-            .siblings
-            .to_vec(),
+#[test]
+fn test_utxo_dry_run() {
+    let (secret_key, address) = get_keypair(101);
+    let (other_secret_key, _) = get_keypair(202);
+
+    let input_notes = [
+        InputNote {
+            note: send_note(50, address, 1),
+            secret_key,
+        },
+        InputNote {
+            note: send_note(30, address, 2),
+            secret_key,
+        },
+    ];
+
+    let tree = tree_with(&input_notes);
+
+    let utxo = spend_from(
+        &tree,
+        Utxo::new_send(
+            input_notes.clone(),
+            [send_note(40, address, 3), send_note(40, address, 4)],
+        ),
+    );
+    utxo.dry_run().unwrap();
+
+    let unbalanced = spend_from(
+        &tree,
+        Utxo::new_send(
+            input_notes.clone(),
+            [send_note(40, address, 3), send_note(41, address, 4)],
+        ),
     );
-    tree.remove(utxo.input_notes[0].note.commitment()).unwrap();
+    assert_eq!(unbalanced.dry_run(), Err(WitnessError::InputOutputMismatch));
 
-    let p2: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.input_notes[1].note.commitment())
-            .siblings
-            .to_vec(),
+    let not_in_tree = spend_from(&tree_with(&input_notes[..1]), utxo.clone());
+    assert_eq!(not_in_tree.dry_run(), Err(WitnessError::InputNoteNotInTree));
+
+    let mut not_owned = utxo.clone();
+    not_owned.input_notes[0].secret_key = other_secret_key;
+    assert_eq!(not_owned.dry_run(), Err(WitnessError::InputNoteNotOwned));
+}
+
+/// The circuit asserts each nullifier is `get_nullifier` of its input, so a utxo only solves
+/// if `InputNote::nullifier` computes the same nullifiers as the circuit
+#[test]
+fn test_nullifier_matches_circuit() {
+    let (secret_key, address) = get_keypair(101);
+    let (other_secret_key, other_address) = get_keypair(202);
+
+    let input_notes = [
+        InputNote {
+            note: send_note(50, address, 7),
+            secret_key,
+        },
+        InputNote {
+            note: send_note(30, other_address, 11),
+            secret_key: other_secret_key,
+        },
+    ];
+    let utxo = spend_from(
+        &tree_with(&input_notes),
+        Utxo::new_send(
+            input_notes.clone(),
+            [send_note(80, address, 3), Note::padding_note()],
+        ),
     );
-    tree.remove(utxo.input_notes[1].note.commitment()).unwrap();
 
-    tree.insert(utxo.output_notes[0].commitment(), ()).unwrap();
-    let p3: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.output_notes[0].commitment())
-            .siblings
-            .to_vec(),
+    let nullifiers = utxo.public_inputs().nullifiers;
+    assert_eq!(nullifiers, input_notes.each_ref().map(InputNote::nullifier));
+    assert!(nullifiers.iter().all(|n| *n != Element::ZERO));
+
+    utxo.dry_run().unwrap();
+}
+
+/// Spend the inputs of `utxo` from the current root of `tree`
+#[cfg(feature = "utxo4")]
+fn spend4_from(tree: &smirk::Tree<161, ()>, utxo: Utxo4) -> Utxo4 {
+    let paths = input_merkle_paths(tree, &utxo.input_notes);
+    utxo.with_merkle_paths(tree.root_hash(), paths)
+}
+
+#[cfg(feature = "utxo4")]
+fn utxo4_input_notes(secret_key: Element, address: Element) -> [InputNote; 4] {
+    [1, 2, 3, 4].map(|psi| InputNote {
+        note: send_note(5, address, psi),
+        secret_key,
+    })
+}
+
+#[cfg(feature = "utxo4")]
+fn utxo4_output_notes(address: Element, values: [u64; 4]) -> [Note; 4] {
+    let mut psi = 4;
+    values.map(|value| {
+        psi += 1;
+        send_note(value, address, psi)
+    })
+}
+
+#[cfg(feature = "utxo4")]
+#[test]
+fn test_utxo4_dry_run() {
+    let (secret_key, address) = get_keypair(101);
+
+    let input_notes = utxo4_input_notes(secret_key, address);
+    let tree = tree_with(&input_notes);
+
+    let utxo = spend4_from(
+        &tree,
+        Utxo4::new_send(
+            input_notes.clone(),
+            utxo4_output_notes(address, [20, 0, 0, 0]),
+        ),
+    );
+    utxo.dry_run().unwrap();
+
+    assert_eq!(
+        spend4_from(
+            &tree,
+            Utxo4::new_send(
+                input_notes.clone(),
+                utxo4_output_notes(address, [10, 6, 4, 1])
+            )
+        )
+        .dry_run(),
+        Err(WitnessError::InputOutputMismatch)
     );
 
-    tree.insert(utxo.output_notes[1].commitment(), ()).unwrap();
-    let p4: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.output_notes[1].commitment())
-            .siblings
-            .to_vec(),
+    let mut mixed = utxo4_output_notes(address, [10, 10, 0, 0]);
+    mixed[1].contract = Element::new(5);
+    assert_eq!(
+        spend4_from(&tree, Utxo4::new_send(input_notes.clone(), mixed)).dry_run(),
+        Err(WitnessError::InconsistentKinds)
     );
 
-    let new_root = tree.root_hash();
+    let not_in_tree = spend4_from(&tree_with(&input_notes[..3]), utxo.clone());
+    assert_eq!(not_in_tree.dry_run(), Err(WitnessError::InputNoteNotInTree));
 
-    Ok((utxo_proof, p1, p2, p3, p4, new_root))
+    let mut not_owned = utxo;
+    not_owned.input_notes[3].secret_key = get_keypair(202).0;
+    assert_eq!(not_owned.dry_run(), Err(WitnessError::InputNoteNotOwned));
+}
+
+#[cfg(feature = "swap")]
+#[test]
+fn test_swap_dry_run() {
+    let (secret_key, address) = get_keypair(101);
+    let other_kind = Element::new(5);
+
+    let input_notes = [
+        InputNote {
+            note: send_note(7, address, 1),
+            secret_key,
+        },
+        InputNote {
+            note: send_note(5, address, 2),
+            secret_key,
+        },
+    ];
+    let received = note(3, address, 3, other_kind);
+    let change = send_note(2, address, 4);
+
+    // Spend each leg's inputs from a tree that holds them
+    let spend = |leg: SwapLeg| {
+        let tree = tree_with(&leg.input_notes);
+        let paths = input_merkle_paths(&tree, &leg.input_notes);
+        leg.with_merkle_paths(tree.root_hash(), paths)
+    };
+
+    spend(SwapLeg::new(
+        input_notes.clone(),
+        received.clone(),
+        change.clone(),
+    ))
+    .dry_run()
+    .unwrap();
+
+    assert_eq!(
+        spend(SwapLeg::new(
+            input_notes.clone(),
+            send_note(3, address, 3),
+            change.clone()
+        ))
+        .dry_run(),
+        Err(WitnessError::SameSwapKinds)
+    );
+
+    let mut other_input = input_notes.clone();
+    other_input[1].note.contract = other_kind;
+    assert_eq!(
+        spend(SwapLeg::new(other_input, received.clone(), change.clone())).dry_run(),
+        Err(WitnessError::InputKindMismatch)
+    );
+
+    assert_eq!(
+        spend(SwapLeg::new(
+            input_notes.clone(),
+            received.clone(),
+            note(2, address, 4, Element::new(6))
+        ))
+        .dry_run(),
+        Err(WitnessError::ChangeKindMismatch)
+    );
+
+    let mut not_owned = input_notes;
+    not_owned[0].secret_key = get_keypair(202).0;
+    assert_eq!(
+        spend(SwapLeg::new(not_owned, received, change)).dry_run(),
+        Err(WitnessError::InputNoteNotOwned)
+    );
+}
+
+/// Records the current root of `tree` as the old root of an aggregation, returning the merkle
+/// path of its root leaf
+fn record_root(tree: &mut smirk::Tree<161, ()>) -> MerklePath<161> {
+    let leaf = root_leaf(tree.root_hash());
+    tree.insert(leaf, ()).unwrap();
+    merkle_path(tree, leaf)
+}
+
+/// The bundle aggregating `utxo_proof`, inserting its leaves into `tree`
+fn process_utxo_for_agg(
+    tree: &mut smirk::Tree<161, ()>,
+    utxo_proof: impl Into<TxnProof>,
+) -> UtxoProofBundleWithMerkleProofs {
+    let utxo_proof = utxo_proof.into();
+
+    let root_merkle_path = match utxo_proof.has_inputs() {
+        true => merkle_path(tree, root_leaf(utxo_proof.root())),
+        false => MerklePath::default(),
+    };
+
+    let merkle_paths = utxo_proof
+        .leaves()
+        .into_iter()
+        .map(|leaf| {
+            if leaf == Element::ZERO {
+                return MerklePath::default();
+            }
+
+            tree.insert(leaf, ()).unwrap();
+            merkle_path(tree, leaf)
+        })
+        .collect::<Vec<_>>();
+
+    UtxoProofBundleWithMerkleProofs::new(utxo_proof, root_merkle_path, &merkle_paths)
+}
+
+/// Aggregate `utxo_proof` on its own, in a slot per leg for a swap, recording the old root as
+/// the first aggregation of a block
+fn agg_utxo_for(tree: &mut smirk::Tree<161, ()>, utxo_proof: impl Into<TxnProof>) -> AggUtxo {
+    let old_root = tree.root_hash();
+    let old_root_merkle_path = record_root(tree);
+    let mut bundles = utxo_proof
+        .into()
+        .slots()
+        .into_iter()
+        .map(|slot| process_utxo_for_agg(tree, slot))
+        .collect::<Vec<_>>();
+    bundles.resize_with(3, UtxoProofBundleWithMerkleProofs::default);
+
+    AggUtxo::new(
+        bundles.try_into().unwrap(),
+        Some(old_root_merkle_path),
+        old_root,
+        tree.root_hash(),
+    )
+}
+
+/// A proof of `utxo` for dry runs, which don't check the aggregated proofs
+fn unproven(utxo: &Utxo) -> UtxoProof {
+    UtxoProof {
+        proof: UtxoProofBytes::default(),
+        public_inputs: utxo.public_inputs(),
+    }
 }
 
 #[test]
 fn test_agg_utxo() {
     let (secret_key, address) = get_keypair(101);
-    let mut tree = smirk::Tree::<161, ()>::new();
 
     let utxo1_input_note1 = InputNote {
         note: send_note(50, address, 1),
         secret_key,
     };
-    tree.insert(utxo1_input_note1.note.commitment(), ())
-        .unwrap();
-
     let utxo1_input_note2 = InputNote {
         note: send_note(30, address, 2),
         secret_key,
     };
-    tree.insert(utxo1_input_note2.note.commitment(), ())
-        .unwrap();
+    let mut tree = tree_with(&[utxo1_input_note1.clone(), utxo1_input_note2.clone()]);
+
+    let utxo1 = spend_from(
+        &tree,
+        Utxo::new_send(
+            [utxo1_input_note1, utxo1_input_note2],
+            [send_note(40, address, 3), send_note(40, address, 4)],
+        ),
+    );
+    let utxo1_proof = prove_and_verify(&utxo1).unwrap();
+
+    let agg_utxo1 = agg_utxo_for(&mut tree, utxo1_proof);
+
+    prove_and_verify(&agg_utxo1).unwrap();
+}
+
+#[cfg(feature = "utxo4")]
+#[test]
+fn test_agg_utxo4() {
+    let (secret_key, address) = get_keypair(101);
+
+    let input_notes = utxo4_input_notes(secret_key, address);
+    let mut tree = tree_with(&input_notes);
+
+    let utxo4 = spend4_from(
+        &tree,
+        Utxo4::new_send(input_notes, utxo4_output_notes(address, [8, 7, 5, 0])),
+    );
+    let utxo4_proof = Utxo4Proof {
+        proof: UtxoProofBytes::default(),
+        public_inputs: utxo4.public_inputs(),
+    };
 
-    let utxo1_old_root = tree.root_hash();
+    agg_utxo_for(&mut tree, utxo4_proof).dry_run().unwrap();
+}
 
-    let utxo1_output_note1 = send_note(40, address, 3);
-    let utxo1_output_note2 = send_note(40, address, 4);
+/// A proof of `leg` for dry runs, which don't check the aggregated proofs
+#[cfg(feature = "swap")]
+fn unproven_leg(leg: &SwapLeg) -> SwapLegProof {
+    SwapLegProof {
+        proof: UtxoProofBytes::default(),
+        public_inputs: leg.public_inputs(),
+    }
+}
 
-    let utxo1 = Utxo {
-        input_notes: [utxo1_input_note1.clone(), utxo1_input_note2.clone()],
-        output_notes: [utxo1_output_note1.clone(), utxo1_output_note2.clone()],
-        kind: UtxoKind::Send,
-        burn_address: None,
+#[cfg(feature = "swap")]
+#[test]
+fn test_agg_swap() {
+    let (alice_key, alice) = get_keypair(101);
+    let (bob_key, bob) = get_keypair(202);
+    let other_kind = Element::new(5);
+
+    let alice_inputs = [
+        InputNote::new(send_note(7, alice, 1), alice_key),
+        InputNote::new(send_note(5, alice, 2), alice_key),
+    ];
+    let bob_inputs = [
+        InputNote::padding_note(),
+        InputNote::new(note(3, bob, 3, other_kind), bob_key),
+    ];
+    let tree = tree_with(&[
+        alice_inputs[0].clone(),
+        alice_inputs[1].clone(),
+        bob_inputs[1].clone(),
+    ]);
+
+    // Alice gives 10 USDC for 3 of the other kind
+    let alice_leg = SwapLeg::new(
+        alice_inputs,
+        note(3, alice, 4, other_kind),
+        send_note(2, alice, 5),
+    );
+    let alice_leg = alice_leg.with_merkle_paths(
+        tree.root_hash(),
+        input_merkle_paths(&tree, &alice_leg.input_notes),
+    );
+    let bob_leg = |wanted: u64, psi: u64| {
+        let leg = SwapLeg::new(
+            bob_inputs.clone(),
+            send_note(wanted, bob, psi),
+            Note::padding_note(),
+        );
+        let paths = input_merkle_paths(&tree, &leg.input_notes);
+        leg.with_merkle_paths(tree.root_hash(), paths)
     };
 
-    let (utxo1_proof, p1, p2, p3, p4, utxo1_new_root) =
-        process_utxo_for_agg(&mut tree, &utxo1).unwrap();
-    verify_proof(&utxo1_proof);
+    let swap = SwapProof::new([unproven_leg(&alice_leg), unproven_leg(&bob_leg(10, 6))]);
+    assert!(swap.legs_match());
+    agg_utxo_for(&mut tree.clone(), swap.clone())
+        .dry_run()
+        .unwrap();
+
+    // Bob wants more than Alice gives
+    let greedy = SwapProof::new([unproven_leg(&alice_leg), unproven_leg(&bob_leg(11, 7))]);
+    assert!(!greedy.legs_match());
+    assert_eq!(
+        agg_utxo_for(&mut tree.clone(), greedy).dry_run(),
+        Err(WitnessError::SwapLegsMismatch)
+    );
+
+    // Neither leg can be included without the other
+    for leg in swap.legs {
+        assert_eq!(
+            agg_utxo_for(&mut tree.clone(), TxnProof::SwapLeg(leg)).dry_run(),
+            Err(WitnessError::UnpairedSwapLeg)
+        );
+    }
+}
 
-    let agg_utxo1 = AggUtxo::new(
+#[test]
+fn test_agg_utxo_double_spend() {
+    let (secret_key, address) = get_keypair(101);
+
+    let input_note = InputNote {
+        note: send_note(50, address, 1),
+        secret_key,
+    };
+    let mut tree = tree_with(std::slice::from_ref(&input_note));
+
+    let utxo = spend_from(
+        &tree,
+        Utxo::new_send(
+            [input_note.clone(), InputNote::padding_note()],
+            [send_note(50, address, 2), Note::padding_note()],
+        ),
+    );
+    agg_utxo_for(&mut tree, unproven(&utxo)).dry_run().unwrap();
+
+    // Spending the note again, from the new root, fails to insert its nullifier
+    let again = spend_from(
+        &tree,
+        Utxo::new_send(
+            [input_note, InputNote::padding_note()],
+            [send_note(50, address, 3), Note::padding_note()],
+        ),
+    );
+    let old_root = tree.root_hash();
+    let old_root_merkle_path = record_root(&mut tree);
+    let bundle = UtxoProofBundleWithMerkleProofs::new(
+        unproven(&again),
+        merkle_path(&tree, root_leaf(again.root)),
+        &[
+            merkle_path(&tree, again.input_notes[0].nullifier()),
+            MerklePath::default(),
+            MerklePath::default(),
+            MerklePath::default(),
+        ],
+    );
+
+    let double_spend = AggUtxo::new(
         [
-            UtxoProofBundleWithMerkleProofs::new(utxo1_proof, &[p1, p2, p3, p4]),
+            bundle,
             UtxoProofBundleWithMerkleProofs::default(),
             UtxoProofBundleWithMerkleProofs::default(),
         ],
-        utxo1_old_root,
-        utxo1_new_root,
+        Some(old_root_merkle_path),
+        old_root,
+        tree.root_hash(),
+    );
+    assert_eq!(
+        double_spend.dry_run(),
+        Err(WitnessError::MerklePathMismatch)
     );
-
-    prove_and_verify(&agg_utxo1).unwrap();
 }
 
 #[test]
 fn test_agg_agg() {
     let (secret_key, address) = get_keypair(101);
-    let mut tree = smirk::Tree::<161, ()>::new();
 
     let utxo1_input_note1 = InputNote {
         note: send_note(60, address, 1),
         secret_key,
     };
-    tree.insert(utxo1_input_note1.note.commitment(), ())
-        .unwrap();
-
     let utxo1_input_note2 = InputNote {
         note: send_note(40, address, 2),
         secret_key,
     };
-    tree.insert(utxo1_input_note2.note.commitment(), ())
-        .unwrap();
-
-    let utxo1_old_root = tree.root_hash();
+    let mut tree = tree_with(&[utxo1_input_note1.clone(), utxo1_input_note2.clone()]);
 
     let utxo1_output_note1 = send_note(70, address, 3);
     let utxo1_output_note2 = send_note(30, address, 4);
 
-    let utxo1 = Utxo {
-        input_notes: [utxo1_input_note1.clone(), utxo1_input_note2.clone()],
-        output_notes: [utxo1_output_note1.clone(), utxo1_output_note2.clone()],
-        kind: UtxoKind::Send,
-        burn_address: None,
-    };
+    let utxo1 = spend_from(
+        &tree,
+        Utxo::new_send(
+            [utxo1_input_note1, utxo1_input_note2],
+            [utxo1_output_note1.clone(), utxo1_output_note2.clone()],
+        ),
+    );
 
-    let (utxo1_proof, p1_1, p1_2, p1_3, p1_4, utxo1_new_root) =
-        process_utxo_for_agg(&mut tree, &utxo1).unwrap();
-    verify_proof(&utxo1_proof);
+    let utxo1_proof = prove_and_verify(&utxo1).unwrap();
 
-    let agg_utxo1 = AggUtxo::new(
-        [
-            UtxoProofBundleWithMerkleProofs::new(utxo1_proof, &[p1_1, p1_2, p1_3, p1_4]),
-            UtxoProofBundleWithMerkleProofs::default(),
-            UtxoProofBundleWithMerkleProofs::default(),
-        ],
-        utxo1_old_root,
-        utxo1_new_root,
-    );
+    let agg_utxo1 = agg_utxo_for(&mut tree, utxo1_proof);
     let agg_utxo1_proof = prove_and_verify(&agg_utxo1).unwrap();
 
     let utxo2_input_note1 = InputNote {
-        note: utxo1_output_note1.clone(),
+        note: utxo1_output_note1,
         secret_key,
     };
-
     let utxo2_input_note2 = InputNote {
-        note: utxo1_output_note2.clone(),
+        note: utxo1_output_note2,
         secret_key,
     };
 
-    let utxo2_old_root = tree.root_hash();
-
-    let utxo2_output_note1 = send_note(55, address, 5);
-    let utxo2_output_note2 = send_note(45, address, 6);
-
-    let utxo2 = Utxo {
-        input_notes: [utxo2_input_note1.clone(), utxo2_input_note2.clone()],
-        output_notes: [utxo2_output_note1.clone(), utxo2_output_note2.clone()],
-        kind: UtxoKind::Send,
-        burn_address: None,
-    };
+    let utxo2 = spend_from(
+        &tree,
+        Utxo::new_send(
+            [utxo2_input_note1, utxo2_input_note2],
+            [send_note(55, address, 5), send_note(45, address, 6)],
+        ),
+    );
 
-    let (utxo2_proof, p2_1, p2_2, p2_3, p2_4, utxo2_new_root) =
-        process_utxo_for_agg(&mut tree, &utxo2).unwrap();
-    verify_proof(&utxo2_proof);
+    let utxo2_proof = prove_and_verify(&utxo2).unwrap();
 
-    let agg_utxo2 = AggUtxo::new(
-        [
-            UtxoProofBundleWithMerkleProofs::new(utxo2_proof, &[p2_1, p2_2, p2_3, p2_4]),
-            UtxoProofBundleWithMerkleProofs::default(),
-            UtxoProofBundleWithMerkleProofs::default(),
-        ],
-        utxo2_old_root,
-        utxo2_new_root,
-    );
+    let agg_utxo2 = agg_utxo_for(&mut tree, utxo2_proof);
     let agg_utxo2_proof = prove_and_verify(&agg_utxo2).unwrap();
 
     let agg_agg = AggAgg::new([agg_utxo1_proof, agg_utxo2_proof]);
@@ -0,0 +1,25 @@
+use crate::{Result, traits::Verify};
+use zk_primitives::TxnProof;
+
+impl Verify for TxnProof {
+    fn verify(&self) -> Result<()> {
+        match self {
+            TxnProof::Utxo(proof) => proof.verify(),
+            #[cfg(feature = "utxo4")]
+            TxnProof::Utxo4(proof) => proof.verify(),
+            #[cfg(not(feature = "utxo4"))]
+            TxnProof::Utxo4(_) => Err("utxo4 proofs need the utxo4 feature".into()),
+            #[cfg(feature = "swap")]
+            TxnProof::Swap(proof) => proof.legs.iter().try_for_each(Verify::verify),
+            #[cfg(feature = "swap")]
+            TxnProof::SwapLeg(proof) => proof.verify(),
+            #[cfg(not(feature = "swap"))]
+            TxnProof::Swap(_) | TxnProof::SwapLeg(_) => {
+                Err("swap proofs need the swap feature".into())
+            }
+            TxnProof::UtxoV1(_) => {
+                Err("utxo proofs from before nullifiers can't be verified".into())
+            }
+        }
+    }
+}
@@ -1,8 +1,13 @@
-use super::note::{BInputNote, BNote};
+use super::{
+    merkle_path_fields, merkle_paths_input_value,
+    note::{BInputNote, BNote},
+};
 use crate::{
     Result,
     backend::DefaultBackend,
     circuits::get_bytecode_from_program,
+    dry_run::{DryRun, WitnessError},
+    execute::check_witness,
     prove::prove,
     traits::{Prove, Verify},
     util::write_to_temp_file,
@@ -59,7 +64,7 @@ impl Prove for Utxo {
             false,
         )?;
 
-        // Slice the first 6, 32 byte chunks as the public inputs
+        // Slice the first 10, 32 byte chunks as the public inputs
         let public_inputs = proof_bytes[..UTXO_PUBLIC_INPUTS_COUNT * 32].to_vec();
         let public_inputs = bytes_to_elements(&public_inputs);
         let raw_proof = proof_bytes[UTXO_PUBLIC_INPUTS_COUNT * 32..].to_vec();
@@ -74,14 +79,15 @@ impl Prove for Utxo {
         let proof = UtxoProof {
             proof: UtxoProofBytes(raw_proof),
             public_inputs: UtxoPublicInput {
-                input_commitments: [public_inputs[0], public_inputs[1]],
-                output_commitments: [public_inputs[2], public_inputs[3]],
+                root: public_inputs[0],
+                nullifiers: [public_inputs[1], public_inputs[2]],
+                output_commitments: [public_inputs[3], public_inputs[4]],
                 messages: [
-                    public_inputs[4],
                     public_inputs[5],
                     public_inputs[6],
                     public_inputs[7],
                     public_inputs[8],
+                    public_inputs[9],
                 ],
             },
         };
@@ -97,12 +103,21 @@ impl Verify for UtxoProof {
     }
 }
 
+impl DryRun for Utxo {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(&PROGRAM_COMPILED, &InputMap::from(UtxoInput::from(self)))
+    }
+}
+
 #[derive(Debug, Clone)]
 struct UtxoInput {
     input_notes: [BInputNote; 2],
+    input_merkle_paths: [[Base; 160]; 2],
     output_notes: [BNote; 2],
     pmessage4: Base,
-    commitments: [Base; 4],
+    root: Base,
+    nullifiers: [Base; 2],
+    output_commitments: [Base; 2],
     messages: [Base; 5],
 }
 
@@ -116,6 +131,7 @@ impl From<&Utxo> for UtxoInput {
                 .collect::<Vec<_>>()
                 .try_into()
                 .unwrap(),
+            input_merkle_paths: utxo.input_merkle_paths.each_ref().map(merkle_path_fields),
             output_notes: utxo
                 .output_notes
                 .iter()
@@ -124,9 +140,12 @@ impl From<&Utxo> for UtxoInput {
                 .try_into()
                 .unwrap(),
             pmessage4: utxo.messages()[4].to_base(),
-            commitments: [
-                utxo.input_notes[0].note.commitment().to_base(),
-                utxo.input_notes[1].note.commitment().to_base(),
+            root: utxo.root.to_base(),
+            nullifiers: [
+                utxo.input_notes[0].nullifier().to_base(),
+                utxo.input_notes[1].nullifier().to_base(),
+            ],
+            output_commitments: [
                 utxo.output_notes[0].commitment().to_base(),
                 utxo.output_notes[1].commitment().to_base(),
             ],
@@ -143,6 +162,10 @@ impl From<UtxoInput> for InputMap {
             "input_notes".to_owned(),
             InputValue::Vec(utxo.input_notes.map(InputValue::from).to_vec()),
         );
+        map.insert(
+            "input_merkle_paths".to_owned(),
+            merkle_paths_input_value(utxo.input_merkle_paths),
+        );
         map.insert(
             "output_notes".to_owned(),
             InputValue::Vec(utxo.output_notes.map(InputValue::from).to_vec()),
@@ -150,9 +173,14 @@ impl From<UtxoInput> for InputMap {
 
         map.insert("pmessage4".to_owned(), InputValue::Field(utxo.pmessage4));
 
+        map.insert("root".to_owned(), InputValue::Field(utxo.root));
+        map.insert(
+            "nullifiers".to_owned(),
+            InputValue::Vec(utxo.nullifiers.map(InputValue::Field).to_vec()),
+        );
         map.insert(
-            "commitments".to_owned(),
-            InputValue::Vec(utxo.commitments.map(InputValue::Field).to_vec()),
+            "output_commitments".to_owned(),
+            InputValue::Vec(utxo.output_commitments.map(InputValue::Field).to_vec()),
         );
 
         map.insert(
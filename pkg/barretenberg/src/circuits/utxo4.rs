@@ -0,0 +1,163 @@
+use super::{
+    merkle_path_fields, merkle_paths_input_value,
+    note::{BInputNote, BNote},
+};
+use crate::{
+    Result,
+    backend::DefaultBackend,
+    circuits::get_bytecode_from_program,
+    dry_run::{DryRun, WitnessError},
+    execute::check_witness,
+    prove::prove,
+    traits::{Prove, Verify},
+    verify::{VerificationKey, VerificationKeyHash, verify},
+};
+use element::Base;
+use lazy_static::lazy_static;
+use noirc_abi::{InputMap, input_parser::InputValue};
+use noirc_artifacts::program::ProgramArtifact;
+use noirc_driver::CompiledProgram;
+use zk_primitives::{
+    ToBytes, UTXO_PROOF_SIZE, UTXO4_PUBLIC_INPUTS_COUNT, Utxo4, Utxo4Proof, Utxo4PublicInput,
+    UtxoProofBytes, bytes_to_elements,
+};
+
+const PROGRAM: &str = include_str!("../../../../fixtures/programs/utxo4.json");
+const KEY: &[u8] = include_bytes!("../../../../fixtures/keys/utxo4_key");
+const KEY_FIELDS: &[u8] = include_bytes!("../../../../fixtures/keys/utxo4_key_fields.json");
+
+lazy_static! {
+    static ref PROGRAM_ARTIFACT: ProgramArtifact = serde_json::from_str(PROGRAM).unwrap();
+    static ref PROGRAM_COMPILED: CompiledProgram = CompiledProgram::from(PROGRAM_ARTIFACT.clone());
+    static ref BYTECODE: Vec<u8> = get_bytecode_from_program(PROGRAM);
+    pub static ref UTXO4_VERIFICATION_KEY: VerificationKey = {
+        let fields = serde_json::from_slice::<Vec<Base>>(KEY_FIELDS).unwrap();
+        VerificationKey(fields)
+    };
+    pub static ref UTXO4_VERIFICATION_KEY_HASH: VerificationKeyHash = VerificationKeyHash(
+        bn254_blackbox_solver::poseidon_hash(&UTXO4_VERIFICATION_KEY.0, false).unwrap()
+    );
+}
+
+impl Prove for Utxo4 {
+    type Proof = Utxo4Proof;
+    type Result<Proof> = Result<Proof>;
+
+    fn prove(&self) -> Self::Result<Self::Proof> {
+        let inputs = InputMap::from(Utxo4Input::from(self));
+
+        let proof_bytes = prove::<DefaultBackend>(
+            &PROGRAM_COMPILED,
+            PROGRAM.as_bytes(),
+            &BYTECODE,
+            KEY,
+            &inputs,
+            true,
+            false,
+        )?;
+
+        // The public inputs are the first 10, 32 byte chunks
+        let public_inputs = proof_bytes[..UTXO4_PUBLIC_INPUTS_COUNT * 32].to_vec();
+        let public_inputs = bytes_to_elements(&public_inputs);
+        let raw_proof = proof_bytes[UTXO4_PUBLIC_INPUTS_COUNT * 32..].to_vec();
+
+        assert_eq!(
+            raw_proof.len(),
+            UTXO_PROOF_SIZE * 32,
+            "Proof must be {UTXO_PROOF_SIZE} elements of 32 bytes"
+        );
+
+        Ok(Utxo4Proof {
+            proof: UtxoProofBytes(raw_proof),
+            public_inputs: Utxo4PublicInput {
+                root: public_inputs[0],
+                nullifiers: [
+                    public_inputs[1],
+                    public_inputs[2],
+                    public_inputs[3],
+                    public_inputs[4],
+                ],
+                output_commitments: [
+                    public_inputs[5],
+                    public_inputs[6],
+                    public_inputs[7],
+                    public_inputs[8],
+                ],
+                kind: public_inputs[9],
+            },
+        })
+    }
+}
+
+impl Verify for Utxo4Proof {
+    fn verify(&self) -> Result<()> {
+        let bytes = self.to_bytes();
+        verify::<DefaultBackend>(KEY, &bytes, false)
+    }
+}
+
+impl DryRun for Utxo4 {
+    fn dry_run(&self) -> std::result::Result<(), WitnessError> {
+        check_witness(&PROGRAM_COMPILED, &InputMap::from(Utxo4Input::from(self)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Utxo4Input {
+    input_notes: [BInputNote; 4],
+    input_merkle_paths: [[Base; 160]; 4],
+    output_notes: [BNote; 4],
+    root: Base,
+    nullifiers: [Base; 4],
+    output_commitments: [Base; 4],
+    kind: Base,
+}
+
+impl From<&Utxo4> for Utxo4Input {
+    fn from(utxo: &Utxo4) -> Self {
+        let public_inputs = utxo.public_inputs();
+
+        Utxo4Input {
+            input_notes: utxo.input_notes.each_ref().map(BInputNote::from),
+            input_merkle_paths: utxo.input_merkle_paths.each_ref().map(merkle_path_fields),
+            output_notes: utxo.output_notes.each_ref().map(BNote::from),
+            root: public_inputs.root.to_base(),
+            nullifiers: public_inputs.nullifiers.map(|e| e.to_base()),
+            output_commitments: public_inputs.output_commitments.map(|e| e.to_base()),
+            kind: public_inputs.kind.to_base(),
+        }
+    }
+}
+
+impl From<Utxo4Input> for InputMap {
+    fn from(utxo: Utxo4Input) -> Self {
+        let mut map = InputMap::new();
+
+        map.insert(
+            "input_notes".to_owned(),
+            InputValue::Vec(utxo.input_notes.map(InputValue::from).to_vec()),
+        );
+        map.insert(
+            "input_merkle_paths".to_owned(),
+            merkle_paths_input_value(utxo.input_merkle_paths),
+        );
+        map.insert(
+            "output_notes".to_owned(),
+            InputValue::Vec(utxo.output_notes.map(InputValue::from).to_vec()),
+        );
+
+        map.insert("root".to_owned(), InputValue::Field(utxo.root));
+        map.insert(
+            "nullifiers".to_owned(),
+            InputValue::Vec(utxo.nullifiers.map(InputValue::Field).to_vec()),
+        );
+        map.insert(
+            "output_commitments".to_owned(),
+            InputValue::Vec(utxo.output_commitments.map(InputValue::Field).to_vec()),
+        );
+
+        map.insert("kind".to_owned(), InputValue::Field(utxo.kind));
+
+        map
+    }
+}
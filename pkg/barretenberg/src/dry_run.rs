@@ -0,0 +1,283 @@
+/// Solve a circuit's witness without generating a proof
+///
+/// Solving takes milliseconds, where proving takes seconds, so inputs can be checked
+/// before committing to a proof. Recursive proofs are only verified while proving, so a
+/// dry run of an aggregation circuit doesn't check the proofs it aggregates
+pub trait DryRun {
+    /// Check the inputs satisfy every constraint of the circuit
+    fn dry_run(&self) -> Result<(), WitnessError>;
+}
+
+/// A constraint that failed while solving a circuit's witness
+///
+/// Failed assertions are matched on the message the circuit asserts with, unknown messages
+/// are returned as [`WitnessError::Assertion`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WitnessError {
+    /// The inputs could not be encoded for the circuit's ABI
+    #[error("invalid circuit input: {0}")]
+    InvalidInput(String),
+
+    /// A note does not match its commitment
+    #[error("note commitment is not valid")]
+    InvalidCommitment,
+
+    /// A padding note has a non-zero value
+    #[error("padding notes must be zero value")]
+    NonZeroPaddingNote,
+
+    /// An input note is not owned by its secret key
+    #[error("input note is not owned by the owner")]
+    InputNoteNotOwned,
+
+    /// An input note is not in the tree at the transaction's root
+    #[error("input note is not in the tree")]
+    InputNoteNotInTree,
+
+    /// A nullifier is not the nullifier of its input note
+    #[error("nullifier is not valid")]
+    InvalidNullifier,
+
+    /// A value is larger than the circuit allows
+    #[error("value is too large")]
+    ValueTooLarge,
+
+    /// The notes of a transaction are of different kinds
+    #[error("inconsistent kinds are not allowed")]
+    InconsistentKinds,
+
+    /// The transaction kind is not supported by the circuit
+    #[error("invalid kind")]
+    InvalidKind,
+
+    /// The input and output values of a send don't match
+    #[error("input and output totals do not match")]
+    InputOutputMismatch,
+
+    /// The output value of a mint doesn't match the minted value
+    #[error("mint output must match value message")]
+    MintValueMismatch,
+
+    /// The mint hash doesn't match the mint message
+    #[error("mint hash must match message")]
+    MintHashMismatch,
+
+    /// The minted note kind doesn't match the mint message
+    #[error("mint note kind must match message")]
+    MintNoteKindMismatch,
+
+    /// The burn address doesn't match the burn message
+    #[error("burn address must match message")]
+    BurnAddressMismatch,
+
+    /// The burn hash doesn't match the burn message
+    #[error("burn hash must match message")]
+    BurnHashMismatch,
+
+    /// The burned value doesn't match the burn message
+    #[error("burn output must match value message")]
+    BurnValueMismatch,
+
+    /// The burned note kind doesn't match the burn message
+    #[error("burn note kind must match message")]
+    BurnNoteKindMismatch,
+
+    /// An aggregated proof was not made with the expected verification key
+    #[error("proof verification key is not allowed")]
+    InvalidVerificationKey,
+
+    /// An aggregated proof isn't from a circuit the aggregation accepts, or has more leaves
+    /// than its circuit, or a swap kind from another circuit
+    #[error("proof circuit is not valid")]
+    InvalidCircuit,
+
+    /// An aggregated proof's kind doesn't match its messages
+    #[error("proof kind must match message")]
+    ProofKindMismatch,
+
+    /// An aggregated swap leg isn't followed by its counterpart leg
+    #[error("swap leg must be next to its counterpart")]
+    UnpairedSwapLeg,
+
+    /// Adjacent aggregated swap legs don't give what the other wants
+    #[error("swap legs must match")]
+    SwapLegsMismatch,
+
+    /// The first aggregated proof is a padding proof
+    #[error("first proof cannot be a padding proof")]
+    FirstProofPadding,
+
+    /// A padding proof has non-zero messages
+    #[error("padding proof messages must be zero")]
+    NonZeroPaddingMessage,
+
+    /// The computed root doesn't match the new root
+    #[error("new root is not valid")]
+    InvalidNewRoot,
+
+    /// A merkle path doesn't lead to the expected root
+    #[error("merkle path root does not match")]
+    MerklePathMismatch,
+
+    /// An aggregated proof's old root doesn't follow the previous proof
+    #[error("proof old root must match previous root")]
+    OldRootMismatch,
+
+    /// The value of the points notes doesn't match the claimed value
+    #[error("invalid points value")]
+    InvalidPointsValue,
+
+    /// A swap leg gives and wants the same kind
+    #[error("swap kinds must be different")]
+    SameSwapKinds,
+
+    /// A swap leg has no kind to give
+    #[error("swap must give a kind")]
+    MissingGiveKind,
+
+    /// A swap leg has no kind to want
+    #[error("swap must want a kind")]
+    MissingWantKind,
+
+    /// A swap leg's inputs don't cover the given value and its change
+    #[error("given kind does not balance")]
+    GiveKindUnbalanced,
+
+    /// A swap leg spends a note that isn't of the given kind
+    #[error("input kind must match give kind")]
+    InputKindMismatch,
+
+    /// A swap leg's change isn't of the given kind
+    #[error("change kind must match give kind")]
+    ChangeKindMismatch,
+
+    /// A swap leg receives a note that isn't of the wanted kind
+    #[error("received kind must match want kind")]
+    ReceivedKindMismatch,
+
+    /// A swap leg receives a different value than it wants
+    #[error("received value must match want value")]
+    ReceivedValueMismatch,
+
+    /// A transaction other than a send was given to a send-only circuit
+    #[error("only send transactions are allowed")]
+    OnlySendAllowed,
+
+    /// A send has non-zero messages after its kind
+    #[error("send messages must be zero")]
+    NonZeroSendMessage,
+
+    /// The signing key doesn't own the address
+    #[error("address is not owned by the owner")]
+    AddressNotOwned,
+
+    /// The signed message hash doesn't match the message
+    #[error("message hash is invalid")]
+    InvalidMessageHash,
+
+    /// The migration key doesn't own the old address
+    #[error("old address is not owned by the owner")]
+    OldAddressNotOwned,
+
+    /// The migration key doesn't own the new address
+    #[error("new address is not owned by the owner")]
+    NewAddressNotOwned,
+
+    /// An assertion failed with a message not known to this enum
+    #[error("assertion failed: {0}")]
+    Assertion(String),
+
+    /// A constraint without a message failed
+    #[error("constraint not satisfied: {0}")]
+    Unsatisfied(String),
+}
+
+impl WitnessError {
+    /// Map the message of a failed circuit assertion to an error
+    #[must_use]
+    pub fn from_message(message: &str) -> Self {
+        match message {
+            "Note commitment is not valid" => Self::InvalidCommitment,
+            "Padding notes must be zero value" => Self::NonZeroPaddingNote,
+            "Input note is not owned by the owner" => Self::InputNoteNotOwned,
+            "Input note is not in the tree" => Self::InputNoteNotInTree,
+            "Nullifier is not valid" => Self::InvalidNullifier,
+            "call to assert_max_bit_size" => Self::ValueTooLarge,
+            "Inconsistent kinds are not allowed" => Self::InconsistentKinds,
+            "Invalid kind" => Self::InvalidKind,
+            "Input and output totals do not match" => Self::InputOutputMismatch,
+            "Mint output must match value message" => Self::MintValueMismatch,
+            "Mint hash must match message" => Self::MintHashMismatch,
+            "Mint note kind must match message" => Self::MintNoteKindMismatch,
+            "messages[4] must match private input" => Self::BurnAddressMismatch,
+            "Burn hash must match message" => Self::BurnHashMismatch,
+            "Burn output must match value message" => Self::BurnValueMismatch,
+            "Burn note kind must match message" => Self::BurnNoteKindMismatch,
+            "only utxo proof allowed"
+            | "only utxo4 proof allowed"
+            | "only swap proof allowed"
+            | "only agg_utxo proof allowed" => Self::InvalidVerificationKey,
+            "Invalid circuit"
+            | "utxo proofs have two nullifiers"
+            | "utxo proofs have two output commitments"
+            | "Only swap proofs have the swap kind" => Self::InvalidCircuit,
+            "Swap leg must be next to its counterpart" => Self::UnpairedSwapLeg,
+            "Swap legs must match" => Self::SwapLegsMismatch,
+            "proof 'kind' must match message" => Self::ProofKindMismatch,
+            "first proof cannot be a padding proof" => Self::FirstProofPadding,
+            "Message is not zero" => Self::NonZeroPaddingMessage,
+            "New root is not valid" | "Roots must match" => Self::InvalidNewRoot,
+            "Merkle path root does not match" => Self::MerklePathMismatch,
+            "proof old_root must match previous root" => Self::OldRootMismatch,
+            "Invalid value" => Self::InvalidPointsValue,
+            "Swap kinds must be different" => Self::SameSwapKinds,
+            "Swap must give a kind" => Self::MissingGiveKind,
+            "Swap must want a kind" => Self::MissingWantKind,
+            "Given kind does not balance" => Self::GiveKindUnbalanced,
+            "Input kind must match give kind" => Self::InputKindMismatch,
+            "Change kind must match give kind" => Self::ChangeKindMismatch,
+            "Received kind must match want kind" => Self::ReceivedKindMismatch,
+            "Received value must match want value" => Self::ReceivedValueMismatch,
+            "Only send transactions are allowed" => Self::OnlySendAllowed,
+            "Send messages must be zero" => Self::NonZeroSendMessage,
+            "Address is not owned by the owner" => Self::AddressNotOwned,
+            "Message hash is invalid" => Self::InvalidMessageHash,
+            "Old address is not owned by the owner" => Self::OldAddressNotOwned,
+            "New address is not owned by the owner" => Self::NewAddressNotOwned,
+            message => Self::Assertion(message.to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_map_to_errors() {
+        assert_eq!(
+            WitnessError::from_message("Input and output totals do not match"),
+            WitnessError::InputOutputMismatch
+        );
+        assert_eq!(
+            WitnessError::from_message("Roots must match"),
+            WitnessError::InvalidNewRoot
+        );
+        assert_eq!(
+            WitnessError::from_message("Given kind does not balance"),
+            WitnessError::GiveKindUnbalanced
+        );
+        assert_eq!(
+            WitnessError::from_message("Swap leg must be next to its counterpart"),
+            WitnessError::UnpairedSwapLeg
+        );
+        assert_eq!(
+            WitnessError::from_message("Only send transactions are allowed"),
+            WitnessError::OnlySendAllowed
+        );
+        assert_eq!(
+            WitnessError::from_message("Something else"),
+            WitnessError::Assertion("Something else".to_owned())
+        );
+    }
+}
@@ -1,13 +1,15 @@
 use acvm::FieldElement;
-use acvm::acir::native_types::WitnessStack;
+use acvm::acir::native_types::{WitnessMap, WitnessStack};
 use bn254_blackbox_solver::Bn254BlackBoxSolver;
-use nargo::errors::try_to_diagnose_runtime_error;
+use nargo::errors::{NargoError, try_to_diagnose_runtime_error};
 use nargo::foreign_calls::DefaultForeignCallBuilder;
 use noirc_abi::InputMap;
 use noirc_abi::input_parser::InputValue;
 use noirc_artifacts::debug::DebugArtifact;
 use noirc_driver::CompiledProgram;
 
+use crate::dry_run::WitnessError;
+
 #[derive(Debug)]
 pub struct ExecutionResults {
     #[allow(dead_code)]
@@ -42,20 +44,12 @@ pub fn execute_program(
 ) -> Result<WitnessStack<FieldElement>, Box<dyn std::error::Error>> {
     let initial_witness = compiled_program.abi.encode(inputs_map, None)?;
 
-    let solved_witness_stack_err = nargo::ops::execute_program(
-        &compiled_program.program,
+    match solve(
+        compiled_program,
         initial_witness,
-        &Bn254BlackBoxSolver(pedantic_solving),
-        &mut DefaultForeignCallBuilder {
-            output: std::io::stdout(),
-            enable_mocks: false,
-            // resolver_url: foreign_call_resolver_url.map(|s| s.to_string()),
-            // root_path,
-            // package_name,
-        }
-        .build(),
-    );
-    match solved_witness_stack_err {
+        pedantic_solving,
+        std::io::stdout(),
+    ) {
         Ok(solved_witness_stack) => Ok(solved_witness_stack),
         Err(err) => {
             let debug_artifact = DebugArtifact {
@@ -73,3 +67,49 @@ pub fn execute_program(
         }
     }
 }
+
+/// Solve the witness for `inputs_map` without proving, returning the circuit's
+/// assertion message if a constraint fails
+///
+/// Output printed by the circuit is discarded, so dry runs don't write to the caller's stdout
+pub fn check_witness(
+    compiled_program: &CompiledProgram,
+    inputs_map: &InputMap,
+) -> Result<(), WitnessError> {
+    let initial_witness = compiled_program
+        .abi
+        .encode(inputs_map, None)
+        .map_err(|err| WitnessError::InvalidInput(err.to_string()))?;
+
+    match solve(compiled_program, initial_witness, false, std::io::sink()) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(
+            match err.user_defined_failure_message(&compiled_program.abi.error_types) {
+                Some(message) => WitnessError::from_message(&message),
+                None => WitnessError::Unsatisfied(err.to_string()),
+            },
+        ),
+    }
+}
+
+/// Solve the witness, writing anything the circuit prints to `output`
+fn solve<W: std::io::Write + 'static>(
+    compiled_program: &CompiledProgram,
+    initial_witness: WitnessMap<FieldElement>,
+    pedantic_solving: bool,
+    output: W,
+) -> Result<WitnessStack<FieldElement>, NargoError<FieldElement>> {
+    nargo::ops::execute_program(
+        &compiled_program.program,
+        initial_witness,
+        &Bn254BlackBoxSolver(pedantic_solving),
+        &mut DefaultForeignCallBuilder {
+            output,
+            enable_mocks: false,
+            // resolver_url: foreign_call_resolver_url.map(|s| s.to_string()),
+            // root_path,
+            // package_name,
+        }
+        .build(),
+    )
+}
@@ -1,12 +1,18 @@
 mod backend;
 mod circuits;
+mod dry_run;
 mod execute;
 mod prove;
 mod traits;
 mod util;
 pub mod verify;
 
+pub use backend::{
+    BackendConfig, BackendKind, BackendStats, backend_stats, configure as configure_backends,
+    health_check as backend_health_check,
+};
 pub use circuits::AGG_UTXO_VERIFICATION_KEY_HASH;
+pub use dry_run::{DryRun, WitnessError};
 pub use traits::{Prove, Verify};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
@@ -11,9 +11,7 @@ pub fn prove<B: Backend>(
     recursive: bool,
     oracle_hash_keccak: bool,
 ) -> Result<Vec<u8>> {
-    let results = execute_program_and_decode(compiled_program, inputs_map, false)?;
-
-    let witness = bincode::serialize(&results.witness_stack)?;
+    let witness = solve_witness(compiled_program, inputs_map)?;
 
     B::prove(
         program,
@@ -26,6 +24,13 @@ pub fn prove<B: Backend>(
     // Ok(proof)
 }
 
+/// Solve the witness for `inputs_map`, serialized for a [`Backend`]
+pub fn solve_witness(compiled_program: &CompiledProgram, inputs_map: &InputMap) -> Result<Vec<u8>> {
+    let results = execute_program_and_decode(compiled_program, inputs_map, false)?;
+
+    Ok(bincode::serialize(&results.witness_stack)?)
+}
+
 // pub fn prove_witness(
 //     bb_path: &PathBuf,
 //     program_path: &PathBuf,
@@ -0,0 +1,221 @@
+use std::{hint::black_box, path::Path};
+
+use benchy::{BenchmarkRun, benchmark};
+use block_store::{Block, BlockListOrder, BlockStore, Transaction};
+use element::Element;
+use primitives::block_height::BlockHeight;
+use rand::{RngCore, thread_rng};
+use tempdir::TempDir;
+use wire_message::WireMessage;
+
+const BLOCK_COUNT: u64 = 2_000;
+const PAGE_SIZE: usize = 100;
+const PROOF_LEN: usize = 16 * 1024;
+
+#[derive(
+    Debug,
+    Clone,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    wire_message::strum_macros::EnumCount,
+)]
+enum BenchBlock {
+    V1(BlockHeight, Vec<BenchTxn>),
+}
+
+#[derive(
+    Debug,
+    Clone,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    wire_message::strum_macros::EnumCount,
+)]
+enum BenchTxn {
+    V1 {
+        hash: [u8; 32],
+        commitments: [Element; 4],
+        // Real UTXO proofs are mostly field elements, with long runs of padding
+        proof: Vec<u8>,
+    },
+}
+
+impl WireMessage for BenchBlock {
+    type Ctx = ();
+    type Err = core::convert::Infallible;
+
+    fn version(&self) -> u64 {
+        1
+    }
+
+    fn upgrade_once(self, _ctx: &mut Self::Ctx) -> Result<Self, wire_message::Error> {
+        Err(Self::max_version_error())
+    }
+}
+
+impl WireMessage for BenchTxn {
+    type Ctx = ();
+    type Err = core::convert::Infallible;
+
+    fn version(&self) -> u64 {
+        1
+    }
+
+    fn upgrade_once(self, _ctx: &mut Self::Ctx) -> Result<Self, wire_message::Error> {
+        Err(Self::max_version_error())
+    }
+}
+
+impl Block for BenchBlock {
+    type Txn = BenchTxn;
+
+    fn block_height(&self) -> BlockHeight {
+        let Self::V1(height, _) = self;
+        *height
+    }
+
+    fn block_hash(&self) -> [u8; 32] {
+        let mut hash = [0; 32];
+        hash[..8].copy_from_slice(&self.block_height().to_be_bytes());
+        hash
+    }
+
+    fn txns(&self) -> Vec<Self::Txn> {
+        let Self::V1(_, txns) = self;
+        txns.clone()
+    }
+}
+
+impl Transaction for BenchTxn {
+    fn txn_hash(&self) -> [u8; 32] {
+        let Self::V1 { hash, .. } = self;
+        *hash
+    }
+
+    fn input_elements(&self) -> Vec<Element> {
+        let Self::V1 { commitments, .. } = self;
+        commitments[..2].to_vec()
+    }
+
+    fn output_elements(&self) -> Vec<Element> {
+        let Self::V1 { commitments, .. } = self;
+        commitments[2..].to_vec()
+    }
+
+    fn mint_hash(&self) -> Option<Element> {
+        None
+    }
+}
+
+fn make_block(height: u64) -> BenchBlock {
+    let mut rng = thread_rng();
+
+    // Roughly one in four blocks carries transactions
+    let txns = if height % 4 == 0 {
+        (0..2)
+            .map(|_| {
+                let mut hash = [0; 32];
+                rng.fill_bytes(&mut hash);
+
+                let mut proof = vec![0; PROOF_LEN];
+                rng.fill_bytes(&mut proof[..PROOF_LEN / 2]);
+
+                BenchTxn::V1 {
+                    hash,
+                    commitments: core::array::from_fn(|_| Element::secure_random(&mut rng)),
+                    proof,
+                }
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+
+    BenchBlock::V1(BlockHeight(height), txns)
+}
+
+fn make_legacy_store(path: &Path) -> BlockStore<BenchBlock> {
+    let store = BlockStore::create_legacy_v1(path).unwrap();
+    for height in 0..BLOCK_COUNT {
+        store.set_legacy_v1(&make_block(height)).unwrap();
+    }
+    store
+}
+
+fn dir_size(path: &Path) -> usize {
+    std::fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len() as usize)
+        .sum()
+}
+
+fn read_all_pages(mut next_page: impl FnMut(&Option<BlockHeight>) -> Vec<BenchBlock>) {
+    let mut cursor = None;
+    loop {
+        let page = next_page(&cursor);
+        let Some(last) = page.last() else {
+            break;
+        };
+
+        cursor = Some(last.block_height());
+        black_box(page);
+    }
+}
+
+fn after(
+    cursor: &Option<BlockHeight>,
+) -> Option<primitives::pagination::CursorChoice<BlockHeight>> {
+    cursor.map(|height| {
+        primitives::pagination::CursorChoice::After(
+            primitives::pagination::CursorChoiceAfter::After(height),
+        )
+    })
+}
+
+#[benchmark]
+pub fn list_paginated_uncompressed(b: &mut BenchmarkRun) {
+    let dir = TempDir::new("block-store-benchmark").unwrap();
+    drop(make_legacy_store(dir.path()));
+
+    // reopening flushes the write-ahead log, so the size reflects the stored tables
+    let store = BlockStore::<BenchBlock>::create_or_load(dir.path()).unwrap();
+
+    b.run(|| {
+        read_all_pages(|cursor| {
+            store
+                .list_paginated_legacy_v1(
+                    &after(cursor),
+                    BlockListOrder::LowestToHighest,
+                    PAGE_SIZE,
+                )
+                .unwrap()
+                .map(|r| r.unwrap().1)
+                .collect()
+        })
+    });
+
+    b.metrics
+        .insert("db_size_bytes".into(), dir_size(dir.path()));
+}
+
+#[benchmark]
+pub fn list_paginated_compressed(b: &mut BenchmarkRun) {
+    let dir = TempDir::new("block-store-benchmark").unwrap();
+    make_legacy_store(dir.path()).migrate().unwrap();
+
+    let store = BlockStore::<BenchBlock>::create_or_load(dir.path()).unwrap();
+
+    b.run(|| {
+        read_all_pages(|cursor| {
+            store
+                .list_paginated(&after(cursor), BlockListOrder::LowestToHighest, PAGE_SIZE)
+                .unwrap()
+                .map(|r| r.unwrap().1)
+                .collect()
+        })
+    });
+
+    b.metrics
+        .insert("db_size_bytes".into(), dir_size(dir.path()));
+}
+
+benchy::main!(list_paginated_uncompressed, list_paginated_compressed);
@@ -4,11 +4,11 @@ use std::{
 };
 
 use crate::list::StoreList;
+use kv_store::KvStore;
 use primitives::{block_height::BlockHeight, pagination::CursorChoice};
-use rocksdb::DB;
 use wire_message::WireMessage;
 
-use crate::{Block, Error, Result, list::List};
+use crate::{Block, Error, Result, list::List, values::BlockValue};
 
 pub(crate) trait StoreKey: Clone {
     fn to_key(&self) -> Key;
@@ -31,6 +31,30 @@ where
     }
 }
 
+/// A block stored under [`Key::Block`], decompressed on read
+pub(crate) struct CompressedBlock<B>(pub(crate) B);
+
+impl<B> StoreValue for CompressedBlock<B>
+where
+    B: WireMessage,
+{
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let value = <BlockValue as borsh::BorshDeserialize>::deserialize(&mut &bytes[..])?;
+        Ok(Self(B::from_bytes(&value.decompress()?)?))
+    }
+}
+
+/// The value stored under [`Key::NonEmptyBlock`].
+///
+/// Since store version 2 this is empty, the block itself is only stored under [`Key::Block`].
+pub(crate) struct NonEmptyBlockRef;
+
+impl StoreValue for NonEmptyBlockRef {
+    fn deserialize(_bytes: &[u8]) -> Result<Self> {
+        Ok(Self)
+    }
+}
+
 pub(crate) trait KeyOrder: Copy {
     /// If this is the default order the keys are indexed in.
     fn is_indexed_order(&self) -> bool;
@@ -48,7 +72,7 @@ where
     fn max_value() -> Self;
 
     fn list<'db>(
-        db: &'db DB,
+        db: &'db dyn KvStore,
         range: impl RangeBounds<Self>,
         order: &Self::Order,
     ) -> List<'db, Value>
@@ -83,7 +107,7 @@ where
     }
 
     fn list_paginated(
-        db: &DB,
+        db: &dyn KvStore,
         cursor: &Option<CursorChoice<Self>>,
         order: Self::Order,
         limit: usize,
@@ -132,6 +156,10 @@ pub enum Key {
     LockedElement([u8; 32]),
     ElementHistory((element::Element, ElementHistoryKind)),
     MintHash(element::Element),
+    /// The last block height rewritten by an in-progress migration to a store version
+    MigrationProgress(u32),
+    /// A nullifier revealed by a spend
+    Nullifier(element::Element),
 }
 
 // TODO: this might be confusing,
@@ -173,6 +201,9 @@ impl Key {
             Self::LockedElement(_) => 7,
             Self::ElementHistory(_) => 8,
             Self::MintHash(_) => 9,
+            Self::MigrationProgress(_) => 10,
+            // 11 was used by inbox notes, which the node now keeps in its own store
+            Self::Nullifier(_) => 12,
         }
     }
 
@@ -205,6 +236,12 @@ impl Key {
             Self::MintHash(mint_hash) => {
                 out.extend_from_slice(&mint_hash.to_be_bytes());
             }
+            Self::MigrationProgress(version) => {
+                out.extend_from_slice(&version.to_be_bytes());
+            }
+            Self::Nullifier(nullifier) => {
+                out.extend_from_slice(&nullifier.to_be_bytes());
+            }
         }
 
         out
@@ -249,6 +286,17 @@ impl Key {
                 let mint_hash = element::Element::from_be_bytes(*mint_hash_arr);
                 Ok(Self::MintHash(mint_hash))
             }
+            10 => {
+                let version_arr: [u8; 4] = bytes.try_into().map_err(|_| Error::InvalidKey)?;
+                Ok(Self::MigrationProgress(u32::from_be_bytes(version_arr)))
+            }
+            12 => {
+                let nullifier_arr: &[u8; 32] =
+                    bytes[0..32].try_into().map_err(|_| Error::InvalidKey)?;
+                Ok(Self::Nullifier(element::Element::from_be_bytes(
+                    *nullifier_arr,
+                )))
+            }
             _ => Err(Error::InvalidKey),
         }
     }
@@ -7,16 +7,21 @@
 mod keys;
 mod list;
 mod migration;
+#[cfg(any(test, feature = "test-api"))]
+mod test_api;
 mod values;
 
 use std::{marker::PhantomData, path::Path};
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use keys::{ElementHistoryKind, Key, KeyBlock, StoreKey};
+use keys::{CompressedBlock, ElementHistoryKind, Key, KeyBlock, StoreKey, StoreValue};
+use kv_store::{KvStore, WriteBatch};
 use migration::LATEST_VERSION;
 use primitives::{block_height::BlockHeight, hash::CryptoHash};
-use rocksdb::DB;
-use values::{ElementHistoryData, ElementHistoryValue, MintHashData, MintHashValue};
+use values::{
+    BlockValue, ElementHistoryData, ElementHistoryValue, MintHashData, MintHashValue,
+    NullifierData, NullifierValue,
+};
 use wire_message::WireMessage;
 
 pub use keys::BlockListOrder;
@@ -33,8 +38,14 @@ pub enum Error {
     #[error("invalid version '{0}'")]
     InvalidVersion(u32),
 
-    #[error("rocksdb error: {0}")]
-    RocksDB(#[from] rocksdb::Error),
+    #[error("missing block {0}")]
+    MissingBlock(u64),
+
+    #[error("block of {0} bytes is too large to store")]
+    BlockTooLarge(usize),
+
+    #[error("kv store error: {0}")]
+    KvStore(#[from] kv_store::Error),
 
     #[error("wire message error: {0}")]
     WireMessage(#[from] wire_message::Error),
@@ -46,7 +57,7 @@ pub enum Error {
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub struct BlockStore<B> {
-    db: DB,
+    db: Box<dyn KvStore>,
     _marker: PhantomData<B>,
 }
 
@@ -64,6 +75,12 @@ pub trait Transaction {
     fn input_elements(&self) -> Vec<element::Element>;
     fn output_elements(&self) -> Vec<element::Element>;
     fn mint_hash(&self) -> Option<element::Element>;
+
+    /// Nullifiers revealed by the transaction's inputs. Transactions that spend notes by
+    /// removing their commitments from the tree don't reveal any
+    fn nullifiers(&self) -> Vec<element::Element> {
+        Vec::new()
+    }
 }
 
 impl<B> BlockStore<B>
@@ -71,12 +88,6 @@ where
     B: Block + WireMessage,
     B::Txn: WireMessage + Transaction,
 {
-    fn db_options(create_if_missing: bool) -> rocksdb::Options {
-        let mut opts = rocksdb::Options::default();
-        opts.create_if_missing(create_if_missing);
-        opts
-    }
-
     pub fn create_or_load(path: &Path) -> Result<Self> {
         if path.exists() && std::fs::read_dir(path)?.next().is_some() {
             Self::load_existing(path)
@@ -85,9 +96,16 @@ where
         }
     }
 
+    /// Creates an empty store held in memory, for tests and simulations
+    pub fn in_memory() -> Result<Self> {
+        Self::create_with(Box::new(kv_store::Memory::new()))
+    }
+
     fn create(path: &Path) -> Result<Self> {
-        let db = DB::open(&Self::db_options(true), path)?;
+        Self::create_with(Box::new(kv_store::RocksDb::open(path, true)?))
+    }
 
+    fn create_with(db: Box<dyn KvStore>) -> Result<Self> {
         let self_ = Self {
             db,
             _marker: PhantomData,
@@ -99,21 +117,24 @@ where
     }
 
     fn load_existing(path: &Path) -> Result<Self> {
-        let db = DB::open(&Self::db_options(false), path)?;
+        let db = kv_store::RocksDb::open(path, false)?;
 
         Ok(Self {
-            db,
+            db: Box::new(db),
             _marker: PhantomData,
         })
     }
 
     pub fn set(&self, block: &B) -> Result<()> {
-        let mut batch = rocksdb::WriteBatchWithTransaction::<false>::default();
+        let mut batch = WriteBatch::default();
 
         let height = block.block_height();
         let block_hash_arr = block.block_hash();
 
-        batch.put(Key::Block(KeyBlock(height)).serialize(), block.to_bytes()?);
+        batch.put(
+            Key::Block(KeyBlock(height)).serialize(),
+            Self::block_value_bytes(block)?,
+        );
 
         let max_height = self.get_max_height()?;
         if max_height.is_none_or(|max_height| height > max_height) {
@@ -131,7 +152,7 @@ where
         }
 
         if let Some(key) = keys::KeyNonEmptyBlock::from_block(block) {
-            batch.put(key.to_key().serialize(), block.to_bytes()?);
+            batch.put(key.to_key().serialize(), b"");
         }
 
         for txn in block.txns() {
@@ -170,6 +191,21 @@ where
 
                 batch.put(mint_hash_key.serialize(), &mint_hash_value_bytes);
             }
+
+            for nullifier in txn.nullifiers() {
+                let nullifier_value = NullifierValue::V1(NullifierData {
+                    txn_hash: txn.txn_hash(),
+                    block_hash: CryptoHash(block_hash_arr),
+                    block_height: height,
+                });
+                let mut nullifier_value_bytes = Vec::new();
+                nullifier_value.serialize(&mut nullifier_value_bytes)?;
+
+                batch.put(
+                    Key::Nullifier(nullifier).serialize(),
+                    &nullifier_value_bytes,
+                );
+            }
         }
 
         self.db.write(batch)?;
@@ -177,6 +213,16 @@ where
         Ok(())
     }
 
+    /// Serializes a block into the compressed value stored under [`Key::Block`]
+    fn block_value_bytes(block: &B) -> Result<Vec<u8>> {
+        let value = BlockValue::compress(&block.to_bytes()?)?;
+
+        let mut bytes = Vec::new();
+        value.serialize(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
     fn txn_entries(block: &B) -> impl Iterator<Item = Result<(Key, Vec<u8>)>> + '_ {
         block
             .txns()
@@ -187,14 +233,25 @@ where
     pub fn get(&self, block_number: BlockHeight) -> Result<Option<B>> {
         let key = Key::Block(KeyBlock(block_number)).serialize();
 
-        let block_bytes = self.db.get(key)?;
-        let block = block_bytes.map(|bytes| B::from_bytes(&bytes)).transpose()?;
+        let block_bytes = self.db.get(&key)?;
+        let block = block_bytes
+            .map(|bytes| CompressedBlock::<B>::deserialize(&bytes).map(|block| block.0))
+            .transpose()?;
 
         Ok(block)
     }
 
+    /// Creates a point-in-time copy of the store at `path` using a RocksDB checkpoint.
+    ///
+    /// `path` must not exist yet. Files are hard-linked where possible, so this is cheap even
+    /// for large stores. In-memory stores can't be checkpointed.
+    pub fn checkpoint(&self, path: &Path) -> Result<()> {
+        self.db.checkpoint(path)?;
+        Ok(())
+    }
+
     pub fn get_max_height(&self) -> Result<Option<BlockHeight>> {
-        if let Some(max_block) = self.db.get(Key::MaxHeight.serialize())? {
+        if let Some(max_block) = self.db.get(&Key::MaxHeight.serialize())? {
             Ok(Some(BlockHeight(u64::from_be_bytes(
                 max_block.try_into().unwrap(),
             ))))
@@ -206,7 +263,7 @@ where
     pub fn get_block_height_by_hash(&self, block_hash: [u8; 32]) -> Result<Option<BlockHeight>> {
         let key_bytes = Key::BlockHashToHeight(block_hash).serialize();
 
-        if let Some(block_height) = self.db.get(key_bytes)? {
+        if let Some(block_height) = self.db.get(&key_bytes)? {
             Ok(Some(BlockHeight(u64::from_be_bytes(
                 block_height.try_into().unwrap(),
             ))))
@@ -217,7 +274,7 @@ where
 
     pub fn get_pending_block(&self) -> Result<Option<B>> {
         let key = Key::PendingBlock;
-        let bytes = self.db.get(key.serialize())?;
+        let bytes = self.db.get(&key.serialize())?;
         let block = bytes.map(|bytes| B::from_bytes(&bytes)).transpose()?;
 
         Ok(block)
@@ -225,7 +282,7 @@ where
 
     pub fn get_txn_by_hash(&self, txn_hash: [u8; 32]) -> Result<Option<B::Txn>> {
         let key = Key::TxnByHash(txn_hash);
-        let bytes = self.db.get(key.serialize())?;
+        let bytes = self.db.get(&key.serialize())?;
 
         if let Some(bytes) = bytes {
             Ok(Some(B::Txn::from_bytes(&bytes)?))
@@ -235,7 +292,7 @@ where
     }
 
     fn store_version(&self) -> Result<u32> {
-        if let Some(version) = self.db.get(Key::StoreVersion.serialize())? {
+        if let Some(version) = self.db.get(&Key::StoreVersion.serialize())? {
             Ok(u32::from_be_bytes(version.try_into().unwrap()))
         } else {
             Ok(0)
@@ -244,7 +301,7 @@ where
 
     fn set_store_version(&self, version: u32) -> Result<()> {
         self.db
-            .put(Key::StoreVersion.serialize(), version.to_be_bytes())?;
+            .put(&Key::StoreVersion.serialize(), &version.to_be_bytes())?;
         Ok(())
     }
 
@@ -254,7 +311,7 @@ where
         kind: ElementHistoryKind,
     ) -> Result<Option<ElementHistoryData>> {
         let key = Key::ElementHistory((element, kind));
-        let Some(bytes) = self.db.get(key.serialize())? else {
+        let Some(bytes) = self.db.get(&key.serialize())? else {
             return Ok(None);
         };
         let value = ElementHistoryValue::deserialize(&mut &bytes[..])?;
@@ -277,7 +334,7 @@ where
     /// Returns mint hash data
     pub fn get_mint_hash(&self, mint_hash: element::Element) -> Result<Option<MintHashData>> {
         let key = Key::MintHash(mint_hash);
-        let Some(bytes) = self.db.get(key.serialize())? else {
+        let Some(bytes) = self.db.get(&key.serialize())? else {
             return Ok(None);
         };
         let value = MintHashValue::deserialize(&mut &bytes[..])?;
@@ -285,6 +342,19 @@ where
             MintHashValue::V1(data) => Ok(Some(data)),
         }
     }
+
+    /// Returns where `nullifier` was revealed, or `None` if the note it belongs to hasn't been
+    /// spent
+    pub fn get_nullifier(&self, nullifier: element::Element) -> Result<Option<NullifierData>> {
+        let key = Key::Nullifier(nullifier);
+        let Some(bytes) = self.db.get(&key.serialize())? else {
+            return Ok(None);
+        };
+        let value = NullifierValue::deserialize(&mut &bytes[..])?;
+        match value {
+            NullifierValue::V1(data) => Ok(Some(data)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -332,6 +402,47 @@ mod tests {
         }
     }
 
+    type NullifierBlock = DummyMsg<(BlockHeight, [u8; 32], Vec<NullifierTxn>)>;
+    type NullifierTxn = DummyMsg<([u8; 32], Vec<Element>)>;
+
+    impl Block for NullifierBlock {
+        type Txn = NullifierTxn;
+
+        fn block_height(&self) -> BlockHeight {
+            self.inner().0
+        }
+
+        fn block_hash(&self) -> [u8; 32] {
+            self.inner().1
+        }
+
+        fn txns(&self) -> Vec<Self::Txn> {
+            self.inner().2.clone()
+        }
+    }
+
+    impl Transaction for NullifierTxn {
+        fn txn_hash(&self) -> [u8; 32] {
+            self.inner().0
+        }
+
+        fn input_elements(&self) -> Vec<element::Element> {
+            Vec::new()
+        }
+
+        fn output_elements(&self) -> Vec<element::Element> {
+            Vec::new()
+        }
+
+        fn mint_hash(&self) -> Option<element::Element> {
+            None
+        }
+
+        fn nullifiers(&self) -> Vec<element::Element> {
+            self.inner().1.clone()
+        }
+    }
+
     fn temp_dir() -> TempDir {
         TempDir::new("block-store").unwrap()
     }
@@ -513,6 +624,71 @@ mod tests {
         assert_eq!(blocks[1..], before_blocks_except_first);
     }
 
+    #[test]
+    fn test_in_memory() {
+        let block_store = BlockStore::<DummyBlock>::in_memory().unwrap();
+        assert_eq!(block_store.store_version().unwrap(), LATEST_VERSION);
+
+        let txn = DummyTxn::V1(([1; 32], (vec![], vec![Element::from(1u64)]), None));
+        for i in 0..10 {
+            let txns = if i % 2 == 0 {
+                vec![txn.clone()]
+            } else {
+                vec![]
+            };
+            block_store
+                .set(&DummyBlock::V1((BlockHeight(i), [i as u8; 32], txns)))
+                .unwrap();
+        }
+
+        assert_eq!(block_store.get_max_height().unwrap(), Some(BlockHeight(9)));
+        assert_eq!(block_store.get_txn_by_hash([1; 32]).unwrap(), Some(txn));
+
+        let heights = block_store
+            .list(.., BlockListOrder::HighestToLowest)
+            .into_iterator()
+            .map(|r| r.map(|(KeyBlock(height), _)| height.0))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(heights, (0..10).rev().collect::<Vec<_>>());
+
+        let non_empty = block_store
+            .list_non_empty(.., BlockListOrder::LowestToHighest)
+            .into_iterator()
+            .map(|r| r.map(|(_, block)| block.block_height().0))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(non_empty, vec![0, 2, 4, 6, 8]);
+
+        let checkpoint_dir = TempDir::new("block-store-checkpoint").unwrap();
+        assert!(matches!(
+            block_store.checkpoint(&checkpoint_dir.path().join("checkpoint")),
+            Err(Error::KvStore(kv_store::Error::Unsupported(_)))
+        ));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let temp_dir = temp_dir();
+        let block_store = BlockStore::<DummyBlock>::create_or_load(temp_dir.path()).unwrap();
+
+        let block = DummyBlock::V1((BlockHeight(1), [1; 32], vec![]));
+        block_store.set(&block).unwrap();
+
+        let checkpoint_dir = TempDir::new("block-store-checkpoint").unwrap();
+        let checkpoint_path = checkpoint_dir.path().join("checkpoint");
+        block_store.checkpoint(&checkpoint_path).unwrap();
+
+        // Writes after the checkpoint are not part of it
+        block_store
+            .set(&DummyBlock::V1((BlockHeight(2), [2; 32], vec![])))
+            .unwrap();
+
+        let restored = BlockStore::<DummyBlock>::create_or_load(&checkpoint_path).unwrap();
+        assert_eq!(restored.get_max_height().unwrap(), Some(BlockHeight(1)));
+        assert_eq!(restored.get(BlockHeight(1)).unwrap(), Some(block));
+    }
+
     #[test]
     fn successor() {
         let temp_dir = temp_dir();
@@ -522,7 +698,7 @@ mod tests {
         db.set(&DummyBlock::V1((height, [0; 32], vec![]))).unwrap();
         db.db
             .put(
-                Key::Block(KeyBlock(BlockHeight(u64::MAX))).serialize_immediate_successor(),
+                &Key::Block(KeyBlock(BlockHeight(u64::MAX))).serialize_immediate_successor(),
                 b"test",
             )
             .unwrap();
@@ -534,4 +710,28 @@ mod tests {
             1
         );
     }
+
+    #[test]
+    fn nullifiers_are_indexed() {
+        let block_store = BlockStore::<NullifierBlock>::in_memory().unwrap();
+        let nullifier = Element::new(1);
+
+        block_store
+            .set(&NullifierBlock::V1((
+                BlockHeight(3),
+                [3; 32],
+                vec![NullifierTxn::V1(([7; 32], vec![nullifier]))],
+            )))
+            .unwrap();
+
+        assert_eq!(
+            block_store.get_nullifier(nullifier).unwrap(),
+            Some(NullifierData {
+                txn_hash: [7; 32],
+                block_hash: CryptoHash([3; 32]),
+                block_height: BlockHeight(3),
+            })
+        );
+        assert_eq!(block_store.get_nullifier(Element::new(2)).unwrap(), None);
+    }
 }
@@ -1,12 +1,14 @@
 use std::{marker::PhantomData, ops::RangeBounds};
 
+use kv_store::{Direction, KeyRange, KvStore};
 use primitives::{block_height::BlockHeight, pagination::CursorChoice};
-use rocksdb::DB;
 use wire_message::WireMessage;
 
 use crate::{
     Block, BlockListOrder, BlockStore, Error, Result,
-    keys::{Key, KeyBlock, KeyNonEmptyBlock, ListableKey, StoreValue},
+    keys::{
+        CompressedBlock, Key, KeyBlock, KeyNonEmptyBlock, ListableKey, NonEmptyBlockRef, StoreValue,
+    },
 };
 
 pub trait StoreList {
@@ -21,7 +23,7 @@ pub trait StoreList {
 }
 
 pub struct List<'db, Stored> {
-    pub(crate) db: &'db DB,
+    pub(crate) db: &'db dyn KvStore,
     pub(crate) start_key: Key,
     pub(crate) end_key: Key,
     pub(crate) lower_exclusive: bool,
@@ -48,24 +50,19 @@ where
             false => self.end_key.serialize(),
         };
 
-        let mut read_opts = rocksdb::ReadOptions::default();
-
-        read_opts.set_iterate_lower_bound(lower_bound);
-        read_opts.set_iterate_upper_bound(upper_bound);
-
-        let iter = self.db.iterator_opt(
+        let iter = self.db.iter(
+            KeyRange::new(lower_bound, upper_bound),
             if self.start_to_end {
-                rocksdb::IteratorMode::Start
+                Direction::Forward
             } else {
-                rocksdb::IteratorMode::End
+                Direction::Reverse
             },
-            read_opts,
         );
 
         iter.map(move |r| {
             let (key, value) = r?;
 
-            let key = Key::deserialize(key.as_ref()).map_err(|_| Error::InvalidKey)?;
+            let key = Key::deserialize(&key).map_err(|_| Error::InvalidKey)?;
             let value = Stored::deserialize(&value)?;
 
             Ok((key, value))
@@ -137,8 +134,8 @@ where
         let end_bound = block_range.end_bound().map(|bh| KeyBlock(*bh));
         let key_range = (start_bound, end_bound);
 
-        KeyBlock::list(&self.db, key_range, &order).map(|r| {
-            let (k, v) = r?;
+        KeyBlock::list(&*self.db, key_range, &order).map(|r| {
+            let (k, CompressedBlock(v)) = r?;
 
             match k {
                 Key::Block(block_number) => Ok((block_number, v)),
@@ -154,13 +151,13 @@ where
         limit: usize,
     ) -> Result<impl Iterator<Item = Result<(KeyBlock, B)>>> {
         Ok(KeyBlock::list_paginated(
-            &self.db,
+            &*self.db,
             &cursor.map(|pag| pag.map_pos(|pos| KeyBlock(*pos))),
             order,
             limit,
         )?
         .into_iter()
-        .map(|(k, v)| match k {
+        .map(|(k, CompressedBlock(v))| match k {
             Key::Block(block_number) => Ok((block_number, v)),
             _ => Err(Error::InvalidKey),
         }))
@@ -175,11 +172,14 @@ where
         let end_bound = block_range.end_bound().map(|bh| KeyNonEmptyBlock(*bh));
         let key_range = (start_bound, end_bound);
 
-        KeyNonEmptyBlock::list(&self.db, key_range, &order).map(|r| {
-            let (k, v) = r?;
+        KeyNonEmptyBlock::list(&*self.db, key_range, &order).map(move |r| {
+            let (k, NonEmptyBlockRef) = r?;
 
             match k {
-                Key::NonEmptyBlock(block_number) => Ok((block_number, v)),
+                Key::NonEmptyBlock(block_number) => {
+                    let block = self.get_non_empty(&block_number)?;
+                    Ok((block_number, block))
+                }
                 _ => Err(Error::InvalidKey),
             }
         })
@@ -192,28 +192,34 @@ where
         limit: usize,
     ) -> Result<impl Iterator<Item = Result<(KeyNonEmptyBlock, B)>> + '_> {
         Ok(KeyNonEmptyBlock::list_paginated(
-            &self.db,
+            &*self.db,
             &cursor.map(|pag| pag.map_pos(|pos| KeyNonEmptyBlock(*pos))),
             order,
             limit,
         )?
         .into_iter()
-        .map(|(k, v)| match k {
-            Key::NonEmptyBlock(block_number) => Ok((block_number, v)),
+        .map(move |(k, NonEmptyBlockRef)| match k {
+            Key::NonEmptyBlock(block_number) => {
+                let block = self.get_non_empty(&block_number)?;
+                Ok((block_number, block))
+            }
             _ => Err(Error::InvalidKey),
         }))
     }
 
-    pub fn list_txns(&self) -> impl Iterator<Item = Result<B::Txn>> + '_ {
-        let mut read_opts = rocksdb::ReadOptions::default();
-
-        read_opts.set_iterate_lower_bound(Key::TxnByHash([0; 32]).serialize());
-        read_opts
-            .set_iterate_upper_bound(Key::TxnByHash([255; 32]).serialize_immediate_successor());
+    /// Resolves an entry of the non-empty block index to the block it points to
+    fn get_non_empty(&self, key: &KeyNonEmptyBlock) -> Result<B> {
+        self.get(key.0)?.ok_or(Error::MissingBlock(key.0.0))
+    }
 
-        let iter = self
-            .db
-            .iterator_opt(rocksdb::IteratorMode::Start, read_opts);
+    pub fn list_txns(&self) -> impl Iterator<Item = Result<B::Txn>> + '_ {
+        let iter = self.db.iter(
+            KeyRange::new(
+                Key::TxnByHash([0; 32]).serialize(),
+                Key::TxnByHash([255; 32]).serialize_immediate_successor(),
+            ),
+            Direction::Forward,
+        );
         iter.map(|r| {
             let (_, value) = r?;
             Ok(B::Txn::from_bytes(&value)?)
@@ -1,12 +1,17 @@
+use kv_store::WriteBatch;
+use primitives::block_height::BlockHeight;
 use wire_message::WireMessage;
 
 use super::Result;
 use crate::{
     Block, BlockStore, Error, StoreList,
-    keys::{self, BlockListOrder, StoreKey},
+    keys::{self, BlockListOrder, Key, KeyBlock, ListableKey, StoreKey},
 };
 
-pub(crate) const LATEST_VERSION: u32 = 1;
+pub(crate) const LATEST_VERSION: u32 = 2;
+
+/// Number of blocks rewritten per write batch when migrating to version 2
+const MIGRATION_V2_BATCH_SIZE: usize = 1000;
 
 impl<B> BlockStore<B>
 where
@@ -19,7 +24,8 @@ where
 
             match version {
                 0 => self.migrate_to_v1()?,
-                1 => break,
+                1 => self.migrate_to_v2()?,
+                2 => break,
                 other => return Err(Error::InvalidVersion(other)),
             }
         }
@@ -27,17 +33,31 @@ where
         Ok(())
     }
 
+    /// Lists blocks stored in the uncompressed format used before version 2
+    fn list_uncompressed(
+        &self,
+        start: KeyBlock,
+    ) -> impl Iterator<Item = Result<(KeyBlock, B)>> + '_ {
+        KeyBlock::list(&*self.db, start.., &BlockListOrder::LowestToHighest)
+            .into_iterator()
+            .map(|r| {
+                let (k, v) = r?;
+
+                match k {
+                    Key::Block(block_number) => Ok((block_number, v)),
+                    _ => Err(Error::InvalidKey),
+                }
+            })
+    }
+
     #[tracing::instrument(skip(self))]
     fn migrate_to_v1(&self) -> Result<()> {
         tracing::info!("Migrating block store to version 1");
 
-        for block in self
-            .list(.., BlockListOrder::LowestToHighest)
-            .into_iterator()
-        {
+        for block in self.list_uncompressed(KeyBlock(BlockHeight(0))) {
             let (_, block) = block?;
 
-            let mut batch = rocksdb::WriteBatchWithTransaction::<false>::default();
+            let mut batch = WriteBatch::default();
 
             let txn_indexes = Self::txn_entries(&block);
             for e in txn_indexes {
@@ -57,11 +77,87 @@ where
 
         Ok(())
     }
+
+    /// Compresses every block in place and replaces the copies held by the non-empty block
+    /// index with empty values.
+    ///
+    /// Blocks are rewritten in batches, each recording the last migrated height, so an
+    /// interrupted migration resumes where it left off instead of reading compressed
+    /// blocks as uncompressed ones.
+    #[tracing::instrument(skip(self))]
+    fn migrate_to_v2(&self) -> Result<()> {
+        tracing::info!("Migrating block store to version 2");
+
+        let start = match self.migration_progress(2)? {
+            Some(height) => {
+                tracing::info!(?height, "Resuming block store migration");
+
+                if height.0 == u64::MAX {
+                    return self.finish_migration(2);
+                }
+
+                KeyBlock(height.next())
+            }
+            None => KeyBlock(BlockHeight(0)),
+        };
+
+        let mut batch = WriteBatch::default();
+        let mut batch_len = 0;
+        let mut migrated = 0;
+
+        for block in self.list_uncompressed(start) {
+            let (KeyBlock(height), block) = block?;
+
+            batch.put(
+                Key::Block(KeyBlock(height)).serialize(),
+                Self::block_value_bytes(&block)?,
+            );
+
+            if let Some(key) = keys::KeyNonEmptyBlock::from_block(&block) {
+                batch.put(key.to_key().serialize(), b"");
+            }
+
+            batch.put(Key::MigrationProgress(2).serialize(), height.to_be_bytes());
+            batch_len += 1;
+
+            if batch_len == MIGRATION_V2_BATCH_SIZE {
+                self.db.write(std::mem::take(&mut batch))?;
+                migrated += batch_len;
+                batch_len = 0;
+
+                tracing::info!(?height, migrated, "Compressed block store batch");
+            }
+        }
+
+        self.db.write(batch)?;
+
+        self.finish_migration(2)
+    }
+
+    /// The last height rewritten by an interrupted migration to `version`. Progress is kept per
+    /// target version, so a later migration never resumes from where an earlier one stopped
+    fn migration_progress(&self, version: u32) -> Result<Option<BlockHeight>> {
+        let Some(bytes) = self.db.get(&Key::MigrationProgress(version).serialize())? else {
+            return Ok(None);
+        };
+
+        let bytes = bytes.try_into().map_err(|_| Error::InvalidKey)?;
+        Ok(Some(BlockHeight(u64::from_be_bytes(bytes))))
+    }
+
+    fn finish_migration(&self, version: u32) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.delete(Key::MigrationProgress(version).serialize());
+        batch.put(Key::StoreVersion.serialize(), version.to_be_bytes());
+        self.db.write(batch)?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::DummyBlock;
+    use crate::tests::{DummyBlock, DummyTxn};
 
     use super::*;
     use tempdir::TempDir;
@@ -79,4 +175,105 @@ mod tests {
 
         assert_eq!(block_store.store_version().unwrap(), LATEST_VERSION);
     }
+
+    fn v1_block(height: u64) -> DummyBlock {
+        let txns = if height % 3 == 0 {
+            vec![DummyTxn::V1(([height as u8; 32], (vec![], vec![]), None))]
+        } else {
+            vec![]
+        };
+
+        DummyBlock::V1((BlockHeight(height), [height as u8; 32], txns))
+    }
+
+    #[test]
+    fn test_migrate_v1_to_v2() {
+        let temp_dir = temp_dir();
+        let block_store = BlockStore::<DummyBlock>::create_legacy_v1(temp_dir.path()).unwrap();
+
+        let blocks = (0..2500).map(v1_block).collect::<Vec<_>>();
+        for block in &blocks {
+            block_store.set_legacy_v1(block).unwrap();
+        }
+
+        // Simulate a migration that was interrupted after the first batch
+        block_store
+            .db
+            .put(
+                &Key::MigrationProgress(2).serialize(),
+                &BlockHeight(999).to_be_bytes(),
+            )
+            .unwrap();
+        for block in &blocks[..1000] {
+            block_store
+                .db
+                .put(
+                    &Key::Block(KeyBlock(block.block_height())).serialize(),
+                    &BlockStore::<DummyBlock>::block_value_bytes(block).unwrap(),
+                )
+                .unwrap();
+        }
+
+        block_store.migrate().unwrap();
+        assert_eq!(block_store.store_version().unwrap(), LATEST_VERSION);
+        assert_eq!(block_store.migration_progress(2).unwrap(), None);
+
+        let listed = block_store
+            .list(.., BlockListOrder::LowestToHighest)
+            .into_iterator()
+            .map(|r| r.map(|(_, block)| block))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(listed, blocks);
+
+        let non_empty = block_store
+            .list_non_empty(.., BlockListOrder::LowestToHighest)
+            .into_iterator()
+            .map(|r| r.map(|(_, block)| block))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let expected_non_empty = blocks
+            .iter()
+            .filter(|block| !block.txns().is_empty())
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(non_empty, expected_non_empty);
+
+        let non_empty_value = block_store
+            .db
+            .get(&keys::KeyNonEmptyBlock(BlockHeight(3)).to_key().serialize())
+            .unwrap()
+            .unwrap();
+        assert!(non_empty_value.is_empty());
+    }
+
+    #[test]
+    fn progress_of_other_migrations_is_ignored() {
+        let temp_dir = temp_dir();
+        let block_store = BlockStore::<DummyBlock>::create_legacy_v1(temp_dir.path()).unwrap();
+
+        let blocks = (0..10).map(v1_block).collect::<Vec<_>>();
+        for block in &blocks {
+            block_store.set_legacy_v1(block).unwrap();
+        }
+
+        // Left behind by a migration to some other version
+        block_store
+            .db
+            .put(
+                &Key::MigrationProgress(3).serialize(),
+                &BlockHeight(5).to_be_bytes(),
+            )
+            .unwrap();
+
+        block_store.migrate().unwrap();
+
+        let listed = block_store
+            .list(.., BlockListOrder::LowestToHighest)
+            .into_iterator()
+            .map(|r| r.map(|(_, block)| block))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(listed, blocks);
+    }
 }
@@ -0,0 +1,78 @@
+//! Helpers for building stores in the pre-compression (version 1) layout, used by the
+//! migration tests and the `list_paginated` benchmark
+
+use std::path::Path;
+
+use kv_store::WriteBatch;
+use primitives::{block_height::BlockHeight, pagination::CursorChoice};
+use wire_message::WireMessage;
+
+use crate::{
+    Block, BlockListOrder, BlockStore, Error, Result, Transaction,
+    keys::{self, Key, KeyBlock, ListableKey, StoreKey},
+};
+
+impl<B> BlockStore<B>
+where
+    B: Block + WireMessage,
+    B::Txn: WireMessage + Transaction,
+{
+    /// Creates an empty store at version 1, where blocks are stored uncompressed
+    pub fn create_legacy_v1(path: &Path) -> Result<Self> {
+        let self_ = Self::create(path)?;
+        self_.set_store_version(1)?;
+        Ok(self_)
+    }
+
+    /// Writes a block and its indexes in the version 1 layout
+    pub fn set_legacy_v1(&self, block: &B) -> Result<()> {
+        let mut batch = WriteBatch::default();
+
+        let height = block.block_height();
+
+        batch.put(Key::Block(KeyBlock(height)).serialize(), block.to_bytes()?);
+
+        let max_height = self.get_max_height()?;
+        if max_height.is_none_or(|max_height| height > max_height) {
+            batch.put(Key::MaxHeight.serialize(), height.to_be_bytes());
+        }
+
+        batch.put(
+            Key::BlockHashToHeight(block.block_hash()).serialize(),
+            height.to_be_bytes(),
+        );
+
+        for e in Self::txn_entries(block) {
+            let (k, v) = e?;
+            batch.put(k.serialize(), v);
+        }
+
+        if let Some(key) = keys::KeyNonEmptyBlock::from_block(block) {
+            batch.put(key.to_key().serialize(), block.to_bytes()?);
+        }
+
+        self.db.write(batch)?;
+
+        Ok(())
+    }
+
+    /// [`BlockStore::list_paginated`] for a store that has not been migrated to version 2
+    pub fn list_paginated_legacy_v1(
+        &self,
+        cursor: &Option<CursorChoice<BlockHeight>>,
+        order: BlockListOrder,
+        limit: usize,
+    ) -> Result<impl Iterator<Item = Result<(BlockHeight, B)>>> {
+        Ok(KeyBlock::list_paginated(
+            &*self.db,
+            &cursor.map(|pag| pag.map_pos(|pos| KeyBlock(*pos))),
+            order,
+            limit,
+        )?
+        .into_iter()
+        .map(|(k, v)| match k {
+            Key::Block(KeyBlock(height)) => Ok((height, v)),
+            _ => Err(Error::InvalidKey),
+        }))
+    }
+}
@@ -1,6 +1,11 @@
+use std::io::{Read, Write};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use primitives::{block_height::BlockHeight, hash::CryptoHash};
 use wire_message::wire_message;
 
+use crate::Error;
+
 #[derive(Debug, Clone, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct ElementHistoryData {
     pub block_hash: CryptoHash,
@@ -24,3 +29,58 @@ pub struct MintHashData {
 pub enum MintHashValue {
     V1(MintHashData),
 }
+
+#[derive(Debug, Clone, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct NullifierData {
+    /// Hash of the transaction that revealed the nullifier
+    pub txn_hash: [u8; 32],
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[wire_message(version = 1)]
+pub enum NullifierValue {
+    V1(NullifierData),
+}
+
+/// Gzip-compressed wire bytes of a block
+#[derive(Debug, Clone, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct CompressedBlockData {
+    /// Length of the block's wire bytes before compression
+    pub uncompressed_len: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// The value stored under [`Key::Block`](crate::keys::Key::Block) since store version 2
+#[derive(Debug, Clone, PartialEq)]
+#[wire_message(version = 1)]
+pub enum BlockValue {
+    V1(CompressedBlockData),
+}
+
+impl BlockValue {
+    pub fn compress(block_bytes: &[u8]) -> Result<Self, Error> {
+        let uncompressed_len = u32::try_from(block_bytes.len())
+            .map_err(|_| Error::BlockTooLarge(block_bytes.len()))?;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(block_bytes)?;
+        let bytes = encoder.finish()?;
+
+        Ok(Self::V1(CompressedBlockData {
+            uncompressed_len,
+            bytes,
+        }))
+    }
+
+    pub fn decompress(&self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::V1(data) => {
+                let mut block_bytes = Vec::with_capacity(data.uncompressed_len as usize);
+                GzDecoder::new(&data.bytes[..]).read_to_end(&mut block_bytes)?;
+                Ok(block_bytes)
+            }
+        }
+    }
+}
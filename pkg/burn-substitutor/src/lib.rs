@@ -1,19 +1,19 @@
 use contracts::{Address, ConfirmationType, RollupContract, USDCContract};
 use element::Element;
 use eth_util::Eth;
-use eyre::{Context, ContextCompat};
+use eyre::Context;
+use node_interface::{ListTxnOrder, ListTxnsPosition, ListTxnsQuery, ListTxnsResponse, NodeClient};
 use primitives::{
     block_height::BlockHeight,
-    pagination::{CursorChoice, CursorChoiceAfter, OpaqueCursor, OpaqueCursorChoice},
+    pagination::{CursorChoice, CursorChoiceAfter, OpaqueCursorChoice},
 };
-use reqwest::StatusCode;
 use std::time::Duration;
-use zk_primitives::{UtxoKindMessages, UtxoProof};
+use zk_primitives::UtxoKindMessages;
 
 pub struct BurnSubstitutor {
     rollup_contract: RollupContract,
     usdc_contract: USDCContract,
-    node_rpc_url: String,
+    node: NodeClient,
     eth_txn_confirm_wait_interval: Duration,
     cursor: Option<OpaqueCursorChoice<ListTxnsPosition>>,
 }
@@ -28,7 +28,7 @@ impl BurnSubstitutor {
         BurnSubstitutor {
             rollup_contract,
             usdc_contract,
-            node_rpc_url,
+            node: NodeClient::new(node_rpc_url),
             eth_txn_confirm_wait_interval,
             cursor: None,
         }
@@ -47,15 +47,16 @@ impl BurnSubstitutor {
             );
         }
 
-        let (txns, cursor) = Self::fetch_transactions(
-            &reqwest::Client::new(),
-            &self.node_rpc_url,
-            None,
-            self.cursor.as_ref(),
-            false,
-        )
-        .await
-        .context("Failed to fetch transactions")?;
+        let ListTxnsResponse { txns, cursor } = self
+            .node
+            .list_transactions(&ListTxnsQuery {
+                limit: None,
+                cursor: self.cursor.clone(),
+                order: ListTxnOrder::OldestToNewest,
+                poll: false,
+            })
+            .await
+            .context("Failed to fetch transactions")?;
 
         let mut substituted_burns = Vec::new();
         for txn in &txns {
@@ -92,7 +93,7 @@ impl BurnSubstitutor {
 
                 if burn_value > usdc_balance {
                     tracing::info!(
-                        ?txn.proof.public_inputs,
+                        ?txn.hash,
                         %burn_value,
                         %usdc_balance,
                         "Skipping burn: value exceeds substitutor balance"
@@ -138,55 +139,4 @@ impl BurnSubstitutor {
     async fn fetch_last_rollup_block(&mut self) -> Result<BlockHeight, contracts::Error> {
         self.rollup_contract.block_height().await.map(BlockHeight)
     }
-
-    async fn fetch_transactions(
-        client: &reqwest::Client,
-        network_base_url: &str,
-        limit: Option<usize>,
-        cursor: Option<&OpaqueCursorChoice<ListTxnsPosition>>,
-        poll: bool,
-    ) -> Result<(Vec<Transaction>, OpaqueCursor<ListTxnsPosition>), eyre::Error> {
-        let req = client
-            .get(format!("{network_base_url}/v0/transactions"))
-            .query(&[
-                ("limit", limit.map(|l| l.to_string())),
-                ("order", Some("OldestToNewest".to_owned())),
-                ("cursor", cursor.map(|c| c.serialize()).transpose()?),
-                ("poll", Some(poll.to_string())),
-            ]);
-
-        let resp = req.send().await?;
-
-        match resp.status() {
-            StatusCode::OK => {}
-            e => return Err(eyre::eyre!("Unexpected status code: {e}")),
-        }
-
-        let mut resp = resp.json::<serde_json::Value>().await?;
-
-        let txns = serde_json::from_value::<Vec<Transaction>>(
-            resp.get_mut("txns").context("Missing txns field")?.take(),
-        )?;
-
-        let cursor = resp
-            .get_mut("cursor")
-            .context("Missing pagination field")?
-            .take();
-
-        let cursor = serde_json::from_value(cursor).context("Failed to parse cursor")?;
-
-        Ok((txns, cursor))
-    }
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct Transaction {
-    pub proof: UtxoProof,
-    pub block_height: BlockHeight,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
-struct ListTxnsPosition {
-    block: BlockHeight,
-    txn: u64,
 }
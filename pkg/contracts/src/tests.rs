@@ -15,8 +15,9 @@ use testutil::eth::{EthNode, EthNodeOptions};
 use web3::signing::{SecretKey, keccak256};
 use web3::types::Address;
 use zk_primitives::{
-    AggAgg, AggUtxo, AggUtxoProof, InputNote, MerklePath, Note, Utxo, UtxoKind, UtxoProof,
+    AggAgg, AggUtxo, AggUtxoProof, InputNote, MerklePath, Note, Utxo, UtxoProof,
     UtxoProofBundleWithMerkleProofs, bridged_polygon_usdc_note_kind, get_address_for_private_key,
+    root_leaf,
 };
 // use zk_circuits::constants::MERKLE_TREE_DEPTH;
 // use zk_circuits::data::{BurnTo, Mint, ParameterSet};
@@ -174,50 +175,61 @@ pub fn prove_proof<P: Prove>(proof_input: &P) -> Result<P::Proof> {
     Ok(proof)
 }
 
+/// Insert `leaf` into `tree`, returning its merkle path
+fn insert_leaf(tree: &mut smirk::Tree<161, ()>, leaf: Element) -> MerklePath<161> {
+    tree.insert(leaf, ()).unwrap();
+    MerklePath::new(tree.path_for(leaf).siblings.to_vec())
+}
+
+/// Spend the input notes of `utxo` from the current root of `tree`
+fn spend_from(tree: &smirk::Tree<161, ()>, utxo: Utxo) -> Utxo {
+    let merkle_paths = utxo.input_notes.each_ref().map(|input| {
+        let commitment = input.note.commitment();
+        match commitment.is_zero() {
+            true => MerklePath::default(),
+            false => MerklePath::new(tree.path_for(commitment).siblings.to_vec()),
+        }
+    });
+
+    utxo.with_merkle_paths(tree.root_hash(), merkle_paths)
+}
+
+/// Prove `utxo` and aggregate it as the only transaction of a block, recording the old root
+/// of `tree` and inserting the utxo's leaves
 fn process_utxo_for_agg(
     tree: &mut smirk::Tree<161, ()>,
     utxo: &Utxo,
-) -> Result<(
-    UtxoProof,
-    MerklePath<161>,
-    MerklePath<161>,
-    MerklePath<161>,
-    MerklePath<161>,
-    Element,
-)> {
+) -> Result<(UtxoProof, AggUtxo)> {
     let utxo_proof = utxo.prove().unwrap();
 
-    let p1: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.input_notes[0].note.commitment())
-            .siblings
-            .to_vec(),
-    );
-    tree.remove(utxo.input_notes[0].note.commitment()).unwrap();
-
-    let p2: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.input_notes[1].note.commitment())
-            .siblings
-            .to_vec(),
-    );
-    tree.remove(utxo.input_notes[1].note.commitment()).unwrap();
+    let old_root = tree.root_hash();
+    let old_root_merkle_path = insert_leaf(tree, root_leaf(old_root));
+    let root_merkle_path = MerklePath::new(tree.path_for(root_leaf(utxo.root)).siblings.to_vec());
 
-    tree.insert(utxo.output_notes[0].commitment(), ()).unwrap();
-    let p3: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.output_notes[0].commitment())
-            .siblings
-            .to_vec(),
-    );
+    let merkle_paths = utxo_proof
+        .public_inputs
+        .leaves()
+        .map(|leaf| match leaf.is_zero() {
+            true => MerklePath::default(),
+            false => insert_leaf(tree, leaf),
+        });
 
-    tree.insert(utxo.output_notes[1].commitment(), ()).unwrap();
-    let p4: MerklePath<161> = MerklePath::new(
-        tree.path_for(utxo.output_notes[1].commitment())
-            .siblings
-            .to_vec(),
+    let agg_utxo = AggUtxo::new(
+        [
+            UtxoProofBundleWithMerkleProofs::new(
+                utxo_proof.clone(),
+                root_merkle_path,
+                &merkle_paths,
+            ),
+            UtxoProofBundleWithMerkleProofs::default(),
+            UtxoProofBundleWithMerkleProofs::default(),
+        ],
+        Some(old_root_merkle_path),
+        old_root,
+        tree.root_hash(),
     );
 
-    let new_root = tree.root_hash();
-
-    Ok((utxo_proof, p1, p2, p3, p4, new_root))
+    Ok((utxo_proof, agg_utxo))
 }
 
 #[tokio::test]
@@ -241,31 +253,20 @@ async fn verify_transfers() {
     tree.insert(utxo1_input_note2.note.commitment(), ())
         .unwrap();
 
-    let utxo1_old_root = tree.root_hash();
-
     let utxo1_output_note1 = send_note(70, address, 3);
     let utxo1_output_note2 = send_note(30, address, 4);
 
-    let utxo1 = Utxo {
-        input_notes: [utxo1_input_note1.clone(), utxo1_input_note2.clone()],
-        output_notes: [utxo1_output_note1.clone(), utxo1_output_note2.clone()],
-        kind: UtxoKind::Send,
-        burn_address: None,
-    };
+    let utxo1 = spend_from(
+        &tree,
+        Utxo::new_send(
+            [utxo1_input_note1.clone(), utxo1_input_note2.clone()],
+            [utxo1_output_note1.clone(), utxo1_output_note2.clone()],
+        ),
+    );
 
-    let (utxo1_proof, p1_1, p1_2, p1_3, p1_4, utxo1_new_root) =
-        process_utxo_for_agg(&mut tree, &utxo1).unwrap();
+    let (utxo1_proof, agg_utxo1) = process_utxo_for_agg(&mut tree, &utxo1).unwrap();
     verify_proof(&utxo1_proof);
 
-    let agg_utxo1 = AggUtxo::new(
-        [
-            UtxoProofBundleWithMerkleProofs::new(utxo1_proof, &[p1_1, p1_2, p1_3, p1_4]),
-            UtxoProofBundleWithMerkleProofs::default(),
-            UtxoProofBundleWithMerkleProofs::default(),
-        ],
-        utxo1_old_root,
-        utxo1_new_root,
-    );
     let agg_utxo1_proof = prove_and_verify(&agg_utxo1).unwrap();
 
     let utxo2_input_note1 = InputNote {
@@ -278,31 +279,21 @@ async fn verify_transfers() {
         secret_key,
     };
 
-    let utxo2_old_root = tree.root_hash();
-
     let utxo2_output_note1 = send_note(55, address, 5);
     let utxo2_output_note2 = send_note(45, address, 6);
 
-    let utxo2 = Utxo {
-        input_notes: [utxo2_input_note1.clone(), utxo2_input_note2.clone()],
-        output_notes: [utxo2_output_note1.clone(), utxo2_output_note2.clone()],
-        kind: UtxoKind::Send,
-        burn_address: None,
-    };
+    // Spends the outputs of the previous block
+    let utxo2 = spend_from(
+        &tree,
+        Utxo::new_send(
+            [utxo2_input_note1.clone(), utxo2_input_note2.clone()],
+            [utxo2_output_note1.clone(), utxo2_output_note2.clone()],
+        ),
+    );
 
-    let (utxo2_proof, p2_1, p2_2, p2_3, p2_4, utxo2_new_root) =
-        process_utxo_for_agg(&mut tree, &utxo2).unwrap();
+    let (utxo2_proof, agg_utxo2) = process_utxo_for_agg(&mut tree, &utxo2).unwrap();
     verify_proof(&utxo2_proof);
 
-    let agg_utxo2 = AggUtxo::new(
-        [
-            UtxoProofBundleWithMerkleProofs::new(utxo2_proof, &[p2_1, p2_2, p2_3, p2_4]),
-            UtxoProofBundleWithMerkleProofs::default(),
-            UtxoProofBundleWithMerkleProofs::default(),
-        ],
-        utxo2_old_root,
-        utxo2_new_root,
-    );
     let agg_utxo2_proof = prove_and_verify(&agg_utxo2).unwrap();
 
     let agg_agg = AggAgg::new([agg_utxo1_proof, agg_utxo2_proof]);
@@ -439,47 +430,15 @@ async fn burn_to() {
     // Add a padding input note
     let input_note2 = InputNote::padding_note();
 
-    let old_root = tree.root_hash();
-
     // Create burn UTXO
     let burn_address = convert_h160_to_element(&env.evm_address);
-    let utxo = Utxo::new_burn([input_note1.clone(), input_note2.clone()], burn_address);
-
-    // Generate UTXO proof
-    let utxo_proof = utxo.prove().unwrap();
-
-    // Get merkle paths for inputs before removal
-    let p1 = MerklePath::new(
-        tree.path_for(input_note1.note.commitment())
-            .siblings
-            .to_vec(),
+    let utxo = spend_from(
+        &tree,
+        Utxo::new_burn([input_note1.clone(), input_note2.clone()], burn_address),
     );
-    let p2 = MerklePath::new(
-        tree.path_for(input_note2.note.commitment())
-            .siblings
-            .to_vec(),
-    );
-
-    // Remove input notes (only the first one, as the second is padding)
-    tree.remove(input_note1.note.commitment()).unwrap();
 
-    // For outputs, both are padding notes with commitment zero
-    let output_commit = Element::ZERO;
-    let p3 = MerklePath::new(tree.path_for(output_commit).siblings.to_vec());
-    let p4 = MerklePath::new(tree.path_for(output_commit).siblings.to_vec());
-
-    let new_root = tree.root_hash();
-
-    // Create an AggUtxo with the burn UTXO and padding
-    let agg_utxo = AggUtxo::new(
-        [
-            UtxoProofBundleWithMerkleProofs::new(utxo_proof, &[p1, p2, p3, p4]),
-            UtxoProofBundleWithMerkleProofs::default(),
-            UtxoProofBundleWithMerkleProofs::default(),
-        ],
-        old_root,
-        new_root,
-    );
+    // Generate the UTXO proof and the AggUtxo with the burn UTXO and padding
+    let (_utxo_proof, agg_utxo) = process_utxo_for_agg(&mut tree, &utxo).unwrap();
 
     // Prove the AggUtxo
     let agg_utxo_proof = agg_utxo.prove().unwrap();
@@ -550,52 +509,20 @@ async fn substitute_burn() {
     // Add a padding input note
     let input_note2 = InputNote::padding_note();
 
-    let old_root = tree.root_hash();
-
     // Create burn UTXO
     let burn_address = Address::random();
-    let utxo = Utxo::new_burn(
-        [input_note1.clone(), input_note2.clone()],
-        convert_h160_to_element(&burn_address),
-    );
-
-    let hash = utxo.input_notes[0].note.commitment();
-
-    // Generate UTXO proof
-    let utxo_proof = utxo.prove().unwrap();
-
-    // Get merkle paths for inputs before removal
-    let p1 = MerklePath::new(
-        tree.path_for(input_note1.note.commitment())
-            .siblings
-            .to_vec(),
+    let utxo = spend_from(
+        &tree,
+        Utxo::new_burn(
+            [input_note1.clone(), input_note2.clone()],
+            convert_h160_to_element(&burn_address),
+        ),
     );
-    let p2 = MerklePath::new(
-        tree.path_for(input_note2.note.commitment())
-            .siblings
-            .to_vec(),
-    );
-
-    // Remove input notes (only the first one, as the second is padding)
-    tree.remove(input_note1.note.commitment()).unwrap();
 
-    // For outputs, both are padding notes with commitment zero
-    let output_commit = Element::ZERO;
-    let p3 = MerklePath::new(tree.path_for(output_commit).siblings.to_vec());
-    let p4 = MerklePath::new(tree.path_for(output_commit).siblings.to_vec());
+    let hash = utxo.burn_hash();
 
-    let new_root = tree.root_hash();
-
-    // Create an AggUtxo with the burn UTXO and padding
-    let agg_utxo = AggUtxo::new(
-        [
-            UtxoProofBundleWithMerkleProofs::new(utxo_proof, &[p1, p2, p3, p4]),
-            UtxoProofBundleWithMerkleProofs::default(),
-            UtxoProofBundleWithMerkleProofs::default(),
-        ],
-        old_root,
-        new_root,
-    );
+    // Generate the UTXO proof and the AggUtxo with the burn UTXO and padding
+    let (_utxo_proof, agg_utxo) = process_utxo_for_agg(&mut tree, &utxo).unwrap();
 
     // Prove the AggUtxo
     let agg_utxo_proof = agg_utxo.prove().unwrap();
@@ -0,0 +1,226 @@
+//! Ordered key-value storage backends
+//!
+//! Storage layers are written against [`KvStore`] rather than a concrete database, so they can
+//! run on [`RocksDb`] in production and on [`Memory`] in tests and simulations.
+
+mod batch;
+mod memory;
+mod rocks;
+
+use std::{path::Path, sync::Arc};
+
+pub use batch::{WriteBatch, WriteOp};
+pub use memory::Memory;
+pub use rocks::RocksDb;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("rocksdb error: {0}")]
+    RocksDB(#[from] rocksdb::Error),
+
+    #[error("operation '{0}' is not supported by this backend")]
+    Unsupported(&'static str),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An iterator over key-value pairs, in the order requested from [`KvStore::iter`]
+pub type KvIter<'a> = Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + Send + 'a>;
+
+/// The order in which [`KvStore::iter`] returns keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Ascending lexicographic order
+    Forward,
+    /// Descending lexicographic order
+    Reverse,
+}
+
+/// A range of keys, with an inclusive lower bound and an exclusive upper bound
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyRange {
+    pub lower: Option<Vec<u8>>,
+    pub upper: Option<Vec<u8>>,
+}
+
+impl KeyRange {
+    /// Every key in the store
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Keys in `lower..upper`
+    pub fn new(lower: Vec<u8>, upper: Vec<u8>) -> Self {
+        Self {
+            lower: Some(lower),
+            upper: Some(upper),
+        }
+    }
+
+    /// Every key starting with `prefix`
+    pub fn prefix(prefix: &[u8]) -> Self {
+        Self {
+            lower: Some(prefix.to_vec()),
+            upper: prefix_successor(prefix),
+        }
+    }
+}
+
+/// The smallest key greater than every key starting with `prefix`, or `None` if there is no
+/// such key (the prefix is empty or all `0xff`)
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut successor = prefix.to_vec();
+
+    while let Some(last) = successor.pop() {
+        if last < u8::MAX {
+            successor.push(last + 1);
+            return Some(successor);
+        }
+    }
+
+    None
+}
+
+/// An ordered key-value store
+pub trait KvStore: Send + Sync {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Apply all operations in `batch` atomically
+    fn write(&self, batch: WriteBatch) -> Result<()>;
+
+    /// Iterate over the keys in `range`. The iterator reads from a consistent snapshot of the
+    /// store taken when this is called
+    fn iter(&self, range: KeyRange, direction: Direction) -> KvIter<'_>;
+
+    /// Create a point-in-time copy of the store at `path`, which must not exist yet
+    fn checkpoint(&self, path: &Path) -> Result<()>;
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.put(key, value);
+        self.write(batch)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.delete(key);
+        self.write(batch)
+    }
+}
+
+/// Lets a store be shared, for example by a tree and readers of its history
+impl<T: KvStore + ?Sized> KvStore for Arc<T> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        (**self).get(key)
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<()> {
+        (**self).write(batch)
+    }
+
+    fn iter(&self, range: KeyRange, direction: Direction) -> KvIter<'_> {
+        (**self).iter(range, direction)
+    }
+
+    fn checkpoint(&self, path: &Path) -> Result<()> {
+        (**self).checkpoint(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_store(store: &dyn KvStore) {
+        let mut batch = WriteBatch::default();
+        for key in [&b"a1"[..], b"a2", b"a3", b"b1", b"\xff"] {
+            batch.put(key, key);
+        }
+        store.write(batch).unwrap();
+
+        assert_eq!(store.get(b"a2").unwrap(), Some(b"a2".to_vec()));
+        assert_eq!(store.get(b"c").unwrap(), None);
+
+        let keys = |range: KeyRange, direction: Direction| {
+            store
+                .iter(range, direction)
+                .map(|r| r.unwrap().0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            keys(KeyRange::prefix(b"a"), Direction::Forward),
+            vec![b"a1".to_vec(), b"a2".to_vec(), b"a3".to_vec()]
+        );
+        assert_eq!(
+            keys(KeyRange::prefix(b"a"), Direction::Reverse),
+            vec![b"a3".to_vec(), b"a2".to_vec(), b"a1".to_vec()]
+        );
+        assert_eq!(
+            keys(
+                KeyRange::new(b"a2".to_vec(), b"b1".to_vec()),
+                Direction::Forward
+            ),
+            vec![b"a2".to_vec(), b"a3".to_vec()]
+        );
+        assert_eq!(
+            keys(KeyRange::prefix(b"\xff"), Direction::Forward),
+            vec![b"\xff".to_vec()]
+        );
+        assert_eq!(keys(KeyRange::all(), Direction::Forward).len(), 5);
+
+        let mut batch = WriteBatch::default();
+        batch.delete(b"a2");
+        batch.put(b"a4", b"a4");
+        store.write(batch).unwrap();
+
+        assert_eq!(store.get(b"a2").unwrap(), None);
+        assert_eq!(
+            keys(KeyRange::prefix(b"a"), Direction::Forward),
+            vec![b"a1".to_vec(), b"a3".to_vec(), b"a4".to_vec()]
+        );
+    }
+
+    #[test]
+    fn memory() {
+        check_store(&Memory::new());
+    }
+
+    #[test]
+    fn memory_iterators_are_lazy_snapshots() {
+        let store = Memory::new();
+        for key in [&b"a"[..], b"b", b"c"] {
+            store.put(key, key).unwrap();
+        }
+
+        let mut iter = store.iter(KeyRange::all(), Direction::Forward);
+        assert_eq!(iter.next().unwrap().unwrap().0, b"a".to_vec());
+
+        // writes don't block on, or show up in, the open iterator
+        store.delete(b"b").unwrap();
+        store.put(b"d", b"d").unwrap();
+
+        let rest = iter.map(|r| r.unwrap().0).collect::<Vec<_>>();
+        assert_eq!(rest, vec![b"b".to_vec(), b"c".to_vec()]);
+
+        let keys = store
+            .iter(KeyRange::all(), Direction::Reverse)
+            .map(|r| r.unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![b"d".to_vec(), b"c".to_vec(), b"a".to_vec()]);
+    }
+
+    #[test]
+    fn rocksdb() {
+        let dir = tempdir::TempDir::new("kv-store").unwrap();
+        check_store(&RocksDb::open_default(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn prefix_successor_wraps() {
+        assert_eq!(prefix_successor(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_successor(b"a\xff"), Some(b"b".to_vec()));
+        assert_eq!(prefix_successor(b"\xff\xff"), None);
+        assert_eq!(prefix_successor(b""), None);
+    }
+}
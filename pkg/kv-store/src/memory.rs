@@ -0,0 +1,99 @@
+use std::{collections::BTreeMap, ops::Bound, path::Path, sync::Arc};
+
+use parking_lot::RwLock;
+
+use crate::{Direction, Error, KeyRange, KvIter, KvStore, Result, WriteBatch, WriteOp};
+
+type Map = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// A [`KvStore`] held entirely in memory, for tests and simulations
+///
+/// Iterators share the map with the store, which copies it on the next write while an iterator
+/// is still alive, so they can read lazily from a snapshot without blocking writes
+#[derive(Debug, Default)]
+pub struct Memory {
+    map: RwLock<Arc<Map>>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.read().is_empty()
+    }
+}
+
+impl KvStore for Memory {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.map.read().get(key).cloned())
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<()> {
+        let mut map = self.map.write();
+        let map = Arc::make_mut(&mut map);
+
+        for op in batch.into_ops() {
+            match op {
+                WriteOp::Put { key, value } => {
+                    map.insert(key, value);
+                }
+                WriteOp::Delete { key } => {
+                    map.remove(&key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn iter(&self, range: KeyRange, direction: Direction) -> KvIter<'_> {
+        Box::new(MemoryIter {
+            map: Arc::clone(&self.map.read()),
+            lower: range.lower.map_or(Bound::Unbounded, Bound::Included),
+            upper: range.upper.map_or(Bound::Unbounded, Bound::Excluded),
+            direction,
+        })
+    }
+
+    fn checkpoint(&self, _path: &Path) -> Result<()> {
+        Err(Error::Unsupported("checkpoint"))
+    }
+}
+
+/// Walks a snapshot of the map one entry at a time, narrowing the range past each entry returned
+struct MemoryIter {
+    map: Arc<Map>,
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+    direction: Direction,
+}
+
+impl Iterator for MemoryIter {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut range = self.map.range::<[u8], _>((
+            self.lower.as_ref().map(Vec::as_slice),
+            self.upper.as_ref().map(Vec::as_slice),
+        ));
+
+        let (key, value) = match self.direction {
+            Direction::Forward => range.next()?,
+            Direction::Reverse => range.next_back()?,
+        };
+        let (key, value) = (key.clone(), value.clone());
+
+        match self.direction {
+            Direction::Forward => self.lower = Bound::Excluded(key.clone()),
+            Direction::Reverse => self.upper = Bound::Excluded(key.clone()),
+        }
+
+        Some(Ok((key, value)))
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use rocksdb::DB;
+
+use crate::{Direction, KeyRange, KvIter, KvStore, Result, WriteBatch, WriteOp};
+
+/// A [`KvStore`] backed by a RocksDB database
+pub struct RocksDb {
+    db: DB,
+}
+
+impl RocksDb {
+    pub fn open(path: &Path, create_if_missing: bool) -> Result<Self> {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(create_if_missing);
+
+        Ok(Self {
+            db: DB::open(&opts, path)?,
+        })
+    }
+
+    /// Open the database at `path`, creating it if it doesn't exist
+    pub fn open_default(path: &Path) -> Result<Self> {
+        Ok(Self {
+            db: DB::open_default(path)?,
+        })
+    }
+
+    /// Get a reference to the underlying rocksdb instance
+    pub fn inner(&self) -> &DB {
+        &self.db
+    }
+}
+
+impl KvStore for RocksDb {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.db.get(key)?)
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<()> {
+        let mut rocks_batch = rocksdb::WriteBatch::default();
+
+        for op in batch.into_ops() {
+            match op {
+                WriteOp::Put { key, value } => rocks_batch.put(key, value),
+                WriteOp::Delete { key } => rocks_batch.delete(key),
+            }
+        }
+
+        self.db.write(rocks_batch)?;
+
+        Ok(())
+    }
+
+    fn iter(&self, range: KeyRange, direction: Direction) -> KvIter<'_> {
+        let mut read_opts = rocksdb::ReadOptions::default();
+
+        if let Some(lower) = range.lower {
+            read_opts.set_iterate_lower_bound(lower);
+        }
+        if let Some(upper) = range.upper {
+            read_opts.set_iterate_upper_bound(upper);
+        }
+
+        let mode = match direction {
+            Direction::Forward => rocksdb::IteratorMode::Start,
+            Direction::Reverse => rocksdb::IteratorMode::End,
+        };
+
+        Box::new(self.db.iterator_opt(mode, read_opts).map(|r| {
+            let (key, value) = r?;
+            Ok((key.into_vec(), value.into_vec()))
+        }))
+    }
+
+    fn checkpoint(&self, path: &Path) -> Result<()> {
+        rocksdb::checkpoint::Checkpoint::new(&self.db)?.create_checkpoint(path)?;
+        Ok(())
+    }
+}
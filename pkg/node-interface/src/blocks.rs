@@ -0,0 +1,134 @@
+use std::fmt;
+
+use element::Element;
+use primitives::{
+    block_height::BlockHeight,
+    hash::CryptoHash,
+    pagination::{OpaqueCursor, OpaqueCursorChoice},
+    sig::Signature,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::TxnWithInfo;
+
+/// A block, identified by its height or its hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockId {
+    /// The block at this height
+    Height(BlockHeight),
+    /// The block with this hash
+    Hash(CryptoHash),
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Height(height) => write!(f, "{}", height.0),
+            Self::Hash(hash) => write!(f, "{hash}"),
+        }
+    }
+}
+
+impl From<BlockHeight> for BlockId {
+    fn from(height: BlockHeight) -> Self {
+        Self::Height(height)
+    }
+}
+
+impl From<CryptoHash> for BlockId {
+    fn from(hash: CryptoHash) -> Self {
+        Self::Hash(hash)
+    }
+}
+
+/// A block with its transactions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Block {
+    /// Content of the block
+    pub content: BlockContent,
+    /// Signature of the block producer
+    pub signature: Signature,
+}
+
+/// Content of a [`Block`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockContent {
+    /// Header of the block
+    pub header: BlockHeader,
+    /// State changes of the block
+    pub state: BlockState,
+}
+
+/// Header of a [`Block`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    /// Height of the block
+    pub height: BlockHeight,
+    /// Hash of the previous block
+    pub last_block_hash: CryptoHash,
+    /// Epoch the block was produced in
+    pub epoch_id: u64,
+    /// Hash of the last finalized block
+    pub last_final_block_hash: CryptoHash,
+    /// Validator approvals of the previous block
+    pub approvals: Vec<Signature>,
+}
+
+/// State changes of a [`Block`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockState {
+    /// Root hash of the notes tree after the block
+    pub root_hash: Element,
+    /// Transactions included in the block
+    pub txns: Vec<TxnWithInfo>,
+}
+
+/// Response from the get block endpoint
+pub type BlockResponse = BlockWithInfo;
+
+/// A [`Block`] with its hash and time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockWithInfo {
+    /// The block
+    pub block: Block,
+    /// Hash of the block
+    pub hash: CryptoHash,
+    /// Unix timestamp of the block in seconds, estimated for old blocks
+    pub time: u64,
+}
+
+/// Order of the blocks returned by the list blocks endpoint
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ListBlocksOrder {
+    /// Oldest block first
+    LowestToHighest,
+    /// Newest block first
+    #[default]
+    HighestToLowest,
+}
+
+/// Query for the list blocks endpoint
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ListBlocksQuery {
+    /// Maximum number of blocks to return, at most 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Where to start listing from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<OpaqueCursorChoice<BlockHeight>>,
+    /// Order of the returned blocks
+    #[serde(default)]
+    pub order: ListBlocksOrder,
+    /// Skip blocks without transactions
+    #[serde(default)]
+    pub skip_empty: bool,
+}
+
+/// Response from the list blocks endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListBlocksResponse {
+    /// The blocks of this page
+    pub blocks: Vec<BlockWithInfo>,
+    /// Cursor for fetching the neighbouring pages
+    pub cursor: OpaqueCursor<BlockHeight>,
+}
@@ -0,0 +1,555 @@
+use std::{future::Future, time::Duration};
+
+use element::Element;
+use futures::{Stream, TryStreamExt, stream};
+use primitives::pagination::{CursorChoice, CursorChoiceAfter, OpaqueCursorChoice};
+use rpc::error::ErrorOutput;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    BlockId, BlockResponse, BlockWithInfo, ElementsResponse, ElementsResponseSingle, Error,
+    GetAllSmirkElementsResponse, GetTxnResponse, HealthResponse, HeightResponse, InboxNote,
+    ListBlocksQuery, ListBlocksResponse, ListElementsQuery, ListInboxNotesQuery,
+    ListInboxNotesResponse, ListNullifiersQuery, ListNullifiersResponse, ListTxnOrder,
+    ListTxnsPosition, ListTxnsQuery, ListTxnsResponse, MerklePathFormat, MerklePathResponse,
+    NonMembershipResponse, PostInboxNoteRequest, Result, RetryPolicy, StatsResponse,
+    TransactionRequest, TransactionResponse, TxnWithInfo,
+};
+
+/// Timeout for a single request, unless changed with [`NodeClient::with_timeout`]
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The node holds a long-poll open for up to 50 seconds before responding with an empty page
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(75);
+
+/// A client for the `/v0` RPC of a node
+///
+/// Errors returned by the node are decoded into [`RpcError`]s where possible, and temporary
+/// failures are retried according to the client's [`RetryPolicy`]
+///
+/// ```rust,no_run
+/// # async fn example() -> node_interface::Result<()> {
+/// use node_interface::NodeClient;
+///
+/// let client = NodeClient::new("http://localhost:8091");
+/// let height = client.height().await?;
+///
+/// println!("the node is at height {}", height.height);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`RpcError`]: crate::RpcError
+#[derive(Debug, Clone)]
+pub struct NodeClient {
+    client: reqwest::Client,
+    base_url: String,
+    retry_policy: RetryPolicy,
+    timeout: Duration,
+}
+
+impl NodeClient {
+    /// Create a client for the node at `base_url`, e.g. `http://localhost:8091`
+    #[must_use]
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_owned();
+
+        Self {
+            client: reqwest::Client::new(),
+            base_url,
+            retry_policy: RetryPolicy::default(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Send requests with `client`, e.g. to share a connection pool
+    #[must_use]
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Retry failed requests according to `retry_policy`
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Give up on a request after `timeout`
+    ///
+    /// Long-polls always wait long enough for the node to respond
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The base URL of the node
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// `GET /v0/health`, which fails if the node is out of sync with the network
+    pub async fn health(&self) -> Result<HealthResponse> {
+        self.send(true, || self.get("/health")).await
+    }
+
+    /// `GET /v0/height`
+    pub async fn height(&self) -> Result<HeightResponse> {
+        self.send(true, || self.get("/height")).await
+    }
+
+    /// `GET /v0/merkle`, the merkle paths of `commitments` in the notes tree as of `height`, or
+    /// the latest tree
+    pub async fn merkle_paths(
+        &self,
+        commitments: &[Element],
+        height: Option<u64>,
+        format: MerklePathFormat,
+    ) -> Result<MerklePathResponse> {
+        let commitments = join_elements(commitments);
+
+        self.send(true, || {
+            let request = self
+                .get("/merkle")
+                .query(&[("commitments", &commitments)])
+                .query(&[("format", format)]);
+
+            match height {
+                Some(height) => request.query(&[("height", height)]),
+                None => request,
+            }
+        })
+        .await
+    }
+
+    /// `GET /v0/merkle/non-membership`, proofs that `commitments` are not in the notes tree
+    pub async fn non_membership_proofs(
+        &self,
+        commitments: &[Element],
+    ) -> Result<NonMembershipResponse> {
+        let commitments = join_elements(commitments);
+
+        self.send(true, || {
+            self.get("/merkle/non-membership")
+                .query(&[("commitments", &commitments)])
+        })
+        .await
+    }
+
+    /// `GET /v0/elements/{element}`
+    pub async fn element(&self, element: Element) -> Result<ElementsResponseSingle> {
+        let path = format!("/elements/{}", element.to_hex());
+
+        self.send(true, || self.get(&path)).await
+    }
+
+    /// `GET /v0/elements`, the elements of `elements` that are in the notes tree as of `height`,
+    /// or the latest tree
+    ///
+    /// Elements that aren't in the tree are left out of the response, unless `include_spent` is
+    /// set and they were in the tree at some point
+    pub async fn list_elements(
+        &self,
+        elements: &[Element],
+        include_spent: bool,
+        height: Option<u64>,
+    ) -> Result<ElementsResponse> {
+        let query = ListElementsQuery {
+            elements: join_elements(elements),
+            include_spent,
+            height,
+        };
+
+        self.send(true, || self.get("/elements").query(&query))
+            .await
+    }
+
+    /// `GET /v0/nullifiers`, the nullifiers of `nullifiers` that have been revealed, the notes
+    /// they belong to are spent
+    pub async fn list_nullifiers(&self, nullifiers: &[Element]) -> Result<ListNullifiersResponse> {
+        let query = ListNullifiersQuery {
+            nullifiers: join_elements(nullifiers),
+        };
+
+        self.send(true, || self.get("/nullifiers").query(&query))
+            .await
+    }
+
+    /// `GET /v0/blocks/{block}`
+    pub async fn block(&self, block: impl Into<BlockId>) -> Result<BlockResponse> {
+        let path = format!("/blocks/{}", block.into());
+
+        self.send(true, || self.get(&path)).await
+    }
+
+    /// `GET /v0/blocks`, a single page of blocks
+    pub async fn list_blocks(&self, query: &ListBlocksQuery) -> Result<ListBlocksResponse> {
+        self.send(true, || self.get("/blocks").query(query)).await
+    }
+
+    /// All blocks from the cursor in `query` onwards, fetching pages as they are needed
+    pub fn blocks(&self, query: ListBlocksQuery) -> impl Stream<Item = Result<BlockWithInfo>> + '_ {
+        paginate(query, move |query| async move {
+            let ListBlocksResponse { blocks, cursor } = self.list_blocks(&query).await?;
+
+            let next = cursor.after.map(|after| ListBlocksQuery {
+                cursor: Some(CursorChoice::After(after.into_inner()).opaque()),
+                ..query
+            });
+
+            Ok((blocks, next))
+        })
+    }
+
+    /// `POST /v0/transaction`, which resolves once the transaction is included in a block
+    ///
+    /// This is only retried if the request never reached the node, so the transaction is never
+    /// submitted twice
+    pub async fn submit_transaction(
+        &self,
+        request: &TransactionRequest,
+    ) -> Result<TransactionResponse> {
+        self.send(false, || {
+            self.client
+                .post(self.url("/transaction"))
+                .timeout(self.timeout)
+                .json(request)
+        })
+        .await
+    }
+
+    /// `POST /v0/inbox`, post an encrypted note for the recipient of an output
+    ///
+    /// The commitment must be in the notes tree or the mempool. Posting the same note again
+    /// succeeds, but each commitment only keeps a few notes, later ones are rejected with
+    /// [`RpcError::InboxFull`][crate::RpcError::InboxFull]
+    pub async fn post_inbox_note(&self, request: &PostInboxNoteRequest) -> Result<InboxNote> {
+        self.send(true, || {
+            self.client
+                .post(self.url("/inbox"))
+                .timeout(self.timeout)
+                .json(request)
+        })
+        .await
+    }
+
+    /// `GET /v0/inbox/{commitment}`, the encrypted notes posted for `commitment`
+    pub async fn inbox_notes(&self, commitment: Element) -> Result<ListInboxNotesResponse> {
+        let path = format!("/inbox/{}", commitment.to_hex());
+
+        self.send(true, || self.get(&path)).await
+    }
+
+    /// `GET /v0/inbox`, the encrypted notes posted for `commitments`
+    ///
+    /// A commitment can have several notes, and commitments without a note are left out
+    pub async fn list_inbox_notes(
+        &self,
+        commitments: &[Element],
+    ) -> Result<ListInboxNotesResponse> {
+        let query = ListInboxNotesQuery {
+            commitments: join_elements(commitments),
+        };
+
+        self.send(true, || self.get("/inbox").query(&query)).await
+    }
+
+    /// `GET /v0/transactions/{hash}`
+    pub async fn transaction(&self, hash: Element) -> Result<GetTxnResponse> {
+        let path = format!("/transactions/{}", hash.to_hex());
+
+        self.send(true, || self.get(&path)).await
+    }
+
+    /// `GET /v0/transactions`, a single page of transactions
+    ///
+    /// If `query.poll` is set, this can take up to a minute to respond
+    pub async fn list_transactions(&self, query: &ListTxnsQuery) -> Result<ListTxnsResponse> {
+        let timeout = match query.poll {
+            true => LONG_POLL_TIMEOUT.max(self.timeout),
+            false => self.timeout,
+        };
+
+        self.send(true, || {
+            self.get("/transactions").query(query).timeout(timeout)
+        })
+        .await
+    }
+
+    /// All transactions from the cursor in `query` onwards, fetching pages as they are needed
+    ///
+    /// The stream ends at the first empty page. Use [`NodeClient::watch_transactions`] to keep
+    /// waiting for new transactions instead
+    pub fn transactions(
+        &self,
+        query: ListTxnsQuery,
+    ) -> impl Stream<Item = Result<TxnWithInfo>> + '_ {
+        paginate(query, move |query| async move {
+            let ListTxnsResponse { txns, cursor } = self.list_transactions(&query).await?;
+
+            let next = cursor.after.map(|after| ListTxnsQuery {
+                cursor: Some(CursorChoice::After(after.into_inner()).opaque()),
+                ..query
+            });
+
+            Ok((txns, next))
+        })
+    }
+
+    /// Wait for transactions after `cursor`, oldest first
+    ///
+    /// Responds as soon as a block with transactions is committed, or with an empty page if none
+    /// was committed while the node held the request open
+    pub async fn poll_transactions(
+        &self,
+        cursor: Option<OpaqueCursorChoice<ListTxnsPosition>>,
+    ) -> Result<ListTxnsResponse> {
+        let query = ListTxnsQuery {
+            limit: None,
+            cursor,
+            order: ListTxnOrder::OldestToNewest,
+            poll: true,
+        };
+
+        self.list_transactions(&query).await
+    }
+
+    /// All transactions after `cursor`, oldest first, long-polling for new transactions once the
+    /// existing ones have been returned
+    ///
+    /// The stream never ends, and only yields an error if a request fails after retries
+    pub fn watch_transactions(
+        &self,
+        cursor: Option<OpaqueCursorChoice<ListTxnsPosition>>,
+    ) -> impl Stream<Item = Result<TxnWithInfo>> + '_ {
+        stream::try_unfold(cursor, move |cursor| async move {
+            let ListTxnsResponse { txns, .. } = self.poll_transactions(cursor.clone()).await?;
+            let cursor = watch_cursor(cursor, &txns);
+
+            Ok(Some((txns, cursor)))
+        })
+        .map_ok(|txns| stream::iter(txns.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// `GET /v0/stats`
+    pub async fn stats(&self) -> Result<StatsResponse> {
+        self.send(true, || self.get("/stats")).await
+    }
+
+    /// `GET /v0/smirk/elements/all`, every element in the latest notes tree
+    pub async fn smirk_elements(&self) -> Result<GetAllSmirkElementsResponse> {
+        self.send(true, || self.get("/smirk/elements/all")).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v0{path}", self.base_url)
+    }
+
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.get(self.url(path)).timeout(self.timeout)
+    }
+
+    /// Send the request built by `request`, building it again for every retry
+    ///
+    /// Requests that aren't `idempotent` are only retried if they didn't reach the node
+    async fn send<T: DeserializeOwned>(
+        &self,
+        idempotent: bool,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            let error = match send_once(request()).await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+
+            if !self.retry_policy.should_retry(attempt, &error, idempotent) {
+                return Err(error);
+            }
+
+            let backoff = self.retry_policy.backoff(attempt);
+            tracing::warn!(?error, attempt, ?backoff, "Retrying node request");
+            tokio::time::sleep(backoff).await;
+
+            attempt += 1;
+        }
+    }
+}
+
+async fn send_once<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<T> {
+    let response = request.send().await.map_err(client_error)?;
+    let status = response.status();
+    let body = response.bytes().await.map_err(client_error)?;
+
+    if status.is_success() {
+        return serde_json::from_slice(&body).map_err(client_error);
+    }
+
+    match serde_json::from_slice::<ErrorOutput>(&body) {
+        Ok(output) => Err(output.into()),
+        Err(_) => Err(client_error(format!(
+            "unexpected response from node ({status}): {}",
+            String::from_utf8_lossy(&body)
+        ))),
+    }
+}
+
+/// The cursor to continue watching from after receiving `txns`
+///
+/// This continues after the last transaction rather than using the cursor in the response, which
+/// may be unset, so a page is never fetched twice
+fn watch_cursor(
+    cursor: Option<OpaqueCursorChoice<ListTxnsPosition>>,
+    txns: &[TxnWithInfo],
+) -> Option<OpaqueCursorChoice<ListTxnsPosition>> {
+    let Some(last) = txns.last() else {
+        return cursor;
+    };
+
+    let position = ListTxnsPosition {
+        block: last.block_height,
+        txn: last.index_in_block,
+    };
+
+    // the node skips the transactions up to and including an inclusive cursor's position
+    Some(CursorChoice::After(CursorChoiceAfter::AfterInclusive(position)).opaque())
+}
+
+fn client_error(error: impl Into<Box<dyn std::error::Error + Send + Sync + 'static>>) -> Error {
+    Error::Client(error.into())
+}
+
+fn join_elements(elements: &[Element]) -> String {
+    elements
+        .iter()
+        .map(|element| element.to_hex())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Fetch pages with `fetch_page` until it returns an empty page or no query for the next page
+fn paginate<'a, Q, T, F, Fut>(query: Q, fetch_page: F) -> impl Stream<Item = Result<T>> + 'a
+where
+    Q: 'a,
+    T: 'a,
+    F: Fn(Q) -> Fut + 'a,
+    Fut: Future<Output = Result<(Vec<T>, Option<Q>)>> + 'a,
+{
+    stream::try_unfold(Some(query), move |query| {
+        let page = query.map(&fetch_page);
+
+        async move {
+            let Some(page) = page else {
+                return Ok(None);
+            };
+
+            let (items, next) = page.await?;
+
+            match items.is_empty() {
+                true => Ok(None),
+                false => Ok(Some((items, next))),
+            }
+        }
+    })
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn paginate_stops_at_empty_page() {
+        let pages = paginate(0, |page: usize| async move {
+            let items = match page {
+                0 => vec![1, 2],
+                1 => vec![3],
+                _ => vec![],
+            };
+
+            Ok((items, Some(page + 1)))
+        });
+
+        let items = pages.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(items, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn paginate_stops_without_next_page() {
+        let pages = paginate(0, |page: usize| async move {
+            let next = (page < 2).then_some(page + 1);
+            Ok((vec![page], next))
+        });
+
+        let items = pages.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(items, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn paginate_stops_at_error() {
+        let pages = paginate(0, |page: usize| async move {
+            match page {
+                0 => Ok((vec![page], Some(page + 1))),
+                _ => Err(client_error("node went away")),
+            }
+        });
+
+        let items = pages.collect::<Vec<_>>().await;
+        assert!(matches!(items.as_slice(), [Ok(0), Err(Error::Client(_))]));
+    }
+
+    fn txn(block: u64, index_in_block: u64) -> TxnWithInfo {
+        TxnWithInfo {
+            proof: zk_primitives::TxnProof::default(),
+            index_in_block,
+            hash: Element::new(block * 100 + index_in_block),
+            block_height: primitives::block_height::BlockHeight(block),
+            time: 0,
+            amount: None,
+        }
+    }
+
+    #[test]
+    fn watch_cursor_advances_past_last_txn() {
+        let cursor = watch_cursor(None, &[txn(3, 0), txn(4, 1)]);
+
+        let Some(CursorChoice::After(CursorChoiceAfter::AfterInclusive(position))) =
+            cursor.map(|cursor| cursor.into_inner())
+        else {
+            panic!("expected an inclusive after cursor");
+        };
+        assert_eq!(
+            position,
+            ListTxnsPosition {
+                block: primitives::block_height::BlockHeight(4),
+                txn: 1,
+            }
+        );
+
+        // an empty page keeps waiting from the same place
+        let cursor = watch_cursor(
+            Some(CursorChoice::After(CursorChoiceAfter::After(position)).opaque()),
+            &[],
+        );
+        assert_eq!(
+            cursor.map(|cursor| cursor.into_inner()),
+            Some(CursorChoice::After(CursorChoiceAfter::After(position)))
+        );
+    }
+
+    #[test]
+    fn base_url_trailing_slash() {
+        let client = NodeClient::new("http://localhost:8091/");
+
+        assert_eq!(client.url("/height"), "http://localhost:8091/v0/height");
+    }
+}
@@ -9,13 +9,16 @@ pub struct ListElementsQuery {
     /// When true, include elements that have been spent (seen historically)
     #[serde(default)]
     pub include_spent: bool,
+    /// Look up elements in the notes tree as of this block height, instead of the latest tree
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
 }
 
 /// Response from the elements endpoint
 pub type ElementsResponse = Vec<ElementsResponseSingle>;
 
 /// Response item from the elements endpoint
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ElementsResponseSingle {
     /// The element being returned
     pub element: Element,
@@ -29,6 +29,15 @@ pub struct ElementData {
     pub element: Element,
 }
 
+/// Error data for a block height related to the error
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct HeightData {
+    /// Block height related to error
+    pub height: u64,
+}
+
 /// Error data detailed the element related to the error
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[cfg_attr(feature = "ts-rs", derive(TS))]
@@ -58,11 +67,41 @@ pub enum Error {
     #[error("rpc error")]
     Rpc(#[from] RpcError),
 
+    /// An error returned by the node that isn't an [`RpcError`], e.g. because the node is out of
+    /// sync
+    #[error("node error: {}", .0.error.message)]
+    Node(Box<ErrorOutput>),
+
     /// A client error
     #[error("client error")]
     Client(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
+impl From<ErrorOutput> for Error {
+    fn from(output: ErrorOutput) -> Self {
+        match RpcError::try_from(output.clone()) {
+            Ok(error) => Self::Rpc(error),
+            Err(TryFromHTTPError::UnknownReason(_)) => Self::Node(Box::new(output)),
+            Err(error) => {
+                error!(?error, ?output, "Failed to decode rpc error");
+                Self::Node(Box::new(output))
+            }
+        }
+    }
+}
+
+impl Error {
+    /// The error code returned by the node, if the node returned an error
+    #[must_use]
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Rpc(error) => Some(HTTPError::from(error.clone()).code),
+            Self::Node(output) => Some(output.error.code),
+            Self::Client(_) => None,
+        }
+    }
+}
+
 /// Public errors from Payy Network node
 #[derive(Debug, Clone, thiserror::Error, HTTPErrorConversion, Serialize, Deserialize)]
 pub enum RpcError {
@@ -71,10 +110,11 @@ pub enum RpcError {
     #[error("invalid proof")]
     InvalidProof,
 
-    /// Output note included in the transaction is already present in the tree,
-    /// the transaction could be accepted but then the user would lose their funds
+    /// The root the transaction spends from is neither the current root nor a root recorded in
+    /// the notes tree within the last `MAX_ROOT_AGE` blocks, so the transaction must be proven
+    /// again against a newer root
     #[already_exists("txn-root-not-recent-enough")]
-    #[error("txn root is not recent enough (must be last 120)")]
+    #[error("txn root is not a recent root of the notes tree")]
     TxnRootNotRecentEnough(ElementData),
 
     /// Conflicting element in another transaction in the same block
@@ -88,10 +128,10 @@ pub enum RpcError {
     #[error("output commitments already exists")]
     TxnOutputCommitmentsExist(ElementsVecData),
 
-    /// Input commitments are not in the tree (note may never have existed or may be spent)
-    #[not_found("input-commitments-not-found")]
-    #[error("input commitments are not in the tree")]
-    TxnInputCommitmentsNotInTree(ElementsVecData),
+    /// Nullifiers have already been revealed, the input notes they belong to are spent
+    #[already_exists("nullifiers-spent")]
+    #[error("nullifiers have already been spent")]
+    TxnNullifiersSpent(ElementsVecData),
 
     /// Output note commitment
     #[already_exists("output-commitments-existed-recetly")]
@@ -120,16 +160,33 @@ pub enum RpcError {
     #[error("mint in contract is different to provided txn proof")]
     MintInContractIsDifferent(Box<MintInContractIsDifferent>),
 
-    /// Transaction contains duplicate input commitments
-    #[bad_request("duplicate-input-commitments")]
-    #[error("transaction contains duplicate input commitments")]
-    TxnDuplicateInputCommitments(ElementsVecData),
+    /// The note kind of a mint isn't on the node's allowlist, so the minted notes would hold a
+    /// token the network doesn't support
+    #[bad_request("note-kind-not-allowed")]
+    #[error("note kind is not allowed")]
+    NoteKindNotAllowed(ElementData),
+
+    /// Transaction contains duplicate nullifiers, it spends the same note twice
+    #[bad_request("duplicate-nullifiers")]
+    #[error("transaction contains duplicate nullifiers")]
+    TxnDuplicateNullifiers(ElementsVecData),
 
     /// Transaction contains duplicate output commitments
     #[bad_request("duplicate-output-commitments")]
     #[error("transaction contains duplicate output commitments")]
     TxnDuplicateOutputCommitments(ElementsVecData),
 
+    /// A swap leg was submitted on its own. Both legs of a swap are submitted together, so one
+    /// can't be included without the other
+    #[bad_request("unpaired-swap-leg")]
+    #[error("swap legs must be submitted as a pair")]
+    UnpairedSwapLeg,
+
+    /// The legs of a swap don't match, one doesn't give exactly what the other wants
+    #[bad_request("swap-legs-mismatch")]
+    #[error("swap legs must give what the other leg wants")]
+    SwapLegsMismatch(ElementData),
+
     /// Transaction uses a commitment that is already pending in the mempool
     #[already_exists("commitment-already-pending")]
     #[error("commitment already pending in another transaction")]
@@ -146,6 +203,22 @@ pub enum RpcError {
     #[error("failed to find element in tree")]
     ElementNotFound(ElementData),
 
+    /// Element is in the tree, so its absence cannot be proven
+    #[already_exists("element-in-tree")]
+    #[error("element is in the tree")]
+    ElementInTree(ElementData),
+
+    /// The notes tree at this height is outside the node's history retention window
+    #[bad_request("height-not-in-history")]
+    #[error("the notes tree at this height is not available")]
+    HeightNotInHistory(HeightData),
+
+    /// The merkle paths of the commitments can't be combined into one multi-path, because they
+    /// weren't all made against the same notes tree
+    #[failed_precondition("merkle-paths-not-combinable")]
+    #[error("merkle paths can't be combined")]
+    MerklePathsNotCombinable,
+
     /// Transaction was not found
     #[not_found("txn-not-found")]
     #[error("failed to find transaction")]
@@ -161,6 +234,16 @@ pub enum RpcError {
     #[error("invalid element")]
     FailedToParseElement(ElementData),
 
+    /// Posted inbox note is not an encrypted note
+    #[bad_request("invalid-inbox-note")]
+    #[error("invalid inbox note")]
+    InvalidInboxNote,
+
+    /// The inbox already holds the maximum number of notes for this commitment
+    #[failed_precondition("inbox-full")]
+    #[error("inbox is full for this commitment")]
+    InboxFull(ElementData),
+
     /// Mint hash already exists
     #[already_exists("mint-hash-already-exists")]
     #[error("mint hash already exists")]
@@ -179,4 +262,24 @@ mod tests {
         let orig_error: RpcError = http_output.try_into().unwrap();
         println!("orig_error: {orig_error}");
     }
+
+    #[test]
+    fn decode_error_output() {
+        let error = RpcError::ElementNotFound(ElementData {
+            element: Element::ONE,
+        });
+        let output: ErrorOutput = HTTPError::from(error).into();
+
+        assert!(matches!(
+            Error::from(output),
+            Error::Rpc(RpcError::ElementNotFound(ElementData { element })) if element == Element::ONE
+        ));
+
+        let output: ErrorOutput =
+            HTTPError::new(ErrorCode::Unavailable, "out-of-sync", None, None::<()>).into();
+        let error = Error::from(output);
+
+        assert!(matches!(&error, Error::Node(_)));
+        assert_eq!(error.code(), Some(ErrorCode::Unavailable));
+    }
 }
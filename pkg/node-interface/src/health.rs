@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Response from the health endpoint
+///
+/// The node responds with an error instead if it is out of sync with the network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthResponse {
+    /// Height of the chain
+    pub height: u64,
+}
@@ -0,0 +1,35 @@
+use element::Element;
+use serde::{Deserialize, Serialize};
+use zk_primitives::EncryptedNote;
+
+/// Request to post an encrypted note to the inbox, for the recipient of an output note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostInboxNoteRequest {
+    /// Commitment of the output note, which recipients look the note up by
+    pub commitment: Element,
+    /// The note, encrypted for the recipient's viewing key
+    pub note: EncryptedNote,
+}
+
+/// An encrypted note in the inbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxNote {
+    /// Commitment of the output note
+    pub commitment: Element,
+    /// The note, encrypted for the recipient's viewing key
+    pub note: EncryptedNote,
+}
+
+/// Query for listing inbox notes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListInboxNotesQuery {
+    /// String comma separated list of commitments to lookup
+    pub commitments: String,
+}
+
+/// Response from the inbox notes endpoints
+///
+/// Anyone can post a note for a commitment, so a commitment can have several notes. Recipients
+/// keep the ones that decrypt to the note of the commitment. Commitments without a note are
+/// omitted
+pub type ListInboxNotesResponse = Vec<InboxNote>;
@@ -8,12 +8,30 @@
 
 //! Interface for requests to Payy Network
 
+mod blocks;
+mod client;
 mod elements;
 mod error;
+mod health;
 mod height;
+mod inbox;
+mod merkle;
+mod nullifiers;
+mod retry;
+mod smirk_elements;
+mod stats;
 mod transaction;
 
+pub use blocks::*;
+pub use client::*;
 pub use elements::*;
 pub use error::*;
+pub use health::*;
 pub use height::*;
+pub use inbox::*;
+pub use merkle::*;
+pub use nullifiers::*;
+pub use retry::*;
+pub use smirk_elements::*;
+pub use stats::*;
 pub use transaction::*;
@@ -0,0 +1,44 @@
+use element::Element;
+use serde::{Deserialize, Serialize};
+use smirk::{CompactPath, MultiPath, NonMembershipProof};
+
+/// The shape of the merkle paths returned by the merkle endpoint
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MerklePathFormat {
+    /// Every sibling of every path, in [`MerklePathResponse::paths`]
+    #[default]
+    Full,
+    /// Each path without empty subtree hashes, in [`MerklePathResponse::compact_paths`]
+    Compact,
+    /// One proof for all the commitments with shared siblings included once, in
+    /// [`MerklePathResponse::multi_path`]
+    Multi,
+}
+
+/// Response from the merkle endpoint
+///
+/// Only the field matching the requested [`MerklePathFormat`] is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerklePathResponse {
+    /// Root hash of the tree the paths were taken from
+    pub root_hash: Element,
+    /// The siblings of each path, deepest first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<Vec<Element>>>,
+    /// Each path with empty subtree hashes omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_paths: Option<Vec<CompactPath>>,
+    /// A single proof for all the requested commitments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multi_path: Option<MultiPath>,
+}
+
+/// Response from the merkle non-membership endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NonMembershipResponse {
+    /// Root hash of the tree the proofs were taken from
+    pub root_hash: Element,
+    /// A proof of absence for each requested commitment
+    pub proofs: Vec<NonMembershipProof>,
+}
@@ -0,0 +1,23 @@
+use element::Element;
+use serde::{Deserialize, Serialize};
+
+/// Query for listing spent nullifiers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListNullifiersQuery {
+    /// String comma separated list of nullifiers to lookup
+    pub nullifiers: String,
+}
+
+/// Response from the nullifiers endpoint, nullifiers that haven't been revealed are omitted
+pub type ListNullifiersResponse = Vec<SpentNullifier>;
+
+/// A nullifier revealed by a transaction, the note it belongs to is spent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpentNullifier {
+    /// The nullifier
+    pub nullifier: Element,
+    /// Block height of the transaction that revealed the nullifier
+    pub height: u64,
+    /// Hash of the transaction that revealed the nullifier
+    pub txn_hash: Element,
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use rpc::code::ErrorCode;
+
+use crate::Error;
+
+/// How a [`NodeClient`] retries failed requests
+///
+/// Requests are retried with exponential backoff when the node is unreachable, times out or
+/// returns an error that is likely to be temporary. [`RpcError`]s are never retried, since the
+/// node would reject the same request again
+///
+/// Requests that change state (i.e. submitting a transaction) are only retried if they never
+/// reached the node, so they can't be applied twice
+///
+/// [`NodeClient`]: crate::NodeClient
+/// [`RpcError`]: crate::RpcError
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of times a request is retried before the error is returned
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every retry after that
+    pub initial_backoff: Duration,
+    /// Upper limit on the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// The delay before retry number `attempt`, starting from 0
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+
+    pub(crate) fn should_retry(&self, attempt: u32, error: &Error, idempotent: bool) -> bool {
+        attempt < self.max_retries && is_retryable(error, idempotent)
+    }
+}
+
+fn is_retryable(error: &Error, idempotent: bool) -> bool {
+    match error {
+        Error::Rpc(_) => false,
+        Error::Node(output) => {
+            idempotent
+                && matches!(
+                    output.error.code,
+                    ErrorCode::Unavailable
+                        | ErrorCode::Internal
+                        | ErrorCode::DeadlineExceeded
+                        | ErrorCode::ResourceExhausted
+                )
+        }
+        Error::Client(error) => match error.downcast_ref::<reqwest::Error>() {
+            Some(error) if idempotent => error.is_connect() || error.is_timeout(),
+            Some(error) => error.is_connect(),
+            None => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rpc::error::{ErrorDetail, ErrorOutput};
+
+    use super::*;
+    use crate::{ElementData, RpcError};
+
+    fn node_error(code: ErrorCode) -> Error {
+        Error::Node(Box::new(ErrorOutput {
+            error: ErrorDetail {
+                code,
+                reason: "out-of-sync".to_owned(),
+                message: "node is out of sync".to_owned(),
+                data: None,
+            },
+        }))
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(4), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_temporary_errors_are_retried() {
+        let policy = RetryPolicy::default();
+        let rpc_error = Error::Rpc(RpcError::ElementNotFound(ElementData {
+            element: element::Element::ONE,
+        }));
+
+        assert!(policy.should_retry(0, &node_error(ErrorCode::Unavailable), true));
+        assert!(!policy.should_retry(0, &node_error(ErrorCode::Unavailable), false));
+        assert!(!policy.should_retry(0, &node_error(ErrorCode::BadRequest), true));
+        assert!(!policy.should_retry(0, &rpc_error, true));
+        assert!(!policy.should_retry(3, &node_error(ErrorCode::Unavailable), true));
+        assert!(!RetryPolicy::none().should_retry(0, &node_error(ErrorCode::Internal), true));
+    }
+}
@@ -0,0 +1,14 @@
+use element::Element;
+use serde::{Deserialize, Serialize};
+
+/// Response from the smirk elements endpoint
+pub type GetAllSmirkElementsResponse = Vec<SmirkElementInfo>;
+
+/// An element in the notes tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmirkElementInfo {
+    /// The element
+    pub element: Element,
+    /// Block height that the element was inserted at
+    pub inserted_at_height: u64,
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Response from the stats endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsResponse {
+    /// Number of transactions on each of the last 7 days, oldest first
+    pub last_7_days_txns: Vec<TxnDayStats>,
+}
+
+/// Number of transactions on a single day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxnDayStats {
+    /// The day, in UTC
+    pub date: chrono::NaiveDate,
+    /// Number of transactions included in blocks on that day
+    pub count: u64,
+}
@@ -1,17 +1,21 @@
 use element::Element;
-use primitives::block_height::BlockHeight;
+use primitives::{
+    block_height::BlockHeight,
+    pagination::{OpaqueCursor, OpaqueCursorChoice},
+};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "ts-rs")]
 use ts_rs::TS;
-use zk_primitives::UtxoProof;
+use zk_primitives::TxnProof;
 
 /// Request for submit transaction
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[cfg_attr(feature = "ts-rs", derive(TS))]
 #[cfg_attr(feature = "ts-rs", ts(export))]
 pub struct TransactionRequest {
-    /// Utxo proof to be verified and applied
-    pub proof: UtxoProof,
+    /// Proof of the transaction, from any of the transaction circuits, to be verified and
+    /// applied
+    pub proof: TxnProof,
 }
 
 /// Response for submit transaction
@@ -26,3 +30,75 @@ pub struct TransactionResponse {
     /// Transaction hash of submitted transaction
     pub txn_hash: Element,
 }
+
+/// A transaction with the block it was included in
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxnWithInfo {
+    /// Proof of the transaction
+    pub proof: TxnProof,
+    /// Index of the transaction in its block
+    pub index_in_block: u64,
+    /// Transaction hash
+    pub hash: Element,
+    /// Height of the block the transaction was included in
+    pub block_height: BlockHeight,
+    /// Unix timestamp of the block in seconds, estimated for old blocks
+    pub time: u64,
+    /// Value minted or burned by the transaction, e.g. `1.5 USDC`. Only set for mints and burns
+    /// of note kinds the node knows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<String>,
+}
+
+/// Position of a transaction in the chain, used as the cursor of the list transactions endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListTxnsPosition {
+    /// Height of the block the transaction was included in
+    pub block: BlockHeight,
+    /// Index of the transaction in its block
+    pub txn: u64,
+}
+
+/// Order of the transactions returned by the list transactions endpoint
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ListTxnOrder {
+    /// Newest transaction first
+    #[default]
+    NewestToOldest,
+    /// Oldest transaction first
+    OldestToNewest,
+}
+
+/// Query for the list transactions endpoint
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ListTxnsQuery {
+    /// Maximum number of transactions to return, at most 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Where to start listing from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<OpaqueCursorChoice<ListTxnsPosition>>,
+    /// Order of the returned transactions
+    #[serde(default)]
+    pub order: ListTxnOrder,
+    /// When there are no newer transactions than the cursor, wait for a block with transactions
+    /// before responding
+    #[serde(default)]
+    pub poll: bool,
+}
+
+/// Response from the list transactions endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListTxnsResponse {
+    /// The transactions of this page
+    pub txns: Vec<TxnWithInfo>,
+    /// Cursor for fetching the neighbouring pages
+    pub cursor: OpaqueCursor<ListTxnsPosition>,
+}
+
+/// Response from the get transaction endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTxnResponse {
+    /// The transaction
+    pub txn: TxnWithInfo,
+}
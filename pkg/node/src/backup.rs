@@ -0,0 +1,461 @@
+//! Online backups of the node's databases.
+//!
+//! A backup is a directory containing RocksDB checkpoints of the block store and the notes
+//! tree (and, on provers, the prover's state and notes tree), plus a [`BackupManifest`]
+//! describing the height and root hash they were taken at. Checkpoints hard-link the
+//! underlying SST files, so taking a backup does not require stopping the node.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use block_store::BlockStore;
+use element::Element;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    BlockFormat, NotesTree, PersistentMerkleTree,
+    prover::db::{self, ProverDb},
+    types::BlockHeight,
+};
+
+/// Name of the manifest file in every backup directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+const BLOCK_STORE_DIR: &str = "db";
+const SMIRK_DIR: &str = "smirk";
+const PROVER_DB_DIR: &str = "prover-db";
+const PROVER_SMIRK_DIR: &str = "prover-smirk";
+
+/// Prefix of directories that are still being written
+const IN_PROGRESS_PREFIX: &str = ".in-progress-";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("block store error: {0}")]
+    BlockStore(#[from] block_store::Error),
+
+    #[error("smirk error: {0}")]
+    Smirk(#[from] smirk::storage::Error),
+
+    #[error("prover db error: {0}")]
+    ProverDb(#[from] db::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid backup manifest: {0}")]
+    InvalidManifest(#[from] serde_json::Error),
+
+    #[error("backup already exists at {0}")]
+    AlreadyExists(PathBuf),
+
+    #[error("restore target {0} is not empty")]
+    TargetNotEmpty(PathBuf),
+
+    #[error("block {0} is missing from the backup")]
+    MissingBlock(BlockHeight),
+
+    #[error("backup root hash mismatch, expected: {expected}, got: {got}")]
+    RootMismatch { expected: Element, got: Element },
+
+    #[error("could not take a consistent backup, the notes tree or prover kept changing")]
+    Inconsistent,
+
+    #[error("backup task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Describes the state captured by a backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Height of the last block in the backed up block store
+    pub height: BlockHeight,
+    /// Root hash of the backed up notes tree, equal to the root hash of the block at `height`
+    pub root_hash: Element,
+    pub created_at_unix_s: u64,
+    /// State of the prover, if the backup was taken on a prover node
+    pub prover: Option<ProverBackupManifest>,
+}
+
+/// A backup directory and the manifest describing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub manifest: BackupManifest,
+}
+
+/// The prover keeps its own notes tree, which may lag behind the node's tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProverBackupManifest {
+    pub last_seen_height: BlockHeight,
+    pub root_hash: Element,
+}
+
+impl BackupManifest {
+    /// The name of the directory this backup is stored in
+    pub fn dir_name(&self) -> String {
+        format!("{:020}-{:x}", self.height.0, self.root_hash)
+    }
+
+    fn read(backup_dir: &Path) -> Result<Self> {
+        let bytes = fs::read(backup_dir.join(MANIFEST_FILE))?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    fn write(&self, backup_dir: &Path) -> Result<()> {
+        fs::write(
+            backup_dir.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// The prover's databases, registered by the prover worker so they can be included in backups
+pub(crate) struct ProverBackupSource {
+    pub(crate) db: Arc<ProverDb>,
+    pub(crate) notes_tree: Arc<tokio::sync::Mutex<Option<PersistentMerkleTree>>>,
+}
+
+/// A backup that has been checkpointed but not yet published with [`PendingBackup::finish`]
+pub(crate) struct PendingBackup {
+    backup_root: PathBuf,
+    tmp_dir: PathBuf,
+}
+
+impl PendingBackup {
+    pub(crate) fn new(backup_root: &Path) -> Result<Self> {
+        fs::create_dir_all(backup_root)?;
+
+        let tmp_dir = backup_root.join(format!(
+            "{IN_PROGRESS_PREFIX}{}-{}",
+            chrono::Utc::now().timestamp_millis(),
+            rand::random::<u32>()
+        ));
+        fs::create_dir(&tmp_dir)?;
+
+        Ok(Self {
+            backup_root: backup_root.to_owned(),
+            tmp_dir,
+        })
+    }
+
+    pub(crate) fn checkpoint_block_store(
+        &self,
+        block_store: &BlockStore<BlockFormat>,
+    ) -> Result<()> {
+        block_store.checkpoint(&self.tmp_dir.join(BLOCK_STORE_DIR))?;
+        Ok(())
+    }
+
+    pub(crate) fn checkpoint_smirk(&self, tree: &NotesTree) -> Result<()> {
+        tree.checkpoint(&self.tmp_dir.join(SMIRK_DIR))?;
+        Ok(())
+    }
+
+    pub(crate) fn checkpoint_prover(
+        &self,
+        prover_db: &ProverDb,
+        tree: &PersistentMerkleTree,
+    ) -> Result<()> {
+        prover_db.checkpoint(&self.tmp_dir.join(PROVER_DB_DIR))?;
+        tree.checkpoint(self.tmp_dir.join(PROVER_SMIRK_DIR))?;
+        Ok(())
+    }
+
+    /// Writes the manifest and moves the backup to its final location
+    pub(crate) fn finish(self, manifest: &BackupManifest) -> Result<PathBuf> {
+        manifest.write(&self.tmp_dir)?;
+
+        let backup_dir = self.backup_root.join(manifest.dir_name());
+        if backup_dir.exists() {
+            return Err(Error::AlreadyExists(backup_dir));
+        }
+
+        fs::rename(&self.tmp_dir, &backup_dir)?;
+
+        info!(
+            ?backup_dir,
+            height = ?manifest.height,
+            root_hash = ?manifest.root_hash,
+            "Created backup"
+        );
+
+        Ok(backup_dir)
+    }
+}
+
+impl Drop for PendingBackup {
+    fn drop(&mut self) {
+        // Only still exists if the backup was abandoned
+        if self.tmp_dir.exists() {
+            let _ = fs::remove_dir_all(&self.tmp_dir);
+        }
+    }
+}
+
+/// Lists the backups in `backup_root`, ordered from lowest to highest height
+pub fn list_backups(backup_root: &Path) -> Result<Vec<BackupInfo>> {
+    if !backup_root.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backup_root)? {
+        let path = entry?.path();
+
+        let is_in_progress = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(IN_PROGRESS_PREFIX));
+        if !path.is_dir() || is_in_progress {
+            continue;
+        }
+
+        match BackupManifest::read(&path) {
+            Ok(manifest) => backups.push(BackupInfo { path, manifest }),
+            Err(err) => {
+                tracing::warn!(?path, ?err, "Skipping directory without a valid manifest");
+            }
+        }
+    }
+
+    backups.sort_by_key(|backup| (backup.manifest.height, backup.manifest.created_at_unix_s));
+
+    Ok(backups)
+}
+
+/// Deletes all but the `retention` most recent backups, returning the deleted paths
+pub fn prune_backups(backup_root: &Path, retention: usize) -> Result<Vec<PathBuf>> {
+    let backups = list_backups(backup_root)?;
+    let to_delete = backups.len().saturating_sub(retention);
+
+    let mut deleted = Vec::with_capacity(to_delete);
+    for BackupInfo { path, .. } in backups.into_iter().take(to_delete) {
+        fs::remove_dir_all(&path)?;
+        info!(?path, "Deleted old backup");
+        deleted.push(path);
+    }
+
+    Ok(deleted)
+}
+
+/// Restores the backup in `backup_dir` to the node's `db_path` and `smirk_path`.
+///
+/// The backup is copied into temporary directories next to the targets, and only moved into
+/// place once the restored notes tree's root hash has been checked against the manifest and the
+/// block at the manifest's height, so a failed restore leaves the targets untouched. The
+/// `latest` (and `prover`) directories in the targets must not exist, or must be empty.
+pub fn restore_backup(
+    backup_dir: &Path,
+    db_path: &Path,
+    smirk_path: &Path,
+) -> Result<BackupManifest> {
+    let manifest = BackupManifest::read(backup_dir)?;
+
+    let mut copies = vec![
+        (BLOCK_STORE_DIR, db_path.join("latest")),
+        (SMIRK_DIR, smirk_path.join("latest")),
+    ];
+    if manifest.prover.is_some() {
+        copies.push((PROVER_DB_DIR, db_path.join("prover")));
+        copies.push((PROVER_SMIRK_DIR, smirk_path.join("prover")));
+    }
+
+    for (_, target) in &copies {
+        if target.exists() && fs::read_dir(target)?.next().is_some() {
+            return Err(Error::TargetNotEmpty(target.clone()));
+        }
+    }
+
+    let mut staged = StagedRestore::default();
+    for (source, target) in &copies {
+        let tmp = staged.stage(target)?;
+        copy_dir(&backup_dir.join(source), &tmp)?;
+    }
+
+    verify_restored(&manifest, |source| {
+        let (_, target) = copies.iter().find(|(name, _)| *name == source).unwrap();
+        staged.path(target)
+    })?;
+
+    for (_, target) in &copies {
+        if target.exists() {
+            fs::remove_dir(target)?;
+        }
+        fs::rename(staged.path(target), target)?;
+    }
+
+    info!(?backup_dir, height = ?manifest.height, "Restored backup");
+
+    Ok(manifest)
+}
+
+/// Temporary directories a backup is restored into, deleted on drop if they weren't moved to
+/// their targets
+#[derive(Default)]
+struct StagedRestore {
+    dirs: Vec<PathBuf>,
+}
+
+impl StagedRestore {
+    /// Create the temporary directory for `target`
+    fn stage(&mut self, target: &Path) -> Result<PathBuf> {
+        let tmp = self.path(target);
+        if tmp.exists() {
+            // Left behind by a restore that was interrupted
+            fs::remove_dir_all(&tmp)?;
+        }
+        fs::create_dir_all(&tmp)?;
+        self.dirs.push(tmp.clone());
+        Ok(tmp)
+    }
+
+    fn path(&self, target: &Path) -> PathBuf {
+        let name = target.file_name().unwrap_or_default().to_string_lossy();
+        target.with_file_name(format!("{IN_PROGRESS_PREFIX}{name}"))
+    }
+}
+
+impl Drop for StagedRestore {
+    fn drop(&mut self) {
+        for dir in &self.dirs {
+            if dir.exists() {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+    }
+}
+
+/// Check the restored databases, `path` maps a backup subdirectory to where it was restored
+fn verify_restored(manifest: &BackupManifest, path: impl Fn(&str) -> PathBuf) -> Result<()> {
+    let tree = PersistentMerkleTree::load(path(SMIRK_DIR))?;
+    check_root(manifest.root_hash, tree.tree().root_hash())?;
+
+    // a backup of an older node can hold a store in an older format
+    let block_store = BlockStore::<BlockFormat>::create_or_load(&path(BLOCK_STORE_DIR))?;
+    block_store.migrate()?;
+    let block = block_store
+        .get(manifest.height)?
+        .ok_or(Error::MissingBlock(manifest.height))?
+        .into_block();
+    check_root(manifest.root_hash, block.content.state.root_hash)?;
+
+    if let Some(prover) = &manifest.prover {
+        let tree = PersistentMerkleTree::load(path(PROVER_SMIRK_DIR))?;
+        check_root(prover.root_hash, tree.tree().root_hash())?;
+
+        let prover_db = ProverDb::create_or_load(&path(PROVER_DB_DIR))?;
+        let last_seen_root = prover_db.get_last_seen_block()?.map_or(
+            smirk::empty_tree_hash(crate::constants::MERKLE_TREE_DEPTH),
+            |b| b.root_hash,
+        );
+        check_root(prover.root_hash, last_seen_root)?;
+    }
+
+    Ok(())
+}
+
+fn check_root(expected: Element, got: Element) -> Result<()> {
+    if expected != got {
+        return Err(Error::RootMismatch { expected, got });
+    }
+
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(height: u64) -> BackupManifest {
+        BackupManifest {
+            height: BlockHeight(height),
+            root_hash: Element::new(height),
+            created_at_unix_s: height,
+            prover: None,
+        }
+    }
+
+    #[test]
+    fn list_and_prune_backups() {
+        let dir = tempdir::TempDir::new("backups").unwrap();
+
+        for height in [3, 1, 2] {
+            let manifest = manifest(height);
+            let backup_dir = dir.path().join(manifest.dir_name());
+            fs::create_dir(&backup_dir).unwrap();
+            manifest.write(&backup_dir).unwrap();
+        }
+        fs::create_dir(dir.path().join(format!("{IN_PROGRESS_PREFIX}123"))).unwrap();
+
+        let heights = |backups: Vec<BackupInfo>| {
+            backups
+                .into_iter()
+                .map(|backup| backup.manifest.height.0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(heights(list_backups(dir.path()).unwrap()), vec![1, 2, 3]);
+
+        let deleted = prune_backups(dir.path(), 2).unwrap();
+        assert_eq!(deleted, vec![dir.path().join(manifest(1).dir_name())]);
+        assert_eq!(heights(list_backups(dir.path()).unwrap()), vec![2, 3]);
+    }
+
+    #[test]
+    fn restore_refuses_non_empty_target() {
+        let dir = tempdir::TempDir::new("backups").unwrap();
+        let backup_dir = dir.path().join("backup");
+        fs::create_dir(&backup_dir).unwrap();
+        manifest(1).write(&backup_dir).unwrap();
+
+        let db_path = dir.path().join("db");
+        fs::create_dir_all(db_path.join("latest")).unwrap();
+        fs::write(db_path.join("latest").join("CURRENT"), b"").unwrap();
+
+        let err = restore_backup(&backup_dir, &db_path, &dir.path().join("smirk")).unwrap_err();
+        assert!(matches!(err, Error::TargetNotEmpty(path) if path == db_path.join("latest")));
+    }
+
+    #[test]
+    fn failed_restore_leaves_targets_untouched() {
+        let dir = tempdir::TempDir::new("backups").unwrap();
+        let backup_dir = dir.path().join("backup");
+        fs::create_dir_all(backup_dir.join(BLOCK_STORE_DIR)).unwrap();
+        fs::write(backup_dir.join(BLOCK_STORE_DIR).join("CURRENT"), b"").unwrap();
+        // The notes tree is missing from the backup
+        manifest(1).write(&backup_dir).unwrap();
+
+        let db_path = dir.path().join("db");
+        let smirk_path = dir.path().join("smirk");
+        assert!(restore_backup(&backup_dir, &db_path, &smirk_path).is_err());
+
+        assert!(!db_path.join("latest").exists());
+        assert!(!smirk_path.join("latest").exists());
+        assert_eq!(fs::read_dir(&db_path).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use eyre::{Result, bail};
+use node::{
+    backup::{self, BackupInfo, BackupManifest},
+    config::{Config, cli::CliArgs},
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Create, list and restore node backups")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Ask a running node to take a backup through its admin API
+    Create {
+        /// Base URL of the node's RPC server
+        #[arg(long, env = "POLY_NODE_URL", default_value = "http://localhost:8091")]
+        node_url: String,
+
+        /// The node's admin token
+        #[arg(long, env = "POLY_ADMIN_TOKEN")]
+        admin_token: String,
+    },
+
+    /// List the backups in the configured backup path
+    List {
+        #[clap(short, long, default_value = "polybase.toml")]
+        config_path: PathBuf,
+    },
+
+    /// Restore a backup into the configured db and smirk paths. The node must not be running
+    Restore {
+        /// Path to the backup directory
+        backup: PathBuf,
+
+        #[clap(short, long, default_value = "polybase.toml")]
+        config_path: PathBuf,
+    },
+}
+
+fn load_config(config_path: PathBuf) -> Result<Config> {
+    let args = CliArgs::try_parse_from(["node"])?;
+    Config::from_env(CliArgs {
+        config_path,
+        ..args
+    })
+}
+
+fn print_manifest(path: &std::path::Path, manifest: &BackupManifest) {
+    println!("{}", path.display());
+    println!("  height:    {}", manifest.height.0);
+    println!("  root hash: 0x{:x}", manifest.root_hash);
+    println!("  created:   {}", manifest.created_at_unix_s);
+    if let Some(prover) = &manifest.prover {
+        println!("  prover height:    {}", prover.last_seen_height.0);
+        println!("  prover root hash: 0x{:x}", prover.root_hash);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install().unwrap();
+
+    match Args::parse().command {
+        Command::Create {
+            node_url,
+            admin_token,
+        } => {
+            let response = reqwest::Client::new()
+                .post(format!(
+                    "{}/v0/admin/backups",
+                    node_url.trim_end_matches('/')
+                ))
+                .bearer_auth(admin_token)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                bail!(
+                    "failed to create backup ({}): {}",
+                    response.status(),
+                    response.text().await?
+                );
+            }
+
+            let BackupInfo { path, manifest } = response.json().await?;
+            print_manifest(&path, &manifest);
+        }
+        Command::List { config_path } => {
+            let config = load_config(config_path)?;
+            for BackupInfo { path, manifest } in backup::list_backups(&config.backup_path)? {
+                print_manifest(&path, &manifest);
+            }
+        }
+        Command::Restore {
+            backup,
+            config_path,
+        } => {
+            let config = load_config(config_path)?;
+            let manifest = backup::restore_backup(&backup, &config.db_path, &config.smirk_path)?;
+            println!("Restored and verified backup:");
+            print_manifest(&backup, &manifest);
+        }
+    }
+
+    Ok(())
+}
@@ -87,6 +87,13 @@ async fn main() -> Result<()> {
         config.env_name.clone(),
     )?;
 
+    barretenberg::configure_backends(config.proving_backend.clone())
+        .map_err(|err| eyre::eyre!("failed to configure proving backends: {err}"))?;
+    if config.mode == Mode::Prover {
+        barretenberg::backend_health_check()
+            .map_err(|err| eyre::eyre!("proving backend health check failed: {err}"))?;
+    }
+
     // Listen address of the server
     let rpc_laddr = config.rpc_laddr.clone();
 
@@ -0,0 +1,74 @@
+use std::{path::PathBuf, time::Duration};
+
+use barretenberg::BackendKind;
+use clap::Parser;
+use eyre::Result;
+use node::prover::remote::{WorkerClient, run_remote_worker};
+
+#[derive(Debug, Parser)]
+#[command(about = "Prove aggregation jobs leased from a prover node")]
+struct Args {
+    /// Base URL of the prover node's coordinator, see `prover-coordinator-laddr`
+    #[arg(
+        long,
+        env = "POLY_PROVER_COORDINATOR_URL",
+        default_value = "http://localhost:8092"
+    )]
+    coordinator_url: String,
+
+    /// Bearer token expected by the coordinator
+    #[arg(long, env = "POLY_PROVER_WORKER_TOKEN")]
+    token: Option<String>,
+
+    /// Milliseconds to wait before asking for a job again when there are none
+    #[arg(long, env = "POLY_PROVER_POLL_INTERVAL_MS", default_value_t = 1000)]
+    poll_interval_ms: u64,
+
+    /// Proving backends to prove with, in order, see `[proving-backend]` in the node config
+    #[arg(
+        long,
+        env = "POLY_PROVING_BACKENDS",
+        value_delimiter = ',',
+        default_value = "native,cli"
+    )]
+    backends: Vec<BackendKind>,
+
+    /// Path to the `bb` binary used by the `cli` backend
+    #[arg(long, env = "POLY_BB_PATH", default_value = "bb")]
+    bb_path: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install().unwrap();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    let args = Args::parse();
+
+    barretenberg::configure_backends(barretenberg::BackendConfig {
+        backends: args.backends,
+        bb_path: args.bb_path,
+    })
+    .map_err(|err| eyre::eyre!("failed to configure proving backends: {err}"))?;
+    barretenberg::backend_health_check()
+        .map_err(|err| eyre::eyre!("proving backend health check failed: {err}"))?;
+
+    tracing::info!(
+        coordinator_url = %args.coordinator_url,
+        "Waiting for proving jobs"
+    );
+
+    run_remote_worker(
+        WorkerClient::new(&args.coordinator_url, args.token),
+        Duration::from_millis(args.poll_interval_ms),
+    )
+    .await;
+
+    Ok(())
+}
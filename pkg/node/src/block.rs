@@ -4,16 +4,17 @@ use element::Element;
 use ethereum_types::U256;
 use node_interface::{ElementsVecData, RpcError};
 use primitives::{hash::CryptoHash, peer::PeerIdSigner};
+use prover::aggregation_slots;
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use std::{collections::HashMap, fmt::Debug};
 use tracing::error;
 
 use crate::types::BlockHeight;
-use crate::{BlockFormat, PersistentMerkleTree, utxo::validate_txn};
+use crate::{BlockFormat, NotesTreeSnapshot, utxo::validate_txn};
 use crate::{Error, Mode};
 use primitives::sig::Signature;
-use zk_primitives::UtxoProof;
+use zk_primitives::{TxnProof, UtxoProofV1, root_leaf};
 
 #[derive(
     Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
@@ -47,23 +48,82 @@ pub struct BlockHeader {
 )]
 pub struct BlockState {
     pub root_hash: Element,
-    pub txns: Vec<UtxoProof>,
+    pub txns: Vec<TxnProof>,
 }
 
 impl BlockState {
-    pub fn new(root_hash: Element, txns: Vec<UtxoProof>) -> Self {
+    pub fn new(root_hash: Element, txns: Vec<TxnProof>) -> Self {
         Self { root_hash, txns }
     }
 
-    // /// Get an iterator over all leaves in this block
-    // pub fn leaves(&self) -> impl Iterator<Item = Element> + '_ {
-    //     self.txns.iter().flat_map(|proof| proof.leaves())
-    // }
+    /// The leaves the block inserts into the notes tree, given the tree's root before the block
+    ///
+    /// A block with transactions first records the old root, so later transactions can spend
+    /// notes from it, then inserts the nullifiers and output commitments of its transactions
+    pub fn leaves(&self, old_root: Element) -> Vec<Element> {
+        if self.txns.is_empty() {
+            return vec![];
+        }
+
+        std::iter::once(root_leaf(old_root))
+            .chain(self.txns.iter().flat_map(TxnProof::leaves))
+            .filter(|e| !e.is_zero())
+            .collect()
+    }
+}
+
+/// A block from before transactions could be from any [`TxnProof`] circuit, when they were all
+/// utxo proofs, from before notes were spent by nullifier. Only used to read old blocks from the store, see
+/// [`BlockFormat`](crate::BlockFormat)
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct UtxoBlock {
+    pub content: UtxoBlockContent,
+    pub signature: Signature,
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct UtxoBlockContent {
+    pub header: BlockHeader,
+    pub state: UtxoBlockState,
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct UtxoBlockState {
+    pub root_hash: Element,
+    pub txns: Vec<UtxoProofV1>,
+}
+
+impl From<UtxoBlock> for Block {
+    fn from(block: UtxoBlock) -> Self {
+        let UtxoBlock { content, signature } = block;
+
+        Block {
+            content: BlockContent {
+                header: content.header,
+                state: BlockState::new(
+                    content.state.root_hash,
+                    content.state.txns.into_iter().map(TxnProof::from).collect(),
+                ),
+            },
+            signature,
+        }
+    }
+}
+
+impl UtxoBlock {
+    pub fn block_height(&self) -> BlockHeight {
+        self.content.header.height
+    }
 
-    // /// Get an iterator over all the non-null leaves in this block
-    // pub fn leaves_non_null(&self) -> impl Iterator<Item = Element> + '_ {
-    //     self.leaves().filter(|&e| e != Element::NULL_HASH)
-    // }
+    /// The hash of the block, the same as once it's converted to a [`Block`], as the hash
+    /// doesn't cover the transactions
+    pub fn hash(&self) -> CryptoHash {
+        BlockContent {
+            header: self.content.header.clone(),
+            state: BlockState::new(self.content.state.root_hash, vec![]),
+        }
+        .hash()
+    }
 }
 
 impl Block {
@@ -137,46 +197,28 @@ impl BlockContent {
         &self,
         mode: Mode,
         block_store: &BlockStore<BlockFormat>,
-        notes_tree: &PersistentMerkleTree,
+        notes_tree: &NotesTreeSnapshot,
     ) -> Result<(), Error> {
-        let mut insert_txn_leaves = HashMap::new();
-        let mut remove_txn_leaves = HashMap::new();
+        // Otherwise the block can't be proven
+        if aggregation_slots(&self.state.txns).is_none() {
+            return Err(Error::BlockSlotsExceeded);
+        }
+
+        let mut txn_leaves = HashMap::new();
 
         for utxo_proof in self.state.txns.iter() {
             // Between transactions in the same block,
             // we check that the leaves are unique,
             // otherwise there could be a double spend.
-            for leaf in utxo_proof.public_inputs.output_commitments {
-                if leaf == Element::ZERO {
-                    continue;
-                }
-
-                let existing_leaf_txn_hash = insert_txn_leaves
-                    .get(&leaf)
-                    .or(remove_txn_leaves.get(&leaf));
-                if existing_leaf_txn_hash.is_some() {
-                    return Err(RpcError::ConflictingElementsInBlock(ElementsVecData {
-                        elements: vec![leaf],
-                    }))?;
-                } else {
-                    insert_txn_leaves.insert(leaf, utxo_proof.hash());
-                }
-            }
-
-            for leaf in utxo_proof.public_inputs.input_commitments {
+            for leaf in utxo_proof.leaves() {
                 if leaf == Element::ZERO {
                     continue;
                 }
 
-                let existing_leaf_txn_hash = insert_txn_leaves
-                    .get(&leaf)
-                    .or(remove_txn_leaves.get(&leaf));
-                if existing_leaf_txn_hash.is_some() {
+                if txn_leaves.insert(leaf, utxo_proof.hash()).is_some() {
                     return Err(RpcError::ConflictingElementsInBlock(ElementsVecData {
                         elements: vec![leaf],
                     }))?;
-                } else {
-                    remove_txn_leaves.insert(leaf, utxo_proof.hash());
                 }
             }
 
@@ -200,13 +242,11 @@ impl BlockContent {
             }
         }
 
-        let new_root_hash = match insert_txn_leaves.is_empty() && remove_txn_leaves.is_empty() {
+        let leaves = self.state.leaves(notes_tree.root_hash());
+        let new_root_hash = match leaves.is_empty() {
             // If there is no leaves to insert, the root hash wouldn't change
-            true => notes_tree.tree().root_hash(),
-            false => notes_tree.tree().root_hash_with(
-                &insert_txn_leaves.into_keys().collect::<Vec<_>>(),
-                &remove_txn_leaves.into_keys().collect::<Vec<_>>(),
-            ),
+            true => notes_tree.root_hash(),
+            false => notes_tree.root_hash_with(&leaves, &[])?,
         };
         if new_root_hash != self.state.root_hash {
             return Err(Error::InvalidBlockRoot {
@@ -1,3 +1,4 @@
+use super::{NotesTreeHashCache, NotesTreeStorage, StorageBackend};
 use crate::Mode;
 use clap::Parser;
 use libp2p::multiaddr::Multiaddr;
@@ -46,6 +47,10 @@ pub struct CliArgs {
     #[arg(value_enum, long, env = "POLY_LOG_FORMAT", default_value = "PRETTY")]
     pub log_format: LogFormat,
 
+    /// Storage backend
+    #[arg(value_enum, long, env = "POLY_STORAGE")]
+    pub storage: Option<StorageBackend>,
+
     /// Data path
     #[arg(long, env = "POLY_DB_PATH")]
     pub db_path: Option<PathBuf>,
@@ -54,6 +59,22 @@ pub struct CliArgs {
     #[arg(long, env = "POLY_SMIRK_PATH")]
     pub smirk_path: Option<PathBuf>,
 
+    /// Notes tree storage
+    #[arg(value_enum, long, env = "POLY_NOTES_TREE_STORAGE")]
+    pub notes_tree_storage: Option<NotesTreeStorage>,
+
+    /// Notes tree hash cache
+    #[arg(value_enum, long, env = "POLY_NOTES_TREE_HASH_CACHE")]
+    pub notes_tree_hash_cache: Option<NotesTreeHashCache>,
+
+    /// Backup path
+    #[arg(long, env = "POLY_BACKUP_PATH")]
+    pub backup_path: Option<PathBuf>,
+
+    /// Bearer token for the admin API
+    #[arg(long, env = "POLY_ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+
     /// Ethereum RPC URL
     #[arg(long, env = "POLY_ETH_RPC_URL")]
     pub eth_rpc_url: Option<String>,
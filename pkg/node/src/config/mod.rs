@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
 use self::cli::CliArgs;
 use crate::Mode;
@@ -9,12 +9,69 @@ use figment::{
     providers::{Env, Format, Toml},
 };
 use primitives::peer::PeerIdSigner;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::io::Read;
 use std::{fs::File, str::FromStr};
+use web3::types::H160;
+use zk_primitives::{NoteKind, NoteKindRegistry};
 
 pub mod cli;
 
+/// Where the node keeps its block store, notes trees and prover state
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// RocksDB databases under `db-path` and `smirk-path`
+    #[default]
+    Rocksdb,
+
+    /// Everything is held in memory and lost on shutdown. Backups are not supported
+    Memory,
+}
+
+/// How the node holds its notes tree
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NotesTreeStorage {
+    /// The whole tree is rebuilt in memory on startup, and readers are served from snapshots of it
+    #[default]
+    Memory,
+
+    /// Node hashes are stored alongside the elements and read on demand, so startup time and
+    /// memory use don't grow with the tree. History isn't kept, so `merkle-history-blocks` must
+    /// be 0
+    Disk,
+}
+
+/// How the notes trees cache the hashes of their nodes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NotesTreeHashCache {
+    /// Every hash is kept for the life of the process
+    #[default]
+    Simple,
+
+    /// At most `notes-tree-hash-cache-capacity` hashes are kept, least recently used first out
+    Lru,
+}
+
+/// A token that can be minted, see [`NoteKind`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NoteKindConfig {
+    /// Chain ID of the EVM chain the token is bridged from
+    pub chain: u64,
+
+    /// Address of the token contract on `chain`
+    pub address: H160,
+
+    /// Token symbol, e.g. `USDC`
+    pub symbol: String,
+
+    /// Number of decimal places of the token
+    pub decimals: u8,
+}
+
 // TODO: should we use kebab-case? Currently _ is used to split into
 // multiple level dictionaries
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
@@ -49,12 +106,42 @@ pub struct Config {
     /// P2P config
     pub p2p: ::p2p2::Config,
 
+    /// Storage backend for the database and Smirk
+    pub storage: StorageBackend,
+
     /// Path to the database
     pub db_path: PathBuf,
 
     /// Path to Smirk
     pub smirk_path: PathBuf,
 
+    /// How the notes tree is held, either implementation can open a tree written by the other
+    pub notes_tree_storage: NotesTreeStorage,
+
+    /// Hash cache for the node's and prover's in-memory notes trees
+    pub notes_tree_hash_cache: NotesTreeHashCache,
+
+    /// Maximum number of hashes held by the `lru` hash cache
+    pub notes_tree_hash_cache_capacity: NonZeroUsize,
+
+    /// Number of past blocks that merkle paths can be requested at. 0 disables history
+    pub merkle_history_blocks: u64,
+
+    /// Directory that backups are written to
+    pub backup_path: PathBuf,
+
+    /// Number of most recent backups to keep, older ones are deleted after each new backup
+    pub backup_retention: usize,
+
+    /// Bearer token required by the `/v0/admin` routes. Admin routes are disabled if unset
+    pub admin_token: Option<String>,
+
+    /// Maximum number of encrypted notes kept in the inbox for one commitment
+    pub inbox_max_notes_per_commitment: usize,
+
+    /// Seconds an encrypted note is kept in the inbox after it is posted
+    pub inbox_retention_secs: u64,
+
     pub eth_rpc_url: String,
 
     pub rollup_contract_addr: String,
@@ -64,9 +151,34 @@ pub struct Config {
 
     pub rollup_wait_time_ms: u64,
 
+    /// Proving backends, see [`barretenberg::BackendConfig`]
+    pub proving_backend: barretenberg::BackendConfig,
+
     /// Optional postgres database for synchronization between provers
     pub prover_database_url: Option<String>,
 
+    /// Listen address for remote proving workers. Aggregation proofs are proved in-process if unset
+    pub prover_coordinator_laddr: Option<String>,
+
+    /// Bearer token required from remote proving workers
+    pub prover_worker_token: Option<String>,
+
+    /// Seconds a remote worker has to return a proof before its job is leased to another worker
+    pub prover_job_lease_secs: u64,
+
+    /// Number of times a proving job is leased before the block fails to prove
+    pub prover_job_max_attempts: u32,
+
+    /// Seconds a proving job can wait for a valid proof, across all its leases
+    pub prover_job_timeout_secs: u64,
+
+    /// Number of blocks proved at once. Later blocks are proved while earlier ones finish, and
+    /// proofs are saved in height order
+    pub prover_pipeline_depth: usize,
+
+    /// Maximum number of aggregation proofs proved in-process at once
+    pub prover_parallelism: usize,
+
     /// Blocks that should not be validated or rolled up
     pub bad_blocks: Vec<u64>,
 
@@ -74,12 +186,23 @@ pub struct Config {
     pub minimum_gas_price_gwei: Option<u64>,
 
     pub safe_eth_height_offset: u64,
+
+    /// Tokens that can be minted, see [`Config::note_kind_registry`]
+    pub note_kinds: Vec<NoteKindConfig>,
 }
 
 impl Config {
     /// The text of the default config string
     pub const DEFAULT_STR: &str = include_str!("./default_config.toml");
 
+    /// The note kinds of [`Config::note_kinds`], which are the only kinds the node mints
+    pub fn note_kind_registry(&self) -> NoteKindRegistry {
+        self.note_kinds
+            .iter()
+            .map(|kind| NoteKind::bridge_evm(kind.chain, kind.address, &kind.symbol, kind.decimals))
+            .collect()
+    }
+
     /// Load a [`Config`] from a file and environment
     ///
     /// `config_path` doesn't need to point to an actual file
@@ -121,6 +244,10 @@ impl Config {
             config.rpc_laddr = rpc_laddr;
         }
 
+        if let Some(storage) = args.storage {
+            config.storage = storage;
+        }
+
         if let Some(db_path) = args.db_path {
             config.db_path = db_path;
         }
@@ -141,6 +268,28 @@ impl Config {
                 .join(config.smirk_path.strip_prefix("~").unwrap());
         }
 
+        if let Some(notes_tree_storage) = args.notes_tree_storage {
+            config.notes_tree_storage = notes_tree_storage;
+        }
+
+        if let Some(notes_tree_hash_cache) = args.notes_tree_hash_cache {
+            config.notes_tree_hash_cache = notes_tree_hash_cache;
+        }
+
+        if let Some(backup_path) = args.backup_path {
+            config.backup_path = backup_path;
+        }
+
+        if config.backup_path.starts_with("~") {
+            config.backup_path = home_dir()
+                .unwrap()
+                .join(config.backup_path.strip_prefix("~").unwrap());
+        }
+
+        if let Some(admin_token) = args.admin_token {
+            config.admin_token = Some(admin_token);
+        }
+
         if let Some(eth_rpc_url) = args.eth_rpc_url {
             config.eth_rpc_url = eth_rpc_url;
         }
@@ -168,4 +317,35 @@ mod tests {
         let args = CliArgs::try_parse_from(["node"]).unwrap();
         Config::from_env(args).unwrap();
     }
+
+    #[test]
+    fn default_proving_backend_falls_back_to_cli() {
+        let args = CliArgs::try_parse_from(["node"]).unwrap();
+        let config = Config::from_env(args).unwrap();
+
+        assert_eq!(
+            config.proving_backend,
+            barretenberg::BackendConfig::default()
+        );
+    }
+
+    #[test]
+    fn notes_tree_hash_cache_from_args() {
+        let args = CliArgs::try_parse_from(["node", "--notes-tree-hash-cache", "lru"]).unwrap();
+        let config = Config::from_env(args).unwrap();
+
+        assert_eq!(config.notes_tree_hash_cache, NotesTreeHashCache::Lru);
+        assert_eq!(config.notes_tree_hash_cache_capacity.get(), 1_000_000);
+    }
+
+    #[test]
+    fn default_allows_polygon_usdc() {
+        let args = CliArgs::try_parse_from(["node"]).unwrap();
+        let config = Config::from_env(args).unwrap();
+
+        assert_eq!(
+            config.note_kind_registry(),
+            NoteKindRegistry::from_iter([NoteKind::polygon_usdc()])
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use std::num::NonZeroUsize;
+
 /// Expected block production time in ms.
 pub const MIN_BLOCK_PRODUCTION_DELAY: u64 = 600;
 
@@ -9,3 +11,13 @@ pub const MAX_BLOCK_WAIT_DELAY: u64 = 6_000;
 
 /// Depth of merkle tree
 pub const MERKLE_TREE_DEPTH: usize = 161;
+
+/// Number of blocks after a root is replaced that transactions can still spend from it
+pub const MAX_ROOT_AGE: u64 = 64;
+
+/// Number of past notes trees to keep in memory for historical merkle path requests
+pub const HISTORICAL_NOTES_TREES_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(8).unwrap();
+
+/// Number of past notes trees that can be reconstructed at once. Each reconstruction is linear in
+/// the size of the tree, so further requests wait for a permit
+pub const HISTORICAL_NOTES_TREES_CONCURRENCY: usize = 2;
@@ -6,7 +6,7 @@ use node_interface::RpcError;
 use primitives::{block_height::BlockHeight, hash::CryptoHash};
 use tracing::error;
 
-use crate::sync;
+use crate::{inbox_store, sync};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -59,6 +59,9 @@ pub enum Error {
     #[error("invalid block root, got: {got}, expected: {expected}")]
     InvalidBlockRoot { got: Element, expected: Element },
 
+    #[error("block transactions don't fit in its aggregation slots")]
+    BlockSlotsExceeded,
+
     #[error("transaction contains locked element {locked_element}")]
     TransactionContainsLockedElement { locked_element: Element },
 
@@ -91,6 +94,9 @@ pub enum Error {
     #[error("block store error: {0}")]
     BlockStore(#[from] block_store::Error),
 
+    #[error("inbox store error: {0}")]
+    InboxStore(#[from] inbox_store::Error),
+
     #[error("smirk error: {0}")]
     Smirk(#[from] smirk::storage::Error),
 
@@ -102,6 +108,17 @@ pub enum Error {
 
     #[error("smirk collision error: {0}")]
     Collision(#[from] smirk::CollisionError),
+
+    #[error("invalid encrypted note in inbox: {0}")]
+    EncryptedNote(#[from] zk_primitives::EncryptedNoteError),
+
+    #[error("task join error: {0}")]
+    TokioJoin(#[from] tokio::task::JoinError),
+
+    #[error(
+        "merkle-history-blocks must be 0 with notes-tree-storage = \"disk\", which keeps no history"
+    )]
+    DiskNotesTreeHistory,
 }
 
 impl From<RpcError> for Error {
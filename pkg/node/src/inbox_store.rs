@@ -0,0 +1,351 @@
+use std::path::Path;
+
+use borsh::BorshDeserialize;
+use element::Element;
+use kv_store::{Direction, KeyRange, KvStore, WriteBatch};
+use parking_lot::Mutex;
+use sha3::{Digest, Keccak256};
+use wire_message::WireMessage;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid key")]
+    InvalidKey,
+
+    #[error("kv store error: {0}")]
+    KvStore(#[from] kv_store::Error),
+
+    #[error("WireMessage error")]
+    WireMessage(#[from] wire_message::Error),
+
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Encrypted notes posted for the recipients of output notes
+///
+/// Kept apart from the block store, as notes are only held by the node they were posted to and
+/// expire. A commitment can have several notes, as anyone can post one for it, and only the
+/// recipient can tell which of them decrypts
+pub(crate) struct InboxStore {
+    db: Box<dyn KvStore>,
+    /// Held while a note is inserted, so the count of a commitment's notes can't be exceeded
+    insert_lock: Mutex<()>,
+}
+
+/// The result of [`InboxStore::insert`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InsertOutcome {
+    Inserted,
+    /// The same note was already posted for the commitment
+    AlreadyExists,
+    /// The commitment already has the maximum number of notes
+    Full,
+}
+
+enum Key {
+    Note {
+        commitment: Element,
+        note_hash: [u8; 32],
+    },
+    /// Indexes notes by when they expire, so they can be pruned without reading every note
+    Expiry {
+        expires_at: u64,
+        commitment: Element,
+        note_hash: [u8; 32],
+    },
+}
+
+impl Key {
+    fn kind(&self) -> u8 {
+        match self {
+            Self::Note { .. } => 0,
+            Self::Expiry { .. } => 1,
+        }
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = vec![self.kind()];
+
+        match self {
+            Self::Note {
+                commitment,
+                note_hash,
+            } => {
+                out.extend_from_slice(&commitment.to_be_bytes());
+                out.extend_from_slice(note_hash);
+            }
+            Self::Expiry {
+                expires_at,
+                commitment,
+                note_hash,
+            } => {
+                out.extend_from_slice(&expires_at.to_be_bytes());
+                out.extend_from_slice(&commitment.to_be_bytes());
+                out.extend_from_slice(note_hash);
+            }
+        }
+
+        out
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let Some((kind, rest)) = bytes.split_first() else {
+            return Err(Error::InvalidKey);
+        };
+
+        let element = |bytes: &[u8]| Element::from_be_bytes(bytes.try_into().unwrap());
+
+        match (kind, rest.len()) {
+            (0, 64) => Ok(Self::Note {
+                commitment: element(&rest[..32]),
+                note_hash: rest[32..].try_into().unwrap(),
+            }),
+            (1, 72) => Ok(Self::Expiry {
+                expires_at: u64::from_be_bytes(rest[..8].try_into().unwrap()),
+                commitment: element(&rest[8..40]),
+                note_hash: rest[40..].try_into().unwrap(),
+            }),
+            _ => Err(Error::InvalidKey),
+        }
+    }
+
+    /// The prefix of the [`Key::Note`]s of `commitment`
+    fn note_prefix(commitment: Element) -> Vec<u8> {
+        let mut out = vec![0];
+        out.extend_from_slice(&commitment.to_be_bytes());
+        out
+    }
+}
+
+#[derive(Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
+struct NoteV1 {
+    note: Vec<u8>,
+    expires_at: u64,
+}
+
+#[wire_message::wire_message]
+enum Value {
+    V1(NoteV1),
+}
+
+impl WireMessage for Value {
+    type Ctx = ();
+    type Err = core::convert::Infallible;
+
+    fn version(&self) -> u64 {
+        match self {
+            Self::V1(_) => 1,
+        }
+    }
+
+    fn upgrade_once(self, _ctx: &mut Self::Ctx) -> Result<Self, wire_message::Error> {
+        match self {
+            Self::V1(_) => Err(Self::max_version_error()),
+        }
+    }
+}
+
+impl InboxStore {
+    pub(crate) fn create_or_load(path: &Path) -> Result<Self> {
+        std::fs::create_dir_all(path)?;
+        let db = kv_store::RocksDb::open_default(path)?;
+        Ok(Self::new(Box::new(db)))
+    }
+
+    /// Creates an empty inbox held in memory
+    pub(crate) fn in_memory() -> Self {
+        Self::new(Box::new(kv_store::Memory::new()))
+    }
+
+    fn new(db: Box<dyn KvStore>) -> Self {
+        Self {
+            db,
+            insert_lock: Mutex::new(()),
+        }
+    }
+
+    /// Stores `note` for `commitment` until `expires_at`, unless the commitment already has
+    /// `max_notes` notes
+    pub(crate) fn insert(
+        &self,
+        commitment: Element,
+        note: &[u8],
+        expires_at: u64,
+        max_notes: usize,
+    ) -> Result<InsertOutcome> {
+        let note_hash: [u8; 32] = Keccak256::digest(note).into();
+        let key = Key::Note {
+            commitment,
+            note_hash,
+        };
+
+        let _guard = self.insert_lock.lock();
+
+        if self.db.get(&key.serialize())?.is_some() {
+            return Ok(InsertOutcome::AlreadyExists);
+        }
+
+        let existing = self
+            .db
+            .iter(
+                KeyRange::prefix(&Key::note_prefix(commitment)),
+                Direction::Forward,
+            )
+            .take(max_notes)
+            .collect::<Result<Vec<_>, _>>()?;
+        if existing.len() >= max_notes {
+            return Ok(InsertOutcome::Full);
+        }
+
+        let value = Value::V1(NoteV1 {
+            note: note.to_vec(),
+            expires_at,
+        });
+
+        let mut batch = WriteBatch::default();
+        batch.put(key.serialize(), value.to_bytes()?);
+        batch.put(
+            Key::Expiry {
+                expires_at,
+                commitment,
+                note_hash,
+            }
+            .serialize(),
+            b"",
+        );
+        self.db.write(batch)?;
+
+        Ok(InsertOutcome::Inserted)
+    }
+
+    /// The notes of `commitment` that haven't expired by `now`
+    pub(crate) fn notes(&self, commitment: Element, now: u64) -> Result<Vec<Vec<u8>>> {
+        self.db
+            .iter(
+                KeyRange::prefix(&Key::note_prefix(commitment)),
+                Direction::Forward,
+            )
+            .filter_map(|r| {
+                let (_, value) = match r {
+                    Ok(kv) => kv,
+                    Err(err) => return Some(Err(err.into())),
+                };
+
+                match Value::deserialize(&mut &*value) {
+                    Ok(Value::V1(note)) => (note.expires_at > now).then_some(Ok(note.note)),
+                    Err(err) => Some(Err(err.into())),
+                }
+            })
+            .collect()
+    }
+
+    /// Deletes the notes that expired by `now`, returning how many were deleted
+    pub(crate) fn prune(&self, now: u64) -> Result<usize> {
+        let _guard = self.insert_lock.lock();
+
+        let range = KeyRange::new(
+            vec![1],
+            Key::Expiry {
+                expires_at: now.saturating_add(1),
+                commitment: Element::ZERO,
+                note_hash: [0; 32],
+            }
+            .serialize(),
+        );
+
+        let mut batch = WriteBatch::default();
+        let mut pruned = 0;
+        for r in self.db.iter(range, Direction::Forward) {
+            let (key, _) = r?;
+            let Key::Expiry {
+                commitment,
+                note_hash,
+                ..
+            } = Key::deserialize(&key)?
+            else {
+                return Err(Error::InvalidKey);
+            };
+
+            batch.delete(&key);
+            batch.delete(
+                Key::Note {
+                    commitment,
+                    note_hash,
+                }
+                .serialize(),
+            );
+            pruned += 1;
+        }
+
+        if !batch.is_empty() {
+            self.db.write(batch)?;
+        }
+
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_are_kept_per_commitment_and_note() {
+        let inbox = InboxStore::in_memory();
+        let commitment = Element::new(1);
+
+        assert_eq!(
+            inbox.insert(commitment, b"a", 10, 2).unwrap(),
+            InsertOutcome::Inserted
+        );
+        assert_eq!(
+            inbox.insert(commitment, b"a", 10, 2).unwrap(),
+            InsertOutcome::AlreadyExists
+        );
+        assert_eq!(
+            inbox.insert(commitment, b"b", 10, 2).unwrap(),
+            InsertOutcome::Inserted
+        );
+        assert_eq!(
+            inbox.insert(commitment, b"c", 10, 2).unwrap(),
+            InsertOutcome::Full
+        );
+        assert_eq!(
+            inbox.insert(Element::new(2), b"c", 10, 2).unwrap(),
+            InsertOutcome::Inserted
+        );
+
+        let mut notes = inbox.notes(commitment, 0).unwrap();
+        notes.sort();
+        assert_eq!(notes, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(
+            inbox.notes(Element::new(2), 0).unwrap(),
+            vec![b"c".to_vec()]
+        );
+        assert!(inbox.notes(Element::new(3), 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn expired_notes_are_pruned() {
+        let inbox = InboxStore::in_memory();
+        let commitment = Element::new(1);
+
+        inbox.insert(commitment, b"a", 10, 2).unwrap();
+        inbox.insert(commitment, b"b", 20, 2).unwrap();
+
+        assert_eq!(inbox.notes(commitment, 10).unwrap(), vec![b"b".to_vec()]);
+
+        assert_eq!(inbox.prune(10).unwrap(), 1);
+        assert_eq!(inbox.prune(10).unwrap(), 0);
+        assert_eq!(inbox.notes(commitment, 0).unwrap(), vec![b"b".to_vec()]);
+
+        // The pruned note no longer counts towards the limit
+        assert_eq!(
+            inbox.insert(commitment, b"c", 30, 2).unwrap(),
+            InsertOutcome::Inserted
+        );
+    }
+}
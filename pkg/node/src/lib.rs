@@ -1,10 +1,12 @@
 #![deny(clippy::disallowed_methods)]
 
+pub mod backup;
 mod block;
 mod cache;
 pub mod config;
 mod constants;
 mod errors;
+mod inbox_store;
 mod mempool;
 mod network;
 mod network_handler;
@@ -117,6 +117,15 @@ where
         Ok(())
     }
 
+    /// Whether a transaction in the mempool has `change` in its changes
+    pub fn contains_change(&self, change: &C) -> bool {
+        self.state
+            .lock()
+            .txns
+            .values()
+            .any(|txn| txn.changes.contains(change))
+    }
+
     /// Commit a given transaction with key, removing it from the mempool
     /// and resolving any waiting futures (from add_txn_wait)
     #[allow(clippy::type_complexity)]
@@ -310,6 +319,18 @@ mod tests {
         assert_eq!(state.txns.get("key2").unwrap().txn, 24);
     }
 
+    #[test]
+    fn test_contains_change() {
+        let mempool = Mp::default();
+        mempool.add("key1".to_string(), 42, vec![1, 2]).unwrap();
+
+        assert!(mempool.contains_change(&2));
+        assert!(!mempool.contains_change(&3));
+
+        mempool.commit(1, vec![(&"key1".to_string(), Ok(()))]);
+        assert!(!mempool.contains_change(&2));
+    }
+
     #[test]
     fn test_lease_batch() {
         let mempool = Mp::default();
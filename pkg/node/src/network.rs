@@ -4,13 +4,14 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use derivative::Derivative;
 use doomslug::Approval;
 use element::Element;
-use zk_primitives::UtxoProof;
+use smirk::{SubtreeId, SubtreeSummary};
+use zk_primitives::TxnProof;
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub enum NetworkEvent {
     Approval(Approval),
     Block(Block),
-    Transaction(UtxoProof),
+    Transaction(TxnProof),
 
     /// Request a snapshot from peers.
     SnapshotRequest(SnapshotRequest),
@@ -23,12 +24,21 @@ pub enum NetworkEvent {
 
     /// A chunk of blocks for the out of sync peer to apply.
     SnapshotChunk(SnapshotChunk),
+
+    /// Request summaries of subtrees of a peer's notes tree, to reconcile with ours.
+    TreeSyncRequest(TreeSyncRequest),
+
+    /// Summaries of the subtrees requested by a [`TreeSyncRequest`].
+    TreeSyncResponse(TreeSyncResponse),
 }
 
-#[derive(Debug, Copy, Clone, BorshSerialize, BorshDeserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
 pub enum SnapshotKind {
     Slow,
     Fast,
+    /// Like [`SnapshotKind::Fast`], but the notes tree is reconciled with
+    /// [`TreeSyncRequest`]s instead of sent in full
+    Reconcile,
 }
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
@@ -42,6 +52,10 @@ pub struct SnapshotRequest {
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct SnapshotOffer {
     pub snapshot_id: SnapshotId,
+    /// The kind of snapshot the peer can send, which is [`SnapshotKind::Fast`] if
+    /// [`SnapshotKind::Reconcile`] was requested but the peer can't serve its notes tree at the
+    /// requested height
+    pub kind: SnapshotKind,
 }
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
@@ -65,15 +79,24 @@ pub struct SnapshotChunkSlow {
 pub struct SnapshotChunkFast {
     pub snapshot_id: SnapshotId,
     pub block: Option<Box<Block>>,
-    /// Elements up to `block`
+    /// Elements before `block`
     #[derivative(Debug(format_with = "fmt_vec"))]
     pub elements: Vec<Element>,
 }
 
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SnapshotChunkReconcile {
+    pub snapshot_id: SnapshotId,
+    /// The block the out of sync peer syncs to, it reconciles its notes tree with the tree
+    /// before the block
+    pub block: Option<Box<Block>>,
+}
+
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub enum SnapshotChunk {
     Slow(SnapshotChunkSlow),
     Fast(SnapshotChunkFast),
+    Reconcile(SnapshotChunkReconcile),
 }
 
 impl SnapshotChunk {
@@ -81,10 +104,29 @@ impl SnapshotChunk {
         match self {
             SnapshotChunk::Slow(sc) => sc.snapshot_id,
             SnapshotChunk::Fast(sc) => sc.snapshot_id,
+            SnapshotChunk::Reconcile(sc) => sc.snapshot_id,
         }
     }
 }
 
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct TreeSyncRequest {
+    pub snapshot_id: SnapshotId,
+    /// The height of the notes tree to compare with
+    pub height: BlockHeight,
+    pub subtrees: Vec<SubtreeId>,
+}
+
+#[derive(Derivative, Clone, BorshSerialize, BorshDeserialize)]
+#[derivative(Debug)]
+pub struct TreeSyncResponse {
+    pub snapshot_id: SnapshotId,
+    pub height: BlockHeight,
+    /// Empty if the peer doesn't have the notes tree at `height`
+    #[derivative(Debug(format_with = "fmt_vec"))]
+    pub subtrees: Vec<(SubtreeId, SubtreeSummary)>,
+}
+
 fn fmt_vec<T>(vec: &[T], fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
     write!(fmt, "Vec(len = {})", vec.len())
 }
@@ -56,8 +56,8 @@ async fn handle_event(
             .await
             .context("Snapshot request failed")?,
 
-        NetworkEvent::SnapshotOffer(SnapshotOffer { snapshot_id }) => node
-            .receive_snapshot_offer(peer, snapshot_id)
+        NetworkEvent::SnapshotOffer(SnapshotOffer { snapshot_id, kind }) => node
+            .receive_snapshot_offer(peer, snapshot_id, kind)
             .context("Snapshot offer failed")?,
 
         NetworkEvent::SnapshotChunk(sc) => node
@@ -73,6 +73,15 @@ async fn handle_event(
             .receive_snapshot_accept(peer, snapshot_id, from_height, to_height, kind)
             .await
             .context("Snapshot accept failed")?,
+
+        NetworkEvent::TreeSyncRequest(request) => node
+            .receive_tree_sync_request(peer, request)
+            .await
+            .context("Tree sync request failed")?,
+
+        NetworkEvent::TreeSyncResponse(response) => node
+            .receive_tree_sync_response(peer, response)
+            .context("Tree sync response failed")?,
     }
 
     Ok(())
@@ -1,11 +1,15 @@
+use crate::backup::ProverBackupSource;
 use crate::block::Block;
 use crate::cache::BlockCache;
-use crate::config::Config;
+use crate::config::{Config, NotesTreeStorage, StorageBackend};
 use crate::constants::{
-    MAX_BLOCK_PRODUCTION_DELAY, MAX_BLOCK_WAIT_DELAY, MERKLE_TREE_DEPTH, MIN_BLOCK_PRODUCTION_DELAY,
+    HISTORICAL_NOTES_TREES_CACHE_SIZE, HISTORICAL_NOTES_TREES_CONCURRENCY,
+    MAX_BLOCK_PRODUCTION_DELAY, MAX_BLOCK_WAIT_DELAY, MERKLE_TREE_DEPTH,
+    MIN_BLOCK_PRODUCTION_DELAY,
 };
 pub use crate::errors::Error;
 use crate::errors::Result;
+use crate::inbox_store::InboxStore;
 use crate::mempool::Mempool;
 use crate::network::NetworkEvent;
 use crate::network_handler::network_handler;
@@ -19,7 +23,8 @@ use doomslug::{Approval, ApprovalContent, ApprovalStake, ApprovalValidated, Doom
 use element::Element;
 use futures::Stream;
 use libp2p::PeerId;
-use node_interface::{ElementData, RpcError};
+use lru::LruCache;
+use node_interface::{ElementData, HeightData, RpcError, SpentNullifier};
 use p2p2::Network;
 use parking_lot::{Mutex, RwLock};
 use primitives::hash::CryptoHash;
@@ -28,32 +33,47 @@ use primitives::peer::{self, Address, PeerIdSigner};
 use primitives::tick_worker::TickWorker;
 use prover::smirk_metadata::SmirkMetadata;
 use serde::{Deserialize, Serialize};
+use smirk::{NonMembershipProof, Path};
 use std::collections::HashSet;
 use std::net::IpAddr;
 use std::ops::RangeBounds;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::{debug, error, info, instrument};
-use zk_primitives::UtxoProof;
+use zk_primitives::{NoteKindRegistry, TxnProof};
 
-pub use self::block_format::BlockFormat;
+pub use self::block_format::{BlockFormat, BlockMetadata};
+pub use self::notes_tree::{
+    DiskMerkleTree, MemoryMerkleTree, NotesTree, NotesTreeCache, NotesTreeSnapshot,
+};
 pub use self::txn_format::TxnFormat;
 pub use self::txn_format::TxnMetadata;
 
+mod backup;
 mod block;
 mod block_format;
+mod inbox;
 mod load;
+mod notes_tree;
 mod proposal;
 mod snapshot;
 mod tick_worker;
 mod transaction;
 mod txn_format;
 
-pub type PersistentMerkleTree = smirk::storage::Persistent<MERKLE_TREE_DEPTH, SmirkMetadata>;
+pub type PersistentMerkleTree =
+    smirk::storage::Persistent<MERKLE_TREE_DEPTH, SmirkMetadata, NotesTreeCache>;
+
+/// The notes tree as of a past block height, reconstructed from [`PersistentMerkleTree`] history
+pub type HistoricalMerkleTree = smirk::Tree<MERKLE_TREE_DEPTH, SmirkMetadata>;
+
+/// A snapshot of the notes tree and its history, past versions are reconstructed from it
+pub type NotesTreeHistory =
+    smirk::storage::HistorySnapshot<MERKLE_TREE_DEPTH, SmirkMetadata, NotesTreeCache>;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
 // This rename is for the config parser,
@@ -93,11 +113,14 @@ pub struct NodeShared {
     /// Config
     config: Config,
 
+    /// The note kinds of [`Config::note_kinds`], the only kinds that can be minted
+    note_kinds: NoteKindRegistry,
+
     /// Doomslug consensus (currently not used)
     doomslug: Arc<Mutex<Doomslug>>,
 
     /// Mempool for storing pending txns
-    mempool: Mempool<Element, UtxoProof, BlockHeight, Element, Arc<Block>>,
+    mempool: Mempool<Element, TxnProof, BlockHeight, Element, Arc<Block>>,
 
     // Block cache (unconfirmed blocks)
     pub(crate) block_cache: Arc<Mutex<BlockCache>>,
@@ -109,7 +132,21 @@ pub struct NodeShared {
     network: Arc<Network<NetworkEvent>>,
 
     /// Smirk tree containing notes
-    notes_tree: Arc<RwLock<PersistentMerkleTree>>,
+    notes_tree: Arc<RwLock<NotesTree>>,
+
+    /// Snapshot of the notes tree, published after every change, so readers never hold the
+    /// notes tree lock
+    notes_tree_snapshot: RwLock<Arc<NotesTreeSnapshot>>,
+
+    /// Snapshot of the notes tree's history, published with [`NodeShared::notes_tree_snapshot`].
+    /// `None` for disk trees, which don't keep history
+    notes_tree_history: RwLock<Option<Arc<NotesTreeHistory>>>,
+
+    /// Recently requested past versions of the notes tree, since rebuilding them is expensive
+    historical_notes_trees: Mutex<LruCache<BlockHeight, Arc<HistoricalMerkleTree>>>,
+
+    /// Limits how many past versions of the notes tree are rebuilt at once
+    historical_notes_tree_permits: tokio::sync::Semaphore,
 
     /// Internal state of node
     state: Mutex<NodeSharedState>,
@@ -128,6 +165,15 @@ pub struct NodeShared {
     ///
     /// If empty, whitelisting is disabled (i.e. all IPs are allowed)
     pub whitelisted_ips: HashSet<IpAddr>,
+
+    /// The prover's state, included in backups if this node is a prover
+    prover_backup_source: OnceLock<ProverBackupSource>,
+
+    /// Held while a backup is being taken
+    backup_lock: tokio::sync::Mutex<()>,
+
+    /// Encrypted notes posted for the recipients of output notes
+    inbox: InboxStore,
 }
 
 pub struct NodeSharedArc(Arc<NodeShared>);
@@ -143,7 +189,6 @@ pub struct NodeSharedState {
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct ElementSeenInfo {
-    #[expect(dead_code)]
     pub input_height: Option<BlockHeight>,
     pub output_height: BlockHeight,
     #[expect(dead_code)]
@@ -165,16 +210,33 @@ impl Node {
             local_peer.address().to_hex()
         );
 
+        if config.notes_tree_storage == NotesTreeStorage::Disk && config.merkle_history_blocks != 0
+        {
+            return Err(Error::DiskNotesTreeHistory);
+        }
+
         let LoadedData {
             block_store,
-            persistent_tree,
+            mut notes_tree,
             block: initial_block,
         } = Self::load_db_and_smirk(&config)?;
 
-        block_store.migrate()?;
+        if let NotesTree::Memory(persistent_tree) = &mut notes_tree {
+            match config.merkle_history_blocks {
+                0 => persistent_tree.clear_history()?,
+                blocks => persistent_tree.enable_history(blocks)?,
+            }
+        }
+
+        let inbox = match config.storage {
+            StorageBackend::Rocksdb => InboxStore::create_or_load(&config.db_path.join("inbox"))?,
+            StorageBackend::Memory => InboxStore::in_memory(),
+        };
 
         let block_store = Arc::new(block_store);
-        let notes_tree = Arc::new(RwLock::new(persistent_tree));
+        let notes_tree_snapshot = RwLock::new(Arc::new(notes_tree.snapshot()));
+        let notes_tree_history = RwLock::new(notes_tree.history_snapshot().map(Arc::new));
+        let notes_tree = Arc::new(RwLock::new(notes_tree));
 
         // Add the pending proposal
         let block_cache = Arc::new(Mutex::new(BlockCache::new(initial_block.clone(), 10_000)));
@@ -210,7 +272,14 @@ impl Node {
             block_cache,
             doomslug,
             notes_tree,
+            notes_tree_snapshot,
+            notes_tree_history,
+            historical_notes_trees: Mutex::new(LruCache::new(HISTORICAL_NOTES_TREES_CACHE_SIZE)),
+            historical_notes_tree_permits: tokio::sync::Semaphore::new(
+                HISTORICAL_NOTES_TREES_CONCURRENCY,
+            ),
             network: Arc::new(network),
+            note_kinds: config.note_kind_registry(),
             config: config.clone(),
             ticker: TickWorker::new(),
             state: Mutex::new(NodeSharedState {
@@ -219,6 +288,9 @@ impl Node {
             }),
             sync_worker: sync::SyncWorkerChannel(sync_worker_sender.clone()),
             whitelisted_ips: config.p2p.whitelisted_ips,
+            prover_backup_source: OnceLock::new(),
+            backup_lock: tokio::sync::Mutex::new(()),
+            inbox,
         });
 
         let sync_worker = SyncWorker::new(
@@ -271,6 +343,26 @@ impl Node {
     }
 }
 
+fn merkle_paths<C>(
+    tree: &smirk::Tree<MERKLE_TREE_DEPTH, SmirkMetadata, C>,
+    elements: &[Element],
+) -> Result<(Element, Vec<Path>)> {
+    let paths = elements
+        .iter()
+        .map(|e| {
+            // Check the element is in the tree
+            if !tree.contains_element(e) {
+                return Err(RpcError::ElementNotFound(ElementData { element: *e }))?;
+            }
+
+            // Return the path
+            Ok(tree.path_for(*e))
+        })
+        .collect::<Result<Vec<Path>>>()?;
+
+    Ok((tree.root_hash(), paths))
+}
+
 impl NodeShared {
     pub(crate) fn height(&self) -> BlockHeight {
         self.block_cache.lock().height()
@@ -285,13 +377,48 @@ impl NodeShared {
     }
 
     pub(crate) fn root_hash(&self) -> Element {
-        self.notes_tree.read().tree().root_hash()
+        self.notes_tree_snapshot().root_hash()
     }
 
-    pub(crate) fn notes_tree(&self) -> &Arc<RwLock<PersistentMerkleTree>> {
+    pub(crate) fn notes_tree(&self) -> &Arc<RwLock<NotesTree>> {
         &self.notes_tree
     }
 
+    /// The notes tree as of the last change
+    ///
+    /// Prefer this over locking [`NodeShared::notes_tree`] for reads. For in-memory trees, it
+    /// never waits for a commit, and commits never wait for it to be dropped
+    pub(crate) fn notes_tree_snapshot(&self) -> Arc<NotesTreeSnapshot> {
+        Arc::clone(&self.notes_tree_snapshot.read())
+    }
+
+    /// Modify the notes tree, then publish a snapshot of the result for readers
+    pub(crate) fn update_notes_tree<T>(&self, f: impl FnOnce(&mut NotesTree) -> T) -> T {
+        let mut notes_tree = self.notes_tree.write();
+        let result = f(&mut notes_tree);
+        *self.notes_tree_snapshot.write() = Arc::new(notes_tree.snapshot());
+        *self.notes_tree_history.write() = notes_tree.history_snapshot().map(Arc::new);
+        result
+    }
+
+    /// Like [`NodeShared::update_notes_tree`], for changes that don't follow on from the current
+    /// tree, such as applying a snapshot from another node
+    ///
+    /// Cached past versions of the tree may not match the new tree's history, so they are dropped
+    pub(crate) fn reset_notes_tree<T>(&self, f: impl FnOnce(&mut NotesTree) -> T) -> T {
+        let result = self.update_notes_tree(f);
+        self.historical_notes_trees.lock().clear();
+        result
+    }
+
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub(crate) fn note_kinds(&self) -> &NoteKindRegistry {
+        &self.note_kinds
+    }
+
     #[must_use]
     pub(crate) fn is_validator_for_height(&self, height: BlockHeight) -> bool {
         if self.config.mode != Mode::Validator {
@@ -306,22 +433,89 @@ impl NodeShared {
             .any(|p| self.local_peer.address() == p)
     }
 
-    pub(crate) fn get_merkle_paths(&self, elements: &[Element]) -> Result<Vec<Vec<Element>>> {
-        let notes_tree = self.notes_tree.read();
-        let tree = notes_tree.tree();
+    /// Whether [`NodeShared::notes_tree_at`] can return the tree at `height`
+    ///
+    /// The history may still be pruned past `height` before the tree is rebuilt
+    pub(crate) fn has_notes_tree_at(&self, height: BlockHeight) -> bool {
+        if self.historical_notes_trees.lock().contains(&height) {
+            return true;
+        }
 
-        elements
-            .iter()
-            .map(|e| {
-                // Check the element is in the tree
-                if !tree.contains_element(e) {
-                    return Err(RpcError::ElementNotFound(ElementData { element: *e }))?;
-                }
+        self.notes_tree_history
+            .read()
+            .as_ref()
+            .and_then(|history| history.history())
+            .is_some_and(|versions| versions.contains(&height.0))
+    }
 
-                // Return the path
-                Ok(tree.path_for(*e).siblings_deepest_first().to_vec())
-            })
-            .collect::<Result<Vec<Vec<Element>>>>()
+    /// The notes tree as it was after the block at `height` was applied
+    ///
+    /// Fails with [`RpcError::HeightNotInHistory`] if `height` is outside the
+    /// `merkle-history-blocks` retention window, or the tree is a [`NotesTree::Disk`], which keeps
+    /// no history. Trees that aren't cached are rebuilt on a blocking thread from
+    /// [`NotesTreeHistory`], without holding the notes tree lock
+    pub(crate) async fn notes_tree_at(
+        &self,
+        height: BlockHeight,
+    ) -> Result<Arc<HistoricalMerkleTree>> {
+        if let Some(tree) = self.historical_notes_trees.lock().get(&height) {
+            return Ok(Arc::clone(tree));
+        }
+
+        let _permit = self
+            .historical_notes_tree_permits
+            .acquire()
+            .await
+            .expect("semaphore is never closed");
+
+        // The tree may have been rebuilt while we waited
+        if let Some(tree) = self.historical_notes_trees.lock().get(&height) {
+            return Ok(Arc::clone(tree));
+        }
+
+        let Some(history) = self.notes_tree_history.read().clone() else {
+            return Err(RpcError::HeightNotInHistory(HeightData {
+                height: height.0,
+            }))?;
+        };
+        let tree = match tokio::task::spawn_blocking(move || history.tree_at(height.0)).await? {
+            Ok(tree) => Arc::new(tree),
+            Err(smirk::storage::Error::VersionUnavailable(_)) => {
+                return Err(RpcError::HeightNotInHistory(HeightData {
+                    height: height.0,
+                }))?;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        self.historical_notes_trees
+            .lock()
+            .put(height, Arc::clone(&tree));
+
+        Ok(tree)
+    }
+
+    /// Merkle paths for `elements`, returning the root hash the paths are against
+    ///
+    /// The paths are against the latest tree, or the tree as of `height` if given
+    pub(crate) async fn get_merkle_paths(
+        &self,
+        elements: &[Element],
+        height: Option<BlockHeight>,
+    ) -> Result<(Element, Vec<Path>)> {
+        match height {
+            Some(height) => merkle_paths(&*self.notes_tree_at(height).await?, elements),
+            None => self.notes_tree_snapshot().merkle_paths(elements),
+        }
+    }
+
+    /// Proves that none of `elements` are in the notes tree, returning the root hash the
+    /// proofs are against
+    pub(crate) fn get_non_membership_proofs(
+        &self,
+        elements: &[Element],
+    ) -> Result<(Element, Vec<NonMembershipProof>)> {
+        self.notes_tree_snapshot().non_membership_proofs(elements)
     }
 
     pub(crate) async fn send_all(&self, event: NetworkEvent) {
@@ -513,6 +707,20 @@ impl NodeShared {
         }
     }
 
+    /// Returns where `nullifier` was revealed, or `None` if the note it belongs to hasn't been
+    /// spent
+    pub(crate) fn get_nullifier(&self, nullifier: Element) -> Result<Option<SpentNullifier>> {
+        let Some(data) = self.block_store.get_nullifier(nullifier)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(SpentNullifier {
+            nullifier,
+            height: data.block_height.0,
+            txn_hash: Element::from_be_bytes(data.txn_hash),
+        }))
+    }
+
     pub(crate) async fn commit_stream(
         &self,
         from_height: Option<BlockHeight>,
@@ -547,9 +755,9 @@ impl NodeShared {
         }
     }
 
-    pub(crate) fn get_txn(&self, txn_hash: [u8; 32]) -> Result<Option<(UtxoProof, TxnMetadata)>> {
+    pub(crate) fn get_txn(&self, txn_hash: [u8; 32]) -> Result<Option<(TxnProof, TxnMetadata)>> {
         let txn = self.block_store.get_txn_by_hash(txn_hash)?;
-        Ok(txn.map(|TxnFormat::V1(txn, metadata)| (txn, metadata)))
+        Ok(txn.map(TxnFormat::into_parts))
     }
 
     pub(crate) fn last_commit_time(&self) -> Option<Instant> {
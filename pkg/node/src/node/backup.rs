@@ -0,0 +1,120 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tracing::{info, warn};
+
+use super::NodeShared;
+use crate::backup::{
+    self, BackupManifest, PendingBackup, ProverBackupManifest, ProverBackupSource,
+};
+
+/// How long to keep retrying a backup while a commit is in progress, or the prover catches up
+/// with the node
+const CONSISTENT_BACKUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+impl NodeShared {
+    /// Registers the prover's state so it is included in backups
+    pub(crate) fn register_prover_backup_source(&self, source: ProverBackupSource) {
+        if self.prover_backup_source.set(source).is_err() {
+            warn!("Prover backup source was already registered");
+        }
+    }
+
+    /// Takes a consistent backup of the block store and notes tree (and the prover's state, if
+    /// this node is a prover), then deletes backups beyond the configured retention
+    pub async fn create_backup(&self) -> backup::Result<(PathBuf, BackupManifest)> {
+        // Only one backup at a time
+        let _guard = self.backup_lock.lock().await;
+
+        let deadline = Instant::now() + CONSISTENT_BACKUP_TIMEOUT;
+        while Instant::now() < deadline {
+            if let Some(backup) = self.try_create_backup().await? {
+                backup::prune_backups(&self.config.backup_path, self.config.backup_retention)?;
+                return Ok(backup);
+            }
+
+            info!("Block store, notes tree and prover are at different heights, retrying backup");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        Err(backup::Error::Inconsistent)
+    }
+
+    /// Returns `None` if the block store, notes tree or prover state were not at the same
+    /// height, which happens if a block is being committed or the prover is catching up
+    async fn try_create_backup(&self) -> backup::Result<Option<(PathBuf, BackupManifest)>> {
+        let pending = PendingBackup::new(&self.config.backup_path)?;
+
+        // The prover holds this lock while applying proved blocks, so take it first and without
+        // holding any of the node's locks. It is held until both trees are checkpointed
+        let prover = match self.prover_backup_source.get() {
+            Some(ProverBackupSource { db, notes_tree }) => {
+                Some((Arc::clone(db), Arc::clone(notes_tree).lock_owned().await))
+            }
+            None => None,
+        };
+
+        let block_store = Arc::clone(&self.block_store);
+        let notes_tree = Arc::clone(&self.notes_tree);
+
+        // Checkpoints are blocking I/O
+        tokio::task::spawn_blocking(move || {
+            // Commits write to the block store before the notes tree, holding a read lock
+            // prevents the tree from advancing while we checkpoint both
+            let notes_tree = notes_tree.read();
+
+            let Some(height) = block_store.get_max_height()? else {
+                return Ok(None);
+            };
+            let Some(block) = block_store.get(height)? else {
+                return Ok(None);
+            };
+
+            let root_hash = notes_tree.root_hash();
+            if block.into_block().content.state.root_hash != root_hash {
+                return Ok(None);
+            }
+
+            let prover = match &prover {
+                Some((db, prover_tree)) => {
+                    let Some(tree) = prover_tree.as_ref() else {
+                        return Ok(None);
+                    };
+                    let Some(last_seen) = db.get_last_seen_block()? else {
+                        return Ok(None);
+                    };
+                    if last_seen.height != height || last_seen.root_hash != tree.tree().root_hash()
+                    {
+                        return Ok(None);
+                    }
+
+                    pending.checkpoint_prover(db, tree)?;
+
+                    Some(ProverBackupManifest {
+                        last_seen_height: last_seen.height,
+                        root_hash: last_seen.root_hash,
+                    })
+                }
+                None => None,
+            };
+
+            pending.checkpoint_block_store(&block_store)?;
+            pending.checkpoint_smirk(&notes_tree)?;
+            drop(notes_tree);
+
+            let manifest = BackupManifest {
+                height,
+                root_hash,
+                created_at_unix_s: chrono::Utc::now().timestamp() as u64,
+                prover,
+            };
+            let backup_dir = pending.finish(&manifest)?;
+
+            Ok(Some((backup_dir, manifest)))
+        })
+        .await?
+    }
+}
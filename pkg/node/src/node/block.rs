@@ -1,10 +1,12 @@
+use element::Element;
 use prover::smirk_metadata::SmirkMetadata;
 use smirk::Batch;
 use tracing::instrument;
 
 use crate::{
-    Error, NodeShared, PersistentMerkleTree, Result,
+    Error, NodeShared, NotesTree, PersistentMerkleTree, Result,
     block::{Block, BlockState},
+    constants::MERKLE_TREE_DEPTH,
     types::BlockHeight,
 };
 
@@ -30,9 +32,11 @@ impl NodeShared {
             return Err(Error::InvalidSignature);
         }
 
-        block
-            .content
-            .validate(self.config.mode, &self.block_store, &self.notes_tree.read())?;
+        block.content.validate(
+            self.config.mode,
+            &self.block_store,
+            &self.notes_tree_snapshot(),
+        )?;
 
         Ok(())
     }
@@ -43,23 +47,34 @@ impl NodeShared {
         state: &BlockState,
         current_height: BlockHeight,
     ) -> Result<()> {
-        let insert_leaves = state
-            .txns
-            .iter()
-            .flat_map(|txn| txn.public_inputs.output_commitments)
-            .filter(|e| !e.is_zero());
+        let batch = Self::block_batch(state, notes_tree.tree().root_hash(), current_height)?;
 
-        let remove_leaves = state
-            .txns
-            .iter()
-            .flat_map(|txn| txn.public_inputs.input_commitments)
-            .filter(|e| !e.is_zero());
+        // Versioned by height, so `/v0/merkle` can serve paths as of recent heights
+        notes_tree.insert_batch_at(batch, current_height.0)?;
+        Ok(())
+    }
 
-        let metadata = SmirkMetadata::inserted_in(current_height.0);
-        let leaves_with_height = insert_leaves.map(|e| (e, metadata.clone()));
-        let batch = Batch::from_entries(leaves_with_height, remove_leaves.collect::<Vec<_>>())?;
+    /// Like [`NodeShared::apply_block_to_tree`], for the node's own [`NotesTree`]
+    pub(crate) fn apply_block_to_notes_tree(
+        notes_tree: &mut NotesTree,
+        state: &BlockState,
+        current_height: BlockHeight,
+    ) -> Result<()> {
+        let batch = Self::block_batch(state, notes_tree.root_hash(), current_height)?;
+        notes_tree.insert_batch_at(batch, current_height.0)
+    }
 
-        notes_tree.insert_batch(batch)?;
-        Ok(())
+    /// The changes a block makes to the notes tree, whose root was `old_root` before the block
+    pub(crate) fn block_batch(
+        state: &BlockState,
+        old_root: Element,
+        current_height: BlockHeight,
+    ) -> Result<Batch<MERKLE_TREE_DEPTH, SmirkMetadata>> {
+        let metadata = SmirkMetadata::inserted_in(current_height.0);
+        let leaves_with_height = state
+            .leaves(old_root)
+            .into_iter()
+            .map(|e| (e, metadata.clone()));
+        Ok(Batch::from_entries(leaves_with_height, vec![])?)
     }
 }
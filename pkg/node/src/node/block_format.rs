@@ -2,7 +2,10 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use primitives::block_height::BlockHeight;
 use wire_message::WireMessage;
 
-use crate::{TxnFormat, block::Block};
+use crate::{
+    TxnFormat,
+    block::{Block, UtxoBlock},
+};
 
 use super::txn_format::TxnMetadata;
 
@@ -14,16 +17,19 @@ pub struct BlockMetadata {
 #[derive(Debug, Clone)]
 #[wire_message::wire_message]
 pub enum BlockFormat {
-    V1(Block),
-    V2(Block, BlockMetadata),
+    V1(UtxoBlock),
+    V2(UtxoBlock, BlockMetadata),
+    /// Transactions can be from any [`TxnProof`](zk_primitives::TxnProof) circuit
+    V3(Block, BlockMetadata),
     // TODO next version: cache the block hash in metadata
 }
 
 impl BlockFormat {
     pub(crate) fn into_block(self) -> Block {
         match self {
-            Self::V1(block) => block,
-            Self::V2(block, _) => block,
+            Self::V1(block) => block.into(),
+            Self::V2(block, _) => block.into(),
+            Self::V3(block, _) => block,
         }
     }
 
@@ -33,6 +39,7 @@ impl BlockFormat {
                 timestamp_unix_s: None,
             },
             Self::V2(_, metadata) => metadata,
+            Self::V3(_, metadata) => metadata,
         }
     }
 }
@@ -45,6 +52,7 @@ impl WireMessage for BlockFormat {
         match self {
             Self::V1(_) => 1,
             Self::V2(_, _) => 2,
+            Self::V3(_, _) => 3,
         }
     }
 
@@ -56,7 +64,8 @@ impl WireMessage for BlockFormat {
                     timestamp_unix_s: None,
                 },
             )),
-            Self::V2(_, _) => Err(Self::max_version_error()),
+            Self::V2(block, metadata) => Ok(Self::V3(block.into(), metadata)),
+            Self::V3(_, _) => Err(Self::max_version_error()),
         }
     }
 }
@@ -68,27 +77,29 @@ impl block_store::Block for BlockFormat {
         match self {
             Self::V1(block) => block.block_height(),
             Self::V2(block, _) => block.block_height(),
+            Self::V3(block, _) => block.block_height(),
         }
     }
 
     fn block_hash(&self) -> [u8; 32] {
         match self {
-            Self::V1(block) => block.block_hash(),
-            Self::V2(block, _) => block.block_hash(),
+            Self::V1(block) => block.hash().into_inner(),
+            Self::V2(block, _) => block.hash().into_inner(),
+            Self::V3(block, _) => block.block_hash(),
         }
     }
 
     fn txns(&self) -> Vec<Self::Txn> {
-        match self {
-            Self::V1(block) => block.txns(),
-            Self::V2(block, block_metadata) => {
-                let mut txns = block.txns();
-                for TxnFormat::V1(_, metadata) in &mut txns {
-                    metadata.block_time = block_metadata.timestamp_unix_s;
-                }
-                txns
-            }
+        let (mut txns, block_metadata) = match self {
+            Self::V1(block) => return Block::from(block.clone()).txns(),
+            Self::V2(block, block_metadata) => (Block::from(block.clone()).txns(), block_metadata),
+            Self::V3(block, block_metadata) => (block.txns(), block_metadata),
+        };
+
+        for txn in &mut txns {
+            txn.metadata_mut().block_time = block_metadata.timestamp_unix_s;
         }
+        txns
     }
 }
 
@@ -110,7 +121,7 @@ impl block_store::Block for Block {
             .iter()
             .enumerate()
             .map(|(i, t)| {
-                TxnFormat::V1(
+                TxnFormat::V2(
                     t.clone(),
                     TxnMetadata {
                         block_height: self.block_height(),
@@ -0,0 +1,60 @@
+use element::Element;
+use node_interface::{ElementData, RpcError};
+use tracing::debug;
+use zk_primitives::EncryptedNote;
+
+use super::NodeShared;
+use crate::{Result, inbox_store::InsertOutcome};
+
+impl NodeShared {
+    /// Stores an encrypted note for the recipient of the output with `commitment`
+    ///
+    /// The commitment must be in the notes tree or the output of a transaction in the mempool.
+    /// Notes are only kept by this node for [`Config::inbox_retention_secs`], they are not
+    /// shared with peers or included in blocks. Posting a note that is already stored succeeds
+    ///
+    /// [`Config::inbox_retention_secs`]: crate::config::Config::inbox_retention_secs
+    pub(crate) fn post_inbox_note(&self, commitment: Element, note: &EncryptedNote) -> Result<()> {
+        let note = note.to_bytes();
+        if EncryptedNote::from_bytes(&note).is_err() {
+            return Err(RpcError::InvalidInboxNote)?;
+        }
+
+        if !self.notes_tree_snapshot().contains_element(&commitment)?
+            && !self.mempool.contains_change(&commitment)
+        {
+            return Err(RpcError::ElementNotFound(ElementData {
+                element: commitment,
+            }))?;
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let pruned = self.inbox.prune(now)?;
+        if pruned > 0 {
+            debug!(pruned, "Pruned expired inbox notes");
+        }
+
+        match self.inbox.insert(
+            commitment,
+            &note,
+            now.saturating_add(self.config.inbox_retention_secs),
+            self.config.inbox_max_notes_per_commitment,
+        )? {
+            InsertOutcome::Inserted | InsertOutcome::AlreadyExists => Ok(()),
+            InsertOutcome::Full => Err(RpcError::InboxFull(ElementData {
+                element: commitment,
+            }))?,
+        }
+    }
+
+    /// The unexpired encrypted notes posted for `commitment`
+    pub(crate) fn inbox_notes(&self, commitment: Element) -> Result<Vec<EncryptedNote>> {
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        self.inbox
+            .notes(commitment, now)?
+            .iter()
+            .map(|bytes| Ok(EncryptedNote::from_bytes(bytes)?))
+            .collect()
+    }
+}
@@ -5,13 +5,16 @@ use element::Element;
 use tracing::info;
 
 use crate::{
-    BlockFormat, Node, NodeShared, PersistentMerkleTree, Result, block::Block, config::Config,
-    constants::MERKLE_TREE_DEPTH, types::BlockHeight,
+    BlockFormat, Node, NodeShared, NotesTree, NotesTreeCache, Result,
+    block::Block,
+    config::{Config, StorageBackend},
+    constants::MERKLE_TREE_DEPTH,
+    types::BlockHeight,
 };
 
 pub(super) struct LoadedData {
     pub block_store: BlockStore<BlockFormat>,
-    pub persistent_tree: PersistentMerkleTree,
+    pub notes_tree: NotesTree,
     pub block: Block,
 }
 
@@ -22,6 +25,19 @@ fn empty_tree_hash() -> Element {
 
 impl Node {
     pub(super) fn load_db_and_smirk(config: &Config) -> Result<LoadedData> {
+        if config.storage == StorageBackend::Memory {
+            info!("Using in-memory storage, nothing will be persisted");
+
+            return Ok(LoadedData {
+                block_store: BlockStore::in_memory()?,
+                notes_tree: NotesTree::new_in_memory(
+                    config.notes_tree_storage,
+                    NotesTreeCache::from_config(config),
+                ),
+                block: Block::genesis(),
+            });
+        }
+
         let db_path = &config.db_path.join("latest");
         info!("Loading DB from: {}", db_path.to_str().unwrap());
 
@@ -29,24 +45,34 @@ impl Node {
         info!("Loading Smirk from: {}", &smirk_path.to_str().unwrap());
 
         let block_store = BlockStore::create_or_load(db_path)?;
-        let mut persistent_tree = smirk::storage::Persistent::load(&smirk_path)?;
+        // Blocks can only be read once the store is in the latest format
+        block_store.migrate()?;
+        let mut notes_tree = NotesTree::load(
+            config.notes_tree_storage,
+            &smirk_path,
+            NotesTreeCache::from_config(config),
+        )?;
 
         let Some(max_height) = block_store.get_max_height()? else {
             info!(
-                smirk_root_hash = ?persistent_tree.tree().root_hash(),
+                smirk_root_hash = ?notes_tree.root_hash(),
                 "No blocks found in the block store, resetting smirk and starting from genesis"
             );
 
-            drop(persistent_tree);
+            drop(notes_tree);
 
             Self::reset_db_and_smirk(None, Some(&config.smirk_path))?;
-            let persistent_tree = smirk::storage::Persistent::load(smirk_path)?;
+            let notes_tree = NotesTree::load(
+                config.notes_tree_storage,
+                &smirk_path,
+                NotesTreeCache::from_config(config),
+            )?;
 
-            debug_assert_eq!(persistent_tree.tree().root_hash(), empty_tree_hash());
+            debug_assert_eq!(notes_tree.root_hash(), empty_tree_hash());
 
             let data = LoadedData {
                 block_store,
-                persistent_tree,
+                notes_tree,
                 block: Block::genesis(),
             };
 
@@ -55,10 +81,10 @@ impl Node {
 
         let block = block_store.get(max_height)?.unwrap().into_block();
 
-        if persistent_tree.tree().root_hash() == block.content.state.root_hash {
+        if notes_tree.root_hash() == block.content.state.root_hash {
             let data = LoadedData {
                 block_store,
-                persistent_tree,
+                notes_tree,
                 block,
             };
 
@@ -70,24 +96,24 @@ impl Node {
             .unwrap()
             .into_block();
 
-        if persistent_tree.tree().root_hash() == previous_block.content.state.root_hash {
+        if notes_tree.root_hash() == previous_block.content.state.root_hash {
             info!(
-                local_tree_root_hash = ?persistent_tree.tree().root_hash(),
+                local_tree_root_hash = ?notes_tree.root_hash(),
                 block_root_hash = ?block.content.state.root_hash,
                 previous_block_root_hash = ?previous_block.content.state.root_hash,
                 "The node crashed after committing to block store, but before committing to notes tree. We will recover by applying the block to the tree."
             );
 
-            NodeShared::apply_block_to_tree(
-                &mut persistent_tree,
+            NodeShared::apply_block_to_notes_tree(
+                &mut notes_tree,
                 &block.content.state,
                 max_height,
             )?;
-            assert!(persistent_tree.tree().root_hash() == block.content.state.root_hash);
+            assert!(notes_tree.root_hash() == block.content.state.root_hash);
 
             let data = LoadedData {
                 block_store,
-                persistent_tree,
+                notes_tree,
                 block,
             };
 
@@ -95,25 +121,29 @@ impl Node {
         }
 
         info!(
-            local_tree_root_hash = ?persistent_tree.tree().root_hash(),
+            local_tree_root_hash = ?notes_tree.root_hash(),
             block_root_hash = ?block.content.state.root_hash,
             previous_block_root_hash = ?previous_block.content.state.root_hash,
             "Block store and tree are too far out of sync to recover. Resetting and starting from genesis"
         );
         drop(block_store);
-        drop(persistent_tree);
+        drop(notes_tree);
 
         Self::reset_db_and_smirk(Some(&config.db_path), Some(&config.smirk_path))?;
 
         let block_store = BlockStore::create_or_load(db_path)?;
-        let persistent_tree = smirk::storage::Persistent::load(&smirk_path)?;
+        let notes_tree = NotesTree::load(
+            config.notes_tree_storage,
+            &smirk_path,
+            NotesTreeCache::from_config(config),
+        )?;
 
         debug_assert!(block_store.get_max_height()?.is_none());
-        debug_assert_eq!(persistent_tree.tree().root_hash(), empty_tree_hash(),);
+        debug_assert_eq!(notes_tree.root_hash(), empty_tree_hash(),);
 
         let data = LoadedData {
             block_store,
-            persistent_tree,
+            notes_tree,
             block: Block::genesis(),
         };
 
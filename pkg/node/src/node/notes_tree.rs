@@ -0,0 +1,284 @@
+use std::{path::Path as FsPath, sync::Arc};
+
+use element::Element;
+use node_interface::{ElementData, RpcError};
+use parking_lot::RwLock;
+use prover::smirk_metadata::SmirkMetadata;
+use smirk::{
+    Batch, NonMembershipProof, Path, SubtreeDiff, SubtreeId, SubtreeSummary,
+    hash_cache::{HashCache, KnownHash, KnownHashCache, LruHashCache, SimpleHashCache},
+};
+
+use crate::{
+    NotesTreeHistory, PersistentMerkleTree, Result,
+    config::{Config, NotesTreeHashCache, NotesTreeStorage},
+    constants::MERKLE_TREE_DEPTH,
+};
+
+/// A notes tree that keeps its node hashes on disk, see [`NotesTreeStorage::Disk`]
+pub type DiskMerkleTree = smirk::storage::DiskTree<MERKLE_TREE_DEPTH, SmirkMetadata>;
+
+/// An in-memory copy of the notes tree, sharing unchanged nodes with [`PersistentMerkleTree`]
+pub type MemoryMerkleTree = smirk::Tree<MERKLE_TREE_DEPTH, SmirkMetadata, NotesTreeCache>;
+
+/// The hash cache of in-memory notes trees, selected by [`NotesTreeHashCache`]
+#[derive(Debug, Clone)]
+pub enum NotesTreeCache {
+    Simple(SimpleHashCache),
+    Lru(LruHashCache),
+}
+
+/// The notes tree, in the implementation selected by [`NotesTreeStorage`]
+pub enum NotesTree {
+    Memory(PersistentMerkleTree),
+    /// Shared with [`NotesTreeSnapshot::Disk`]s, which read it directly
+    Disk(Arc<RwLock<DiskMerkleTree>>),
+}
+
+/// The notes tree as readers see it
+///
+/// Memory snapshots are copies of the tree as of the last change, so they never wait for a
+/// commit. Disk snapshots read the current tree under a read lock, so each method sees a
+/// consistent tree, but successive calls may see different versions
+pub enum NotesTreeSnapshot {
+    Memory(MemoryMerkleTree),
+    Disk(Arc<RwLock<DiskMerkleTree>>),
+}
+
+impl NotesTreeCache {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        match config.notes_tree_hash_cache {
+            NotesTreeHashCache::Simple => Self::Simple(SimpleHashCache::new()),
+            NotesTreeHashCache::Lru => {
+                Self::Lru(LruHashCache::new(config.notes_tree_hash_cache_capacity))
+            }
+        }
+    }
+}
+
+impl Default for NotesTreeCache {
+    fn default() -> Self {
+        Self::Simple(SimpleHashCache::new())
+    }
+}
+
+impl HashCache for NotesTreeCache {
+    #[inline]
+    fn hash(&self, left: Element, right: Element) -> Element {
+        match self {
+            Self::Simple(cache) => cache.hash(left, right),
+            Self::Lru(cache) => cache.hash(left, right),
+        }
+    }
+}
+
+impl KnownHashCache for NotesTreeCache {
+    fn provide_known_hashes(&mut self, hashes: Vec<KnownHash>) {
+        match self {
+            Self::Simple(cache) => cache.provide_known_hashes(hashes),
+            Self::Lru(cache) => cache.provide_known_hashes(hashes),
+        }
+    }
+}
+
+impl NotesTree {
+    /// An empty tree that is only held in memory
+    ///
+    /// `cache` is only used by [`NotesTree::Memory`], disk trees read their hashes from the store
+    pub(crate) fn new_in_memory(storage: NotesTreeStorage, cache: NotesTreeCache) -> Self {
+        match storage {
+            NotesTreeStorage::Memory => {
+                Self::Memory(PersistentMerkleTree::new_in_memory_with_cache(cache))
+            }
+            NotesTreeStorage::Disk => {
+                Self::Disk(Arc::new(RwLock::new(DiskMerkleTree::new_in_memory())))
+            }
+        }
+    }
+
+    /// Load the tree at `path`, which either implementation can open, whichever wrote it last
+    pub(crate) fn load(
+        storage: NotesTreeStorage,
+        path: &FsPath,
+        cache: NotesTreeCache,
+    ) -> Result<Self> {
+        Ok(match storage {
+            NotesTreeStorage::Memory => {
+                Self::Memory(PersistentMerkleTree::load_with_cache(path, cache)?)
+            }
+            NotesTreeStorage::Disk => {
+                Self::Disk(Arc::new(RwLock::new(DiskMerkleTree::load(path)?)))
+            }
+        })
+    }
+
+    pub(crate) fn root_hash(&self) -> Element {
+        match self {
+            Self::Memory(tree) => tree.tree().root_hash(),
+            Self::Disk(tree) => tree.read().root_hash(),
+        }
+    }
+
+    /// Apply `batch` as the changes made by the block at `height`
+    ///
+    /// Only [`NotesTree::Memory`] records history, so the height is ignored for disk trees
+    pub(crate) fn insert_batch_at(
+        &mut self,
+        batch: Batch<MERKLE_TREE_DEPTH, SmirkMetadata>,
+        height: u64,
+    ) -> Result<()> {
+        match self {
+            Self::Memory(tree) => tree.insert_batch_at(batch, height)?,
+            Self::Disk(tree) => tree.write().insert_batch(batch)?,
+        }
+
+        Ok(())
+    }
+
+    /// Apply `batch` without attributing it to a height, which clears any history
+    pub(crate) fn insert_batch(
+        &mut self,
+        batch: Batch<MERKLE_TREE_DEPTH, SmirkMetadata>,
+    ) -> Result<()> {
+        match self {
+            Self::Memory(tree) => tree.insert_batch(batch)?,
+            Self::Disk(tree) => tree.write().insert_batch(batch)?,
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn snapshot(&self) -> NotesTreeSnapshot {
+        match self {
+            Self::Memory(tree) => NotesTreeSnapshot::Memory(tree.snapshot()),
+            Self::Disk(tree) => NotesTreeSnapshot::Disk(Arc::clone(tree)),
+        }
+    }
+
+    /// The tree's history, or `None` if it is a disk tree, which doesn't keep any
+    pub(crate) fn history_snapshot(&self) -> Option<NotesTreeHistory> {
+        match self {
+            Self::Memory(tree) => Some(tree.history_snapshot()),
+            Self::Disk(_) => None,
+        }
+    }
+
+    /// Write a consistent copy of the tree's store to `path`
+    pub(crate) fn checkpoint(&self, path: &FsPath) -> Result<(), smirk::storage::Error> {
+        match self {
+            Self::Memory(tree) => tree.checkpoint(path),
+            Self::Disk(tree) => tree.read().checkpoint(path),
+        }
+    }
+}
+
+impl NotesTreeSnapshot {
+    pub(crate) fn root_hash(&self) -> Element {
+        match self {
+            Self::Memory(tree) => tree.root_hash(),
+            Self::Disk(tree) => tree.read().root_hash(),
+        }
+    }
+
+    pub(crate) fn contains_element(&self, element: &Element) -> Result<bool> {
+        Ok(match self {
+            Self::Memory(tree) => tree.contains_element(element),
+            Self::Disk(tree) => tree.read().contains_element(element)?,
+        })
+    }
+
+    pub(crate) fn get(&self, element: Element) -> Result<Option<SmirkMetadata>> {
+        Ok(match self {
+            Self::Memory(tree) => tree.get(element).cloned(),
+            Self::Disk(tree) => tree.read().get(element)?,
+        })
+    }
+
+    pub(crate) fn root_hash_with(
+        &self,
+        insert_elements: &[Element],
+        remove_elements: &[Element],
+    ) -> Result<Element> {
+        Ok(match self {
+            Self::Memory(tree) => tree.root_hash_with(insert_elements, remove_elements),
+            Self::Disk(tree) => tree
+                .read()
+                .root_hash_with(insert_elements, remove_elements)?,
+        })
+    }
+
+    /// Every element in the tree and its metadata
+    pub(crate) fn elements(&self) -> Result<Vec<(Element, SmirkMetadata)>> {
+        match self {
+            Self::Memory(tree) => Ok(tree
+                .elements()
+                .map(|(element, metadata)| (*element, metadata.clone()))
+                .collect()),
+            Self::Disk(tree) => Ok(tree.read().elements().collect::<Result<_, _>>()?),
+        }
+    }
+
+    /// Merkle paths for `elements`, and the root hash they are against
+    pub(crate) fn merkle_paths(&self, elements: &[Element]) -> Result<(Element, Vec<Path>)> {
+        match self {
+            Self::Memory(tree) => super::merkle_paths(tree, elements),
+            Self::Disk(tree) => {
+                let tree = tree.read();
+
+                let paths = elements
+                    .iter()
+                    .map(|e| {
+                        if !tree.contains_element(e)? {
+                            return Err(RpcError::ElementNotFound(ElementData { element: *e }))?;
+                        }
+
+                        Ok(tree.path_for(*e)?)
+                    })
+                    .collect::<Result<Vec<Path>>>()?;
+
+                Ok((tree.root_hash(), paths))
+            }
+        }
+    }
+
+    /// Proofs that none of `elements` are in the tree, and the root hash they are against
+    pub(crate) fn non_membership_proofs(
+        &self,
+        elements: &[Element],
+    ) -> Result<(Element, Vec<NonMembershipProof>)> {
+        let in_tree = |e: &Element| RpcError::ElementInTree(ElementData { element: *e });
+
+        match self {
+            Self::Memory(tree) => {
+                let proofs = elements
+                    .iter()
+                    .map(|e| tree.non_membership_proof(*e).ok_or_else(|| in_tree(e)))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok((tree.root_hash(), proofs))
+            }
+            Self::Disk(tree) => {
+                let tree = tree.read();
+
+                let proofs = elements
+                    .iter()
+                    .map(|e| Ok(tree.non_membership_proof(*e)?.ok_or_else(|| in_tree(e))?))
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok((tree.root_hash(), proofs))
+            }
+        }
+    }
+
+    /// See [`smirk::Tree::diff_subtree`]
+    pub(crate) fn diff_subtree(
+        &self,
+        id: &SubtreeId,
+        remote: &SubtreeSummary,
+    ) -> Result<SubtreeDiff> {
+        Ok(match self {
+            Self::Memory(tree) => tree.diff_subtree(id, remote),
+            Self::Disk(tree) => tree.read().diff_subtree(id, remote)?,
+        })
+    }
+}
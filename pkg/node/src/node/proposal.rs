@@ -1,7 +1,9 @@
 use std::{sync::Arc, time::Instant};
 
 use doomslug::ApprovalValidated;
+use element::Element;
 use primitives::hash::CryptoHash;
+use prover::aggregation_slots;
 use tracing::{info, instrument, warn};
 
 use crate::{
@@ -40,47 +42,28 @@ impl NodeShared {
                 hash = format!("0x{}", utxo_proof.hash()),
                 kind = ?utxo_proof.kind(),
                 kind_messages = ?utxo_proof.kind_messages(),
-                messages =  ?utxo_proof.public_inputs.messages.iter().map(|l| format!("0x{l:x}")).collect::<Vec<_>>(),
-                input_leaves = ?utxo_proof.public_inputs.input_commitments.iter().map(|l| format!("0x{l:x}")).collect::<Vec<_>>(),
-                output_leaves = ?utxo_proof.public_inputs.output_commitments.iter().map(|l| format!("0x{l:x}")).collect::<Vec<_>>(),
+                messages =  ?utxo_proof.messages().iter().map(|l| format!("0x{l:x}")).collect::<Vec<_>>(),
+                root = format!("0x{:x}", utxo_proof.root()),
+                nullifiers = ?utxo_proof.nullifiers().iter().map(|l| format!("0x{l:x}")).collect::<Vec<_>>(),
+                output_leaves = ?utxo_proof.output_commitments().iter().map(|l| format!("0x{l:x}")).collect::<Vec<_>>(),
                 "Committing transaction"
             )
         }
 
-        // Input commitments (to be removed from the tree)
-        let block_input_commitments = state
-            .txns
-            .iter()
-            .flat_map(|utxo_proof| utxo_proof.public_inputs.input_commitments)
-            .filter(|l: &_| !l.is_zero())
-            .collect::<Vec<_>>();
-
-        // Input notes should be in the tree
-        for input_commitment in block_input_commitments {
-            if !self
-                .notes_tree
-                .read()
-                .tree()
-                .contains_element(&input_commitment)
-            {
-                panic!(
-                    "Double-spend detected -> input note NOT in tree. This should never happen, this should have been caught before commit"
-                );
-            }
-        }
-
         // The order of these operations is important.
         // If we exit after commiting to block store,
         // but before commiting to notes tree, we
         // can detect it by checking the previous block's root hash.
-        self.block_store.set(&BlockFormat::V2(
+        self.block_store.set(&BlockFormat::V3(
             block.clone(),
             BlockMetadata {
                 timestamp_unix_s: Some(commit_time.timestamp() as u64),
             },
         ))?;
 
-        Self::apply_block_to_tree(&mut self.notes_tree.write(), state, height)?;
+        self.update_notes_tree(|notes_tree| {
+            Self::apply_block_to_notes_tree(notes_tree, state, height)
+        })?;
 
         let block = Arc::new(block);
 
@@ -156,32 +139,25 @@ impl NodeShared {
                 }
             }
 
-            utxos.into_iter().map(|(_, utxo)| utxo).collect::<Vec<_>>()
-        };
-
-        let insert_leaves = txns
-            .iter()
-            .flat_map(|utxo| utxo.public_inputs.output_commitments)
-            .filter(|l| !l.is_zero())
-            .collect::<Vec<_>>();
-        let remove_leaves = txns
-            .iter()
-            .flat_map(|utxo| utxo.public_inputs.input_commitments)
-            .filter(|l| !l.is_zero())
-            .collect::<Vec<_>>();
+            let mut txns = utxos.into_iter().map(|(_, utxo)| utxo).collect::<Vec<_>>();
 
-        let new_root_hash = match insert_leaves.is_empty() && remove_leaves.is_empty() {
-            true => {
-                // Root is unchanged
-                self.notes_tree.read().tree().root_hash()
-            }
-            false => {
-                // TODO_NOIR: we also need to remove some elements from the tree too
-                self.notes_tree
-                    .read()
-                    .tree()
-                    .root_hash_with(&insert_leaves, &remove_leaves)
+            // Swaps fill a slot per leg, so fewer txns than were leased may fit in the block.
+            // The rest are released back to the pool when the block is committed
+            while aggregation_slots(&txns).is_none() {
+                txns.pop();
             }
+
+            txns
+        };
+
+        // The root is set once the leaves the txns insert are known
+        let mut state = BlockState::new(Element::ZERO, txns);
+        let notes_tree = self.notes_tree_snapshot();
+        let leaves = state.leaves(notes_tree.root_hash());
+        state.root_hash = match leaves.is_empty() {
+            // Root is unchanged
+            true => notes_tree.root_hash(),
+            false => notes_tree.root_hash_with(&leaves, &[])?,
         };
 
         let block_content = BlockContent {
@@ -192,7 +168,7 @@ impl NodeShared {
                 last_final_block_hash: last_block_hash,
                 approvals: accepts.into_iter().map(|a| a.signature).collect(),
             },
-            state: BlockState::new(new_root_hash, txns),
+            state,
         };
 
         // Create a signed block
@@ -5,7 +5,7 @@ use tracing::{info, instrument};
 
 use crate::{
     NodeShared, Result,
-    network::{SnapshotChunk, SnapshotKind},
+    network::{SnapshotChunk, SnapshotKind, TreeSyncRequest, TreeSyncResponse},
     types::{BlockHeight, SnapshotId},
 };
 
@@ -18,9 +18,10 @@ impl NodeShared {
         &self,
         peer: PeerId,
         snapshot_id: SnapshotId,
+        kind: SnapshotKind,
     ) -> Result<()> {
         info!("Received snapshot offer");
-        self.sync_worker.snapshot_offer(peer, snapshot_id)?;
+        self.sync_worker.snapshot_offer(peer, snapshot_id, kind)?;
 
         Ok(())
     }
@@ -75,4 +76,28 @@ impl NodeShared {
 
         Ok(())
     }
+
+    /// A node that is reconciling its notes tree with ours wants summaries of our subtrees
+    #[instrument(skip(self))]
+    pub(crate) async fn receive_tree_sync_request(
+        &self,
+        peer: PeerId,
+        request: TreeSyncRequest,
+    ) -> Result<()> {
+        sync::handle_tree_sync_request(self, peer, request).await?;
+
+        Ok(())
+    }
+
+    /// A node is sending us summaries of its subtrees, for the notes tree we are reconciling
+    #[instrument(skip(self))]
+    pub(crate) fn receive_tree_sync_response(
+        &self,
+        peer: PeerId,
+        response: TreeSyncResponse,
+    ) -> Result<()> {
+        self.sync_worker.tree_sync_response(peer, response)?;
+
+        Ok(())
+    }
 }
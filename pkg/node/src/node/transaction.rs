@@ -5,10 +5,10 @@ use ethereum_types::U64;
 use node_interface::{ElementData, ElementsVecData, MintInContractIsDifferent, RpcError};
 use std::{sync::Arc, time::Duration};
 use tracing::{error, info, instrument};
-use zk_primitives::{UtxoKindMessages, UtxoProof};
+use zk_primitives::{TxnProof, UtxoKindMessages};
 
 impl NodeShared {
-    pub async fn submit_transaction_and_wait(&self, utxo: UtxoProof) -> Result<Arc<Block>> {
+    pub async fn submit_transaction_and_wait(&self, utxo: TxnProof) -> Result<Arc<Block>> {
         let mut started_waiting_at_eth_block = None;
         loop {
             match self.validate_transaction(&utxo).await {
@@ -50,17 +50,12 @@ impl NodeShared {
         self.send_all(NetworkEvent::Transaction(utxo.clone())).await;
 
         let mut changes = Vec::new();
-        for commitment in utxo
-            .public_inputs
-            .input_commitments
-            .into_iter()
-            .chain(utxo.public_inputs.output_commitments)
-        {
-            if commitment.is_zero() {
+        for leaf in utxo.leaves() {
+            if leaf.is_zero() {
                 continue;
             }
-            if !changes.contains(&commitment) {
-                changes.push(commitment);
+            if !changes.contains(&leaf) {
+                changes.push(leaf);
             }
         }
 
@@ -83,8 +78,14 @@ impl NodeShared {
         receiver.await.expect("recv error")
     }
 
-    pub(super) async fn validate_transaction(&self, utxo: &UtxoProof) -> Result<()> {
+    pub(super) async fn validate_transaction(&self, utxo: &TxnProof) -> Result<()> {
         if let UtxoKindMessages::Mint(mint_msgs) = utxo.kind_messages() {
+            if !self.note_kinds.contains(mint_msgs.note_kind) {
+                return Err(RpcError::NoteKindNotAllowed(ElementData {
+                    element: mint_msgs.note_kind,
+                }))?;
+            }
+
             let eth_block = self
                 .rollup_contract
                 .client
@@ -117,7 +118,7 @@ impl NodeShared {
             // Check if mint is already spent
             if get_mint_res.spent {
                 return Err(RpcError::MintIsAlreadySpent(ElementsVecData {
-                    elements: utxo.public_inputs.output_commitments.to_vec(),
+                    elements: utxo.output_commitments(),
                 }))?;
             }
 
@@ -141,12 +142,12 @@ impl NodeShared {
             utxo,
             self.height(),
             &self.block_store,
-            &self.notes_tree.read(),
+            &self.notes_tree_snapshot(),
         )
     }
 
     #[instrument(skip(self, txn))]
-    pub async fn receive_transaction(&self, txn: UtxoProof) -> Result<()> {
+    pub async fn receive_transaction(&self, txn: TxnProof) -> Result<()> {
         info!("Received transaction");
 
         if let Err(err) = self.validate_transaction(&txn).await {
@@ -158,17 +159,12 @@ impl NodeShared {
         }
 
         let mut changes = Vec::new();
-        for commitment in txn
-            .public_inputs
-            .input_commitments
-            .into_iter()
-            .chain(txn.public_inputs.output_commitments)
-        {
-            if commitment.is_zero() {
+        for leaf in txn.leaves() {
+            if leaf.is_zero() {
                 continue;
             }
-            if !changes.contains(&commitment) {
-                changes.push(commitment);
+            if !changes.contains(&leaf) {
+                changes.push(leaf);
             }
         }
 
@@ -2,7 +2,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use primitives::block_height::BlockHeight;
 use serde::{Deserialize, Serialize};
 use wire_message::WireMessage;
-use zk_primitives::UtxoProof;
+use zk_primitives::{TxnProof, UtxoProofV1};
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 pub struct TxnMetadata {
@@ -15,11 +15,31 @@ pub struct TxnMetadata {
 #[derive(Debug, Clone)]
 #[wire_message::wire_message]
 pub enum TxnFormat {
-    V1(UtxoProof, TxnMetadata),
+    /// A utxo proof from before notes were spent by nullifier
+    V1(UtxoProofV1, TxnMetadata),
+    /// The transaction can be from any [`TxnProof`] circuit
+    V2(TxnProof, TxnMetadata),
     // TODO next version:
     // - cache the hash of the transaction in the metadata
 }
 
+impl TxnFormat {
+    /// The transaction proof and its metadata, upgrading older versions
+    pub(crate) fn into_parts(self) -> (TxnProof, TxnMetadata) {
+        match self {
+            Self::V1(txn, metadata) => (txn.into(), metadata),
+            Self::V2(txn, metadata) => (txn, metadata),
+        }
+    }
+
+    pub(crate) fn metadata_mut(&mut self) -> &mut TxnMetadata {
+        match self {
+            Self::V1(_, metadata) => metadata,
+            Self::V2(_, metadata) => metadata,
+        }
+    }
+}
+
 impl WireMessage for TxnFormat {
     type Ctx = ();
     type Err = core::convert::Infallible;
@@ -27,12 +47,14 @@ impl WireMessage for TxnFormat {
     fn version(&self) -> u64 {
         match self {
             Self::V1(_, _) => 1,
+            Self::V2(_, _) => 2,
         }
     }
 
     fn upgrade_once(self, _ctx: &mut Self::Ctx) -> Result<Self, wire_message::Error> {
         match self {
-            Self::V1(_, _) => Err(Self::max_version_error()),
+            Self::V1(txn, metadata) => Ok(Self::V2(txn.into(), metadata)),
+            Self::V2(_, _) => Err(Self::max_version_error()),
         }
     }
 }
@@ -41,42 +63,59 @@ impl block_store::Transaction for TxnFormat {
     fn txn_hash(&self) -> [u8; 32] {
         match self {
             Self::V1(txn, _) => txn.hash().to_be_bytes(),
+            Self::V2(txn, _) => txn.hash().to_be_bytes(),
         }
     }
 
+    /// Inputs are spent by revealing their nullifiers, their commitments stay in the tree.
+    /// Legacy utxo proofs spent their inputs by removing their commitments
     fn input_elements(&self) -> Vec<element::Element> {
-        match self {
-            TxnFormat::V1(utxo_proof, _) => utxo_proof
-                .public_inputs
-                .input_commitments
-                .iter()
-                .filter(|c| !c.is_zero())
-                .copied()
-                .collect(),
-        }
+        let input_commitments = match self {
+            TxnFormat::V1(utxo_proof, _) | TxnFormat::V2(TxnProof::UtxoV1(utxo_proof), _) => {
+                utxo_proof.public_inputs.input_commitments.to_vec()
+            }
+            TxnFormat::V2(_, _) => Vec::new(),
+        };
+
+        input_commitments
+            .into_iter()
+            .filter(|c| !c.is_zero())
+            .collect()
     }
 
     fn output_elements(&self) -> Vec<element::Element> {
-        match self {
-            TxnFormat::V1(utxo_proof, _) => utxo_proof
-                .public_inputs
-                .output_commitments
-                .iter()
-                .filter(|c| !c.is_zero())
-                .copied()
-                .collect(),
-        }
+        let output_commitments = match self {
+            TxnFormat::V1(utxo_proof, _) => utxo_proof.public_inputs.output_commitments.to_vec(),
+            TxnFormat::V2(txn_proof, _) => txn_proof.output_commitments(),
+        };
+
+        output_commitments
+            .into_iter()
+            .filter(|c| !c.is_zero())
+            .collect()
+    }
+
+    fn nullifiers(&self) -> Vec<element::Element> {
+        let nullifiers = match self {
+            TxnFormat::V1(_, _) => Vec::new(),
+            TxnFormat::V2(txn_proof, _) => txn_proof.nullifiers(),
+        };
+
+        nullifiers.into_iter().filter(|n| !n.is_zero()).collect()
     }
 
     fn mint_hash(&self) -> Option<element::Element> {
-        match self {
-            TxnFormat::V1(utxo_proof, _) => match utxo_proof.public_inputs.kind_messages() {
-                zk_primitives::UtxoKindMessages::Mint(utxo_kind_mint_messages) => {
-                    Some(utxo_kind_mint_messages.mint_hash)
-                }
-                zk_primitives::UtxoKindMessages::Burn(_) => None,
-                zk_primitives::UtxoKindMessages::None => None,
-            },
+        let kind_messages = match self {
+            TxnFormat::V1(utxo_proof, _) => utxo_proof.public_inputs.kind_messages(),
+            TxnFormat::V2(txn_proof, _) => txn_proof.kind_messages(),
+        };
+
+        match kind_messages {
+            zk_primitives::UtxoKindMessages::Mint(utxo_kind_mint_messages) => {
+                Some(utxo_kind_mint_messages.mint_hash)
+            }
+            zk_primitives::UtxoKindMessages::Burn(_) => None,
+            zk_primitives::UtxoKindMessages::None => None,
         }
     }
 }
@@ -2,6 +2,7 @@ use std::{ops::Range, path::Path};
 
 use borsh::BorshDeserialize;
 use element::Element;
+use kv_store::{Direction, KeyRange, KvStore};
 use prover::RollupInput;
 use wire_message::WireMessage;
 
@@ -15,8 +16,8 @@ pub enum Error {
     #[error("invalid value")]
     InvalidValue,
 
-    #[error("rocksdb error: {0}")]
-    RocksDB(#[from] rocksdb::Error),
+    #[error("kv store error: {0}")]
+    KvStore(#[from] kv_store::Error),
 
     #[error("WireMessage error")]
     WireMessage(#[from] wire_message::Error),
@@ -28,7 +29,7 @@ pub enum Error {
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub(crate) struct ProverDb {
-    db: rocksdb::DB,
+    db: Box<dyn KvStore>,
 }
 
 pub(crate) enum Key {
@@ -119,16 +120,31 @@ pub(crate) const LATEST_VERSION: u64 = 1;
 impl ProverDb {
     pub(crate) fn create_or_load(path: &Path) -> Result<Self> {
         let new_db = !(path.exists() && std::fs::read_dir(path)?.next().is_some());
-        let db = rocksdb::DB::open_default(path)?;
-        let db = Self { db };
+        let db = kv_store::RocksDb::open_default(path)?;
+        let db = Self { db: Box::new(db) };
         if new_db {
             db.set_version(LATEST_VERSION)?;
         }
         Ok(db)
     }
 
+    /// Creates an empty prover state held in memory
+    pub(crate) fn in_memory() -> Result<Self> {
+        let db = Self {
+            db: Box::new(kv_store::Memory::new()),
+        };
+        db.set_version(LATEST_VERSION)?;
+        Ok(db)
+    }
+
+    /// Creates a point-in-time copy of the prover state at `path` using a RocksDB checkpoint
+    pub(crate) fn checkpoint(&self, path: &Path) -> Result<()> {
+        self.db.checkpoint(path)?;
+        Ok(())
+    }
+
     fn get(&self, key: Key) -> Result<Option<Vec<u8>>> {
-        let bytes = self.db.get(key.serialize())?;
+        let bytes = self.db.get(&key.serialize())?;
         Ok(bytes)
     }
 
@@ -146,7 +162,7 @@ impl ProverDb {
     }
 
     fn set(&self, key: Key, value: Value) -> Result<()> {
-        self.db.put(key.serialize(), value.to_bytes()?)?;
+        self.db.put(&key.serialize(), &value.to_bytes()?)?;
         Ok(())
     }
 
@@ -167,28 +183,22 @@ impl ProverDb {
         &self,
         height_range: Range<BlockHeight>,
     ) -> impl Iterator<Item = Result<(BlockHeight, RollupInput)>> + '_ {
-        let mut read_opts = rocksdb::ReadOptions::default();
-
-        read_opts.set_iterate_lower_bound(
+        let range = KeyRange::new(
             Key::Rollup {
                 height: height_range.start,
             }
             .serialize(),
-        );
-        read_opts.set_iterate_upper_bound(
             Key::Rollup {
                 height: height_range.end,
             }
             .serialize(),
         );
 
-        let iter = self
-            .db
-            .iterator_opt(rocksdb::IteratorMode::Start, read_opts);
+        let iter = self.db.iter(range, Direction::Forward);
         iter.map(|r| {
             let (key, value) = r?;
 
-            let key = Key::deserialize(key.as_ref()).map_err(|_| Error::InvalidKey)?;
+            let key = Key::deserialize(&key).map_err(|_| Error::InvalidKey)?;
 
             let Key::Rollup { height } = key else {
                 return Err(Error::InvalidKey);
@@ -235,7 +245,18 @@ mod tests {
         let tmpdir = tempdir::TempDir::new("list_rollups").unwrap();
 
         let db = ProverDb::create_or_load(tmpdir.path()).unwrap();
+        check_list_rollups(&db);
+    }
+
+    #[test]
+    fn list_rollups_in_memory() {
+        let db = ProverDb::in_memory().unwrap();
+        assert_eq!(db.get_version().unwrap(), Some(LATEST_VERSION));
+
+        check_list_rollups(&db);
+    }
 
+    fn check_list_rollups(db: &ProverDb) {
         db.set_rollup(1.into(), RollupInput::default()).unwrap();
         db.set_rollup(2.into(), RollupInput::default()).unwrap();
 
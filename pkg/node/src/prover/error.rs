@@ -42,6 +42,9 @@ pub enum Error {
     #[error("prover error")]
     Prover(#[from] prover::Error),
 
+    #[error("proving job error")]
+    ProvingJob(#[from] super::jobs::JobError),
+
     #[error("smirk storage error")]
     SmirkStorage(#[from] smirk::storage::Error),
 
@@ -51,8 +54,8 @@ pub enum Error {
     #[error("io error")]
     Io(#[from] std::io::Error),
 
-    #[error("rocksdb error")]
-    RocksdbError(#[from] rocksdb::Error),
+    #[error("kv store error")]
+    KvStoreError(#[from] kv_store::Error),
 
     #[error("parse int error")]
     ParseIntError(#[from] std::num::ParseIntError),
@@ -60,6 +63,9 @@ pub enum Error {
     #[error("tokio mpsc send error")]
     TokioMpscError(#[from] tokio::sync::mpsc::error::SendError<BlockHeight>),
 
+    #[error("tokio task join error")]
+    TokioTaskJoin(#[from] tokio::task::JoinError),
+
     #[error("tokio-postgres error")]
     TokioPostgresError(#[from] tokio_postgres::Error),
 }
@@ -0,0 +1,504 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use barretenberg::{Prove, Verify};
+use borsh::{BorshDeserialize, BorshSerialize};
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+use zk_primitives::{AggAgg, AggAggProof, AggUtxo, AggUtxoProof};
+
+/// How often a waiting job checks whether its last lease has expired, at most
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An aggregation proof for a worker to prove
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub enum ProvingJob {
+    AggUtxo(AggUtxo),
+    AggAgg(AggAgg),
+}
+
+/// The proof of a [`ProvingJob`]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub enum ProvingOutput {
+    AggUtxo(AggUtxoProof),
+    AggAgg(AggAggProof),
+}
+
+impl ProvingJob {
+    /// Prove the job, blocks for the duration of the proof
+    pub fn prove(&self) -> Result<ProvingOutput, String> {
+        match self {
+            ProvingJob::AggUtxo(agg_utxo) => agg_utxo.prove().map(ProvingOutput::AggUtxo),
+            ProvingJob::AggAgg(agg_agg) => agg_agg.prove().map(ProvingOutput::AggAgg),
+        }
+        .map_err(|err| err.to_string())
+    }
+
+    /// Check `output` is a valid proof of this job, so a faulty worker can't get a proof of
+    /// other inputs rolled up
+    pub fn check(&self, output: &ProvingOutput) -> Result<(), JobError> {
+        let public_inputs_match = match (self, output) {
+            (ProvingJob::AggUtxo(agg_utxo), ProvingOutput::AggUtxo(proof)) => {
+                let public_inputs = &proof.public_inputs;
+                public_inputs.old_root == agg_utxo.old_root
+                    && public_inputs.new_root == agg_utxo.new_root
+                    && public_inputs.commit_hash == agg_utxo.commit_hash()
+                    && public_inputs.messages == agg_utxo.messages()
+            }
+            (ProvingJob::AggAgg(agg_agg), ProvingOutput::AggAgg(proof)) => {
+                let expected = agg_agg.public_inputs();
+                let public_inputs = &proof.public_inputs;
+                public_inputs.old_root == expected.old_root
+                    && public_inputs.new_root == expected.new_root
+                    && public_inputs.commit_hash == expected.commit_hash
+                    && public_inputs.messages == expected.messages
+            }
+            _ => false,
+        };
+
+        if !public_inputs_match {
+            return Err(JobError::PublicInputsMismatch);
+        }
+
+        match output {
+            ProvingOutput::AggUtxo(proof) => proof.verify(),
+            ProvingOutput::AggAgg(proof) => proof.verify(),
+        }
+        .map_err(|err| JobError::InvalidProof(err.to_string()))
+    }
+}
+
+/// A job leased to a worker, until the worker returns its outcome or the lease expires
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct LeasedJob {
+    pub job_id: u64,
+    pub lease_id: u64,
+    pub job: ProvingJob,
+}
+
+/// The outcome of a leased job, as returned by a worker
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct JobOutcome {
+    pub lease_id: u64,
+    /// The proof, or why the worker failed to prove the job
+    pub result: Result<ProvingOutput, String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error("job is not leased with this lease, it may have expired")]
+    LeaseNotFound,
+
+    #[error("proof public inputs do not match the job")]
+    PublicInputsMismatch,
+
+    #[error("invalid proof: {0}")]
+    InvalidProof(String),
+
+    #[error("job failed after {0} attempts")]
+    TooManyAttempts(u32),
+
+    #[error("job was not proved within {0:?}")]
+    Timeout(Duration),
+
+    #[error("job queue was dropped")]
+    QueueDropped,
+}
+
+struct QueuedJob {
+    job: Arc<ProvingJob>,
+    attempts: u32,
+    lease: Option<Lease>,
+    sender: oneshot::Sender<Result<ProvingOutput, JobError>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lease {
+    id: u64,
+    expires_at: Instant,
+}
+
+impl QueuedJob {
+    fn is_leasable(&self, now: Instant) -> bool {
+        self.lease.is_none_or(|lease| lease.expires_at <= now)
+    }
+
+    fn is_leased_with(&self, lease_id: u64) -> bool {
+        self.lease.is_some_and(|lease| lease.id == lease_id)
+    }
+
+    fn is_exhausted(&self, max_attempts: u32) -> bool {
+        self.attempts >= max_attempts
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_job_id: u64,
+    jobs: BTreeMap<u64, QueuedJob>,
+}
+
+/// Jobs waiting for remote workers to prove them
+///
+/// Workers lease jobs in the order they were queued. A lease that isn't completed within the
+/// lease timeout expires, and the job is leased to the next worker that asks. Jobs are failed
+/// once they have been leased `max_attempts` times without a valid proof, or if they aren't
+/// proved within `job_timeout`, whether or not any worker is asking for jobs
+pub struct JobQueue {
+    state: Mutex<QueueState>,
+    lease_timeout: Duration,
+    max_attempts: u32,
+    job_timeout: Duration,
+}
+
+impl JobQueue {
+    pub fn new(lease_timeout: Duration, max_attempts: u32, job_timeout: Duration) -> Self {
+        Self {
+            state: Mutex::new(QueueState::default()),
+            lease_timeout,
+            max_attempts,
+            job_timeout,
+        }
+    }
+
+    /// Queue a job and wait for a worker to prove it
+    ///
+    /// The job is removed from the queue if it times out, or if the returned future is dropped
+    pub async fn prove(&self, job: ProvingJob) -> Result<ProvingOutput, JobError> {
+        let (sender, mut receiver) = oneshot::channel();
+        let deadline = tokio::time::Instant::now() + self.job_timeout;
+
+        let job_id = {
+            let mut state = self.state.lock();
+            let job_id = state.next_job_id;
+            state.next_job_id += 1;
+            state.jobs.insert(
+                job_id,
+                QueuedJob {
+                    job: Arc::new(job),
+                    attempts: 0,
+                    lease: None,
+                    sender,
+                },
+            );
+            job_id
+        };
+
+        info!(
+            job_id,
+            counter.queued_proving_jobs = 1,
+            "Queued proving job"
+        );
+
+        scopeguard::defer! {
+            self.state.lock().jobs.remove(&job_id);
+        }
+
+        // Exhausted jobs are failed when their last lease expires, even if no worker asks for
+        // another job
+        let check_interval = self.lease_timeout.max(MIN_CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                result = &mut receiver => {
+                    return result.map_err(|_| JobError::QueueDropped)?;
+                }
+                _ = tokio::time::sleep_until(deadline) => {
+                    warn!(job_id, timeout = ?self.job_timeout, "Proving job timed out");
+                    return Err(JobError::Timeout(self.job_timeout));
+                }
+                _ = tokio::time::sleep(check_interval) => {
+                    self.fail_if_exhausted(job_id);
+                }
+            }
+        }
+    }
+
+    /// Queue an `AggUtxo` job and wait for its proof
+    pub async fn prove_agg_utxo(&self, agg_utxo: AggUtxo) -> Result<AggUtxoProof, JobError> {
+        match self.prove(ProvingJob::AggUtxo(agg_utxo)).await? {
+            ProvingOutput::AggUtxo(proof) => Ok(proof),
+            ProvingOutput::AggAgg(_) => Err(JobError::PublicInputsMismatch),
+        }
+    }
+
+    /// Queue an `AggAgg` job and wait for its proof
+    pub async fn prove_agg_agg(&self, agg_agg: AggAgg) -> Result<AggAggProof, JobError> {
+        match self.prove(ProvingJob::AggAgg(agg_agg)).await? {
+            ProvingOutput::AggAgg(proof) => Ok(proof),
+            ProvingOutput::AggUtxo(_) => Err(JobError::PublicInputsMismatch),
+        }
+    }
+
+    /// Lease the oldest job that isn't leased, or whose lease has expired, and that hasn't been
+    /// leased `max_attempts` times
+    pub fn lease(&self) -> Option<LeasedJob> {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+
+        let (&job_id, job) = state
+            .jobs
+            .iter_mut()
+            .find(|(_, job)| !job.is_exhausted(self.max_attempts) && job.is_leasable(now))?;
+
+        let lease_id = rand::random();
+        job.attempts += 1;
+        job.lease = Some(Lease {
+            id: lease_id,
+            expires_at: now + self.lease_timeout,
+        });
+
+        info!(job_id, attempt = job.attempts, "Leased proving job");
+
+        Some(LeasedJob {
+            job_id,
+            lease_id,
+            job: ProvingJob::clone(&job.job),
+        })
+    }
+
+    /// Complete a job with the outcome returned by a worker
+    ///
+    /// Proofs are checked against the job before they are accepted. If the worker failed, or
+    /// the proof is invalid, the lease is released so another worker can retry the job
+    pub async fn submit(&self, job_id: u64, outcome: JobOutcome) -> Result<(), JobError> {
+        let job = self.leased_job(job_id, outcome.lease_id)?;
+
+        let output = match outcome.result {
+            Ok(output) => output,
+            Err(err) => {
+                warn!(job_id, %err, "Worker failed to prove job");
+                return self.release(job_id, outcome.lease_id);
+            }
+        };
+
+        let checked = tokio::task::spawn_blocking({
+            let output = output.clone();
+            move || job.check(&output)
+        })
+        .await
+        .unwrap_or_else(|err| Err(JobError::InvalidProof(err.to_string())));
+
+        if let Err(err) = checked {
+            warn!(job_id, ?err, "Worker returned an invalid proof");
+            self.release(job_id, outcome.lease_id)?;
+            return Err(err);
+        }
+
+        let mut state = self.state.lock();
+        match state.jobs.get(&job_id) {
+            Some(job) if job.is_leased_with(outcome.lease_id) => {}
+            _ => return Err(JobError::LeaseNotFound),
+        }
+
+        if let Some(job) = state.jobs.remove(&job_id) {
+            info!(job_id, attempt = job.attempts, "Completed proving job");
+            let _ = job.sender.send(Ok(output));
+        }
+
+        Ok(())
+    }
+
+    /// The job leased with `lease_id`
+    fn leased_job(&self, job_id: u64, lease_id: u64) -> Result<Arc<ProvingJob>, JobError> {
+        match self.state.lock().jobs.get(&job_id) {
+            Some(job) if job.is_leased_with(lease_id) => Ok(Arc::clone(&job.job)),
+            _ => Err(JobError::LeaseNotFound),
+        }
+    }
+
+    /// Make a leased job available to other workers, or fail it if it has no attempts left
+    fn release(&self, job_id: u64, lease_id: u64) -> Result<(), JobError> {
+        let mut state = self.state.lock();
+        match state.jobs.get_mut(&job_id) {
+            Some(job) if job.is_leased_with(lease_id) => {
+                if job.is_exhausted(self.max_attempts) {
+                    Self::fail(&mut state, job_id);
+                } else {
+                    job.lease = None;
+                }
+                Ok(())
+            }
+            _ => Err(JobError::LeaseNotFound),
+        }
+    }
+
+    /// Fail a job whose last attempt's lease has expired
+    fn fail_if_exhausted(&self, job_id: u64) {
+        let mut state = self.state.lock();
+        let expired = state.jobs.get(&job_id).is_some_and(|job| {
+            job.is_exhausted(self.max_attempts) && job.is_leasable(Instant::now())
+        });
+        if expired {
+            Self::fail(&mut state, job_id);
+        }
+    }
+
+    fn fail(state: &mut QueueState, job_id: u64) {
+        if let Some(job) = state.jobs.remove(&job_id) {
+            warn!(job_id, attempts = job.attempts, "Proving job failed");
+            let _ = job
+                .sender
+                .send(Err(JobError::TooManyAttempts(job.attempts)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job() -> ProvingJob {
+        ProvingJob::AggAgg(AggAgg::new([
+            AggUtxoProof::default(),
+            AggUtxoProof::default(),
+        ]))
+    }
+
+    fn queue_job(
+        queue: &Arc<JobQueue>,
+    ) -> tokio::task::JoinHandle<Result<ProvingOutput, JobError>> {
+        let queue = Arc::clone(queue);
+        tokio::spawn(async move { queue.prove(job()).await })
+    }
+
+    async fn wait_for_lease(queue: &JobQueue) -> LeasedJob {
+        loop {
+            if let Some(leased) = queue.lease() {
+                return leased;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn jobs_are_leased_once() {
+        let queue = Arc::new(JobQueue::new(
+            Duration::from_secs(60),
+            3,
+            Duration::from_secs(60),
+        ));
+        let _first = queue_job(&queue);
+        let leased = wait_for_lease(&queue).await;
+
+        assert_eq!(leased.job_id, 0);
+        assert!(queue.lease().is_none());
+    }
+
+    #[tokio::test]
+    async fn expired_leases_are_leased_again() {
+        let queue = Arc::new(JobQueue::new(Duration::ZERO, 3, Duration::from_secs(60)));
+        let _first = queue_job(&queue);
+        let first_lease = wait_for_lease(&queue).await;
+        let second_lease = queue.lease().unwrap();
+
+        assert_eq!(first_lease.job_id, second_lease.job_id);
+        assert_ne!(first_lease.lease_id, second_lease.lease_id);
+
+        // The first worker's lease is stale
+        let outcome = JobOutcome {
+            lease_id: first_lease.lease_id,
+            result: Err("too slow".to_owned()),
+        };
+        assert!(matches!(
+            queue.submit(first_lease.job_id, outcome).await,
+            Err(JobError::LeaseNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_jobs_are_retried_until_max_attempts() {
+        let queue = Arc::new(JobQueue::new(
+            Duration::from_secs(60),
+            2,
+            Duration::from_secs(60),
+        ));
+        let handle = queue_job(&queue);
+
+        for _ in 0..2 {
+            let leased = wait_for_lease(&queue).await;
+            let outcome = JobOutcome {
+                lease_id: leased.lease_id,
+                result: Err("out of memory".to_owned()),
+            };
+            queue.submit(leased.job_id, outcome).await.unwrap();
+        }
+
+        assert!(queue.lease().is_none());
+        assert!(matches!(
+            handle.await.unwrap(),
+            Err(JobError::TooManyAttempts(2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn proofs_for_other_inputs_are_rejected() {
+        let queue = Arc::new(JobQueue::new(
+            Duration::from_secs(60),
+            3,
+            Duration::from_secs(60),
+        ));
+        let _first = queue_job(&queue);
+        let leased = wait_for_lease(&queue).await;
+
+        let outcome = JobOutcome {
+            lease_id: leased.lease_id,
+            result: Ok(ProvingOutput::AggUtxo(AggUtxoProof::default())),
+        };
+        assert!(matches!(
+            queue.submit(leased.job_id, outcome).await,
+            Err(JobError::PublicInputsMismatch)
+        ));
+
+        // The job is released for another worker
+        assert_eq!(queue.lease().unwrap().job_id, leased.job_id);
+    }
+
+    #[tokio::test]
+    async fn expired_last_attempts_fail_without_another_lease() {
+        let queue = Arc::new(JobQueue::new(Duration::ZERO, 1, Duration::from_secs(60)));
+        let handle = queue_job(&queue);
+        wait_for_lease(&queue).await;
+
+        assert!(matches!(
+            handle.await.unwrap(),
+            Err(JobError::TooManyAttempts(1))
+        ));
+        assert!(queue.state.lock().jobs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn jobs_time_out() {
+        let queue = Arc::new(JobQueue::new(
+            Duration::from_secs(60),
+            3,
+            Duration::from_millis(10),
+        ));
+
+        assert!(matches!(
+            queue_job(&queue).await.unwrap(),
+            Err(JobError::Timeout(_))
+        ));
+        assert!(queue.lease().is_none());
+    }
+
+    #[test]
+    fn leased_job_roundtrips_through_borsh() {
+        let leased = LeasedJob {
+            job_id: 1,
+            lease_id: 2,
+            job: job(),
+        };
+
+        let bytes = borsh::to_vec(&leased).unwrap();
+        let decoded = borsh::from_slice::<LeasedJob>(&bytes).unwrap();
+
+        assert_eq!(decoded.job_id, 1);
+        assert_eq!(decoded.lease_id, 2);
+        assert!(matches!(decoded.job, ProvingJob::AggAgg(_)));
+    }
+}
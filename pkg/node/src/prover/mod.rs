@@ -1,5 +1,7 @@
 pub(crate) mod db;
 mod error;
+pub mod jobs;
+pub mod remote;
 pub mod worker;
 
 use error::Result;
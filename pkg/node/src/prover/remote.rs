@@ -0,0 +1,253 @@
+use std::{net::ToSocketAddrs, sync::Arc, time::Duration, time::Instant};
+
+use actix_server::Server;
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, http::header, web};
+use reqwest::StatusCode;
+use tracing::{info, warn};
+
+use super::jobs::{JobError, JobOutcome, JobQueue, LeasedJob};
+use crate::util::constant_time_eq;
+
+/// Jobs and proofs hold every utxo proof and merkle path of an aggregation
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+struct CoordinatorState {
+    jobs: Arc<JobQueue>,
+    token: Option<String>,
+}
+
+impl CoordinatorState {
+    fn is_authorized(&self, req: &HttpRequest) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|provided| constant_time_eq(provided.as_bytes(), token.as_bytes()))
+    }
+}
+
+/// Whether every address `laddr` resolves to is a loopback address
+fn is_loopback(laddr: &str) -> std::io::Result<bool> {
+    let mut addrs = laddr.to_socket_addrs()?.peekable();
+    Ok(addrs.peek().is_some() && addrs.all(|addr| addr.ip().is_loopback()))
+}
+
+/// Serve proving jobs to remote workers
+///
+/// - `POST /jobs/lease` leases the next job, as a borsh [`LeasedJob`], or `204` if there is none
+/// - `POST /jobs/{job_id}/outcome` completes a job with a borsh [`JobOutcome`]
+///
+/// A `token` is required unless `laddr` is a loopback address
+#[tracing::instrument(err, skip(jobs, token))]
+pub fn create_coordinator_server(
+    laddr: &str,
+    jobs: Arc<JobQueue>,
+    token: Option<String>,
+) -> Result<Server, std::io::Error> {
+    if token.is_none() && !is_loopback(laddr)? {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "prover-worker-token is required when prover-coordinator-laddr isn't a loopback address",
+        ));
+    }
+
+    let state = web::Data::new(CoordinatorState { jobs, token });
+
+    Ok(HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .app_data(web::PayloadConfig::new(MAX_BODY_SIZE))
+            .route("/jobs/lease", web::post().to(lease_job))
+            .route("/jobs/{job_id}/outcome", web::post().to(submit_outcome))
+    })
+    .bind(laddr)?
+    .run())
+}
+
+async fn lease_job(state: web::Data<CoordinatorState>, req: HttpRequest) -> HttpResponse {
+    if !state.is_authorized(&req) {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    let Some(leased) = state.jobs.lease() else {
+        return HttpResponse::NoContent().finish();
+    };
+
+    match borsh::to_vec(&leased) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .body(body),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+async fn submit_outcome(
+    state: web::Data<CoordinatorState>,
+    req: HttpRequest,
+    job_id: web::Path<u64>,
+    body: web::Bytes,
+) -> HttpResponse {
+    if !state.is_authorized(&req) {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    let outcome = match borsh::from_slice::<JobOutcome>(&body) {
+        Ok(outcome) => outcome,
+        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+    };
+
+    match state.jobs.submit(job_id.into_inner(), outcome).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(err @ JobError::LeaseNotFound) => HttpResponse::Conflict().body(err.to_string()),
+        Err(err) => HttpResponse::UnprocessableEntity().body(err.to_string()),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkerError {
+    #[error("reqwest error")]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+
+    #[error("coordinator responded with {status}: {body}")]
+    Status { status: StatusCode, body: String },
+}
+
+/// HTTP client for a prover node's coordinator, see [`create_coordinator_server`]
+#[derive(Debug, Clone)]
+pub struct WorkerClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl WorkerClient {
+    pub fn new(base_url: &str, token: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            token,
+        }
+    }
+
+    /// Lease the next job, `None` if there are no jobs waiting
+    pub async fn lease(&self) -> Result<Option<LeasedJob>, WorkerError> {
+        let response = self.post("/jobs/lease", Vec::new()).await?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+
+        let body = response.bytes().await?;
+        Ok(Some(borsh::from_slice(&body)?))
+    }
+
+    /// Return the outcome of a leased job
+    pub async fn submit(&self, job_id: u64, outcome: &JobOutcome) -> Result<(), WorkerError> {
+        self.post(&format!("/jobs/{job_id}/outcome"), borsh::to_vec(outcome)?)
+            .await?;
+        Ok(())
+    }
+
+    async fn post(&self, path: &str, body: Vec<u8>) -> Result<reqwest::Response, WorkerError> {
+        let mut request = self
+            .http
+            .post(format!("{}{path}", self.base_url))
+            .header("content-type", "application/octet-stream")
+            .body(body);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(WorkerError::Status { status, body });
+        }
+
+        Ok(response)
+    }
+}
+
+/// Lease, prove and return jobs until the process is stopped
+///
+/// Errors talking to the coordinator are logged and retried after `poll_interval`. A job whose
+/// outcome can't be returned is leased to another worker once its lease expires
+pub async fn run_remote_worker(client: WorkerClient, poll_interval: Duration) {
+    loop {
+        let leased = match client.lease().await {
+            Ok(Some(leased)) => leased,
+            Ok(None) => {
+                tokio::time::sleep(poll_interval).await;
+                continue;
+            }
+            Err(err) => {
+                warn!(?err, "Failed to lease a proving job");
+                tokio::time::sleep(poll_interval).await;
+                continue;
+            }
+        };
+
+        let LeasedJob {
+            job_id,
+            lease_id,
+            job,
+        } = leased;
+
+        info!(job_id, "Proving job");
+        let start = Instant::now();
+        let result = tokio::task::spawn_blocking(move || job.prove())
+            .await
+            .unwrap_or_else(|err| Err(err.to_string()));
+        info!(
+            job_id,
+            histogram.remote_prove_ms = start.elapsed().as_millis() as u64,
+            ok = result.is_ok(),
+            "Proved job"
+        );
+
+        let outcome = JobOutcome { lease_id, result };
+        if let Err(err) = client.submit(job_id, &outcome).await {
+            warn!(
+                job_id,
+                ?err,
+                "Failed to return the outcome of a proving job"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_compared_exactly() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(!constant_time_eq(b"", b"token"));
+    }
+
+    #[test]
+    fn public_coordinators_require_a_token() {
+        let jobs = Arc::new(JobQueue::new(
+            Duration::from_secs(60),
+            3,
+            Duration::from_secs(60),
+        ));
+
+        let err = create_coordinator_server("0.0.0.0:0", Arc::clone(&jobs), None).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        assert!(is_loopback("127.0.0.1:0").unwrap());
+        assert!(is_loopback("[::1]:0").unwrap());
+        assert!(!is_loopback("0.0.0.0:0").unwrap());
+    }
+}
@@ -4,24 +4,31 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::jobs::JobQueue;
+use super::remote::create_coordinator_server;
 use super::{Error, Result};
-use crate::config::Config;
+use crate::backup::ProverBackupSource;
+use crate::block::{Block, BlockState};
+use crate::config::{Config, StorageBackend};
 use crate::constants::MERKLE_TREE_DEPTH;
 use crate::prover::db::{LastSeenBlock, ProverDb};
 use crate::types::BlockHeight;
-use crate::{Mode, NodeShared, PersistentMerkleTree};
+use crate::{MemoryMerkleTree, Mode, NodeShared, NotesTreeCache, PersistentMerkleTree};
+use barretenberg::Prove;
 use contracts::RollupContract;
 use either::Either;
 use element::Element;
 use futures::StreamExt;
+use futures::future::LocalBoxFuture;
 use prover::{MAXIMUM_TXNS, RollupInput};
 use prover::{Prover, Transaction};
 use prover::{RollupInput, MAXIMUM_TXNS};
 use scopeguard::ScopeGuard;
 use smirk::empty_tree_hash;
-use tokio::sync::{Mutex, Notify, mpsc};
+use tokio::sync::{Mutex, Notify, Semaphore, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, info};
-use zk_primitives::{AggAggProof, UtxoKind};
+use zk_primitives::{AggAgg, AggAggProof, AggUtxo, AggUtxoProof, UtxoKind};
 
 pub async fn run_prover(config: &Config, node: Arc<NodeShared>) -> Result<()> {
     let (client, postgres_future) = if let Some(url) = &config.prover_database_url {
@@ -56,25 +63,64 @@ pub async fn run_prover(config: &Config, node: Arc<NodeShared>) -> Result<()> {
     )
     .await?;
 
+    let in_memory = config.storage == StorageBackend::Memory;
+
     let db_path = config.db_path.join("prover");
-    let prover_state_db = Arc::new(ProverDb::create_or_load(&db_path)?);
+    let prover_state_db = Arc::new(match in_memory {
+        true => ProverDb::in_memory()?,
+        false => ProverDb::create_or_load(&db_path)?,
+    });
     let prover = Arc::new(Prover::new(contract.clone()));
 
     let smirk_path = config.smirk_path.join("prover");
-    let notes_tree = Arc::new(Mutex::new(Some(PersistentMerkleTree::load(&smirk_path)?)));
+    let cache = NotesTreeCache::from_config(config);
+    let notes_tree = match in_memory {
+        true => PersistentMerkleTree::new_in_memory_with_cache(cache),
+        false => PersistentMerkleTree::load_with_cache(&smirk_path, cache)?,
+    };
+    let notes_tree = Arc::new(Mutex::new(Some(notes_tree)));
     let delete_smirk = || {
         let notes_tree = Arc::clone(&notes_tree);
         let smirk_path = smirk_path.clone();
         move || async move {
             *notes_tree.lock().await = None;
-            std::fs::remove_dir_all(&smirk_path)?;
+            if !in_memory {
+                std::fs::remove_dir_all(&smirk_path)?;
+            }
             Ok(())
         }
     };
     let prover_worker_delete_smirk = delete_smirk();
 
+    node.register_prover_backup_source(ProverBackupSource {
+        db: Arc::clone(&prover_state_db),
+        notes_tree: Arc::clone(&notes_tree),
+    });
+
     let proof_notifier = Arc::new(Notify::new());
 
+    let (aggregation_prover, coordinator_future) = match &config.prover_coordinator_laddr {
+        Some(laddr) => {
+            let jobs = Arc::new(JobQueue::new(
+                Duration::from_secs(config.prover_job_lease_secs),
+                config.prover_job_max_attempts,
+                Duration::from_secs(config.prover_job_timeout_secs),
+            ));
+            let server = create_coordinator_server(
+                laddr,
+                Arc::clone(&jobs),
+                config.prover_worker_token.clone(),
+            )?;
+            info!(%laddr, "Serving proving jobs to remote workers");
+
+            (AggregationProver::Remote(jobs), Either::Left(server))
+        }
+        None => (
+            AggregationProver::Local(Arc::new(Semaphore::new(config.prover_parallelism.max(1)))),
+            Either::Right(futures::future::pending::<std::io::Result<()>>()),
+        ),
+    };
+
     match prover_state_db.get_version()? {
         None => {
             let contract_height = contract.block_height().await?;
@@ -111,6 +157,7 @@ pub async fn run_prover(config: &Config, node: Arc<NodeShared>) -> Result<()> {
             prover_worker_delete_smirk,
             Arc::clone(&prover),
             Arc::clone(&proof_notifier),
+            aggregation_prover,
         ),
         run_rollup_worker(
             Duration::from_millis(config.rollup_wait_time_ms),
@@ -124,6 +171,10 @@ pub async fn run_prover(config: &Config, node: Arc<NodeShared>) -> Result<()> {
         async move {
             postgres_future.await?;
             Ok(())
+        },
+        async move {
+            coordinator_future.await?;
+            Ok(())
         }
     )?;
 
@@ -141,6 +192,7 @@ async fn run_prover_worker<Fut>(
     delete_smirk: impl FnOnce() -> Fut,
     prover: Arc<Prover>,
     proof_notifier: Arc<Notify>,
+    aggregation_prover: AggregationProver,
 ) -> Result<()>
 where
     Fut: Future<Output = Result<()>>,
@@ -186,157 +238,252 @@ where
     let height = last_seen_block.height + BlockHeight(1);
     let mut stream = node.commit_stream(Some(height)).await.peekable();
 
-    while let Some(commit) = stream.next().await {
-        let commit = commit?;
-        let commit_height = commit.content.header.height;
-        if commit.content.state.txns.is_empty() {
-            prover_state_db.set_last_seen_block(LastSeenBlock {
-                height: commit_height,
-                root_hash: commit.content.state.root_hash,
-            })?;
-            continue;
-        }
+    // Proof inputs are computed from a copy of the notes tree that runs ahead of it, so blocks
+    // are proved while earlier blocks are still being proved. The notes tree and last seen block
+    // only advance in height order, once a block's proof is saved
+    let mut lookahead_tree = notes_tree.lock().await.as_ref().unwrap().tree().clone();
+    let (commits_sender, commits_receiver) = mpsc::channel::<PendingCommit>(1);
+
+    let prepare_commits = async {
+        while let Some(commit) = stream.next().await {
+            let commit = commit?;
+            let commit_height = commit.content.header.height;
+            if commit.content.state.txns.is_empty() {
+                send_pending_commit(&commits_sender, ProvedCommit::skipped(commit)).await;
+                continue;
+            }
 
-        let commit_was_already_rolled_up = initial_contract_block_height >= commit_height;
-        let mut release_lock = Option::<ScopeGuard<(), _>>::None;
-        let postgres_db_clone = postgres_db.clone();
-        let we_are_the_prover_for_this_block = || async {
-            let Some(postgres_db_clone) = postgres_db_clone else {
-                return Ok(true);
-            };
+            let commit_was_already_rolled_up = initial_contract_block_height >= commit_height;
+            let mut release_lock = Option::<ReleaseLock>::None;
+            let postgres_db_clone = postgres_db.clone();
+            let we_are_the_prover_for_this_block = || async {
+                let Some(postgres_db_clone) = postgres_db_clone else {
+                    return Ok(true);
+                };
 
-            let rows = postgres_db_clone
-                .query(
-                    "WITH height_if_no_proof AS (
-                        SELECT CASE
-                            WHEN EXISTS (SELECT 1 FROM rollup_proofs WHERE height = $1) THEN NULL
-                            ELSE $1
-                        END AS height
-                    ) SELECT pg_try_advisory_lock((SELECT height::bigint FROM height_if_no_proof))",
-                    &[&(commit_height.0 as i64)],
-                )
-                .await?;
-            match rows.first().unwrap().get::<_, Option<bool>>(0) {
-                // We acquired the lock
-                Some(true) => {
-                    // release it after the block is processed
-                    release_lock.replace(scopeguard::guard((), |_| {
-                        tokio::spawn(async move {
-                            postgres_db_clone
-                                .execute(
-                                    "SELECT pg_advisory_unlock($1)",
-                                    &[&(commit_height.0 as i64)],
-                                )
-                                .await
-                                .unwrap();
+                let rows = postgres_db_clone
+                    .query(
+                        "WITH height_if_no_proof AS (
+                            SELECT CASE
+                                WHEN EXISTS (SELECT 1 FROM rollup_proofs WHERE height = $1) THEN NULL
+                                ELSE $1
+                            END AS height
+                        ) SELECT pg_try_advisory_lock((SELECT height::bigint FROM height_if_no_proof))",
+                        &[&(commit_height.0 as i64)],
+                    )
+                    .await?;
+                match rows.first().unwrap().get::<_, Option<bool>>(0) {
+                    // We acquired the lock
+                    Some(true) => {
+                        // release it after the block is processed
+                        let release: Box<dyn FnOnce(())> = Box::new(move |_| {
+                            tokio::spawn(async move {
+                                postgres_db_clone
+                                    .execute(
+                                        "SELECT pg_advisory_unlock($1)",
+                                        &[&(commit_height.0 as i64)],
+                                    )
+                                    .await
+                                    .unwrap();
+                            });
                         });
-                    }));
+                        release_lock.replace(scopeguard::guard((), release));
 
-                    Ok::<bool, tokio_postgres::Error>(true)
+                        Ok::<bool, tokio_postgres::Error>(true)
+                    }
+                    // Someone else is proving this block
+                    Some(false) => Ok(false),
+                    // There already is a proof for this block
+                    None => Ok(false),
                 }
-                // Someone else is proving this block
-                Some(false) => Ok(false),
-                // There already is a proof for this block
-                None => Ok(false),
+            };
+            let is_a_bad_block = config.bad_blocks.contains(&commit.content.header.height);
+            if commit_was_already_rolled_up
+                || is_a_bad_block
+                || !we_are_the_prover_for_this_block().await?
+            {
+                apply_block_to_lookahead(
+                    &mut lookahead_tree,
+                    &commit.content.state,
+                    commit_height,
+                )?;
+                send_pending_commit(&commits_sender, ProvedCommit::skipped(commit)).await;
+                continue;
             }
-        };
-        let is_a_bad_block = config.bad_blocks.contains(&commit.content.header.height);
-        if commit_was_already_rolled_up
-            || is_a_bad_block
-            || !we_are_the_prover_for_this_block().await?
-        {
-            NodeShared::apply_block_to_tree(
-                notes_tree.lock().await.as_mut().unwrap(),
-                &commit.content.state,
-                commit.content.header.height,
-            )?;
-            prover_state_db.set_last_seen_block(LastSeenBlock {
-                height: commit_height,
-                root_hash: commit.content.state.root_hash,
-            })?;
-            continue;
-        }
 
-        tracing::info!(?commit, "Proving commit");
-        tracing::info!(counter.proving_height = ?commit.content.header.height);
-        let prover = Arc::clone(&prover);
+            tracing::info!(?commit, "Proving commit");
+            tracing::info!(counter.proving_height = ?commit.content.header.height);
 
-        let mut txns = commit
-            .content
-            .state
-            .txns
-            .iter()
-            .map(|utxo_proof| Ok(Some(Transaction::new(utxo_proof.clone()))))
-            .collect::<Result<Vec<_>>>()?;
+            let mut txns = commit
+                .content
+                .state
+                .txns
+                .iter()
+                .map(|utxo_proof| Ok(Some(Transaction::new(utxo_proof.clone()))))
+                .collect::<Result<Vec<_>>>()?;
 
-        while txns.len() < MAXIMUM_TXNS {
-            txns.push(None);
-        }
-
-        let other_hash = *commit.content.header_hash().inner();
+            while txns.len() < MAXIMUM_TXNS {
+                txns.push(None);
+            }
 
-        let next_commit = Pin::new(&mut stream)
-            .peek()
-            .await
-            .unwrap()
-            .as_ref()
-            .map_err(|_| Error::FailedToPeekNextCommit)?;
+            let other_hash = *commit.content.header_hash().inner();
 
-        let signatures = next_commit.content.header.approvals.clone();
+            let next_commit = Pin::new(&mut stream)
+                .peek()
+                .await
+                .unwrap()
+                .as_ref()
+                .map_err(|_| Error::FailedToPeekNextCommit)?;
+
+            let signatures = next_commit.content.header.approvals.clone();
+
+            let old_root = lookahead_tree.root_hash();
+            let proof: LocalBoxFuture<'static, Result<AggAggProof>> = match config.mode {
+                Mode::MockProver => {
+                    apply_block_to_lookahead(
+                        &mut lookahead_tree,
+                        &commit.content.state,
+                        commit_height,
+                    )?;
+
+                    let mut agg_agg_proof = AggAggProof::default();
+                    agg_agg_proof.public_inputs.old_root = old_root;
+                    agg_agg_proof.public_inputs.new_root = commit.content.state.root_hash;
+
+                    let mut messages = [Element::ZERO; 30];
+                    let mut index = 0;
+
+                    for proof in commit.content.state.txns.iter() {
+                        let txn_messages = proof.messages();
+                        let proof_messages = match proof.kind() {
+                            UtxoKind::Null | UtxoKind::Send => &[][..],
+                            UtxoKind::Mint => &txn_messages[..4],
+                            UtxoKind::Burn => &txn_messages[..],
+                        };
+
+                        for &message in proof_messages {
+                            messages[index] = message;
+                            index += 1;
+                        }
+                    }
 
-        let mut notes_tree = notes_tree.lock().await;
-        let notes_tree = notes_tree.as_mut().unwrap();
+                    agg_agg_proof.public_inputs.messages = messages.into_iter().collect::<Vec<_>>();
 
-        let proof = match config.mode {
-            Mode::MockProver => {
-                let mut agg_agg_proof = AggAggProof::default();
-                agg_agg_proof.public_inputs.old_root = notes_tree.tree().root_hash();
-                agg_agg_proof.public_inputs.new_root = commit.content.state.root_hash;
-
-                let mut messages = [Element::ZERO; 30];
-                let mut index = 0;
-
-                for proof in commit.content.state.txns.iter() {
-                    let proof_messages = match proof.kind() {
-                        UtxoKind::Null | UtxoKind::Send => &[][..],
-                        UtxoKind::Mint => &proof.public_inputs.messages[..4],
-                        UtxoKind::Burn => &proof.public_inputs.messages[..],
-                    };
-
-                    for &message in proof_messages {
-                        messages[index] = message;
-                        index += 1;
-                    }
+                    Box::pin(futures::future::ready(Ok(agg_agg_proof)))
                 }
+                _ => {
+                    let mut tree = std::mem::take(&mut lookahead_tree);
+                    let (tree, inputs) = tokio::task::spawn_blocking({
+                        let prover = Arc::clone(&prover);
+                        let txns = txns.try_into().unwrap();
+                        move || {
+                            let inputs =
+                                prover.next_aggregation_inputs(&mut tree, commit_height.0, txns);
+                            (tree, inputs)
+                        }
+                    })
+                    .await?;
+                    lookahead_tree = tree;
+                    let inputs = inputs?;
 
-                agg_agg_proof.public_inputs.messages = messages.into_iter().collect::<Vec<_>>();
+                    let aggregation_prover = aggregation_prover.clone();
+                    Box::pin(async move { aggregation_prover.prove(inputs).await })
+                }
+            };
 
-                agg_agg_proof
-            }
-            _ => {
-                prover
-                    .prove(notes_tree.tree(), commit_height.0, txns.try_into().unwrap())
-                    .await?
+            if commit.content.state.root_hash != lookahead_tree.root_hash() {
+                // Something went very wrong and our tree doesn't match the blockchain state
+                return Err(Error::ProverTreeRootDoesNotMatchBlockStateRoot {
+                    prover_tree: lookahead_tree.root_hash(),
+                    block_tree: commit.content.state.root_hash,
+                });
             }
-        };
 
-        if proof.public_inputs.new_root != commit.content.state.root_hash {
-            return Err(Error::RootMismatch {
-                got: proof.public_inputs.new_root,
-                expected: commit.content.state.root_hash,
-            });
+            send_pending_commit(
+                &commits_sender,
+                Box::pin(async move {
+                    let proof = proof.await?;
+
+                    if proof.public_inputs.new_root != commit.content.state.root_hash {
+                        return Err(Error::RootMismatch {
+                            got: proof.public_inputs.new_root,
+                            expected: commit.content.state.root_hash,
+                        });
+                    }
+
+                    let rollup_input =
+                        RollupInput::new(proof, commit_height.0, other_hash, signatures);
+
+                    Ok::<_, Error>(ProvedCommit {
+                        commit,
+                        rollup_input: Some(rollup_input),
+                        _release_lock: release_lock,
+                    })
+                }),
+            )
+            .await;
+        }
+
+        drop(commits_sender);
+        Ok::<_, Error>(())
+    };
+
+    let save_commits = save_proved_commits(
+        commits_receiver,
+        config.prover_pipeline_depth,
+        &prover_state_db,
+        &notes_tree,
+        postgres_db.as_deref(),
+        &proof_notifier,
+    );
+
+    tokio::try_join!(prepare_commits, save_commits)?;
+
+    unreachable!()
+}
+
+/// Save proofs and apply their blocks to the notes tree in height order, as they are proved
+///
+/// Up to `pipeline_depth` blocks are proved at once. The last seen block only advances once a
+/// block and every block before it are proved, so a failed proof is retried after a restart
+async fn save_proved_commits(
+    commits: mpsc::Receiver<PendingCommit>,
+    pipeline_depth: usize,
+    prover_state_db: &ProverDb,
+    notes_tree: &Mutex<Option<PersistentMerkleTree>>,
+    postgres_db: Option<&tokio_postgres::Client>,
+    proof_notifier: &Notify,
+) -> Result<()> {
+    let mut proved_commits = ReceiverStream::new(commits).buffered(pipeline_depth.max(1));
+
+    while let Some(proved_commit) = proved_commits.next().await {
+        let ProvedCommit {
+            commit,
+            rollup_input,
+            _release_lock,
+        } = proved_commit?;
+        let commit_height = commit.content.header.height;
+
+        if let Some(rollup_input) = &rollup_input {
+            prover_state_db.set_rollup(commit_height, rollup_input.clone())?;
         }
 
-        let rollup_input = RollupInput::new(proof, commit_height.0, other_hash, signatures);
-        prover_state_db.set_rollup(commit_height, rollup_input.clone())?;
+        let mut notes_tree = notes_tree.lock().await;
+        let notes_tree = notes_tree.as_mut().unwrap();
 
-        NodeShared::apply_block_to_tree(notes_tree, &commit.content.state, commit_height)?;
+        if !commit.content.state.txns.is_empty() {
+            NodeShared::apply_block_to_tree(notes_tree, &commit.content.state, commit_height)?;
+        }
         prover_state_db.set_last_seen_block(LastSeenBlock {
             height: commit_height,
             root_hash: commit.content.state.root_hash,
         })?;
 
-        if let Some(postgres_db) = postgres_db.as_ref() {
+        let Some(rollup_input) = rollup_input else {
+            continue;
+        };
+
+        if let Some(postgres_db) = postgres_db {
             #[allow(clippy::disallowed_methods)]
             postgres_db
                 .execute(
@@ -364,7 +511,98 @@ where
         tracing::info!(counter.proved_height = ?commit.content.header.height);
     }
 
-    unreachable!()
+    Ok(())
+}
+
+/// Releases the postgres lock on a block's height when dropped
+type ReleaseLock = ScopeGuard<(), Box<dyn FnOnce(())>>;
+
+/// A block's proof, resolved in [`run_prover_worker`]'s pipeline
+type PendingCommit = LocalBoxFuture<'static, Result<ProvedCommit>>;
+
+/// A committed block, ready to be applied to the prover's notes tree
+struct ProvedCommit {
+    commit: Arc<Block>,
+    /// `None` if the block isn't proved by us
+    rollup_input: Option<RollupInput>,
+    /// Held until the block's proof is saved
+    _release_lock: Option<ReleaseLock>,
+}
+
+impl ProvedCommit {
+    fn skipped(commit: Arc<Block>) -> PendingCommit {
+        Box::pin(futures::future::ready(Ok(ProvedCommit {
+            commit,
+            rollup_input: None,
+            _release_lock: None,
+        })))
+    }
+}
+
+async fn send_pending_commit(sender: &mpsc::Sender<PendingCommit>, commit: PendingCommit) {
+    // The receiver only stops if saving a commit failed, which fails the worker
+    let _ = sender.send(commit).await;
+}
+
+fn apply_block_to_lookahead(
+    lookahead_tree: &mut MemoryMerkleTree,
+    state: &BlockState,
+    height: BlockHeight,
+) -> Result<()> {
+    let batch = NodeShared::block_batch(state, lookahead_tree.root_hash(), height)?;
+    lookahead_tree.insert_batch(batch, |_| {}, |_| {})?;
+    Ok(())
+}
+
+/// Proves a block's aggregations, in-process or with remote workers
+#[derive(Clone)]
+enum AggregationProver {
+    /// Proves on the blocking thread pool, up to the number of permits at a time
+    Local(Arc<Semaphore>),
+    Remote(Arc<JobQueue>),
+}
+
+impl AggregationProver {
+    /// Prove the `AggUtxo`s concurrently, then the `AggAgg`
+    async fn prove(&self, inputs: [Option<AggUtxo>; 2]) -> Result<AggAggProof> {
+        let [first, second] = inputs;
+        let (first, second) =
+            tokio::try_join!(self.prove_agg_utxo(first), self.prove_agg_utxo(second))?;
+        let agg_agg = AggAgg::new([first, second]);
+
+        match self {
+            AggregationProver::Local(permits) => prove_locally(permits, agg_agg).await,
+            AggregationProver::Remote(jobs) => Ok(jobs.prove_agg_agg(agg_agg).await?),
+        }
+    }
+
+    async fn prove_agg_utxo(&self, agg_utxo: Option<AggUtxo>) -> Result<AggUtxoProof> {
+        let Some(agg_utxo) = agg_utxo else {
+            return Ok(AggUtxoProof::default());
+        };
+
+        match self {
+            AggregationProver::Local(permits) => prove_locally(permits, agg_utxo).await,
+            AggregationProver::Remote(jobs) => Ok(jobs.prove_agg_utxo(agg_utxo).await?),
+        }
+    }
+}
+
+async fn prove_locally<P>(permits: &Semaphore, input: P) -> Result<P::Proof>
+where
+    P: Prove + Send + 'static,
+    P::Proof: Send + 'static,
+{
+    let _permit = permits.acquire().await.unwrap();
+
+    let proof = tokio::task::spawn_blocking(move || {
+        input
+            .prove()
+            .map_err(|err| prover::Error::BarretenbergProve(err.to_string()))
+    })
+    .await??;
+
+    Ok(proof)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -533,7 +771,164 @@ mod tests {
         ACCOUNT_1_SK,
         eth::{EthNode, EthNodeOptions},
     };
-    use zk_primitives::AggAggProof;
+    use zk_primitives::{AggAggProof, UtxoProof};
+
+    #[test]
+    fn lookahead_tree_matches_notes_tree() {
+        let mut notes_tree = PersistentMerkleTree::new_in_memory();
+        let mut lookahead_tree = notes_tree.tree().clone();
+
+        let mut mint = UtxoProof::default();
+        mint.public_inputs.output_commitments = [Element::new(1), Element::new(2)];
+        let mut send = UtxoProof::default();
+        send.public_inputs.nullifiers = [Element::new(4), Element::ZERO];
+        send.public_inputs.output_commitments = [Element::new(3), Element::ZERO];
+
+        for (height, txn) in [(1, mint), (2, send)] {
+            let state = BlockState::new(Element::ZERO, vec![txn.into()]);
+
+            NodeShared::apply_block_to_tree(&mut notes_tree, &state, BlockHeight(height)).unwrap();
+            apply_block_to_lookahead(&mut lookahead_tree, &state, BlockHeight(height)).unwrap();
+
+            assert_eq!(lookahead_tree.root_hash(), notes_tree.tree().root_hash());
+        }
+    }
+
+    /// Blocks that each insert one note, with the notes tree roots after each block
+    fn blocks(signer: &PeerIdSigner, heights: std::ops::RangeInclusive<u64>) -> Vec<Arc<Block>> {
+        let mut tree = PersistentMerkleTree::new_in_memory().tree().clone();
+
+        heights
+            .map(|height| {
+                let mut mint = UtxoProof::default();
+                mint.public_inputs.output_commitments = [Element::new(height), Element::ZERO];
+
+                let mut state = BlockState::new(Element::ZERO, vec![mint.into()]);
+                apply_block_to_lookahead(&mut tree, &state, BlockHeight(height)).unwrap();
+                state.root_hash = tree.root_hash();
+
+                Arc::new(
+                    BlockContent {
+                        header: BlockHeader {
+                            height: BlockHeight(height),
+                            ..Default::default()
+                        },
+                        state,
+                    }
+                    .to_block(signer),
+                )
+            })
+            .collect()
+    }
+
+    fn proved(commit: Arc<Block>) -> ProvedCommit {
+        let height = commit.content.header.height.0;
+        let other_hash = *commit.content.header_hash().inner();
+
+        ProvedCommit {
+            commit,
+            rollup_input: Some(RollupInput::new(
+                AggAggProof::default(),
+                height,
+                other_hash,
+                Vec::new(),
+            )),
+            _release_lock: None,
+        }
+    }
+
+    fn saved_heights(prover_db: &ProverDb) -> Vec<BlockHeight> {
+        prover_db
+            .list_rollups(BlockHeight(0)..BlockHeight(u64::MAX))
+            .map(|rollup| rollup.unwrap().0)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn proofs_are_saved_in_height_order() {
+        let tempdir = TempDir::new("prover").unwrap();
+        let prover_db = ProverDb::create_or_load(tempdir.path()).unwrap();
+        let notes_tree = Mutex::new(Some(PersistentMerkleTree::new_in_memory()));
+        let proof_notifier = Notify::new();
+        let signer = PeerIdSigner::new(secp256k1::SecretKey::from_str(ACCOUNT_1_SK).unwrap());
+        let [first, second] = blocks(&signer, 1..=2).try_into().unwrap();
+        let expected_root = second.content.state.root_hash;
+
+        let (sender, receiver) = mpsc::channel::<PendingCommit>(2);
+        let (first_proved, first_proof) = tokio::sync::oneshot::channel::<()>();
+        sender
+            .send(Box::pin(async move {
+                first_proof.await.unwrap();
+                Ok(proved(first))
+            }))
+            .await
+            .unwrap();
+        sender
+            .send(Box::pin(futures::future::ready(Ok(proved(second)))))
+            .await
+            .unwrap();
+        drop(sender);
+
+        let save = save_proved_commits(receiver, 2, &prover_db, &notes_tree, None, &proof_notifier);
+        tokio::pin!(save);
+
+        // The second block is proved first, but isn't saved until the first block is
+        tokio::select! {
+            res = &mut save => panic!("Saving finished before the first block was proved: {res:?}"),
+            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+        }
+        assert!(saved_heights(&prover_db).is_empty());
+        assert!(prover_db.get_last_seen_block().unwrap().is_none());
+
+        first_proved.send(()).unwrap();
+        save.await.unwrap();
+
+        assert_eq!(saved_heights(&prover_db), [BlockHeight(1), BlockHeight(2)]);
+        let last_seen_block = prover_db.get_last_seen_block().unwrap().unwrap();
+        assert_eq!(last_seen_block.height, BlockHeight(2));
+        assert_eq!(last_seen_block.root_hash, expected_root);
+        assert_eq!(
+            notes_tree.lock().await.as_ref().unwrap().tree().root_hash(),
+            expected_root
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_proofs_do_not_advance_the_last_seen_block() {
+        let tempdir = TempDir::new("prover").unwrap();
+        let prover_db = ProverDb::create_or_load(tempdir.path()).unwrap();
+        let notes_tree = Mutex::new(Some(PersistentMerkleTree::new_in_memory()));
+        let proof_notifier = Notify::new();
+        let signer = PeerIdSigner::new(secp256k1::SecretKey::from_str(ACCOUNT_1_SK).unwrap());
+        let [first, second, third] = blocks(&signer, 1..=3).try_into().unwrap();
+        let first_root = first.content.state.root_hash;
+
+        let (sender, receiver) = mpsc::channel::<PendingCommit>(3);
+        let pending: [PendingCommit; 3] = [
+            Box::pin(futures::future::ready(Ok(proved(first)))),
+            Box::pin(futures::future::ready(Err(Error::RootMismatch {
+                got: Element::ZERO,
+                expected: second.content.state.root_hash,
+            }))),
+            Box::pin(futures::future::ready(Ok(proved(third)))),
+        ];
+        for commit in pending {
+            sender.send(commit).await.unwrap();
+        }
+        drop(sender);
+
+        let res =
+            save_proved_commits(receiver, 3, &prover_db, &notes_tree, None, &proof_notifier).await;
+
+        assert!(matches!(res, Err(Error::RootMismatch { .. })));
+        assert_eq!(saved_heights(&prover_db), [BlockHeight(1)]);
+        let last_seen_block = prover_db.get_last_seen_block().unwrap().unwrap();
+        assert_eq!(last_seen_block.height, BlockHeight(1));
+        assert_eq!(
+            notes_tree.lock().await.as_ref().unwrap().tree().root_hash(),
+            first_root
+        );
+    }
 
     #[ignore]
     #[tokio::test]
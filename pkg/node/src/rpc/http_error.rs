@@ -1,5 +1,5 @@
 use super::routes;
-use crate::errors;
+use crate::{backup, errors};
 use element::Element;
 use rpc::{code::ErrorCode, error::HTTPError};
 use serde::Serialize;
@@ -31,6 +31,18 @@ impl From<routes::error::Error> for HTTPError {
                 Some(err.into()),
                 None::<()>,
             ),
+            routes::error::Error::AdminDisabled => HTTPError::new(
+                ErrorCode::NotFound,
+                "admin-disabled",
+                Some(err.into()),
+                None::<()>,
+            ),
+            routes::error::Error::InvalidAdminToken => HTTPError::new(
+                ErrorCode::Unauthenticated,
+                "invalid-admin-token",
+                Some(err.into()),
+                None::<()>,
+            ),
         }
     }
 }
@@ -63,3 +75,22 @@ impl From<errors::Error> for HTTPError {
         }
     }
 }
+
+impl From<backup::Error> for HTTPError {
+    fn from(err: backup::Error) -> Self {
+        match err {
+            backup::Error::Inconsistent => HTTPError::new(
+                ErrorCode::Unavailable,
+                "backup-inconsistent",
+                Some(err.into()),
+                None::<()>,
+            ),
+            _ => HTTPError::new(
+                ErrorCode::Internal,
+                "internal",
+                Some(err.into()),
+                None::<()>,
+            ),
+        }
+    }
+}
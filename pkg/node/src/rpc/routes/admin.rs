@@ -0,0 +1,57 @@
+use actix_web::{HttpRequest, http::header, web};
+use rpc::error::HttpResult;
+
+use super::{State, error};
+use crate::backup::{self, BackupInfo};
+use crate::util::constant_time_eq;
+
+pub type ListBackupsResponse = Vec<BackupInfo>;
+
+/// Admin routes are only enabled if an admin token is configured, and require it as a bearer
+/// token
+fn authorize(state: &State, req: &HttpRequest) -> Result<(), error::Error> {
+    let Some(admin_token) = &state.node.config().admin_token else {
+        return Err(error::Error::AdminDisabled);
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), admin_token.as_bytes()) => Ok(()),
+        _ => Err(error::Error::InvalidAdminToken),
+    }
+}
+
+/// POST /admin/backups - takes a backup of the node's databases
+#[tracing::instrument(err, skip_all)]
+pub async fn create_backup(
+    state: web::Data<State>,
+    req: HttpRequest,
+) -> HttpResult<web::Json<BackupInfo>> {
+    tracing::info!(method = "create_backup", "Incoming request");
+
+    authorize(&state, &req)?;
+
+    let (path, manifest) = state.node.create_backup().await?;
+
+    Ok(web::Json(BackupInfo { path, manifest }))
+}
+
+/// GET /admin/backups - lists the backups available on this node
+#[tracing::instrument(err, skip_all)]
+pub async fn list_backups(
+    state: web::Data<State>,
+    req: HttpRequest,
+) -> HttpResult<web::Json<ListBackupsResponse>> {
+    tracing::info!(method = "list_backups", "Incoming request");
+
+    authorize(&state, &req)?;
+
+    let backups = backup::list_backups(&state.node.config().backup_path)?;
+
+    Ok(web::Json(backups))
+}
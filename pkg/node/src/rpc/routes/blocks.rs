@@ -1,4 +1,4 @@
-use super::{State, txn::TxnWithInfo};
+use super::{State, txn::txn_amount};
 use crate::{
     Error,
     block::{
@@ -8,87 +8,64 @@ use crate::{
     node,
 };
 use actix_web::web;
+use block_store::BlockListOrder;
 use either::Either;
-use element::Element;
-use primitives::{
-    block_height::BlockHeight,
-    hash::CryptoHash,
-    pagination::{OpaqueCursor, OpaqueCursorChoice, Paginator},
-    sig::Signature,
+use node_interface::{
+    Block, BlockContent, BlockHeader, BlockResponse, BlockState, BlockWithInfo, ListBlocksOrder,
+    ListBlocksQuery, ListBlocksResponse, TxnWithInfo,
 };
+use primitives::{block_height::BlockHeight, hash::CryptoHash, pagination::Paginator};
 use rpc::error::HttpResult;
 use serde::{Deserialize, Serialize, de::IntoDeserializer};
 use wire_message::WireMessage;
-
-pub type BlockResponse = BlockWithInfo;
-
-#[derive(Serialize)]
-pub struct Block {
-    content: BlockContent,
-    signature: Signature,
-}
-
-impl Block {
-    fn from_node_block(block: NodeBlock, time: u64) -> Self {
-        let NodeBlock { content, signature } = block;
-        let NodeBlockContent { header, state } = content;
-        let NodeBlockHeader {
-            height,
-            last_block_hash,
-            epoch_id,
-            last_final_block_hash,
-            approvals,
-        } = header;
-        let NodeBlockState { root_hash, txns } = state;
-
-        Self {
-            content: BlockContent {
-                header: BlockHeader {
-                    height,
-                    last_block_hash,
-                    epoch_id,
-                    last_final_block_hash,
-                    approvals,
-                },
-                state: BlockState {
-                    root_hash,
-                    txns: txns
-                        .into_iter()
-                        .enumerate()
-                        .map(|(index_in_block, proof)| TxnWithInfo {
-                            hash: proof.hash(),
-                            proof,
-                            index_in_block: index_in_block as u64,
-                            block_height: height,
-                            time,
-                        })
-                        .collect(),
-                },
+use zk_primitives::NoteKindRegistry;
+
+fn rpc_block(block: NodeBlock, time: u64, note_kinds: &NoteKindRegistry) -> Block {
+    let NodeBlock { content, signature } = block;
+    let NodeBlockContent { header, state } = content;
+    let NodeBlockHeader {
+        height,
+        last_block_hash,
+        epoch_id,
+        last_final_block_hash,
+        approvals,
+    } = header;
+    let NodeBlockState { root_hash, txns } = state;
+
+    Block {
+        content: BlockContent {
+            header: BlockHeader {
+                height,
+                last_block_hash,
+                epoch_id,
+                last_final_block_hash,
+                approvals,
             },
-            signature,
-        }
+            state: BlockState {
+                root_hash,
+                txns: txns
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index_in_block, proof)| TxnWithInfo {
+                        hash: proof.hash(),
+                        amount: txn_amount(note_kinds, &proof),
+                        proof,
+                        index_in_block: index_in_block as u64,
+                        block_height: height,
+                        time,
+                    })
+                    .collect(),
+            },
+        },
+        signature,
     }
 }
 
-#[derive(Serialize)]
-struct BlockContent {
-    header: BlockHeader,
-    state: BlockState,
-}
-
-#[derive(Serialize)]
-struct BlockHeader {
-    height: BlockHeight,
-    last_block_hash: CryptoHash,
-    epoch_id: u64,
-    last_final_block_hash: CryptoHash,
-    approvals: Vec<Signature>,
-}
-
-#[derive(Serialize)]
-struct BlockState {
-    root_hash: Element,
-    txns: Vec<TxnWithInfo>,
+fn block_list_order(order: ListBlocksOrder) -> BlockListOrder {
+    match order {
+        ListBlocksOrder::LowestToHighest => BlockListOrder::LowestToHighest,
+        ListBlocksOrder::HighestToLowest => BlockListOrder::HighestToLowest,
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -136,8 +113,8 @@ pub async fn get_block(
     };
 
     let (block, metadata) = match block.upgrade(&mut ()).unwrap() {
-        node::BlockFormat::V1(_) => unreachable!("already upgraded"),
-        node::BlockFormat::V2(block, metadata) => (block, metadata),
+        node::BlockFormat::V1(_) | node::BlockFormat::V2(_, _) => unreachable!("already upgraded"),
+        node::BlockFormat::V3(block, metadata) => (block, metadata),
     };
 
     let max_height = state.node.max_height();
@@ -151,54 +128,10 @@ pub async fn get_block(
     Ok(web::Json(BlockResponse {
         time,
         hash: block.hash(),
-        block: Block::from_node_block(block, time),
+        block: rpc_block(block, time, state.node.note_kinds()),
     }))
 }
 
-#[derive(Serialize)]
-pub struct BlockWithInfo {
-    block: Block,
-    hash: CryptoHash,
-    time: u64,
-}
-
-#[derive(Serialize)]
-pub struct ListBlocksResponse {
-    blocks: Vec<BlockWithInfo>,
-    cursor: OpaqueCursor<BlockHeight>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
-pub enum ListBlocksOrder {
-    LowestToHighest,
-    HighestToLowest,
-}
-
-impl ListBlocksOrder {
-    pub fn highest_to_lowest() -> Self {
-        Self::HighestToLowest
-    }
-}
-
-impl From<ListBlocksOrder> for block_store::BlockListOrder {
-    fn from(order: ListBlocksOrder) -> Self {
-        match order {
-            ListBlocksOrder::LowestToHighest => block_store::BlockListOrder::LowestToHighest,
-            ListBlocksOrder::HighestToLowest => block_store::BlockListOrder::HighestToLowest,
-        }
-    }
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ListBlocksQuery {
-    limit: Option<usize>,
-    cursor: Option<OpaqueCursorChoice<BlockHeight>>,
-    #[serde(default = "ListBlocksOrder::highest_to_lowest")]
-    order: ListBlocksOrder,
-    #[serde(default = "bool::default")]
-    skip_empty: bool,
-}
-
 #[tracing::instrument(err, skip_all)]
 pub async fn list_blocks(
     state: web::Data<State>,
@@ -217,16 +150,16 @@ pub async fn list_blocks(
     let limit = limit.unwrap_or(10).min(100);
 
     let blocks = if skip_empty {
-        Either::Left(
-            state
-                .node
-                .fetch_blocks_non_empty_paginated(&cursor, order.into(), limit)?,
-        )
+        Either::Left(state.node.fetch_blocks_non_empty_paginated(
+            &cursor,
+            block_list_order(order),
+            limit,
+        )?)
     } else {
         Either::Right(
             state
                 .node
-                .fetch_blocks_paginated(&cursor, order.into(), limit)?,
+                .fetch_blocks_paginated(&cursor, block_list_order(order), limit)?,
         )
     };
 
@@ -235,8 +168,10 @@ pub async fn list_blocks(
     let (cursor, blocks) = Paginator::new(
         blocks.map(|r| {
             let (block, metadata) = match r?.upgrade(&mut ()).unwrap() {
-                node::BlockFormat::V1(_) => unreachable!("already upgraded"),
-                node::BlockFormat::V2(block, metadata) => (block, metadata),
+                node::BlockFormat::V1(_) | node::BlockFormat::V2(_, _) => {
+                    unreachable!("already upgraded")
+                }
+                node::BlockFormat::V3(block, metadata) => (block, metadata),
             };
 
             let time = metadata
@@ -248,7 +183,7 @@ pub async fn list_blocks(
             Ok::<_, node::Error>(BlockWithInfo {
                 time,
                 hash: block.hash(),
-                block: Block::from_node_block(block, time),
+                block: rpc_block(block, time, state.node.note_kinds()),
             })
         }),
         |r| {
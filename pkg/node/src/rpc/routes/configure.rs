@@ -1,4 +1,6 @@
-use super::{State, blocks, element, health, height, merkle, smirk, stats, txn};
+use super::{
+    State, admin, blocks, element, health, height, inbox, merkle, nullifiers, smirk, stats, txn,
+};
 use actix_web::web;
 
 pub fn configure_routes(state: State) -> Box<dyn FnOnce(&mut web::ServiceConfig)> {
@@ -7,8 +9,10 @@ pub fn configure_routes(state: State) -> Box<dyn FnOnce(&mut web::ServiceConfig)
             .service(web::resource("/health").get(health::get_health))
             .service(web::resource("/height").get(height::get_height))
             .service(web::resource("/merkle").get(merkle::get_merkle_paths))
+            .service(web::resource("/merkle/non-membership").get(merkle::get_non_membership_proofs))
             .service(web::resource("/elements/{element}").get(element::get_element))
             .service(web::resource("/elements").get(element::list_elements))
+            .service(web::resource("/nullifiers").get(nullifiers::list_nullifiers))
             .service(web::resource("/blocks/{block}").get(blocks::get_block))
             .service(web::resource("/blocks").get(blocks::list_blocks))
             .service(web::resource("/transaction").post(txn::submit_txn))
@@ -18,7 +22,19 @@ pub fn configure_routes(state: State) -> Box<dyn FnOnce(&mut web::ServiceConfig)
                     .get(txn::list_txns)
                     .post(txn::submit_txn),
             )
+            .service(web::resource("/inbox/{commitment}").get(inbox::get_inbox_notes))
+            .service(
+                web::resource("/inbox")
+                    .app_data(inbox::post_json_config())
+                    .get(inbox::list_inbox_notes)
+                    .post(inbox::post_inbox_note),
+            )
             .service(web::resource("/stats").get(stats::get_stats))
-            .service(web::resource("/smirk/elements/all").get(smirk::get_all_smirk_elements));
+            .service(web::resource("/smirk/elements/all").get(smirk::get_all_smirk_elements))
+            .service(
+                web::resource("/admin/backups")
+                    .get(admin::list_backups)
+                    .post(admin::create_backup),
+            );
     })
 }
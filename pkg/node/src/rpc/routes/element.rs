@@ -1,4 +1,4 @@
-use crate::{Error, Result};
+use crate::{Error, HistoricalMerkleTree, Result, types::BlockHeight};
 
 use super::{State, error};
 use actix_web::web;
@@ -16,7 +16,9 @@ pub async fn get_element(
     path: web::Path<(Element,)>,
 ) -> HttpResult<web::Json<ElementsResponseSingle>> {
     let (element,) = path.into_inner();
-    Ok(web::Json(get_element_response(&state, element, false)?))
+    Ok(web::Json(get_element_response(
+        &state, element, false, None, None,
+    )?))
 }
 
 #[tracing::instrument(err, skip_all)]
@@ -38,33 +40,48 @@ pub async fn list_elements(
         })
         .collect::<HttpResult<Vec<Element>>>()?;
 
+    let height = query.height.map(BlockHeight);
+    let tree = match height {
+        Some(height) => Some(state.node.notes_tree_at(height).await?),
+        None => None,
+    };
+
     Ok(web::Json(
         elements
             .iter()
-            .map(
-                |element| match get_element_response(&state, *element, query.include_spent) {
+            .map(|element| {
+                match get_element_response(
+                    &state,
+                    *element,
+                    query.include_spent,
+                    height,
+                    tree.as_deref(),
+                ) {
                     Ok(response) => Ok(Some(response)),
                     Err(e) => match e {
                         Error::Rpc(RpcError::ElementNotFound { .. }) => Ok(None),
                         _ => Err(e),
                     },
-                },
-            )
+                }
+            })
             .filter_map(Result::transpose)
             .collect::<Result<Vec<ElementsResponseSingle>>>()?,
     ))
 }
 
+/// Look up `element` in the latest notes tree, or in `tree`, the tree as of `height`, if given
 fn get_element_response(
     state: &web::Data<State>,
     element: Element,
     include_spent: bool,
+    height: Option<BlockHeight>,
+    tree: Option<&HistoricalMerkleTree>,
 ) -> Result<ElementsResponseSingle> {
-    match get_element_response_unspent(state, element) {
+    match get_element_response_unspent(state, element, tree) {
         Ok(resp) => Ok(resp),
         Err(e) => match e {
             Error::Rpc(RpcError::ElementNotFound { .. }) if include_spent => {
-                get_element_response_from_history(state, element)
+                get_element_response_from_history(state, element, height)
             }
             _ => Err(e),
         },
@@ -74,12 +91,13 @@ fn get_element_response(
 fn get_element_response_unspent(
     state: &web::Data<State>,
     element: Element,
+    tree: Option<&HistoricalMerkleTree>,
 ) -> Result<ElementsResponseSingle> {
-    let notes_tree = state.node.notes_tree().read();
-    let tree = notes_tree.tree();
-    let meta = tree
-        .get(element)
-        .ok_or(RpcError::ElementNotFound(ElementData { element }))?;
+    let meta = match tree {
+        Some(tree) => tree.get(element).cloned(),
+        None => state.node.notes_tree_snapshot().get(element)?,
+    }
+    .ok_or(RpcError::ElementNotFound(ElementData { element }))?;
 
     let Some(block) = state.node.get_block(meta.inserted_in.into())? else {
         return Err(Error::BlockNotFound {
@@ -93,7 +111,7 @@ fn get_element_response_unspent(
         .state
         .txns
         .iter()
-        .find(|txn| txn.public_inputs.commitments().contains(&element))
+        .find(|txn| txn.leaves().contains(&element))
         .ok_or(Error::ElementNotInTxn {
             element,
             block_height: block.block_height(),
@@ -111,11 +129,22 @@ fn get_element_response_unspent(
 fn get_element_response_from_history(
     state: &web::Data<State>,
     element: Element,
+    height: Option<BlockHeight>,
 ) -> Result<ElementsResponseSingle> {
     let Some(info) = state.node.get_element_seen_info(element)? else {
         return Err(RpcError::ElementNotFound(ElementData { element }).into());
     };
 
+    // Elements added after `height` hadn't been seen yet
+    if height.is_some_and(|height| info.output_height > height) {
+        return Err(RpcError::ElementNotFound(ElementData { element }).into());
+    }
+
+    let spent = match height {
+        Some(height) => info.input_height.is_some_and(|input| input <= height),
+        None => info.spent,
+    };
+
     let Some(block) = state.node.get_block_by_hash(info.output_block_hash)? else {
         return Err(Error::BlockNotFound {
             block: info.output_height,
@@ -128,7 +157,7 @@ fn get_element_response_from_history(
         .state
         .txns
         .iter()
-        .find(|txn| txn.public_inputs.commitments().contains(&element))
+        .find(|txn| txn.leaves().contains(&element))
         .ok_or(Error::ElementNotInTxn {
             element,
             block_height: info.output_height,
@@ -139,6 +168,6 @@ fn get_element_response_from_history(
         height: info.output_height.0, // Use the height when the element was added to tree
         root_hash: block.content.state.root_hash,
         txn_hash: txn.hash(),
-        spent: info.spent,
+        spent,
     })
 }
@@ -13,4 +13,10 @@ pub enum Error {
 
     #[error("Invalid list query")]
     InvalidListQuery(#[source] serde_json::Error),
+
+    #[error("Admin API is disabled")]
+    AdminDisabled,
+
+    #[error("Missing or invalid admin token")]
+    InvalidAdminToken,
 }
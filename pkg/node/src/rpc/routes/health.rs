@@ -1,18 +1,13 @@
 use super::{State, error};
 use actix_web::web;
+use node_interface::HealthResponse;
 use rpc::error::HttpResult;
-use serde::Serialize;
-
-#[derive(Serialize)]
-pub struct HealthResp {
-    height: u64,
-}
 
 /// GET /health - returns data about the rollup (e.g. root hash, version, etc)
 /// unlike /height, /health will return an error if the node is unhealthy (i.e.
 /// out of sync with other nodes)
 #[tracing::instrument(skip(state))]
-pub async fn get_health(state: web::Data<State>) -> HttpResult<web::Json<HealthResp>> {
+pub async fn get_health(state: web::Data<State>) -> HttpResult<web::Json<HealthResponse>> {
     // Out of sync, will trigger service unavailable
     if state.node.is_out_of_sync() {
         return Err(error::Error::OutOfSync)?;
@@ -26,7 +21,7 @@ pub async fn get_health(state: web::Data<State>) -> HttpResult<web::Json<HealthR
         return Err(error::Error::OutOfSync)?;
     }
 
-    Ok(web::Json(HealthResp {
+    Ok(web::Json(HealthResponse {
         height: state.node.height().0,
     }))
 }
@@ -0,0 +1,79 @@
+use super::{State, error};
+use crate::Result;
+use actix_web::web;
+use element::Element;
+use node_interface::{
+    InboxNote, ListInboxNotesQuery, ListInboxNotesResponse, PostInboxNoteRequest, RpcError,
+};
+use rpc::error::{HTTPError, HttpResult};
+use std::str::FromStr;
+
+/// Maximum size of a `POST /inbox` body, a commitment and an encrypted note are well under this
+const MAX_POST_BODY_SIZE: usize = 4 * 1024;
+
+/// Bounds the size of posted notes, and rejects bodies that aren't a valid
+/// [`PostInboxNoteRequest`] with [`RpcError::InvalidInboxNote`]
+pub(super) fn post_json_config() -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(MAX_POST_BODY_SIZE)
+        .error_handler(|_, _| HTTPError::from(RpcError::InvalidInboxNote).into())
+}
+
+#[tracing::instrument(err, skip_all)]
+pub async fn post_inbox_note(
+    state: web::Data<State>,
+    web::Json(data): web::Json<PostInboxNoteRequest>,
+) -> HttpResult<web::Json<InboxNote>> {
+    state.node.post_inbox_note(data.commitment, &data.note)?;
+
+    Ok(web::Json(InboxNote {
+        commitment: data.commitment,
+        note: data.note,
+    }))
+}
+
+#[tracing::instrument(err, skip_all)]
+pub async fn get_inbox_notes(
+    state: web::Data<State>,
+    path: web::Path<(Element,)>,
+) -> HttpResult<web::Json<ListInboxNotesResponse>> {
+    let (commitment,) = path.into_inner();
+
+    Ok(web::Json(inbox_notes(&state, commitment)?))
+}
+
+#[tracing::instrument(err, skip_all)]
+pub async fn list_inbox_notes(
+    state: web::Data<State>,
+    query: web::Query<ListInboxNotesQuery>,
+) -> HttpResult<web::Json<ListInboxNotesResponse>> {
+    if query.commitments.is_empty() {
+        return Ok(web::Json(vec![]));
+    }
+
+    let commitments = query
+        .commitments
+        .split(',')
+        .map(|c| {
+            Element::from_str(c)
+                .map_err(|e| error::Error::InvalidElement(c.to_string(), e))
+                .map_err(rpc::error::HTTPError::from)
+        })
+        .collect::<HttpResult<Vec<Element>>>()?;
+
+    let mut notes = vec![];
+    for commitment in commitments {
+        notes.extend(inbox_notes(&state, commitment)?);
+    }
+
+    Ok(web::Json(notes))
+}
+
+fn inbox_notes(state: &State, commitment: Element) -> Result<Vec<InboxNote>> {
+    Ok(state
+        .node
+        .inbox_notes(commitment)?
+        .into_iter()
+        .map(|note| InboxNote { commitment, note })
+        .collect())
+}
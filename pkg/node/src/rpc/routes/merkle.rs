@@ -1,18 +1,20 @@
 use super::{State, error};
+use crate::types::BlockHeight;
 use actix_web::web;
 use element::Element;
+use node_interface::{MerklePathFormat, MerklePathResponse, NonMembershipResponse, RpcError};
 use rpc::error::HttpResult;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use smirk::{CompactPath, MultiPath};
 use std::str::FromStr;
 
 #[derive(Debug, Deserialize)]
 pub struct MerklePathRequestQuery {
     commitments: String,
-}
-
-#[derive(Serialize)]
-pub struct MerklePathResponse {
-    paths: Vec<Vec<Element>>,
+    /// Return paths against the notes tree as of this block height
+    height: Option<u64>,
+    #[serde(default)]
+    format: MerklePathFormat,
 }
 
 #[tracing::instrument(err, skip_all)]
@@ -22,17 +24,73 @@ pub async fn get_merkle_paths(
 ) -> HttpResult<web::Json<MerklePathResponse>> {
     tracing::info!(method = "get_merkle_paths", ?query, "Incoming request");
 
-    let commitments = query
-        .0
-        .commitments
+    let commitments = parse_commitments(&query.commitments)?;
+
+    let (root_hash, paths) = state
+        .node
+        .get_merkle_paths(&commitments, query.height.map(BlockHeight))
+        .await?;
+
+    let mut response = MerklePathResponse {
+        root_hash,
+        paths: None,
+        compact_paths: None,
+        multi_path: None,
+    };
+
+    match query.format {
+        MerklePathFormat::Full => {
+            response.paths = Some(
+                paths
+                    .iter()
+                    .map(|path| path.siblings_deepest_first().to_vec())
+                    .collect(),
+            );
+        }
+        MerklePathFormat::Compact => {
+            response.compact_paths = Some(paths.iter().map(CompactPath::from).collect());
+        }
+        MerklePathFormat::Multi => {
+            let multi_path = MultiPath::from_paths(&paths).map_err(|err| {
+                tracing::error!(?err, "Failed to combine merkle paths");
+                RpcError::MerklePathsNotCombinable
+            })?;
+            response.multi_path = Some(multi_path);
+        }
+    }
+
+    Ok(web::Json(response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NonMembershipRequestQuery {
+    commitments: String,
+}
+
+#[tracing::instrument(err, skip_all)]
+pub async fn get_non_membership_proofs(
+    state: web::Data<State>,
+    query: web::Query<NonMembershipRequestQuery>,
+) -> HttpResult<web::Json<NonMembershipResponse>> {
+    tracing::info!(
+        method = "get_non_membership_proofs",
+        ?query,
+        "Incoming request"
+    );
+
+    let commitments = parse_commitments(&query.commitments)?;
+
+    let (root_hash, proofs) = state.node.get_non_membership_proofs(&commitments)?;
+    Ok(web::Json(NonMembershipResponse { root_hash, proofs }))
+}
+
+fn parse_commitments(commitments: &str) -> HttpResult<Vec<Element>> {
+    commitments
         .split(',')
         .map(|c| {
             Element::from_str(c)
                 .map_err(|e| error::Error::InvalidElement(c.to_string(), e))
                 .map_err(rpc::error::HTTPError::from)
         })
-        .collect::<HttpResult<Vec<Element>>>()?;
-
-    let paths = state.node.get_merkle_paths(&commitments)?;
-    Ok(web::Json(MerklePathResponse { paths }))
+        .collect()
 }
@@ -1,10 +1,13 @@
+pub mod admin;
 pub mod blocks;
 pub mod configure;
 pub mod element;
 pub mod error;
 pub mod health;
 pub mod height;
+pub mod inbox;
 pub mod merkle;
+pub mod nullifiers;
 pub mod smirk;
 pub mod state;
 pub mod stats;
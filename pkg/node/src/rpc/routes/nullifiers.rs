@@ -0,0 +1,33 @@
+use super::{State, error};
+use actix_web::web;
+use element::Element;
+use node_interface::{ListNullifiersQuery, ListNullifiersResponse};
+use rpc::error::HttpResult;
+use std::str::FromStr;
+
+#[tracing::instrument(err, skip_all)]
+pub async fn list_nullifiers(
+    state: web::Data<State>,
+    query: web::Query<ListNullifiersQuery>,
+) -> HttpResult<web::Json<ListNullifiersResponse>> {
+    if query.nullifiers.is_empty() {
+        return Ok(web::Json(vec![]));
+    }
+
+    let nullifiers = query
+        .nullifiers
+        .split(',')
+        .map(|n| {
+            Element::from_str(n)
+                .map_err(|e| error::Error::InvalidElement(n.to_string(), e))
+                .map_err(rpc::error::HTTPError::from)
+        })
+        .collect::<HttpResult<Vec<Element>>>()?;
+
+    let mut spent = vec![];
+    for nullifier in nullifiers {
+        spent.extend(state.node.get_nullifier(nullifier)?);
+    }
+
+    Ok(web::Json(spent))
+}
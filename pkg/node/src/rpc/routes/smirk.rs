@@ -1,30 +1,22 @@
 use actix_web::web;
-use element::Element;
+use node_interface::{GetAllSmirkElementsResponse, SmirkElementInfo};
 use rpc::error::HttpResult;
-use serde::Serialize;
 
 use crate::rpc::routes::State;
 
-#[derive(Serialize)]
-pub struct SmirkElementInfo {
-    element: Element,
-    inserted_at_height: u64,
-}
-
-pub type GetAllSmirkElementsResponse = Vec<SmirkElementInfo>;
-
 #[tracing::instrument(err, skip_all)]
 pub async fn get_all_smirk_elements(
     state: web::Data<State>,
 ) -> HttpResult<web::Json<GetAllSmirkElementsResponse>> {
     tracing::info!(method = "get_all_smirk_elements", "Incoming request");
 
-    let notes_tree = state.node.notes_tree().read();
-    let elements = notes_tree
-        .tree()
-        .elements()
+    let elements = state
+        .node
+        .notes_tree_snapshot()
+        .elements()?
+        .into_iter()
         .map(|(element, metadata)| SmirkElementInfo {
-            element: *element,
+            element,
             inserted_at_height: metadata.inserted_in,
         })
         .collect();
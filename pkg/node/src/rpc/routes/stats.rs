@@ -1,21 +1,11 @@
 use actix_web::web;
 
 use crate::State;
+use node_interface::{StatsResponse, TxnDayStats};
 use rpc::error::HttpResult;
 
 use super::error;
 
-#[derive(Debug, serde::Serialize)]
-struct TxnDayStats {
-    date: chrono::NaiveDate,
-    count: u64,
-}
-
-#[derive(Debug, serde::Serialize)]
-pub struct StatsResponse {
-    last_7_days_txns: Vec<TxnDayStats>,
-}
-
 #[tracing::instrument(err, skip_all)]
 pub async fn get_stats(state: web::Data<State>) -> HttpResult<web::Json<StatsResponse>> {
     if !state.txn_stats.ready() {
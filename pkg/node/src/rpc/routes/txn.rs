@@ -7,16 +7,18 @@ use element::Element;
 use eyre::Context;
 use futures::StreamExt;
 use itertools::Itertools;
-use node_interface::{ElementData, RpcError, TransactionRequest, TransactionResponse};
+use node_interface::{
+    ElementData, GetTxnResponse, ListTxnOrder, ListTxnsPosition, ListTxnsQuery, ListTxnsResponse,
+    RpcError, TransactionRequest, TransactionResponse, TxnWithInfo,
+};
 use primitives::{
     block_height::BlockHeight,
-    pagination::{Cursor, CursorChoice, OpaqueCursor, OpaqueCursorChoice, Paginator},
+    pagination::{Cursor, CursorChoice, Paginator},
 };
 use rpc::error::{HTTPError, HttpResult};
-use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use wire_message::WireMessage;
-use zk_primitives::UtxoProof;
+use zk_primitives::{NoteKindRegistry, TxnProof, UtxoKindMessages};
 
 #[tracing::instrument(err, skip_all)]
 pub async fn submit_txn(
@@ -49,56 +51,13 @@ pub async fn submit_txn(
     }))
 }
 
-#[derive(Serialize)]
-pub(crate) struct TxnWithInfo {
-    pub(crate) proof: UtxoProof,
-    pub(crate) index_in_block: u64,
-    pub(crate) hash: Element,
-    pub(crate) block_height: BlockHeight,
-    pub(crate) time: u64,
-}
-
-#[derive(Serialize)]
-pub struct ListTxnsResponse {
-    txns: Vec<TxnWithInfo>,
-    cursor: OpaqueCursor<ListTxnsPosition>,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-struct ListTxnsPosition {
-    block: BlockHeight,
-    txn: u64,
-}
-
-#[derive(Debug, Deserialize)]
-enum ListTxnOrder {
-    NewestToOldest,
-    OldestToNewest,
-}
-
-impl ListTxnOrder {
-    fn to_block_list_order(&self) -> BlockListOrder {
-        match self {
-            ListTxnOrder::NewestToOldest => BlockListOrder::HighestToLowest,
-            ListTxnOrder::OldestToNewest => BlockListOrder::LowestToHighest,
-        }
-    }
-
-    fn newest_to_oldest() -> Self {
-        ListTxnOrder::NewestToOldest
+fn block_list_order(order: ListTxnOrder) -> BlockListOrder {
+    match order {
+        ListTxnOrder::NewestToOldest => BlockListOrder::HighestToLowest,
+        ListTxnOrder::OldestToNewest => BlockListOrder::LowestToHighest,
     }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ListTxnsQuery {
-    limit: Option<usize>,
-    cursor: Option<OpaqueCursorChoice<ListTxnsPosition>>,
-    #[serde(default = "ListTxnOrder::newest_to_oldest")]
-    order: ListTxnOrder,
-    #[serde(default = "bool::default")]
-    poll: bool,
-}
-
 #[tracing::instrument(err, skip_all)]
 pub async fn list_txns(
     state: web::Data<State>,
@@ -121,8 +80,14 @@ pub async fn list_txns(
 
     let max_height = state.node.max_height();
 
-    let (cursor, transactions) =
-        list_txns_inner(make_block_fetcher(state.clone()), &query, max_height)?;
+    let note_kinds = state.node.note_kinds();
+
+    let (cursor, transactions) = list_txns_inner(
+        make_block_fetcher(state.clone()),
+        &query,
+        max_height,
+        note_kinds,
+    )?;
 
     let (cursor, transactions) = if transactions.is_empty() && query.poll {
         let towards_newer_height = match (&query.order, query.cursor.as_deref()) {
@@ -161,6 +126,7 @@ pub async fn list_txns(
                             make_block_fetcher(state.clone()),
                             &query,
                             max_height,
+                            note_kinds,
                         )?
                     }
                 }
@@ -185,6 +151,7 @@ fn list_txns_inner<I: Iterator<Item = Result<BlockFormat, node::Error>>>(
     ) -> Result<I, node::Error>,
     query: &ListTxnsQuery,
     max_height: BlockHeight,
+    note_kinds: &NoteKindRegistry,
 ) -> Result<(Cursor<ListTxnsPosition>, Vec<TxnWithInfo>), HTTPError> {
     let txn_limit = query.limit.unwrap_or(10).min(100);
 
@@ -193,7 +160,7 @@ fn list_txns_inner<I: Iterator<Item = Result<BlockFormat, node::Error>>>(
             .cursor
             .as_ref()
             .map(|pag| pag.map_pos(|pos| pos.block)),
-        query.order.to_block_list_order(),
+        block_list_order(query.order),
         // Because we filter later in the code, we need to fetch an extra block
         txn_limit + 1,
     )?;
@@ -202,8 +169,10 @@ fn list_txns_inner<I: Iterator<Item = Result<BlockFormat, node::Error>>>(
         .map(|r| {
             r.map(|r| {
                 let (block, metadata) = match r.upgrade(&mut ()).unwrap() {
-                    node::BlockFormat::V1(_) => unreachable!("already upgraded"),
-                    node::BlockFormat::V2(block, metadata) => (block, metadata),
+                    node::BlockFormat::V1(_) | node::BlockFormat::V2(_, _) => {
+                        unreachable!("already upgraded")
+                    }
+                    node::BlockFormat::V3(block, metadata) => (block, metadata),
                 };
 
                 block
@@ -214,6 +183,7 @@ fn list_txns_inner<I: Iterator<Item = Result<BlockFormat, node::Error>>>(
                     .enumerate()
                     .map(move |(i, txn)| TxnWithInfo {
                         hash: txn.hash(),
+                        amount: txn_amount(note_kinds, &txn),
                         proof: txn,
                         index_in_block: i as u64,
                         block_height: block.content.header.height,
@@ -271,11 +241,6 @@ fn list_txns_inner<I: Iterator<Item = Result<BlockFormat, node::Error>>>(
     ))
 }
 
-#[derive(Serialize)]
-pub struct GetTxnResponse {
-    txn: TxnWithInfo,
-}
-
 #[tracing::instrument(err, skip_all)]
 pub async fn get_txn(
     state: web::Data<State>,
@@ -298,6 +263,7 @@ pub async fn get_txn(
 
     Ok(web::Json(GetTxnResponse {
         txn: TxnWithInfo {
+            amount: txn_amount(state.node.note_kinds(), &txn),
             proof: txn,
             index_in_block: metadata.block_txn_index as u64,
             hash: txn_hash,
@@ -307,28 +273,38 @@ pub async fn get_txn(
     }))
 }
 
+/// The value minted or burned by `txn` as a human-readable amount, if its note kind is known
+pub(super) fn txn_amount(note_kinds: &NoteKindRegistry, txn: &TxnProof) -> Option<String> {
+    let (note_kind, value) = match txn.kind_messages() {
+        UtxoKindMessages::Mint(mint) => (mint.note_kind, mint.value),
+        UtxoKindMessages::Burn(burn) => (burn.note_kind, burn.value),
+        UtxoKindMessages::None => return None,
+    };
+
+    note_kinds.format_amount(note_kind, value)
+}
+
 #[cfg(test)]
 mod tests {
     use primitives::pagination::Opaque;
+    use zk_primitives::{NoteKind, UtxoKind, UtxoProof, UtxoPublicInput};
 
-    use crate::{Block, BlockFormat};
+    use crate::{Block, BlockFormat, BlockMetadata};
 
     use super::*;
 
     #[test]
     fn list_txns_pagination() {
-        let tempdir = tempdir::TempDir::new("list_txns").unwrap();
-
-        let store = block_store::BlockStore::<BlockFormat>::create_or_load(tempdir.path()).unwrap();
+        let store = block_store::BlockStore::<BlockFormat>::in_memory().unwrap();
 
-        let new_block = |height: u64, txns: Vec<UtxoProof>| {
+        let new_block = |height: u64, txns: Vec<TxnProof>| {
             let mut block = Block::default();
             block.content.header.height = BlockHeight(height);
             block.content.state.txns = txns;
             block
         };
 
-        let new_proof = || UtxoProof::default();
+        let new_proof = TxnProof::default;
 
         let blocks = [
             new_block(1, vec![]),
@@ -340,7 +316,14 @@ mod tests {
         let max_height = blocks.last().unwrap().content.header.height;
 
         for block in &blocks {
-            store.set(&BlockFormat::V1(block.clone())).unwrap();
+            store
+                .set(&BlockFormat::V3(
+                    block.clone(),
+                    BlockMetadata {
+                        timestamp_unix_s: None,
+                    },
+                ))
+                .unwrap();
         }
 
         let block_fetcher =
@@ -361,6 +344,7 @@ mod tests {
                 poll: false,
             },
             max_height,
+            &NoteKindRegistry::default(),
         )
         .unwrap();
         assert_eq!(txns.len(), 4);
@@ -376,6 +360,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -390,6 +375,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -404,6 +390,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -418,6 +405,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -432,6 +420,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 0);
@@ -450,6 +439,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -464,6 +454,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -478,6 +469,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -492,6 +484,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 1);
@@ -506,6 +499,7 @@ mod tests {
                     poll: false,
                 },
                 max_height,
+                &NoteKindRegistry::default(),
             )
             .unwrap();
             assert_eq!(txns.len(), 0);
@@ -513,4 +507,43 @@ mod tests {
             assert_eq!(cursor.after, None);
         };
     }
+
+    #[test]
+    fn txn_amount_of_mints_and_burns() {
+        let usdc = NoteKind::polygon_usdc();
+        let note_kinds = NoteKindRegistry::from_iter([usdc.clone()]);
+
+        let proof = |kind: UtxoKind, note_kind: Element| {
+            TxnProof::from(UtxoProof {
+                public_inputs: UtxoPublicInput {
+                    messages: [
+                        kind.to_element(),
+                        note_kind,
+                        Element::new(1_500_000),
+                        Element::ZERO,
+                        Element::ZERO,
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(
+            txn_amount(&note_kinds, &proof(UtxoKind::Mint, usdc.kind)),
+            Some("1.5 USDC".to_owned())
+        );
+        assert_eq!(
+            txn_amount(&note_kinds, &proof(UtxoKind::Burn, usdc.kind)),
+            Some("1.5 USDC".to_owned())
+        );
+        assert_eq!(
+            txn_amount(&note_kinds, &proof(UtxoKind::Mint, Element::ONE)),
+            None
+        );
+        assert_eq!(
+            txn_amount(&note_kinds, &proof(UtxoKind::Send, usdc.kind)),
+            None
+        );
+    }
 }
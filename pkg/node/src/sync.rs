@@ -5,10 +5,7 @@
 //! via the [SyncWorkerChannel].
 //! The main entry point is [SyncWorker::run].
 
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{collections::HashSet, sync::Arc};
 
 use block_store::{BlockListOrder, StoreList};
 use contracts::RollupContract;
@@ -16,21 +13,34 @@ use element::Element;
 use libp2p::PeerId;
 use parking_lot::Mutex;
 use prover::smirk_metadata::SmirkMetadata;
+use smirk::{SubtreeDiff, SubtreeId};
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
 use crate::{
-    Error as NodeError, NodeShared, PersistentMerkleTree,
+    Error as NodeError, NodeShared, NotesTree,
     block::Block,
     cache::BlockCache,
+    constants::MERKLE_TREE_DEPTH,
     network::{
-        NetworkEvent, SnapshotAccept, SnapshotChunk, SnapshotChunkFast, SnapshotChunkSlow,
-        SnapshotKind, SnapshotOffer as NetworkSnapshotOffer, SnapshotRequest,
+        NetworkEvent, SnapshotAccept, SnapshotChunk, SnapshotChunkFast, SnapshotChunkReconcile,
+        SnapshotChunkSlow, SnapshotKind, SnapshotOffer as NetworkSnapshotOffer, SnapshotRequest,
+        TreeSyncRequest, TreeSyncResponse,
     },
     node::Mode,
     types::{BlockHeight, SnapshotId},
 };
 
+/// The maximum number of subtrees to request summaries of in one [`TreeSyncRequest`].
+const TREE_SYNC_SUBTREES_PER_REQUEST: usize = 256;
+
+/// Subtrees with at most this many elements are sent as elements rather than hashes.
+const TREE_SYNC_MAX_ELEMENTS: usize = 64;
+
+/// The maximum number of subtrees requested while reconciling a notes tree. Trees that differ by
+/// more are synced with a [`SnapshotKind::Fast`] snapshot instead.
+const TREE_SYNC_MAX_SUBTREES: usize = 1 << 16;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("channel was closed")]
@@ -56,6 +66,7 @@ pub enum Message {
     OutOfSync(OutOfSync),
     SnapshotOffer(SyncSnapshotOffer),
     SnapshotChunk(PeerId, SnapshotChunk),
+    TreeSyncResponse(PeerId, TreeSyncResponse),
 }
 
 /// A message signaling that the node needs to start syncing,
@@ -68,6 +79,7 @@ pub struct OutOfSync {
 pub struct SyncSnapshotOffer {
     pub peer: PeerId,
     pub snapshot_id: SnapshotId,
+    pub kind: SnapshotKind,
 }
 
 /// A channel for sending messages to the sync worker.
@@ -85,11 +97,17 @@ impl SyncWorkerChannel {
     }
 
     /// Handled by [SyncWorker::handle_snapshot_offer].
-    pub fn snapshot_offer(&self, peer: PeerId, snapshot_id: SnapshotId) -> Result<(), Error> {
+    pub fn snapshot_offer(
+        &self,
+        peer: PeerId,
+        snapshot_id: SnapshotId,
+        kind: SnapshotKind,
+    ) -> Result<(), Error> {
         self.0
             .send(Message::SnapshotOffer(SyncSnapshotOffer {
                 peer,
                 snapshot_id,
+                kind,
             }))
             .map_err(|_| Error::ChannelWasClosed)
     }
@@ -100,6 +118,17 @@ impl SyncWorkerChannel {
             .send(Message::SnapshotChunk(peer, sc))
             .map_err(|_| Error::ChannelWasClosed)
     }
+
+    /// Handled by [SyncWorker::reconcile_tree].
+    pub fn tree_sync_response(
+        &self,
+        peer: PeerId,
+        response: TreeSyncResponse,
+    ) -> Result<(), Error> {
+        self.0
+            .send(Message::TreeSyncResponse(peer, response))
+            .map_err(|_| Error::ChannelWasClosed)
+    }
 }
 
 pub struct SyncWorker {
@@ -119,6 +148,9 @@ pub struct SyncWorker {
     /// we can trigger out of sync again,
     /// without recursing.
     channel_sender: SyncWorkerChannel,
+    /// The highest out of sync message received while waiting for another message,
+    /// see [SyncWorker::defer].
+    deferred_out_of_sync: Option<OutOfSync>,
 }
 
 impl SyncWorker {
@@ -142,6 +174,7 @@ impl SyncWorker {
             node_mode,
             channel,
             channel_sender,
+            deferred_out_of_sync: None,
         }
     }
 
@@ -158,6 +191,10 @@ impl SyncWorker {
     }
 
     pub async fn wait_for_out_of_sync(&mut self) -> Result<OutOfSync, Error> {
+        if let Some(out_of_sync) = self.deferred_out_of_sync.take() {
+            return Ok(out_of_sync);
+        }
+
         while let Some(msg) = self.channel.recv().await {
             if let Message::OutOfSync(out_of_sync) = msg {
                 return Ok(out_of_sync);
@@ -167,6 +204,23 @@ impl SyncWorker {
         Err(Error::ChannelWasClosed)
     }
 
+    /// Set aside a message that arrived while waiting for another one
+    ///
+    /// Out of sync messages are kept for [SyncWorker::wait_for_out_of_sync], of which only the
+    /// highest is needed. Anything else is a reply to a snapshot we have given up on, as only one
+    /// snapshot is requested at a time.
+    fn defer(&mut self, msg: Message) {
+        if let Message::OutOfSync(out_of_sync) = msg {
+            if self
+                .deferred_out_of_sync
+                .as_ref()
+                .is_none_or(|deferred| deferred.max_seen_height < out_of_sync.max_seen_height)
+            {
+                self.deferred_out_of_sync = Some(out_of_sync);
+            }
+        }
+    }
+
     async fn handle_out_of_sync(
         &mut self,
         OutOfSync { max_seen_height }: OutOfSync,
@@ -189,7 +243,7 @@ impl SyncWorker {
                     let contract_height = BlockHeight(contract_height);
                     if contract_height > to_height {
                         to_height = contract_height;
-                        snapshot_kind = SnapshotKind::Fast;
+                        snapshot_kind = SnapshotKind::Reconcile;
                     }
                 }
                 Err(err) => {
@@ -220,15 +274,14 @@ impl SyncWorker {
             .await;
 
         let so = tokio::select! {
-            so = self.wait_for_snapshot_offer(snapshot_id) => so?,
+            so = self.wait_for_snapshot_offer(snapshot_id, snapshot_kind) => so?,
             _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
                 warn!(?snapshot_id, "snapshot offer timed out");
                 return Ok(());
             },
         };
 
-        self.handle_snapshot_offer(snapshot_kind, to_height, so)
-            .await?;
+        self.handle_snapshot_offer(to_height, so).await?;
 
         Ok(())
     }
@@ -236,11 +289,16 @@ impl SyncWorker {
     async fn wait_for_snapshot_offer(
         &mut self,
         snapshot_id: SnapshotId,
+        kind: SnapshotKind,
     ) -> Result<SyncSnapshotOffer, Error> {
         while let Some(msg) = self.channel.recv().await {
             match msg {
-                Message::SnapshotOffer(so) if so.snapshot_id == snapshot_id => return Ok(so),
-                _ => {}
+                Message::SnapshotOffer(so)
+                    if so.snapshot_id == snapshot_id && is_acceptable_offer(kind, so.kind) =>
+                {
+                    return Ok(so);
+                }
+                msg => self.defer(msg),
             }
         }
 
@@ -249,40 +307,60 @@ impl SyncWorker {
 
     async fn handle_snapshot_offer(
         &mut self,
-        kind: SnapshotKind,
         to_height: BlockHeight,
-        SyncSnapshotOffer { peer, snapshot_id }: SyncSnapshotOffer,
+        SyncSnapshotOffer {
+            peer,
+            snapshot_id,
+            mut kind,
+        }: SyncSnapshotOffer,
     ) -> Result<(), Error> {
-        let from_height = self.node.height() + BlockHeight(1);
+        loop {
+            let from_height = self.node.height() + BlockHeight(1);
+
+            info!(
+                ?snapshot_id,
+                ?from_height,
+                ?to_height,
+                ?peer,
+                ?kind,
+                "Accepting snapshot offer"
+            );
+            let accept = SnapshotAccept {
+                snapshot_id,
+                from_height,
+                to_height,
+                kind,
+            };
+            self.node
+                .send(peer, NetworkEvent::SnapshotAccept(accept))
+                .await;
 
-        info!(
-            ?snapshot_id,
-            ?from_height,
-            ?to_height,
-            ?peer,
-            "Accepting snapshot offer"
-        );
-        let accept = SnapshotAccept {
-            snapshot_id,
-            from_height,
-            to_height,
-            kind,
-        };
-        self.node
-            .send(peer, NetworkEvent::SnapshotAccept(accept))
-            .await;
+            let (peer, sc) = tokio::select! {
+                _ = tokio::time::sleep(self.timeout) => {
+                    warn!(?snapshot_id, ?peer, "snapshot chunk timed out");
+                    return Ok(());
+                }
+                sc = self.wait_for_snapshot_chunk(peer, snapshot_id) => sc?,
+            };
+
+            match sc {
+                SnapshotChunk::Slow(sc) => return self.handle_snapshot_chunk_slow(peer, sc).await,
+                SnapshotChunk::Fast(sc) => return self.handle_snapshot_chunk_fast(peer, sc).await,
+                SnapshotChunk::Reconcile(sc) if kind == SnapshotKind::Reconcile => {
+                    if self.handle_snapshot_chunk_reconcile(peer, sc).await? {
+                        return Ok(());
+                    }
 
-        let (peer, sc) = tokio::select! {
-            _ = tokio::time::sleep(self.timeout) => {
-                warn!(?snapshot_id, ?peer, "snapshot chunk timed out");
-                return Ok(());
+                    // The peer may have pruned the tree since it offered to reconcile it
+                    warn!(?snapshot_id, ?peer, "Falling back to a fast snapshot");
+                    kind = SnapshotKind::Fast;
+                }
+                SnapshotChunk::Reconcile(_) => {
+                    warn!(?snapshot_id, ?peer, "Unexpected reconcile snapshot chunk");
+                    return Ok(());
+                }
             }
-            sc = self.wait_for_snapshot_chunk(peer, snapshot_id) => sc?,
-        };
-
-        self.handle_snapshot_chunk(peer, sc).await?;
-
-        Ok(())
+        }
     }
 
     async fn wait_for_snapshot_chunk(
@@ -297,24 +375,13 @@ impl SyncWorker {
                 {
                     return Ok((peer, sc));
                 }
-                _ => {}
+                msg => self.defer(msg),
             }
         }
 
         Err(Error::ChannelWasClosed)
     }
 
-    async fn handle_snapshot_chunk(
-        &mut self,
-        peer: PeerId,
-        sc: SnapshotChunk,
-    ) -> Result<(), Error> {
-        match sc {
-            SnapshotChunk::Slow(sc) => self.handle_snapshot_chunk_slow(peer, sc).await,
-            SnapshotChunk::Fast(sc) => self.handle_snapshot_chunk_fast(peer, sc).await,
-        }
-    }
-
     async fn handle_snapshot_chunk_slow(
         &mut self,
         peer: PeerId,
@@ -379,14 +446,14 @@ impl SyncWorker {
             return Ok(());
         };
 
-        {
-            let mut tree = self.node.notes_tree().write();
-            Self::apply_fast_snapshot_chunk(&mut tree, &block, &elements)?;
+        self.node.reset_notes_tree(|tree| {
+            Self::apply_fast_snapshot_chunk(tree, &block, &elements)?;
             self.node
                 .block_cache
                 .lock()
-                .confirm(block.content.header.height - BlockHeight(1))
-        }
+                .confirm(block.content.header.height - BlockHeight(1));
+            Ok::<_, Error>(())
+        })?;
 
         self.node.receive_proposal(*block).map_err(Box::new)?;
         self.node.ticker.tick();
@@ -394,30 +461,163 @@ impl SyncWorker {
         Ok(())
     }
 
-    fn apply_fast_snapshot_chunk(
-        tree: &mut PersistentMerkleTree,
-        block: &Block,
-        elements: &[Element],
-    ) -> Result<(), Error> {
-        // Elements for the last block in the chunk
-        let mut last_block_elements = HashMap::<
-            Element,
-            // true = add
-            // false = remove
-            bool,
-        >::new();
-        for utxo in block.content.state.txns.iter() {
-            for e in &utxo.public_inputs.input_commitments {
-                last_block_elements.insert(*e, false);
+    /// Returns `false` if the chunk couldn't be applied, and a fast snapshot should be tried
+    /// instead
+    async fn handle_snapshot_chunk_reconcile(
+        &mut self,
+        peer: PeerId,
+        SnapshotChunkReconcile { snapshot_id, block }: SnapshotChunkReconcile,
+    ) -> Result<bool, Error> {
+        let Some(block) = block else {
+            warn!("Reconcile snapshot chunk missing block");
+            return Ok(false);
+        };
+
+        // The block's changes are applied when it's received, so reconcile with the tree before it
+        let height = block.content.header.height;
+        let Some(diff) = self
+            .reconcile_tree(peer, snapshot_id, height - BlockHeight(1))
+            .await?
+        else {
+            return Ok(false);
+        };
+
+        info!(
+            ?snapshot_id,
+            ?height,
+            missing = diff.missing.len(),
+            extra = diff.extra.len(),
+            "Reconciled notes tree"
+        );
+
+        self.node.reset_notes_tree(|tree| {
+            Self::apply_fast_snapshot_diff(tree, &block, diff.missing, diff.extra)?;
+            self.node
+                .block_cache
+                .lock()
+                .confirm(height - BlockHeight(1));
+            Ok::<_, Error>(())
+        })?;
+
+        self.node.receive_proposal(*block).map_err(Box::new)?;
+        self.node.ticker.tick();
+
+        Ok(true)
+    }
+
+    /// Find the differences between our notes tree and `peer`'s tree at `height`
+    ///
+    /// Subtree summaries are requested top-down, starting from the root, and only subtrees whose
+    /// hashes differ are descended into, so the cost depends on the size of the difference rather
+    /// than the size of the tree. Returns `None` if the peer stops responding, doesn't have the
+    /// tree at `height`, or the trees differ by more than [`TREE_SYNC_MAX_SUBTREES`] subtrees
+    async fn reconcile_tree(
+        &mut self,
+        peer: PeerId,
+        snapshot_id: SnapshotId,
+        height: BlockHeight,
+    ) -> Result<Option<SubtreeDiff>, Error> {
+        let mut pending = vec![SubtreeId::root()];
+        let mut diff = SubtreeDiff::default();
+        let mut requested_subtrees = 0;
+
+        while !pending.is_empty() {
+            let split_at = pending.len().saturating_sub(TREE_SYNC_SUBTREES_PER_REQUEST);
+            let subtrees = pending.split_off(split_at);
+
+            requested_subtrees += subtrees.len();
+            if requested_subtrees > TREE_SYNC_MAX_SUBTREES {
+                warn!(
+                    ?snapshot_id,
+                    ?peer,
+                    "Notes trees differ too much to reconcile"
+                );
+                return Ok(None);
             }
-            for e in &utxo.public_inputs.output_commitments {
-                last_block_elements.insert(*e, true);
+
+            let request = TreeSyncRequest {
+                snapshot_id,
+                height,
+                subtrees: subtrees.clone(),
+            };
+            self.node
+                .send(peer, NetworkEvent::TreeSyncRequest(request))
+                .await;
+
+            let response = tokio::select! {
+                _ = tokio::time::sleep(self.timeout) => {
+                    warn!(?snapshot_id, ?peer, "tree sync response timed out");
+                    return Ok(None);
+                }
+                response = self.wait_for_tree_sync_response(peer, snapshot_id, height) => response?,
+            };
+
+            if response.subtrees.is_empty() {
+                warn!(
+                    ?snapshot_id,
+                    ?peer,
+                    ?height,
+                    "Peer doesn't have the notes tree"
+                );
+                return Ok(None);
+            }
+
+            let requested = subtrees.into_iter().collect::<HashSet<_>>();
+
+            let notes_tree = self.node.notes_tree_snapshot();
+
+            for (id, summary) in response.subtrees {
+                // Ignore subtrees we didn't ask for
+                if !requested.contains(&id) {
+                    continue;
+                }
+
+                let subtree_diff = notes_tree.diff_subtree(&id, &summary).map_err(Box::new)?;
+                pending.extend(subtree_diff.mismatched);
+                diff.missing.extend(subtree_diff.missing);
+                diff.extra.extend(subtree_diff.extra);
+            }
+        }
+
+        Ok(Some(diff))
+    }
+
+    async fn wait_for_tree_sync_response(
+        &mut self,
+        peer: PeerId,
+        snapshot_id: SnapshotId,
+        height: BlockHeight,
+    ) -> Result<TreeSyncResponse, Error> {
+        while let Some(msg) = self.channel.recv().await {
+            match msg {
+                Message::TreeSyncResponse(response_peer, response)
+                    if response_peer == peer
+                        && response.snapshot_id == snapshot_id
+                        && response.height == height =>
+                {
+                    return Ok(response);
+                }
+                msg => self.defer(msg),
             }
         }
 
+        Err(Error::ChannelWasClosed)
+    }
+
+    fn apply_fast_snapshot_chunk(
+        tree: &mut NotesTree,
+        block: &Block,
+        elements: &[Element],
+    ) -> Result<(), Error> {
         // Build sets for diffing
         let elements_set: HashSet<_> = elements.iter().copied().collect();
-        let tree_elements_set: HashSet<_> = tree.tree().elements().map(|(e, _)| *e).collect();
+        let tree_elements_set: HashSet<_> = tree
+            .snapshot()
+            .elements()
+            .map_err(Box::new)?
+            .into_iter()
+            .map(|(e, _)| e)
+            .collect();
 
         // New elements (present in `elements` but not in the tree)
         let new_elements = elements_set
@@ -431,62 +631,65 @@ impl SyncWorker {
             .copied()
             .collect::<Vec<_>>();
 
-        // Validate root hash with both insertions and removals
-        if tree.tree().root_hash_with(&new_elements, &missing_elements)
-            != block.content.state.root_hash
-        {
+        Self::apply_fast_snapshot_diff(tree, block, new_elements, missing_elements)
+    }
+
+    /// Apply the difference between our tree and the tree before `block`, whose changes are
+    /// applied when the block is received
+    ///
+    /// The tree before a block can't be checked on its own, so the diff is only applied if adding
+    /// the block's leaves to it gives the block's root
+    fn apply_fast_snapshot_diff(
+        tree: &mut NotesTree,
+        block: &Block,
+        new_elements: Vec<Element>,
+        missing_elements: Vec<Element>,
+    ) -> Result<(), Error> {
+        let root_with_block = {
+            let snapshot = tree.snapshot();
+            let previous_root = snapshot
+                .root_hash_with(&new_elements, &missing_elements)
+                .map_err(Box::new)?;
+
+            let mut elements_with_block = new_elements.clone();
+            elements_with_block.extend(block.content.state.leaves(previous_root));
+
+            snapshot
+                .root_hash_with(&elements_with_block, &missing_elements)
+                .map_err(Box::new)?
+        };
+
+        if root_with_block != block.content.state.root_hash {
             error!("Fast snapshot chunk root hash mismatch");
             return Ok(());
         }
 
         let mut batch = smirk::Batch::new();
-        let mut block_elements_left_to_find = last_block_elements.clone();
-
-        // Apply insertions that are not part of the last block (the last block
-        // will be applied separately via receive_proposal)
         for element in new_elements {
             if element == Element::ZERO {
                 continue;
             }
-            if let Some(is_add) = last_block_elements.get(&element) {
-                if *is_add {
-                    // This element is expected to be added by the last block; skip now.
-                    block_elements_left_to_find.remove(&element);
-                    continue;
-                }
-            }
             batch.insert(element, SmirkMetadata::inserted_in(0))?;
         }
-
-        // Apply removals that are not part of the last block (the last block
-        // will remove its inputs separately via receive_proposal)
         for element in missing_elements {
             if element == Element::ZERO {
                 continue;
             }
-            if let Some(is_add) = last_block_elements.get(&element) {
-                if !*is_add {
-                    // This element is expected to be removed by the last block; skip now.
-                    block_elements_left_to_find.remove(&element);
-                    continue;
-                }
-            }
             batch.remove(element)?;
         }
 
-        if !block_elements_left_to_find.is_empty() {
-            error!(
-                ?block_elements_left_to_find,
-                "Fast snapshot chunk missing elements"
-            );
-            return Ok(());
-        }
-
-        tree.insert_batch(batch)?;
+        tree.insert_batch(batch).map_err(Box::new)?;
         Ok(())
     }
 }
 
+/// Whether a snapshot offer of `offered` kind can be accepted when `requested` was requested
+///
+/// A peer that can't reconcile its notes tree offers a [`SnapshotKind::Fast`] snapshot instead.
+fn is_acceptable_offer(requested: SnapshotKind, offered: SnapshotKind) -> bool {
+    requested == offered || (requested == SnapshotKind::Reconcile && offered == SnapshotKind::Fast)
+}
+
 /// An out of sync node sent a snapshot request,
 /// if we are in sync, we should send a snapshot offer.
 pub(crate) async fn handle_snapshot_request(
@@ -494,17 +697,26 @@ pub(crate) async fn handle_snapshot_request(
     peer: PeerId,
     snapshot_id: SnapshotId,
     from_height: BlockHeight,
-    _to_height: BlockHeight,
-    _kind: SnapshotKind,
+    to_height: BlockHeight,
+    kind: SnapshotKind,
 ) -> Result<(), Error> {
     if node.is_out_of_sync() || from_height > node.height() {
         info!("Ignoring snapshot request, we're too far behind");
         return Ok(());
     }
 
-    info!(?snapshot_id, "Sending snapshot offer");
+    // Disk notes trees and trees past the history window can't be reconciled, but they can
+    // still be sent in full
+    let kind = match kind {
+        SnapshotKind::Reconcile if !node.has_notes_tree_at(to_height - BlockHeight(1)) => {
+            SnapshotKind::Fast
+        }
+        kind => kind,
+    };
+
+    info!(?snapshot_id, ?kind, "Sending snapshot offer");
 
-    let offer = NetworkSnapshotOffer { snapshot_id };
+    let offer = NetworkSnapshotOffer { snapshot_id, kind };
     node.send(peer, NetworkEvent::SnapshotOffer(offer)).await;
 
     Ok(())
@@ -537,6 +749,9 @@ pub(crate) async fn handle_snapshot_accept(
         SnapshotKind::Fast => {
             send_snapshot_chunk_fast(node, peer, snapshot_id, from_height, to_height).await
         }
+        SnapshotKind::Reconcile => {
+            send_snapshot_chunk_reconcile(node, peer, snapshot_id, to_height).await
+        }
     }
 }
 
@@ -589,15 +804,16 @@ async fn send_snapshot_chunk_fast(
     let block = node.get_block(to_height).map_err(Box::new)?;
 
     let elements = node
-        .notes_tree()
-        .read()
-        .tree()
+        .notes_tree_snapshot()
         .elements()
+        .map_err(Box::new)?
+        .into_iter()
         .filter_map(|(e, meta)| {
             // We can't filter by from_height,
-            // because we don't know the height of the elements if they were fast-synced
-            if meta.inserted_in <= to_height.0 {
-                Some(*e)
+            // because we don't know the height of the elements if they were fast-synced.
+            // The block's own elements are applied by the peer when it receives the block
+            if meta.inserted_in < to_height.0 {
+                Some(e)
             } else {
                 None
             }
@@ -618,6 +834,68 @@ async fn send_snapshot_chunk_fast(
     Ok(())
 }
 
+async fn send_snapshot_chunk_reconcile(
+    node: &NodeShared,
+    peer: PeerId,
+    snapshot_id: SnapshotId,
+    to_height: BlockHeight,
+) -> Result<(), Error> {
+    let block = node.get_block(to_height).map_err(Box::new)?;
+
+    // The peer will request the notes tree at `to_height` with tree sync requests
+    node.send(
+        peer,
+        NetworkEvent::SnapshotChunk(SnapshotChunk::Reconcile(SnapshotChunkReconcile {
+            snapshot_id,
+            block: block.map(|b| Box::new(b.into_block())),
+        })),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// A peer reconciling its notes tree with ours wants summaries of some of our subtrees
+pub(crate) async fn handle_tree_sync_request(
+    node: &NodeShared,
+    peer: PeerId,
+    TreeSyncRequest {
+        snapshot_id,
+        height,
+        subtrees,
+    }: TreeSyncRequest,
+) -> Result<(), Error> {
+    let subtrees = match node.notes_tree_at(height).await {
+        Ok(tree) => subtrees
+            .into_iter()
+            .take(TREE_SYNC_SUBTREES_PER_REQUEST)
+            // Deeper subtrees would be below the leaves
+            .filter(|id| id.path().len() < MERKLE_TREE_DEPTH)
+            .filter_map(|id| {
+                let summary = tree.subtree_summary(&id, TREE_SYNC_MAX_ELEMENTS)?;
+                Some((id, summary))
+            })
+            .collect(),
+        Err(err) => {
+            // An empty response tells the peer to give up
+            warn!(?err, ?height, "Can't serve tree sync request");
+            Vec::new()
+        }
+    };
+
+    node.send(
+        peer,
+        NetworkEvent::TreeSyncResponse(TreeSyncResponse {
+            snapshot_id,
+            height,
+            subtrees,
+        }),
+    )
+    .await;
+
+    Ok(())
+}
+
 #[cfg(test)]
 #[path = "sync_test.rs"]
 mod sync_test;
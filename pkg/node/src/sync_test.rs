@@ -1,5 +1,8 @@
-use super::SyncWorker;
-use crate::{PersistentMerkleTree, block::Block, constants::MERKLE_TREE_DEPTH, types::BlockHeight};
+use super::{SyncWorker, is_acceptable_offer};
+use crate::{
+    NotesTree, NotesTreeCache, PersistentMerkleTree, block::Block, config::NotesTreeStorage,
+    constants::MERKLE_TREE_DEPTH, network::SnapshotKind, types::BlockHeight,
+};
 use element::Element;
 use prover::smirk_metadata::SmirkMetadata;
 use zk_primitives::{UtxoProof, UtxoPublicInput};
@@ -8,121 +11,77 @@ fn e(n: u64) -> Element {
     Element::new(n)
 }
 
-fn make_block(height: u64, root: Element, inputs: [Element; 2], outputs: [Element; 2]) -> Block {
+fn make_block(
+    height: u64,
+    root: Element,
+    nullifiers: [Element; 2],
+    outputs: [Element; 2],
+) -> Block {
     let mut block = Block::default();
     block.content.header.height = BlockHeight(height);
     block.content.state.root_hash = root;
-    block.content.state.txns = vec![UtxoProof {
-        proof: Default::default(),
-        public_inputs: UtxoPublicInput {
-            input_commitments: inputs,
-            output_commitments: outputs,
-            messages: [Element::ZERO; 5],
-        },
-    }];
+    block.content.state.txns = vec![
+        UtxoProof {
+            proof: Default::default(),
+            public_inputs: UtxoPublicInput {
+                root: Element::ZERO,
+                nullifiers,
+                output_commitments: outputs,
+                messages: [Element::ZERO; 5],
+            },
+        }
+        .into(),
+    ];
     block
 }
 
-fn compute_fast_snapshot_diffs(
-    tree: &PersistentMerkleTree,
-    block: &Block,
-    elements: &[Element],
-) -> (
-    Vec<Element>,
-    Vec<Element>,
-    std::collections::HashMap<Element, bool>,
-) {
-    // Elements for the last block in the chunk (ignoring ZERO)
-    let mut last_block_elements = std::collections::HashMap::<Element, bool>::new();
-    for utxo in block.content.state.txns.iter() {
-        for e in &utxo.public_inputs.input_commitments {
-            if *e != Element::ZERO {
-                last_block_elements.insert(*e, false);
-            }
-        }
-        for e in &utxo.public_inputs.output_commitments {
-            if *e != Element::ZERO {
-                last_block_elements.insert(*e, true);
-            }
-        }
-    }
-
-    let elements_set: std::collections::HashSet<_> = elements.iter().copied().collect();
-    let tree_elements_set: std::collections::HashSet<_> =
-        tree.tree().elements().map(|(e, _)| *e).collect();
-
-    let mut new_elements = elements_set
-        .difference(&tree_elements_set)
-        .copied()
-        .collect::<Vec<_>>();
-    let mut missing_elements = tree_elements_set
-        .difference(&elements_set)
-        .copied()
-        .collect::<Vec<_>>();
-
-    // Track last block elements that are still unaccounted for in the snapshot `elements`
-    let mut block_elements_left_to_find = last_block_elements.clone();
-    // Filter out last-block changes from the returned diffs (they are applied by the block)
-    new_elements.retain(|e| {
-        if last_block_elements.get(e) == Some(&true) {
-            block_elements_left_to_find.remove(e);
-            false
-        } else {
-            true
-        }
-    });
-
-    missing_elements.retain(|e| {
-        if last_block_elements.get(e) == Some(&false) {
-            block_elements_left_to_find.remove(e);
-            false
-        } else {
-            true
-        }
-    });
-
-    (new_elements, missing_elements, block_elements_left_to_find)
+/// A block inserting nullifier 5 and output 6 into `previous`
+fn block_after(height: u64, previous: &smirk::Tree<MERKLE_TREE_DEPTH, ()>) -> Block {
+    let mut block = make_block(
+        height,
+        Element::ZERO,
+        [e(5), Element::ZERO],
+        [e(6), Element::ZERO],
+    );
+    let leaves = block.content.state.leaves(previous.root_hash());
+    block.content.state.root_hash = previous.root_hash_with(&leaves, &[]);
+    block
 }
 
-#[test]
-fn fast_snapshot_diff_computation() {
-    let dir = tempdir::TempDir::new("fast_snapshot").unwrap();
-    let tree_path = dir.path().join("db");
-    let mut tree: PersistentMerkleTree = PersistentMerkleTree::new(&tree_path).unwrap();
+fn tree_elements(tree: &NotesTree) -> std::collections::HashSet<Element> {
+    let elements = tree.snapshot().elements().unwrap();
+    elements.into_iter().map(|(e, _)| e).collect()
+}
 
-    // Initial tree: {1,2,3}
-    for v in [1u64, 2, 3] {
-        tree.insert(Element::new(v), SmirkMetadata::inserted_in(0))
+fn memory_tree(elements: &[u64]) -> NotesTree {
+    let mut tree = PersistentMerkleTree::new_in_memory();
+    for v in elements {
+        tree.insert(Element::new(*v), SmirkMetadata::inserted_in(0))
             .unwrap();
     }
+    NotesTree::Memory(tree)
+}
 
-    // Expected final state after to_height (elements vector): {2,4,5}
-    let elements = vec![e(2), e(4), e(5)];
+#[test]
+fn fast_snapshot_is_applied_up_to_last_block() {
+    // Initial tree: {1,2,3}
+    let mut tree = memory_tree(&[1, 2, 3]);
 
-    // Last block removes 3, adds 5
-    let block_root = tree.tree().root_hash_with(&[e(4), e(5)], &[e(1), e(3)]);
-    let block = make_block(10, block_root, [e(3), Element::ZERO], [e(5), Element::ZERO]);
+    // Peer's tree before the last block: {2,4}
+    let previous: smirk::Tree<MERKLE_TREE_DEPTH, ()> = smirk::smirk! { 2, 4 };
+    let block = block_after(10, &previous);
 
-    let (new_elems, missing_elems, left_to_find) =
-        compute_fast_snapshot_diffs(&tree, &block, &elements);
+    SyncWorker::apply_fast_snapshot_chunk(&mut tree, &block, &[e(2), e(4)]).unwrap();
 
-    let new_set: std::collections::HashSet<_> = new_elems.into_iter().collect();
-    let missing_set: std::collections::HashSet<_> = missing_elems.into_iter().collect();
-    assert_eq!(new_set, [e(4)].into_iter().collect());
-    assert_eq!(missing_set, [e(1)].into_iter().collect());
-    assert!(left_to_find.is_empty());
+    // The last block is applied when it's received
+    assert_eq!(tree_elements(&tree), [e(2), e(4)].into_iter().collect());
+    assert_eq!(tree.root_hash(), previous.root_hash());
 }
 
 #[test]
 fn fast_snapshot_root_mismatch_does_not_mutate_tree() {
-    let dir = tempdir::TempDir::new("fast_snapshot_mismatch").unwrap();
-    let mut tree: PersistentMerkleTree = PersistentMerkleTree::new(dir.path().join("db")).unwrap();
-
     // Initial tree: {1,2}
-    for v in [1u64, 2] {
-        tree.insert(Element::new(v), SmirkMetadata::inserted_in(0))
-            .unwrap();
-    }
+    let mut tree = memory_tree(&[1, 2]);
 
     // Elements claim state {2,3}
     let elements = vec![e(2), e(3)];
@@ -131,47 +90,84 @@ fn fast_snapshot_root_mismatch_does_not_mutate_tree() {
     let wrong_root = smirk::empty_tree_hash(MERKLE_TREE_DEPTH);
     let block = make_block(5, wrong_root, [Element::ZERO; 2], [Element::ZERO; 2]);
 
-    // Capture before state
-    let before: std::collections::HashSet<_> = tree.tree().elements().map(|(e, _)| *e).collect();
-
+    let before = tree_elements(&tree);
     SyncWorker::apply_fast_snapshot_chunk(&mut tree, &block, &elements).unwrap();
 
     // Tree unchanged
-    let after: std::collections::HashSet<_> = tree.tree().elements().map(|(e, _)| *e).collect();
-    assert_eq!(before, after);
+    assert_eq!(before, tree_elements(&tree));
 }
 
 #[test]
-fn fast_snapshot_missing_last_block_elements_diff_computation() {
-    let dir = tempdir::TempDir::new("fast_snapshot_missing_last").unwrap();
-    let mut tree: PersistentMerkleTree = PersistentMerkleTree::new(dir.path().join("db")).unwrap();
-
+fn fast_snapshot_with_wrong_elements_does_not_mutate_tree() {
     // Initial tree: {1,3}
-    for v in [1u64, 3] {
-        tree.insert(Element::new(v), SmirkMetadata::inserted_in(0))
+    let mut tree = memory_tree(&[1, 3]);
+
+    let previous: smirk::Tree<MERKLE_TREE_DEPTH, ()> = smirk::smirk! { 1, 2 };
+    let block = block_after(7, &previous);
+
+    // The elements include the last block's output, which is applied when the block is received
+    let before = tree_elements(&tree);
+    SyncWorker::apply_fast_snapshot_chunk(&mut tree, &block, &[e(1), e(2), e(6)]).unwrap();
+    assert_eq!(before, tree_elements(&tree));
+
+    // The elements are missing 2
+    SyncWorker::apply_fast_snapshot_chunk(&mut tree, &block, &[e(1)]).unwrap();
+    assert_eq!(before, tree_elements(&tree));
+}
+
+#[test]
+fn reconciled_diff_is_applied_except_last_block() {
+    for storage in [NotesTreeStorage::Memory, NotesTreeStorage::Disk] {
+        reconciled_diff_is_applied_except_last_block_with(storage);
+    }
+}
+
+fn reconciled_diff_is_applied_except_last_block_with(storage: NotesTreeStorage) {
+    let mut tree = NotesTree::new_in_memory(storage, NotesTreeCache::default());
+
+    // Initial tree: {1,2,3}
+    let mut batch = smirk::Batch::new();
+    for v in [1u64, 2, 3] {
+        batch
+            .insert(Element::new(v), SmirkMetadata::inserted_in(0))
             .unwrap();
     }
+    tree.insert_batch(batch).unwrap();
+
+    // Peer's tree before the last block: {2,3,4}
+    let remote: smirk::Tree<MERKLE_TREE_DEPTH, ()> = smirk::smirk! { 2, 3, 4 };
+    let block = block_after(10, &remote);
+
+    let mut pending = vec![smirk::SubtreeId::root()];
+    let mut missing = Vec::new();
+    let mut extra = Vec::new();
+    while let Some(id) = pending.pop() {
+        let summary = remote.subtree_summary(&id, 1).unwrap();
+        let diff = tree.snapshot().diff_subtree(&id, &summary).unwrap();
+        pending.extend(diff.mismatched);
+        missing.extend(diff.missing);
+        extra.extend(diff.extra);
+    }
 
-    // Final intended state after block: remove 3, add 5, plus 2
-    let elements_full = vec![e(1), e(2), e(5)];
-    let expected_root = tree.tree().root_hash_with(&[e(2), e(5)], &[e(3)]);
+    SyncWorker::apply_fast_snapshot_diff(&mut tree, &block, missing, extra).unwrap();
 
-    // Last block removes 3, adds 5
-    let block = make_block(
-        7,
-        expected_root,
-        [e(3), Element::ZERO],
-        [e(5), Element::ZERO],
+    // Everything but the last block has been applied
+    assert_eq!(
+        tree_elements(&tree),
+        [e(2), e(3), e(4)].into_iter().collect()
     );
+}
 
-    // Provide an elements vector missing the last block's add (5)
-    let elements_missing = vec![e(1), e(2)];
+#[test]
+fn reconcile_offers_can_fall_back_to_fast() {
+    use SnapshotKind::*;
 
-    let (_new_full, _missing_full, left_full) =
-        compute_fast_snapshot_diffs(&tree, &block, &elements_full);
-    assert!(left_full.is_empty());
+    assert!(is_acceptable_offer(Reconcile, Reconcile));
+    assert!(is_acceptable_offer(Reconcile, Fast));
+    assert!(is_acceptable_offer(Fast, Fast));
+    assert!(is_acceptable_offer(Slow, Slow));
 
-    let (_new_missing, _missing_missing, left_missing) =
-        compute_fast_snapshot_diffs(&tree, &block, &elements_missing);
-    assert!(left_missing.contains_key(&e(5))); // last block add is missing from snapshot elements
+    assert!(!is_acceptable_offer(Reconcile, Slow));
+    assert!(!is_acceptable_offer(Fast, Reconcile));
+    assert!(!is_acceptable_offer(Slow, Fast));
 }
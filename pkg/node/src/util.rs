@@ -10,3 +10,9 @@ pub(crate) fn generate_p2p_key() -> (identity::Keypair, [u8; 32]) {
     let keypair = identity::Keypair::ed25519_from_bytes(bytes).unwrap();
     (keypair, bytes)
 }
+
+/// Compare secrets without short-circuiting on the first differing byte, so response times
+/// don't leak how much of a guessed token is right
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
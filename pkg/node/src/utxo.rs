@@ -1,53 +1,60 @@
 use crate::Mode;
-use crate::{BlockFormat, PersistentMerkleTree, Result, types::BlockHeight};
+use crate::constants::MAX_ROOT_AGE;
+use crate::{BlockFormat, NotesTreeSnapshot, Result, types::BlockHeight};
 use barretenberg::Verify;
 use block_store::BlockStore;
 use element::Element;
 use node_interface::{ElementData, ElementsVecData, RpcError};
-use zk_primitives::UtxoProof;
+use zk_primitives::{TxnProof, root_leaf};
 
-/// Validate a UTXO txn, we check the following:
+/// Validate a txn, from any [`TxnProof`] circuit, we check the following:
 /// - The proof is valid
-/// - The recent root is recent enough
-/// - The input notes are not already spent (not in tree)
+/// - A swap has both of its legs, and they match
+/// - The root the input notes are spent from is the current root, or was replaced within the
+///   last [`MAX_ROOT_AGE`] blocks
+/// - The input notes are not already spent (their nullifiers haven't been revealed)
 /// - The output notes do not already exist (not in tree)
 pub fn validate_txn(
     _mode: Mode,
-    utxo_proof: &UtxoProof,
-    _height: BlockHeight,
+    utxo_proof: &TxnProof,
+    height: BlockHeight,
     block_store: &BlockStore<BlockFormat>,
-    notes_tree: &PersistentMerkleTree,
+    notes_tree: &NotesTreeSnapshot,
 ) -> Result<()> {
+    match utxo_proof {
+        TxnProof::SwapLeg(_) => Err(RpcError::UnpairedSwapLeg)?,
+        TxnProof::Swap(swap) if !swap.legs_match() => {
+            Err(RpcError::SwapLegsMismatch(ElementData {
+                element: swap.hash(),
+            }))?
+        }
+        _ => {}
+    }
+
     if let Err(_err) = utxo_proof.verify() {
         Err(RpcError::InvalidProof)?;
     }
 
-    let public_inputs = &utxo_proof.public_inputs;
-
-    let [input_0, input_1] = public_inputs.input_commitments;
-    if input_0 != Element::ZERO && input_0 == input_1 {
-        Err(RpcError::TxnDuplicateInputCommitments(ElementsVecData {
-            elements: vec![input_0],
+    if let Some(nullifier) = first_duplicate(&utxo_proof.nullifiers()) {
+        Err(RpcError::TxnDuplicateNullifiers(ElementsVecData {
+            elements: vec![nullifier],
         }))?;
     }
 
-    let [output_0, output_1] = public_inputs.output_commitments;
-    if output_0 != Element::ZERO && output_0 == output_1 {
+    if let Some(output) = first_duplicate(&utxo_proof.output_commitments()) {
         Err(RpcError::TxnDuplicateOutputCommitments(ElementsVecData {
-            elements: vec![output_0],
+            elements: vec![output],
         }))?;
     }
 
     // Check if any of the txn inserts are already in the tree
-    let tree = notes_tree.tree();
-
-    for leaf in utxo_proof.public_inputs.output_commitments {
+    for leaf in utxo_proof.output_commitments() {
         if leaf >= Element::MODULUS {
             Err(RpcError::InvalidElementSize(ElementData { element: leaf }))?;
         }
 
         if leaf != Element::ZERO {
-            if tree.contains_element(&leaf) {
+            if notes_tree.contains_element(&leaf)? {
                 Err(RpcError::TxnOutputCommitmentsExist(ElementsVecData {
                     elements: vec![leaf],
                 }))?;
@@ -65,14 +72,28 @@ pub fn validate_txn(
         }
     }
 
-    for leaf in utxo_proof.public_inputs.input_commitments {
-        if leaf >= Element::MODULUS {
-            Err(RpcError::InvalidElementSize(ElementData { element: leaf }))?;
+    // Mints have no inputs, so don't spend from a root. Each leg of a swap has its own root
+    for slot in utxo_proof.slots() {
+        if slot.has_inputs() && !is_recent_root(slot.root(), height, notes_tree)? {
+            Err(RpcError::TxnRootNotRecentEnough(ElementData {
+                element: slot.root(),
+            }))?;
+        }
+    }
+
+    for nullifier in utxo_proof.nullifiers() {
+        if nullifier >= Element::MODULUS {
+            Err(RpcError::InvalidElementSize(ElementData {
+                element: nullifier,
+            }))?;
         }
 
-        if leaf != Element::ZERO && !tree.contains_element(&leaf) {
-            Err(RpcError::TxnInputCommitmentsNotInTree(ElementsVecData {
-                elements: vec![leaf],
+        if nullifier != Element::ZERO
+            && (block_store.get_nullifier(nullifier)?.is_some()
+                || notes_tree.contains_element(&nullifier)?)
+        {
+            Err(RpcError::TxnNullifiersSpent(ElementsVecData {
+                elements: vec![nullifier],
             }))?;
         }
     }
@@ -96,3 +117,31 @@ pub fn validate_txn(
 
     Ok(())
 }
+
+/// Whether `root` is the current root of the notes tree, or was replaced within the last
+/// [`MAX_ROOT_AGE`] blocks before `height`
+///
+/// A block records the root it replaces as a [`root_leaf`], inserted at the block's height, so
+/// the leaf's metadata says when the root stopped being current
+fn is_recent_root(
+    root: Element,
+    height: BlockHeight,
+    notes_tree: &NotesTreeSnapshot,
+) -> Result<bool> {
+    if root == notes_tree.root_hash() {
+        return Ok(true);
+    }
+
+    Ok(notes_tree
+        .get(root_leaf(root))?
+        .is_some_and(|metadata| height.0.saturating_sub(metadata.inserted_in) <= MAX_ROOT_AGE))
+}
+
+/// The first non-zero element that appears more than once in `elements`
+fn first_duplicate(elements: &[Element]) -> Option<Element> {
+    elements
+        .iter()
+        .enumerate()
+        .find(|(i, e)| **e != Element::ZERO && elements[i + 1..].contains(e))
+        .map(|(_, e)| *e)
+}
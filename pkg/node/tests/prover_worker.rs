@@ -0,0 +1,120 @@
+use std::{
+    net::TcpListener,
+    process::{Child, Command},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use barretenberg::{Prove, Verify};
+use contracts::{RollupContract, SecretKey};
+use element::Element;
+use node::{
+    PersistentMerkleTree,
+    prover::{
+        jobs::JobQueue,
+        remote::{WorkerClient, create_coordinator_server},
+    },
+};
+use prover::{MAXIMUM_TXNS, Prover, Transaction};
+use testutil::{ACCOUNT_1_SK, eth::EthNode};
+use zk_primitives::{
+    AggAgg, AggUtxoProof, Note, Utxo, bridged_polygon_usdc_note_kind, get_address_for_private_key,
+};
+
+const TOKEN: &str = "worker-token";
+
+/// Kills the worker process when the test ends, even if it panics
+struct Worker(Child);
+
+impl Worker {
+    fn spawn(coordinator_url: &str) -> Self {
+        let child = Command::new(env!("CARGO_BIN_EXE_prover_worker"))
+            .args(["--coordinator-url", coordinator_url])
+            .args(["--token", TOKEN])
+            .args(["--poll-interval-ms", "100"])
+            .spawn()
+            .expect("Failed to start prover worker");
+
+        Self(child)
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+    }
+}
+
+fn free_laddr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn workers_prove_leased_jobs() {
+    let eth_node = EthNode::default().run_and_deploy().await;
+    let contract =
+        RollupContract::from_eth_node(&eth_node, SecretKey::from_str(ACCOUNT_1_SK).unwrap())
+            .await
+            .unwrap();
+    let prover = Arc::new(Prover::new(contract));
+
+    let jobs = Arc::new(JobQueue::new(
+        Duration::from_secs(300),
+        3,
+        Duration::from_secs(3600),
+    ));
+    let laddr = free_laddr();
+    let server =
+        create_coordinator_server(&laddr, Arc::clone(&jobs), Some(TOKEN.to_owned())).unwrap();
+    let coordinator_url = format!("http://{laddr}");
+
+    let alice_pk = Element::new(0xA11CE);
+    let note = Note::new(
+        get_address_for_private_key(alice_pk),
+        Element::new(100),
+        bridged_polygon_usdc_note_kind(),
+    );
+    let utxo_proof = Utxo::new_mint([note, Note::padding_note()])
+        .prove()
+        .unwrap();
+
+    let mut txns: [Option<Transaction>; MAXIMUM_TXNS] = Default::default();
+    txns[0] = Some(Transaction::new(utxo_proof.into()));
+
+    let notes_tree = PersistentMerkleTree::new_in_memory();
+    let [first, second] = prover
+        .aggregation_inputs(notes_tree.tree(), 1, txns)
+        .await
+        .unwrap();
+    assert!(second.is_none());
+
+    let prove = async {
+        // Workers with the wrong token can't lease jobs
+        assert!(
+            WorkerClient::new(&coordinator_url, Some("wrong".to_owned()))
+                .lease()
+                .await
+                .is_err()
+        );
+
+        let _workers = [
+            Worker::spawn(&coordinator_url),
+            Worker::spawn(&coordinator_url),
+        ];
+
+        let first = jobs.prove_agg_utxo(first.unwrap()).await.unwrap();
+        jobs.prove_agg_agg(AggAgg::new([first, AggUtxoProof::default()]))
+            .await
+            .unwrap()
+    };
+
+    let proof = tokio::select! {
+        res = server => panic!("Coordinator stopped: {res:?}"),
+        proof = prove => proof,
+    };
+
+    proof.verify().unwrap();
+    assert_eq!(proof.public_inputs.old_root, notes_tree.tree().root_hash());
+}
@@ -2,7 +2,8 @@ use std::sync::Arc;
 
 use element::Element;
 use hash::hash_merge;
-use zk_primitives::{InputNote, Note, Utxo};
+use node_interface::SpentNullifier;
+use zk_primitives::{InputNote, Note, Utxo, bridged_polygon_usdc_note_kind};
 
 use super::{Server, ServerConfig, mint, rollup_contract, usdc_contract};
 use barretenberg::Prove;
@@ -32,7 +33,8 @@ async fn list_elements_unspent_default() {
 
     let commitment = alice_note.commitment();
     let list = server
-        .list_elements(&[commitment], false)
+        .client
+        .list_elements(&[commitment], false, None)
         .await
         .expect("list_elements failed");
     assert_eq!(list.len(), 1, "expected exactly one element");
@@ -63,33 +65,31 @@ async fn list_elements_include_spent() {
     eth_tx.await.unwrap();
     let _tx = node_tx.await.unwrap();
 
-    // Spend Alice's note to Bob so Alice's commitment is removed from the tree
+    // Spend Alice's note to Bob, which reveals its nullifier but leaves the commitment in the tree
     let bob_pk = Element::new(0xB0B);
     let bob_address = hash_merge([bob_pk, Element::ZERO]);
-    let bob_note = Note::new_with_psi(bob_address, Element::from(5u64), Element::ZERO);
+    let bob_note = Note::new_with_psi(
+        bob_address,
+        Element::from(5u64),
+        Element::ZERO,
+        bridged_polygon_usdc_note_kind(),
+    );
     let input_note = InputNote::new(alice_note.clone(), alice_pk);
+    let nullifier = input_note.nullifier();
     let utxo = Utxo::new_send(
         [input_note, InputNote::padding_note()],
         [bob_note, Note::padding_note()],
     );
+    let utxo = server.with_merkle_paths(utxo).await;
     let snark = utxo.prove().unwrap();
-    let _resp = server.transaction(&snark).await.unwrap();
+    let resp = server.transaction(&snark).await.unwrap();
 
     let commitment = alice_note.commitment();
 
-    // Default behavior should not include spent elements
-    let list_default = server
-        .list_elements(&[commitment], false)
-        .await
-        .expect("list_elements failed");
-    assert!(
-        list_default.is_empty(),
-        "expected no elements without include_spent"
-    );
-
-    // With include_spent=true we should see it and it should be marked as spent
+    // The tree can't tell which commitment a nullifier spends, so the commitment is still listed
     let list_spent = server
-        .list_elements(&[commitment], true)
+        .client
+        .list_elements(&[commitment], true, None)
         .await
         .expect("list_elements failed");
     assert_eq!(
@@ -100,7 +100,22 @@ async fn list_elements_include_spent() {
     let item = &list_spent[0];
     assert_eq!(item.element, commitment);
     assert!(
-        item.spent,
-        "expected spent=true for previously spent element"
+        !item.spent,
+        "expected spent=false for a spent note's commitment"
+    );
+
+    // The nullifier records the spend
+    let nullifiers = server
+        .client
+        .list_nullifiers(&[nullifier])
+        .await
+        .expect("list_nullifiers failed");
+    assert_eq!(
+        nullifiers,
+        vec![SpentNullifier {
+            nullifier,
+            height: resp.height.0,
+            txn_hash: resp.txn_hash,
+        }]
     );
 }
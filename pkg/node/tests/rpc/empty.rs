@@ -9,6 +9,6 @@ use super::Server;
 async fn empty() {
     let eth_node = EthNode::default().run_and_deploy().await;
     let server = Server::setup_and_wait(ServerConfig::single_node(false), eth_node).await;
-    let resp = server.height().await.unwrap();
+    let resp = server.client.height().await.unwrap();
     assert_eq!(resp.root_hash, smirk::empty_tree_hash(MERKLE_TREE_DEPTH));
 }
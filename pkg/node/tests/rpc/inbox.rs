@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use element::Element;
+use hash::hash_merge;
+use node_interface::{ElementData, PostInboxNoteRequest, RpcError};
+use rand::{SeedableRng, rngs::StdRng};
+use testutil::eth::EthNode;
+use zk_primitives::{EncryptedNote, ViewingKey};
+
+use crate::rpc::{Server, ServerConfig, mint, rollup_contract, usdc_contract};
+
+/// The default `inbox-max-notes-per-commitment`
+const MAX_NOTES_PER_COMMITMENT: usize = 4;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn inbox_notes() {
+    let eth_node = EthNode::default().run_and_deploy().await;
+    let server =
+        Server::setup_and_wait(ServerConfig::single_node(false), Arc::clone(&eth_node)).await;
+    let rollup = rollup_contract(server.rollup_contract_addr, &eth_node).await;
+    let usdc = usdc_contract(&rollup, &eth_node).await;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let alice_pk = Element::new(0xA11CE);
+    let alice_viewing_key = ViewingKey::from_secret_key(alice_pk);
+    let alice_address = hash_merge([alice_pk, Element::ZERO]);
+
+    let (note, eth_tx, node_tx) = mint(
+        &rollup,
+        &usdc,
+        &server,
+        alice_address,
+        Element::from(100u64),
+        Element::ZERO,
+    );
+    let commitment = note.commitment();
+    let encrypt =
+        |rng: &mut StdRng| EncryptedNote::encrypt(&note, &alice_viewing_key.public_key(), rng);
+
+    // The commitment isn't known until the mint is submitted
+    let err = server
+        .client
+        .post_inbox_note(&PostInboxNoteRequest {
+            commitment,
+            note: encrypt(&mut rng),
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            node_interface::Error::Rpc(RpcError::ElementNotFound(ElementData { element }))
+                if *element == commitment
+        ),
+        "unexpected error: {err:?}"
+    );
+
+    eth_tx.await.unwrap();
+    node_tx.await.unwrap();
+
+    let first = encrypt(&mut rng);
+    let second = encrypt(&mut rng);
+    for note in [&first, &second, &first] {
+        server
+            .client
+            .post_inbox_note(&PostInboxNoteRequest {
+                commitment,
+                note: note.clone(),
+            })
+            .await
+            .unwrap();
+    }
+
+    // Reposting a note doesn't store it again
+    let notes = server.client.inbox_notes(commitment).await.unwrap();
+    assert_eq!(notes.len(), 2);
+    assert!(notes.iter().all(|n| n.commitment == commitment));
+    assert!(notes.iter().any(|n| n.note == first));
+    assert!(notes.iter().any(|n| n.note == second));
+    for n in &notes {
+        assert_eq!(
+            n.note.decrypt(&alice_viewing_key, commitment).unwrap(),
+            note
+        );
+    }
+
+    let listed = server
+        .client
+        .list_inbox_notes(&[commitment, Element::new(1)])
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(
+        server
+            .client
+            .inbox_notes(Element::new(1))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    for _ in notes.len()..MAX_NOTES_PER_COMMITMENT {
+        server
+            .client
+            .post_inbox_note(&PostInboxNoteRequest {
+                commitment,
+                note: encrypt(&mut rng),
+            })
+            .await
+            .unwrap();
+    }
+
+    let err = server
+        .client
+        .post_inbox_note(&PostInboxNoteRequest {
+            commitment,
+            note: encrypt(&mut rng),
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            node_interface::Error::Rpc(RpcError::InboxFull(ElementData { element }))
+                if *element == commitment
+        ),
+        "unexpected error: {err:?}"
+    );
+    assert_eq!(
+        server.client.inbox_notes(commitment).await.unwrap().len(),
+        MAX_NOTES_PER_COMMITMENT
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn inbox_rejects_invalid_notes() {
+    let eth_node = EthNode::default().run_and_deploy().await;
+    let server =
+        Server::setup_and_wait(ServerConfig::single_node(false), Arc::clone(&eth_node)).await;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut note = EncryptedNote::encrypt(
+        &zk_primitives::Note::padding_note(),
+        &ViewingKey::random(&mut rng).public_key(),
+        &mut rng,
+    );
+    note.ciphertext.truncate(16);
+
+    let err = server
+        .client
+        .post_inbox_note(&PostInboxNoteRequest {
+            commitment: Element::new(1),
+            note,
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, node_interface::Error::Rpc(RpcError::InvalidInboxNote)),
+        "unexpected error: {err:?}"
+    );
+}
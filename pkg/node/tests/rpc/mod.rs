@@ -1,16 +1,13 @@
 mod elements;
 mod empty;
+mod inbox;
 mod merkle;
 mod smirk;
 mod sync;
 mod transaction;
-mod types;
 
 use barretenberg::Prove;
 use element::Element;
-pub use types::*;
-
-use crate::rpc::types::ElementsListItem;
 
 use std::{
     env::VarError,
@@ -21,17 +18,22 @@ use std::{
     sync::{Arc, Mutex, mpsc},
 };
 
+use constants::MERKLE_TREE_DEPTH;
 use contracts::{Address, RollupContract, SecretKey, USDCContract, util::convert_h160_to_element};
 use futures::Future;
+use node_interface::{
+    MerklePathFormat, NodeClient, RetryPolicy, TransactionRequest, TransactionResponse,
+};
 use once_cell::sync::Lazy;
-use primitives::hash::CryptoHash;
-use reqwest::Url;
-use serde_json::json;
 use testutil::{PortPool, eth::EthNode};
 use tokio::runtime::RuntimeFlavor;
-use zk_primitives::{InputNote, Note, UtxoProof};
+#[cfg(feature = "utxo4")]
+use zk_primitives::Utxo4;
+use zk_primitives::{
+    InputNote, MerklePath, Note, TxnProof, Utxo, bridged_polygon_usdc_note_kind, root_leaf,
+};
 
-type Error = serde_json::Value;
+type Error = node_interface::Error;
 
 fn find_binary() -> PathBuf {
     let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -55,6 +57,8 @@ struct ServerConfig {
     rollup_contract: Address,
     secret_key: [u8; 32],
     mock_prover: bool,
+    /// Extra `POLY_` config variables passed to the node
+    env: Vec<(&'static str, String)>,
 }
 
 impl ServerConfig {
@@ -72,6 +76,7 @@ impl ServerConfig {
             .try_into()
             .unwrap(),
             mock_prover: false,
+            env: Vec::new(),
         }
     }
 
@@ -95,7 +100,8 @@ struct Server {
     keep_port_after_drop: bool,
     safe_eth_height_offset: u64,
     prover: bool,
-    client: reqwest::Client,
+    env: Vec<(&'static str, String)>,
+    client: NodeClient,
     eth_node: Arc<EthNode>,
     stdout: mpsc::Receiver<String>,
     stdout_sender: Option<mpsc::Sender<String>>,
@@ -156,13 +162,16 @@ impl Server {
         Self {
             process: None,
             root_dir,
-            client: reqwest::Client::new(),
+            // the tests expect to see every error, so requests are never retried
+            client: NodeClient::new(format!("http://localhost:{api_port}"))
+                .with_retry_policy(RetryPolicy::none()),
             keep_port_after_drop: config.keep_port_after_drop,
             safe_eth_height_offset: config.safe_eth_height_offset,
             secret_key: config.secret_key,
             rollup_contract_addr: config.rollup_contract,
             peers: vec![Peer { p2p_port }],
             prover: config.mock_prover,
+            env: config.env,
             api_port,
             p2p_port,
             eth_node,
@@ -174,12 +183,6 @@ impl Server {
         }
     }
 
-    fn base_url(&self) -> Url {
-        format!("http://localhost:{}", self.api_port)
-            .parse()
-            .unwrap()
-    }
-
     fn to_peer(&self) -> Peer {
         Peer {
             p2p_port: self.p2p_port,
@@ -231,6 +234,7 @@ impl Server {
             "POLY_SAFE_ETH_HEIGHT_OFFSET",
             self.safe_eth_height_offset.to_string(),
         );
+        command.envs(self.env.clone());
 
         let should_log = log_output.unwrap_or(
             std::env::var("LOG_NODE_OUTPUT")
@@ -275,7 +279,7 @@ impl Server {
         println!(
             "Node started: {}; Base URL: {}",
             process.id(),
-            self.base_url()
+            self.client.base_url()
         );
 
         self.process = Some(process);
@@ -360,18 +364,8 @@ impl Server {
         loop {
             let is_last_retry = retry == max_retries - 1;
 
-            let req = self
-                .client
-                .get(self.base_url().join("/v0/health").unwrap())
-                .build()
-                .unwrap();
-
-            match self.client.execute(req).await {
-                Ok(res) if res.status().is_success() => return Ok(()),
-                Ok(res) if is_last_retry => {
-                    return Err(format!("Failed to get health: {}", res.status()).into());
-                }
-                Ok(_) => {}
+            match self.client.health().await {
+                Ok(_) => return Ok(()),
                 Err(err) if is_last_retry => return Err(err.into()),
                 Err(_) => {}
             }
@@ -394,213 +388,88 @@ impl Server {
         Ok(())
     }
 
-    pub async fn transaction(&self, proof: &UtxoProof) -> Result<TransactionResp, Error> {
-        let res = self
-            .client
-            .post(self.base_url().join("/v0/transaction").unwrap())
-            .json(&json!({
-                "proof": proof,
-            }))
-            .send()
+    /// Submit `proof` and wait for it to be included in a block
+    pub async fn transaction(
+        &self,
+        proof: &(impl Into<TxnProof> + Clone),
+    ) -> Result<TransactionResponse, Error> {
+        self.client
+            .submit_transaction(&TransactionRequest {
+                proof: proof.clone().into(),
+            })
             .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
-        }
-
-        Ok(res.json::<TransactionResp>().await.unwrap())
     }
 
-    pub async fn height(&self) -> Result<HeightResp, Error> {
-        let res = self
-            .client
-            .get(self.base_url().join("/v0/height").unwrap())
-            .send()
-            .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
+    /// Set the root and input merkle paths of `utxo` from the node's current notes tree
+    pub async fn with_merkle_paths(&self, utxo: Utxo) -> Utxo {
+        match self.input_merkle_paths(&utxo.input_notes).await {
+            Some((root, merkle_paths)) => utxo.with_merkle_paths(root, merkle_paths),
+            None => utxo,
         }
-
-        Ok(res.json::<HeightResp>().await.unwrap())
     }
 
-    pub async fn merkle(&self, commitments: &[Element]) -> Result<MerklePathResponse, Error> {
-        let res = self
-            .client
-            .get(self.base_url().join("/v0/merkle").unwrap())
-            .query(&[(
-                "commitments",
-                commitments
-                    .iter()
-                    .map(|e| e.to_hex())
-                    .collect::<Vec<_>>()
-                    .join(","),
-            )])
-            .send()
-            .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
-        }
-
-        Ok(res.json::<MerklePathResponse>().await.unwrap())
-    }
-
-    pub async fn element(&self, element: Element) -> Result<ElementResponse, Error> {
-        let res = self
-            .client
-            .get(
-                self.base_url()
-                    .join(&format!("/v0/elements/{}", element.to_hex()))
-                    .unwrap(),
-            )
-            .send()
-            .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
+    /// Set the root and input merkle paths of `utxo` from the node's current notes tree
+    #[cfg(feature = "utxo4")]
+    pub async fn with_merkle_paths4(&self, utxo: Utxo4) -> Utxo4 {
+        match self.input_merkle_paths(&utxo.input_notes).await {
+            Some((root, merkle_paths)) => utxo.with_merkle_paths(root, merkle_paths),
+            None => utxo,
         }
-
-        Ok(res.json::<ElementResponse>().await.unwrap())
     }
 
-    pub async fn list_elements(
+    /// The node's current root and the merkle paths of `input_notes` in it, if there are any
+    /// non-padding inputs
+    async fn input_merkle_paths<const N: usize>(
         &self,
-        elements: &[Element],
-        include_spent: bool,
-    ) -> Result<Vec<ElementsListItem>, Error> {
-        let res = self
-            .client
-            .get(self.base_url().join("/v0/elements").unwrap())
-            .query(&[
-                (
-                    "elements",
-                    elements
-                        .iter()
-                        .map(|e| e.to_hex())
-                        .collect::<Vec<_>>()
-                        .join(","),
-                ),
-                if include_spent {
-                    ("include_spent", include_spent.to_string())
-                } else {
-                    ("", "".to_string())
-                },
-            ])
-            .send()
-            .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
+        input_notes: &[InputNote; N],
+    ) -> Option<(Element, [MerklePath<161>; N])> {
+        let commitments = input_notes
+            .iter()
+            .map(|input| input.note.commitment())
+            .filter(|commitment| !commitment.is_zero())
+            .collect::<Vec<_>>();
+
+        if commitments.is_empty() {
+            return None;
         }
 
-        Ok(res.json().await.unwrap())
-    }
-
-    pub async fn list_blocks(&self, query: &ListBlocksQuery) -> Result<ListBlocksResponse, Error> {
-        let res = self
+        let response = self
             .client
-            .get(self.base_url().join("/v0/blocks").unwrap())
-            .query(&query)
-            .send()
+            .merkle_paths(&commitments, None, MerklePathFormat::Full)
             .await
             .unwrap();
+        let mut paths = response.paths.unwrap().into_iter();
 
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
-        }
-
-        Ok(res.json::<ListBlocksResponse>().await.unwrap())
-    }
-
-    pub async fn list_transactions(
-        &self,
-        query: &ListTxnsQuery,
-    ) -> Result<ListTransactionsResponse, Error> {
-        let res = self
-            .client
-            .get(self.base_url().join("/v0/transactions").unwrap())
-            .query(&query)
-            .send()
-            .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
-        }
+        let merkle_paths = input_notes.each_ref().map(|input| {
+            if input.note.commitment().is_zero() {
+                MerklePath::default()
+            } else {
+                MerklePath::new(paths.next().unwrap())
+            }
+        });
 
-        Ok(res.json().await.unwrap())
+        Some((response.root_hash, merkle_paths))
     }
+}
 
-    pub async fn get_transaction(&self, hash: CryptoHash) -> Result<GetTransactionResponse, Error> {
-        let res = self
-            .client
-            .get(
-                self.base_url()
-                    .join(&format!("/v0/transactions/{hash}"))
-                    .unwrap(),
-            )
-            .send()
-            .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
-        }
-
-        Ok(res.json().await.unwrap())
-    }
+/// The notes tree after a block inserting each of `blocks`, starting from the empty tree
+///
+/// Like the node, a block first records the root it replaces as a [`root_leaf`], then inserts
+/// the non-zero nullifiers and output commitments of its transactions
+fn notes_tree_after(blocks: &[&[Element]]) -> ::smirk::Tree<MERKLE_TREE_DEPTH, ()> {
+    let mut tree = ::smirk::Tree::default();
 
-    pub async fn get_block(&self, identifier: &str) -> Result<BlockWithInfo, Error> {
-        let res = self
-            .client
-            .get(
-                self.base_url()
-                    .join(&format!("/v0/blocks/{identifier}"))
-                    .unwrap(),
-            )
-            .send()
-            .await
-            .unwrap();
+    for leaves in blocks {
+        let old_root = tree.root_hash();
 
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
+        for leaf in std::iter::once(root_leaf(old_root)).chain(leaves.iter().copied()) {
+            if !leaf.is_zero() {
+                tree.insert(leaf, ()).unwrap();
+            }
         }
-
-        Ok(res.json().await.unwrap())
     }
 
-    pub async fn get_all_smirk_elements(&self) -> Result<GetAllSmirkElementsResponse, Error> {
-        let res = self
-            .client
-            .get(self.base_url().join("/v0/smirk/elements/all").unwrap())
-            .header("x-guild", "LC38R7uFkM&M")
-            .send()
-            .await
-            .unwrap();
-
-        if !res.status().is_success() {
-            let err = res.json::<Error>().await.unwrap();
-            return Err(err);
-        }
-
-        Ok(res.json().await.unwrap())
-    }
+    tree
 }
 
 fn mint_with_note<'m, 't>(
@@ -610,7 +479,7 @@ fn mint_with_note<'m, 't>(
     note: Note,
 ) -> (
     impl Future<Output = Result<(), contracts::Error>> + 'm,
-    impl Future<Output = Result<TransactionResp, Error>> + 't,
+    impl Future<Output = Result<TransactionResponse, Error>> + 't,
 ) {
     let output_notes = [note.clone(), Note::padding_note()];
     let utxo = zk_primitives::Utxo::new_mint(output_notes.clone());
@@ -650,25 +519,27 @@ fn mint<'m, 't>(
 ) -> (
     Note,
     impl Future<Output = Result<(), contracts::Error>> + 'm,
-    impl Future<Output = Result<TransactionResp, Error>> + 't,
+    impl Future<Output = Result<TransactionResponse, Error>> + 't,
 ) {
-    let note = Note::new_with_psi(address, value, psi);
+    let note = Note::new_with_psi(address, value, psi, bridged_polygon_usdc_note_kind());
     let (eth_tx, rpc_tx) = mint_with_note(rollup, usdc, server, note.clone());
 
     (note, eth_tx, rpc_tx)
 }
 
-fn burn<'m, 't>(
+async fn burn<'m, 't>(
     server: &'t Server,
     note: &'m InputNote,
     to: &'m Address,
 ) -> (
     impl Future<Output = Result<(), contracts::Error>> + 'm,
-    impl Future<Output = Result<TransactionResp, Error>> + 't,
+    impl Future<Output = Result<TransactionResponse, Error>> + 't,
 ) {
     let input_notes = [note.clone(), InputNote::padding_note()];
     let evm_address = convert_h160_to_element(to);
-    let utxo = zk_primitives::Utxo::new_burn(input_notes, evm_address);
+    let utxo = server
+        .with_merkle_paths(Utxo::new_burn(input_notes, evm_address))
+        .await;
     let proof = utxo.prove().unwrap();
 
     (
@@ -27,7 +27,7 @@ async fn smirk_elements_export() {
     eth_mint_tx1.await.unwrap();
     let tx_resp1 = tx1.await.unwrap();
 
-    let smirk_elements = server.get_all_smirk_elements().await.unwrap();
+    let smirk_elements = server.client.smirk_elements().await.unwrap();
 
     assert_eq!(smirk_elements.len(), 1);
 
@@ -2,6 +2,14 @@
 //! These can be very CPU intensive, since each spawn 4 nodes,
 //! and Rust by default will run as many tests in parallel as you have cores.
 
+use std::sync::Arc;
+
+use element::Element;
+use hash::hash_merge;
+use testutil::eth::{EthNode, EthNodeOptions};
+
+use crate::rpc::{Server, ServerConfig, mint, rollup_contract, usdc_contract};
+
 // use node::PersistentMerkleTree;
 // use prover::smirk_metadata::SmirkMetadata;
 // use serial_test::serial;
@@ -176,3 +184,96 @@
 //     );
 //     assert_eq!(server_a_height.root_hash, server_b_height.root_hash);
 // }
+
+/// A prover that is far behind requests a reconcile snapshot, which a validator without notes
+/// tree history can't serve, so it offers and sends a fast snapshot instead
+#[tokio::test(flavor = "multi_thread")]
+async fn reconcile_falls_back_to_fast_snapshot() {
+    let eth_node = EthNode::new(EthNodeOptions {
+        use_noop_verifier: true,
+        ..Default::default()
+    })
+    .run_and_deploy()
+    .await;
+
+    let server = Server::setup_and_wait(
+        ServerConfig {
+            env: vec![("POLY_MERKLE_HISTORY_BLOCKS", "0".to_owned())],
+            ..ServerConfig::single_node(false)
+        },
+        Arc::clone(&eth_node),
+    )
+    .await;
+    let mut prover_server = Server::new(ServerConfig::mock_prover(false), Arc::clone(&eth_node));
+    prover_server.set_peers(&[server.to_peer()]);
+    prover_server.run(None);
+    prover_server.wait().await.unwrap();
+
+    let rollup = rollup_contract(server.rollup_contract_addr, &eth_node).await;
+    let usdc = usdc_contract(&rollup, &eth_node).await;
+
+    let alice_address = hash_merge([Element::new(0xA11CE), Element::ZERO]);
+    let (note, eth_tx, tx) = mint(
+        &rollup,
+        &usdc,
+        &server,
+        alice_address,
+        Element::from(100u64),
+        Element::ZERO,
+    );
+    eth_tx.await.unwrap();
+    let tx_resp = tx.await.unwrap();
+
+    // The new prover only tries a reconcile snapshot if the contract is past its first chunk
+    for i in 0.. {
+        let height = rollup.block_height().await.unwrap();
+        if height >= tx_resp.height.0 && height > 2 {
+            break;
+        }
+
+        if i == 120 {
+            panic!("Failed to wait for the mint to be rolled up");
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    }
+    drop(prover_server);
+
+    let mut new_prover_server = Server::new(
+        ServerConfig {
+            env: vec![
+                ("POLY_FAST_SYNC_THRESHOLD", "0".to_owned()),
+                ("POLY_SYNC_CHUNK_SIZE", "1".to_owned()),
+            ],
+            ..ServerConfig::mock_prover(false)
+        },
+        Arc::clone(&eth_node),
+    );
+    new_prover_server.set_peers(&[server.to_peer()]);
+    new_prover_server.run(None);
+    new_prover_server.wait().await.unwrap();
+
+    let contract_height = rollup.block_height().await.unwrap();
+    for i in 0.. {
+        let height = new_prover_server.client.height().await.unwrap();
+        if height.height >= contract_height {
+            assert_eq!(
+                height.root_hash,
+                server.client.height().await.unwrap().root_hash
+            );
+            break;
+        }
+
+        if i == 60 {
+            panic!("Failed to wait for the new prover to sync");
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    }
+
+    new_prover_server
+        .client
+        .element(note.commitment())
+        .await
+        .unwrap();
+}
@@ -4,61 +4,54 @@ use std::{time::Duration, time::Instant};
 
 use barretenberg::Prove;
 use burn_substitutor::BurnSubstitutor;
+use constants::MERKLE_TREE_DEPTH;
 use contracts::{Address, Client, ConfirmationType};
 use element::Element;
 use ethereum_types::{H160, U256};
 use hash::hash_merge;
-use hex::ToHex;
-use primitives::{block_height::BlockHeight, hash::CryptoHash, pagination::CursorChoice};
+use node_interface::{
+    ElementData, ElementsResponseSingle, ListBlocksOrder, ListBlocksQuery, ListTxnOrder,
+    ListTxnsQuery, RpcError, SpentNullifier,
+};
+use primitives::{block_height::BlockHeight, pagination::CursorChoice};
+use rpc::{code::ErrorCode, error::HTTPError};
 use testutil::eth::{EthNode, EthNodeOptions};
-use zk_primitives::{InputNote, Note, Utxo, bridged_polygon_usdc_note_kind};
+#[cfg(feature = "utxo4")]
+use zk_primitives::Utxo4;
+use zk_primitives::{
+    InputNote, Note, SwapLegProof, SwapLegPublicInput, SwapProof, SwapTerms, TxnProof, Utxo,
+    UtxoProofBytes, UtxoPublicInput, bridged_polygon_usdc_note_kind, generate_note_kind_bridge_evm,
+};
 
 use crate::rpc::{
-    ElementResponse, ListBlocksOrder, ListBlocksQuery, ListTxnOrder, ListTxnsQuery, ServerConfig,
-    burn, mint, mint_with_note, rollup_contract, usdc_contract,
+    ServerConfig, burn, mint, mint_with_note, notes_tree_after, rollup_contract, usdc_contract,
 };
 
 use super::Server;
 
-fn extract_error_code(err: &serde_json::Value) -> String {
-    let error_obj = err.get("error").expect("expected error object in response");
-
-    let primary_code = error_obj
-        .get("code")
-        .and_then(|code| code.as_str())
-        .unwrap_or_default();
-
-    if primary_code != "bad-request" {
-        return primary_code.to_owned();
+/// The reason the node gave for rejecting a request, e.g. `element-not-found`
+fn error_reason(err: &node_interface::Error) -> String {
+    match err {
+        node_interface::Error::Rpc(error) => HTTPError::from(error.clone()).reason,
+        node_interface::Error::Node(output) => output.error.reason.clone(),
+        node_interface::Error::Client(error) => panic!("request to the node failed: {error}"),
     }
+}
 
-    error_obj
-        .get("details")
-        .and_then(|details| details.get("code"))
-        .and_then(|code| code.as_str())
-        .map(|code| code.to_owned())
-        .or_else(|| {
-            error_obj
-                .get("reason")
-                .and_then(|reason| reason.as_str())
-                .map(|reason| reason.to_owned())
-        })
-        .or_else(|| {
-            error_obj
-                .get("data")
-                .and_then(|data| data.get("code"))
-                .and_then(|code| code.as_str())
-                .map(|code| code.to_owned())
-        })
-        .unwrap_or_else(|| primary_code.to_owned())
+/// The error code of `err`, or the reason for bad requests, which all share a code
+fn extract_error_code(err: &node_interface::Error) -> String {
+    match err.code() {
+        Some(code) if code != ErrorCode::BadRequest => code.to_string(),
+        _ => error_reason(err),
+    }
 }
 
-macro_rules! expect_root_hash {
+macro_rules! assert_root_hash {
     ($server:expr, $root_hash:expr) => {
         if option_env!("TEMP_NOIR") == Some("1") {
         } else {
-            let resp = $server.height().await.unwrap();
-            $root_hash.assert_debug_eq(&resp.root_hash);
+            let resp = $server.client.height().await.unwrap();
+            assert_eq!(resp.root_hash, $root_hash);
         }
     };
 }
@@ -66,14 +59,14 @@ macro_rules! expect_root_hash {
 const ALLOWED_DUPLICATE_CODES: &[&str] = &[
     "commitment-already-pending",
     "duplicate-output-commitments",
-    "duplicate-input-commitments",
+    "duplicate-nullifiers",
     "already-exists",
 ];
 
 const ALLOWED_DUPLICATE_INPUT_CODES: &[&str] = &[
     "commitment-already-pending",
-    "duplicate-input-commitments",
-    "input-commitments-not-found",
+    "duplicate-nullifiers",
+    "nullifiers-spent",
     "already-exists",
 ];
 
@@ -86,7 +79,7 @@ async fn mint_transaction_not_in_contract() {
     let rollup = rollup_contract(server.rollup_contract_addr, &eth_node).await;
     let usdc = usdc_contract(&rollup, &eth_node).await;
 
-    let root_hash_before = server.height().await.unwrap().root_hash;
+    let root_hash_before = server.client.height().await.unwrap().root_hash;
     let alice_pk = Element::new(0xA11CE);
 
     let (_note, _eth_tx, node_tx) = mint(
@@ -133,24 +126,13 @@ async fn mint_transaction_not_in_contract() {
         time_before_sending_node_txn.elapsed()
     );
 
-    assert_eq!(
-        err.get("error").unwrap().get("reason").unwrap(),
-        &serde_json::Value::String("mint-not-in-contract".to_owned())
-    );
+    assert_eq!(error_reason(&err), "mint-not-in-contract");
 
-    let resp = server.height().await.unwrap();
+    let resp = server.client.height().await.unwrap();
     // Root hash should not change
     assert_eq!(root_hash_before, resp.root_hash);
 
-    if option_env!("TEMP_NOIR") == Some("1") {
-    } else {
-        expect_root_hash!(
-            server,
-            expect_test::expect![[r#"
-            0x577b5b4aa3eaba75b2a919d5d7c63b7258aa507d38e346bf2ff1d48790379ff
-        "#]]
-        );
-    }
+    assert_root_hash!(server, smirk::empty_tree_hash(MERKLE_TREE_DEPTH));
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -162,7 +144,7 @@ async fn mint_transaction_only() {
     let rollup = rollup_contract(server.rollup_contract_addr, &eth_node).await;
     let usdc = usdc_contract(&rollup, &eth_node).await;
 
-    let root_hash_before = server.height().await.unwrap().root_hash;
+    let root_hash_before = server.client.height().await.unwrap().root_hash;
     let alice_pk = Element::new(0xA11CE);
     let alice_address = hash_merge([alice_pk, Element::ZERO]);
     let (alice_note, eth_tx, node_tx) = mint(
@@ -188,30 +170,30 @@ async fn mint_transaction_only() {
         time_before_sending_node_txn.elapsed()
     );
 
-    let resp = server.height().await.unwrap();
+    let resp = server.client.height().await.unwrap();
     assert_ne!(root_hash_before, resp.root_hash);
     assert_eq!(tx.root_hash, resp.root_hash);
 
-    let element_info = server.element(alice_note.commitment()).await.unwrap();
+    let element_info = server
+        .client
+        .element(alice_note.commitment())
+        .await
+        .unwrap();
     assert_eq!(
         element_info,
-        ElementResponse {
+        ElementsResponseSingle {
             element: alice_note.commitment(),
             height: tx.height.0,
             root_hash: tx.root_hash,
             txn_hash: tx.txn_hash,
+            spent: false,
         }
     );
 
-    if option_env!("TEMP_NOIR") == Some("1") {
-    } else {
-        expect_root_hash!(
-            server,
-            expect_test::expect![[r#"
-                0xea000ebbc4e827874e8f3743b6c68765ea7d513731625f595139bf56381827
-            "#]]
-        );
-    }
+    assert_root_hash!(
+        server,
+        notes_tree_after(&[&[alice_note.commitment()]]).root_hash()
+    );
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -248,20 +230,146 @@ async fn mint_and_transfer_alice_to_bob() {
     let input_note = InputNote::new(alice_note.clone(), alice_pk);
     let utxo = Utxo::new_send(
         [input_note.clone(), InputNote::padding_note()],
-        [bob_note, Note::padding_note()],
+        [bob_note.clone(), Note::padding_note()],
     );
+    let utxo = server.with_merkle_paths(utxo).await;
 
     // let snark = cache_utxo_proof("mint_and_transfer_alice_to_bob", &utxo);
     let snark = utxo.prove().unwrap();
     let resp = server.transaction(&snark).await.unwrap();
     assert_ne!(tx.root_hash, resp.root_hash);
 
-    expect_root_hash!(
+    assert_root_hash!(
         server,
-        expect_test::expect![[r#"
-            0xf478b616bd7df6d0443336bf26784eeefb226444106ddb5135c58cdc6927e99
-        "#]]
+        notes_tree_after(&[
+            &[alice_note.commitment()],
+            &[input_note.nullifier(), bob_note.commitment()],
+        ])
+        .root_hash()
+    );
+}
+
+#[cfg(feature = "utxo4")]
+#[tokio::test(flavor = "multi_thread")]
+async fn consolidate_with_utxo4() {
+    let eth_node = EthNode::default().run_and_deploy().await;
+    let server =
+        Server::setup_and_wait(ServerConfig::single_node(false), Arc::clone(&eth_node)).await;
+    let rollup = rollup_contract(server.rollup_contract_addr, &eth_node).await;
+    let usdc = usdc_contract(&rollup, &eth_node).await;
+
+    let alice_pk = Element::new(0xA11CE);
+    let alice_address = hash_merge([alice_pk, Element::ZERO]);
+
+    let mut input_notes = Vec::new();
+    for (value, psi) in [(30u64, 1u64), (30, 2), (40, 3)] {
+        let (note, eth_tx, tx) = mint(
+            &rollup,
+            &usdc,
+            &server,
+            alice_address,
+            Element::from(value),
+            Element::new(psi),
+        );
+        eth_tx.await.unwrap();
+        tx.await.unwrap();
+        input_notes.push(InputNote::new(note, alice_pk));
+    }
+    input_notes.push(InputNote::padding_note());
+
+    // Alice's three notes are consolidated in a single transaction
+    let consolidated = Note::new_with_psi(
+        alice_address,
+        Element::new(100),
+        Element::new(4),
+        bridged_polygon_usdc_note_kind(),
+    );
+    let utxo = Utxo4::new_send(
+        input_notes.try_into().unwrap(),
+        [
+            consolidated.clone(),
+            Note::padding_note(),
+            Note::padding_note(),
+            Note::padding_note(),
+        ],
+    );
+    let utxo = server.with_merkle_paths4(utxo).await;
+
+    let snark = utxo.prove().unwrap();
+    let resp = server.transaction(&snark).await.unwrap();
+
+    let txn = server.client.transaction(resp.txn_hash).await.unwrap().txn;
+    assert!(matches!(txn.proof, TxnProof::Utxo4(_)));
+    assert!(
+        txn.proof
+            .output_commitments()
+            .contains(&consolidated.commitment())
     );
+
+    // The consolidated note can be spent like any other
+    let bob_address = hash_merge([Element::new(0xB0B), Element::ZERO]);
+    let utxo = server
+        .with_merkle_paths(Utxo::new_send(
+            [
+                InputNote::new(consolidated, alice_pk),
+                InputNote::padding_note(),
+            ],
+            [
+                Note::new_with_psi(
+                    bob_address,
+                    Element::new(100),
+                    Element::new(5),
+                    bridged_polygon_usdc_note_kind(),
+                ),
+                Note::padding_note(),
+            ],
+        ))
+        .await;
+    server.transaction(&utxo.prove().unwrap()).await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn swap_legs_must_match() {
+    let eth_node = EthNode::default().run_and_deploy().await;
+    let server =
+        Server::setup_and_wait(ServerConfig::single_node(false), Arc::clone(&eth_node)).await;
+
+    // Alice gives 10 USDC for 3 of another kind
+    let terms = SwapTerms {
+        give_kind: bridged_polygon_usdc_note_kind(),
+        give_value: Element::new(10),
+        want_kind: Element::new(5),
+        want_value: Element::new(3),
+    };
+    let leg = |terms: SwapTerms, commitment: u64| SwapLegProof {
+        proof: UtxoProofBytes::default(),
+        public_inputs: SwapLegPublicInput(UtxoPublicInput {
+            output_commitments: [Element::new(commitment), Element::ZERO],
+            messages: terms.messages(),
+            ..Default::default()
+        }),
+    };
+
+    // Bob wants more than Alice gives, so the swap is rejected before its proofs are checked
+    let greedy = SwapTerms {
+        want_value: Element::new(11),
+        ..terms.counterpart()
+    };
+    let err = server
+        .transaction(&SwapProof::new([leg(terms, 1), leg(greedy, 2)]))
+        .await
+        .unwrap_err();
+    assert_eq!(extract_error_code(&err), "swap-legs-mismatch");
+
+    // Matching legs are verified like any other proof
+    let err = server
+        .transaction(&SwapProof::new([
+            leg(terms, 1),
+            leg(terms.counterpart(), 2),
+        ]))
+        .await
+        .unwrap_err();
+    assert_eq!(extract_error_code(&err), "invalid-proof");
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -287,24 +395,36 @@ async fn double_spend() {
 
     let bob_pk = Element::new(0xB0B);
     let bob_address = hash_merge([bob_pk, Element::ZERO]);
-    let bob_note = Note::new_with_psi(bob_address, Element::from(100u64), Element::ZERO);
+    let bob_note = Note::new_with_psi(
+        bob_address,
+        Element::from(100u64),
+        Element::ZERO,
+        bridged_polygon_usdc_note_kind(),
+    );
 
     let input_note = InputNote::new(alice_note.clone(), alice_pk);
     let utxo = Utxo::new_send(
         [input_note.clone(), InputNote::padding_note()],
-        [bob_note, Note::padding_note()],
+        [bob_note.clone(), Note::padding_note()],
     );
+    let utxo = server.with_merkle_paths(utxo).await;
 
     // let snark = cache_utxo_proof("double_spend", &utxo);
     let snark = utxo.prove().unwrap();
 
     let resp_1 = server.transaction(&snark);
 
-    let bob_note_2 = Note::new_with_psi(bob_address, Element::from(100u64), Element::new(1));
+    let bob_note_2 = Note::new_with_psi(
+        bob_address,
+        Element::from(100u64),
+        Element::new(1),
+        bridged_polygon_usdc_note_kind(),
+    );
     let utxo = Utxo::new_send(
         [input_note.clone(), InputNote::padding_note()],
-        [bob_note_2, Note::padding_note()],
+        [bob_note_2.clone(), Note::padding_note()],
     );
+    let utxo = server.with_merkle_paths(utxo).await;
 
     // let snark_2 = cache_utxo_proof("double_spend-2", &utxo);
     let snark_2 = utxo.prove().unwrap();
@@ -321,11 +441,13 @@ async fn double_spend() {
                 "unexpected error code {code}"
             );
 
-            expect_root_hash!(
+            assert_root_hash!(
                 server,
-                expect_test::expect![[r#"
-                    0xf478b616bd7df6d0443336bf26784eeefb226444106ddb5135c58cdc6927e99
-                "#]]
+                notes_tree_after(&[
+                    &[alice_note.commitment()],
+                    &[input_note.nullifier(), bob_note.commitment()],
+                ])
+                .root_hash()
             );
         }
         (Err(err), Ok(_)) => {
@@ -335,11 +457,13 @@ async fn double_spend() {
                 "unexpected error code {code}"
             );
 
-            expect_root_hash!(
+            assert_root_hash!(
                 server,
-                expect_test::expect![[r#"
-                    0x27a9c15038cc9786757ea83814c73e70781f481d2504ca845cc1a5ba42ab8f11
-                "#]]
+                notes_tree_after(&[
+                    &[alice_note.commitment()],
+                    &[input_note.nullifier(), bob_note_2.commitment()],
+                ])
+                .root_hash()
             );
         }
         (Ok(_), Ok(_)) => {
@@ -374,20 +498,26 @@ async fn send_transaction_with_duplicate_inputs_is_rejected() {
 
     let bob_pk = Element::new(0xB0B);
     let bob_address = hash_merge([bob_pk, Element::ZERO]);
-    let duplicate_output = Note::new_with_psi(bob_address, Element::from(200u64), Element::ZERO);
+    let duplicate_output = Note::new_with_psi(
+        bob_address,
+        Element::from(200u64),
+        Element::ZERO,
+        bridged_polygon_usdc_note_kind(),
+    );
 
     let input_note = InputNote::new(alice_note.clone(), alice_pk);
     let utxo = Utxo::new_send(
         [input_note.clone(), input_note.clone()],
         [duplicate_output, Note::padding_note()],
     );
+    let utxo = server.with_merkle_paths(utxo).await;
     let proof = utxo.prove().unwrap();
 
     let res = server.transaction(&proof).await;
     match res {
         Ok(_) => panic!("duplicate inputs should be rejected"),
         Err(err) => {
-            assert_eq!(extract_error_code(&err), "duplicate-input-commitments");
+            assert_eq!(extract_error_code(&err), "duplicate-nullifiers");
         }
     }
 }
@@ -429,7 +559,12 @@ async fn two_transactions_with_duplicate_output_should_conflict() {
 
     let bob_pk = Element::new(0xB0B);
     let bob_address = hash_merge([bob_pk, Element::ZERO]);
-    let duplicated_output = Note::new_with_psi(bob_address, Element::from(100u64), Element::ZERO);
+    let duplicated_output = Note::new_with_psi(
+        bob_address,
+        Element::from(100u64),
+        Element::ZERO,
+        bridged_polygon_usdc_note_kind(),
+    );
 
     let alice_input = InputNote::new(alice_note.clone(), alice_pk);
     let charlie_input = InputNote::new(charlie_note.clone(), charlie_pk);
@@ -438,10 +573,12 @@ async fn two_transactions_with_duplicate_output_should_conflict() {
         [alice_input.clone(), InputNote::padding_note()],
         [duplicated_output.clone(), Note::padding_note()],
     );
+    let utxo_1 = server.with_merkle_paths(utxo_1).await;
     let utxo_2 = Utxo::new_send(
         [charlie_input.clone(), InputNote::padding_note()],
         [duplicated_output.clone(), Note::padding_note()],
     );
+    let utxo_2 = server.with_merkle_paths(utxo_2).await;
 
     let proof_1 = utxo_1.prove().unwrap();
     let proof_2 = utxo_2.prove().unwrap();
@@ -491,8 +628,18 @@ async fn two_transactions_with_duplicate_input_should_conflict() {
 
     let bob_pk = Element::new(0xB0B);
     let bob_address = hash_merge([bob_pk, Element::ZERO]);
-    let bob_note_1 = Note::new_with_psi(bob_address, Element::from(100u64), Element::ZERO);
-    let bob_note_2 = Note::new_with_psi(bob_address, Element::from(100u64), Element::from(1u64));
+    let bob_note_1 = Note::new_with_psi(
+        bob_address,
+        Element::from(100u64),
+        Element::ZERO,
+        bridged_polygon_usdc_note_kind(),
+    );
+    let bob_note_2 = Note::new_with_psi(
+        bob_address,
+        Element::from(100u64),
+        Element::from(1u64),
+        bridged_polygon_usdc_note_kind(),
+    );
 
     let alice_input = InputNote::new(alice_note.clone(), alice_pk);
 
@@ -500,10 +647,12 @@ async fn two_transactions_with_duplicate_input_should_conflict() {
         [alice_input.clone(), InputNote::padding_note()],
         [bob_note_1, Note::padding_note()],
     );
+    let utxo_1 = server.with_merkle_paths(utxo_1).await;
     let utxo_2 = Utxo::new_send(
         [alice_input.clone(), InputNote::padding_note()],
         [bob_note_2, Note::padding_note()],
     );
+    let utxo_2 = server.with_merkle_paths(utxo_2).await;
 
     let proof_1 = utxo_1.prove().unwrap();
     let proof_2 = utxo_2.prove().unwrap();
@@ -564,7 +713,7 @@ async fn burn_tx() {
     let input_note = InputNote::new(alice_note.clone(), alice_pk);
 
     let to = Address::from_low_u64_be(1);
-    let (eth_tx, tx) = burn(&server, &input_note, &to);
+    let (eth_tx, tx) = burn(&server, &input_note, &to).await;
     eth_tx.await.unwrap();
 
     let tx_resp = tx.await.unwrap();
@@ -585,11 +734,19 @@ async fn burn_tx() {
     let balance = usdc.balance(to).await.unwrap();
     assert_eq!(balance, U256::from(100));
 
-    expect_root_hash!(
-        server,
-        expect_test::expect![[r#"
-            0x577b5b4aa3eaba75b2a919d5d7c63b7258aa507d38e346bf2ff1d48790379ff
-        "#]]
+    // The burn spent Alice's note by revealing its nullifier
+    let spent = server
+        .client
+        .list_nullifiers(&[input_note.nullifier()])
+        .await
+        .unwrap();
+    assert_eq!(
+        spent,
+        vec![SpentNullifier {
+            nullifier: input_note.nullifier(),
+            height: tx_resp.height.0,
+            txn_hash: tx_resp.txn_hash,
+        }]
     );
 }
 
@@ -615,11 +772,7 @@ async fn substitute_burn_to_address() {
     let mut burn_substitutor = BurnSubstitutor::new(
         rollup.clone(),
         usdc.clone(),
-        server
-            .base_url()
-            .to_string()
-            .trim_end_matches('/')
-            .to_owned(),
+        server.client.base_url().to_owned(),
         Duration::from_millis(50),
     );
 
@@ -643,7 +796,7 @@ async fn substitute_burn_to_address() {
 
     let to = Address::from_low_u64_be(1);
 
-    let (eth_tx, tx) = burn(&server, &input_note, &to);
+    let (eth_tx, tx) = burn(&server, &input_note, &to).await;
     eth_tx.await.unwrap();
 
     let tx_resp = tx.await.unwrap();
@@ -713,16 +866,11 @@ async fn double_mint() {
     let (_eth_tx, tx) = mint_with_note(&rollup, &usdc, &server, alice_note.clone());
 
     let err = tx.await.unwrap_err();
-    assert_eq!(
-        err.get("error").unwrap().get("reason").unwrap(),
-        &serde_json::Value::String("output-commitments-exists".to_owned())
-    );
+    assert_eq!(error_reason(&err), "output-commitments-exists");
 
-    expect_root_hash!(
+    assert_root_hash!(
         server,
-        expect_test::expect![[r#"
-            0xea000ebbc4e827874e8f3743b6c68765ea7d513731625f595139bf56381827
-        "#]]
+        notes_tree_after(&[&[alice_note.commitment()]]).root_hash()
     );
 }
 
@@ -753,30 +901,64 @@ async fn double_mint_same_mint_hash_different_address() {
     rollup.mint(&mint_hash, &value, &note_kind).await.unwrap();
 
     // Mint note A for Alice
-    let alice_note = Note::new_with_psi(alice_address, value, psi);
+    let alice_note = Note::new_with_psi(alice_address, value, psi, note_kind);
     let utxo_a = Utxo::new_mint([alice_note.clone(), Note::padding_note()]);
     let proof_a = utxo_a.prove().unwrap();
     let _tx_a = server.transaction(&proof_a).await.unwrap();
 
     // Mint note B for Bob with same psi
-    let bob_note = Note::new_with_psi(bob_address, value, psi);
+    let bob_note = Note::new_with_psi(bob_address, value, psi, note_kind);
     let utxo_b = Utxo::new_mint([bob_note.clone(), Note::padding_note()]);
     let proof_b = utxo_b.prove().unwrap();
     let tx_b_err = server.transaction(&proof_b).await.unwrap_err();
-    assert_eq!(
-        tx_b_err.get("error").unwrap().get("reason").unwrap(),
-        &serde_json::Value::String("mint-hash-already-exists".to_owned())
-    );
+    assert_eq!(error_reason(&tx_b_err), "mint-hash-already-exists");
 
     // Check only one note was minted
-    let alice_element = server.element(alice_note.commitment()).await.unwrap();
+    let alice_element = server
+        .client
+        .element(alice_note.commitment())
+        .await
+        .unwrap();
     assert_eq!(alice_element.element, alice_note.commitment());
 
-    let bob_element_err = server.element(bob_note.commitment()).await.unwrap_err();
-    assert_eq!(
-        bob_element_err.get("error").unwrap().get("reason").unwrap(),
-        &serde_json::Value::String("element-not-found".to_owned())
+    let bob_element_err = server
+        .client
+        .element(bob_note.commitment())
+        .await
+        .unwrap_err();
+    assert_eq!(error_reason(&bob_element_err), "element-not-found");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mint_unlisted_note_kind() {
+    let eth_node = EthNode::default().run_and_deploy().await;
+    let server =
+        Server::setup_and_wait(ServerConfig::single_node(false), Arc::clone(&eth_node)).await;
+
+    let alice_address = hash_merge([Element::new(0xA11CE), Element::ZERO]);
+    let note_kind = generate_note_kind_bridge_evm(1, H160::repeat_byte(1));
+    let note = Note::new_with_psi(
+        alice_address,
+        Element::from(100u64),
+        Element::ZERO,
+        note_kind,
+    );
+    let utxo = Utxo::new_mint([note, Note::padding_note()]);
+    let proof = utxo.prove().unwrap();
+
+    // The kind is checked before the mint is looked up in the contract
+    let err = server.transaction(&proof).await.unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            node_interface::Error::Rpc(RpcError::NoteKindNotAllowed(ElementData { element }))
+                if *element == note_kind
+        ),
+        "unexpected error: {err:?}"
     );
+
+    let resp = server.client.height().await.unwrap();
+    assert_eq!(resp.root_hash, smirk::empty_tree_hash(MERKLE_TREE_DEPTH));
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -805,23 +987,27 @@ async fn query_transactions() {
         notes.push((alice_note, tx));
     }
 
+    // Each mint is in its own block
+    let mut minted = notes
+        .iter()
+        .map(|(note, _)| note.commitment())
+        .collect::<Vec<_>>();
+
     for note in &notes {
-        let resp = server.get_transaction(note.1.txn_hash).await.unwrap();
+        let resp = server.client.transaction(note.1.txn_hash).await.unwrap();
         assert!(resp.txn.time > 1);
         // assert!(resp.txn.proof.leaves().contains(&(note.0.commitment())));
 
-        let not_found = server
-            .get_transaction(CryptoHash::new([0; 32]))
-            .await
-            .unwrap_err();
-        assert_eq!(
-            not_found.get("error").unwrap().get("reason").unwrap(),
-            &serde_json::Value::String("txn-not-found".to_owned())
-        );
+        let not_found = server.client.transaction(Element::ZERO).await.unwrap_err();
+        assert_eq!(error_reason(&not_found), "txn-not-found");
     }
 
     {
-        let resp = server.list_transactions(&Default::default()).await.unwrap();
+        let resp = server
+            .client
+            .list_transactions(&Default::default())
+            .await
+            .unwrap();
         // Latest transaction should be first
         assert_eq!(resp.txns.len(), 2);
         // assert!(resp.txns[0]
@@ -837,9 +1023,10 @@ async fn query_transactions() {
     {
         // Oldest to newest transaction list
         let resp = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 limit: Some(1),
-                order: Some(ListTxnOrder::OldestToNewest),
+                order: ListTxnOrder::OldestToNewest,
                 ..Default::default()
             })
             .await
@@ -852,9 +1039,10 @@ async fn query_transactions() {
 
         // Next page
         let resp = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 cursor: Some(CursorChoice::After(*resp.cursor.after.unwrap()).opaque()),
-                order: Some(ListTxnOrder::OldestToNewest),
+                order: ListTxnOrder::OldestToNewest,
                 ..Default::default()
             })
             .await
@@ -867,9 +1055,10 @@ async fn query_transactions() {
 
         // Previous page
         let resp = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
-                order: Some(ListTxnOrder::OldestToNewest),
+                order: ListTxnOrder::OldestToNewest,
                 ..Default::default()
             })
             .await
@@ -882,9 +1071,10 @@ async fn query_transactions() {
 
         // Previous page again should return nothing
         let resp = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
-                order: Some(ListTxnOrder::OldestToNewest),
+                order: ListTxnOrder::OldestToNewest,
                 ..Default::default()
             })
             .await
@@ -894,6 +1084,7 @@ async fn query_transactions() {
 
     {
         let resp = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 limit: Some(1),
                 ..Default::default()
@@ -908,6 +1099,7 @@ async fn query_transactions() {
 
         // Next page
         let resp = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 cursor: Some(CursorChoice::After(*resp.cursor.after.unwrap()).opaque()),
                 ..Default::default()
@@ -922,6 +1114,7 @@ async fn query_transactions() {
 
         // Previous page
         let resp = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
                 ..Default::default()
@@ -936,6 +1129,7 @@ async fn query_transactions() {
 
         // Previous page again should return nothing
         let resp_with_nothing = server
+            .client
             .list_transactions(&ListTxnsQuery {
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
                 ..Default::default()
@@ -954,8 +1148,9 @@ async fn query_transactions() {
 
             async move {
                 server
+                    .client
                     .list_transactions(&ListTxnsQuery {
-                        poll: Some(true),
+                        poll: true,
                         cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
                         ..Default::default()
                     })
@@ -977,9 +1172,10 @@ async fn query_transactions() {
             new_note
         });
 
-        let (resp, _mint) = local_set
+        let (resp, new_note) = local_set
             .run_until(async { tokio::join!(resp, mint) })
             .await;
+        minted.push(new_note.unwrap().commitment());
 
         // We should get the new note in the resp
         let resp = resp.unwrap().unwrap();
@@ -995,11 +1191,9 @@ async fn query_transactions() {
         // );
     }
 
-    expect_root_hash!(
+    assert_root_hash!(
         server,
-        expect_test::expect![[r#"
-            0xdc8048c64aab47beea9e3d82c8f7ed835748fd9cd975252e6fae7a7a489fe3a
-        "#]]
+        notes_tree_after(&minted.iter().map(std::slice::from_ref).collect::<Vec<_>>()).root_hash()
     );
 }
 
@@ -1028,21 +1222,21 @@ async fn query_blocks() {
         notes.push((alice_note, tx));
     }
 
+    // Each mint is in its own block
+    let mut minted = notes
+        .iter()
+        .map(|(note, _)| note.commitment())
+        .collect::<Vec<_>>();
+
     for (_note, txn_resp) in &notes {
-        let resp = server
-            .get_block(&txn_resp.height.to_string())
-            .await
-            .unwrap();
+        let resp = server.client.block(txn_resp.height).await.unwrap();
         assert_eq!(resp.block.content.header.height, txn_resp.height);
         // assert!(resp.block.content.state.txns[0]
         //     .proof
         //     .leaves()
         //     .contains(&(note.commitment())));
 
-        let resp_by_hash = server
-            .get_block(&resp.hash.0.encode_hex::<String>())
-            .await
-            .unwrap();
+        let resp_by_hash = server.client.block(resp.hash).await.unwrap();
         assert_eq!(resp, resp_by_hash);
     }
 
@@ -1054,6 +1248,7 @@ async fn query_blocks() {
 
     {
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(100),
                 ..Default::default()
@@ -1088,9 +1283,10 @@ async fn query_blocks() {
     {
         // Lowest to highest block list
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(1),
-                order: Some(ListBlocksOrder::LowestToHighest),
+                order: ListBlocksOrder::LowestToHighest,
                 ..Default::default()
             })
             .await
@@ -1100,10 +1296,12 @@ async fn query_blocks() {
 
         // Next page
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(1),
                 cursor: Some(CursorChoice::After(*resp.cursor.after.unwrap()).opaque()),
-                order: Some(ListBlocksOrder::LowestToHighest),
+                order: ListBlocksOrder::LowestToHighest,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1112,10 +1310,12 @@ async fn query_blocks() {
 
         // Previous page
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(1),
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
-                order: Some(ListBlocksOrder::LowestToHighest),
+                order: ListBlocksOrder::LowestToHighest,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1124,10 +1324,12 @@ async fn query_blocks() {
 
         // Previous page again should return nothing
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(1),
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
-                order: Some(ListBlocksOrder::LowestToHighest),
+                order: ListBlocksOrder::LowestToHighest,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1136,6 +1338,7 @@ async fn query_blocks() {
 
     {
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(1),
                 ..Default::default()
@@ -1148,6 +1351,7 @@ async fn query_blocks() {
 
         // Next page
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(1),
                 cursor: Some(CursorChoice::After(*resp.cursor.after.unwrap()).opaque()),
@@ -1163,6 +1367,7 @@ async fn query_blocks() {
 
         // Previous page
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 limit: Some(1),
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
@@ -1178,6 +1383,7 @@ async fn query_blocks() {
 
         // Previous page again should return nothing
         let resp_with_nothing = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
                 ..Default::default()
@@ -1187,7 +1393,7 @@ async fn query_blocks() {
         assert_eq!(resp_with_nothing.blocks.len(), 0);
 
         // If we add a transaction and try again, we should get the new transaction
-        let (_new_note, eth_tx, tx) = mint(
+        let (new_note, eth_tx, tx) = mint(
             &rollup,
             &usdc,
             &server,
@@ -1197,8 +1403,10 @@ async fn query_blocks() {
         );
         eth_tx.await.unwrap();
         let _tx = tx.await.unwrap();
+        minted.push(new_note.commitment());
 
         let resp = server
+            .client
             .list_blocks(&ListBlocksQuery {
                 cursor: Some(CursorChoice::Before(*resp.cursor.before.unwrap()).opaque()),
                 limit: Some(100),
@@ -1220,10 +1428,8 @@ async fn query_blocks() {
         //     .contains(&new_note.commitment()));
     }
 
-    expect_root_hash!(
+    assert_root_hash!(
         server,
-        expect_test::expect![[r#"
-            0xdc8048c64aab47beea9e3d82c8f7ed835748fd9cd975252e6fae7a7a489fe3a
-        "#]]
+        notes_tree_after(&minted.iter().map(std::slice::from_ref).collect::<Vec<_>>()).root_hash()
     );
 }
@@ -4,7 +4,7 @@
 mod constants;
 pub mod smirk_metadata;
 use crate::constants::{
-    MERKLE_TREE_DEPTH, MERKLE_TREE_PATH_DEPTH, UTXO_AGG_LEAVES, UTXO_AGG_NUMBER, UTXO_AGGREGATIONS,
+    MERKLE_TREE_DEPTH, MERKLE_TREE_PATH_DEPTH, UTXO_AGG_NUMBER, UTXO_AGGREGATIONS,
 };
 use barretenberg::Prove;
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -22,8 +22,8 @@ use std::sync::Arc;
 use tracing::info;
 use web3::{ethabi, types::TransactionId};
 use zk_primitives::{
-    AggAgg, AggAggProof, AggUtxo, AggUtxoProof, MerklePath, UtxoProof,
-    UtxoProofBundleWithMerkleProofs,
+    AggAgg, AggAggProof, AggUtxo, AggUtxoProof, MerklePath, TxnProof,
+    UtxoProofBundleWithMerkleProofs, root_leaf,
 };
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -77,15 +77,21 @@ pub enum Error {
 
     #[error("vec to array conversion failed, expected {expected}, got {actual}")]
     VecToArrayConversion { expected: usize, actual: usize },
+
+    #[error("transactions don't fit in the aggregation slots of a block")]
+    TooManySlots,
+
+    #[error("utxo proofs from before nullifiers can't be aggregated")]
+    LegacyTxn,
 }
 
 #[derive(Debug, Clone)]
 pub struct Transaction {
-    pub proof: UtxoProof,
+    pub proof: TxnProof,
 }
 
 impl Transaction {
-    pub fn new(proof: UtxoProof) -> Self {
+    pub fn new(proof: TxnProof) -> Self {
         Self { proof }
     }
 }
@@ -158,6 +164,38 @@ impl Prover {
         Ok(proof)
     }
 
+    /// Compute the inputs of the `AggUtxo` proofs of a block without proving them, `None` for
+    /// an aggregation of only padding proofs. The notes tree is not modified
+    #[tracing::instrument(err, skip_all, fields(height))]
+    pub async fn aggregation_inputs(
+        self: &Arc<Self>,
+        notes_tree: &MerkleTree<SimpleHashCache>,
+        height: u64,
+        txns: [Option<Transaction>; MAXIMUM_TXNS],
+    ) -> Result<[Option<AggUtxo>; UTXO_AGGREGATIONS]> {
+        let inputs = tokio::task::spawn_blocking({
+            let s: Arc<Prover> = Arc::clone(self);
+            let mut tree = notes_tree.clone();
+
+            move || s.agg_utxo_inputs(&mut tree, txns, height)
+        })
+        .await??;
+
+        Ok(inputs)
+    }
+
+    /// Like [`Prover::aggregation_inputs`], but applies the block to `notes_tree`, so the inputs of
+    /// the next block can be computed from it
+    #[tracing::instrument(err, skip_all, fields(height))]
+    pub fn next_aggregation_inputs(
+        &self,
+        notes_tree: &mut MerkleTree<SimpleHashCache>,
+        height: u64,
+        txns: [Option<Transaction>; MAXIMUM_TXNS],
+    ) -> Result<[Option<AggUtxo>; UTXO_AGGREGATIONS]> {
+        self.agg_utxo_inputs(notes_tree, txns, height)
+    }
+
     #[tracing::instrument(err, skip(self), fields(height = input.height))]
     pub async fn rollup(&self, input: &RollupInput) -> Result<H256> {
         info!("Sending proof and new root to Ethereum");
@@ -236,32 +274,16 @@ impl Prover {
         txns: [Option<Transaction>; 6],
         current_block: u64,
     ) -> Result<AggAggProof, Error> {
-        let txns = txns
+        let utxo_aggregations = self
+            .agg_utxo_inputs(tree, txns, current_block)?
             .into_iter()
-            .map(|t| match t {
-                Some(t) => Ok(t),
-                None => Ok(Transaction {
-                    proof: UtxoProof::default(),
-                }),
+            .map(|agg_utxo| match agg_utxo {
+                Some(agg_utxo) => agg_utxo
+                    .prove()
+                    .map_err(|e| Error::BarretenbergProve(e.to_string())),
+                None => Ok(AggUtxoProof::default()),
             })
             .collect::<Result<Vec<_>>>()?;
-        let txns = &mut txns.iter();
-
-        let mut utxo_aggregations = Vec::new();
-        for _i in 0..UTXO_AGGREGATIONS {
-            // Take the first 3 txns (removing from the vec)
-            // Unwrap is safe because we know we have enough txns
-            #[allow(clippy::unwrap_used)]
-            let txns: [Transaction; UTXO_AGG_NUMBER] = txns
-                .take(3)
-                .cloned()
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap();
-
-            let utxo_aggregate = self.aggregate_utxo(tree, txns.clone(), current_block)?;
-            utxo_aggregations.push(utxo_aggregate);
-        }
 
         let utxo_aggregations: [AggUtxoProof; UTXO_AGGREGATIONS] = utxo_aggregations
             .try_into()
@@ -279,37 +301,77 @@ impl Prover {
     }
 
     #[tracing::instrument(err, skip_all)]
-    fn aggregate_utxo(
+    fn agg_utxo_inputs(
         &self,
         tree: &mut MerkleTree<SimpleHashCache>,
-        utxos: [Transaction; UTXO_AGG_NUMBER],
+        txns: [Option<Transaction>; 6],
         current_block: u64,
-    ) -> Result<AggUtxoProof, Error> {
-        if utxos.iter().all(|utxo| utxo.proof.is_padding()) {
-            return Ok(AggUtxoProof::default());
+    ) -> Result<[Option<AggUtxo>; UTXO_AGGREGATIONS], Error> {
+        let txns = txns
+            .into_iter()
+            .flatten()
+            .map(|t| t.proof)
+            .collect::<Vec<_>>();
+        if txns.iter().any(|txn| matches!(txn, TxnProof::UtxoV1(_))) {
+            return Err(Error::LegacyTxn);
+        }
+        let slots = aggregation_slots(&txns).ok_or(Error::TooManySlots)?;
+        let slots = &mut slots.into_iter();
+
+        // The first aggregation of the block records the block's old root in the tree, so
+        // later transactions can spend from it
+        let mut record_old_root = true;
+
+        let mut utxo_aggregations = Vec::new();
+        for _i in 0..UTXO_AGGREGATIONS {
+            // Take the slots of the next aggregation
+            // Unwrap is safe because we know we have enough slots
+            #[allow(clippy::unwrap_used)]
+            let slots: [TxnProof; UTXO_AGG_NUMBER] = slots
+                .take(UTXO_AGG_NUMBER)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+
+            let utxo_aggregate =
+                self.agg_utxo_input(tree, slots, current_block, record_old_root)?;
+            if utxo_aggregate.is_some() {
+                record_old_root = false;
+            }
+            utxo_aggregations.push(utxo_aggregate);
         }
 
-        let (_, old_tree, new_tree, merkle_paths) =
-            self.gen_merkle_paths(tree, &utxos, current_block)?;
+        utxo_aggregations
+            .try_into()
+            .map_err(|v: Vec<_>| Error::VecToArrayConversion {
+                expected: UTXO_AGGREGATIONS,
+                actual: v.len(),
+            })
+    }
+
+    #[tracing::instrument(err, skip_all)]
+    fn agg_utxo_input(
+        &self,
+        tree: &mut MerkleTree<SimpleHashCache>,
+        slots: [TxnProof; UTXO_AGG_NUMBER],
+        current_block: u64,
+        record_old_root: bool,
+    ) -> Result<Option<AggUtxo>, Error> {
+        if slots.iter().all(TxnProof::is_padding) {
+            return Ok(None);
+        }
 
-        // Chunk the merkle paths into 3 x 4
-        let merkle_paths = merkle_paths.chunks(4).collect::<Vec<_>>();
+        let old_root = tree.root_hash();
+        let old_root_merkle_path = match record_old_root {
+            true => Some(insert_leaf(tree, root_leaf(old_root), current_block)?),
+            false => None,
+        };
 
         let mut utxo_proof_bundles = Vec::new();
 
-        for (i, chunk) in merkle_paths.into_iter().enumerate() {
-            let utxo = &utxos[i];
-
-            let utxo_proof_bundle = match utxo.proof.is_padding() {
-                false => UtxoProofBundleWithMerkleProofs::new(
-                    utxo.proof.clone(),
-                    &[
-                        chunk[0].clone(),
-                        chunk[1].clone(),
-                        chunk[2].clone(),
-                        chunk[3].clone(),
-                    ],
-                ),
+        for slot in &slots {
+            let utxo_proof_bundle = match slot.is_padding() {
+                false => self.utxo_proof_bundle(tree, slot, current_block)?,
                 true => UtxoProofBundleWithMerkleProofs::default(),
             };
 
@@ -324,76 +386,43 @@ impl Prover {
                     actual: v.len(),
                 })?;
 
-        let agg_utxo = AggUtxo::new(utxo_proof_bundles, old_tree, new_tree);
-
-        let agg_utxo_proof = agg_utxo
-            .prove()
-            .map_err(|e| Error::BarretenbergProve(e.to_string()))?;
-
-        Ok(agg_utxo_proof)
+        Ok(Some(AggUtxo::new(
+            utxo_proof_bundles,
+            old_root_merkle_path,
+            old_root,
+            tree.root_hash(),
+        )))
     }
 
+    /// Inserts the nullifiers and outputs of `proof` into `tree`, with the merkle paths proving
+    /// the proof's root was recorded and the leaves were inserted
     #[tracing::instrument(err, skip_all)]
-    fn gen_merkle_paths(
+    fn utxo_proof_bundle(
         &self,
         tree: &mut MerkleTree<SimpleHashCache>,
-        txns: &[Transaction; UTXO_AGG_NUMBER],
+        proof: &TxnProof,
         current_block: u64,
-    ) -> Result<(
-        usize,
-        Element,
-        Element,
-        [MerklePath<MERKLE_TREE_DEPTH>; UTXO_AGG_LEAVES],
-    )> {
-        let padding_path = MerklePath::default();
-
-        let (merkle_paths, old_tree, new_tree) = {
-            let old_tree = tree.root_hash();
-
-            let mut merkle_paths = vec![];
-
-            // Extract leaves to be inserted from proof
-            for Transaction { proof } in txns {
-                for leaf in proof.public_inputs.input_commitments {
-                    if leaf.is_zero() {
-                        merkle_paths.push(padding_path.clone());
-                        continue;
-                    }
-
-                    merkle_paths.push(path_to_merkle_path(tree.path_for(leaf)));
-                    tree.remove(leaf)?;
-                }
-
-                for leaf in proof.public_inputs.output_commitments {
-                    if leaf.is_zero() {
-                        merkle_paths.push(padding_path.clone());
-                        continue;
-                    }
-
-                    // Insert the leaf into the tree
-                    tree.insert(
-                        leaf,
-                        SmirkMetadata {
-                            inserted_in: current_block,
-                        },
-                    )?;
-                    // Then add the path to the merkle paths
-                    merkle_paths.push(path_to_merkle_path(tree.path_for(leaf)));
-                }
+    ) -> Result<UtxoProofBundleWithMerkleProofs> {
+        let root_merkle_path = match proof.has_inputs() {
+            true => path_to_merkle_path(tree.path_for(root_leaf(proof.root()))),
+            false => MerklePath::default(),
+        };
+
+        let mut merkle_paths = Vec::new();
+        for leaf in proof.leaves() {
+            if leaf.is_zero() {
+                merkle_paths.push(MerklePath::default());
+                continue;
             }
 
-            let new_tree = tree.root_hash();
+            merkle_paths.push(insert_leaf(tree, leaf, current_block)?);
+        }
 
-            (merkle_paths, old_tree, new_tree)
-        };
-        let inserts_len: usize = merkle_paths.len();
-        let merkle_paths: [MerklePath<MERKLE_TREE_DEPTH>; UTXO_AGG_LEAVES] = merkle_paths
-            .try_into()
-            .map_err(|v: Vec<_>| Error::VecToArrayConversion {
-                expected: UTXO_AGG_LEAVES,
-                actual: v.len(),
-            })?;
-        Ok((inserts_len, old_tree, new_tree, merkle_paths))
+        Ok(UtxoProofBundleWithMerkleProofs::new(
+            proof.clone(),
+            root_merkle_path,
+            &merkle_paths,
+        ))
     }
 
     // pub fn get_merkle_path_for_commitment(
@@ -417,6 +446,47 @@ impl Prover {
     // }
 }
 
+/// Lay out the transactions of a block in the slots of its aggregations, in order, padded to
+/// [`MAXIMUM_TXNS`] slots. A swap fills a slot per leg, and the aggregation only accepts a leg
+/// next to its counterpart, so when an aggregation has a single free slot left it's padded and
+/// the swap starts the next one. `None` if the transactions don't fit in a block
+pub fn aggregation_slots(txns: &[TxnProof]) -> Option<Vec<TxnProof>> {
+    let mut slots = Vec::with_capacity(MAXIMUM_TXNS);
+
+    for txn in txns {
+        let txn_slots = txn.slots();
+        let free = UTXO_AGG_NUMBER - slots.len() % UTXO_AGG_NUMBER;
+        if txn_slots.len() > free {
+            slots.resize(slots.len() + free, TxnProof::default());
+        }
+
+        slots.extend(txn_slots);
+    }
+
+    if slots.len() > MAXIMUM_TXNS {
+        return None;
+    }
+
+    slots.resize(MAXIMUM_TXNS, TxnProof::default());
+    Some(slots)
+}
+
+/// Inserts `leaf` into `tree`, returning its merkle path
+fn insert_leaf(
+    tree: &mut MerkleTree<SimpleHashCache>,
+    leaf: Element,
+    current_block: u64,
+) -> Result<MerklePath<MERKLE_TREE_DEPTH>> {
+    tree.insert(
+        leaf,
+        SmirkMetadata {
+            inserted_in: current_block,
+        },
+    )?;
+
+    Ok(path_to_merkle_path(tree.path_for(leaf)))
+}
+
 fn path_to_merkle_path(path: Path) -> MerklePath<MERKLE_TREE_DEPTH> {
     let elements = path
         .siblings_deepest_first()
@@ -1,10 +1,22 @@
-use std::{hint::black_box, process::Command};
+use std::{
+    hint::black_box,
+    num::NonZeroUsize,
+    process::Command,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
 
 use benchy::{BenchmarkRun, benchmark};
 use element::Element;
 use hash::hash_merge;
 use rand::thread_rng;
-use smirk::{Batch, storage::Persistent};
+use smirk::{
+    Batch, Tree,
+    hash_cache::{CacheMetrics, HashCache, LruHashCache, SimpleHashCache},
+    storage::Persistent,
+};
 use tempdir::TempDir;
 
 fn make_batch(n: usize) -> Batch<160, ()> {
@@ -114,9 +126,134 @@ pub fn storage_load(b: &mut BenchmarkRun) {
         .insert("hash_element_count".into(), hash::hash_element_count());
 }
 
+/// Insert many batches into a tree using `cache`, recording how large the cache grew
+///
+/// `cache_len` and `metrics` are passed in, since the caches don't share a trait for them
+fn insert_batches_with_cache<C: HashCache>(
+    b: &mut BenchmarkRun,
+    cache: C,
+    cache_len: impl Fn(&C) -> usize,
+    metrics: impl Fn(&C) -> &CacheMetrics,
+) {
+    let mut batches = (0..50).map(|_| make_batch(1000)).collect::<Vec<_>>();
+    let mut tree = Tree::<160, (), C>::new_with_cache(cache);
+
+    b.run(|| {
+        for batch in batches.drain(..) {
+            tree.insert_batch(batch, |_| {}, |_| {}).unwrap();
+        }
+    });
+
+    let len = cache_len(tree.cache());
+    let metrics = metrics(tree.cache());
+
+    b.metrics.insert("cache_len".into(), len);
+    // the size of the cached entries, ignoring the overhead of each cache's data structure
+    b.metrics.insert(
+        "cache_entry_bytes".into(),
+        len * std::mem::size_of::<((Element, Element), Element)>(),
+    );
+    b.metrics.insert("cache_hits".into(), metrics.cache_hits());
+    b.metrics
+        .insert("cache_evictions".into(), metrics.evictions());
+}
+
+#[benchmark]
+pub fn insert_batches_simple_cache(b: &mut BenchmarkRun) {
+    insert_batches_with_cache(
+        b,
+        SimpleHashCache::new(),
+        SimpleHashCache::len,
+        SimpleHashCache::metrics,
+    );
+}
+
+#[benchmark]
+pub fn insert_batches_lru_cache(b: &mut BenchmarkRun) {
+    insert_batches_with_cache(
+        b,
+        LruHashCache::new(NonZeroUsize::new(100_000).unwrap()),
+        LruHashCache::len,
+        LruHashCache::metrics,
+    );
+}
+
+/// Commit batches while reader threads repeatedly iterate the tree, either holding the same lock
+/// as the commits, or working on the latest published snapshot
+fn commits_under_read_load(b: &mut BenchmarkRun, use_snapshots: bool) {
+    const READERS: usize = 4;
+
+    let mut persistent = Persistent::<160, ()>::new_in_memory();
+    persistent.insert_batch(make_batch(100_000)).unwrap();
+
+    let snapshot = Arc::new(RwLock::new(Arc::new(persistent.snapshot())));
+    let persistent = Arc::new(RwLock::new(persistent));
+    let stop = Arc::new(AtomicBool::new(false));
+    let reads = Arc::new(AtomicUsize::new(0));
+
+    let readers = (0..READERS)
+        .map(|_| {
+            let persistent = Arc::clone(&persistent);
+            let snapshot = Arc::clone(&snapshot);
+            let stop = Arc::clone(&stop);
+            let reads = Arc::clone(&reads);
+
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let count = match use_snapshots {
+                        true => {
+                            let tree = Arc::clone(&snapshot.read().unwrap());
+                            tree.elements().count()
+                        }
+                        false => persistent.read().unwrap().tree().elements().count(),
+                    };
+
+                    black_box(count);
+                    reads.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut batches = (0..20).map(|_| make_batch(100)).collect::<Vec<_>>();
+
+    b.run(|| {
+        for batch in batches.drain(..) {
+            let mut persistent = persistent.write().unwrap();
+            persistent.insert_batch(batch).unwrap();
+
+            if use_snapshots {
+                *snapshot.write().unwrap() = Arc::new(persistent.snapshot());
+            }
+        }
+    });
+
+    stop.store(true, Ordering::Relaxed);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    b.metrics
+        .insert("reads".into(), reads.load(Ordering::Relaxed));
+}
+
+#[benchmark]
+pub fn commits_under_read_load_locked(b: &mut BenchmarkRun) {
+    commits_under_read_load(b, false);
+}
+
+#[benchmark]
+pub fn commits_under_read_load_snapshots(b: &mut BenchmarkRun) {
+    commits_under_read_load(b, true);
+}
+
 benchy::main!(
     // hash_merge_1_000_000,
     // hash_merge_1_000_000_cached,
     create_tree,
     storage_load,
+    commits_under_read_load_locked,
+    commits_under_read_load_snapshots,
+    insert_batches_simple_cache,
+    insert_batches_lru_cache,
 );
@@ -22,38 +22,53 @@ const COMPUTE_DEPTH: usize = 257;
 #[inline]
 #[must_use]
 pub fn empty_tree_hash(depth: usize) -> Element {
+    static CACHE: OnceLock<Vec<Element>> = OnceLock::new();
+
+    cached_empty_tree_hash(&CACHE, |left, right| hash::hash_merge([left, right]), depth)
+}
+
+/// The hash of an empty tree with a given depth, where parents are hashed with `merge`
+///
+/// The first 256 hashes are computed once and stored in `cache`, which should only ever be used
+/// with the same `merge`
+pub(crate) fn cached_empty_tree_hash(
+    cache: &'static OnceLock<Vec<Element>>,
+    merge: fn(Element, Element) -> Element,
+    depth: usize,
+) -> Element {
     assert_ne!(depth, 0, "the smallest possible tree has depth 1");
 
-    let cache = get_cache();
+    let cache = get_cache(cache, merge);
 
     cache
         .get(depth - 1)
         .copied()
-        .unwrap_or_else(|| fallback(depth))
+        .unwrap_or_else(|| fallback(cache, merge, depth))
 }
 
-fn fallback(depth: usize) -> Element {
+fn fallback(cache: &[Element], merge: fn(Element, Element) -> Element, depth: usize) -> Element {
     match depth {
-        1..=COMPUTE_DEPTH => get_cache()[depth - 1],
+        1..=COMPUTE_DEPTH => cache[depth - 1],
         other => {
             // if you hit this warning, consider increasing `COMPUTE_DEPTH` above
             eprintln!("WARNING - using slow fallback for `empty_tree_hash` for depth: {other}");
-            let hashed = fallback(other - 1);
-            hash::hash_merge([hashed, hashed])
+            let hashed = fallback(cache, merge, other - 1);
+            merge(hashed, hashed)
         }
     }
 }
 
-fn get_cache() -> &'static [Element] {
-    static CACHE: OnceLock<Vec<Element>> = OnceLock::new();
-
-    CACHE.get_or_init(|| {
+fn get_cache(
+    cache: &'static OnceLock<Vec<Element>>,
+    merge: fn(Element, Element) -> Element,
+) -> &'static [Element] {
+    cache.get_or_init(|| {
         let mut vec = Vec::with_capacity(COMPUTE_DEPTH);
         vec.push(Element::NULL_HASH);
 
         for _ in 1..COMPUTE_DEPTH {
             let hash = *vec.last().unwrap();
-            let new_hash = hash::hash_merge([hash, hash]);
+            let new_hash = merge(hash, hash);
             vec.push(new_hash);
         }
 
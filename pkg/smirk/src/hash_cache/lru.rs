@@ -0,0 +1,240 @@
+use std::{
+    marker::PhantomData,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use ::lru::LruCache;
+use element::Element;
+
+use super::{CacheMetrics, HashCache, KnownHash, KnownHashCache};
+use crate::hasher::{Poseidon2, TreeHasher};
+
+/// The number of independently locked shards in an [`LruHashCache`]
+const SHARDS: usize = 16;
+
+type Shard = Mutex<LruCache<(Element, Element), Element>>;
+
+/// A cache that holds at most a fixed number of hashes, evicting the least recently used hash to
+/// make room for new ones
+///
+/// Unlike [`SimpleHashCache`], memory use doesn't grow with the number of hashes computed, so it
+/// is suitable for long-running processes. It is cheap to clone, and clones share the same
+/// hashes. Hashes are split between shards with separate locks, so parallel hashing (e.g. in
+/// [`Tree::insert_batch`]) rarely contends. Like [`SimpleHashCache`], the hashes are computed with
+/// `H`, so the cache can only be used by trees with the same hasher
+///
+/// ```rust
+/// # use smirk::*;
+/// # use smirk::hash_cache::*;
+/// # use element::Element;
+/// # use std::num::NonZeroUsize;
+/// let cache = LruHashCache::new(NonZeroUsize::new(1024).unwrap());
+/// let mut tree = Tree::<64, (), _>::new_with_cache(cache);
+///
+/// tree.insert(Element::new(1), ()).unwrap();
+/// assert!(tree.cache().len() <= 1024);
+/// ```
+///
+/// [`SimpleHashCache`]: super::SimpleHashCache
+/// [`Tree::insert_batch`]: crate::Tree::insert_batch
+#[derive(Debug, Clone)]
+pub struct LruHashCache<H = Poseidon2> {
+    shards: Arc<[Shard]>,
+    metrics: CacheMetrics,
+    hasher: PhantomData<H>,
+}
+
+impl<H: TreeHasher> HashCache<H> for LruHashCache<H> {
+    #[inline]
+    fn hash(&self, left: Element, right: Element) -> Element {
+        self.metrics.incr_hashes();
+
+        let shard = self.shard(left, right);
+
+        if let Some(result) = shard.lock().unwrap().get(&(left, right)) {
+            self.metrics.incr_cache_hits();
+            return *result;
+        }
+
+        self.metrics.incr_cache_misses();
+
+        // don't hold the lock while hashing. Threads that miss the same hash at the same time
+        // each compute it, but only the first to retake the lock inserts it
+        let result = H::hash(left, right);
+
+        let mut shard = shard.lock().unwrap();
+        if let Some(existing) = shard.get(&(left, right)) {
+            return *existing;
+        }
+
+        // the key isn't in the shard, so anything `push` returns was evicted to make room
+        if shard.push((left, right), result).is_some() {
+            self.metrics.incr_evictions();
+        }
+
+        result
+    }
+}
+
+impl<H: TreeHasher> KnownHashCache<H> for LruHashCache<H> {
+    /// Insert `hashes` until the cache is full, so the cache starts warm without growing past
+    /// its capacity
+    fn provide_known_hashes(&mut self, hashes: Vec<KnownHash>) {
+        for KnownHash {
+            left,
+            right,
+            result,
+        } in hashes
+        {
+            let mut shard = self.shard(left, right).lock().unwrap();
+            if shard.len() < shard.cap().get() {
+                shard.put((left, right), result);
+            }
+        }
+    }
+}
+
+impl<H> LruHashCache<H> {
+    /// Create a new, empty [`LruHashCache`] which holds at most roughly `capacity` hashes
+    ///
+    /// The capacity is split evenly between shards, so it is rounded up to a multiple of the
+    /// number of shards
+    #[must_use]
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        let shard_capacity = NonZeroUsize::new(capacity.get().div_ceil(SHARDS)).unwrap();
+
+        Self {
+            shards: (0..SHARDS)
+                .map(|_| Mutex::new(LruCache::new(shard_capacity)))
+                .collect(),
+            metrics: CacheMetrics::default(),
+            hasher: PhantomData,
+        }
+    }
+
+    /// The maximum number of hashes this cache holds
+    #[inline]
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().cap().get())
+            .sum()
+    }
+
+    /// The number of hashes in this cache
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Whether this cache contains no hashes
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove the result of a hash from memory
+    #[inline]
+    pub fn evict(&self, left: Element, right: Element) {
+        if self
+            .shard(left, right)
+            .lock()
+            .unwrap()
+            .pop(&(left, right))
+            .is_some()
+        {
+            self.metrics.incr_evictions();
+        }
+    }
+
+    /// Remove all hashes from the cache
+    #[inline]
+    pub fn evict_all(&self) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock().unwrap();
+            self.metrics.add_evictions(shard.len());
+            shard.clear();
+        }
+    }
+
+    /// Get metrics for this cache
+    #[inline]
+    #[must_use]
+    pub fn metrics(&self) -> &CacheMetrics {
+        &self.metrics
+    }
+
+    fn shard(&self, left: Element, right: Element) -> &Shard {
+        // hashes are uniformly distributed, so the low bytes are enough to spread hashes evenly
+        let byte = left.to_be_bytes()[31] ^ right.to_be_bytes()[31];
+        &self.shards[usize::from(byte) % SHARDS]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use hash::hash_merge;
+
+    use super::*;
+    use crate::{Batch, Tree};
+
+    fn cache(capacity: usize) -> LruHashCache {
+        LruHashCache::new(NonZeroUsize::new(capacity).unwrap())
+    }
+
+    #[test]
+    fn lru_cache_is_bounded() {
+        let cache = cache(SHARDS * 4);
+
+        for i in 0..1000 {
+            let hash = cache.hash(Element::new(i), Element::new(i + 1));
+            assert_eq!(hash, hash_merge([Element::new(i), Element::new(i + 1)]));
+        }
+
+        assert!(cache.len() <= cache.capacity());
+        assert_eq!(cache.metrics().cache_misses(), 1000);
+        assert_eq!(cache.metrics().evictions(), 1000 - cache.len());
+    }
+
+    #[test]
+    fn lru_cache_keeps_recently_used_hashes() {
+        // a single slot per shard
+        let cache = cache(SHARDS);
+        let (left, right) = (Element::new(1), Element::new(2));
+
+        cache.hash(left, right);
+        cache.hash(left, right);
+        assert_eq!(cache.metrics().cache_hits(), 1);
+
+        // same shard, since the low bytes xor to the same value
+        cache.hash(Element::new(3), Element::new(0));
+        cache.hash(left, right);
+        assert_eq!(cache.metrics().cache_hits(), 1);
+        assert_eq!(cache.metrics().evictions(), 2);
+
+        cache.evict_all();
+        assert!(cache.is_empty());
+        assert_eq!(cache.metrics().evictions(), 3);
+    }
+
+    #[proptest]
+    fn lru_cache_tree_matches_uncached(batch: Batch<64, i32>) {
+        let mut cached = Tree::<64, i32, _>::new_with_cache(cache(32));
+        let mut uncached = Tree::<64, i32>::new();
+
+        cached.insert_batch(batch.clone(), |_| {}, |_| {}).unwrap();
+        uncached.insert_batch(batch, |_| {}, |_| {}).unwrap();
+
+        assert_eq!(cached.root_hash(), uncached.root_hash());
+        assert!(cached.cache().len() <= cached.cache().capacity());
+    }
+}
@@ -9,6 +9,7 @@ pub struct CacheMetrics {
     hashes: Arc<AtomicUsize>,
     cache_hits: Arc<AtomicUsize>,
     cache_misses: Arc<AtomicUsize>,
+    evictions: Arc<AtomicUsize>,
 }
 
 impl CacheMetrics {
@@ -33,6 +34,13 @@ impl CacheMetrics {
         self.cache_misses.load(Ordering::Relaxed)
     }
 
+    /// The number of hashes that have been removed from the cache
+    #[inline]
+    #[must_use]
+    pub fn evictions(&self) -> usize {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn incr_hashes(&self) {
         self.hashes.fetch_add(1, Ordering::Relaxed);
     }
@@ -44,4 +52,12 @@ impl CacheMetrics {
     pub(crate) fn incr_cache_misses(&self) {
         self.cache_misses.fetch_add(1, Ordering::Relaxed);
     }
+
+    pub(crate) fn incr_evictions(&self) {
+        self.add_evictions(1);
+    }
+
+    pub(crate) fn add_evictions(&self, count: usize) {
+        self.evictions.fetch_add(count, Ordering::Relaxed);
+    }
 }
@@ -1,28 +1,29 @@
-use std::sync::Arc;
+use std::{marker::PhantomData, sync::Arc};
 
 use dashmap::{DashMap, mapref::entry::Entry};
 use element::Element;
-use hash::hash_merge;
 
+use crate::hasher::{Poseidon2, TreeHasher};
+
+pub use self::lru::LruHashCache;
 pub use self::metrics::CacheMetrics;
 
+mod lru;
 mod metrics;
 
-/// A known result of computation [`hash_merge([left, right])`][hash_merge]
-///
-/// [hash_merge]: hash::hash_merge
+/// A known result of hashing two [`Element`]s together with a [`TreeHasher`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct KnownHash {
     /// The left input [`Element`]
     pub left: Element,
     /// The right input [`Element`]
     pub right: Element,
-    /// The result of applying [`hash_merge([left, right])`][hash_merge]
+    /// The result of applying [`TreeHasher::hash`] to `left` and `right`
     pub result: Element,
 }
 
 /// Types which can be used to speed up hash computations (perhaps by storing known values in a
-/// table)
+/// table) for trees using the hasher `H`
 ///
 /// Take special care when implementing this trait, since incorrect external implementations can
 /// cause [`Tree`] (and, by extension, [`Persistent`]) to exhibit unspecified
@@ -36,36 +37,55 @@ pub struct KnownHash {
 /// [`Hash`]: std::hash::Hash
 ///
 /// [ub]: https://doc.rust-lang.org/reference/behavior-considered-undefined.html
-pub trait HashCache: Sync + 'static {
-    /// Calculate [`hash_merge([left, right])`][hash_merge], potentially using data in `self` to
+pub trait HashCache<H: TreeHasher = Poseidon2>: Sync + 'static {
+    /// Calculate [`H::hash(left, right)`][TreeHasher::hash], potentially using data in `self` to
     /// speed up the calculation
     ///
     /// Implementors should make sure that the result from this function *always* matches the
-    /// result from [`hash_merge`]
+    /// result from [`TreeHasher::hash`]
     fn hash(&self, left: Element, right: Element) -> Element {
-        hash_merge([left, right])
+        H::hash(left, right)
     }
 }
 
+/// [`HashCache`]s that can be seeded with hashes computed earlier, such as the hashes a
+/// [`Persistent`] stores alongside its elements
+///
+/// [`Persistent`]: crate::storage::Persistent
+pub trait KnownHashCache<H: TreeHasher = Poseidon2>: HashCache<H> {
+    /// Provide a set of known hashes to this cache, before it is used by a tree
+    ///
+    /// Note that these hashes will not be validated - providing incorrect hashes will lead to
+    /// incorrect results
+    fn provide_known_hashes(&mut self, hashes: Vec<KnownHash>);
+}
+
 /// A ZST that does no caching - the default cache for [`Tree`]
 ///
+/// It can be used with any [`TreeHasher`]
+///
 /// [`Tree`]: crate::Tree
 #[derive(Debug, Clone, Default)]
 pub struct NoopHashCache;
 
-impl HashCache for NoopHashCache {}
+impl<H: TreeHasher> HashCache<H> for NoopHashCache {}
 
 /// A simple cache (conceptually an [`Arc<Mutex<HashMap<(Element, Element), Element>>>`])
 ///
-/// It is cheap to clone, thread-safe, but has limited eviction capabilities
+/// It is cheap to clone, thread-safe, but has limited eviction capabilities, so it grows with
+/// every hash computed. Long-running processes should consider [`LruHashCache`] instead
+///
+/// The cached hashes are computed with `H`, so the cache can only be used by trees with the same
+/// hasher
 #[derive(Debug, Clone, Default)]
-pub struct SimpleHashCache {
+pub struct SimpleHashCache<H = Poseidon2> {
     inner: Arc<DashMap<(Element, Element), Element>>,
     initial: Arc<Vec<KnownHash>>,
     metrics: metrics::CacheMetrics,
+    hasher: PhantomData<H>,
 }
 
-impl HashCache for SimpleHashCache {
+impl<H: TreeHasher> HashCache<H> for SimpleHashCache<H> {
     #[inline]
     fn hash(&self, left: Element, right: Element) -> Element {
         self.metrics.incr_hashes();
@@ -85,18 +105,30 @@ impl HashCache for SimpleHashCache {
             }
             Entry::Vacant(entry) => {
                 self.metrics.incr_cache_misses();
-                *entry.insert(hash_merge([left, right]))
+                *entry.insert(H::hash(left, right))
             }
         }
     }
 }
 
-impl SimpleHashCache {
+impl<H: TreeHasher> KnownHashCache<H> for SimpleHashCache<H> {
+    #[inline]
+    fn provide_known_hashes(&mut self, hashes: Vec<KnownHash>) {
+        SimpleHashCache::provide_known_hashes(self, hashes);
+    }
+}
+
+impl<H> SimpleHashCache<H> {
     /// Create a new, empty [`SimpleHashCache`]
     #[inline]
     #[must_use]
     pub fn new() -> Self {
-        Self::default()
+        Self {
+            inner: Arc::default(),
+            initial: Arc::default(),
+            metrics: CacheMetrics::default(),
+            hasher: PhantomData,
+        }
     }
 
     /// The number of precomputed hashes in this cache
@@ -131,12 +163,15 @@ impl SimpleHashCache {
     /// Remove the result of a hash from memory
     #[inline]
     pub fn evict(&self, left: Element, right: Element) {
-        self.inner.remove(&(left, right));
+        if self.inner.remove(&(left, right)).is_some() {
+            self.metrics.incr_evictions();
+        }
     }
 
     /// Remove all hashes from the cache
     #[inline]
     pub fn evict_all(&self) {
+        self.metrics.add_evictions(self.inner.len());
         self.inner.clear();
     }
 
@@ -154,7 +189,7 @@ mod tests {
 
     #[test]
     fn simple_cache_persists_hashes() {
-        let cache = SimpleHashCache::default();
+        let cache = SimpleHashCache::<Poseidon2>::default();
 
         cache.hash(Element::new(1), Element::new(2));
         cache.hash(Element::new(3), Element::new(4));
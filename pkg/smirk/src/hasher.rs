@@ -0,0 +1,175 @@
+use element::Element;
+
+/// The hash function used to compute the hash of a parent node from the hashes of its children
+///
+/// A [`Tree`] uses [`Poseidon2`] unless another hasher is given as its last type parameter. Trees
+/// with the same elements but different hashers have different root hashes, so paths and root
+/// hashes are only comparable between trees that use the same hasher
+///
+/// ```rust
+/// # use smirk::*;
+/// # use smirk::hasher::*;
+/// # use smirk::hash_cache::*;
+/// # use element::Element;
+/// /// A (very insecure) hasher for testing
+/// #[derive(Debug, Clone, Copy, Default)]
+/// struct Sum;
+///
+/// impl TreeHasher for Sum {
+///     fn hash(left: Element, right: Element) -> Element {
+///         left + right
+///     }
+/// }
+///
+/// let mut tree = Tree::<8, (), NoopHashCache, Sum>::new();
+/// tree.insert(Element::new(1), ()).unwrap();
+/// tree.insert(Element::new(2), ()).unwrap();
+///
+/// assert_eq!(tree.root_hash(), Element::new(3));
+/// ```
+///
+/// [`Tree`]: crate::Tree
+pub trait TreeHasher: Send + Sync + 'static {
+    /// Hash the children of a node together
+    fn hash(left: Element, right: Element) -> Element;
+
+    /// The hash of an empty tree with a given depth
+    ///
+    /// The default implementation recomputes every level, so implementations should override it
+    /// with a cached version if trees with this hasher are used for more than tests
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is 0, since there is no such thing as a tree with depth 0.
+    fn empty_tree_hash(depth: usize) -> Element {
+        assert_ne!(depth, 0, "the smallest possible tree has depth 1");
+
+        (1..depth).fold(Element::NULL_HASH, |hash, _| Self::hash(hash, hash))
+    }
+}
+
+/// The Poseidon2 hash from [`hash::hash_merge`], which is used by the current circuits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Poseidon2;
+
+impl TreeHasher for Poseidon2 {
+    #[inline]
+    fn hash(left: Element, right: Element) -> Element {
+        hash::hash_merge([left, right])
+    }
+
+    #[inline]
+    fn empty_tree_hash(depth: usize) -> Element {
+        crate::empty_tree_hash(depth)
+    }
+}
+
+/// The legacy Poseidon hash from [`hash_poseidon::hash_merge`], which was used by the Halo2
+/// circuits
+///
+/// Only useful for rebuilding and verifying trees created before the migration away from Halo2
+#[cfg(feature = "legacy-poseidon")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MinimalPoseidon;
+
+#[cfg(feature = "legacy-poseidon")]
+impl TreeHasher for MinimalPoseidon {
+    #[inline]
+    fn hash(left: Element, right: Element) -> Element {
+        hash_poseidon::hash_merge([left, right])
+    }
+
+    #[inline]
+    fn empty_tree_hash(depth: usize) -> Element {
+        static CACHE: std::sync::OnceLock<Vec<Element>> = std::sync::OnceLock::new();
+
+        crate::hash::cached_empty_tree_hash(&CACHE, Self::hash, depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use super::*;
+    use crate::{Batch, Tree, hash_cache::NoopHashCache};
+
+    /// Only for testing that nothing in the tree assumes [`Poseidon2`]
+    #[derive(Debug, Clone, Copy, Default)]
+    struct Sum;
+
+    impl TreeHasher for Sum {
+        fn hash(left: Element, right: Element) -> Element {
+            left + right + Element::ONE
+        }
+    }
+
+    fn uncached_empty_tree_hash<H: TreeHasher>(depth: usize) -> Element {
+        (1..depth).fold(Element::NULL_HASH, |hash, _| H::hash(hash, hash))
+    }
+
+    #[test]
+    fn empty_tree_hashes_match_uncached() {
+        for depth in [1, 2, 3, 64, 257] {
+            assert_eq!(
+                Poseidon2::empty_tree_hash(depth),
+                uncached_empty_tree_hash::<Poseidon2>(depth)
+            );
+        }
+
+        #[cfg(feature = "legacy-poseidon")]
+        for depth in [1, 2, 64] {
+            assert_eq!(
+                MinimalPoseidon::empty_tree_hash(depth),
+                uncached_empty_tree_hash::<MinimalPoseidon>(depth)
+            );
+        }
+    }
+
+    #[proptest(cases = 32)]
+    fn paths_verify_with_tree_hasher(batch: Batch<16, i32>) {
+        let mut tree = Tree::<16, i32, NoopHashCache, Sum>::new();
+        let elements = batch.insert_elements().collect::<Vec<_>>();
+        tree.insert_batch(batch, |_| {}, |_| {}).unwrap();
+
+        for element in elements {
+            let path = tree.path_for(element);
+            assert_eq!(
+                path.compute_root_hash_with::<Sum>(element),
+                tree.root_hash()
+            );
+        }
+    }
+
+    #[proptest(cases = 32)]
+    fn hashers_give_different_roots(batch: Batch<16, i32>) {
+        let mut poseidon2 = Tree::<16, i32>::new();
+        let mut sum = Tree::<16, i32, NoopHashCache, Sum>::new();
+
+        poseidon2
+            .insert_batch(batch.clone(), |_| {}, |_| {})
+            .unwrap();
+        sum.insert_batch(batch, |_| {}, |_| {}).unwrap();
+
+        assert_ne!(poseidon2.root_hash(), sum.root_hash());
+    }
+
+    #[cfg(feature = "legacy-poseidon")]
+    #[test]
+    fn legacy_tree_uses_legacy_hash() {
+        let empty = Tree::<4, (), NoopHashCache, MinimalPoseidon>::new();
+        let merge = |e| hash_poseidon::hash_merge([e, e]);
+        let expected = merge(merge(merge(Element::NULL_HASH)));
+        assert_eq!(empty.root_hash(), expected);
+
+        let legacy: Tree<4, (), NoopHashCache, MinimalPoseidon> = crate::smirk! { 1 };
+        let poseidon2: Tree<4, ()> = crate::smirk! { 1 };
+        let path = legacy.path_for(Element::new(1));
+
+        assert_eq!(
+            path.compute_root_hash_with::<MinimalPoseidon>(Element::new(1)),
+            legacy.root_hash()
+        );
+        assert_ne!(legacy.root_hash(), poseidon2.root_hash());
+    }
+}
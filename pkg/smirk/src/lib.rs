@@ -74,6 +74,8 @@ mod batch;
 mod hash;
 /// Caching of hash values
 pub mod hash_cache;
+/// Hash functions for the nodes of a [`Tree`]
+pub mod hasher;
 mod macros;
 /// APIs relating to persistence of a [`Tree`]
 pub mod storage;
@@ -81,4 +83,7 @@ mod tree;
 
 pub use batch::Batch;
 pub use hash::empty_tree_hash;
-pub use tree::{Collision, CollisionError, Path, Tree};
+pub use tree::{
+    Collision, CollisionError, CompactPath, CompactPathError, MultiPath, NonMembershipProof, Path,
+    SubtreeDiff, SubtreeId, SubtreeSummary, Tree,
+};
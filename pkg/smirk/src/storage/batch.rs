@@ -4,11 +4,12 @@ use std::{
 };
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use rocksdb::WriteBatch;
+use kv_store::WriteBatch;
 use wire_message::WireMessage;
 
 use crate::{
     Batch,
+    hash_cache::HashCache,
     storage::format::{ValueFormat, ValueV2},
 };
 
@@ -17,7 +18,7 @@ use super::{
     format::{KeyFormat, KeyV2},
 };
 
-impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
+impl<const DEPTH: usize, V, C: HashCache> Persistent<DEPTH, V, C> {
     /// Insert a [`Batch`] into this [`Persistent`] tree
     ///
     /// ```rust
@@ -35,17 +36,46 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
     /// assert!(persistent.tree().contains_element(&Element::new(2)));
     /// assert!(persistent.tree().contains_element(&Element::new(3)));
     /// ```
+    ///
+    /// If this tree has history (see [`Persistent::insert_batch_at`]), inserting without a version
+    /// clears it, since the change can't be attributed to a version
     pub fn insert_batch(&mut self, batch: Batch<DEPTH, V>) -> Result<(), Error>
     where
         V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
     {
+        self.insert_batch_inner(batch, None)
+    }
+
+    /// Insert a [`Batch`], recording it as the changes made at `version` if history is enabled
+    pub(super) fn insert_batch_inner(
+        &mut self,
+        batch: Batch<DEPTH, V>,
+        version: Option<u64>,
+    ) -> Result<(), Error>
+    where
+        V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+    {
+        let record_history = version.is_some() && self.history_enabled();
+
         if batch.is_empty() {
-            return Ok(());
+            return match (version, record_history) {
+                (Some(version), true) => self.record_empty_version(version),
+                _ => Ok(()),
+            };
         }
 
         let new_kv_pairs: HashMap<_, _> = batch.insert_entries().iter().cloned().collect();
         let removed_elements = batch.remove_elements().collect::<Vec<_>>();
 
+        // the values of removed elements are needed to restore them in older versions
+        let removed_entries = match record_history {
+            true => removed_elements
+                .iter()
+                .filter_map(|e| self.tree.get(*e).map(|v| (*e, Arc::new(v.clone()))))
+                .collect(),
+            false => Vec::new(),
+        };
+
         let hash_changes = Arc::new(Mutex::new(HashMap::new()));
         self.tree.insert_batch(
             batch,
@@ -68,6 +98,19 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
 
         let mut write_batch = WriteBatch::default();
 
+        let history = match version {
+            Some(version) if record_history => Some(self.write_history(
+                &mut write_batch,
+                version,
+                new_kv_pairs.keys().copied().collect(),
+                removed_entries,
+            )?),
+            _ => {
+                self.write_clear_history(&mut write_batch)?;
+                None
+            }
+        };
+
         for (key, value) in new_kv_pairs {
             // insert the v2 key
             let new_key = KeyFormat::V2(KeyV2::Element(key));
@@ -86,6 +129,12 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
             }
         }
 
+        // a `DiskTree` opening this store has to recompute its nodes to see these changes, which
+        // only needs marking once, since `Persistent` doesn't write the metadata back
+        if self.disk_tree_meta {
+            write_batch.delete(KeyFormat::V2(KeyV2::DiskTreeMeta).to_bytes().unwrap());
+        }
+
         for ((left, right), _) in hashes_to_remove {
             let key = KeyFormat::V2(KeyV2::KnownHash { left, right });
             write_batch.delete(key.to_bytes().unwrap());
@@ -98,8 +147,10 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
         }
 
         self.db.write(write_batch)?;
+        self.history = history;
+        self.disk_tree_meta = false;
 
-        // TODO: handle case where rocksdb fails with pending list
+        // TODO: handle case where the store fails with pending list
 
         Ok(())
     }
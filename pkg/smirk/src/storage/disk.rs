@@ -0,0 +1,874 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    marker::PhantomData,
+    num::NonZeroUsize,
+    path::Path as FsPath,
+    sync::{Arc, Mutex},
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+use hash::hash_merge;
+use kv_store::{Direction, KeyRange, KvStore, WriteBatch};
+use lru::LruCache;
+use wire_message::WireMessage;
+
+use crate::{
+    Batch, Collision, CollisionError, NonMembershipProof, Path, SubtreeDiff, SubtreeId,
+    SubtreeSummary, empty_tree_hash, tree::StructName,
+};
+
+use super::{
+    Error,
+    format::{KeyFormat, KeyV2, ValueFormat, ValueV2},
+    history,
+};
+
+/// The number of node hashes a [`DiskTree`] keeps in memory by default
+const DEFAULT_CACHE_CAPACITY: usize = 1 << 16;
+
+/// The number of elements hashed, or stale nodes deleted, per write when migrating a store to the
+/// [`DiskTree`] format
+const MIGRATION_CHUNK: usize = 4096;
+
+/// A sparse Merkle tree that keeps its nodes in a [`KvStore`] rather than in memory
+///
+/// [`Persistent`] holds the whole [`Tree`] in memory and only uses the store to rebuild it on
+/// startup, so its memory use grows with the number of elements. A [`DiskTree`] persists the hash
+/// of every non-empty node, and reads nodes from the store when they are needed, keeping only a
+/// bounded number of recently used hashes in memory. This makes the root hash free, and
+/// [`DiskTree::contains_element`] and [`DiskTree::path_for`] cost one read per level at worst,
+/// regardless of the size of the tree
+///
+/// ```rust
+/// # use smirk::*;
+/// # use element::Element;
+/// # use smirk::storage::*;
+/// let mut disk = DiskTree::<64, i32>::new_in_memory();
+/// let mut tree = Tree::<64, i32>::new();
+///
+/// disk.insert_batch(batch! { 1 => 10, 2 => 20 }).unwrap();
+/// tree.insert_batch(batch! { 1 => 10, 2 => 20 }, |_| {}, |_| {}).unwrap();
+///
+/// assert_eq!(disk.root_hash(), tree.root_hash());
+/// assert_eq!(disk.get(Element::new(2)).unwrap(), Some(20));
+///
+/// let path = disk.path_for(Element::new(1)).unwrap();
+/// assert_eq!(path.compute_root_hash(Element::new(1)), tree.root_hash());
+/// ```
+///
+/// Stores written by [`Persistent`] are migrated when they are loaded, so the same store can be
+/// opened with either type. A [`DiskTree`] doesn't record history, so any history in a migrated
+/// store is removed
+///
+/// [`Persistent`]: super::Persistent
+/// [`Tree`]: crate::Tree
+pub struct DiskTree<const DEPTH: usize, V> {
+    db: Box<dyn KvStore>,
+    root_hash: Element,
+    len: usize,
+    /// Recently used node hashes, keyed by their position in the tree
+    nodes: Mutex<LruCache<SubtreeId, Element>>,
+    _value: PhantomData<fn() -> V>,
+}
+
+impl<const DEPTH: usize, V> DiskTree<DEPTH, V>
+where
+    V: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+{
+    /// Create a new, empty [`DiskTree`] backed by a rocksdb instance at `path`
+    pub fn new<P: AsRef<FsPath>>(path: P) -> Result<Self, Error> {
+        let db = kv_store::RocksDb::open_default(path.as_ref())?;
+
+        Ok(Self::new_with_store(Box::new(db)))
+    }
+
+    /// Create a new, empty [`DiskTree`] that is only held in memory, for tests and simulations
+    #[must_use]
+    pub fn new_in_memory() -> Self {
+        Self::new_with_store(Box::new(kv_store::Memory::new()))
+    }
+
+    /// Create a new, empty [`DiskTree`] backed by an empty `db`
+    #[must_use]
+    pub fn new_with_store(db: Box<dyn KvStore>) -> Self {
+        Self {
+            db,
+            root_hash: empty_tree_hash(DEPTH),
+            len: 0,
+            nodes: Mutex::new(LruCache::new(
+                NonZeroUsize::new(DEFAULT_CACHE_CAPACITY).unwrap(),
+            )),
+            _value: PhantomData,
+        }
+    }
+
+    /// Load a [`DiskTree`] from a rocksdb database located at `path`
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// # let dir = tempdir::TempDir::new("smirk_doctest").unwrap();
+    /// # let path = dir.path().join("db");
+    /// let mut disk = DiskTree::<64, i32>::new(&path).unwrap();
+    /// disk.insert(Element::ONE, 123).unwrap();
+    /// let root_hash = disk.root_hash();
+    ///
+    /// drop(disk);
+    ///
+    /// let disk = DiskTree::<64, i32>::load(&path).unwrap();
+    /// assert_eq!(disk.root_hash(), root_hash);
+    /// assert_eq!(disk.get(Element::ONE).unwrap(), Some(123));
+    /// ```
+    pub fn load<P: AsRef<FsPath>>(path: P) -> Result<Self, Error> {
+        let db = kv_store::RocksDb::open_default(path.as_ref())?;
+
+        Self::load_from_store(Box::new(db))
+    }
+
+    /// Load a [`DiskTree`] from the contents of `db`
+    ///
+    /// If `db` was written by a [`Persistent`], the hashes of its nodes are computed and stored
+    /// first, a chunk of elements at a time. Only the nodes touched by the current chunk are held
+    /// in memory, so this works for trees that don't fit in memory
+    ///
+    /// [`Persistent`]: super::Persistent
+    pub fn load_from_store(db: Box<dyn KvStore>) -> Result<Self, Error> {
+        let meta = db.get(&meta_key()?)?;
+        let mut tree = Self::new_with_store(db);
+
+        match meta {
+            Some(bytes) => match ValueFormat::<V>::from_bytes(&bytes)? {
+                ValueFormat::V2(ValueV2::DiskTreeMeta { len }) => {
+                    tree.len = usize::try_from(len).map_err(|_| Error::DatabaseConsistency)?;
+                }
+                _ => return Err(Error::DatabaseConsistency),
+            },
+            None => tree.migrate()?,
+        }
+
+        tree.root_hash = tree.node(&SubtreeId::root())?;
+
+        Ok(tree)
+    }
+
+    /// The root hash of the tree
+    #[inline]
+    #[must_use]
+    pub fn root_hash(&self) -> Element {
+        self.root_hash
+    }
+
+    /// The number of elements in the tree
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree contains no elements
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get a reference to the backing store
+    #[inline]
+    #[must_use]
+    pub fn db(&self) -> &dyn KvStore {
+        &*self.db
+    }
+
+    /// Write a consistent copy of the backing store to `path`, like
+    /// [`Persistent::checkpoint`](super::Persistent::checkpoint)
+    pub fn checkpoint<P: AsRef<FsPath>>(&self, path: P) -> Result<(), Error> {
+        self.db.checkpoint(path.as_ref())?;
+        Ok(())
+    }
+
+    /// Take the backing store out of this [`DiskTree`]
+    #[inline]
+    #[must_use]
+    pub fn into_store(self) -> Box<dyn KvStore> {
+        self.db
+    }
+
+    /// Whether `element` is in the tree
+    ///
+    /// This only reads the element's slot, so it doesn't touch any other nodes
+    pub fn contains_element(&self, element: &Element) -> Result<bool, Error> {
+        if *element == Element::NULL_HASH {
+            return Ok(false);
+        }
+
+        Ok(self.node(&SubtreeId::slot::<DEPTH>(*element))? == *element)
+    }
+
+    /// Get the value associated with `element`, if it is in the tree
+    pub fn get(&self, element: Element) -> Result<Option<V>, Error> {
+        let Some(bytes) = self.db.get(&element_key(element)?)? else {
+            return Ok(None);
+        };
+
+        match ValueFormat::<V>::from_bytes(&bytes)? {
+            ValueFormat::V2(ValueV2::Metadata(value)) => Ok(Some(Arc::unwrap_or_clone(value))),
+            _ => Err(Error::DatabaseConsistency),
+        }
+    }
+
+    /// Generate a [`Path`] for `element`, which is the same as [`Tree::path_for`] would generate
+    /// for a [`Tree`] with the same elements
+    ///
+    /// [`Tree`]: crate::Tree
+    /// [`Tree::path_for`]: crate::Tree::path_for
+    pub fn path_for(&self, element: Element) -> Result<Path, Error> {
+        let slot = SubtreeId::slot::<DEPTH>(element);
+
+        let mut siblings = Vec::with_capacity(DEPTH - 1);
+        let mut id = SubtreeId::root();
+
+        for (level, right) in slot.path().iter().enumerate() {
+            // every sibling below an empty node is empty, so there is no need to read them
+            if self.node(&id)? == empty_tree_hash(DEPTH - level) {
+                siblings.extend((1..DEPTH - level).rev().map(empty_tree_hash));
+                break;
+            }
+
+            siblings.push(self.node(&id.child(!right))?);
+            id = id.child(*right);
+        }
+
+        // the siblings were collected from the root down
+        siblings.reverse();
+
+        Ok(Path {
+            siblings,
+            element,
+            root_hash: self.root_hash,
+        })
+    }
+
+    /// Generate a [`NonMembershipProof`] for `element`, or `None` if `element` is in the tree,
+    /// like [`Tree::non_membership_proof`]
+    ///
+    /// [`Tree::non_membership_proof`]: crate::Tree::non_membership_proof
+    pub fn non_membership_proof(
+        &self,
+        element: Element,
+    ) -> Result<Option<NonMembershipProof>, Error> {
+        if self.contains_element(&element)? {
+            return Ok(None);
+        }
+
+        let occupant = self.node(&SubtreeId::slot::<DEPTH>(element))?;
+        let path = self.path_for(element)?;
+
+        Ok(Some(NonMembershipProof {
+            element,
+            occupant,
+            siblings: path.siblings,
+        }))
+    }
+
+    /// The root hash the tree would have after inserting `insert_elements` and removing
+    /// `remove_elements`, like [`Tree::root_hash_with`], without changing the tree
+    ///
+    /// [`Tree::root_hash_with`]: crate::Tree::root_hash_with
+    pub fn root_hash_with(
+        &self,
+        insert_elements: &[Element],
+        remove_elements: &[Element],
+    ) -> Result<Element, Error> {
+        let leaves = insert_elements
+            .iter()
+            .map(|element| (SubtreeId::slot::<DEPTH>(*element), *element))
+            .chain(
+                remove_elements
+                    .iter()
+                    .map(|element| (SubtreeId::slot::<DEPTH>(*element), Element::NULL_HASH)),
+            )
+            .collect();
+
+        let updated = self.update_nodes(&mut WriteBatch::default(), leaves)?;
+
+        Ok(updated
+            .get(&SubtreeId::root())
+            .copied()
+            .unwrap_or(self.root_hash))
+    }
+
+    /// Every element in the tree and its value, in the order of the backing store's keys
+    ///
+    /// Elements are read from the store as the iterator advances, so they aren't all held in
+    /// memory at once
+    pub fn elements(&self) -> impl Iterator<Item = Result<(Element, V), Error>> + '_ {
+        self.db
+            .iter(KeyRange::all(), Direction::Forward)
+            .filter_map(|entry| {
+                let (key, value) = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => return Some(Err(err.into())),
+                };
+
+                let element = match KeyFormat::from_bytes(&key) {
+                    Ok(KeyFormat::V1(element) | KeyFormat::V2(KeyV2::Element(element))) => element,
+                    Ok(_) => return None,
+                    Err(err) => return Some(Err(err.into())),
+                };
+
+                Some(match ValueFormat::<V>::from_bytes(&value) {
+                    Ok(ValueFormat::V2(ValueV2::Metadata(value))) => {
+                        Ok((element, Arc::unwrap_or_clone(value)))
+                    }
+                    Ok(_) => Err(Error::DatabaseConsistency),
+                    Err(err) => Err(err.into()),
+                })
+            })
+    }
+
+    /// Compare a subtree of this tree with the same subtree of a peer's tree, like
+    /// [`Tree::diff_subtree`]
+    ///
+    /// [`Tree::diff_subtree`]: crate::Tree::diff_subtree
+    pub fn diff_subtree(
+        &self,
+        id: &SubtreeId,
+        remote: &SubtreeSummary,
+    ) -> Result<SubtreeDiff, Error> {
+        if id.path().len() > DEPTH - 1 {
+            return Ok(SubtreeDiff::default());
+        }
+
+        match remote {
+            SubtreeSummary::Children { left, right } => {
+                // the bottom of the tree only has elements
+                if id.path().len() == DEPTH - 1 {
+                    return Ok(SubtreeDiff::default());
+                }
+
+                let mut mismatched = Vec::new();
+                for (child, remote) in [(false, left), (true, right)] {
+                    if self.node(&id.child(child))? != *remote {
+                        mismatched.push(id.child(child));
+                    }
+                }
+
+                Ok(SubtreeDiff {
+                    mismatched,
+                    ..SubtreeDiff::default()
+                })
+            }
+            SubtreeSummary::Elements(remote) => {
+                let mut local = BTreeSet::new();
+                self.collect_elements(id.clone(), &mut local)?;
+
+                // ignore anything the peer sent that doesn't belong in this subtree
+                let remote: BTreeSet<_> = remote
+                    .iter()
+                    .copied()
+                    .filter(|e| *e != Element::NULL_HASH && id.contains::<DEPTH>(*e))
+                    .collect();
+
+                Ok(SubtreeDiff {
+                    mismatched: Vec::new(),
+                    missing: remote.difference(&local).copied().collect(),
+                    extra: local.difference(&remote).copied().collect(),
+                })
+            }
+        }
+    }
+
+    /// Add every element in the subtree at `id` to `elements`, only reading non-empty nodes
+    fn collect_elements(
+        &self,
+        id: SubtreeId,
+        elements: &mut BTreeSet<Element>,
+    ) -> Result<(), Error> {
+        let hash = self.node(&id)?;
+
+        if hash == empty_tree_hash(DEPTH - id.path().len()) {
+            return Ok(());
+        }
+
+        if id.path().len() == DEPTH - 1 {
+            elements.insert(hash);
+            return Ok(());
+        }
+
+        self.collect_elements(id.child(false), elements)?;
+        self.collect_elements(id.child(true), elements)
+    }
+
+    /// Insert a single element into the tree
+    pub fn insert(&mut self, element: Element, value: V) -> Result<(), Error> {
+        let mut batch = Batch::new();
+        batch.insert(element, value)?;
+        self.insert_batch(batch)
+    }
+
+    /// Insert and remove the elements in `batch`, writing the values and every changed node to the
+    /// backing store in a single write
+    ///
+    /// Inserting an element whose slot is already occupied, or removing an element that isn't in
+    /// the tree, is a collision, and nothing is written. Like [`Tree::insert`], inserting or
+    /// removing [`Element::NULL_HASH`] always collides
+    ///
+    /// [`Tree::insert`]: crate::Tree::insert
+    pub fn insert_batch(&mut self, batch: Batch<DEPTH, V>) -> Result<(), Error> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        if batch
+            .insert_elements()
+            .chain(batch.remove_elements())
+            .any(|element| element == Element::NULL_HASH)
+        {
+            let mut collisions = CollisionError::new();
+            collisions.push(collision::<DEPTH>(Element::NULL_HASH, Element::NULL_HASH));
+            return Err(collisions.into());
+        }
+
+        let mut collisions = CollisionError::new();
+        let mut write_batch = WriteBatch::default();
+        let mut leaves = BTreeMap::new();
+        let mut len = self.len;
+
+        for (element, value) in batch.insert_entries() {
+            let slot = SubtreeId::slot::<DEPTH>(*element);
+            let in_tree = self.node(&slot)?;
+
+            if in_tree != Element::NULL_HASH {
+                collisions.push(collision::<DEPTH>(in_tree, *element));
+                continue;
+            }
+
+            let value = ValueFormat::V2(ValueV2::Metadata(Arc::new(value.clone())));
+            write_batch.put(element_key(*element)?, value.to_bytes()?);
+            leaves.insert(slot, *element);
+            len += 1;
+        }
+
+        for element in batch.remove_elements() {
+            let slot = SubtreeId::slot::<DEPTH>(element);
+            let in_tree = self.node(&slot)?;
+
+            if in_tree != element {
+                collisions.push(collision::<DEPTH>(in_tree, element));
+                continue;
+            }
+
+            for key in [
+                KeyFormat::V2(KeyV2::Element(element)),
+                KeyFormat::V1(element),
+            ] {
+                write_batch.delete(key.to_bytes()?);
+            }
+            leaves.insert(slot, Element::NULL_HASH);
+            len -= 1;
+        }
+
+        if !collisions.is_empty() {
+            return Err(collisions.into());
+        }
+
+        let updated = self.update_nodes(&mut write_batch, leaves)?;
+        write_batch.put(meta_key()?, meta_value::<V>(len)?);
+
+        self.db.write(write_batch)?;
+
+        self.len = len;
+        if let Some(root_hash) = updated.get(&SubtreeId::root()) {
+            self.root_hash = *root_hash;
+        }
+        self.cache_nodes(updated);
+
+        Ok(())
+    }
+
+    /// Compute the node hashes of a store written by [`Persistent`](super::Persistent)
+    fn migrate(&mut self) -> Result<(), Error> {
+        let mut write_batch = WriteBatch::default();
+
+        // nodes left by an earlier migration may be stale, so start from an empty tree
+        let mut deletes = 0;
+        for entry in self.db.iter(KeyRange::all(), Direction::Forward) {
+            let (key, _) = entry?;
+            if let KeyFormat::V2(KeyV2::Node { .. }) = KeyFormat::from_bytes(&key)? {
+                write_batch.delete(key);
+                deletes += 1;
+            }
+
+            if deletes == MIGRATION_CHUNK {
+                self.db.write(std::mem::take(&mut write_batch))?;
+                deletes = 0;
+            }
+        }
+        history::clear(&*self.db, &mut write_batch)?;
+        self.db.write(std::mem::take(&mut write_batch))?;
+
+        let mut leaves = BTreeMap::new();
+        let mut len = 0;
+
+        for entry in self.db.iter(KeyRange::all(), Direction::Forward) {
+            let (key, value) = entry?;
+
+            let element = match KeyFormat::from_bytes(&key)? {
+                KeyFormat::V1(element) => {
+                    // values are upgraded when they are read, so only the key needs rewriting
+                    write_batch.delete(key);
+                    write_batch.put(element_key(element)?, value);
+                    element
+                }
+                KeyFormat::V2(KeyV2::Element(element)) => element,
+                _ => continue,
+            };
+
+            let slot = SubtreeId::slot::<DEPTH>(element);
+            if leaves.contains_key(&slot) || self.node(&slot)? != Element::NULL_HASH {
+                return Err(Error::DatabaseConsistency);
+            }
+
+            leaves.insert(slot, element);
+            len += 1;
+
+            if leaves.len() == MIGRATION_CHUNK {
+                let updated = self.update_nodes(&mut write_batch, std::mem::take(&mut leaves))?;
+                self.db.write(std::mem::take(&mut write_batch))?;
+                self.cache_nodes(updated);
+            }
+        }
+
+        let updated = self.update_nodes(&mut write_batch, leaves)?;
+        write_batch.put(meta_key()?, meta_value::<V>(len)?);
+        self.db.write(write_batch)?;
+        self.cache_nodes(updated);
+
+        self.len = len;
+
+        Ok(())
+    }
+
+    /// Recompute the hashes of every ancestor of `leaves`, and add them to `write_batch`
+    ///
+    /// Returns the new hash of every changed node, which should be cached once `write_batch` has
+    /// been written
+    fn update_nodes(
+        &self,
+        write_batch: &mut WriteBatch,
+        leaves: BTreeMap<SubtreeId, Element>,
+    ) -> Result<HashMap<SubtreeId, Element>, Error> {
+        let mut updated = HashMap::with_capacity(leaves.len() * DEPTH);
+        let mut level = BTreeSet::new();
+
+        for (slot, element) in leaves {
+            level.extend(slot.parent());
+            updated.insert(slot, element);
+        }
+
+        // every node in `level` is at the same depth, so their children are already up to date
+        while !level.is_empty() {
+            let mut next = BTreeSet::new();
+
+            for id in level {
+                let depth = DEPTH - id.path().len();
+                let left = self.updated_node(&updated, &id.child(false))?;
+                let right = self.updated_node(&updated, &id.child(true))?;
+
+                let empty_child = empty_tree_hash(depth - 1);
+                let hash = match left == empty_child && right == empty_child {
+                    true => empty_tree_hash(depth),
+                    false => hash_merge([left, right]),
+                };
+
+                next.extend(id.parent());
+                updated.insert(id, hash);
+            }
+
+            level = next;
+        }
+
+        // only non-empty nodes are stored
+        for (id, hash) in &updated {
+            let key = node_key(id)?;
+
+            match *hash == empty_tree_hash(DEPTH - id.path().len()) {
+                true => write_batch.delete(key),
+                false => {
+                    write_batch.put(key, ValueFormat::<V>::V2(ValueV2::Node(*hash)).to_bytes()?)
+                }
+            }
+        }
+
+        Ok(updated)
+    }
+
+    fn updated_node(
+        &self,
+        updated: &HashMap<SubtreeId, Element>,
+        id: &SubtreeId,
+    ) -> Result<Element, Error> {
+        match updated.get(id) {
+            Some(hash) => Ok(*hash),
+            None => self.node(id),
+        }
+    }
+
+    /// The hash of the node at `id`, from the cache if possible
+    fn node(&self, id: &SubtreeId) -> Result<Element, Error> {
+        if let Some(hash) = self.nodes.lock().unwrap().get(id) {
+            return Ok(*hash);
+        }
+
+        let hash = match self.db.get(&node_key(id)?)? {
+            None => empty_tree_hash(DEPTH - id.path().len()),
+            Some(bytes) => match ValueFormat::<V>::from_bytes(&bytes)? {
+                ValueFormat::V2(ValueV2::Node(hash)) => hash,
+                _ => return Err(Error::DatabaseConsistency),
+            },
+        };
+
+        self.nodes.lock().unwrap().put(id.clone(), hash);
+
+        Ok(hash)
+    }
+
+    fn cache_nodes(&self, updated: HashMap<SubtreeId, Element>) {
+        let mut nodes = self.nodes.lock().unwrap();
+
+        for (id, hash) in updated {
+            nodes.put(id, hash);
+        }
+    }
+}
+
+fn collision<const DEPTH: usize>(in_tree: Element, inserted: Element) -> Collision {
+    Collision {
+        in_tree,
+        inserted,
+        depth: DEPTH,
+        struct_name: StructName::Tree,
+    }
+}
+
+fn element_key(element: Element) -> Result<Vec<u8>, Error> {
+    Ok(KeyFormat::V2(KeyV2::Element(element)).to_bytes()?)
+}
+
+fn meta_key() -> Result<Vec<u8>, Error> {
+    Ok(KeyFormat::V2(KeyV2::DiskTreeMeta).to_bytes()?)
+}
+
+/// Whether `db` was last written by a [`DiskTree`], so its node hashes are up to date
+pub(super) fn has_meta(db: &dyn KvStore) -> Result<bool, Error> {
+    Ok(db.get(&meta_key()?)?.is_some())
+}
+
+fn meta_value<V>(len: usize) -> Result<Vec<u8>, Error>
+where
+    V: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+{
+    let len = u64::try_from(len).unwrap();
+    Ok(ValueFormat::<V>::V2(ValueV2::DiskTreeMeta { len }).to_bytes()?)
+}
+
+/// The path is packed into bytes most significant bit first
+fn node_key(id: &SubtreeId) -> Result<Vec<u8>, Error> {
+    let mut path = vec![0; id.path().len().div_ceil(8)];
+
+    for (index, right) in id.path().iter().enumerate() {
+        if *right {
+            path[index / 8] |= 0x80 >> (index % 8);
+        }
+    }
+
+    let level = u16::try_from(id.path().len()).unwrap();
+
+    Ok(KeyFormat::V2(KeyV2::Node { level, path }).to_bytes()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use super::*;
+    use crate::{Tree, batch, storage::Persistent};
+
+    fn assert_matches(disk: &DiskTree<64, i32>, tree: &Tree<64, i32>, elements: &[Element]) {
+        assert_eq!(disk.root_hash(), tree.root_hash());
+        assert_eq!(disk.len(), tree.len());
+
+        for element in elements {
+            let path = disk.path_for(*element).unwrap();
+            assert_eq!(path.siblings, tree.path_for(*element).siblings);
+            assert_eq!(path.actual_root_hash(), tree.root_hash());
+
+            assert_eq!(
+                disk.contains_element(element).unwrap(),
+                tree.contains_element(element)
+            );
+            assert_eq!(disk.get(*element).unwrap().as_ref(), tree.get(*element));
+            assert_eq!(
+                disk.non_membership_proof(*element).unwrap(),
+                tree.non_membership_proof(*element)
+            );
+        }
+
+        let mut disk_elements = disk.elements().map(Result::unwrap).collect::<Vec<_>>();
+        disk_elements.sort_unstable_by_key(|(element, _)| *element);
+        assert!(
+            disk_elements
+                .iter()
+                .map(|(element, value)| (element, value))
+                .eq(tree.elements())
+        );
+    }
+
+    #[proptest(cases = 32)]
+    fn disk_tree_matches_tree(batch: Batch<64, i32>, other: Element) {
+        let mut disk = DiskTree::<64, i32>::new_in_memory();
+        let mut tree = Tree::<64, i32>::new();
+
+        let mut elements = batch.insert_elements().collect::<Vec<_>>();
+        elements.retain(|element| *element != Element::NULL_HASH);
+        elements.push(other);
+
+        disk.insert_batch(batch.clone()).unwrap();
+        tree.insert_batch(batch, |_| {}, |_| {}).unwrap();
+
+        assert_matches(&disk, &tree, &elements);
+
+        // `other` was pushed last, so this only removes elements in the tree
+        let removed = &elements[..elements.len() / 2];
+        assert_eq!(
+            disk.root_hash_with(&[], removed).unwrap(),
+            tree.root_hash_with(&[], removed)
+        );
+    }
+
+    #[test]
+    fn diffs_subtrees_like_tree() {
+        let mut local = DiskTree::<64, i32>::new_in_memory();
+        local
+            .insert_batch(batch! { 1 => 0, 2 => 0, 3 => 0 })
+            .unwrap();
+        let remote: Tree<64, i32> = crate::smirk! { 1 => 0, 2 => 0, 4 => 0 };
+
+        let mut pending = vec![SubtreeId::root()];
+        let mut diff = SubtreeDiff::default();
+
+        while let Some(id) = pending.pop() {
+            let summary = remote.subtree_summary(&id, 2).unwrap();
+            let subtree_diff = local.diff_subtree(&id, &summary).unwrap();
+
+            pending.extend(subtree_diff.mismatched);
+            diff.missing.extend(subtree_diff.missing);
+            diff.extra.extend(subtree_diff.extra);
+        }
+
+        assert_eq!(diff.missing, [Element::new(4)]);
+        assert_eq!(diff.extra, [Element::new(3)]);
+    }
+
+    #[test]
+    fn removes_elements() {
+        let mut disk = DiskTree::<64, i32>::new_in_memory();
+        disk.insert_batch(batch! { 1 => 10, 2 => 20, 3 => 30 })
+            .unwrap();
+
+        let mut batch = Batch::new();
+        batch.remove(Element::new(2)).unwrap();
+        disk.insert_batch(batch).unwrap();
+
+        let tree: Tree<64, i32> = crate::smirk! { 1 => 10, 3 => 30 };
+        assert_matches(&disk, &tree, &[Element::new(1), Element::new(2)]);
+
+        // removing an element that isn't in the tree doesn't change anything
+        let mut batch = Batch::new();
+        batch.remove(Element::new(2)).unwrap();
+        disk.insert_batch(batch).unwrap_err();
+        assert_eq!(disk.root_hash(), tree.root_hash());
+
+        // nor does inserting into an occupied slot
+        let collides = Element::new(1) + (Element::new(1) << 100);
+        disk.insert(collides, 0).unwrap_err();
+        assert_eq!(disk.root_hash(), tree.root_hash());
+    }
+
+    #[test]
+    #[expect(deprecated)]
+    fn rejects_null_hash() {
+        let mut disk = DiskTree::<64, i32>::new_in_memory();
+
+        let Error::Collision(error) = disk.insert(Element::NULL_HASH, 1).unwrap_err() else {
+            panic!("expected a collision");
+        };
+        assert_eq!(
+            error.collisions(),
+            [collision::<64>(Element::NULL_HASH, Element::NULL_HASH)]
+        );
+
+        let mut batch = Batch::new();
+        batch.remove(Element::NULL_HASH).unwrap();
+        disk.insert_batch(batch).unwrap_err();
+
+        assert_eq!(disk.len(), 0);
+        assert_eq!(disk.root_hash(), empty_tree_hash(64));
+    }
+
+    #[test]
+    fn reloads_without_migrating() {
+        let mut disk = DiskTree::<64, i32>::new_in_memory();
+        disk.insert_batch(batch! { 1 => 10, 2 => 20 }).unwrap();
+        let root_hash = disk.root_hash();
+
+        let disk = DiskTree::<64, i32>::load_from_store(disk.into_store()).unwrap();
+
+        assert_eq!(disk.root_hash(), root_hash);
+        assert_eq!(disk.len(), 2);
+        assert_eq!(disk.get(Element::new(1)).unwrap(), Some(10));
+    }
+
+    #[test]
+    fn migrates_persistent_store() {
+        let mut persistent = Persistent::<64, i32>::new_in_memory();
+        let elements = (1..=MIGRATION_CHUNK as u64 + 10)
+            .map(Element::new)
+            .collect::<Vec<_>>();
+        let batch = Batch::from_entries(elements.iter().map(|e| (*e, 1)), []).unwrap();
+
+        persistent.insert_batch(batch).unwrap();
+        persistent.persist_hashes().unwrap();
+        let (mut tree, db) = persistent.into_parts();
+
+        let mut disk = DiskTree::<64, i32>::load_from_store(db).unwrap();
+        assert_matches(&disk, &tree.clone_without_cache(), &elements[..20]);
+
+        // the migrated store can be written by either type
+        disk.insert(Element::new(0xdead), 2).unwrap();
+        tree.insert(Element::new(0xdead), 2).unwrap();
+        assert_eq!(disk.root_hash(), tree.root_hash());
+
+        let persistent = Persistent::<64, i32>::load_from_store(disk.into_store()).unwrap();
+        assert_eq!(persistent.tree().root_hash(), tree.root_hash());
+    }
+
+    #[test]
+    fn persistent_writes_are_picked_up() {
+        let mut disk = DiskTree::<64, i32>::new_in_memory();
+        disk.insert(Element::new(1), 10).unwrap();
+
+        let mut persistent = Persistent::<64, i32>::load_from_store(disk.into_store()).unwrap();
+        persistent.insert(Element::new(2), 20).unwrap();
+
+        let (tree, db) = persistent.into_parts();
+        let disk = DiskTree::<64, i32>::load_from_store(db).unwrap();
+        assert_matches(
+            &disk,
+            &tree.clone_without_cache(),
+            &[Element::new(1), Element::new(2)],
+        );
+    }
+}
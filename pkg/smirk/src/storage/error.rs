@@ -1,3 +1,5 @@
+use element::Element;
+
 use crate::CollisionError;
 
 /// An error that can occur when interacting with a a [`Persistent`]
@@ -5,9 +7,9 @@ use crate::CollisionError;
 /// [`Persistent`]: crate::storage::Persistent
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    /// An error from rocksdb
-    #[error("rocksdb error: {0}")]
-    Rocksdb(#[from] rocksdb::Error),
+    /// An error from the backing store
+    #[error("kv store error: {0}")]
+    KvStore(#[from] kv_store::Error),
 
     /// A collision error
     #[error("collision: {0}")]
@@ -26,7 +28,44 @@ pub enum Error {
     #[error("wire message error: {0}")]
     WireMessage(#[from] wire_message::Error),
 
+    /// The requested version of the tree is not in the retained history
+    #[error("version {0} of the tree is not available")]
+    VersionUnavailable(u64),
+
     /// Database consistency
     #[error("the database contained inconsistent data")]
     DatabaseConsistency,
+
+    /// An error reading or writing an export
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The data passed to [`Tree::import_from`] wasn't written by [`Tree::export_to`]
+    ///
+    /// [`Tree::import_from`]: crate::Tree::import_from
+    /// [`Tree::export_to`]: crate::Tree::export_to
+    #[error("not a smirk tree export")]
+    NotAnExport,
+
+    /// The export is of a tree with a different depth
+    #[error("expected an export of a tree with depth {expected}, got depth {actual}")]
+    DepthMismatch {
+        /// The depth of the tree being imported
+        expected: usize,
+        /// The depth recorded in the export
+        actual: u64,
+    },
+
+    /// The checksum at the end of the export didn't match its contents
+    #[error("the export checksum didn't match its contents")]
+    ChecksumMismatch,
+
+    /// The root hash of the imported tree didn't match the root hash recorded in the export
+    #[error("expected root hash {expected}, got {actual}")]
+    RootHashMismatch {
+        /// The root hash recorded in the export
+        expected: Element,
+        /// The root hash of the imported tree
+        actual: Element,
+    },
 }
@@ -0,0 +1,301 @@
+use std::io::{Read, Write};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+use sha2::{Digest, Sha256};
+use wire_message::{WireMessage, wire_message};
+
+use crate::{Batch, Tree, hash_cache::HashCache, hasher::TreeHasher};
+
+use super::Error;
+
+/// Written before anything else, so that other files are rejected before we try to parse them
+const MAGIC: [u8; 8] = *b"SMIRKEXP";
+
+/// Don't trust the length in the header for preallocating
+const MAX_PREALLOCATED_ENTRIES: usize = 1 << 16;
+
+/// An export is laid out as:
+///  - [`MAGIC`]
+///  - an [`ExportFormat`] header
+///  - `len` borsh-encoded `(Element, V)` records, in ascending order of element
+///  - the SHA-256 of everything before it
+#[derive(Debug, Clone)]
+#[wire_message]
+enum ExportFormat {
+    V1(HeaderV1),
+}
+
+impl WireMessage for ExportFormat {
+    type Ctx = ();
+    type Err = core::convert::Infallible;
+
+    fn version(&self) -> u64 {
+        match self {
+            Self::V1(_) => 1,
+        }
+    }
+
+    fn upgrade_once(self, _ctx: &mut Self::Ctx) -> Result<Self, wire_message::Error> {
+        match self {
+            Self::V1(_) => Err(Self::max_version_error()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+struct HeaderV1 {
+    depth: u64,
+    len: u64,
+    root_hash: Element,
+}
+
+/// Hashes everything that passes through it
+struct Checksummed<T> {
+    inner: T,
+    hasher: Sha256,
+}
+
+impl<T> Checksummed<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn checksum(&self) -> [u8; 32] {
+        self.hasher.clone().finalize().into()
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<const DEPTH: usize, V, C, H> Tree<DEPTH, V, C, H>
+where
+    H: TreeHasher,
+{
+    /// Write the entries of this tree to `writer` in a portable format that can be read back with
+    /// [`Tree::import_from`]
+    ///
+    /// Entries are streamed one at a time, so the export is never held in memory. The format is
+    /// versioned, and includes the root hash of the tree and a checksum of the contents, so an
+    /// export that was truncated or modified is rejected on import. Wrap `writer` in a
+    /// [`BufWriter`][std::io::BufWriter] if it is a file or a socket
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// let tree: Tree<64, i32> = smirk! { 1 => 10, 2 => 20 };
+    ///
+    /// let mut bytes = Vec::new();
+    /// tree.export_to(&mut bytes).unwrap();
+    ///
+    /// let imported = Tree::<64, i32>::import_from(&*bytes).unwrap();
+    /// assert_eq!(imported.root_hash(), tree.root_hash());
+    /// assert_eq!(imported.get(element::Element::new(2)), Some(&20));
+    /// ```
+    pub fn export_to<W: Write>(&self, writer: W) -> Result<(), Error>
+    where
+        V: BorshSerialize,
+    {
+        let mut writer = Checksummed::new(writer);
+
+        let header = ExportFormat::V1(HeaderV1 {
+            depth: DEPTH as u64,
+            len: self.len() as u64,
+            root_hash: self.root_hash(),
+        });
+
+        writer.write_all(&MAGIC)?;
+        header.to_bytes_in(&mut writer)?;
+
+        for (element, value) in self.elements() {
+            element.serialize(&mut writer)?;
+            value.serialize(&mut writer)?;
+        }
+
+        let checksum = writer.checksum();
+        writer.write_all(&checksum)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Read a tree written by [`Tree::export_to`] from `reader`
+    ///
+    /// The checksum and the root hash are verified before the tree is returned, and an export of a
+    /// tree with a different `DEPTH` is rejected. Only the bytes of the export are read, so
+    /// `reader` can contain other data after it
+    pub fn import_from<R: Read>(reader: R) -> Result<Self, Error>
+    where
+        V: BorshDeserialize + Clone,
+        C: HashCache<H> + Default,
+    {
+        let mut reader = Checksummed::new(reader);
+
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+
+        if magic != MAGIC {
+            return Err(Error::NotAnExport);
+        }
+
+        let ExportFormat::V1(header) = ExportFormat::from_reader(&mut reader)?.upgrade(&mut ())?;
+
+        if header.depth != DEPTH as u64 {
+            return Err(Error::DepthMismatch {
+                expected: DEPTH,
+                actual: header.depth,
+            });
+        }
+
+        let len = usize::try_from(header.len).map_err(|_| Error::DatabaseConsistency)?;
+        let mut batch = Batch::with_capacity(len.min(MAX_PREALLOCATED_ENTRIES));
+
+        for _ in 0..len {
+            let element = Element::deserialize_reader(&mut reader)?;
+            let value = V::deserialize_reader(&mut reader)?;
+            batch.insert(element, value)?;
+        }
+
+        let expected = reader.checksum();
+        let mut checksum = [0; 32];
+        reader.inner.read_exact(&mut checksum)?;
+
+        if checksum != expected {
+            return Err(Error::ChecksumMismatch);
+        }
+
+        let mut tree = Self::new();
+        tree.insert_batch(batch, |_| {}, |_| {})?;
+
+        let actual = tree.root_hash();
+
+        if actual != header.root_hash {
+            return Err(Error::RootHashMismatch {
+                expected: header.root_hash,
+                actual,
+            });
+        }
+
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use super::*;
+    use crate::{hash_cache::SimpleHashCache, smirk};
+
+    fn export<const DEPTH: usize>(tree: &Tree<DEPTH, i32>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tree.export_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[proptest(cases = 32)]
+    fn export_import_round_trip(tree: Tree<64, i32>) {
+        let bytes = export(&tree);
+        let imported = Tree::<64, i32, SimpleHashCache>::import_from(&*bytes).unwrap();
+
+        assert_eq!(imported.root_hash(), tree.root_hash());
+        assert_eq!(
+            imported.elements().collect::<Vec<_>>(),
+            tree.elements().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn empty_tree_round_trips() {
+        let tree = Tree::<16, i32>::new();
+        let imported = Tree::<16, i32>::import_from(&*export(&tree)).unwrap();
+
+        assert!(imported.is_empty());
+        assert_eq!(imported.root_hash(), tree.root_hash());
+    }
+
+    #[test]
+    fn leaves_trailing_data_unread() {
+        let tree: Tree<64, i32> = smirk! { 1 => 10 };
+        let mut bytes = export(&tree);
+        bytes.extend_from_slice(b"trailing");
+
+        let mut reader = &*bytes;
+        Tree::<64, i32>::import_from(&mut reader).unwrap();
+
+        assert_eq!(reader, b"trailing");
+    }
+
+    #[test]
+    fn rejects_modified_exports() {
+        let tree: Tree<64, i32> = smirk! { 1 => 10, 2 => 20, 3 => 30 };
+        let bytes = export(&tree);
+
+        for i in 0..bytes.len() {
+            let mut modified = bytes.clone();
+            modified[i] ^= 1;
+
+            assert!(Tree::<64, i32>::import_from(&*modified).is_err());
+        }
+
+        for len in 0..bytes.len() {
+            assert!(Tree::<64, i32>::import_from(&bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_root_hash() {
+        let tree: Tree<64, i32> = smirk! { 1 => 10 };
+        let mut writer = Checksummed::new(Vec::new());
+
+        writer.write_all(&MAGIC).unwrap();
+        ExportFormat::V1(HeaderV1 {
+            depth: 64,
+            len: 1,
+            root_hash: tree.root_hash() + Element::ONE,
+        })
+        .to_bytes_in(&mut writer)
+        .unwrap();
+        Element::new(1).serialize(&mut writer).unwrap();
+        10i32.serialize(&mut writer).unwrap();
+        let checksum = writer.checksum();
+        writer.write_all(&checksum).unwrap();
+
+        let err = Tree::<64, i32>::import_from(&*writer.inner).unwrap_err();
+        assert!(matches!(err, Error::RootHashMismatch { .. }));
+    }
+
+    #[test]
+    fn rejects_wrong_depth() {
+        let tree: Tree<64, i32> = smirk! { 1 => 10 };
+        let err = Tree::<32, i32>::import_from(&*export(&tree)).unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::DepthMismatch {
+                expected: 32,
+                actual: 64
+            }
+        ));
+    }
+}
@@ -33,7 +33,24 @@ impl WireMessage for KeyFormat {
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub(super) enum KeyV2 {
     Element(Element),
-    KnownHash { left: Element, right: Element },
+    KnownHash {
+        left: Element,
+        right: Element,
+    },
+    HistoryDiff {
+        version: [u8; 8],
+    },
+    HistoryRange,
+    /// The hash of a node of a [`DiskTree`], keyed by the left/right choices from the root packed
+    /// into bytes most significant bit first
+    ///
+    /// [`DiskTree`]: super::DiskTree
+    Node {
+        level: u16,
+        path: Vec<u8>,
+    },
+    DiskTreeMeta,
+    HistoryRetention,
 }
 
 #[derive(Debug, Clone)]
@@ -69,4 +86,17 @@ where
 pub(super) enum ValueV2<V: Clone> {
     Metadata(Arc<V>),
     KnownHash(Element),
+    HistoryDiff {
+        inserted: Vec<Element>,
+        removed: Vec<(Element, Arc<V>)>,
+    },
+    HistoryRange {
+        start: u64,
+        latest: u64,
+    },
+    Node(Element),
+    DiskTreeMeta {
+        len: u64,
+    },
+    HistoryRetention(u64),
 }
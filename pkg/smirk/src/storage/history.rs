@@ -0,0 +1,601 @@
+use std::{collections::BTreeMap, ops::RangeInclusive, sync::Arc};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+use kv_store::{Direction, KeyRange, KvStore, WriteBatch};
+use wire_message::WireMessage;
+
+use crate::{
+    Batch, Tree,
+    hash_cache::{HashCache, SimpleHashCache},
+};
+
+use super::{
+    Error, Persistent,
+    format::{KeyFormat, KeyV2, ValueFormat, ValueV2},
+};
+
+/// The versions of a tree that can be reconstructed from the stored diffs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct HistoryRange {
+    /// The oldest version that can be reconstructed
+    pub(super) start: u64,
+    /// The version of the current tree
+    pub(super) latest: u64,
+}
+
+fn range_key() -> Result<Vec<u8>, Error> {
+    Ok(KeyFormat::V2(KeyV2::HistoryRange).to_bytes()?)
+}
+
+fn retention_key() -> Result<Vec<u8>, Error> {
+    Ok(KeyFormat::V2(KeyV2::HistoryRetention).to_bytes()?)
+}
+
+/// The version is stored big-endian, so diffs are ordered by version
+fn diff_key(version: u64) -> Result<Vec<u8>, Error> {
+    Ok(KeyFormat::V2(KeyV2::HistoryDiff {
+        version: version.to_be_bytes(),
+    })
+    .to_bytes()?)
+}
+
+/// The keys of the diffs for versions in `from..to`, or every version from `from` if `to` is
+/// `None`
+fn diff_keys(from: u64, to: Option<u64>) -> Result<KeyRange, Error> {
+    let upper = match to {
+        Some(to) => diff_key(to)?,
+        None => {
+            // the immediate successor of the largest possible diff key
+            let mut upper = diff_key(u64::MAX)?;
+            upper.push(0);
+            upper
+        }
+    };
+
+    Ok(KeyRange::new(diff_key(from)?, upper))
+}
+
+pub(super) fn load_range<V>(db: &dyn KvStore) -> Result<Option<HistoryRange>, Error>
+where
+    V: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+{
+    let Some(bytes) = db.get(&range_key()?)? else {
+        return Ok(None);
+    };
+
+    match ValueFormat::<V>::from_bytes(&bytes)? {
+        ValueFormat::V2(ValueV2::HistoryRange { start, latest }) => {
+            Ok(Some(HistoryRange { start, latest }))
+        }
+        _ => Err(Error::DatabaseConsistency),
+    }
+}
+
+pub(super) fn load_retention<V>(db: &dyn KvStore) -> Result<Option<u64>, Error>
+where
+    V: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+{
+    let Some(bytes) = db.get(&retention_key()?)? else {
+        return Ok(None);
+    };
+
+    match ValueFormat::<V>::from_bytes(&bytes)? {
+        ValueFormat::V2(ValueV2::HistoryRetention(retention)) => Ok(Some(retention)),
+        _ => Err(Error::DatabaseConsistency),
+    }
+}
+
+impl<const DEPTH: usize, V, C> Persistent<DEPTH, V, C> {
+    /// Start recording the changes made by [`Persistent::insert_batch_at`], so older versions of
+    /// the tree can be reconstructed with [`Persistent::tree_at`]
+    ///
+    /// Only the last `retention` versions are kept, older diffs are deleted as new versions are
+    /// inserted. The retention is persisted, so a loaded tree keeps recording history with it
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// let mut persistent = Persistent::<64, ()>::new_in_memory();
+    /// persistent.enable_history(2).unwrap();
+    ///
+    /// persistent.insert_batch_at(batch! { 1 }, 1).unwrap();
+    /// persistent.insert_batch_at(batch! { 2 }, 2).unwrap();
+    /// persistent.insert_batch_at(batch! { 3 }, 3).unwrap();
+    ///
+    /// assert_eq!(persistent.history(), Some(1..=3));
+    ///
+    /// let tree_at_1 = persistent.tree_at(1).unwrap();
+    /// assert_eq!(tree_at_1, smirk! { 1 });
+    /// ```
+    pub fn enable_history(&mut self, retention: u64) -> Result<(), Error> {
+        let value = ValueFormat::<V>::V2(ValueV2::HistoryRetention(retention));
+        self.db.put(&retention_key()?, &value.to_bytes()?)?;
+
+        self.history_retention = Some(retention);
+
+        Ok(())
+    }
+
+    /// The versions that [`Persistent::tree_at`] can reconstruct, if any
+    #[must_use]
+    pub fn history(&self) -> Option<RangeInclusive<u64>> {
+        self.history.map(|range| range.start..=range.latest)
+    }
+
+    /// Delete all recorded history, and stop recording it
+    pub fn clear_history(&mut self) -> Result<(), Error> {
+        let mut write_batch = WriteBatch::default();
+        self.write_clear_history(&mut write_batch)?;
+        write_batch.delete(retention_key()?);
+        self.db.write(write_batch)?;
+
+        self.history = None;
+        self.history_retention = None;
+
+        Ok(())
+    }
+
+    /// Insert a [`Batch`] as the changes made at `version`
+    ///
+    /// If history is enabled (see [`Persistent::enable_history`]) or was recorded previously,
+    /// the changes are stored so this version can be reverted by [`Persistent::tree_at`].
+    /// Versions must be inserted in increasing order, otherwise the existing history is discarded
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// let mut persistent = Persistent::<64, ()>::new_in_memory();
+    /// persistent.enable_history(100).unwrap();
+    ///
+    /// persistent.insert_batch_at(batch! { 1, 2 }, 10).unwrap();
+    /// assert!(persistent.tree().contains_element(&Element::new(1)));
+    /// assert_eq!(persistent.history(), Some(9..=10));
+    /// ```
+    pub fn insert_batch_at(&mut self, batch: Batch<DEPTH, V>, version: u64) -> Result<(), Error>
+    where
+        V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+        C: HashCache,
+    {
+        self.insert_batch_inner(batch, Some(version))
+    }
+
+    /// Reconstruct the tree as it was at `version`
+    ///
+    /// This clones the current tree and reverts every diff after `version`, so it is linear in
+    /// the size of the tree. Fails with [`Error::VersionUnavailable`] if `version` is outside
+    /// [`Persistent::history`]
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// let mut persistent = Persistent::<64, ()>::new_in_memory();
+    /// persistent.enable_history(100).unwrap();
+    ///
+    /// persistent.insert_batch_at(batch! { 1, 2 }, 1).unwrap();
+    /// let root_at_1 = persistent.tree().root_hash();
+    ///
+    /// let mut batch = batch! { 3 };
+    /// batch.remove(Element::new(1)).unwrap();
+    /// persistent.insert_batch_at(batch, 2).unwrap();
+    ///
+    /// let tree_at_1 = persistent.tree_at(1).unwrap();
+    /// assert_eq!(tree_at_1.root_hash(), root_at_1);
+    /// assert!(tree_at_1.contains_element(&Element::new(1)));
+    ///
+    /// assert!(persistent.tree_at(3).is_err());
+    /// ```
+    pub fn tree_at(&self, version: u64) -> Result<Tree<DEPTH, V>, Error>
+    where
+        V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+    {
+        reconstruct(&self.tree, &*self.db, self.history, version)
+    }
+
+    /// Take a [`HistorySnapshot`], which can reconstruct the versions in
+    /// [`Persistent::history`] without holding a reference to this [`Persistent`]
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// let mut persistent = Persistent::<64, ()>::new_in_memory();
+    /// persistent.enable_history(100).unwrap();
+    ///
+    /// persistent.insert_batch_at(batch! { 1 }, 1).unwrap();
+    /// let root_at_1 = persistent.tree().root_hash();
+    ///
+    /// let snapshot = persistent.history_snapshot();
+    /// persistent.insert_batch_at(batch! { 2 }, 2).unwrap();
+    ///
+    /// assert_eq!(snapshot.tree_at(1).unwrap().root_hash(), root_at_1);
+    /// // versions after the snapshot was taken aren't available
+    /// assert!(snapshot.tree_at(2).is_err());
+    /// ```
+    #[must_use]
+    pub fn history_snapshot(&self) -> HistorySnapshot<DEPTH, V, C>
+    where
+        V: Clone,
+        C: Clone,
+    {
+        HistorySnapshot {
+            tree: self.tree.clone(),
+            db: Arc::clone(&self.db),
+            history: self.history,
+        }
+    }
+
+    pub(super) fn history_enabled(&self) -> bool {
+        self.history.is_some() || self.history_retention.is_some()
+    }
+
+    /// Record that `version` made no changes to the tree
+    pub(super) fn record_empty_version(&mut self, version: u64) -> Result<(), Error>
+    where
+        V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+    {
+        let mut write_batch = WriteBatch::default();
+        let history = self.write_history(&mut write_batch, version, Vec::new(), Vec::new())?;
+        self.db.write(write_batch)?;
+
+        self.history = Some(history);
+
+        Ok(())
+    }
+
+    /// Add the diff for `version` to `write_batch`, pruning diffs outside the retention window,
+    /// and return the new history range
+    pub(super) fn write_history(
+        &self,
+        write_batch: &mut WriteBatch,
+        version: u64,
+        inserted: Vec<Element>,
+        removed: Vec<(Element, Arc<V>)>,
+    ) -> Result<HistoryRange, Error>
+    where
+        V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+    {
+        let mut history = match self.history {
+            Some(history) if version > history.latest => history,
+            Some(history) => {
+                tracing::warn!(
+                    version,
+                    latest = history.latest,
+                    "Version is not newer than the recorded history, discarding history"
+                );
+
+                self.write_clear_history(write_batch)?;
+                HistoryRange {
+                    start: version.saturating_sub(1),
+                    latest: version,
+                }
+            }
+            // the tree before this version is the oldest one we can reconstruct
+            None => HistoryRange {
+                start: version.saturating_sub(1),
+                latest: version,
+            },
+        };
+
+        history.latest = version;
+
+        if !(inserted.is_empty() && removed.is_empty()) {
+            let diff = ValueFormat::<V>::V2(ValueV2::HistoryDiff { inserted, removed });
+            write_batch.put(diff_key(version)?, diff.to_bytes()?);
+        }
+
+        if let Some(retention) = self.history_retention {
+            let floor = version.saturating_sub(retention);
+
+            if floor > history.start {
+                // diffs up to and including `floor` are only needed for versions before `floor`
+                for entry in self.db.iter(
+                    diff_keys(history.start + 1, Some(floor + 1))?,
+                    Direction::Forward,
+                ) {
+                    let (key, _) = entry?;
+                    write_batch.delete(key);
+                }
+
+                history.start = floor;
+            }
+        }
+
+        let range = ValueFormat::<V>::V2(ValueV2::HistoryRange {
+            start: history.start,
+            latest: history.latest,
+        });
+        write_batch.put(range_key()?, range.to_bytes()?);
+
+        Ok(history)
+    }
+
+    /// Add deletes for all recorded history to `write_batch`
+    pub(super) fn write_clear_history(&self, write_batch: &mut WriteBatch) -> Result<(), Error> {
+        if self.history.is_none() {
+            return Ok(());
+        }
+
+        clear(&*self.db, write_batch)
+    }
+}
+
+/// Reconstruct `tree`, whose history is `history`, as it was at `version`, see
+/// [`Persistent::tree_at`]
+fn reconstruct<const DEPTH: usize, V, C>(
+    tree: &Tree<DEPTH, V, C>,
+    db: &dyn KvStore,
+    history: Option<HistoryRange>,
+    version: u64,
+) -> Result<Tree<DEPTH, V>, Error>
+where
+    V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+{
+    let Some(history) = history.filter(|range| (range.start..=range.latest).contains(&version))
+    else {
+        return Err(Error::VersionUnavailable(version));
+    };
+
+    // Walk the diffs from newest to oldest, so the last state recorded for an element is its
+    // state at `version`. `None` means the element was absent
+    let mut states = BTreeMap::new();
+
+    // the store may already hold diffs for versions after `history`
+    let diffs = diff_keys(version + 1, history.latest.checked_add(1))?;
+    for entry in db.iter(diffs, Direction::Reverse) {
+        let (_, value) = entry?;
+
+        let ValueFormat::V2(ValueV2::HistoryDiff { inserted, removed }) =
+            ValueFormat::<V>::from_bytes(&value)?
+        else {
+            return Err(Error::DatabaseConsistency);
+        };
+
+        for element in inserted {
+            states.insert(element, None);
+        }
+
+        for (element, value) in removed {
+            states.insert(element, Some(Arc::unwrap_or_clone(value)));
+        }
+    }
+
+    let mut tree = tree.clone_without_cache();
+
+    // Removals and insertions can share a slot, so they are applied as separate batches
+    let mut removals = Batch::new();
+    let mut insertions = Batch::new();
+
+    for (element, state) in states {
+        // elements that were re-inserted later may have a different value now, so they are
+        // replaced rather than kept
+        if tree.contains_element(&element) {
+            removals.remove(element)?;
+        }
+
+        if let Some(value) = state {
+            insertions.insert(element, value)?;
+        }
+    }
+
+    tree.insert_batch(removals, |_| {}, |_| {})?;
+    tree.insert_batch(insertions, |_| {}, |_| {})?;
+
+    Ok(tree)
+}
+
+/// The tree and history of a [`Persistent`] at some point in time, see
+/// [`Persistent::history_snapshot`]
+///
+/// Reconstructing an older version is linear in the size of the tree, so this lets it happen
+/// without blocking writes to the [`Persistent`]
+pub struct HistorySnapshot<const DEPTH: usize, V, C = SimpleHashCache> {
+    tree: Tree<DEPTH, V, C>,
+    db: Arc<dyn KvStore>,
+    history: Option<HistoryRange>,
+}
+
+impl<const DEPTH: usize, V, C> HistorySnapshot<DEPTH, V, C> {
+    /// The versions that [`HistorySnapshot::tree_at`] can reconstruct, if any
+    #[must_use]
+    pub fn history(&self) -> Option<RangeInclusive<u64>> {
+        self.history.map(|range| range.start..=range.latest)
+    }
+
+    /// Reconstruct the tree as it was at `version`, like [`Persistent::tree_at`]
+    ///
+    /// Fails with [`Error::VersionUnavailable`] if `version` is outside
+    /// [`HistorySnapshot::history`], or if the [`Persistent`] pruned it while it was being
+    /// reconstructed
+    pub fn tree_at(&self, version: u64) -> Result<Tree<DEPTH, V>, Error>
+    where
+        V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+    {
+        let tree = reconstruct(&self.tree, &*self.db, self.history, version)?;
+
+        // Diffs are only pruned once they are older than the retained history, so if `version`
+        // is still retained, every diff that was read is still valid
+        let retained = load_range::<V>(&*self.db)?
+            .is_some_and(|range| range.start <= version && range.latest >= version);
+        if !retained {
+            return Err(Error::VersionUnavailable(version));
+        }
+
+        Ok(tree)
+    }
+}
+
+/// Add deletes for all history stored in `db` to `write_batch`
+pub(super) fn clear(db: &dyn KvStore, write_batch: &mut WriteBatch) -> Result<(), Error> {
+    for entry in db.iter(diff_keys(0, None)?, Direction::Forward) {
+        let (key, _) = entry?;
+        write_batch.delete(key);
+    }
+
+    write_batch.delete(range_key()?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch;
+
+    use super::*;
+
+    fn remove(batch: &mut Batch<64, i32>, elements: &[u64]) {
+        for element in elements {
+            batch.remove(Element::new(*element)).unwrap();
+        }
+    }
+
+    #[test]
+    fn reconstructs_every_retained_version() {
+        let mut persistent = Persistent::<64, i32>::new_in_memory();
+        persistent.enable_history(10).unwrap();
+
+        let mut roots = vec![persistent.tree().root_hash()];
+
+        persistent
+            .insert_batch_at(batch! { 1 => 1, 2 => 2, 3 => 3 }, 1)
+            .unwrap();
+        roots.push(persistent.tree().root_hash());
+
+        let mut batch = batch! { 4 => 4 };
+        remove(&mut batch, &[1, 2]);
+        persistent.insert_batch_at(batch, 2).unwrap();
+        roots.push(persistent.tree().root_hash());
+
+        // an empty version
+        persistent.insert_batch_at(Batch::new(), 3).unwrap();
+        roots.push(persistent.tree().root_hash());
+
+        // re-insert a removed element with a different value
+        let mut batch = batch! { 1 => 10 };
+        remove(&mut batch, &[4]);
+        persistent.insert_batch_at(batch, 4).unwrap();
+        roots.push(persistent.tree().root_hash());
+
+        assert_eq!(persistent.history(), Some(0..=4));
+
+        for (version, root) in roots.iter().enumerate() {
+            let tree = persistent.tree_at(version as u64).unwrap();
+            assert_eq!(tree.root_hash(), *root, "version {version}");
+        }
+
+        assert_eq!(
+            persistent.tree_at(1).unwrap().get(Element::new(1)),
+            Some(&1)
+        );
+        assert_eq!(
+            persistent.tree_at(4).unwrap().get(Element::new(1)),
+            Some(&10)
+        );
+    }
+
+    #[test]
+    fn prunes_old_versions() {
+        let mut persistent = Persistent::<64, i32>::new_in_memory();
+        persistent.enable_history(2).unwrap();
+
+        for version in 1..=5 {
+            persistent
+                .insert_batch_at(batch! { version => 0 }, version)
+                .unwrap();
+        }
+
+        assert_eq!(persistent.history(), Some(3..=5));
+        assert!(matches!(
+            persistent.tree_at(2),
+            Err(Error::VersionUnavailable(2))
+        ));
+
+        let tree = persistent.tree_at(3).unwrap();
+        assert_eq!(tree.len(), 3);
+
+        let diffs = persistent
+            .db()
+            .iter(diff_keys(0, None).unwrap(), Direction::Forward)
+            .count();
+        assert_eq!(diffs, 2);
+    }
+
+    #[test]
+    fn unversioned_insert_clears_history() {
+        let mut persistent = Persistent::<64, i32>::new_in_memory();
+        persistent.enable_history(10).unwrap();
+
+        persistent.insert_batch_at(batch! { 1 => 1 }, 1).unwrap();
+        persistent.insert_batch(batch! { 2 => 2 }).unwrap();
+
+        assert_eq!(persistent.history(), None);
+        assert!(persistent.tree_at(1).is_err());
+
+        persistent.insert_batch_at(batch! { 3 => 3 }, 5).unwrap();
+        assert_eq!(persistent.history(), Some(4..=5));
+    }
+
+    #[test]
+    fn history_survives_reload() {
+        let dir = tempdir::TempDir::new("smirk_history").unwrap();
+        let path = dir.path().join("db");
+
+        let mut persistent = Persistent::<64, i32>::new(&path).unwrap();
+        persistent.enable_history(10).unwrap();
+        persistent.insert_batch_at(batch! { 1 => 1 }, 1).unwrap();
+        let root_at_1 = persistent.tree().root_hash();
+        persistent.insert_batch_at(batch! { 2 => 2 }, 2).unwrap();
+        drop(persistent);
+
+        let persistent = Persistent::<64, i32>::load(&path).unwrap();
+        assert_eq!(persistent.history(), Some(0..=2));
+        assert_eq!(persistent.tree_at(1).unwrap().root_hash(), root_at_1);
+    }
+
+    #[test]
+    fn retention_survives_reload() {
+        let mut persistent = Persistent::<64, i32>::new_in_memory();
+        persistent.enable_history(2).unwrap();
+        persistent.insert_batch_at(batch! { 1 => 1 }, 1).unwrap();
+
+        let (_, db) = persistent.into_parts();
+        let mut persistent = Persistent::<64, i32>::load_from_store(db).unwrap();
+        for version in 2..=5 {
+            persistent
+                .insert_batch_at(batch! { version => 0 }, version)
+                .unwrap();
+        }
+        assert_eq!(persistent.history(), Some(3..=5));
+
+        persistent.clear_history().unwrap();
+        let (_, db) = persistent.into_parts();
+        let mut persistent = Persistent::<64, i32>::load_from_store(db).unwrap();
+        persistent.insert_batch_at(batch! { 6 => 0 }, 6).unwrap();
+        assert_eq!(persistent.history(), None);
+    }
+
+    #[test]
+    fn snapshots_reject_versions_pruned_after_they_were_taken() {
+        let mut persistent = Persistent::<64, i32>::new_in_memory();
+        persistent.enable_history(2).unwrap();
+        persistent.insert_batch_at(batch! { 1 => 1 }, 1).unwrap();
+        persistent.insert_batch_at(batch! { 2 => 2 }, 2).unwrap();
+        let root_at_1 = persistent.tree_at(1).unwrap().root_hash();
+
+        let snapshot = persistent.history_snapshot();
+        assert_eq!(snapshot.tree_at(1).unwrap().root_hash(), root_at_1);
+
+        // version 3 is written after the snapshot, and isn't reverted by it
+        persistent.insert_batch_at(batch! { 3 => 3 }, 3).unwrap();
+        assert_eq!(snapshot.tree_at(1).unwrap().root_hash(), root_at_1);
+
+        persistent.insert_batch_at(batch! { 4 => 4 }, 4).unwrap();
+        assert!(matches!(
+            snapshot.tree_at(1),
+            Err(Error::VersionUnavailable(1))
+        ));
+    }
+}
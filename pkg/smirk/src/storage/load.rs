@@ -3,12 +3,12 @@ use std::sync::Arc;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use element::Element;
-use rocksdb::{DB, IteratorMode};
+use kv_store::{Direction, KeyRange, KvStore};
 use wire_message::WireMessage;
 
 use crate::{
     Batch, Tree,
-    hash_cache::{KnownHash, SimpleHashCache},
+    hash_cache::{KnownHash, KnownHashCache},
     storage::format::{KeyV2, ValueFormat},
 };
 
@@ -17,11 +17,13 @@ use super::{
     format::{KeyFormat, ValueV2},
 };
 
-pub(super) fn load_tree<const DEPTH: usize, V>(
-    db: &DB,
-) -> Result<Tree<DEPTH, V, SimpleHashCache>, Error>
+pub(super) fn load_tree<const DEPTH: usize, V, C>(
+    db: &dyn KvStore,
+    mut cache: C,
+) -> Result<Tree<DEPTH, V, C>, Error>
 where
     V: BorshDeserialize + BorshSerialize + Debug + Clone + Send + Sync + 'static,
+    C: KnownHashCache,
 {
     let mut known_hashes = Vec::new();
     let mut smirk_kv = Vec::new();
@@ -30,15 +32,14 @@ where
         match entry {
             Ok(RocksbEntry::KnownHash(hash)) => known_hashes.push(hash),
             Ok(RocksbEntry::SmirkKV { key, value }) => smirk_kv.push((key, value)),
+            Ok(RocksbEntry::History | RocksbEntry::DiskTree) => {}
             Err(err) => return Err(err),
         }
     }
 
-    let mut cache = SimpleHashCache::new();
-
     cache.provide_known_hashes(known_hashes);
 
-    let mut smirk = Tree::<DEPTH, V, SimpleHashCache>::new_with_cache(cache);
+    let mut smirk = Tree::<DEPTH, V, C>::new_with_cache(cache);
 
     let mut batch = Batch::new();
     for (key, value) in smirk_kv {
@@ -50,11 +51,13 @@ where
     Ok(smirk)
 }
 
-pub(crate) fn entries<V>(db: &DB) -> impl Iterator<Item = Result<RocksbEntry<V>, Error>> + '_
+pub(crate) fn entries<V>(
+    db: &dyn KvStore,
+) -> impl Iterator<Item = Result<RocksbEntry<V>, Error>> + '_
 where
     V: Debug + Clone + Sync + Send + 'static + BorshSerialize + BorshDeserialize,
 {
-    db.iterator(IteratorMode::Start)
+    db.iter(KeyRange::all(), Direction::Forward)
         .filter_map(Result::ok)
         .map(|(key, value)| {
             let key_format = KeyFormat::from_bytes(&key)?;
@@ -81,16 +84,39 @@ where
                     right,
                     result,
                 })),
+                // history is only read when reconstructing older versions of the tree
+                (
+                    KeyFormat::V2(KeyV2::HistoryDiff { .. }),
+                    ValueFormat::V2(ValueV2::HistoryDiff { .. }),
+                )
+                | (
+                    KeyFormat::V2(KeyV2::HistoryRange),
+                    ValueFormat::V2(ValueV2::HistoryRange { .. }),
+                )
+                | (
+                    KeyFormat::V2(KeyV2::HistoryRetention),
+                    ValueFormat::V2(ValueV2::HistoryRetention(_)),
+                ) => Ok(RocksbEntry::History),
+                // node hashes are only read by a `DiskTree`
+                (KeyFormat::V2(KeyV2::Node { .. }), ValueFormat::V2(ValueV2::Node(_)))
+                | (
+                    KeyFormat::V2(KeyV2::DiskTreeMeta),
+                    ValueFormat::V2(ValueV2::DiskTreeMeta { .. }),
+                ) => Ok(RocksbEntry::DiskTree),
                 // Any other case shouldn't be possible
                 _ => Err(Error::DatabaseConsistency),
             }
         })
 }
 
-/// Possible meanings of a key-value pair in rocksdb
+/// Possible meanings of a key-value pair in the backing store
 pub(crate) enum RocksbEntry<V> {
     /// A smirk key-value pair (i.e. an element and its metadata)
     SmirkKV { key: Element, value: V },
     /// A precomputed hash merge
     KnownHash(KnownHash),
+    /// A diff or range used to reconstruct older versions of the tree
+    History,
+    /// A node hash or metadata stored by a [`DiskTree`](super::DiskTree)
+    DiskTree,
 }
@@ -1,21 +1,29 @@
-use crate::{Tree, batch as batch_macro, hash_cache::SimpleHashCache};
+use crate::{
+    Tree, batch as batch_macro,
+    hash_cache::{HashCache, KnownHashCache, SimpleHashCache},
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 use core::fmt::Debug;
+pub use disk::DiskTree;
 use element::Element;
 pub use error::Error;
-use rocksdb::DB;
-use std::path::Path;
+pub use history::HistorySnapshot;
+use kv_store::KvStore;
+use std::{path::Path, sync::Arc};
 
 mod batch;
+mod disk;
 mod error;
+mod export;
 mod format;
+mod history;
 mod load;
 mod store;
 
 #[cfg(test)]
 mod tests;
 
-/// A wrapper around [`Tree`] that persists data to a rocksdb instance
+/// A wrapper around [`Tree`] that persists data to a [`KvStore`], usually a rocksdb instance
 ///
 /// ```rust
 /// # use smirk::*;
@@ -24,12 +32,38 @@ mod tests;
 /// # let dir = tempdir::TempDir::new("smirk_doctest").unwrap();
 /// # let path = dir.path().join("db");
 /// ```
-pub struct Persistent<const DEPTH: usize, V> {
-    tree: Tree<DEPTH, V, SimpleHashCache>,
-    db: DB,
+///
+/// Hashes are cached with a [`SimpleHashCache`] by default. Other caches, such as a bounded
+/// [`LruHashCache`], can be used with the `*_with_cache` constructors
+///
+/// ```rust
+/// # use smirk::*;
+/// # use element::Element;
+/// # use smirk::storage::*;
+/// # use smirk::hash_cache::*;
+/// # use std::num::NonZeroUsize;
+/// let cache = LruHashCache::new(NonZeroUsize::new(1024).unwrap());
+/// let mut persistent = Persistent::<64, i32, _>::new_in_memory_with_cache(cache);
+///
+/// persistent.insert(Element::ONE, 123).unwrap();
+/// assert!(persistent.tree().cache().len() <= 1024);
+/// ```
+///
+/// [`LruHashCache`]: crate::hash_cache::LruHashCache
+pub struct Persistent<const DEPTH: usize, V, C = SimpleHashCache> {
+    tree: Tree<DEPTH, V, C>,
+    /// Shared with [`HistorySnapshot`]s
+    db: Arc<dyn KvStore>,
+    /// The versions that can be reconstructed from stored diffs, if any
+    history: Option<history::HistoryRange>,
+    /// How many versions of history to keep, if recording history is enabled
+    history_retention: Option<u64>,
+    /// Whether the store was last written by a [`DiskTree`], whose node hashes the next write
+    /// makes stale
+    disk_tree_meta: bool,
 }
 
-impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
+impl<const DEPTH: usize, V, C: KnownHashCache + Default> Persistent<DEPTH, V, C> {
     /// Create a new, empty [`Persistent`] [`Tree`] backed by a rocksdb instance at `path`
     ///
     /// ```rust
@@ -44,10 +78,32 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
     /// println!("{}", persistent.tree().root_hash());
     /// ```
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let db = DB::open_default(path)?;
-        let tree = Tree::new();
+        Self::new_with_cache(path, C::default())
+    }
+
+    /// Create a new, empty [`Persistent`] [`Tree`] that is only held in memory
+    ///
+    /// Useful for tests and simulations that shouldn't touch disk. Everything is lost when this
+    /// is dropped, and [`Persistent::checkpoint`] is not supported
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// let mut persistent = Persistent::<64, i32>::new_in_memory();
+    ///
+    /// persistent.insert(Element::ONE, 123).unwrap();
+    /// assert_eq!(persistent.tree().get(Element::ONE), Some(&123));
+    /// ```
+    #[must_use]
+    pub fn new_in_memory() -> Self {
+        Self::new_in_memory_with_cache(C::default())
+    }
 
-        Ok(Self { tree, db })
+    /// Create a new, empty [`Persistent`] [`Tree`] backed by an empty `db`
+    #[must_use]
+    pub fn new_with_store(db: Box<dyn KvStore>) -> Self {
+        Self::new_with_store_and_cache(db, C::default())
     }
 
     /// Load a [`Persistent`] [`Tree`] from a rocksdb database located at `path`
@@ -71,12 +127,79 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
     where
         V: BorshDeserialize + BorshSerialize + Debug + Clone + Send + Sync + 'static,
     {
-        let db = DB::open_default(path)?;
-        let tree = load::load_tree(&db)?;
+        Self::load_with_cache(path, C::default())
+    }
+
+    /// Load a [`Persistent`] [`Tree`] from the contents of `db`
+    pub fn load_from_store(db: Box<dyn KvStore>) -> Result<Self, Error>
+    where
+        V: BorshDeserialize + BorshSerialize + Debug + Clone + Send + Sync + 'static,
+    {
+        Self::load_from_store_and_cache(db, C::default())
+    }
+}
+
+impl<const DEPTH: usize, V, C: KnownHashCache> Persistent<DEPTH, V, C> {
+    /// Like [`Persistent::new`], but caching hashes in `cache`
+    pub fn new_with_cache<P: AsRef<Path>>(path: P, cache: C) -> Result<Self, Error> {
+        let db = kv_store::RocksDb::open_default(path.as_ref())?;
 
-        Ok(Self { tree, db })
+        Ok(Self::new_with_store_and_cache(Box::new(db), cache))
     }
 
+    /// Like [`Persistent::new_in_memory`], but caching hashes in `cache`
+    #[must_use]
+    pub fn new_in_memory_with_cache(cache: C) -> Self {
+        Self::new_with_store_and_cache(Box::new(kv_store::Memory::new()), cache)
+    }
+
+    /// Like [`Persistent::new_with_store`], but caching hashes in `cache`
+    #[must_use]
+    pub fn new_with_store_and_cache(db: Box<dyn KvStore>, cache: C) -> Self {
+        let tree = Tree::new_with_cache(cache);
+
+        Self {
+            tree,
+            db: Arc::from(db),
+            history: None,
+            history_retention: None,
+            disk_tree_meta: false,
+        }
+    }
+
+    /// Like [`Persistent::load`], but caching hashes in `cache`, which is given the hashes stored
+    /// in the database
+    pub fn load_with_cache<P: AsRef<Path>>(path: P, cache: C) -> Result<Self, Error>
+    where
+        V: BorshDeserialize + BorshSerialize + Debug + Clone + Send + Sync + 'static,
+    {
+        let db = kv_store::RocksDb::open_default(path.as_ref())?;
+
+        Self::load_from_store_and_cache(Box::new(db), cache)
+    }
+
+    /// Like [`Persistent::load_from_store`], but caching hashes in `cache`, which is given the
+    /// hashes stored in `db`
+    pub fn load_from_store_and_cache(db: Box<dyn KvStore>, cache: C) -> Result<Self, Error>
+    where
+        V: BorshDeserialize + BorshSerialize + Debug + Clone + Send + Sync + 'static,
+    {
+        let tree = load::load_tree(&*db, cache)?;
+        let history = history::load_range::<V>(&*db)?;
+        let history_retention = history::load_retention::<V>(&*db)?;
+        let disk_tree_meta = disk::has_meta(&*db)?;
+
+        Ok(Self {
+            tree,
+            db: Arc::from(db),
+            history,
+            history_retention,
+            disk_tree_meta,
+        })
+    }
+}
+
+impl<const DEPTH: usize, V, C> Persistent<DEPTH, V, C> {
     /// Get a reference to the wrapped tree
     ///
     /// ```rust
@@ -93,26 +216,84 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
     /// ```
     #[inline]
     #[must_use]
-    pub fn tree(&self) -> &Tree<DEPTH, V, SimpleHashCache> {
+    pub fn tree(&self) -> &Tree<DEPTH, V, C> {
         &self.tree
     }
 
-    /// Get a reference to the rocksdb instance
+    /// Take an immutable snapshot of the wrapped tree
+    ///
+    /// The snapshot shares its nodes and values with this tree, so taking one is cheap, and later
+    /// inserts only copy the parts of the tree they modify. Readers can work on a snapshot without
+    /// holding a lock on this [`Persistent`], and never see a partially applied batch
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// let mut persistent = Persistent::<64, i32>::new_in_memory();
+    /// persistent.insert(Element::ONE, 123).unwrap();
+    ///
+    /// let snapshot = persistent.snapshot();
+    /// persistent.insert(Element::new(2), 456).unwrap();
+    ///
+    /// assert_eq!(snapshot.get(Element::ONE), Some(&123));
+    /// assert_eq!(snapshot.get(Element::new(2)), None);
+    /// assert_ne!(snapshot.root_hash(), persistent.tree().root_hash());
+    /// ```
     #[inline]
     #[must_use]
-    pub fn db(&self) -> &DB {
-        &self.db
+    pub fn snapshot(&self) -> Tree<DEPTH, V, C>
+    where
+        V: Clone,
+        C: Clone,
+    {
+        self.tree.clone()
+    }
+
+    /// Get a reference to the backing store
+    #[inline]
+    #[must_use]
+    pub fn db(&self) -> &dyn KvStore {
+        &*self.db
+    }
+
+    /// Create a point-in-time copy of the backing rocksdb instance at `path`
+    ///
+    /// `path` must not already exist, and the tree must not be held in memory. The copy can be
+    /// opened with [`Persistent::load`]. Make sure the hashes you want included have been
+    /// persisted with [`Persistent::persist_hashes`]
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// # use smirk::storage::*;
+    /// # let dir = tempdir::TempDir::new("smirk_doctest").unwrap();
+    /// # let path = dir.path().join("db");
+    /// # let checkpoint_path = dir.path().join("checkpoint");
+    /// let mut persistent = Persistent::<64, i32>::new(&path).unwrap();
+    /// persistent.insert(Element::ONE, 123).unwrap();
+    ///
+    /// persistent.checkpoint(&checkpoint_path).unwrap();
+    /// persistent.insert(Element::new(2), 456).unwrap();
+    ///
+    /// let copy = Persistent::<64, i32>::load(&checkpoint_path).unwrap();
+    /// assert_eq!(copy.tree().get(Element::ONE), Some(&123));
+    /// assert_eq!(copy.tree().get(Element::new(2)), None);
+    /// ```
+    pub fn checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.db.checkpoint(path.as_ref())?;
+        Ok(())
     }
 
-    /// Split this instance into the [`Tree`] and [`DB`] that make up this [`Persistent`]
+    /// Split this instance into the [`Tree`] and [`KvStore`] that make up this [`Persistent`]
     ///
     /// Since [`Persistent`] doesn't provide any way to get a `&mut Tree`, this is the only way to
     /// get mutable access to the inner tree
     #[inline]
     #[must_use]
-    pub fn into_parts(self) -> (Tree<DEPTH, V, SimpleHashCache>, DB) {
-        let Self { tree, db } = self;
-        (tree, db)
+    pub fn into_parts(self) -> (Tree<DEPTH, V, C>, Box<dyn KvStore>) {
+        let Self { tree, db, .. } = self;
+        (tree, Box::new(db))
     }
 
     /// Insert an element into the in-memory tree, and persist the element to the backing rocksdb
@@ -141,6 +322,7 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
     pub fn insert(&mut self, element: Element, value: V) -> Result<(), Error>
     where
         V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+        C: HashCache,
     {
         self.insert_batch(batch_macro! { element => value })
     }
@@ -153,6 +335,6 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
     where
         V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
     {
-        store::synchronize_hashes(&self.db, &self.tree)
+        store::synchronize_hashes(&*self.db, &self.tree)
     }
 }
@@ -1,19 +1,16 @@
 use std::collections::HashSet;
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use rocksdb::{DB, IteratorMode, WriteBatch};
+use kv_store::{Direction, KeyRange, KvStore, WriteBatch};
 use wire_message::WireMessage;
 
-use crate::{
-    Tree,
-    hash_cache::{KnownHash, SimpleHashCache},
-};
+use crate::{Tree, hash_cache::KnownHash};
 
 use super::format::{KeyFormat, KeyV2, ValueFormat, ValueV2};
 
-pub(super) fn synchronize_hashes<const DEPTH: usize, V>(
-    db: &DB,
-    tree: &Tree<DEPTH, V, SimpleHashCache>,
+pub(super) fn synchronize_hashes<const DEPTH: usize, V, C>(
+    db: &dyn KvStore,
+    tree: &Tree<DEPTH, V, C>,
 ) -> Result<(), super::Error>
 where
     V: Clone + Send + Sync + 'static + BorshDeserialize + BorshSerialize,
@@ -23,7 +20,7 @@ where
     let in_memory_hashes = tree.known_hashes();
 
     let in_db_hashes = db
-        .iterator(IteratorMode::Start)
+        .iter(KeyRange::all(), Direction::Forward)
         .filter_map(|result| {
             let (key, value) = result.ok()?;
 
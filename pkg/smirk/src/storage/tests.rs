@@ -52,6 +52,23 @@ fn simple_storage_test() {
     assert!(persistent.tree().get(Element::ONE) == Some(&1));
 }
 
+#[test]
+fn in_memory_storage_test() {
+    let mut persistent = Persistent::<64, i32>::new_in_memory();
+
+    persistent
+        .insert_batch(batch! { 1 => 10, 2 => 20 })
+        .unwrap();
+    persistent.persist_hashes().unwrap();
+
+    // reload the tree from the same in-memory store
+    let (tree, db) = persistent.into_parts();
+    let loaded = Persistent::<64, i32>::load_from_store(db).unwrap();
+
+    assert_eq!(loaded.tree().root_hash(), tree.root_hash());
+    assert_eq!(loaded.tree().get(Element::new(2)), Some(&20));
+}
+
 #[test]
 fn persist_hashes_works() {
     let (_dir, path) = setup_path();
@@ -70,6 +87,27 @@ fn persist_hashes_works() {
     assert!(persistent.tree().cache().metrics().hashes() > 0);
 }
 
+#[test]
+fn lru_cache_is_seeded_with_persisted_hashes() {
+    use crate::hash_cache::LruHashCache;
+    use std::num::NonZeroUsize;
+
+    let cache = || LruHashCache::new(NonZeroUsize::new(1024).unwrap());
+
+    let mut persistent = Persistent::<64, i32, _>::new_in_memory_with_cache(cache());
+    persistent
+        .insert_batch(batch! { 1 => 10, 2 => 20, 3 => 30 })
+        .unwrap();
+    persistent.persist_hashes().unwrap();
+
+    let (tree, db) = persistent.into_parts();
+    let loaded = Persistent::<64, i32, _>::load_from_store_and_cache(db, cache()).unwrap();
+
+    assert_eq!(loaded.tree().root_hash(), tree.root_hash());
+    assert!(!loaded.tree().cache().is_empty());
+    assert!(loaded.tree().cache().len() <= loaded.tree().cache().capacity());
+}
+
 #[proptest(cases = cases())]
 fn insert_batch_works(batch_1: Batch<64, i32>, mut batch_2: Batch<64, i32>) {
     let (_dir1, path) = setup_path();
@@ -113,7 +151,7 @@ macro_rules! expect_storage_known_hashes {
             .iter()
             .filter_map(|entry| match entry {
                 RocksbEntry::KnownHash(hash) => Some(*hash),
-                RocksbEntry::SmirkKV { .. } => None,
+                RocksbEntry::SmirkKV { .. } | RocksbEntry::History => None,
             })
             .collect::<Vec<_>>();
 
@@ -2,9 +2,9 @@ use std::collections::HashSet;
 
 use element::Element;
 
-use crate::{Batch, Collision, CollisionError, Tree, hash_cache::HashCache};
+use crate::{Batch, Collision, CollisionError, Tree, hash_cache::HashCache, hasher::TreeHasher};
 
-impl<const DEPTH: usize, V, C: HashCache> Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C: HashCache<H>, H: TreeHasher> Tree<DEPTH, V, C, H> {
     /// Check whether this batch contains any [`Element`]s which would collide with an [`Element`]
     /// that is already in the tree
     ///
@@ -120,8 +120,11 @@ impl<const DEPTH: usize, V, C: HashCache> Tree<DEPTH, V, C> {
             .unwrap();
 
         tracing::info_span!("recalculate_hashes").in_scope(|| {
-            self.tree
-                .recalculate_hashes(&self.cache, &hash_remove_callback, &hash_set_callback);
+            self.tree.recalculate_hashes::<H, C>(
+                &self.cache,
+                &hash_remove_callback,
+                &hash_set_callback,
+            );
         });
 
         Ok(())
@@ -0,0 +1,572 @@
+use std::collections::BTreeMap;
+
+use element::Element;
+
+use crate::{Path, empty_tree_hash};
+
+/// An error decoding a [`CompactPath`] or [`MultiPath`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CompactPathError {
+    /// The bitmap doesn't have one bit per sibling
+    #[error("bitmap has {actual} bytes, expected {expected}")]
+    BitmapLength {
+        /// The number of bytes needed for one bit per sibling
+        expected: usize,
+        /// The number of bytes in the bitmap
+        actual: usize,
+    },
+
+    /// The number of values doesn't match the number of bits set in the bitmap
+    #[error("expected {expected} non-default siblings, found {actual}")]
+    SiblingCount {
+        /// The number of bits set in the bitmap
+        expected: usize,
+        /// The number of siblings provided
+        actual: usize,
+    },
+
+    /// The number of leaves doesn't match the number of elements in a [`MultiPath`]
+    #[error("expected {expected} leaves, found {actual}")]
+    LeafCount {
+        /// The number of elements in the [`MultiPath`]
+        expected: usize,
+        /// The number of leaves provided
+        actual: usize,
+    },
+
+    /// Two elements share a slot, but were given different leaves
+    #[error("elements sharing a slot were given different leaves")]
+    ConflictingLeaves,
+
+    /// The paths combined into a [`MultiPath`] don't come from the same tree
+    #[error("paths have different depths or root hashes")]
+    MismatchedPaths,
+}
+
+/// A [`Path`] that omits siblings which are the hash of an empty subtree
+///
+/// In a sparse tree, most siblings of a path are empty subtrees, whose hash only depends on their
+/// depth (see [`empty_tree_hash`]). A [`CompactPath`] stores a bitmap with a bit set for each
+/// sibling that isn't the empty subtree hash, and only the values of those siblings. The empty
+/// subtree hashes are those of the default [`Poseidon2`] hasher, so paths from trees with another
+/// [`TreeHasher`] can't be compacted.
+///
+/// ```rust
+/// # use smirk::*;
+/// # use element::Element;
+/// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+/// let path = tree.path_for(Element::new(1));
+///
+/// let compact = CompactPath::from(&path);
+/// assert!(compact.siblings.len() < path.siblings_deepest_first().len());
+///
+/// let decoded = compact.into_path(tree.root_hash()).unwrap();
+/// assert_eq!(decoded.siblings_deepest_first(), path.siblings_deepest_first());
+/// assert!(decoded.proves(Element::new(1)));
+/// ```
+///
+/// [`Poseidon2`]: crate::hasher::Poseidon2
+/// [`TreeHasher`]: crate::hasher::TreeHasher
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactPath {
+    /// The element that this path leads to
+    pub element: Element,
+
+    /// The total number of siblings, including empty subtrees
+    pub len: usize,
+
+    /// One bit per sibling, deepest first, set if the sibling is in [`Self::siblings`]
+    ///
+    /// Bits are packed least significant bit first, so sibling `i` is bit `i % 8` of byte `i / 8`
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub bitmap: Vec<u8>,
+
+    /// The siblings that aren't empty subtrees, deepest first
+    pub siblings: Vec<Element>,
+}
+
+impl From<&Path> for CompactPath {
+    fn from(path: &Path) -> Self {
+        let (bitmap, siblings) = encode(path.siblings_deepest_first().iter().copied(), |i| i);
+
+        Self {
+            element: path.element(),
+            len: path.siblings_deepest_first().len(),
+            bitmap,
+            siblings,
+        }
+    }
+}
+
+impl CompactPath {
+    /// Expand the siblings, filling in the empty subtree hashes
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+    /// let path = tree.path_for(Element::new(2));
+    ///
+    /// let compact = CompactPath::from(&path);
+    /// assert_eq!(compact.siblings_deepest_first().unwrap(), path.siblings_deepest_first());
+    /// ```
+    pub fn siblings_deepest_first(&self) -> Result<Vec<Element>, CompactPathError> {
+        decode(self.len, &self.bitmap, &self.siblings, |i| i)
+    }
+
+    /// Decode this into a [`Path`] for a tree with root hash `root_hash`
+    ///
+    /// The root hash isn't part of the compact encoding, since it is shared by every path from
+    /// the same tree
+    pub fn into_path(self, root_hash: Element) -> Result<Path, CompactPathError> {
+        Ok(Path {
+            siblings: self.siblings_deepest_first()?,
+            element: self.element,
+            root_hash,
+        })
+    }
+}
+
+/// A proof for several elements of a tree that includes each sibling once
+///
+/// Paths for nearby elements share siblings, and a sibling that is an ancestor of another
+/// element can be computed from that element's path. A [`MultiPath`] only stores the siblings
+/// that can't be computed, and, like [`CompactPath`], omits empty subtree hashes.
+///
+/// Siblings are ordered by level, deepest first, and by slot within each level.
+///
+/// ```rust
+/// # use smirk::*;
+/// # use element::Element;
+/// let tree: Tree<64, _> = smirk! { 1, 2, 3, 4 };
+/// let elements = [Element::new(1), Element::new(2), Element::new(3)];
+/// let paths: Vec<_> = elements.iter().map(|e| tree.path_for(*e)).collect();
+///
+/// let multi = MultiPath::from_paths(&paths).unwrap();
+///
+/// // the elements are in the tree, so each slot contains the element itself
+/// assert_eq!(multi.compute_root_hash(&elements).unwrap(), tree.root_hash());
+///
+/// // the individual paths can be recovered
+/// let decoded = multi.paths(&elements).unwrap();
+/// assert_eq!(decoded[1].siblings_deepest_first(), paths[1].siblings_deepest_first());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiPath {
+    /// The elements this proof is for
+    pub elements: Vec<Element>,
+
+    /// The number of siblings in each element's path
+    pub len: usize,
+
+    /// One bit per sibling that can't be computed, set if the sibling is in [`Self::siblings`]
+    ///
+    /// Packed the same way as [`CompactPath::bitmap`]
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub bitmap: Vec<u8>,
+
+    /// The siblings that can't be computed and aren't empty subtrees
+    pub siblings: Vec<Element>,
+}
+
+/// The slot of a node at some level, as the left/right choices from the root
+type Position = Vec<bool>;
+
+impl MultiPath {
+    /// Combine the paths of several elements from the same tree
+    ///
+    /// Elements that share a slot share a leaf, so [`MultiPath::compute_root_hash`] requires
+    /// them to be given the same leaf
+    pub fn from_paths(paths: &[Path]) -> Result<Self, CompactPathError> {
+        let Some(first) = paths.first() else {
+            return Ok(Self {
+                elements: Vec::new(),
+                len: 0,
+                bitmap: Vec::new(),
+                siblings: Vec::new(),
+            });
+        };
+
+        let len = first.siblings_deepest_first().len();
+        let same_tree = paths.iter().all(|path| {
+            path.siblings_deepest_first().len() == len
+                && path.actual_root_hash() == first.actual_root_hash()
+        });
+        if !same_tree {
+            return Err(CompactPathError::MismatchedPaths);
+        }
+
+        // any path through a node can provide that node's sibling
+        let mut nodes: BTreeMap<Position, &Path> = paths
+            .iter()
+            .map(|path| (position(path.element(), len), path))
+            .collect();
+
+        let mut needed = Vec::new();
+
+        for level in 0..len {
+            let mut parents = BTreeMap::new();
+
+            for (position, path) in &nodes {
+                let (parent, sibling) = parent_and_sibling(position);
+
+                if parents.contains_key(&parent) {
+                    continue;
+                }
+
+                if !nodes.contains_key(&sibling) {
+                    needed.push((level, path.siblings_deepest_first()[level]));
+                }
+
+                parents.insert(parent, *path);
+            }
+
+            nodes = parents;
+        }
+
+        let (bitmap, siblings) =
+            encode(needed.iter().map(|(_, sibling)| *sibling), |i| needed[i].0);
+
+        Ok(Self {
+            elements: paths.iter().map(Path::element).collect(),
+            len,
+            bitmap,
+            siblings,
+        })
+    }
+
+    /// Compute the root hash of the tree, given the leaf in each element's slot
+    ///
+    /// The leaf is the element itself if it is in the tree, or [`Element::NULL_HASH`] if its slot
+    /// is empty
+    pub fn compute_root_hash(&self, leaves: &[Element]) -> Result<Element, CompactPathError> {
+        let levels = self.levels(leaves)?;
+
+        Ok(levels
+            .last()
+            .and_then(|root| root.values().next().copied())
+            .unwrap_or_else(|| leaves.first().copied().unwrap_or(Element::NULL_HASH)))
+    }
+
+    /// Decode this into a [`Path`] for each element, given the leaf in each element's slot
+    ///
+    /// The root hash of each path is the root hash computed from `leaves`
+    pub fn paths(&self, leaves: &[Element]) -> Result<Vec<Path>, CompactPathError> {
+        let levels = self.levels(leaves)?;
+        let root_hash = self.compute_root_hash(leaves)?;
+
+        let paths = self
+            .elements
+            .iter()
+            .map(|element| {
+                let mut position = position(*element, self.len);
+
+                let siblings = (0..self.len)
+                    .map(|level| {
+                        let (parent, sibling) = parent_and_sibling(&position);
+                        position = parent;
+                        levels[level][&sibling]
+                    })
+                    .collect();
+
+                Path {
+                    siblings,
+                    element: *element,
+                    root_hash,
+                }
+            })
+            .collect();
+
+        Ok(paths)
+    }
+
+    /// The hash of every node needed to compute the root, by level, deepest first
+    ///
+    /// Each level contains the nodes on the elements' paths and their siblings, and the last
+    /// level contains the root
+    fn levels(
+        &self,
+        leaves: &[Element],
+    ) -> Result<Vec<BTreeMap<Position, Element>>, CompactPathError> {
+        if leaves.len() != self.elements.len() {
+            return Err(CompactPathError::LeafCount {
+                expected: self.elements.len(),
+                actual: leaves.len(),
+            });
+        }
+
+        let mut nodes = BTreeMap::new();
+        for (element, leaf) in self.elements.iter().zip(leaves) {
+            let existing = nodes.insert(position(*element, self.len), *leaf);
+            if existing.is_some_and(|existing| existing != *leaf) {
+                return Err(CompactPathError::ConflictingLeaves);
+            }
+        }
+
+        let bits = self.bitmap.len() * 8;
+        let mut provided = self.siblings.iter();
+        let mut sibling_index = 0;
+        let mut next_sibling = |level: usize| -> Result<Element, CompactPathError> {
+            let index = sibling_index;
+            sibling_index += 1;
+
+            if index >= bits {
+                return Err(CompactPathError::BitmapLength {
+                    expected: bytes_for(index + 1),
+                    actual: self.bitmap.len(),
+                });
+            }
+
+            match bit(&self.bitmap, index) {
+                true => provided
+                    .next()
+                    .copied()
+                    .ok_or(CompactPathError::SiblingCount {
+                        expected: count_ones(&self.bitmap),
+                        actual: self.siblings.len(),
+                    }),
+                false => Ok(empty_tree_hash(level + 1)),
+            }
+        };
+
+        let mut levels = Vec::with_capacity(self.len + 1);
+
+        for level in 0..self.len {
+            let mut parents = BTreeMap::new();
+            let mut with_siblings = nodes.clone();
+
+            for (position, hash) in &nodes {
+                let (parent, sibling) = parent_and_sibling(position);
+
+                if parents.contains_key(&parent) {
+                    continue;
+                }
+
+                let sibling_hash = match nodes.get(&sibling) {
+                    Some(hash) => *hash,
+                    None => {
+                        let hash = next_sibling(level)?;
+                        with_siblings.insert(sibling, hash);
+                        hash
+                    }
+                };
+
+                let is_right = *position.last().unwrap();
+                let parent_hash = match is_right {
+                    false => hash::hash_merge([*hash, sibling_hash]),
+                    true => hash::hash_merge([sibling_hash, *hash]),
+                };
+
+                parents.insert(parent, parent_hash);
+            }
+
+            levels.push(with_siblings);
+            nodes = parents;
+        }
+
+        if self.bitmap.len() != bytes_for(sibling_index) {
+            return Err(CompactPathError::BitmapLength {
+                expected: bytes_for(sibling_index),
+                actual: self.bitmap.len(),
+            });
+        }
+
+        let unused = provided.count();
+        if unused != 0 {
+            return Err(CompactPathError::SiblingCount {
+                expected: self.siblings.len() - unused,
+                actual: self.siblings.len(),
+            });
+        }
+
+        levels.push(nodes);
+
+        Ok(levels)
+    }
+}
+
+/// The slot of `element` in a tree with `len` levels below the root
+fn position(element: Element, len: usize) -> Position {
+    element.lsb(len).iter().map(|bit| *bit).collect()
+}
+
+fn parent_and_sibling(position: &Position) -> (Position, Position) {
+    let (last, parent) = position.split_last().unwrap();
+
+    let mut sibling = parent.to_vec();
+    sibling.push(!last);
+
+    (parent.to_vec(), sibling)
+}
+
+fn bytes_for(bits: usize) -> usize {
+    bits.div_ceil(8)
+}
+
+fn bit(bitmap: &[u8], index: usize) -> bool {
+    bitmap[index / 8] & (1 << (index % 8)) != 0
+}
+
+fn count_ones(bitmap: &[u8]) -> usize {
+    bitmap.iter().map(|byte| byte.count_ones() as usize).sum()
+}
+
+/// Encode `siblings` as a bitmap and the non-default values, where `level_of(i)` is the level of
+/// the `i`th sibling
+fn encode(
+    siblings: impl ExactSizeIterator<Item = Element>,
+    level_of: impl Fn(usize) -> usize,
+) -> (Vec<u8>, Vec<Element>) {
+    let mut bitmap = vec![0; bytes_for(siblings.len())];
+    let mut values = Vec::new();
+
+    for (i, sibling) in siblings.enumerate() {
+        if sibling != empty_tree_hash(level_of(i) + 1) {
+            bitmap[i / 8] |= 1 << (i % 8);
+            values.push(sibling);
+        }
+    }
+
+    (bitmap, values)
+}
+
+/// The inverse of [`encode`], for `len` siblings
+fn decode(
+    len: usize,
+    bitmap: &[u8],
+    values: &[Element],
+    level_of: impl Fn(usize) -> usize,
+) -> Result<Vec<Element>, CompactPathError> {
+    if bitmap.len() != bytes_for(len) {
+        return Err(CompactPathError::BitmapLength {
+            expected: bytes_for(len),
+            actual: bitmap.len(),
+        });
+    }
+
+    let expected = (0..len).filter(|i| bit(bitmap, *i)).count();
+    if expected != values.len() {
+        return Err(CompactPathError::SiblingCount {
+            expected,
+            actual: values.len(),
+        });
+    }
+
+    let mut values = values.iter();
+
+    Ok((0..len)
+        .map(|i| match bit(bitmap, i) {
+            true => *values.next().unwrap(),
+            false => empty_tree_hash(level_of(i) + 1),
+        })
+        .collect())
+}
+
+#[cfg(feature = "serde")]
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        serializer.serialize_str(&format!("0x{hex}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let hex = s.strip_prefix("0x").unwrap_or(&s);
+
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("hex string has an odd number of digits"));
+        }
+
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use crate::Tree;
+
+    use super::*;
+
+    #[proptest]
+    fn compact_path_round_trips(tree: Tree<64, i32>, element: Element) {
+        let path = tree.path_for(element);
+        let decoded = CompactPath::from(&path)
+            .into_path(tree.root_hash())
+            .unwrap();
+
+        assert_eq!(
+            decoded.siblings_deepest_first(),
+            path.siblings_deepest_first()
+        );
+    }
+
+    #[proptest]
+    fn multi_path_matches_individual_paths(tree: Tree<64, i32>, extra: Element) {
+        let mut elements: Vec<_> = tree.elements().map(|(e, _)| *e).take(5).collect();
+        if !tree.contains_element(&extra) && tree.non_membership_proof(extra).is_some() {
+            elements.push(extra);
+        }
+
+        let leaves: Vec<_> = elements
+            .iter()
+            .map(|e| match tree.contains_element(e) {
+                true => *e,
+                false => tree.non_membership_proof(*e).unwrap().occupant,
+            })
+            .collect();
+
+        let paths: Vec<_> = elements.iter().map(|e| tree.path_for(*e)).collect();
+        let multi = MultiPath::from_paths(&paths).unwrap();
+
+        if !elements.is_empty() {
+            assert_eq!(multi.compute_root_hash(&leaves).unwrap(), tree.root_hash());
+        }
+
+        for (decoded, path) in multi.paths(&leaves).unwrap().iter().zip(&paths) {
+            assert_eq!(
+                decoded.siblings_deepest_first(),
+                path.siblings_deepest_first()
+            );
+        }
+    }
+
+    #[test]
+    fn multi_path_shares_siblings() {
+        let tree: Tree<64, ()> = crate::smirk! { 1, 2, 3, 4, 5, 6 };
+        let elements: Vec<_> = (1..=6).map(Element::new).collect();
+        let paths: Vec<_> = elements.iter().map(|e| tree.path_for(*e)).collect();
+
+        let multi = MultiPath::from_paths(&paths).unwrap();
+        let compact: usize = paths
+            .iter()
+            .map(|path| CompactPath::from(path).siblings.len())
+            .sum();
+
+        assert!(multi.siblings.len() < compact);
+        assert_eq!(
+            multi.compute_root_hash(&elements).unwrap(),
+            tree.root_hash()
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_sibling_count() {
+        let tree: Tree<64, ()> = crate::smirk! { 1, 2 };
+        let mut compact = CompactPath::from(&tree.path_for(Element::new(1)));
+        compact.siblings.push(Element::new(5));
+
+        assert!(matches!(
+            compact.siblings_deepest_first(),
+            Err(CompactPathError::SiblingCount { .. })
+        ));
+    }
+}
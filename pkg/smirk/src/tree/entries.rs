@@ -0,0 +1,276 @@
+use std::{
+    collections::{BTreeMap, btree_map},
+    sync::{Arc, OnceLock},
+};
+
+use element::Element;
+
+/// The number of shards [`Entries`] splits its elements between
+const SHARDS: usize = 256;
+
+/// The values of a [`Tree`], split into shards by element so that clones share every shard that
+/// neither of them has modified since
+///
+/// Each shard covers a contiguous range of elements, so iterating the shards in order yields
+/// elements in ascending order, like a single [`BTreeMap`]
+///
+/// Shards can only be shared by cloning, which requires `V: Clone`, so cloning records how to copy
+/// values, and copying a shared shard on write doesn't need a `V: Clone` bound of its own
+///
+/// [`Tree`]: crate::Tree
+#[derive(Debug)]
+pub(crate) struct Entries<V> {
+    shards: Vec<Arc<BTreeMap<Element, V>>>,
+    len: usize,
+    clone_value: Arc<OnceLock<fn(&V) -> V>>,
+}
+
+impl<V: Clone> Clone for Entries<V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.clone_value.get_or_init(|| V::clone);
+
+        Self {
+            shards: self.shards.clone(),
+            len: self.len,
+            clone_value: Arc::clone(&self.clone_value),
+        }
+    }
+}
+
+impl<V> Default for Entries<V> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Entries<V> {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Arc::new(BTreeMap::new())).collect(),
+            len: 0,
+            clone_value: Arc::new(OnceLock::new()),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn get(&self, element: &Element) -> Option<&V> {
+        self.shards[shard(element)].get(element)
+    }
+
+    #[inline]
+    pub fn contains_key(&self, element: &Element) -> bool {
+        self.shards[shard(element)].contains_key(element)
+    }
+
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            shards: self.shards.iter(),
+            current: None,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Element> {
+        self.iter().map(|(element, _)| element)
+    }
+
+    /// Insert a value, copying its shard first if it is shared with another [`Entries`]
+    pub fn insert(&mut self, element: Element, value: V) -> Option<V> {
+        let old = self.shard_mut(&element).insert(element, value);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Remove a value, copying its shard first if it is shared with another [`Entries`]
+    pub fn remove(&mut self, element: &Element) -> Option<V> {
+        // avoid copying the shard if there is nothing to remove
+        if !self.contains_key(element) {
+            return None;
+        }
+
+        let old = self.shard_mut(element).remove(element);
+        if old.is_some() {
+            self.len -= 1;
+        }
+        old
+    }
+
+    /// The shard an element belongs in, copied first if it is shared with another [`Entries`]
+    fn shard_mut(&mut self, element: &Element) -> &mut BTreeMap<Element, V> {
+        let shard = &mut self.shards[shard(element)];
+
+        if Arc::get_mut(shard).is_none() {
+            *shard = Arc::new(copy_shard(shard, &self.clone_value));
+        }
+
+        Arc::get_mut(shard).expect("the shard was just copied, so it isn't shared")
+    }
+}
+
+/// Copy a shard that is shared with another [`Entries`]
+fn copy_shard<V>(
+    shard: &BTreeMap<Element, V>,
+    clone_value: &OnceLock<fn(&V) -> V>,
+) -> BTreeMap<Element, V> {
+    let clone_value = clone_value
+        .get()
+        .expect("shards are only shared by cloning, which records how to copy values");
+
+    shard
+        .iter()
+        .map(|(element, value)| (*element, clone_value(value)))
+        .collect()
+}
+
+/// An owning iterator over the entries of every shard, in ascending order
+#[derive(Debug)]
+pub(crate) struct IntoIter<V> {
+    shards: std::vec::IntoIter<Arc<BTreeMap<Element, V>>>,
+    current: Option<btree_map::IntoIter<Element, V>>,
+    clone_value: Arc<OnceLock<fn(&V) -> V>>,
+}
+
+impl<V> Iterator for IntoIter<V> {
+    type Item = (Element, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(entry);
+            }
+
+            // only shards shared with another tree need to be copied
+            let shard = Arc::try_unwrap(self.shards.next()?)
+                .unwrap_or_else(|shard| copy_shard(&shard, &self.clone_value));
+            self.current = Some(shard.into_iter());
+        }
+    }
+}
+
+impl<V> IntoIterator for Entries<V> {
+    type Item = (Element, V);
+    type IntoIter = IntoIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            shards: self.shards.into_iter(),
+            current: None,
+            clone_value: self.clone_value,
+        }
+    }
+}
+
+/// An iterator over the entries of every shard, in ascending order
+#[derive(Debug, Clone)]
+pub(crate) struct Iter<'a, V> {
+    shards: std::slice::Iter<'a, Arc<BTreeMap<Element, V>>>,
+    current: Option<btree_map::Iter<'a, Element, V>>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a Element, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(entry);
+            }
+
+            self.current = Some(self.shards.next()?.iter());
+        }
+    }
+}
+
+/// The shard an element belongs in
+///
+/// Shards split the range of field elements evenly by their most significant 64 bits, so shard
+/// order matches element order. Elements above the modulus all go in the last shard
+fn shard(element: &Element) -> usize {
+    let top = |e: Element| {
+        let bytes = e.to_be_bytes();
+        u128::from(u64::from_be_bytes(bytes[..8].try_into().unwrap()))
+    };
+
+    let shard = top(*element) * SHARDS as u128 / (top(Element::MODULUS) + 1);
+    usize::try_from(shard).map_or(SHARDS - 1, |shard| shard.min(SHARDS - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use super::*;
+
+    #[proptest]
+    fn iterates_in_ascending_order(elements: Vec<Element>) {
+        let mut entries = Entries::new();
+        let mut expected = BTreeMap::new();
+
+        for (i, element) in elements.into_iter().enumerate() {
+            assert_eq!(entries.insert(element, i), expected.insert(element, i));
+        }
+
+        assert_eq!(entries.len(), expected.len());
+        assert!(entries.iter().eq(expected.iter()));
+        assert!(entries.into_iter().eq(expected));
+    }
+
+    #[test]
+    fn clones_share_unmodified_shards() {
+        let mut entries = Entries::new();
+        entries.insert(Element::new(1), 1);
+        entries.insert(Element::MODULUS - Element::ONE, 2);
+
+        let snapshot = entries.clone();
+        entries.insert(Element::new(2), 3);
+        entries.remove(&Element::new(1));
+
+        assert_eq!(snapshot.get(&Element::new(1)), Some(&1));
+        assert_eq!(snapshot.get(&Element::new(2)), None);
+        assert_eq!(entries.get(&Element::new(1)), None);
+        assert_eq!(entries.get(&Element::new(2)), Some(&3));
+
+        // the last shard wasn't touched, so it is still shared
+        assert!(Arc::ptr_eq(
+            &entries.shards[SHARDS - 1],
+            &snapshot.shards[SHARDS - 1]
+        ));
+        assert!(!Arc::ptr_eq(&entries.shards[0], &snapshot.shards[0]));
+    }
+
+    #[test]
+    fn values_need_not_be_clone() {
+        #[derive(Debug, PartialEq)]
+        struct NotClone(u64);
+
+        let mut entries = Entries::new();
+        entries.insert(Element::new(1), NotClone(1));
+        entries.insert(Element::new(2), NotClone(2));
+        assert_eq!(entries.remove(&Element::new(1)), Some(NotClone(1)));
+
+        assert!(entries.into_iter().eq([(Element::new(2), NotClone(2))]));
+    }
+
+    #[test]
+    fn consuming_a_clone_leaves_the_other_intact() {
+        let mut entries = Entries::new();
+        entries.insert(Element::new(1), 1);
+
+        let snapshot = entries.clone();
+        assert!(entries.into_iter().eq([(Element::new(1), 1)]));
+        assert!(snapshot.into_iter().eq([(Element::new(1), 1)]));
+    }
+}
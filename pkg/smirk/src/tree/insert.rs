@@ -1,7 +1,7 @@
-use crate::{CollisionError, Path, Tree, batch, hash_cache::HashCache};
+use crate::{CollisionError, Path, Tree, batch, hash_cache::HashCache, hasher::TreeHasher};
 use element::Element;
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C, H: TreeHasher> Tree<DEPTH, V, C, H> {
     /// Insert a non-null element and a value into the tree
     ///
     /// Returns whether the value was newly inserted. That is:
@@ -53,7 +53,7 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
     /// ```
     pub fn insert(&mut self, element: Element, value: V) -> Result<(), CollisionError>
     where
-        C: HashCache,
+        C: HashCache<H>,
     {
         self.insert_batch(batch! { element => value }, |_| {}, |_| {})
     }
@@ -94,7 +94,7 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
         entries: I,
     ) -> Result<Vec<Path>, CollisionError>
     where
-        C: HashCache,
+        C: HashCache<H>,
     {
         let elements = entries.into_iter();
         let ((_, Some(hint)) | (hint, None)) = elements.size_hint();
@@ -120,7 +120,7 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
     where
         I: IntoIterator<Item = Element>,
         V: Default,
-        C: HashCache,
+        C: HashCache<H>,
     {
         self.insert_with_paths(elements.into_iter().map(|e| (e, V::default())))
     }
@@ -1,10 +1,10 @@
+use super::entries;
 use crate::Tree;
 use element::Element;
-use std::collections::btree_map;
 
 #[derive(Debug, Clone)]
 pub struct Elements<'a, V> {
-    inner: btree_map::Iter<'a, Element, V>,
+    inner: entries::Iter<'a, V>,
 }
 
 impl<'a, V> Iterator for Elements<'a, V> {
@@ -16,7 +16,7 @@ impl<'a, V> Iterator for Elements<'a, V> {
     }
 }
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C, H> Tree<DEPTH, V, C, H> {
     /// Get an iterator over the elements in this set
     ///
     /// Elements are yielded in ascending order. Note that this is not necessarily the same order
@@ -47,7 +47,7 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
 
 #[derive(Debug)]
 pub struct IntoIter<V> {
-    inner: btree_map::IntoIter<Element, V>,
+    inner: entries::IntoIter<V>,
 }
 
 impl<V> Iterator for IntoIter<V> {
@@ -58,7 +58,8 @@ impl<V> Iterator for IntoIter<V> {
     }
 }
 
-impl<const N: usize, V, C> IntoIterator for Tree<N, V, C> {
+/// Values shared with clones of the tree are cloned, the rest are moved
+impl<const N: usize, V, C, H> IntoIterator for Tree<N, V, C, H> {
     type Item = (Element, V);
     type IntoIter = IntoIter<V>;
 
@@ -71,7 +72,7 @@ impl<const N: usize, V, C> IntoIterator for Tree<N, V, C> {
 /// An [`Iterator`] over [`Element`]s and values
 #[derive(Debug, Clone)]
 pub struct Iter<'a, V> {
-    inner: btree_map::Iter<'a, Element, V>,
+    inner: entries::Iter<'a, V>,
 }
 
 impl<'a, V> Iterator for Iter<'a, V> {
@@ -1,21 +1,21 @@
-use crate::{Tree, hash_cache::KnownHash};
+use crate::{Tree, hash_cache::KnownHash, hasher::TreeHasher};
 
 use super::tree_repr::Node;
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C, H: TreeHasher> Tree<DEPTH, V, C, H> {
     pub(crate) fn known_hashes(&self) -> Vec<KnownHash> {
-        self.tree.known_hashes(DEPTH * self.len())
+        self.tree.known_hashes::<H>(DEPTH * self.len())
     }
 }
 
 impl Node {
-    pub(crate) fn known_hashes(&self, cap: usize) -> Vec<KnownHash> {
+    pub(crate) fn known_hashes<H: TreeHasher>(&self, cap: usize) -> Vec<KnownHash> {
         let mut hashes = Vec::with_capacity(cap);
-        self.known_hashes_inner(&mut hashes);
+        self.known_hashes_inner::<H>(&mut hashes);
         hashes
     }
 
-    fn known_hashes_inner(&self, hashes: &mut Vec<KnownHash>) {
+    fn known_hashes_inner<H: TreeHasher>(&self, hashes: &mut Vec<KnownHash>) {
         match self {
             Node::Leaf(_) | Node::Empty { .. } => {}
             Node::Parent {
@@ -27,14 +27,14 @@ impl Node {
                 assert!(!hash_dirty, "hash should never be dirty in normal use");
 
                 let known_hash = KnownHash {
-                    left: left.hash(),
-                    right: right.hash(),
+                    left: left.hash::<H>(),
+                    right: right.hash::<H>(),
                     result: *hash,
                 };
 
                 hashes.push(known_hash);
-                left.known_hashes_inner(hashes);
-                right.known_hashes_inner(hashes);
+                left.known_hashes_inner::<H>(hashes);
+                right.known_hashes_inner::<H>(hashes);
             }
         }
     }
@@ -1,19 +1,30 @@
-use crate::hash_cache::{HashCache, NoopHashCache};
+use std::marker::PhantomData;
+
+use crate::{
+    hash_cache::{HashCache, NoopHashCache},
+    hasher::{Poseidon2, TreeHasher},
+};
 use element::Element;
-use std::collections::BTreeMap;
 
 mod batch;
+mod compact;
+mod entries;
 mod error;
 mod insert;
 mod iter;
 mod known_hashes;
+mod non_membership;
 mod path;
 mod raw_api;
+mod reconcile;
 mod remove;
 mod tree_repr;
 
+pub use compact::{CompactPath, CompactPathError, MultiPath};
 pub use error::{Collision, CollisionError};
+pub use non_membership::NonMembershipProof;
 pub use path::Path;
+pub use reconcile::{SubtreeDiff, SubtreeId, SubtreeSummary};
 
 pub(crate) use error::StructName;
 
@@ -39,25 +50,29 @@ pub mod proptest;
 ///     println!("the tree contains {value} at element {element}");
 /// }
 /// ```
+///
+/// Parent hashes are computed with [`Poseidon2`] by default. Another [`TreeHasher`] can be given
+/// as the last type parameter, e.g. to rebuild trees created with a legacy hash
 #[derive(Debug, Clone)]
-pub struct Tree<const DEPTH: usize, V, C = NoopHashCache> {
+pub struct Tree<const DEPTH: usize, V, C = NoopHashCache, H = Poseidon2> {
     /// The tree-like representation
     #[expect(clippy::struct_field_names)]
     tree: tree_repr::Node,
-    entries: BTreeMap<Element, V>,
+    entries: entries::Entries<V>,
     cache: C,
+    hasher: PhantomData<H>,
 }
 
-impl<const DEPTH: usize, V, C> PartialEq for Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C, H: TreeHasher> PartialEq for Tree<DEPTH, V, C, H> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         self.root_hash() == other.root_hash()
     }
 }
 
-impl<const DEPTH: usize, V, C> Eq for Tree<DEPTH, V, C> {}
+impl<const DEPTH: usize, V, C, H: TreeHasher> Eq for Tree<DEPTH, V, C, H> {}
 
-impl<const DEPTH: usize, V, C> Default for Tree<DEPTH, V, C>
+impl<const DEPTH: usize, V, C, H> Default for Tree<DEPTH, V, C, H>
 where
     C: Default,
 {
@@ -67,7 +82,7 @@ where
     }
 }
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C, H> Tree<DEPTH, V, C, H> {
     /// Creates a new, empty tree
     ///
     /// ```rust
@@ -83,9 +98,10 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
         C: Default,
     {
         Self {
-            entries: BTreeMap::new(),
+            entries: entries::Entries::new(),
             tree: tree_repr::Node::Empty { depth: DEPTH },
             cache: C::default(),
+            hasher: PhantomData,
         }
     }
 
@@ -101,9 +117,10 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
     #[must_use]
     pub fn new_with_cache(cache: C) -> Self {
         Self {
-            entries: BTreeMap::new(),
+            entries: entries::Entries::new(),
             tree: tree_repr::Node::Empty { depth: DEPTH },
             cache,
+            hasher: PhantomData,
         }
     }
 
@@ -113,7 +130,7 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
     /// # use smirk::*;
     /// # use element::Element;
     /// # use smirk::hash_cache::*;
-    /// let tree = Tree::<64, i32, NoopHashCache>::new();
+    /// let tree = Tree::<64, i32, SimpleHashCache>::new();
     /// let cache = tree.cache();
     /// let hash = cache.hash(Element::new(1), Element::new(2));
     /// println!("{hash}");
@@ -124,6 +141,22 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
         &self.cache
     }
 
+    /// Clone the elements and structure of this tree, but not its cache
+    ///
+    /// Like [`Clone::clone`], this is cheap, since the clone shares its nodes and values with
+    /// this tree until either of them is modified
+    pub(crate) fn clone_without_cache(&self) -> Tree<DEPTH, V, NoopHashCache, H>
+    where
+        V: Clone,
+    {
+        Tree {
+            tree: self.tree.clone(),
+            entries: self.entries.clone(),
+            cache: NoopHashCache,
+            hasher: PhantomData,
+        }
+    }
+
     /// The number of elements stored in this tree
     ///
     /// ```rust
@@ -206,14 +239,18 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
     /// This value is cached internally, so calls to this function are essentially free
     #[inline]
     #[must_use]
-    pub fn root_hash(&self) -> Element {
-        self.tree.hash()
+    pub fn root_hash(&self) -> Element
+    where
+        H: TreeHasher,
+    {
+        self.tree.hash::<H>()
     }
 }
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C>
+impl<const DEPTH: usize, V, C, H> Tree<DEPTH, V, C, H>
 where
-    C: HashCache,
+    C: HashCache<H>,
+    H: TreeHasher,
 {
     /// Compute what the root hash would be if all of `extra_elements` were inserted
     ///
@@ -236,6 +273,6 @@ where
         remove_elements: &[Element],
     ) -> Element {
         self.tree
-            .hash_with::<DEPTH, C>(self.cache(), insert_elements, remove_elements)
+            .hash_with::<DEPTH, H, C>(self.cache(), insert_elements, remove_elements)
     }
 }
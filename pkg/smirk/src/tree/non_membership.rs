@@ -0,0 +1,213 @@
+use crate::{Tree, hasher::TreeHasher};
+use element::Element;
+
+use super::tree_repr::Node;
+
+/// A proof that an [`Element`] is *not* in a [`Tree`] with a known root hash
+///
+/// Every element has a slot in the tree, determined by its `DEPTH - 1` least significant bits.
+/// The proof contains the merkle path to that slot, and the `occupant` of the slot, which is
+/// either [`Element::NULL_HASH`] (the slot is empty), or a different element that shares the
+/// least significant bits (a collision). In both cases, the element itself cannot be in the tree.
+///
+/// ```rust
+/// # use smirk::*;
+/// # use element::Element;
+/// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+///
+/// let proof = tree.non_membership_proof(Element::new(4)).unwrap();
+/// assert_eq!(proof.occupant, Element::NULL_HASH);
+/// assert!(proof.verify::<64>(tree.root_hash()));
+///
+/// // elements that are in the tree don't have a non-membership proof
+/// assert!(tree.non_membership_proof(Element::new(1)).is_none());
+///
+/// // an element with the same least significant bits as 1 collides with it
+/// let collides_with_1 = Element::ONE + (Element::ONE << 100);
+/// let proof = tree.non_membership_proof(collides_with_1).unwrap();
+/// assert_eq!(proof.occupant, Element::ONE);
+/// assert!(proof.verify::<64>(tree.root_hash()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NonMembershipProof {
+    /// The element that this proof shows is absent
+    pub element: Element,
+
+    /// The element occupying `element`'s slot, or [`Element::NULL_HASH`] if the slot is empty
+    pub occupant: Element,
+
+    /// The siblings of the slot with the deepest siblings first, as in
+    /// [`Path::siblings_deepest_first`](crate::Path::siblings_deepest_first)
+    pub siblings: Vec<Element>,
+}
+
+impl NonMembershipProof {
+    /// Check that this proof shows [`Self::element`] is not in a tree of depth `DEPTH` with root
+    /// hash `root_hash`
+    ///
+    /// This holds if the proof has `DEPTH - 1` siblings, the slot of the element hashes to
+    /// `root_hash` with [`Self::occupant`] in it, and the occupant is not the element itself.
+    /// [`Element::NULL_HASH`] can never be in a tree, so any valid path to its slot proves its
+    /// absence
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+    /// let mut proof = tree.non_membership_proof(Element::new(4)).unwrap();
+    ///
+    /// assert!(proof.verify::<64>(tree.root_hash()));
+    ///
+    /// // the proof doesn't hold for a different root
+    /// let other_tree: Tree<64, _> = smirk! { 1, 2, 3, 4 };
+    /// assert!(!proof.verify::<64>(other_tree.root_hash()));
+    ///
+    /// // or for a tree of a different depth
+    /// assert!(!proof.verify::<32>(tree.root_hash()));
+    ///
+    /// // or if the occupant is tampered with
+    /// proof.occupant = Element::new(5);
+    /// assert!(!proof.verify::<64>(tree.root_hash()));
+    /// ```
+    #[must_use]
+    pub fn verify<const DEPTH: usize>(&self, root_hash: Element) -> bool {
+        // a shorter path would prove the absence of the element from a subtree, whose hash can be
+        // passed off as a sibling
+        if self.siblings.len() != DEPTH - 1 {
+            return false;
+        }
+
+        let is_member = self.occupant == self.element && self.element != Element::NULL_HASH;
+        if is_member {
+            return false;
+        }
+
+        hash::compute_merkle_root(self.occupant, self.element, &self.siblings) == root_hash
+    }
+}
+
+impl<const DEPTH: usize, V, C, H: TreeHasher> Tree<DEPTH, V, C, H> {
+    /// Generate a [`NonMembershipProof`] for `element`, or `None` if `element` is in the tree
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+    ///
+    /// let proof = tree.non_membership_proof(Element::new(4)).unwrap();
+    /// assert!(proof.verify::<64>(tree.root_hash()));
+    ///
+    /// assert!(tree.non_membership_proof(Element::new(1)).is_none());
+    /// ```
+    #[must_use]
+    pub fn non_membership_proof(&self, element: Element) -> Option<NonMembershipProof> {
+        if self.contains_element(&element) {
+            return None;
+        }
+
+        let path = self.path_for(element);
+
+        Some(NonMembershipProof {
+            element,
+            occupant: self.slot_occupant(element),
+            siblings: path.siblings,
+        })
+    }
+
+    /// The element in the slot that `element` would occupy, or [`Element::NULL_HASH`] if that
+    /// slot is empty
+    fn slot_occupant(&self, element: Element) -> Element {
+        let mut node = &self.tree;
+
+        for bit in element.lsb(DEPTH - 1).iter() {
+            match node {
+                Node::Parent { left, right, .. } => match *bit {
+                    false => node = left,
+                    true => node = right,
+                },
+                Node::Empty { .. } => return Element::NULL_HASH,
+                Node::Leaf(_) => unreachable!("leaves only exist at the bottom of the tree"),
+            }
+        }
+
+        match node {
+            Node::Leaf(occupant) => *occupant,
+            Node::Empty { .. } => Element::NULL_HASH,
+            Node::Parent { .. } => unreachable!("parents don't exist at the bottom of the tree"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use super::*;
+
+    #[proptest]
+    fn non_membership_proof_verifies(tree: Tree<64, i32>, element: Element) {
+        match tree.non_membership_proof(element) {
+            Some(proof) => {
+                assert!(!tree.contains_element(&element));
+                assert!(proof.verify::<64>(tree.root_hash()));
+            }
+            None => assert!(tree.contains_element(&element)),
+        }
+    }
+
+    #[proptest]
+    fn membership_path_is_not_a_non_membership_proof(tree: Tree<64, i32>) {
+        for (element, _) in tree.elements() {
+            let proof = NonMembershipProof {
+                element: *element,
+                occupant: *element,
+                siblings: tree.path_for(*element).siblings,
+            };
+
+            assert!(!proof.verify::<64>(tree.root_hash()));
+        }
+    }
+
+    #[test]
+    fn collision_occupant() {
+        let mut tree = Tree::<16, ()>::new();
+        tree.insert(Element::new(1), ()).unwrap();
+
+        let collides = Element::new(1) + (Element::new(1) << 100);
+        let proof = tree.non_membership_proof(collides).unwrap();
+
+        assert_eq!(proof.occupant, Element::new(1));
+        assert!(proof.verify::<16>(tree.root_hash()));
+
+        // claiming the slot is empty doesn't verify
+        let forged = NonMembershipProof {
+            occupant: Element::NULL_HASH,
+            ..proof
+        };
+        assert!(!forged.verify::<16>(tree.root_hash()));
+    }
+
+    #[test]
+    fn short_proof_is_rejected() {
+        let mut tree = Tree::<16, ()>::new();
+        for i in 1..=3 {
+            tree.insert(Element::new(i), ()).unwrap();
+        }
+
+        // 2 and 3 are siblings at the bottom of the tree, so their parent is one level up from the
+        // slots, where the path of 1 would end if it had one sibling fewer
+        let siblings = tree.path_for(Element::new(2)).siblings;
+        let forged = NonMembershipProof {
+            element: Element::new(1),
+            occupant: hash::hash_merge([Element::new(2), Element::new(3)]),
+            siblings: siblings[1..].to_vec(),
+        };
+
+        assert_eq!(
+            hash::compute_merkle_root(forged.occupant, forged.element, &forged.siblings),
+            tree.root_hash(),
+        );
+        assert!(!forged.verify::<16>(tree.root_hash()));
+    }
+}
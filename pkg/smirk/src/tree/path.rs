@@ -1,4 +1,4 @@
-use crate::Tree;
+use crate::{Tree, hasher::TreeHasher};
 use element::{Element, Lsb};
 
 use super::tree_repr::Node;
@@ -168,6 +168,33 @@ impl Path {
         hash::compute_merkle_root(element, self.element(), siblings)
     }
 
+    /// Compute the root hash of the tree from this path like [`Self::compute_root_hash`], for a
+    /// path from a [`Tree`] which uses the hasher `H`
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use smirk::hasher::*;
+    /// # use element::Element;
+    /// let tree: Tree<64, ()> = smirk! { 1, 2, 3 };
+    /// let path = tree.path_for(Element::new(2));
+    ///
+    /// assert_eq!(
+    ///     path.compute_root_hash_with::<Poseidon2>(Element::new(2)),
+    ///     path.compute_root_hash(Element::new(2)),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn compute_root_hash_with<H: TreeHasher>(&self, element: Element) -> Element {
+        // the bits are most significant first, so the deepest sibling goes with the last bit
+        self.siblings_deepest_first()
+            .iter()
+            .zip(self.lsb().iter().rev())
+            .fold(element, |hash, (sibling, bit)| match *bit {
+                false => H::hash(hash, *sibling),
+                true => H::hash(*sibling, hash),
+            })
+    }
+
     /// The root hash of the tree when this path was created
     ///
     /// ```rust
@@ -185,7 +212,7 @@ impl Path {
     }
 }
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C, H: TreeHasher> Tree<DEPTH, V, C, H> {
     /// Generate a [`Path`] that proves the presence/absence of a particular value at a location in
     /// the tree
     ///
@@ -223,13 +250,13 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
                 Node::Parent { left, right, .. } => match *bit {
                     // the bit is 0, so we follow the left hash, so right is the sibling
                     false => {
-                        siblings[index] = right.hash();
+                        siblings[index] = right.hash::<H>();
                         tree = left;
                     }
 
                     // the bit is 1, so we follow the right hash, so left is the sibling
                     true => {
-                        siblings[index] = left.hash();
+                        siblings[index] = left.hash::<H>();
                         tree = right;
                     }
                 },
@@ -241,7 +268,7 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
                     // we don't want to include `depth` here, because it was included when we
                     // calculated the parent (or root hash if this is the root of the tree)
                     for (i, depth) in (1..*depth).rev().enumerate() {
-                        siblings[index + i] = Node::Empty { depth }.hash();
+                        siblings[index + i] = H::empty_tree_hash(depth);
                     }
 
                     break;
@@ -3,12 +3,14 @@ use ::proptest::{arbitrary::StrategyFor, prelude::*, strategy::Map};
 use crate::{
     Batch, Tree,
     hash_cache::{HashCache, NoopHashCache, SimpleHashCache},
+    hasher::TreeHasher,
 };
 
-impl<const DEPTH: usize, V, C> Arbitrary for Tree<DEPTH, V, C>
+impl<const DEPTH: usize, V, C, H> Arbitrary for Tree<DEPTH, V, C, H>
 where
     V: Arbitrary,
-    C: HashCache + Arbitrary,
+    C: HashCache<H> + Arbitrary,
+    H: TreeHasher + core::fmt::Debug + Clone,
 {
     type Parameters = ();
     type Strategy = Map<StrategyFor<(C, Batch<DEPTH, V>)>, fn((C, Batch<DEPTH, V>)) -> Self>;
@@ -1,10 +1,11 @@
 use super::{error::StructName, tree_repr::Change};
-use crate::{Collision, Tree, hash_cache::HashCache};
+use crate::{Collision, Tree, hash_cache::HashCache, hasher::TreeHasher};
 use element::Element;
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C>
+impl<const DEPTH: usize, V, C, H> Tree<DEPTH, V, C, H>
 where
-    C: HashCache,
+    C: HashCache<H>,
+    H: TreeHasher,
 {
     /// Insert into the tree and btreemap at the same time, without updating the hash
     pub(crate) fn insert_without_hashing(
@@ -71,7 +72,7 @@ where
 
         let result = self
             .tree
-            .insert_without_hashing::<DEPTH>(&elements, &bits, 0)?;
+            .insert_without_hashing::<DEPTH, H>(&elements, &bits, 0)?;
 
         match result {
             true => {
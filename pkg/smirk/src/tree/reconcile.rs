@@ -0,0 +1,382 @@
+use std::collections::BTreeSet;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+
+use crate::{Tree, hasher::TreeHasher};
+
+use super::tree_repr::Node;
+
+/// A subtree of a [`Tree`], identified by the left (`false`) and right (`true`) choices from the
+/// root to the subtree's root
+///
+/// The root of the tree has an empty path, and the slot of an element has a path of the
+/// element's `DEPTH - 1` least significant bits, most significant first
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize, BorshDeserialize,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubtreeId {
+    path: Vec<bool>,
+}
+
+impl SubtreeId {
+    /// The whole tree
+    #[inline]
+    #[must_use]
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// The left (`right = false`) or right (`right = true`) child of this subtree
+    #[inline]
+    #[must_use]
+    pub fn child(&self, right: bool) -> Self {
+        let mut path = self.path.clone();
+        path.push(right);
+        Self { path }
+    }
+
+    /// The choices from the root to this subtree
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &[bool] {
+        &self.path
+    }
+
+    /// The subtree containing this one, or `None` for the root
+    pub(crate) fn parent(&self) -> Option<Self> {
+        let (_, path) = self.path.split_last()?;
+        Some(Self {
+            path: path.to_vec(),
+        })
+    }
+
+    /// The slot of `element` in a tree with depth `DEPTH`
+    pub(crate) fn slot<const DEPTH: usize>(element: Element) -> Self {
+        Self {
+            path: element.lsb(DEPTH - 1).iter().map(|bit| *bit).collect(),
+        }
+    }
+
+    /// Whether `element` belongs in this subtree of a tree with depth `DEPTH`
+    #[must_use]
+    pub fn contains<const DEPTH: usize>(&self, element: Element) -> bool {
+        let lsb = element.lsb(DEPTH - 1);
+        self.path.len() <= DEPTH - 1 && lsb.iter().zip(&self.path).all(|(bit, p)| *bit == *p)
+    }
+}
+
+/// What a peer knows about one of its subtrees, sent so another peer can compare it with its own
+/// tree using [`Tree::diff_subtree`]
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubtreeSummary {
+    /// The hashes of the subtree's children
+    Children {
+        /// The hash of the left child
+        left: Element,
+        /// The hash of the right child
+        right: Element,
+    },
+
+    /// Every element in the subtree, sent instead of hashes once the subtree is small
+    Elements(Vec<Element>),
+}
+
+/// The result of comparing a local subtree with a [`SubtreeSummary`] from a peer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtreeDiff {
+    /// Children with different hashes, whose summaries should be requested next
+    pub mismatched: Vec<SubtreeId>,
+
+    /// Elements the peer has that this tree doesn't
+    pub missing: Vec<Element>,
+
+    /// Elements this tree has that the peer doesn't
+    pub extra: Vec<Element>,
+}
+
+impl<const DEPTH: usize, V, C, H: TreeHasher> Tree<DEPTH, V, C, H> {
+    /// The hash of a subtree, or `None` if `id` is deeper than the slots of this tree
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+    ///
+    /// assert_eq!(tree.subtree_hash(&SubtreeId::root()), Some(tree.root_hash()));
+    /// ```
+    #[must_use]
+    pub fn subtree_hash(&self, id: &SubtreeId) -> Option<Element> {
+        self.subtree(id).map(|subtree| subtree.hash::<H>())
+    }
+
+    /// Summarize a subtree for a peer, or `None` if `id` is deeper than the slots of this tree
+    ///
+    /// Subtrees with at most `max_elements` elements, or at the bottom of the tree, are
+    /// summarized by their elements, otherwise by the hashes of their children
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+    ///
+    /// let summary = tree.subtree_summary(&SubtreeId::root(), 10).unwrap();
+    /// assert_eq!(summary, SubtreeSummary::Elements(vec![
+    ///     Element::new(1),
+    ///     Element::new(2),
+    ///     Element::new(3),
+    /// ]));
+    ///
+    /// let summary = tree.subtree_summary(&SubtreeId::root(), 2).unwrap();
+    /// assert!(matches!(summary, SubtreeSummary::Children { .. }));
+    /// ```
+    #[must_use]
+    pub fn subtree_summary(&self, id: &SubtreeId, max_elements: usize) -> Option<SubtreeSummary> {
+        let subtree = self.subtree(id)?;
+
+        let mut elements = Vec::new();
+        let at_bottom = id.path.len() == DEPTH - 1;
+
+        if at_bottom || subtree.collect_elements(&mut elements, max_elements) {
+            return Some(SubtreeSummary::Elements(elements));
+        }
+
+        let (left, right) = subtree.children_hashes::<H>();
+        Some(SubtreeSummary::Children { left, right })
+    }
+
+    /// Compare a subtree of this tree with the same subtree of a peer's tree
+    ///
+    /// Repeating this for every [`SubtreeDiff::mismatched`] subtree, starting from
+    /// [`SubtreeId::root`], finds every difference between the two trees while only transferring
+    /// the parts that differ
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use element::Element;
+    /// let local: Tree<64, _> = smirk! { 1, 2, 3 };
+    /// let remote: Tree<64, _> = smirk! { 1, 2, 4 };
+    ///
+    /// let root = SubtreeId::root();
+    /// let summary = remote.subtree_summary(&root, 10).unwrap();
+    /// let diff = local.diff_subtree(&root, &summary);
+    ///
+    /// assert_eq!(diff.missing, vec![Element::new(4)]);
+    /// assert_eq!(diff.extra, vec![Element::new(3)]);
+    /// ```
+    #[must_use]
+    pub fn diff_subtree(&self, id: &SubtreeId, remote: &SubtreeSummary) -> SubtreeDiff {
+        let Some(subtree) = self.subtree(id) else {
+            return SubtreeDiff::default();
+        };
+
+        match remote {
+            SubtreeSummary::Children { left, right } => {
+                // the bottom of the tree only has elements
+                if id.path.len() == DEPTH - 1 {
+                    return SubtreeDiff::default();
+                }
+
+                let (local_left, local_right) = subtree.children_hashes::<H>();
+
+                let mismatched = [(false, local_left, left), (true, local_right, right)]
+                    .into_iter()
+                    .filter(|(_, local, remote)| local != *remote)
+                    .map(|(right, _, _)| id.child(right))
+                    .collect();
+
+                SubtreeDiff {
+                    mismatched,
+                    ..SubtreeDiff::default()
+                }
+            }
+            SubtreeSummary::Elements(remote) => {
+                let mut local = Vec::new();
+                subtree.collect_elements(&mut local, usize::MAX);
+
+                let local: BTreeSet<_> = local.into_iter().collect();
+                // ignore anything the peer sent that doesn't belong in this subtree
+                let remote: BTreeSet<_> = remote
+                    .iter()
+                    .copied()
+                    .filter(|e| *e != Element::NULL_HASH && id.contains::<DEPTH>(*e))
+                    .collect();
+
+                SubtreeDiff {
+                    mismatched: Vec::new(),
+                    missing: remote.difference(&local).copied().collect(),
+                    extra: local.difference(&remote).copied().collect(),
+                }
+            }
+        }
+    }
+
+    fn subtree(&self, id: &SubtreeId) -> Option<Subtree<'_>> {
+        if id.path.len() > DEPTH - 1 {
+            return None;
+        }
+
+        let mut node = &self.tree;
+
+        for (level, bit) in id.path.iter().enumerate() {
+            match node {
+                Node::Parent { left, right, .. } => match *bit {
+                    false => node = left,
+                    true => node = right,
+                },
+                // every subtree of an empty subtree is empty
+                Node::Empty { .. } => {
+                    return Some(Subtree::Empty {
+                        depth: DEPTH - id.path.len(),
+                    });
+                }
+                Node::Leaf(_) => {
+                    unreachable!("leaf at level {level}, above the bottom of the tree")
+                }
+            }
+        }
+
+        Some(Subtree::Node(node))
+    }
+}
+
+/// A subtree that may be inside a [`Node::Empty`]
+enum Subtree<'a> {
+    Node(&'a Node),
+    Empty { depth: usize },
+}
+
+impl Subtree<'_> {
+    fn hash<H: TreeHasher>(&self) -> Element {
+        match self {
+            Self::Node(node) => node.hash::<H>(),
+            Self::Empty { depth } => H::empty_tree_hash(*depth),
+        }
+    }
+
+    fn children_hashes<H: TreeHasher>(&self) -> (Element, Element) {
+        match self {
+            Self::Node(Node::Parent { left, right, .. }) => (left.hash::<H>(), right.hash::<H>()),
+            Self::Node(Node::Empty { depth }) | Self::Empty { depth } => {
+                let child = H::empty_tree_hash(depth - 1);
+                (child, child)
+            }
+            Self::Node(Node::Leaf(_)) => unreachable!("leaves don't have children"),
+        }
+    }
+
+    /// Push the elements of this subtree onto `elements`, returning `false` if there are more
+    /// than `max` of them
+    fn collect_elements(&self, elements: &mut Vec<Element>, max: usize) -> bool {
+        match self {
+            Self::Node(node) => collect_elements(node, elements, max),
+            Self::Empty { .. } => true,
+        }
+    }
+}
+
+fn collect_elements(node: &Node, elements: &mut Vec<Element>, max: usize) -> bool {
+    match node {
+        Node::Leaf(element) => {
+            elements.push(*element);
+            elements.len() <= max
+        }
+        Node::Empty { .. } => true,
+        Node::Parent { left, right, .. } => {
+            collect_elements(left, elements, max) && collect_elements(right, elements, max)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use super::*;
+
+    /// Reconcile `local` with `remote` the way two peers would, returning the number of
+    /// summaries exchanged
+    fn reconcile(local: &mut Tree<64, ()>, remote: &Tree<64, ()>, max_elements: usize) -> usize {
+        let mut pending = vec![SubtreeId::root()];
+        let mut missing = Vec::new();
+        let mut extra = Vec::new();
+        let mut exchanged = 0;
+
+        while let Some(id) = pending.pop() {
+            let summary = remote.subtree_summary(&id, max_elements).unwrap();
+            let diff = local.diff_subtree(&id, &summary);
+            exchanged += 1;
+
+            pending.extend(diff.mismatched);
+            missing.extend(diff.missing);
+            extra.extend(diff.extra);
+        }
+
+        let mut batch = crate::Batch::new();
+        for element in missing {
+            batch.insert(element, ()).unwrap();
+        }
+        for element in extra {
+            batch.remove(element).unwrap();
+        }
+        local.insert_batch(batch, |_| {}, |_| {}).unwrap();
+
+        exchanged
+    }
+
+    #[proptest]
+    fn reconciling_makes_trees_equal(mut local: Tree<64, ()>, remote: Tree<64, ()>) {
+        // collisions between the trees can't be reconciled by a single batch
+        let local_slots: BTreeSet<_> = local
+            .elements()
+            .map(|(e, _)| e.lsb(63).to_bitvec())
+            .collect();
+        let collides = remote.elements().any(|(e, _)| {
+            !local.contains_element(e) && local_slots.contains(&e.lsb(63).to_bitvec())
+        });
+        proptest::prop_assume!(!collides);
+
+        reconcile(&mut local, &remote, 4);
+        assert_eq!(local.root_hash(), remote.root_hash());
+    }
+
+    #[test]
+    fn only_differing_subtrees_are_exchanged() {
+        let elements: Vec<_> = (1..=1000).map(Element::new).collect();
+
+        let mut remote = Tree::<64, ()>::new();
+        let mut batch = crate::Batch::new();
+        for element in &elements {
+            batch.insert(*element, ()).unwrap();
+        }
+        remote.insert_batch(batch, |_| {}, |_| {}).unwrap();
+
+        let mut local = remote.clone();
+        local.insert(Element::new(5000), ()).unwrap();
+
+        let exchanged = reconcile(&mut local, &remote, 4);
+
+        assert_eq!(local.root_hash(), remote.root_hash());
+        // two summaries per level on the path to the changed element, not the whole tree
+        assert!(exchanged <= 2 * 64, "exchanged {exchanged} summaries");
+    }
+
+    #[test]
+    fn ids_deeper_than_the_tree_are_rejected() {
+        let tree: Tree<4, ()> = crate::smirk! { 1 };
+        let id = SubtreeId::root()
+            .child(false)
+            .child(false)
+            .child(true)
+            .child(true);
+
+        assert_eq!(tree.subtree_hash(&id), None);
+        assert_eq!(tree.subtree_summary(&id, 1), None);
+        assert_eq!(
+            tree.diff_subtree(&id, &SubtreeSummary::Elements(vec![])),
+            SubtreeDiff::default()
+        );
+    }
+}
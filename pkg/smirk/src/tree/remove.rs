@@ -1,11 +1,11 @@
-use crate::{Batch, CollisionError, Tree, hash_cache::HashCache};
+use crate::{Batch, CollisionError, Tree, hash_cache::HashCache, hasher::TreeHasher};
 use element::Element;
 
-impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
+impl<const DEPTH: usize, V, C, H: TreeHasher> Tree<DEPTH, V, C, H> {
     /// Remove a non-null element from the tree
     pub fn remove(&mut self, element: Element) -> Result<(), CollisionError>
     where
-        C: HashCache,
+        C: HashCache<H>,
     {
         let mut b = Batch::new();
         b.remove(element)?;
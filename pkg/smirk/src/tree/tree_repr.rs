@@ -1,11 +1,16 @@
+use std::sync::Arc;
+
 use bitvec::{prelude::Msb0, vec::BitVec};
 
-use crate::{Collision, hash::empty_tree_hash, hash_cache::HashCache};
+use crate::{Collision, hash_cache::HashCache, hasher::TreeHasher};
 use element::Element;
 
 use super::StructName;
 
 /// A tree-like representation of a sparse tree, for easier computation of merkle paths and hashes
+///
+/// Children are reference counted and copied on write, so cloning a node is cheap and clones
+/// share every subtree that neither of them has modified since
 #[derive(Debug, Clone)]
 pub(crate) enum Node {
     /// A single leaf at the max depth of the tree
@@ -19,8 +24,8 @@ pub(crate) enum Node {
 
     /// A parent of two nodes with a cached hash
     Parent {
-        left: Box<Self>,
-        right: Box<Self>,
+        left: Arc<Self>,
+        right: Arc<Self>,
         hash: Element,
         /// if true, the children have changed without recalculating the hash
         hash_dirty: bool,
@@ -36,7 +41,7 @@ pub enum Change {
 impl Node {
     /// Calculates the root hash as if the given insertions and removals were applied.
     /// Does not modify the actual tree node.
-    pub fn hash_with<const DEPTH: usize, C: HashCache>(
+    pub fn hash_with<const DEPTH: usize, H: TreeHasher, C: HashCache<H>>(
         &self,
         cache: &C,
         insert_elements: &[Element],
@@ -61,11 +66,11 @@ impl Node {
         let (changes, bits): (Vec<_>, Vec<_>) = changes_with_bits.into_iter().unzip();
 
         // Start the recursive hash calculation
-        self.hash_with_inner::<DEPTH, C>(cache, &changes, &bits, 0)
+        self.hash_with_inner::<DEPTH, H, C>(cache, &changes, &bits, 0)
     }
 
     /// Recursive helper for `hash_with`.
-    fn hash_with_inner<const DEPTH: usize, C: HashCache>(
+    fn hash_with_inner<const DEPTH: usize, H: TreeHasher, C: HashCache<H>>(
         &self,
         cache: &C,
         changes: &[(Change, Element)], // Sorted list of changes (insert/remove, element)
@@ -97,7 +102,7 @@ impl Node {
                         // A remove targets this leaf's path.
                         // Check if it's removing the element currently here.
                         if current_element == removed_element {
-                            empty_tree_hash(1) // Removed, so becomes empty
+                            H::empty_tree_hash(1) // Removed, so becomes empty
                         } else {
                             // Attempting to remove a different element than what's present.
                             // This indicates either an invalid removal request (should be checked before)
@@ -125,13 +130,13 @@ impl Node {
                 let right_changes = &changes[right_start..];
 
                 // Recursively calculate hashes for left and right children
-                let left_hash = left.hash_with_inner::<DEPTH, C>(
+                let left_hash = left.hash_with_inner::<DEPTH, H, C>(
                     cache,
                     left_changes,
                     left_bits,
                     path_depth + 1,
                 );
-                let right_hash = right.hash_with_inner::<DEPTH, C>(
+                let right_hash = right.hash_with_inner::<DEPTH, H, C>(
                     cache,
                     right_changes,
                     right_bits,
@@ -150,14 +155,14 @@ impl Node {
                 );
                 match changes.first() {
                     Some((Change::Insert, element)) => *element, // Insert creates a leaf
-                    Some((Change::Remove, _)) | None => empty_tree_hash(1), // Removing from empty or no change -> stays empty
+                    Some((Change::Remove, _)) | None => H::empty_tree_hash(1), // Removing from empty or no change -> stays empty
                 }
             }
             Self::Empty { depth } => {
                 // An empty node at an intermediate depth.
                 // If no changes pass through this node, its hash remains the empty tree hash.
                 if changes.is_empty() {
-                    return empty_tree_hash(*depth);
+                    return H::empty_tree_hash(*depth);
                 }
 
                 // Otherwise, we need to simulate its expansion and recurse.
@@ -176,13 +181,13 @@ impl Node {
                 let temp_right_node = Node::Empty { depth: *depth - 1 };
 
                 // Recurse into the simulated children.
-                let left_hash = temp_left_node.hash_with_inner::<DEPTH, C>(
+                let left_hash = temp_left_node.hash_with_inner::<DEPTH, H, C>(
                     cache,
                     left_changes,
                     left_bits,
                     path_depth + 1,
                 );
-                let right_hash = temp_right_node.hash_with_inner::<DEPTH, C>(
+                let right_hash = temp_right_node.hash_with_inner::<DEPTH, H, C>(
                     cache,
                     right_changes,
                     right_bits,
@@ -195,10 +200,21 @@ impl Node {
         }
     }
 
-    pub fn hash(&self) -> Element {
+    /// Whether this node's children have changed without recalculating its hash
+    fn is_hash_dirty(&self) -> bool {
+        matches!(
+            self,
+            Self::Parent {
+                hash_dirty: true,
+                ..
+            }
+        )
+    }
+
+    pub fn hash<H: TreeHasher>(&self) -> Element {
         match self {
             Self::Leaf(hash) | Self::Parent { hash, .. } => *hash,
-            Self::Empty { depth } => empty_tree_hash(*depth),
+            Self::Empty { depth } => H::empty_tree_hash(*depth),
         }
     }
 
@@ -208,7 +224,7 @@ impl Node {
     /// potentially out of date
     ///
     /// The elements and bits should be sorted by the bits before calling this function
-    pub(crate) fn insert_without_hashing<const N: usize>(
+    pub(crate) fn insert_without_hashing<const N: usize, H: TreeHasher>(
         &mut self,
         elements: &[(Change, Element)],
         bits: &[BitVec<u8, Msb0>],
@@ -273,15 +289,33 @@ impl Node {
                 let (left, right) = match (lefts.is_empty(), rights.is_empty()) {
                     (true, true) => return Ok(false),
                     (false, true) => (
-                        { left.insert_without_hashing::<N>(lefts_elements, lefts, path_depth + 1) },
+                        {
+                            Arc::make_mut(left).insert_without_hashing::<N, H>(
+                                lefts_elements,
+                                lefts,
+                                path_depth + 1,
+                            )
+                        },
                         Ok(false),
                     ),
                     (true, false) => (Ok(false), {
-                        right.insert_without_hashing::<N>(rights_elements, rights, path_depth + 1)
+                        Arc::make_mut(right).insert_without_hashing::<N, H>(
+                            rights_elements,
+                            rights,
+                            path_depth + 1,
+                        )
                     }),
                     (false, false) => (
-                        right.insert_without_hashing::<N>(rights_elements, rights, path_depth + 1),
-                        left.insert_without_hashing::<N>(lefts_elements, lefts, path_depth + 1),
+                        Arc::make_mut(right).insert_without_hashing::<N, H>(
+                            rights_elements,
+                            rights,
+                            path_depth + 1,
+                        ),
+                        Arc::make_mut(left).insert_without_hashing::<N, H>(
+                            lefts_elements,
+                            lefts,
+                            path_depth + 1,
+                        ),
                     ),
                 };
 
@@ -306,19 +340,19 @@ impl Node {
             Self::Empty { depth } => {
                 // split an empty tree into two empty subtrees
                 *self = Self::Parent {
-                    left: Box::new(Self::Empty { depth: *depth - 1 }),
-                    right: Box::new(Self::Empty { depth: *depth - 1 }),
-                    hash: empty_tree_hash(*depth),
+                    left: Arc::new(Self::Empty { depth: *depth - 1 }),
+                    right: Arc::new(Self::Empty { depth: *depth - 1 }),
+                    hash: H::empty_tree_hash(*depth),
                     hash_dirty: false,
                 };
 
                 // now try again
-                self.insert_without_hashing::<N>(elements, bits, path_depth)
+                self.insert_without_hashing::<N, H>(elements, bits, path_depth)
             }
         }
     }
 
-    pub fn recalculate_hashes<C: HashCache>(
+    pub fn recalculate_hashes<H: TreeHasher, C: HashCache<H>>(
         &mut self,
         cache: &C,
         hash_remove_callback: &(impl Fn((&Element, &Element)) + Send + Sync),
@@ -338,17 +372,34 @@ impl Node {
             return;
         }
 
-        let left_hash_before = left.hash();
-        let right_hash_before = right.hash();
+        let left_hash_before = left.hash::<H>();
+        let right_hash_before = right.hash::<H>();
         hash_remove_callback((&left_hash_before, &right_hash_before));
 
+        // only dirty children need to be unshared, clean ones can stay shared with other trees
         rayon::join(
-            || left.recalculate_hashes(cache, hash_remove_callback, hash_set_callback),
-            || right.recalculate_hashes(cache, hash_remove_callback, hash_set_callback),
+            || {
+                if left.is_hash_dirty() {
+                    Arc::make_mut(left).recalculate_hashes::<H, C>(
+                        cache,
+                        hash_remove_callback,
+                        hash_set_callback,
+                    );
+                }
+            },
+            || {
+                if right.is_hash_dirty() {
+                    Arc::make_mut(right).recalculate_hashes::<H, C>(
+                        cache,
+                        hash_remove_callback,
+                        hash_set_callback,
+                    );
+                }
+            },
         );
 
-        let left_hash = left.hash();
-        let right_hash = right.hash();
+        let left_hash = left.hash::<H>();
+        let right_hash = right.hash::<H>();
         *hash = cache.hash(left_hash, right_hash);
         *hash_dirty = false;
         hash_set_callback((&left_hash, &right_hash, hash));
@@ -357,9 +408,12 @@ impl Node {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use proptest::prop_assume;
     use test_strategy::proptest;
 
+    use super::Node;
     use crate::{Batch, Tree};
 
     #[proptest]
@@ -371,4 +425,34 @@ mod tests {
 
         assert_eq!(tree.root_hash(), hash_with);
     }
+
+    #[test]
+    fn clones_share_unmodified_subtrees() {
+        use element::Element;
+
+        // the root's left/right choice is bit 62, so these go on different sides
+        let mut tree = Tree::<64, ()>::new();
+        tree.insert(Element::new(1), ()).unwrap();
+        tree.insert(Element::new(1 << 62), ()).unwrap();
+        let snapshot = tree.clone();
+
+        tree.insert(Element::new(2), ()).unwrap();
+
+        let (
+            Node::Parent { left, right, .. },
+            Node::Parent {
+                left: snapshot_left,
+                right: snapshot_right,
+                ..
+            },
+        ) = (&tree.tree, &snapshot.tree)
+        else {
+            panic!("non-empty trees have a parent at the root");
+        };
+
+        assert!(Arc::ptr_eq(right, snapshot_right));
+        assert!(!Arc::ptr_eq(left, snapshot_left));
+        assert_ne!(tree.root_hash(), snapshot.root_hash());
+        assert!(!snapshot.contains_element(&Element::new(2)));
+    }
 }
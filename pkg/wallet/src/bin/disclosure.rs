@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use element::Element;
+use eyre::{Result, eyre};
+use node_interface::NodeClient;
+use rand::rngs::OsRng;
+use wallet::{Wallet, verify_disclosure};
+use zk_primitives::{AuditorKey, AuditorPublicKey, EncryptedDisclosure};
+
+#[derive(Debug, Parser)]
+#[command(about = "Generate and verify transaction disclosures for auditors")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate a new auditor key pair
+    AuditorKey,
+
+    /// Encrypt a wallet's submitted transactions for an auditor
+    Generate {
+        /// Path to the wallet file
+        #[arg(long)]
+        wallet: PathBuf,
+
+        /// The auditor's public key, in hex
+        #[arg(long)]
+        auditor: AuditorPublicKey,
+
+        /// Hashes of the transactions to disclose, all submitted transactions if omitted
+        #[arg(long = "txn")]
+        txns: Vec<Element>,
+
+        /// Where to write the encrypted bundle
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Decrypt a bundle and check it against the chain
+    Verify {
+        /// Path to the encrypted bundle
+        bundle: PathBuf,
+
+        /// The auditor's secret key, in hex
+        #[arg(long, env = "POLY_AUDITOR_KEY")]
+        auditor_key: String,
+
+        /// Base URL of a node's RPC server
+        #[arg(long, env = "POLY_NODE_URL", default_value = "http://localhost:8091")]
+        node_url: String,
+    },
+}
+
+fn parse_auditor_key(hex_key: &str) -> Result<AuditorKey> {
+    let mut bytes = [0; 32];
+    hex::decode_to_slice(hex_key.trim_start_matches("0x"), &mut bytes)
+        .map_err(|err| eyre!("invalid auditor key: {err}"))?;
+    Ok(AuditorKey::from_bytes(bytes))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install().unwrap();
+
+    match Args::parse().command {
+        Command::AuditorKey => {
+            let key = AuditorKey::random(OsRng);
+            println!("Secret key:");
+            println!("  0x{}", hex::encode(key.to_bytes()));
+            println!();
+            println!("Public key:");
+            println!("  0x{}", key.public_key());
+            println!();
+        }
+        Command::Generate {
+            wallet,
+            auditor,
+            txns,
+            out,
+        } => {
+            let wallet = Wallet::open(wallet)?;
+            let bundle = wallet.disclosure_bundle(&txns)?;
+            let encrypted = bundle.encrypt(&auditor, OsRng);
+
+            std::fs::write(&out, serde_json::to_vec(&encrypted)?)?;
+            println!(
+                "Disclosed {} transactions to {}",
+                bundle.transactions.len(),
+                out.display()
+            );
+        }
+        Command::Verify {
+            bundle,
+            auditor_key,
+            node_url,
+        } => {
+            let auditor_key = parse_auditor_key(&auditor_key)?;
+            let encrypted: EncryptedDisclosure = serde_json::from_slice(&std::fs::read(&bundle)?)?;
+            let bundle = encrypted.decrypt(&auditor_key)?;
+
+            let client = NodeClient::new(node_url);
+            for verified in verify_disclosure(&client, &bundle).await? {
+                let txn = &verified.transaction;
+                println!("0x{}", verified.hash.to_hex());
+                println!("  kind:   {:?}", txn.kind);
+                println!("  height: {}", verified.block_height.0);
+                println!("  time:   {}", verified.time);
+                println!("  value:  {}", txn.value().to_u256());
+                for note in txn.input_notes.iter().filter(|note| !note.value.is_zero()) {
+                    println!(
+                        "  input:  {} from 0x{}",
+                        note.value.to_u256(),
+                        note.address.to_hex()
+                    );
+                }
+                for note in txn.output_notes.iter().filter(|note| !note.value.is_zero()) {
+                    println!(
+                        "  output: {} to 0x{}",
+                        note.value.to_u256(),
+                        note.address.to_hex()
+                    );
+                }
+            }
+            println!("Verified {} transactions", bundle.transactions.len());
+        }
+    }
+
+    Ok(())
+}
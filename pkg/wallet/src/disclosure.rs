@@ -0,0 +1,111 @@
+use element::Element;
+use node_interface::NodeClient;
+use primitives::block_height::BlockHeight;
+use zk_primitives::{DisclosedTransaction, DisclosureBundle, DisclosureError, TxnProof};
+
+use crate::{Error, Result};
+
+/// A disclosed transaction that was checked against the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedTransaction {
+    /// The disclosed transaction
+    pub transaction: DisclosedTransaction,
+    /// The hash of the transaction
+    pub hash: Element,
+    /// Height of the block the transaction was included in
+    pub block_height: BlockHeight,
+    /// Unix timestamp of the block in seconds
+    pub time: u64,
+}
+
+/// Check every transaction of a disclosure bundle against the chain, as seen by `client`
+///
+/// A transaction is verified if:
+///  - a transaction with its hash was included in a block
+///  - its disclosed notes produce the public inputs of that transaction, so the notes are the
+///    preimages of its commitments
+///  - the element history of its output commitments shows they were created by it, its input
+///    commitments are in the notes tree, and its nullifiers were revealed by it
+///
+/// Nullifiers can't be linked to the input notes without their secret keys, so the auditor
+/// trusts the discloser that the nullifiers belong to the disclosed input notes
+pub async fn verify_disclosure(
+    client: &NodeClient,
+    bundle: &DisclosureBundle,
+) -> Result<Vec<VerifiedTransaction>> {
+    let mut verified = Vec::with_capacity(bundle.transactions.len());
+
+    for transaction in &bundle.transactions {
+        let hash = transaction.hash();
+        let txn = client.transaction(hash).await?.txn;
+        // Only utxo transactions can be disclosed
+        let TxnProof::Utxo(proof) = &txn.proof else {
+            return Err(DisclosureError::PublicInputsMismatch(hash))?;
+        };
+        transaction.verify(&proof.public_inputs)?;
+
+        verify_element_history(client, transaction, hash).await?;
+
+        verified.push(VerifiedTransaction {
+            transaction: transaction.clone(),
+            hash,
+            block_height: txn.block_height,
+            time: txn.time,
+        });
+    }
+
+    Ok(verified)
+}
+
+async fn verify_element_history(
+    client: &NodeClient,
+    transaction: &DisclosedTransaction,
+    hash: Element,
+) -> Result<()> {
+    let public_inputs = transaction.public_inputs();
+    let commitments = transaction
+        .input_notes
+        .iter()
+        .map(|note| note.commitment())
+        .chain(public_inputs.output_commitments)
+        .filter(|commitment| !commitment.is_zero())
+        .collect::<Vec<_>>();
+    let history = client.list_elements(&commitments, true, None).await?;
+
+    for commitment in commitments {
+        let is_output = public_inputs.output_commitments.contains(&commitment);
+        let matches = history
+            .iter()
+            .find(|element| element.element == commitment)
+            .is_some_and(|element| !is_output || element.txn_hash == hash);
+
+        if !matches {
+            return Err(Error::ElementHistoryMismatch {
+                commitment,
+                txn: hash,
+            });
+        }
+    }
+
+    let nullifiers = public_inputs
+        .nullifiers
+        .into_iter()
+        .filter(|nullifier| !nullifier.is_zero())
+        .collect::<Vec<_>>();
+    let spent = client.list_nullifiers(&nullifiers).await?;
+
+    for nullifier in nullifiers {
+        let matches = spent
+            .iter()
+            .any(|spent| spent.nullifier == nullifier && spent.txn_hash == hash);
+
+        if !matches {
+            return Err(Error::NullifierMismatch {
+                nullifier,
+                txn: hash,
+            });
+        }
+    }
+
+    Ok(())
+}
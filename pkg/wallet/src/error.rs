@@ -0,0 +1,83 @@
+use element::Element;
+
+/// Result type for wallet operations
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the wallet
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The wallet doesn't own enough unspent notes of the requested contract
+    #[error("insufficient funds: {available} available, {required} required")]
+    InsufficientFunds {
+        /// The total value of the unspent notes
+        available: Element,
+        /// The value needed to make the payment
+        required: Element,
+    },
+
+    /// Payments, mints and burns must have a non-zero value
+    #[error("amount must be greater than zero")]
+    ZeroAmount,
+
+    /// The wallet has no secret keys, so it can't receive change
+    #[error("wallet has no secret keys")]
+    NoSecretKeys,
+
+    /// The note can't be spent by any of the wallet's secret keys
+    #[error("note {0} is not owned by this wallet")]
+    NotOwned(Element),
+
+    /// The wallet was never saved, or opened from a file
+    #[error("wallet has no path to save to")]
+    NoPath,
+
+    /// The wallet didn't submit a transaction with this hash
+    #[error("transaction {0} was not submitted by this wallet")]
+    UnknownTransaction(Element),
+
+    /// A disclosure couldn't be decrypted, or doesn't match the transaction on chain
+    #[error("disclosure error: {0}")]
+    Disclosure(#[from] zk_primitives::DisclosureError),
+
+    /// The history of a disclosed note's commitment doesn't match the disclosed transaction
+    #[error("element history of {commitment} does not match disclosed transaction {txn}")]
+    ElementHistoryMismatch {
+        /// The commitment of the note
+        commitment: Element,
+        /// The hash of the disclosed transaction
+        txn: Element,
+    },
+
+    /// A disclosed nullifier wasn't revealed by the disclosed transaction
+    #[error("nullifier {nullifier} was not revealed by disclosed transaction {txn}")]
+    NullifierMismatch {
+        /// The disclosed nullifier
+        nullifier: Element,
+        /// The hash of the disclosed transaction
+        txn: Element,
+    },
+
+    /// The node didn't return a merkle path of the notes tree's depth for an input note
+    #[error("invalid merkle path for {0}")]
+    InvalidMerklePath(Element),
+
+    /// Failed to generate a proof
+    #[error("failed to prove utxo: {0}")]
+    Prove(String),
+
+    /// The proving task panicked or was cancelled
+    #[error("proving task failed: {0}")]
+    Join(#[from] tokio::task::JoinError),
+
+    /// Request to the node failed
+    #[error("node error: {0}")]
+    Node(#[from] node_interface::Error),
+
+    /// Failed to read or write the wallet file
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The wallet file is invalid
+    #[error("serde error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
@@ -0,0 +1,31 @@
+#![warn(clippy::pedantic)]
+#![allow(clippy::module_name_repetitions)]
+#![allow(clippy::match_bool)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::doc_markdown)]
+#![deny(missing_docs)]
+
+//! Client-side wallet for Payy Network
+//!
+//! A [`Wallet`] holds secret keys and the notes they own, and turns payments into the sequence of
+//! [`Utxo`][zk_primitives::Utxo]s needed to make them. Circuits only take two inputs, so paying
+//! with more than two notes first merges them together, and each [`TransactionPlan`] records the
+//! notes it creates so the wallet can keep track of them.
+//!
+//! Submitted transactions are recorded, so they can be disclosed to an auditor with
+//! [`Wallet::disclosure_bundle`] and checked against the chain with [`verify_disclosure`]
+
+mod disclosure;
+mod error;
+mod note;
+mod plan;
+mod select;
+mod wallet;
+
+pub use disclosure::*;
+pub use error::*;
+pub use note::*;
+pub use plan::*;
+pub use select::*;
+pub use wallet::*;
@@ -0,0 +1,58 @@
+use element::Element;
+use serde::{Deserialize, Serialize};
+use zk_primitives::InputNote;
+
+/// Whether a note owned by the wallet can be spent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteStatus {
+    /// The note is in the notes tree and has not been spent
+    Unspent,
+    /// The note has been received (or created by a transaction the wallet submitted), but the
+    /// node hasn't included it in a block yet
+    #[default]
+    Pending,
+    /// The note has been spent
+    Spent,
+}
+
+/// A note that can be spent by one of the wallet's secret keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnedNote {
+    /// The note, and the secret key needed to spend it
+    pub note: InputNote,
+    /// Whether the note can be spent
+    pub status: NoteStatus,
+}
+
+impl OwnedNote {
+    /// Create a new owned note
+    #[must_use]
+    pub fn new(note: InputNote, status: NoteStatus) -> Self {
+        Self { note, status }
+    }
+
+    /// The commitment of the note, which is the element inserted into the notes tree
+    #[must_use]
+    pub fn commitment(&self) -> Element {
+        self.note.note.commitment()
+    }
+
+    /// The value of the note
+    #[must_use]
+    pub fn value(&self) -> Element {
+        self.note.note.value
+    }
+
+    /// The contract (i.e. token) of the note
+    #[must_use]
+    pub fn contract(&self) -> Element {
+        self.note.note.contract
+    }
+
+    /// Whether the note can be used as an input
+    #[must_use]
+    pub fn is_spendable(&self) -> bool {
+        self.status == NoteStatus::Unspent
+    }
+}
@@ -0,0 +1,279 @@
+use element::Element;
+use zk_primitives::{InputNote, Note, Utxo, get_address_for_private_key};
+
+use crate::Selection;
+
+/// The transactions needed to make a payment, in the order they must be submitted
+///
+/// Each [`Utxo`] may spend notes created by the ones before it, so they have to be proven and
+/// submitted one after another, and each must be included in a block before the next is
+/// submitted
+#[derive(Debug, Clone)]
+pub struct TransactionPlan {
+    /// The transactions to prove and submit
+    pub utxos: Vec<Utxo>,
+    /// Notes created by the plan that are owned by the wallet (consolidated notes and change),
+    /// including ones that are spent again by a later transaction in the plan
+    pub created: Vec<InputNote>,
+    /// The note sent to (or minted for) the recipient, which has to be shared with them so
+    /// they can spend it
+    pub payment: Option<Note>,
+}
+
+impl TransactionPlan {
+    /// The commitments of the notes spent by the plan
+    pub fn spent(&self) -> impl Iterator<Item = Element> + '_ {
+        self.utxos
+            .iter()
+            .flat_map(|utxo| &utxo.input_notes)
+            .map(|input| input.note.commitment())
+            .filter(|commitment| !commitment.is_zero())
+    }
+
+    /// Plan a payment of `amount` to `address`, with change going to `change_key`
+    ///
+    /// `selection` must have a total of at least `amount`
+    #[must_use]
+    pub fn send(
+        selection: Selection,
+        address: Element,
+        amount: Element,
+        change_key: Element,
+    ) -> Self {
+        let change = selection.change(amount);
+        let (mut plan, inputs) = Self::consolidate(selection.notes, change_key);
+        let contract = inputs[0].note.contract;
+
+        let payment = Note::new(address, amount, contract);
+        let change = match change.is_zero() {
+            true => Note::padding_note(),
+            false => {
+                let change = owned_note(change_key, change, contract);
+                plan.created.push(change.clone());
+                change.note
+            }
+        };
+
+        plan.utxos
+            .push(Utxo::new_send(inputs, [payment.clone(), change]));
+        plan.payment = Some(payment);
+        plan
+    }
+
+    /// Plan a burn of `amount` to `evm_address`, with change going to `change_key`
+    ///
+    /// A burn always burns the full value of its inputs, so unless `selection` adds up to exactly
+    /// `amount` a note of exactly `amount` is split off first
+    #[must_use]
+    pub fn burn(
+        selection: Selection,
+        evm_address: Element,
+        amount: Element,
+        change_key: Element,
+    ) -> Self {
+        let change = selection.change(amount);
+
+        let (mut plan, inputs) = match change.is_zero() {
+            true => Self::consolidate(selection.notes, change_key),
+            false => {
+                let address = get_address_for_private_key(change_key);
+                let mut plan = Self::send(selection, address, amount, change_key);
+                let payment = plan.payment.take().expect("send always has a payment");
+                let exact = InputNote::new(payment, change_key);
+                plan.created.push(exact.clone());
+                (plan, [exact, InputNote::padding_note()])
+            }
+        };
+
+        plan.utxos.push(Utxo::new_burn(inputs, evm_address));
+        plan
+    }
+
+    /// Plan a mint of `amount` of `contract` to the wallet's `key`
+    ///
+    /// The mint only completes once the [`Utxo::mint_hash`] is also approved by the rollup
+    /// contract
+    #[must_use]
+    pub fn mint(amount: Element, contract: Element, key: Element) -> Self {
+        let note = owned_note(key, amount, contract);
+
+        Self {
+            utxos: vec![Utxo::new_mint([note.note.clone(), Note::padding_note()])],
+            payment: Some(note.note.clone()),
+            created: vec![note],
+        }
+    }
+
+    /// Merge `notes` together until there are at most two left, which are returned (with
+    /// padding) as the inputs for the last transaction of the plan
+    fn consolidate(mut notes: Vec<InputNote>, key: Element) -> (Self, [InputNote; 2]) {
+        let mut plan = Self {
+            utxos: Vec::new(),
+            created: Vec::new(),
+            payment: None,
+        };
+
+        // Merge the two smallest notes each time, so large notes are only spent once
+        while notes.len() > 2 {
+            let b = notes.pop().expect("more than 2 notes");
+            let a = notes.pop().expect("more than 2 notes");
+
+            let merged = owned_note(key, a.note.value + b.note.value, a.note.contract);
+            plan.utxos.push(Utxo::new_send(
+                [a, b],
+                [merged.note.clone(), Note::padding_note()],
+            ));
+            plan.created.push(merged.clone());
+
+            let position = notes
+                .iter()
+                .position(|note| note.note.value < merged.note.value)
+                .unwrap_or(notes.len());
+            notes.insert(position, merged);
+        }
+
+        let mut notes = notes.into_iter();
+        let inputs = [
+            notes.next().expect("selection is never empty"),
+            notes.next().unwrap_or_else(InputNote::padding_note),
+        ];
+
+        (plan, inputs)
+    }
+}
+
+fn owned_note(key: Element, value: Element, contract: Element) -> InputNote {
+    InputNote::new(
+        Note::new(get_address_for_private_key(key), value, contract),
+        key,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use zk_primitives::{UtxoKind, bridged_polygon_usdc_note_kind};
+
+    use super::*;
+
+    const KEY: Element = Element::ONE;
+
+    fn selection(values: &[u64]) -> Selection {
+        let notes = values
+            .iter()
+            .map(|&value| owned_note(KEY, Element::new(value), bridged_polygon_usdc_note_kind()))
+            .collect::<Vec<_>>();
+        let total = notes.iter().map(|n| n.note.value).sum();
+
+        Selection { notes, total }
+    }
+
+    /// Every input is either one of the selected notes or created by an earlier utxo, and every
+    /// utxo conserves value (apart from burns)
+    fn assert_valid_chain(plan: &TransactionPlan, selection: &Selection) {
+        let mut available = selection
+            .notes
+            .iter()
+            .map(|n| n.note.commitment())
+            .collect::<HashSet<_>>();
+
+        for utxo in &plan.utxos {
+            for input in &utxo.input_notes {
+                let commitment = input.note.commitment();
+                if !commitment.is_zero() {
+                    assert!(available.remove(&commitment), "input spent before created");
+                }
+            }
+
+            if utxo.kind != UtxoKind::Burn {
+                assert_eq!(utxo.input_value(), utxo.output_value());
+            }
+
+            for output in &utxo.output_notes {
+                available.insert(output.commitment());
+            }
+        }
+    }
+
+    #[test]
+    fn send_with_change() {
+        let selection = selection(&[30, 20]);
+        let plan = TransactionPlan::send(selection.clone(), Element::new(7), Element::new(45), KEY);
+
+        assert_valid_chain(&plan, &selection);
+        assert_eq!(plan.utxos.len(), 1);
+        assert_eq!(plan.payment.as_ref().unwrap().value, Element::new(45));
+        assert_eq!(plan.payment.as_ref().unwrap().address, Element::new(7));
+        assert_eq!(plan.created.len(), 1);
+        assert_eq!(plan.created[0].note.value, Element::new(5));
+        assert_eq!(plan.spent().count(), 2);
+    }
+
+    #[test]
+    fn exact_send_has_no_change() {
+        let selection = selection(&[10]);
+        let plan = TransactionPlan::send(selection.clone(), Element::new(7), Element::new(10), KEY);
+
+        assert_valid_chain(&plan, &selection);
+        assert!(plan.created.is_empty());
+        assert!(plan.utxos[0].output_notes[1].is_padding_note());
+        assert!(plan.utxos[0].input_notes[1].note.is_padding_note());
+    }
+
+    #[test]
+    fn consolidates_more_than_two_notes() {
+        let selection = selection(&[50, 40, 30, 20, 10]);
+        let plan =
+            TransactionPlan::send(selection.clone(), Element::new(7), Element::new(145), KEY);
+
+        assert_valid_chain(&plan, &selection);
+        assert_eq!(plan.utxos.len(), 4);
+        assert_eq!(plan.spent().count(), 5 + 3);
+
+        let unspent = plan
+            .created
+            .iter()
+            .filter(|n| !plan.spent().any(|c| c == n.note.commitment()))
+            .collect::<Vec<_>>();
+        assert_eq!(unspent.len(), 1);
+        assert_eq!(unspent[0].note.value, Element::new(5));
+    }
+
+    #[test]
+    fn burn_splits_off_exact_note() {
+        let selection = selection(&[30, 20, 5]);
+        let plan = TransactionPlan::burn(selection.clone(), Element::new(9), Element::new(42), KEY);
+
+        assert_valid_chain(&plan, &selection);
+
+        let burn = plan.utxos.last().unwrap();
+        assert_eq!(burn.kind, UtxoKind::Burn);
+        assert_eq!(burn.input_value(), Element::new(42));
+        assert_eq!(burn.burn_address, Some(Element::new(9)));
+        assert!(plan.payment.is_none());
+    }
+
+    #[test]
+    fn exact_burn_spends_selection_directly() {
+        let selection = selection(&[30, 12]);
+        let plan = TransactionPlan::burn(selection.clone(), Element::new(9), Element::new(42), KEY);
+
+        assert_valid_chain(&plan, &selection);
+        assert_eq!(plan.utxos.len(), 1);
+        assert_eq!(plan.utxos[0].input_value(), Element::new(42));
+    }
+
+    #[test]
+    fn mint_creates_owned_note() {
+        let plan = TransactionPlan::mint(Element::new(3), Element::new(4), KEY);
+
+        assert_eq!(plan.utxos.len(), 1);
+        assert_eq!(plan.utxos[0].kind, UtxoKind::Mint);
+        assert_eq!(plan.created[0].note.contract, Element::new(4));
+        assert_eq!(
+            plan.created[0].note.address,
+            get_address_for_private_key(KEY)
+        );
+    }
+}
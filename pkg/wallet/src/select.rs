@@ -0,0 +1,134 @@
+use element::Element;
+use zk_primitives::InputNote;
+
+use crate::{Error, OwnedNote, Result};
+
+/// Notes chosen to pay an amount
+#[derive(Debug, Clone)]
+pub struct Selection {
+    /// The notes to spend, largest first
+    pub notes: Vec<InputNote>,
+    /// The total value of `notes`, which is at least the amount that was requested
+    pub total: Element,
+}
+
+impl Selection {
+    /// The value left over after paying `amount`, which is returned to the wallet as change
+    #[must_use]
+    pub fn change(&self, amount: Element) -> Element {
+        self.total - amount
+    }
+}
+
+/// Choose unspent notes of `contract` with a total value of at least `amount`
+///
+/// Notes are picked largest first, which keeps the number of inputs (and so the number of
+/// consolidation transactions) as small as possible
+pub fn select_notes<'a>(
+    notes: impl IntoIterator<Item = &'a OwnedNote>,
+    contract: Element,
+    amount: Element,
+) -> Result<Selection> {
+    if amount.is_zero() {
+        return Err(Error::ZeroAmount);
+    }
+
+    let mut candidates = notes
+        .into_iter()
+        .filter(|note| note.is_spendable() && note.contract() == contract)
+        .filter(|note| !note.value().is_zero())
+        .collect::<Vec<_>>();
+
+    candidates.sort_by_key(|note| std::cmp::Reverse(note.value()));
+
+    let mut selected = Vec::new();
+    let mut total = Element::ZERO;
+
+    for note in &candidates {
+        if total >= amount {
+            break;
+        }
+
+        total = total + note.value();
+        selected.push(note.note.clone());
+    }
+
+    if total < amount {
+        return Err(Error::InsufficientFunds {
+            available: total,
+            required: amount,
+        });
+    }
+
+    Ok(Selection {
+        notes: selected,
+        total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use zk_primitives::{Note, bridged_polygon_usdc_note_kind};
+
+    use super::*;
+    use crate::NoteStatus;
+
+    fn owned(value: u64, status: NoteStatus) -> OwnedNote {
+        let note = Note::new(
+            Element::new(1),
+            Element::new(value),
+            bridged_polygon_usdc_note_kind(),
+        );
+        OwnedNote::new(InputNote::new(note, Element::new(2)), status)
+    }
+
+    fn values(selection: &Selection) -> Vec<Element> {
+        selection.notes.iter().map(|n| n.note.value).collect()
+    }
+
+    #[test]
+    fn picks_largest_notes_first() {
+        let notes = [
+            owned(5, NoteStatus::Unspent),
+            owned(20, NoteStatus::Unspent),
+            owned(10, NoteStatus::Unspent),
+        ];
+
+        let selection =
+            select_notes(&notes, bridged_polygon_usdc_note_kind(), Element::new(25)).unwrap();
+
+        assert_eq!(values(&selection), [Element::new(20), Element::new(10)]);
+        assert_eq!(selection.total, Element::new(30));
+        assert_eq!(selection.change(Element::new(25)), Element::new(5));
+    }
+
+    #[test]
+    fn skips_unspendable_and_other_contracts() {
+        let mut other_contract = owned(100, NoteStatus::Unspent);
+        other_contract.note.note.contract = Element::new(99);
+
+        let notes = [
+            owned(50, NoteStatus::Spent),
+            owned(40, NoteStatus::Pending),
+            other_contract,
+            owned(3, NoteStatus::Unspent),
+        ];
+
+        let err =
+            select_notes(&notes, bridged_polygon_usdc_note_kind(), Element::new(10)).unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::InsufficientFunds { available, required }
+                if available == Element::new(3) && required == Element::new(10)
+        ));
+    }
+
+    #[test]
+    fn rejects_zero_amount() {
+        let notes = [owned(1, NoteStatus::Unspent)];
+        let err = select_notes(&notes, bridged_polygon_usdc_note_kind(), Element::ZERO);
+
+        assert!(matches!(err, Err(Error::ZeroAmount)));
+    }
+}
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 /// The data required to prove an AggAgg transaction, this aggregates multiple AggUtxo proofs into
 /// a single proof. Expects each new_root from the previous AggUtxo proof to be the same as the
 /// old_root of the next AggUtxo proof.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct AggAgg {
     /// The proofs for the AggAgg transaction
     pub proofs: [AggUtxoProof; 2],
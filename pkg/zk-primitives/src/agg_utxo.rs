@@ -1,17 +1,31 @@
-use crate::{MerklePath, UtxoProof};
+use crate::{MerklePath, TxnProof};
 use crate::{ToBytes, UtxoKind, bytes_to_elements, impl_serde_for_element_array};
+use borsh::{BorshDeserialize, BorshSerialize};
 use element::{Base, Element};
 use hash::hash_merge;
 use primitives::serde::{deserialize_base64, serialize_base64};
 use serde::{Deserialize, Serialize};
 
-/// The data required to prove an AggUtxo transaction, this aggregates multiple Utxo proofs into
-/// a single proof. It also validates that the input notes are removed from the tree and the output
-/// notes are added to the tree.
-#[derive(Debug, Clone)]
+/// Domain separator for root leaves, see [`root_leaf`]
+const ROOT_LEAF_DOMAIN: u64 = 4;
+
+/// The leaf recording that the notes tree has had `root`, so utxo proofs can spend input notes
+/// from it. Matches `get_root_leaf` in the `agg_utxo` circuit
+#[must_use]
+pub fn root_leaf(root: Element) -> Element {
+    hash_merge([Element::new(ROOT_LEAF_DOMAIN), root])
+}
+
+/// The data required to prove an AggUtxo transaction, this aggregates multiple transaction
+/// proofs into a single proof. It also validates that the input notes are in a root the tree has had, and
+/// that their nullifiers and the output notes are added to the tree.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct AggUtxo {
     /// The proofs for the AggUtxo transaction
     pub proofs: [UtxoProofBundleWithMerkleProofs; 3],
+    /// The merkle path for inserting the [`root_leaf`] of `old_root`, if it is recorded. Set for
+    /// the first AggUtxo of a block
+    pub old_root_merkle_path: Option<MerklePath<161>>,
     /// The old root of the tree (must match the first merkle proof)
     pub old_root: Element,
     /// The new root of the tree (must match the last merkle proof)
@@ -23,11 +37,13 @@ impl AggUtxo {
     #[must_use]
     pub fn new(
         proofs: [UtxoProofBundleWithMerkleProofs; 3],
+        old_root_merkle_path: Option<MerklePath<161>>,
         old_root: Element,
         new_root: Element,
     ) -> Self {
         Self {
             proofs,
+            old_root_merkle_path,
             old_root,
             new_root,
         }
@@ -37,9 +53,9 @@ impl AggUtxo {
     #[must_use]
     pub fn commit_hash(&self) -> Element {
         hash_merge([
-            self.proofs[0].utxo_proof.public_inputs.commit_hash(),
-            self.proofs[1].utxo_proof.public_inputs.commit_hash(),
-            self.proofs[2].utxo_proof.public_inputs.commit_hash(),
+            self.proofs[0].utxo_proof.commit_hash(),
+            self.proofs[1].utxo_proof.commit_hash(),
+            self.proofs[2].utxo_proof.commit_hash(),
         ])
     }
 
@@ -50,10 +66,11 @@ impl AggUtxo {
         let mut index = 0;
 
         for proof in &self.proofs {
+            let txn_messages = proof.utxo_proof.messages();
             let proof_messages = match proof.utxo_proof.kind() {
                 UtxoKind::Null | UtxoKind::Send => &[][..],
-                UtxoKind::Mint => &proof.utxo_proof.public_inputs.messages[..4],
-                UtxoKind::Burn => &proof.utxo_proof.public_inputs.messages[..],
+                UtxoKind::Mint => &txn_messages[..4],
+                UtxoKind::Burn => &txn_messages[..],
             };
 
             for &message in proof_messages {
@@ -66,43 +83,49 @@ impl AggUtxo {
     }
 }
 
-/// A Utxo proof bundle with merkle proofs
-#[derive(Debug, Clone)]
+/// A transaction proof bundle with merkle proofs
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct UtxoProofBundleWithMerkleProofs {
-    /// The proof for the Utxo
-    pub utxo_proof: UtxoProof,
-    /// The merkle path proofs for removing input notes
-    pub input_merkle_paths: [MerklePath<161>; 2],
-    /// The merkle path proofs for adding output notes
-    pub output_merkle_paths: [MerklePath<161>; 2],
+    /// The proof in the slot, one of the [slots](TxnProof::slots) of a transaction. A padding
+    /// utxo proof by default
+    pub utxo_proof: TxnProof,
+    /// The merkle path proof for the [`root_leaf`] of the proof's root, unused if the proof
+    /// has no inputs
+    pub root_merkle_path: MerklePath<161>,
+    /// The merkle path proofs for adding nullifiers, unused past the proof's nullifiers
+    pub nullifier_merkle_paths: [MerklePath<161>; 4],
+    /// The merkle path proofs for adding output notes, unused past the proof's outputs
+    pub output_merkle_paths: [MerklePath<161>; 4],
 }
 
 impl UtxoProofBundleWithMerkleProofs {
-    /// Create a new UtxoProofBundleWithMerkleProofs
+    /// Create a new UtxoProofBundleWithMerkleProofs, with `merkle_paths` for inserting the
+    /// proof's [leaves](TxnProof::leaves)
     #[must_use]
-    pub fn new(utxo_proof: UtxoProof, merkle_paths: &[MerklePath<161>; 4]) -> Self {
-        Self {
-            utxo_proof,
-            input_merkle_paths: [merkle_paths[0].clone(), merkle_paths[1].clone()],
-            output_merkle_paths: [merkle_paths[2].clone(), merkle_paths[3].clone()],
-        }
-    }
-}
+    pub fn new(
+        utxo_proof: impl Into<TxnProof>,
+        root_merkle_path: MerklePath<161>,
+        merkle_paths: &[MerklePath<161>],
+    ) -> Self {
+        let utxo_proof = utxo_proof.into();
+        let (nullifier_paths, output_paths) = merkle_paths.split_at(utxo_proof.nullifiers().len());
+
+        let mut nullifier_merkle_paths: [MerklePath<161>; 4] = Default::default();
+        nullifier_merkle_paths[..nullifier_paths.len()].clone_from_slice(nullifier_paths);
+        let mut output_merkle_paths: [MerklePath<161>; 4] = Default::default();
+        output_merkle_paths[..output_paths.len()].clone_from_slice(output_paths);
 
-impl Default for UtxoProofBundleWithMerkleProofs {
-    /// Create a padding UtxoProofBundleWithMerkleProofs
-    fn default() -> UtxoProofBundleWithMerkleProofs {
-        let merkle_path = MerklePath::default();
         Self {
-            utxo_proof: UtxoProof::default(),
-            input_merkle_paths: [merkle_path.clone(), merkle_path.clone()],
-            output_merkle_paths: [merkle_path.clone(), merkle_path.clone()],
+            utxo_proof,
+            root_merkle_path,
+            nullifier_merkle_paths,
+            output_merkle_paths,
         }
     }
 }
 
 /// Raw proof bytes for AggUtxo proof
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct AggUtxoProofBytes(
     #[serde(
         serialize_with = "serialize_base64",
@@ -143,7 +166,7 @@ impl From<&AggUtxoProofFields> for [Base; 93] {
 }
 
 /// The public input for a AggUtxo transaction
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct AggUtxoPublicInput {
     /// The messages of the transactions
     pub messages: [Element; 15],
@@ -190,7 +213,7 @@ impl AggUtxoPublicInput {
 }
 
 /// The output proof for a AggUtxo transaction
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct AggUtxoProof {
     /// The proof for the AggUtxo transaction
     pub proof: AggUtxoProofBytes,
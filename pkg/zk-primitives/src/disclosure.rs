@@ -0,0 +1,378 @@
+use element::Element;
+use primitives::serde::{deserialize_base64, serialize_base64};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    InputNote, Note, Utxo, UtxoKind, UtxoPublicInput, ViewingKey, ViewingPublicKey,
+    encrypted_note::{NONCE_LEN, open, seal},
+};
+
+/// The version of the [`EncryptedDisclosure`] format
+const DISCLOSURE_VERSION: u8 = 2;
+
+const DISCLOSURE_DOMAIN: &[u8] = b"payy-disclosure-v1";
+
+/// Length of an encoded [`EncryptedDisclosure`] without its ciphertext
+const HEADER_LEN: usize = 1 + 32 + 32 + NONCE_LEN;
+
+/// The key an auditor decrypts disclosures with. Auditors use the same key pairs as viewing
+/// keys, but should use one that isn't derived from a wallet's secret key
+pub type AuditorKey = ViewingKey;
+
+/// The public key that disclosures are encrypted to
+pub type AuditorPublicKey = ViewingPublicKey;
+
+/// Errors from decrypting or verifying a disclosure
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DisclosureError {
+    /// The encoded disclosure is too short to contain its header
+    #[error("encrypted disclosure is truncated")]
+    Truncated,
+
+    /// The encoded disclosure has a version this version of the library can't read
+    #[error("unsupported disclosure version {0}")]
+    UnsupportedVersion(u8),
+
+    /// The disclosure was not encrypted for this auditor key, or was modified
+    #[error("failed to decrypt disclosure")]
+    Decryption,
+
+    /// The decrypted disclosure isn't a valid bundle
+    #[error("invalid disclosure bundle: {0}")]
+    InvalidBundle(String),
+
+    /// The disclosed notes don't produce the public inputs of the transaction on chain
+    #[error("disclosed transaction {0} does not match the transaction on chain")]
+    PublicInputsMismatch(Element),
+}
+
+/// A transaction disclosed to an auditor: its notes in full, without the secret keys needed to
+/// spend them
+///
+/// The notes are linked to the chain by their commitments, which together with the messages,
+/// nullifiers and root make up the public inputs of the transaction, and so its hash. Nullifiers
+/// can't be computed without the secret keys of the input notes, so they are disclosed as they
+/// are.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosedTransaction {
+    /// The kind of transaction
+    pub kind: UtxoKind,
+    /// The notes spent by the transaction
+    pub input_notes: [Note; 2],
+    /// The notes created by the transaction
+    pub output_notes: [Note; 2],
+    /// The EVM address of a burn
+    pub burn_address: Option<Element>,
+    /// The root the input notes were spent from
+    pub root: Element,
+    /// The nullifiers of the input notes
+    pub nullifiers: [Element; 2],
+}
+
+impl From<&Utxo> for DisclosedTransaction {
+    fn from(utxo: &Utxo) -> Self {
+        Self {
+            kind: utxo.kind,
+            input_notes: utxo.input_notes.clone().map(|input| input.note),
+            output_notes: utxo.output_notes.clone(),
+            burn_address: utxo.burn_address,
+            root: utxo.root,
+            nullifiers: utxo.input_notes.each_ref().map(InputNote::nullifier),
+        }
+    }
+}
+
+impl DisclosedTransaction {
+    /// The public inputs of the transaction, recomputed from the disclosed notes
+    #[must_use]
+    pub fn public_inputs(&self) -> UtxoPublicInput {
+        // Secret keys aren't part of the public inputs, so placeholders are fine here
+        let input_notes = self
+            .input_notes
+            .clone()
+            .map(|note| InputNote::new(note, Element::ZERO));
+
+        let mut public_inputs = Utxo::new(
+            self.kind,
+            input_notes,
+            self.output_notes.clone(),
+            self.burn_address,
+        )
+        .public_inputs();
+
+        public_inputs.root = self.root;
+        public_inputs.nullifiers = self.nullifiers;
+        if self.kind == UtxoKind::Burn {
+            // The burn hash is the nullifier of the first input
+            public_inputs.messages[3] = self.nullifiers[0];
+        }
+
+        public_inputs
+    }
+
+    /// The hash of the transaction, which identifies it on chain
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        self.public_inputs().hash()
+    }
+
+    /// The value moved by the transaction, which is the total of its inputs for sends and burns,
+    /// and of its outputs for mints
+    #[must_use]
+    pub fn value(&self) -> Element {
+        match self.kind {
+            UtxoKind::Mint => self.output_notes.iter().map(|note| note.value).sum(),
+            _ => self.input_notes.iter().map(|note| note.value).sum(),
+        }
+    }
+
+    /// Check the disclosed notes against the public inputs of the transaction on chain
+    pub fn verify(&self, on_chain: &UtxoPublicInput) -> Result<(), DisclosureError> {
+        let public_inputs = self.public_inputs();
+
+        match public_inputs == *on_chain {
+            true => Ok(()),
+            false => Err(DisclosureError::PublicInputsMismatch(public_inputs.hash())),
+        }
+    }
+}
+
+/// A set of transactions disclosed to an auditor
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosureBundle {
+    /// The disclosed transactions
+    pub transactions: Vec<DisclosedTransaction>,
+}
+
+impl FromIterator<DisclosedTransaction> for DisclosureBundle {
+    fn from_iter<T: IntoIterator<Item = DisclosedTransaction>>(iter: T) -> Self {
+        Self {
+            transactions: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl DisclosureBundle {
+    /// Encrypt the bundle for `auditor`
+    #[must_use]
+    pub fn encrypt(
+        &self,
+        auditor: &AuditorPublicKey,
+        rng: impl RngCore + CryptoRng,
+    ) -> EncryptedDisclosure {
+        let plaintext = serde_json::to_vec(self).expect("bundles always serialize");
+        let (ephemeral_public_key, nonce, ciphertext) = seal(
+            DISCLOSURE_DOMAIN,
+            &plaintext,
+            &[DISCLOSURE_VERSION],
+            auditor,
+            rng,
+        );
+
+        EncryptedDisclosure {
+            auditor: *auditor,
+            ephemeral_public_key,
+            nonce,
+            ciphertext,
+        }
+    }
+}
+
+/// A [`DisclosureBundle`] encrypted for an auditor
+///
+/// Encrypted the same way as an [`EncryptedNote`](crate::EncryptedNote), and encoded as:
+///  - version (1 byte)
+///  - auditor public key (32 bytes)
+///  - ephemeral public key (32 bytes)
+///  - nonce (12 bytes)
+///  - JSON encoded bundle, encrypted (variable length, including the 16 byte tag)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedDisclosure {
+    /// The auditor the bundle is encrypted for
+    pub auditor: AuditorPublicKey,
+    /// The discloser's ephemeral public key
+    pub ephemeral_public_key: ViewingPublicKey,
+    /// The AEAD nonce
+    pub nonce: [u8; NONCE_LEN],
+    /// The encrypted bundle, including the AEAD tag
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedDisclosure {
+    /// Decrypt the bundle with the auditor's key
+    pub fn decrypt(&self, auditor: &AuditorKey) -> Result<DisclosureBundle, DisclosureError> {
+        let plaintext = open(
+            DISCLOSURE_DOMAIN,
+            auditor,
+            &self.ephemeral_public_key,
+            &self.nonce,
+            &self.ciphertext,
+            &[DISCLOSURE_VERSION],
+        )
+        .ok_or(DisclosureError::Decryption)?;
+
+        serde_json::from_slice(&plaintext)
+            .map_err(|err| DisclosureError::InvalidBundle(err.to_string()))
+    }
+
+    /// Encode the encrypted disclosure as bytes
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.ciphertext.len());
+        bytes.push(DISCLOSURE_VERSION);
+        bytes.extend_from_slice(&self.auditor.0);
+        bytes.extend_from_slice(&self.ephemeral_public_key.0);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// Decode an encrypted disclosure written by [`EncryptedDisclosure::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DisclosureError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(DisclosureError::Truncated);
+        }
+
+        let (&version, rest) = bytes.split_first().expect("length was checked");
+        if version != DISCLOSURE_VERSION {
+            return Err(DisclosureError::UnsupportedVersion(version));
+        }
+
+        let (auditor, rest) = rest.split_at(32);
+        let (ephemeral_public_key, rest) = rest.split_at(32);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        Ok(Self {
+            auditor: ViewingPublicKey(auditor.try_into().expect("length was checked")),
+            ephemeral_public_key: ViewingPublicKey(
+                ephemeral_public_key.try_into().expect("length was checked"),
+            ),
+            nonce: nonce.try_into().expect("length was checked"),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+impl Serialize for EncryptedDisclosure {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_base64(&self.to_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EncryptedDisclosure {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_base64(deserializer)?;
+        Self::from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::{bridged_polygon_usdc_note_kind, get_address_for_private_key};
+
+    fn send() -> Utxo {
+        let kind = bridged_polygon_usdc_note_kind();
+        let sender = Element::new(101);
+        let recipient = get_address_for_private_key(Element::new(202));
+
+        let input = InputNote::new(
+            Note::new(get_address_for_private_key(sender), Element::new(10), kind),
+            sender,
+        );
+
+        Utxo::new_send(
+            [input, InputNote::padding_note()],
+            [
+                Note::new(recipient, Element::new(7), kind),
+                Note::new(get_address_for_private_key(sender), Element::new(3), kind),
+            ],
+        )
+    }
+
+    #[test]
+    fn disclosed_transaction_matches_utxo() {
+        let utxo = send();
+        let disclosed = DisclosedTransaction::from(&utxo);
+
+        assert_eq!(disclosed.public_inputs(), utxo.public_inputs());
+        assert_eq!(disclosed.hash(), utxo.public_inputs().hash());
+        assert_eq!(disclosed.value(), Element::new(10));
+        assert_eq!(disclosed.verify(&utxo.public_inputs()), Ok(()));
+    }
+
+    #[test]
+    fn disclosed_burn_matches_utxo() {
+        let utxo = Utxo::new_burn(send().input_notes, Element::new(0xB0B));
+        let disclosed = DisclosedTransaction::from(&utxo);
+
+        assert_eq!(disclosed.verify(&utxo.public_inputs()), Ok(()));
+    }
+
+    #[test]
+    fn modified_notes_fail_verification() {
+        let utxo = send();
+        let mut disclosed = DisclosedTransaction::from(&utxo);
+        disclosed.output_notes[0].value = Element::new(8);
+
+        assert!(matches!(
+            disclosed.verify(&utxo.public_inputs()),
+            Err(DisclosureError::PublicInputsMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn bundle_round_trips_for_auditor() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let auditor = AuditorKey::random(&mut rng);
+        let bundle = DisclosureBundle {
+            transactions: vec![DisclosedTransaction::from(&send())],
+        };
+
+        let encrypted = bundle.encrypt(&auditor.public_key(), &mut rng);
+        let decoded = EncryptedDisclosure::from_bytes(&encrypted.to_bytes()).unwrap();
+        assert_eq!(decoded, encrypted);
+        assert_eq!(decoded.auditor, auditor.public_key());
+        assert_eq!(decoded.decrypt(&auditor), Ok(bundle));
+
+        let json = serde_json::to_string(&encrypted).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EncryptedDisclosure>(&json).unwrap(),
+            encrypted
+        );
+    }
+
+    #[test]
+    fn other_keys_cannot_decrypt() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let auditor = AuditorKey::random(&mut rng);
+        let other = AuditorKey::random(&mut rng);
+        let bundle = DisclosureBundle {
+            transactions: vec![DisclosedTransaction::from(&send())],
+        };
+
+        let mut encrypted = bundle.encrypt(&auditor.public_key(), &mut rng);
+        assert_eq!(encrypted.decrypt(&other), Err(DisclosureError::Decryption));
+
+        encrypted.ciphertext[0] ^= 1;
+        assert_eq!(
+            encrypted.decrypt(&auditor),
+            Err(DisclosureError::Decryption)
+        );
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        assert_eq!(
+            EncryptedDisclosure::from_bytes(&[DISCLOSURE_VERSION; HEADER_LEN - 1]),
+            Err(DisclosureError::Truncated)
+        );
+        assert_eq!(
+            EncryptedDisclosure::from_bytes(&[1; HEADER_LEN]),
+            Err(DisclosureError::UnsupportedVersion(1))
+        );
+    }
+}
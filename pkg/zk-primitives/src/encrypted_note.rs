@@ -0,0 +1,488 @@
+use std::{fmt, str::FromStr};
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use element::Element;
+use primitives::serde::{deserialize_base64, serialize_base64};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Sha3_256};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::{InputNote, Note, get_address_for_private_key};
+
+/// The version of the [`EncryptedNote`] format
+const ENCRYPTED_NOTE_VERSION: u8 = 1;
+
+pub(crate) const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+/// kind, contract, address, psi and value
+const PLAINTEXT_LEN: usize = 5 * 32;
+
+/// Length of an encoded [`EncryptedNote`]
+pub const ENCRYPTED_NOTE_LEN: usize = 1 + 32 + NONCE_LEN + PLAINTEXT_LEN + TAG_LEN;
+
+const VIEWING_KEY_DOMAIN: &[u8] = b"payy-viewing-key-v1";
+const ENCRYPTION_KEY_DOMAIN: &[u8] = b"payy-note-encryption-v1";
+
+/// Errors from decoding or decrypting an [`EncryptedNote`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EncryptedNoteError {
+    /// The encoded note is not [`ENCRYPTED_NOTE_LEN`] bytes
+    #[error("encrypted note must be {ENCRYPTED_NOTE_LEN} bytes, got {0}")]
+    InvalidLength(usize),
+
+    /// The encoded note has a version this version of the library can't read
+    #[error("unsupported encrypted note version {0}")]
+    UnsupportedVersion(u8),
+
+    /// The note was not encrypted for this viewing key, was encrypted for a different
+    /// commitment, or was modified
+    #[error("failed to decrypt note")]
+    Decryption,
+
+    /// The decrypted note doesn't have the commitment it was posted under
+    #[error("decrypted note does not match commitment {0}")]
+    CommitmentMismatch(Element),
+
+    /// The note isn't sent to the address of the secret key
+    #[error("decrypted note is not owned by the secret key")]
+    NotOwned,
+}
+
+/// The secret half of a viewing key pair, used to decrypt notes sent to its
+/// [`ViewingPublicKey`]
+///
+/// Viewing keys can only decrypt notes, spending them still needs the note's secret key
+#[derive(Clone)]
+pub struct ViewingKey(StaticSecret);
+
+impl fmt::Debug for ViewingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ViewingKey").field(&"..").finish()
+    }
+}
+
+impl ViewingKey {
+    /// Derive the viewing key for a secret key, so it doesn't need to be backed up separately
+    #[must_use]
+    pub fn from_secret_key(secret_key: Element) -> Self {
+        let mut hasher = Sha3_256::new();
+        hasher.update(VIEWING_KEY_DOMAIN);
+        hasher.update(secret_key.to_be_bytes());
+
+        let bytes: [u8; 32] = hasher.finalize().into();
+        Self(StaticSecret::from(bytes))
+    }
+
+    /// Create a viewing key from its secret bytes, as returned by [`ViewingKey::to_bytes`]
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(StaticSecret::from(bytes))
+    }
+
+    /// The secret bytes of the viewing key, for backing up keys that aren't derived from a
+    /// secret key
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// Generate a random viewing key
+    #[must_use]
+    pub fn random(rng: impl RngCore + CryptoRng) -> Self {
+        Self(StaticSecret::random_from_rng(rng))
+    }
+
+    /// The public key that senders encrypt notes to
+    #[must_use]
+    pub fn public_key(&self) -> ViewingPublicKey {
+        ViewingPublicKey(PublicKey::from(&self.0).to_bytes())
+    }
+}
+
+/// The public half of a viewing key pair, published by a recipient so notes can be sent to them
+/// while they are offline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ViewingPublicKey(pub [u8; 32]);
+
+impl fmt::Display for ViewingPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl FromStr for ViewingPublicKey {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0; 32];
+        hex::decode_to_slice(s.trim_start_matches("0x"), &mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for ViewingPublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ViewingPublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A [`Note`] encrypted for the holder of a [`ViewingKey`]
+///
+/// The note is encrypted with AES-256-GCM, using a key derived from an X25519 key exchange
+/// between a fresh ephemeral key and the recipient's [`ViewingPublicKey`]. The commitment of
+/// the note is authenticated as associated data, so an encrypted note can only be decrypted
+/// under the commitment it was created for.
+///
+/// Encoded as [`ENCRYPTED_NOTE_LEN`] bytes:
+///  - version (1 byte)
+///  - ephemeral public key (32 bytes)
+///  - nonce (12 bytes)
+///  - ciphertext of the note fields, with the AEAD tag (176 bytes)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedNote {
+    /// The sender's ephemeral public key
+    pub ephemeral_public_key: ViewingPublicKey,
+    /// The AEAD nonce
+    pub nonce: [u8; NONCE_LEN],
+    /// The encrypted note, including the AEAD tag
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedNote {
+    /// Encrypt `note` for `recipient`
+    #[must_use]
+    pub fn encrypt(
+        note: &Note,
+        recipient: &ViewingPublicKey,
+        rng: impl RngCore + CryptoRng,
+    ) -> Self {
+        let (ephemeral_public_key, nonce, ciphertext) = seal(
+            ENCRYPTION_KEY_DOMAIN,
+            &note_to_bytes(note),
+            &note.commitment().to_be_bytes(),
+            recipient,
+            rng,
+        );
+
+        Self {
+            ephemeral_public_key,
+            nonce,
+            ciphertext,
+        }
+    }
+
+    /// Decrypt the note, which was posted under `commitment`
+    pub fn decrypt(
+        &self,
+        viewing_key: &ViewingKey,
+        commitment: Element,
+    ) -> Result<Note, EncryptedNoteError> {
+        let plaintext = open(
+            ENCRYPTION_KEY_DOMAIN,
+            viewing_key,
+            &self.ephemeral_public_key,
+            &self.nonce,
+            &self.ciphertext,
+            &commitment.to_be_bytes(),
+        )
+        .ok_or(EncryptedNoteError::Decryption)?;
+
+        let plaintext = <[u8; PLAINTEXT_LEN]>::try_from(plaintext)
+            .map_err(|_| EncryptedNoteError::Decryption)?;
+        let note = note_from_bytes(&plaintext);
+
+        if note.commitment() != commitment {
+            return Err(EncryptedNoteError::CommitmentMismatch(commitment));
+        }
+
+        Ok(note)
+    }
+
+    /// Decrypt the note and pair it with `secret_key`, so it can be spent
+    pub fn decrypt_input_note(
+        &self,
+        viewing_key: &ViewingKey,
+        commitment: Element,
+        secret_key: Element,
+    ) -> Result<InputNote, EncryptedNoteError> {
+        let note = self.decrypt(viewing_key, commitment)?;
+
+        if note.address != get_address_for_private_key(secret_key) {
+            return Err(EncryptedNoteError::NotOwned);
+        }
+
+        Ok(InputNote::new(note, secret_key))
+    }
+
+    /// Encode the encrypted note as [`ENCRYPTED_NOTE_LEN`] bytes
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ENCRYPTED_NOTE_LEN);
+        bytes.push(ENCRYPTED_NOTE_VERSION);
+        bytes.extend_from_slice(&self.ephemeral_public_key.0);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// Decode an encrypted note written by [`EncryptedNote::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EncryptedNoteError> {
+        if bytes.len() != ENCRYPTED_NOTE_LEN {
+            return Err(EncryptedNoteError::InvalidLength(bytes.len()));
+        }
+
+        let (&version, rest) = bytes.split_first().expect("length was checked");
+        if version != ENCRYPTED_NOTE_VERSION {
+            return Err(EncryptedNoteError::UnsupportedVersion(version));
+        }
+
+        let (ephemeral_public_key, rest) = rest.split_at(32);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        Ok(Self {
+            ephemeral_public_key: ViewingPublicKey(
+                ephemeral_public_key.try_into().expect("length was checked"),
+            ),
+            nonce: nonce.try_into().expect("length was checked"),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+impl Serialize for EncryptedNote {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_base64(&self.to_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EncryptedNote {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_base64(deserializer)?;
+        Self::from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Encrypt `plaintext` for `recipient` with a fresh ephemeral key, returning the ephemeral
+/// public key, nonce and ciphertext
+pub(crate) fn seal(
+    domain: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+    recipient: &ViewingPublicKey,
+    mut rng: impl RngCore + CryptoRng,
+) -> (ViewingPublicKey, [u8; NONCE_LEN], Vec<u8>) {
+    let ephemeral_secret = StaticSecret::random_from_rng(&mut rng);
+    let ephemeral_public_key = ViewingPublicKey(PublicKey::from(&ephemeral_secret).to_bytes());
+    let shared_secret = ephemeral_secret.diffie_hellman(&PublicKey::from(recipient.0));
+
+    let mut nonce = [0; NONCE_LEN];
+    rng.fill_bytes(&mut nonce);
+
+    let cipher = cipher(
+        domain,
+        shared_secret.as_bytes(),
+        &ephemeral_public_key,
+        recipient,
+    );
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("encrypting an in-memory plaintext can't fail");
+
+    (ephemeral_public_key, nonce, ciphertext)
+}
+
+/// Decrypt a ciphertext created by [`seal`], or `None` if it wasn't sealed for `viewing_key`
+/// with the same `domain` and `aad`
+pub(crate) fn open(
+    domain: &[u8],
+    viewing_key: &ViewingKey,
+    ephemeral_public_key: &ViewingPublicKey,
+    nonce: &[u8; NONCE_LEN],
+    ciphertext: &[u8],
+    aad: &[u8],
+) -> Option<Vec<u8>> {
+    let shared_secret = viewing_key
+        .0
+        .diffie_hellman(&PublicKey::from(ephemeral_public_key.0));
+
+    let cipher = cipher(
+        domain,
+        shared_secret.as_bytes(),
+        ephemeral_public_key,
+        &viewing_key.public_key(),
+    );
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .ok()
+}
+
+fn cipher(
+    domain: &[u8],
+    shared_secret: &[u8; 32],
+    ephemeral_public_key: &ViewingPublicKey,
+    recipient: &ViewingPublicKey,
+) -> Aes256Gcm {
+    let mut hasher = Sha3_256::new();
+    hasher.update(domain);
+    hasher.update(shared_secret);
+    hasher.update(ephemeral_public_key.0);
+    hasher.update(recipient.0);
+
+    Aes256Gcm::new(&hasher.finalize())
+}
+
+fn note_to_bytes(note: &Note) -> [u8; PLAINTEXT_LEN] {
+    let mut bytes = [0; PLAINTEXT_LEN];
+    let fields = [note.kind, note.contract, note.address, note.psi, note.value];
+
+    for (chunk, field) in bytes.chunks_exact_mut(32).zip(fields) {
+        chunk.copy_from_slice(&field.to_be_bytes());
+    }
+
+    bytes
+}
+
+fn note_from_bytes(bytes: &[u8; PLAINTEXT_LEN]) -> Note {
+    let mut fields = bytes
+        .chunks_exact(32)
+        .map(|chunk| Element::from_be_bytes(chunk.try_into().expect("chunks are 32 bytes")));
+    let mut next = || fields.next().expect("plaintext has 5 fields");
+
+    Note {
+        kind: next(),
+        contract: next(),
+        address: next(),
+        psi: next(),
+        value: next(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+    use test_strategy::proptest;
+
+    use super::*;
+    use crate::bridged_polygon_usdc_note_kind;
+
+    fn note(secret_key: Element, value: u64) -> Note {
+        Note::new(
+            get_address_for_private_key(secret_key),
+            Element::new(value),
+            bridged_polygon_usdc_note_kind(),
+        )
+    }
+
+    #[proptest(cases = 32)]
+    fn encrypt_decrypt_round_trip(secret_key: Element, value: u64, seed: u64) {
+        let viewing_key = ViewingKey::from_secret_key(secret_key);
+        let note = note(secret_key, value.max(1));
+        let commitment = note.commitment();
+
+        let encrypted = EncryptedNote::encrypt(
+            &note,
+            &viewing_key.public_key(),
+            StdRng::seed_from_u64(seed),
+        );
+        let decoded = EncryptedNote::from_bytes(&encrypted.to_bytes()).unwrap();
+
+        assert_eq!(decoded, encrypted);
+        assert_eq!(decoded.decrypt(&viewing_key, commitment).unwrap(), note);
+
+        let input_note = decoded
+            .decrypt_input_note(&viewing_key, commitment, secret_key)
+            .unwrap();
+        assert_eq!(input_note.secret_key, secret_key);
+    }
+
+    #[test]
+    fn only_recipient_can_decrypt() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let recipient = ViewingKey::from_secret_key(Element::new(1));
+        let other = ViewingKey::random(&mut rng);
+        let note = note(Element::new(1), 10);
+
+        let encrypted = EncryptedNote::encrypt(&note, &recipient.public_key(), &mut rng);
+
+        assert_eq!(
+            encrypted.decrypt(&other, note.commitment()),
+            Err(EncryptedNoteError::Decryption)
+        );
+        assert_eq!(
+            encrypted.decrypt(&recipient, Element::new(5)),
+            Err(EncryptedNoteError::Decryption)
+        );
+        assert_eq!(
+            encrypted.decrypt_input_note(&recipient, note.commitment(), Element::new(2)),
+            Err(EncryptedNoteError::NotOwned)
+        );
+    }
+
+    #[test]
+    fn modified_ciphertext_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let viewing_key = ViewingKey::random(&mut rng);
+        let note = note(Element::new(1), 10);
+        let bytes = EncryptedNote::encrypt(&note, &viewing_key.public_key(), &mut rng).to_bytes();
+
+        assert_eq!(bytes.len(), ENCRYPTED_NOTE_LEN);
+
+        for i in 1..bytes.len() {
+            let mut modified = bytes.clone();
+            modified[i] ^= 1;
+
+            let encrypted = EncryptedNote::from_bytes(&modified).unwrap();
+            assert!(encrypted.decrypt(&viewing_key, note.commitment()).is_err());
+        }
+
+        assert_eq!(
+            EncryptedNote::from_bytes(&bytes[1..]),
+            Err(EncryptedNoteError::InvalidLength(ENCRYPTED_NOTE_LEN - 1))
+        );
+
+        let mut wrong_version = bytes;
+        wrong_version[0] = 2;
+        assert_eq!(
+            EncryptedNote::from_bytes(&wrong_version),
+            Err(EncryptedNoteError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let viewing_key = ViewingKey::random(&mut rng);
+        let public_key = viewing_key.public_key();
+        let encrypted = EncryptedNote::encrypt(&note(Element::new(1), 10), &public_key, &mut rng);
+
+        let json = serde_json::to_string(&(public_key, &encrypted)).unwrap();
+        let (public_key_again, encrypted_again): (ViewingPublicKey, EncryptedNote) =
+            serde_json::from_str(&json).unwrap();
+
+        assert_eq!(public_key_again, public_key);
+        assert_eq!(encrypted_again, encrypted);
+    }
+}
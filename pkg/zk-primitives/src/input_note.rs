@@ -1,7 +1,14 @@
-use crate::{NoteURLPayload, decode_activity_url_payload, note::Note};
+use crate::{
+    NoteURLError, NoteURLPayload, bridged_polygon_usdc_note_kind, decode_activity_url_payload,
+    note::Note,
+};
 use element::Element;
 use serde::{Deserialize, Serialize};
 
+/// Domain separator for nullifiers, so a nullifier never equals the address or commitment
+/// hashed from the same values
+const NULLIFIER_DOMAIN: u64 = 3;
+
 /// InputNote is a Note that belongs to the current user, i.e. they have the
 /// spending sercret key and can therefore use it as an input, "spending" the note. Extra
 /// constraints need to be applied to input notes to ensure they are valid.
@@ -20,6 +27,27 @@ impl InputNote {
         Self { note, secret_key }
     }
 
+    /// The nullifier revealed when the note is spent, derived from the secret key, psi and the
+    /// note's commitment, or zero for padding notes
+    ///
+    /// Only the holder of the secret key can compute it, so publishing it marks the note as
+    /// spent without revealing which commitment it belongs to. The commitment is bound so notes
+    /// sent to the same key with the same psi still have distinct nullifiers. Matches
+    /// `get_nullifier` in the circuits' `common` library.
+    #[must_use]
+    pub fn nullifier(&self) -> Element {
+        if self.note.value == Element::ZERO {
+            Element::ZERO
+        } else {
+            hash::hash_merge([
+                Element::new(NULLIFIER_DOMAIN),
+                self.secret_key,
+                self.note.psi,
+                self.note.commitment(),
+            ])
+        }
+    }
+
     /// Create a new padding note
     #[must_use]
     pub fn padding_note() -> Self {
@@ -29,12 +57,16 @@ impl InputNote {
         }
     }
 
-    /// Generates a new note with given value, for an ephemeral private key, the private key
-    /// must only be used once
+    /// Generates a new note of `note_kind` with given value, for an ephemeral private key, the
+    /// private key must only be used once
     #[must_use]
-    pub fn new_from_ephemeral_private_key(private_key: Element, value: Element) -> Self {
+    pub fn new_from_ephemeral_private_key(
+        private_key: Element,
+        value: Element,
+        note_kind: Element,
+    ) -> Self {
         Self {
-            note: Note::new_from_ephemeral_private_key(private_key, value),
+            note: Note::new_from_ephemeral_private_key(private_key, value, note_kind),
             secret_key: private_key,
         }
     }
@@ -45,10 +77,60 @@ impl InputNote {
         InputNote::from(&decode_activity_url_payload(link))
     }
 
+    /// Generates an InputNote from a link string e.g. /s#A0F3..., returning an error if the link
+    /// is invalid
+    pub fn try_from_link(link: &str) -> Result<Self, NoteURLError> {
+        Ok(InputNote::from(&NoteURLPayload::decode(link)?))
+    }
+
     /// Generates a Payy link from the Note + Private Key
+    ///
+    /// Links to USDC on Polygon notes use [`NOTE_URL_VERSION`](crate::NOTE_URL_VERSION), which
+    /// every client can read, other note kinds need
+    /// [`LATEST_NOTE_URL_VERSION`](crate::LATEST_NOTE_URL_VERSION)
     #[must_use]
     pub fn generate_link(&self) -> String {
-        let payload: NoteURLPayload = self.into();
-        payload.encode_activity_url_payload()
+        let mut payload = NoteURLPayload::from(self);
+        if self.note.contract != bridged_polygon_usdc_note_kind() {
+            payload = payload.with_latest_version();
+        }
+
+        payload
+            .encode_activity_url_payload()
+            .expect("payloads of notes have no memo or expiry")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bridged_polygon_usdc_note_kind, get_address_for_private_key};
+
+    #[test]
+    fn nullifiers() {
+        let secret_key = Element::new(101);
+        let address = get_address_for_private_key(secret_key);
+        let kind = bridged_polygon_usdc_note_kind();
+
+        let note = InputNote::new(
+            Note::new_with_psi(address, Element::new(10), Element::new(1), kind),
+            secret_key,
+        );
+        let other_psi = InputNote::new(
+            Note::new_with_psi(address, Element::new(10), Element::new(2), kind),
+            secret_key,
+        );
+        // Same key and psi, e.g. two notes from links with the same key
+        let same_psi = InputNote::new(
+            Note::new_with_psi(address, Element::new(20), Element::new(1), kind),
+            secret_key,
+        );
+
+        assert_ne!(note.nullifier(), Element::ZERO);
+        assert_ne!(note.nullifier(), other_psi.nullifier());
+        assert_ne!(note.nullifier(), same_psi.nullifier());
+        assert_ne!(note.nullifier(), note.note.commitment());
+        assert_ne!(note.nullifier(), address);
+        assert_eq!(InputNote::padding_note().nullifier(), Element::ZERO);
     }
 }
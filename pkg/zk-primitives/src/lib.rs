@@ -13,28 +13,42 @@ mod address;
 mod agg_agg;
 mod agg_utxo;
 mod burn;
+mod disclosure;
+mod encrypted_note;
 mod input_note;
 mod merkle_path;
 mod migrate;
 mod note;
+mod note_kind;
 mod note_url;
 mod points;
 mod signature;
+mod swap;
 mod traits;
+mod txn_proof;
 mod util;
 mod utxo;
+mod utxo4;
+mod utxo_v1;
 
 pub use address::*;
 pub use agg_agg::*;
 pub use agg_utxo::*;
 pub use burn::*;
+pub use disclosure::*;
+pub use encrypted_note::*;
 pub use input_note::*;
 pub use merkle_path::*;
 pub use migrate::*;
 pub use note::*;
+pub use note_kind::*;
 pub use note_url::*;
 pub use points::*;
 pub use signature::*;
+pub use swap::*;
 pub use traits::*;
+pub use txn_proof::*;
 pub use util::*;
 pub use utxo::*;
+pub use utxo_v1::*;
+pub use utxo4::*;
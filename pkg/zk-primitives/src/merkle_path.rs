@@ -1,9 +1,10 @@
+use borsh::{BorshDeserialize, BorshSerialize};
 use element::Base;
 use element::Element;
 use serde::{Deserialize, Serialize};
 
 /// The siblings of a merkle path, for a `smirk::Tree` of depth `DEPTH`
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct MerklePath<const DEPTH: usize> {
     /// The siblings that form the merkle path
     pub siblings: Vec<Element>,
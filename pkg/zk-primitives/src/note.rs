@@ -1,6 +1,4 @@
-use crate::{
-    bridged_polygon_usdc_note_kind, get_address_for_private_key, hash_private_key_for_psi,
-};
+use crate::{get_address_for_private_key, hash_private_key_for_psi};
 use element::Element;
 use noirc_abi::input_parser::InputValue;
 use rand::thread_rng;
@@ -29,38 +27,47 @@ pub struct Note {
 }
 
 impl Note {
-    /// Create a new note
+    /// Create a new note of `note_kind` (see [`NoteKind`][crate::NoteKind])
     #[must_use]
-    pub fn new(address: Element, value: Element) -> Self {
+    pub fn new(address: Element, value: Element, note_kind: Element) -> Self {
         Self {
             kind: Element::new(2),
-            contract: bridged_polygon_usdc_note_kind(),
+            contract: note_kind,
             address,
             psi: Element::secure_random(thread_rng()),
             value,
         }
     }
 
-    /// Create a new note with custom PSI
+    /// Create a new note of `note_kind` with custom PSI
     #[must_use]
-    pub fn new_with_psi(address: Element, value: Element, psi: Element) -> Self {
+    pub fn new_with_psi(
+        address: Element,
+        value: Element,
+        psi: Element,
+        note_kind: Element,
+    ) -> Self {
         Self {
             kind: Element::new(2),
-            contract: bridged_polygon_usdc_note_kind(),
+            contract: note_kind,
             address,
             psi,
             value,
         }
     }
 
-    /// New note from ephemeral private key (only use private key once)
+    /// New note of `note_kind` from ephemeral private key (only use private key once)
     #[must_use]
-    pub fn new_from_ephemeral_private_key(private_key: Element, value: Element) -> Self {
+    pub fn new_from_ephemeral_private_key(
+        private_key: Element,
+        value: Element,
+        note_kind: Element,
+    ) -> Self {
         let address = get_address_for_private_key(private_key);
         let psi = hash_private_key_for_psi(private_key);
         Self {
             kind: Element::new(2),
-            contract: bridged_polygon_usdc_note_kind(),
+            contract: note_kind,
             address,
             psi,
             value,
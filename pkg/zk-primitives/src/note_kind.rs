@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use element::Element;
+use serde::{Deserialize, Serialize};
+use web3::types::H160;
+
+use crate::generate_note_kind_bridge_evm;
+
+/// Metadata for a token that notes can hold
+///
+/// The `kind` is the value stored in [`Note::contract`][crate::Note::contract], and is derived
+/// from the chain and contract address of the bridged token with
+/// [`generate_note_kind_bridge_evm`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteKind {
+    /// The note kind element
+    pub kind: Element,
+    /// The chain ID of the EVM chain the token is bridged from
+    pub chain: u64,
+    /// The address of the token contract on `chain`
+    pub address: H160,
+    /// The token symbol, e.g. `USDC`
+    pub symbol: String,
+    /// The number of decimal places of the token, i.e. a note with value `10^decimals` holds
+    /// one token
+    pub decimals: u8,
+}
+
+impl NoteKind {
+    /// Metadata for a token bridged from an EVM chain
+    #[must_use]
+    pub fn bridge_evm(chain: u64, address: H160, symbol: impl Into<String>, decimals: u8) -> Self {
+        Self {
+            kind: generate_note_kind_bridge_evm(chain, address),
+            chain,
+            address,
+            symbol: symbol.into(),
+            decimals,
+        }
+    }
+
+    /// USDC on Polygon, the kind of [`bridged_polygon_usdc_note_kind`][crate::bridged_polygon_usdc_note_kind]
+    #[must_use]
+    pub fn polygon_usdc() -> Self {
+        let address =
+            H160::from_slice(&hex::decode("3c499c542cef5e3811e1192ce70d8cc03d5c3359").unwrap());
+
+        Self::bridge_evm(137, address, "USDC", 6)
+    }
+
+    /// Format a note value of this kind as a human-readable amount, e.g. `1.5 USDC`
+    #[must_use]
+    pub fn format_amount(&self, value: Element) -> String {
+        let decimals = usize::from(self.decimals);
+        let digits = format!(
+            "{:0>width$}",
+            value.to_u256().to_string(),
+            width = decimals + 1
+        );
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+
+        match fraction.is_empty() {
+            true => format!("{whole} {}", self.symbol),
+            false => format!("{whole}.{fraction} {}", self.symbol),
+        }
+    }
+}
+
+/// The set of note kinds known to a client or node
+///
+/// The default registry only contains [`NoteKind::polygon_usdc`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteKindRegistry {
+    kinds: BTreeMap<Element, NoteKind>,
+}
+
+impl Default for NoteKindRegistry {
+    fn default() -> Self {
+        Self::from_iter([NoteKind::polygon_usdc()])
+    }
+}
+
+impl FromIterator<NoteKind> for NoteKindRegistry {
+    fn from_iter<T: IntoIterator<Item = NoteKind>>(iter: T) -> Self {
+        let mut registry = Self::empty();
+        for kind in iter {
+            registry.register(kind);
+        }
+        registry
+    }
+}
+
+impl NoteKindRegistry {
+    /// A registry with no note kinds
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            kinds: BTreeMap::new(),
+        }
+    }
+
+    /// Add a note kind, returning the metadata it replaced if the kind was already registered
+    pub fn register(&mut self, kind: NoteKind) -> Option<NoteKind> {
+        self.kinds.insert(kind.kind, kind)
+    }
+
+    /// The metadata for `kind`
+    #[must_use]
+    pub fn get(&self, kind: Element) -> Option<&NoteKind> {
+        self.kinds.get(&kind)
+    }
+
+    /// Whether `kind` is registered
+    #[must_use]
+    pub fn contains(&self, kind: Element) -> bool {
+        self.kinds.contains_key(&kind)
+    }
+
+    /// The registered note kinds, in order of kind
+    pub fn iter(&self) -> impl Iterator<Item = &NoteKind> {
+        self.kinds.values()
+    }
+
+    /// Format a note value as a human-readable amount, or `None` if `kind` isn't registered
+    #[must_use]
+    pub fn format_amount(&self, kind: Element, value: Element) -> Option<String> {
+        self.get(kind).map(|kind| kind.format_amount(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridged_polygon_usdc_note_kind;
+
+    #[test]
+    fn polygon_usdc_matches_helper() {
+        assert_eq!(
+            NoteKind::polygon_usdc().kind,
+            bridged_polygon_usdc_note_kind()
+        );
+        assert!(NoteKindRegistry::default().contains(bridged_polygon_usdc_note_kind()));
+        assert!(!NoteKindRegistry::empty().contains(bridged_polygon_usdc_note_kind()));
+    }
+
+    #[test]
+    fn format_amount() {
+        let usdc = NoteKind::polygon_usdc();
+
+        assert_eq!(usdc.format_amount(Element::ZERO), "0 USDC");
+        assert_eq!(usdc.format_amount(Element::new(1)), "0.000001 USDC");
+        assert_eq!(usdc.format_amount(Element::new(1_500_000)), "1.5 USDC");
+        assert_eq!(usdc.format_amount(Element::new(42_000_000)), "42 USDC");
+
+        let whole = NoteKind::bridge_evm(1, H160::zero(), "NFT", 0);
+        assert_eq!(whole.format_amount(Element::new(7)), "7 NFT");
+    }
+
+    #[test]
+    fn register_and_look_up() {
+        let weth = NoteKind::bridge_evm(1, H160::repeat_byte(1), "WETH", 18);
+        let mut registry = NoteKindRegistry::default();
+
+        assert!(registry.register(weth.clone()).is_none());
+        assert_eq!(registry.get(weth.kind), Some(&weth));
+        assert_eq!(registry.iter().count(), 2);
+        assert_eq!(
+            registry.format_amount(weth.kind, Element::new(10u64.pow(18))),
+            Some("1 WETH".to_owned())
+        );
+        assert_eq!(registry.format_amount(Element::ONE, Element::ONE), None);
+    }
+}
@@ -1,5 +1,6 @@
 use element::Element;
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
 use std::str::FromStr;
 use web3::signing::keccak256;
 
@@ -8,15 +9,70 @@ use crate::{
     hash_private_key_for_psi,
 };
 
+/// The version of note URL payloads created from notes, which every client can read
+pub const NOTE_URL_VERSION: u8 = 2;
+
+/// The latest version of the note URL payload, which adds the note kind, memo, expiry and a
+/// checksum. Older clients can't read it, so it has to be opted into with
+/// [`NoteURLPayload::with_latest_version`]
+pub const LATEST_NOTE_URL_VERSION: u8 = 3;
+
+/// Maximum length of a memo in a note URL payload, in bytes
+pub const MAX_NOTE_URL_MEMO_LEN: usize = 64;
+
+/// Number of bytes of the SHA3-256 hash appended to version 3 payloads
+const CHECKSUM_LEN: usize = 4;
+
+const FLAG_EXPIRES_AT: u8 = 1 << 0;
+const FLAG_MEMO: u8 = 1 << 1;
+
+/// Errors from decoding a note URL payload
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NoteURLError {
+    /// The payload is not valid base58
+    #[error("invalid base58 in note url payload: {0}")]
+    InvalidBase58(#[from] bs58::decode::Error),
+
+    /// The payload ended before all of its fields were read
+    #[error("note url payload is truncated")]
+    Truncated,
+
+    /// The payload has a version this version of the library can't read
+    #[error("unsupported note url payload version {0}")]
+    UnsupportedVersion(u8),
+
+    /// The checksum doesn't match the rest of the payload, so the link was truncated or modified
+    #[error("note url payload checksum mismatch")]
+    ChecksumMismatch,
+
+    /// The value has more than 32 leading zero bytes
+    #[error("invalid value length in note url payload")]
+    InvalidValue,
+
+    /// The memo is longer than [`MAX_NOTE_URL_MEMO_LEN`]
+    #[error("note url memo is {0} bytes, the maximum is {MAX_NOTE_URL_MEMO_LEN}")]
+    MemoTooLong(usize),
+
+    /// The memo or referral code is not valid UTF-8
+    #[error("invalid utf-8 in note url payload")]
+    InvalidUtf8(#[from] std::string::FromUtf8Error),
+
+    /// The payload has a note kind other than USDC on Polygon, a memo or an expiry, which
+    /// version `{0}` can't encode
+    #[error("note url payload version {0} can't encode a note kind, memo or expiry")]
+    RequiresLatestVersion(u8),
+}
+
 /// NoteURLPayload is a struct that contains the data required to create a note URL
 ///
 /// These are used to send payments to a user e.g. `https://payy.link/s#<NoteURLPayload>`
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NoteURLPayload {
     /// The version of the note URL payload
     /// 0 -> old rollup note
     /// 1 -> old rollup note (derived psi)
     /// 2 -> new rollup (derived psi)
+    /// 3 -> new rollup (derived psi) with note kind, memo, expiry and checksum
     pub version: u8,
     /// The private key of the note
     pub private_key: Element,
@@ -27,6 +83,18 @@ pub struct NoteURLPayload {
     pub value: Element,
     /// The referral code of the note
     pub referral_code: String,
+    /// The note kind (i.e. [`Note::contract`]) of the note, only encoded by version 3. Older
+    /// versions are always USDC on Polygon
+    #[serde(default)]
+    pub note_kind: Option<Element>,
+    /// A short message for the recipient, at most [`MAX_NOTE_URL_MEMO_LEN`] bytes. Only encoded
+    /// by version 3
+    #[serde(default)]
+    pub memo: Option<String>,
+    /// Unix timestamp (in seconds) after which the sender may reclaim the note, so the recipient
+    /// should claim it before then. Only encoded by version 3
+    #[serde(default)]
+    pub expires_at: Option<u64>,
 }
 
 impl From<&NoteURLPayload> for InputNote {
@@ -38,7 +106,7 @@ impl From<&NoteURLPayload> for InputNote {
             secret_key: value.private_key,
             note: Note {
                 kind: Element::new(2),
-                contract: bridged_polygon_usdc_note_kind(),
+                contract: value.note_kind(),
                 address: get_address_for_private_key(value.private_key),
                 psi,
                 value: value.value,
@@ -51,16 +119,53 @@ impl From<&InputNote> for NoteURLPayload {
     fn from(input_note: &InputNote) -> Self {
         Self {
             // New notes use the new rollup version
-            version: 2,
+            version: NOTE_URL_VERSION,
             private_key: input_note.secret_key,
             psi: None,
             value: input_note.note.value,
             referral_code: String::new(),
+            note_kind: Some(input_note.note.contract),
+            memo: None,
+            expires_at: None,
         }
     }
 }
 
 impl NoteURLPayload {
+    /// Encode the payload as [`LATEST_NOTE_URL_VERSION`], so it can hold a note kind other than
+    /// USDC on Polygon, a memo and an expiry
+    #[must_use]
+    pub fn with_latest_version(mut self) -> Self {
+        self.version = LATEST_NOTE_URL_VERSION;
+        self
+    }
+
+    /// Set the memo of the payload, which is only encoded by [`LATEST_NOTE_URL_VERSION`]
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Result<Self, NoteURLError> {
+        let memo = memo.into();
+
+        if memo.len() > MAX_NOTE_URL_MEMO_LEN {
+            return Err(NoteURLError::MemoTooLong(memo.len()));
+        }
+
+        self.memo = Some(memo);
+        Ok(self)
+    }
+
+    /// Set the expiry hint of the payload, as a unix timestamp in seconds, which is only encoded
+    /// by [`LATEST_NOTE_URL_VERSION`]
+    #[must_use]
+    pub fn with_expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Whether the expiry hint has passed at `now` (a unix timestamp in seconds)
+    #[must_use]
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
     /// Gets the commitment for the note represented by the URL payload
     #[must_use]
     pub fn address(&self) -> Element {
@@ -73,6 +178,15 @@ impl NoteURLPayload {
         InputNote::from(self).note.commitment()
     }
 
+    /// Gets the note kind of the note represented by the URL payload
+    ///
+    /// Versions before 3 don't encode a note kind, and are always USDC on Polygon
+    #[must_use]
+    pub fn note_kind(&self) -> Element {
+        self.note_kind
+            .unwrap_or_else(bridged_polygon_usdc_note_kind)
+    }
+
     /// Gets the explicit or derived psi for the note url
     #[must_use]
     pub fn psi(&self) -> Element {
@@ -81,14 +195,25 @@ impl NoteURLPayload {
             1 => {
                 Element::from_str(&hex::encode(keccak256(&self.private_key.to_be_bytes()))).unwrap()
             }
-            2 => hash_private_key_for_psi(self.private_key),
-            _ => unreachable!("only version 1, 2 or 3 is supported"),
+            2 | 3 => hash_private_key_for_psi(self.private_key),
+            _ => unreachable!("only version 0, 1, 2 or 3 is supported"),
         }
     }
 
     /// Encode a note URL payload to a base58-encoded string
-    #[must_use]
-    pub fn encode_activity_url_payload(&self) -> String {
+    ///
+    /// The note kind, memo and expiry are only encoded by version 3, so older versions return
+    /// [`NoteURLError::RequiresLatestVersion`] if the payload has a note kind other than USDC on
+    /// Polygon, a memo or an expiry, rather than encoding a link to a different note
+    pub fn encode_activity_url_payload(&self) -> Result<String, NoteURLError> {
+        if self.version < 3
+            && (self.note_kind() != bridged_polygon_usdc_note_kind()
+                || self.memo.is_some()
+                || self.expires_at.is_some())
+        {
+            return Err(NoteURLError::RequiresLatestVersion(self.version));
+        }
+
         let mut bytes = Vec::new();
 
         // Encode version
@@ -104,6 +229,10 @@ impl NoteURLPayload {
             }
         }
 
+        if self.version >= 3 {
+            bytes.extend_from_slice(&self.note_kind().to_be_bytes());
+        }
+
         // Encode value with leading zeros
         let value_bytes = self.value.to_be_bytes();
         let leading_zeros = value_bytes.iter().take_while(|&&b| b == 0).count();
@@ -111,81 +240,413 @@ impl NoteURLPayload {
         bytes.push(leading_zeros as u8);
         bytes.extend_from_slice(&value_bytes[leading_zeros..]);
 
+        if self.version >= 3 {
+            self.encode_v3_fields(&mut bytes)?;
+        }
+
         // Encode referral_code as UTF-8
         bytes.extend_from_slice(self.referral_code.as_bytes());
 
+        if self.version >= 3 {
+            let checksum = checksum(&bytes);
+            bytes.extend_from_slice(&checksum);
+        }
+
         // Return Base58-encoded string
-        bs58::encode(bytes).into_string()
+        Ok(bs58::encode(bytes).into_string())
     }
-}
 
-/// Decode a note URL payload from a base58-encoded string
-#[must_use]
-pub fn decode_activity_url_payload(payload: &str) -> NoteURLPayload {
-    let payload_bytes = bs58::decode(payload)
-        .into_vec()
-        .expect("Failed to decode base58 payload");
+    fn encode_v3_fields(&self, bytes: &mut Vec<u8>) -> Result<(), NoteURLError> {
+        let mut flags = 0;
+        if self.expires_at.is_some() {
+            flags |= FLAG_EXPIRES_AT;
+        }
+        if self.memo.is_some() {
+            flags |= FLAG_MEMO;
+        }
+        bytes.push(flags);
+
+        if let Some(expires_at) = self.expires_at {
+            bytes.extend_from_slice(&expires_at.to_be_bytes());
+        }
+
+        if let Some(memo) = &self.memo {
+            if memo.len() > MAX_NOTE_URL_MEMO_LEN {
+                return Err(NoteURLError::MemoTooLong(memo.len()));
+            }
 
-    let mut rest = &payload_bytes[..];
+            #[allow(clippy::cast_possible_truncation)]
+            bytes.push(memo.len() as u8);
+            bytes.extend_from_slice(memo.as_bytes());
+        }
+
+        Ok(())
+    }
+
+    /// Decode a note URL payload from a base58-encoded string
+    ///
+    /// Versions 0 to 3 can be decoded. The checksum of version 3 payloads is verified, so
+    /// truncated or mistyped links are rejected rather than decoding to the wrong note
+    pub fn decode(payload: &str) -> Result<Self, NoteURLError> {
+        let payload_bytes = bs58::decode(payload).into_vec()?;
+
+        let (&version, _) = payload_bytes.split_first().ok_or(NoteURLError::Truncated)?;
+
+        let body = match version {
+            0..=2 => &payload_bytes[1..],
+            3 => {
+                let split = payload_bytes
+                    .len()
+                    .checked_sub(CHECKSUM_LEN)
+                    .filter(|&split| split > 0)
+                    .ok_or(NoteURLError::Truncated)?;
+                let (data, expected) = payload_bytes.split_at(split);
+
+                if checksum(data) != expected {
+                    return Err(NoteURLError::ChecksumMismatch);
+                }
+
+                &data[1..]
+            }
+            version => return Err(NoteURLError::UnsupportedVersion(version)),
+        };
+
+        let mut reader = Reader(body);
+
+        let private_key_bytes = reader.take_array::<32>()?;
+        let private_key = Element::from_be_bytes(private_key_bytes);
+
+        let psi = match version {
+            0 => Some(Element::from_be_bytes(reader.take_array::<32>()?)),
+            1 => Some(Element::from_str(&hex::encode(keccak256(&private_key_bytes))).unwrap()),
+            _ => Some(hash_private_key_for_psi(private_key)),
+        };
+
+        let note_kind = match version {
+            3 => Some(Element::from_be_bytes(reader.take_array::<32>()?)),
+            _ => None,
+        };
+
+        let leading_zeros = usize::from(reader.take_u8()?);
+        let value_len = 32usize
+            .checked_sub(leading_zeros)
+            .ok_or(NoteURLError::InvalidValue)?;
+
+        let mut value_bytes = [0u8; 32];
+        value_bytes[leading_zeros..].copy_from_slice(reader.take(value_len)?);
+        let value = Element::from_be_bytes(value_bytes);
+
+        let (expires_at, memo) = match version {
+            3 => reader.take_v3_fields()?,
+            _ => (None, None),
+        };
+
+        let referral_code = String::from_utf8(reader.0.to_vec())?;
+
+        Ok(NoteURLPayload {
+            version,
+            private_key,
+            psi,
+            value,
+            referral_code,
+            note_kind,
+            memo,
+            expires_at,
+        })
+    }
+}
 
-    let version = rest[0];
-    rest = &rest[1..];
+/// The first [`CHECKSUM_LEN`] bytes of the SHA3-256 hash of `bytes`
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let hash = Sha3_256::digest(bytes);
+    let mut checksum = [0; CHECKSUM_LEN];
+    checksum.copy_from_slice(&hash[..CHECKSUM_LEN]);
+    checksum
+}
 
-    let private_key_bytes: [u8; 32] = rest[..32]
-        .try_into()
-        .expect("Not enough bytes for private_key");
-    let private_key = Element::from_be_bytes(private_key_bytes);
-    rest = &rest[32..];
+/// Reads fields from the front of a payload
+struct Reader<'a>(&'a [u8]);
 
-    let psi = match version {
-        0 => {
-            let psi_bytes: [u8; 32] = rest[..32].try_into().expect("Not enough bytes for psi");
-            rest = &rest[32..];
-            Some(Element::from_be_bytes(psi_bytes))
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NoteURLError> {
+        if self.0.len() < len {
+            return Err(NoteURLError::Truncated);
         }
-        1 => Some(Element::from_str(&hex::encode(keccak256(&private_key_bytes))).unwrap()),
-        2 => Some(hash_private_key_for_psi(private_key)),
-        _ => unreachable!("only version 1, 2 or 3 is supported"),
-    };
 
-    let leading_zeros = rest[0] as usize;
-    rest = &rest[1..];
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], NoteURLError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn take_u8(&mut self) -> Result<u8, NoteURLError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take_v3_fields(&mut self) -> Result<(Option<u64>, Option<String>), NoteURLError> {
+        let flags = self.take_u8()?;
 
-    let value_len = 32 - leading_zeros;
-    let value_without_leading_zeros = &rest[..value_len];
-    rest = &rest[value_len..];
+        let expires_at = match flags & FLAG_EXPIRES_AT != 0 {
+            true => Some(u64::from_be_bytes(self.take_array::<8>()?)),
+            false => None,
+        };
 
-    let mut value_bytes = [0u8; 32];
-    value_bytes[leading_zeros..].copy_from_slice(value_without_leading_zeros);
-    let value = Element::from_be_bytes(value_bytes);
+        let memo = match flags & FLAG_MEMO != 0 {
+            true => {
+                let len = usize::from(self.take_u8()?);
 
-    let referral_code = String::from_utf8(rest.to_vec()).expect("Invalid UTF-8 in referral code");
+                if len > MAX_NOTE_URL_MEMO_LEN {
+                    return Err(NoteURLError::MemoTooLong(len));
+                }
 
-    NoteURLPayload {
-        version,
-        private_key,
-        psi,
-        value,
-        referral_code,
+                Some(String::from_utf8(self.take(len)?.to_vec())?)
+            }
+            false => None,
+        };
+
+        Ok((expires_at, memo))
     }
 }
 
+/// Decode a note URL payload from a base58-encoded string
+///
+/// # Panics
+///
+/// Panics if the payload is invalid, use [`NoteURLPayload::decode`] to handle invalid payloads
+#[must_use]
+pub fn decode_activity_url_payload(payload: &str) -> NoteURLPayload {
+    NoteURLPayload::decode(payload).expect("Failed to decode note url payload")
+}
+
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+    use test_strategy::proptest;
+
     use super::*;
     use crate::InputNote;
 
+    fn payload(version: u8) -> NoteURLPayload {
+        NoteURLPayload {
+            version,
+            private_key: Element::new(101),
+            psi: (version == 0).then(|| Element::new(7)),
+            value: Element::new(1_000_000),
+            referral_code: "ref".to_owned(),
+            note_kind: None,
+            memo: None,
+            expires_at: None,
+        }
+    }
+
+    fn round_trip(payload: &NoteURLPayload) -> NoteURLPayload {
+        NoteURLPayload::decode(&payload.encode_activity_url_payload().unwrap()).unwrap()
+    }
+
+    #[proptest]
+    fn v3_round_trip(
+        private_key: Element,
+        note_kind: Element,
+        value: Element,
+        #[strategy(proptest::option::of("[a-zA-Z0-9 ]{0,64}"))] memo: Option<String>,
+        expires_at: Option<u64>,
+        #[strategy("[a-zA-Z0-9]{0,16}")] referral_code: String,
+    ) {
+        let payload = NoteURLPayload {
+            version: 3,
+            private_key,
+            psi: Some(hash_private_key_for_psi(private_key)),
+            value,
+            referral_code,
+            note_kind: Some(note_kind),
+            memo,
+            expires_at,
+        };
+
+        prop_assert_eq!(round_trip(&payload), payload);
+    }
+
+    #[proptest]
+    fn legacy_round_trip(
+        #[strategy(0u8..=2)] version: u8,
+        private_key: Element,
+        psi: Element,
+        value: Element,
+        #[strategy("[a-zA-Z0-9]{0,16}")] referral_code: String,
+    ) {
+        let payload = NoteURLPayload {
+            version,
+            private_key,
+            psi: Some(psi),
+            value,
+            referral_code,
+            note_kind: None,
+            memo: None,
+            expires_at: None,
+        };
+
+        let decoded = round_trip(&payload);
+
+        prop_assert_eq!(decoded.private_key, private_key);
+        prop_assert_eq!(decoded.value, value);
+        prop_assert_eq!(decoded.psi, Some(payload.psi()));
+        prop_assert_eq!(decoded.note_kind(), bridged_polygon_usdc_note_kind());
+        prop_assert_eq!(&decoded.referral_code, &payload.referral_code);
+    }
+
+    #[proptest(cases = 32)]
+    fn v3_truncated_or_modified_is_rejected(
+        note_kind: Element,
+        #[strategy("[a-z]{0,64}")] memo: String,
+    ) {
+        let mut payload = payload(3)
+            .with_memo(memo)
+            .unwrap()
+            .with_expires_at(1_700_000_000);
+        payload.note_kind = Some(note_kind);
+
+        let bytes = bs58::decode(payload.encode_activity_url_payload().unwrap())
+            .into_vec()
+            .unwrap();
+
+        for len in 0..bytes.len() {
+            let truncated = bs58::encode(&bytes[..len]).into_string();
+            prop_assert!(NoteURLPayload::decode(&truncated).is_err());
+        }
+
+        for i in 1..bytes.len() {
+            let mut modified = bytes.clone();
+            modified[i] ^= 1;
+            let modified = bs58::encode(modified).into_string();
+            prop_assert_eq!(
+                NoteURLPayload::decode(&modified),
+                Err(NoteURLError::ChecksumMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_payloads_return_errors() {
+        assert!(matches!(
+            NoteURLPayload::decode("0OIl"),
+            Err(NoteURLError::InvalidBase58(_))
+        ));
+        assert_eq!(NoteURLPayload::decode(""), Err(NoteURLError::Truncated));
+        assert_eq!(
+            NoteURLPayload::decode(&bs58::encode([9, 1, 2]).into_string()),
+            Err(NoteURLError::UnsupportedVersion(9))
+        );
+        assert_eq!(
+            NoteURLPayload::decode(&bs58::encode([2, 1, 2]).into_string()),
+            Err(NoteURLError::Truncated)
+        );
+
+        let mut bytes = vec![2];
+        bytes.extend_from_slice(&[0; 32]);
+        bytes.push(33);
+        assert_eq!(
+            NoteURLPayload::decode(&bs58::encode(bytes).into_string()),
+            Err(NoteURLError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn memo_length_is_limited() {
+        let memo = "a".repeat(MAX_NOTE_URL_MEMO_LEN + 1);
+
+        assert_eq!(
+            payload(3).with_memo(memo.clone()),
+            Err(NoteURLError::MemoTooLong(MAX_NOTE_URL_MEMO_LEN + 1))
+        );
+
+        // the field is public, so encoding checks it too
+        let payload = NoteURLPayload {
+            memo: Some(memo),
+            ..payload(3)
+        };
+        assert_eq!(
+            payload.encode_activity_url_payload(),
+            Err(NoteURLError::MemoTooLong(MAX_NOTE_URL_MEMO_LEN + 1))
+        );
+    }
+
+    #[test]
+    fn older_versions_reject_v3_fields() {
+        let with_memo = payload(2).with_memo("hi").unwrap();
+        let with_expiry = payload(2).with_expires_at(100);
+        let with_kind = NoteURLPayload {
+            note_kind: Some(Element::new(12345)),
+            ..payload(2)
+        };
+
+        for payload in [with_memo, with_expiry, with_kind] {
+            assert_eq!(
+                payload.encode_activity_url_payload(),
+                Err(NoteURLError::RequiresLatestVersion(2))
+            );
+            assert!(
+                payload
+                    .with_latest_version()
+                    .encode_activity_url_payload()
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn expiry_hint() {
+        let expiring = payload(3).with_expires_at(100);
+
+        assert!(!expiring.is_expired_at(99));
+        assert!(expiring.is_expired_at(100));
+        assert!(!payload(3).is_expired_at(u64::MAX));
+    }
+
+    #[test]
+    fn notes_are_encoded_as_v2_by_default() {
+        let input_note = InputNote::new_from_ephemeral_private_key(
+            Element::new(5),
+            Element::new(10),
+            bridged_polygon_usdc_note_kind(),
+        );
+
+        let decoded = round_trip(&NoteURLPayload::from(&input_note));
+
+        assert_eq!(decoded.version, NOTE_URL_VERSION);
+        assert_eq!(decoded.commitment(), input_note.note.commitment());
+    }
+
+    #[test]
+    fn v3_keeps_note_kind() {
+        let note_kind = Element::new(12345);
+        let input_note =
+            InputNote::new_from_ephemeral_private_key(Element::new(5), Element::new(10), note_kind);
+
+        let payload = NoteURLPayload::from(&input_note).with_latest_version();
+        let decoded = round_trip(&payload);
+
+        assert_eq!(decoded.version, LATEST_NOTE_URL_VERSION);
+        assert_eq!(decoded.commitment(), input_note.note.commitment());
+        assert_eq!(InputNote::from(&decoded).note.contract, note_kind);
+    }
+
     #[test]
     fn test_roundtrip_from_input_note() {
         // Create an InputNote
-        let input_note =
-            InputNote::new_from_ephemeral_private_key(Element::new(101), Element::new(1));
+        let input_note = InputNote::new_from_ephemeral_private_key(
+            Element::new(101),
+            Element::new(1),
+            bridged_polygon_usdc_note_kind(),
+        );
 
         // Convert to NoteURLPayload
         let payload: NoteURLPayload = (&input_note).into();
 
         // Encode
-        let encoded = payload.encode_activity_url_payload();
+        let encoded = payload.encode_activity_url_payload().unwrap();
 
         println!("encoded: {encoded}");
 
@@ -0,0 +1,311 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+use hash::hash_merge;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ts-rs")]
+use ts_rs::TS;
+
+use crate::traits::ToBytes;
+use crate::{InputNote, MerklePath, Note, UtxoProofBytes, UtxoPublicInput};
+
+/// The first message of a swap leg, in place of a [`UtxoKind`](crate::UtxoKind)
+pub const SWAP_KIND: u64 = 4;
+
+/// What one party of a swap gives and wants in return
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapTerms {
+    /// The note kind given
+    pub give_kind: Element,
+    /// The value given
+    pub give_value: Element,
+    /// The note kind wanted in return
+    pub want_kind: Element,
+    /// The value wanted in return
+    pub want_value: Element,
+}
+
+impl SwapTerms {
+    /// The terms of the other party of the swap
+    #[must_use]
+    pub fn counterpart(&self) -> Self {
+        Self {
+            give_kind: self.want_kind,
+            give_value: self.want_value,
+            want_kind: self.give_kind,
+            want_value: self.give_value,
+        }
+    }
+
+    /// Whether `other` gives exactly what these terms want, and wants exactly what they give
+    #[must_use]
+    pub fn matches(&self, other: &Self) -> bool {
+        self.counterpart() == *other
+    }
+
+    /// The messages of a swap leg with these terms
+    #[must_use]
+    pub fn messages(&self) -> [Element; 5] {
+        [
+            Element::new(SWAP_KIND),
+            self.give_kind,
+            self.give_value,
+            self.want_kind,
+            self.want_value,
+        ]
+    }
+}
+
+/// One party's leg of an atomic swap of two note kinds
+///
+/// Each party proves their own leg, so neither shares their secret keys with the other. A leg
+/// gives notes of one kind and receives a note of another. The two legs are submitted together
+/// as a [`SwapProof`], and the aggregation only accepts a leg next to a counterpart leg with
+/// [matching](SwapTerms::matches) terms, which makes the swap atomic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapLeg {
+    /// The notes given, all of the given kind
+    pub input_notes: [InputNote; 2],
+    /// The note received from the other party, of the wanted kind
+    pub received: Note,
+    /// The party's change, of the given kind
+    pub change: Note,
+    /// The root of the tree the input notes are spent from
+    #[serde(default)]
+    pub root: Element,
+    /// The merkle paths of the input notes in the tree at `root`, unused for padding notes
+    #[serde(default)]
+    pub input_merkle_paths: [MerklePath<161>; 2],
+}
+
+impl SwapLeg {
+    /// Create a leg giving `input_notes` and receiving `received`, with the rest of the inputs
+    /// returned as `change`
+    #[must_use]
+    pub fn new(input_notes: [InputNote; 2], received: Note, change: Note) -> Self {
+        Self {
+            input_notes,
+            received,
+            change,
+            root: Element::ZERO,
+            input_merkle_paths: Default::default(),
+        }
+    }
+
+    /// Spend the input notes from the tree at `root`, see [`Utxo::with_merkle_paths`]
+    ///
+    /// [`Utxo::with_merkle_paths`]: crate::Utxo::with_merkle_paths
+    #[must_use]
+    pub fn with_merkle_paths(
+        mut self,
+        root: Element,
+        input_merkle_paths: [MerklePath<161>; 2],
+    ) -> Self {
+        self.root = root;
+        self.input_merkle_paths = input_merkle_paths;
+        self
+    }
+
+    /// The terms of the leg
+    #[must_use]
+    pub fn terms(&self) -> SwapTerms {
+        let give_kind = match self.input_notes[0].note.value.is_zero() {
+            true => self.input_notes[1].note.contract,
+            false => self.input_notes[0].note.contract,
+        };
+
+        SwapTerms {
+            give_kind,
+            give_value: self.input_value() - self.change.value,
+            want_kind: self.received.contract,
+            want_value: self.received.value,
+        }
+    }
+
+    /// The output notes, in the order of the circuit
+    #[must_use]
+    pub fn output_notes(&self) -> [Note; 2] {
+        [self.received.clone(), self.change.clone()]
+    }
+
+    /// Get the input value for the leg
+    #[must_use]
+    pub fn input_value(&self) -> Element {
+        self.input_notes[0].note.value + self.input_notes[1].note.value
+    }
+
+    /// Get the public inputs for the leg
+    #[must_use]
+    pub fn public_inputs(&self) -> SwapLegPublicInput {
+        SwapLegPublicInput(UtxoPublicInput {
+            root: self.root,
+            nullifiers: [
+                self.input_notes[0].nullifier(),
+                self.input_notes[1].nullifier(),
+            ],
+            output_commitments: [self.received.commitment(), self.change.commitment()],
+            messages: self.terms().messages(),
+        })
+    }
+}
+
+/// The public input for a swap leg, with the same layout as a [`UtxoPublicInput`] so legs are
+/// aggregated like utxo proofs
+#[derive(
+    Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct SwapLegPublicInput(pub UtxoPublicInput);
+
+impl SwapLegPublicInput {
+    /// The terms of the leg, from its messages
+    #[must_use]
+    pub fn terms(&self) -> SwapTerms {
+        let [_, give_kind, give_value, want_kind, want_value] = self.0.messages;
+
+        SwapTerms {
+            give_kind,
+            give_value,
+            want_kind,
+            want_value,
+        }
+    }
+
+    /// Hash the public input, can be used to uniquely identify the leg
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        self.0.hash()
+    }
+}
+
+/// SwapLegProof is the proof of a swap leg
+#[derive(Default, Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct SwapLegProof {
+    /// The proof for the leg
+    #[cfg_attr(feature = "ts-rs", ts(type = "string"))]
+    pub proof: UtxoProofBytes,
+    /// The public input for the leg
+    pub public_inputs: SwapLegPublicInput,
+}
+
+impl PartialEq for SwapLegProof {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_inputs == other.public_inputs
+    }
+}
+
+impl Eq for SwapLegProof {}
+
+impl ToBytes for SwapLegProof {
+    fn to_bytes(&self) -> Vec<u8> {
+        let pi = self.public_inputs.0.to_bytes();
+        [pi.as_slice(), self.proof.0.as_slice()].concat()
+    }
+}
+
+impl SwapLegProof {
+    /// Hash the SwapLegProof, can be used to uniquely identify the leg
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        self.public_inputs.hash()
+    }
+}
+
+/// SwapProof is an atomic swap, the proofs of both of its legs. The legs are aggregated next to
+/// each other, and are only valid together
+#[derive(
+    Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct SwapProof {
+    /// The legs of the swap, one per party
+    pub legs: [SwapLegProof; 2],
+}
+
+impl ToBytes for SwapProof {
+    fn to_bytes(&self) -> Vec<u8> {
+        [self.legs[0].to_bytes(), self.legs[1].to_bytes()].concat()
+    }
+}
+
+impl SwapProof {
+    /// Create a swap from the proofs of its two legs
+    #[must_use]
+    pub fn new(legs: [SwapLegProof; 2]) -> Self {
+        Self { legs }
+    }
+
+    /// Hash the SwapProof, can be used to uniquely identify the swap
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        hash_merge([self.legs[0].hash(), self.legs[1].hash()])
+    }
+
+    /// Whether each leg gives exactly what the other leg wants
+    #[must_use]
+    pub fn legs_match(&self) -> bool {
+        self.legs[0]
+            .public_inputs
+            .terms()
+            .matches(&self.legs[1].public_inputs.terms())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bridged_polygon_usdc_note_kind, get_address_for_private_key};
+
+    #[test]
+    fn legs_match() {
+        let usdc = bridged_polygon_usdc_note_kind();
+        let other = Element::new(5);
+        let alice_key = Element::new(101);
+        let alice = get_address_for_private_key(alice_key);
+        let bob_key = Element::new(202);
+        let bob = get_address_for_private_key(bob_key);
+
+        let alice_leg = SwapLeg::new(
+            [
+                InputNote::new(Note::new(alice, Element::new(7), usdc), alice_key),
+                InputNote::new(Note::new(alice, Element::new(5), usdc), alice_key),
+            ],
+            Note::new(alice, Element::new(3), other),
+            Note::new(alice, Element::new(2), usdc),
+        );
+        let bob_leg = SwapLeg::new(
+            [
+                InputNote::padding_note(),
+                InputNote::new(Note::new(bob, Element::new(3), other), bob_key),
+            ],
+            Note::new(bob, Element::new(10), usdc),
+            Note::padding_note(),
+        );
+
+        let alice_terms = alice_leg.terms();
+        assert_eq!(
+            alice_terms,
+            SwapTerms {
+                give_kind: usdc,
+                give_value: Element::new(10),
+                want_kind: other,
+                want_value: Element::new(3),
+            }
+        );
+        assert!(alice_terms.matches(&bob_leg.terms()));
+        assert_eq!(alice_leg.public_inputs().terms(), alice_terms);
+        assert_eq!(
+            alice_leg.public_inputs().0.messages[0],
+            Element::new(SWAP_KIND)
+        );
+
+        let greedy_bob = SwapTerms {
+            want_value: Element::new(11),
+            ..bob_leg.terms()
+        };
+        assert!(!alice_terms.matches(&greedy_bob));
+    }
+}
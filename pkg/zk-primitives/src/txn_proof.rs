@@ -0,0 +1,376 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ts-rs")]
+use ts_rs::TS;
+
+use crate::traits::ToBytes;
+use crate::{
+    SwapLegProof, SwapProof, Utxo4Proof, UtxoKind, UtxoKindMessages, UtxoProof, UtxoProofV1,
+};
+
+/// The proof of a transaction, tagged by the circuit it's from. Blocks accept a proof from any of
+/// these circuits, and the aggregation verifies it in one [slot](TxnProof::slots), or two for a
+/// swap
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[serde(untagged)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub enum TxnProof {
+    /// A [`Utxo`](crate::Utxo) proof
+    Utxo(UtxoProof),
+    /// A [`Utxo4`](crate::Utxo4) proof
+    Utxo4(Utxo4Proof),
+    /// An atomic swap, the proofs of both of its [`SwapLeg`](crate::SwapLeg)s
+    Swap(SwapProof),
+    /// A single leg of a swap, only valid in the aggregation slot next to its counterpart. Never
+    /// a transaction on its own, see [`TxnProof::slots`]
+    #[serde(skip)]
+    #[cfg_attr(feature = "ts-rs", ts(skip))]
+    SwapLeg(SwapLegProof),
+    /// A utxo proof from before notes were spent by nullifier, only read from old blocks and
+    /// transactions. Its inputs were spent by removing their commitments from the tree, so it
+    /// has no root or nullifiers, and the current circuits can't verify or aggregate it
+    UtxoV1(UtxoProofV1),
+}
+
+impl Default for TxnProof {
+    fn default() -> Self {
+        Self::Utxo(UtxoProof::default())
+    }
+}
+
+impl From<UtxoProof> for TxnProof {
+    fn from(proof: UtxoProof) -> Self {
+        Self::Utxo(proof)
+    }
+}
+
+impl From<UtxoProofV1> for TxnProof {
+    fn from(proof: UtxoProofV1) -> Self {
+        Self::UtxoV1(proof)
+    }
+}
+
+impl From<Utxo4Proof> for TxnProof {
+    fn from(proof: Utxo4Proof) -> Self {
+        Self::Utxo4(proof)
+    }
+}
+
+impl From<SwapProof> for TxnProof {
+    fn from(proof: SwapProof) -> Self {
+        Self::Swap(proof)
+    }
+}
+
+impl ToBytes for TxnProof {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Utxo(proof) => proof.to_bytes(),
+            Self::Utxo4(proof) => proof.to_bytes(),
+            Self::Swap(proof) => proof.to_bytes(),
+            Self::SwapLeg(proof) => proof.to_bytes(),
+            Self::UtxoV1(proof) => proof.to_bytes(),
+        }
+    }
+}
+
+impl TxnProof {
+    /// Hash the proof's public inputs, can be used to uniquely identify the transaction
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        match self {
+            Self::Utxo(proof) => proof.hash(),
+            Self::Utxo4(proof) => proof.hash(),
+            Self::Swap(proof) => proof.hash(),
+            Self::SwapLeg(proof) => proof.hash(),
+            Self::UtxoV1(proof) => proof.hash(),
+        }
+    }
+
+    /// The proofs the transaction fills aggregation slots with, in order. A swap fills a slot
+    /// per leg, the other proofs fill a single slot
+    #[must_use]
+    pub fn slots(&self) -> Vec<TxnProof> {
+        match self {
+            Self::Swap(proof) => proof.legs.iter().cloned().map(Self::SwapLeg).collect(),
+            _ => vec![self.clone()],
+        }
+    }
+
+    /// The root of the tree the input notes are in, zero if there are no inputs. The legs of a
+    /// swap each have their own root, so for a swap this is the root of its first leg, see
+    /// [`slots`](Self::slots)
+    #[must_use]
+    pub fn root(&self) -> Element {
+        match self {
+            Self::Utxo(proof) => proof.public_inputs.root,
+            Self::Utxo4(proof) => proof.public_inputs.root,
+            Self::Swap(proof) => proof.legs[0].public_inputs.0.root,
+            Self::SwapLeg(proof) => proof.public_inputs.0.root,
+            Self::UtxoV1(_) => Element::ZERO,
+        }
+    }
+
+    /// The nullifiers of the input notes, zero for padding notes. Legacy utxo proofs have none
+    #[must_use]
+    pub fn nullifiers(&self) -> Vec<Element> {
+        match self {
+            Self::Utxo(proof) => proof.public_inputs.nullifiers.to_vec(),
+            Self::Utxo4(proof) => proof.public_inputs.nullifiers.to_vec(),
+            Self::Swap(proof) => proof
+                .legs
+                .iter()
+                .flat_map(|leg| leg.public_inputs.0.nullifiers)
+                .collect(),
+            Self::SwapLeg(proof) => proof.public_inputs.0.nullifiers.to_vec(),
+            Self::UtxoV1(_) => Vec::new(),
+        }
+    }
+
+    /// The output commitments, zero for padding notes
+    #[must_use]
+    pub fn output_commitments(&self) -> Vec<Element> {
+        match self {
+            Self::Utxo(proof) => proof.public_inputs.output_commitments.to_vec(),
+            Self::Utxo4(proof) => proof.public_inputs.output_commitments.to_vec(),
+            Self::Swap(proof) => proof
+                .legs
+                .iter()
+                .flat_map(|leg| leg.public_inputs.0.output_commitments)
+                .collect(),
+            Self::SwapLeg(proof) => proof.public_inputs.0.output_commitments.to_vec(),
+            Self::UtxoV1(proof) => proof.public_inputs.output_commitments.to_vec(),
+        }
+    }
+
+    /// Get the leaves the proof inserts into the tree, its nullifiers then its output
+    /// commitments, leg by leg for a swap. Zero leaves are padding and aren't inserted. Legacy
+    /// utxo proofs only inserted their output commitments
+    #[must_use]
+    pub fn leaves(&self) -> Vec<Element> {
+        match self {
+            Self::Utxo(proof) => proof.public_inputs.leaves().to_vec(),
+            Self::Utxo4(proof) => proof.public_inputs.leaves().to_vec(),
+            Self::Swap(proof) => proof
+                .legs
+                .iter()
+                .flat_map(|leg| leg.public_inputs.0.leaves())
+                .collect(),
+            Self::SwapLeg(proof) => proof.public_inputs.0.leaves().to_vec(),
+            Self::UtxoV1(proof) => proof.public_inputs.output_commitments.to_vec(),
+        }
+    }
+
+    /// Whether the proof spends any input notes, and so depends on [`root`](Self::root)
+    #[must_use]
+    pub fn has_inputs(&self) -> bool {
+        match self {
+            Self::Utxo(proof) => proof.public_inputs.has_inputs(),
+            Self::Utxo4(proof) => proof.public_inputs.has_inputs(),
+            Self::Swap(proof) => proof
+                .legs
+                .iter()
+                .any(|leg| leg.public_inputs.0.has_inputs()),
+            Self::SwapLeg(proof) => proof.public_inputs.0.has_inputs(),
+            Self::UtxoV1(_) => false,
+        }
+    }
+
+    /// Get the commit hash for the proof, the commit hashes of its legs merged for a swap
+    #[must_use]
+    pub fn commit_hash(&self) -> Element {
+        match self {
+            Self::Utxo(proof) => proof.public_inputs.commit_hash(),
+            Self::Utxo4(proof) => proof.public_inputs.commit_hash(),
+            Self::Swap(proof) => hash::hash_merge(
+                proof
+                    .legs
+                    .each_ref()
+                    .map(|leg| leg.public_inputs.0.commit_hash()),
+            ),
+            Self::SwapLeg(proof) => proof.public_inputs.0.commit_hash(),
+            Self::UtxoV1(proof) => proof.public_inputs.commit_hash(),
+        }
+    }
+
+    /// Get the messages of the transaction. Utxo4 proofs are always sends, so only have a kind.
+    /// Swap legs have their [terms](crate::SwapTerms), and a swap the terms of its first leg
+    #[must_use]
+    pub fn messages(&self) -> [Element; 5] {
+        match self {
+            Self::Utxo(proof) => proof.public_inputs.messages,
+            Self::Utxo4(proof) => [
+                proof.public_inputs.kind,
+                Element::ZERO,
+                Element::ZERO,
+                Element::ZERO,
+                Element::ZERO,
+            ],
+            Self::Swap(proof) => proof.legs[0].public_inputs.0.messages,
+            Self::SwapLeg(proof) => proof.public_inputs.0.messages,
+            Self::UtxoV1(proof) => proof.public_inputs.messages,
+        }
+    }
+
+    /// Get the kind of the transaction. Swaps only move notes between their parties, so are
+    /// sends
+    #[must_use]
+    pub fn kind(&self) -> UtxoKind {
+        match self {
+            Self::Utxo(proof) => proof.kind(),
+            Self::Utxo4(proof) => proof.public_inputs.kind(),
+            Self::Swap(_) | Self::SwapLeg(_) => UtxoKind::Send,
+            Self::UtxoV1(proof) => proof.public_inputs.kind(),
+        }
+    }
+
+    /// Get the mint/burn messages of the transaction
+    #[must_use]
+    pub fn kind_messages(&self) -> UtxoKindMessages {
+        match self {
+            Self::Utxo(proof) => proof.kind_messages(),
+            Self::UtxoV1(proof) => proof.public_inputs.kind_messages(),
+            Self::Utxo4(_) | Self::Swap(_) | Self::SwapLeg(_) => UtxoKindMessages::None,
+        }
+    }
+
+    /// Gets the hash of the mint/burn, otherwise None
+    #[must_use]
+    pub fn mint_burn_hash(&self) -> Option<Element> {
+        match self {
+            Self::Utxo(proof) => proof.mint_burn_hash(),
+            Self::UtxoV1(proof) => proof.public_inputs.mint_burn_hash(),
+            Self::Utxo4(_) | Self::Swap(_) | Self::SwapLeg(_) => None,
+        }
+    }
+
+    /// Returns true if the proof is a padding proof
+    #[must_use]
+    pub fn is_padding(&self) -> bool {
+        self.kind() == UtxoKind::Null
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        SwapLegPublicInput, SwapTerms, Utxo4PublicInput, UtxoProofBytes, UtxoPublicInput,
+        UtxoPublicInputV1,
+    };
+
+    fn swap() -> SwapProof {
+        let terms = SwapTerms {
+            give_kind: Element::new(10),
+            give_value: Element::new(7),
+            want_kind: Element::new(20),
+            want_value: Element::new(3),
+        };
+        let leg = |offset: u64, terms: SwapTerms| SwapLegProof {
+            proof: UtxoProofBytes(vec![1, 2, 3]),
+            public_inputs: SwapLegPublicInput(UtxoPublicInput {
+                root: Element::new(1),
+                nullifiers: [Element::new(offset + 1), Element::ZERO],
+                output_commitments: [Element::new(offset + 2), Element::new(offset + 3)],
+                messages: terms.messages(),
+            }),
+        };
+
+        SwapProof::new([leg(100, terms), leg(200, terms.counterpart())])
+    }
+
+    #[test]
+    fn json_is_untagged() {
+        let utxo = TxnProof::Utxo(UtxoProof {
+            proof: UtxoProofBytes(vec![1, 2, 3]),
+            public_inputs: UtxoPublicInput {
+                root: Element::new(1),
+                nullifiers: [Element::new(2), Element::ZERO],
+                output_commitments: [Element::new(3), Element::ZERO],
+                messages: [
+                    Element::new(1),
+                    Element::ZERO,
+                    Element::ZERO,
+                    Element::ZERO,
+                    Element::ZERO,
+                ],
+            },
+        });
+        let utxo4 = TxnProof::Utxo4(Utxo4Proof {
+            proof: UtxoProofBytes(vec![1, 2, 3]),
+            public_inputs: Utxo4PublicInput {
+                root: Element::new(1),
+                nullifiers: [
+                    Element::new(2),
+                    Element::new(3),
+                    Element::ZERO,
+                    Element::ZERO,
+                ],
+                output_commitments: [Element::new(4), Element::ZERO, Element::ZERO, Element::ZERO],
+                kind: Element::new(1),
+            },
+        });
+
+        let utxo_v1 = TxnProof::UtxoV1(UtxoProofV1 {
+            proof: UtxoProofBytes(vec![1, 2, 3]),
+            public_inputs: UtxoPublicInputV1 {
+                input_commitments: [Element::new(2), Element::ZERO],
+                output_commitments: [Element::new(3), Element::ZERO],
+                messages: [
+                    Element::new(1),
+                    Element::ZERO,
+                    Element::ZERO,
+                    Element::ZERO,
+                    Element::ZERO,
+                ],
+            },
+        });
+
+        for proof in [utxo, utxo4, TxnProof::Swap(swap()), utxo_v1] {
+            let json = serde_json::to_string(&proof).unwrap();
+            assert_eq!(serde_json::from_str::<TxnProof>(&json).unwrap(), proof);
+
+            let bytes = borsh::to_vec(&proof).unwrap();
+            assert_eq!(borsh::from_slice::<TxnProof>(&bytes).unwrap(), proof);
+        }
+
+        // Utxo proofs keep the JSON of a plain `UtxoProof`
+        let utxo_proof = UtxoProof::default();
+        let json = serde_json::to_string(&utxo_proof).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TxnProof>(&json).unwrap(),
+            TxnProof::Utxo(utxo_proof)
+        );
+    }
+
+    #[test]
+    fn swap_fills_a_slot_per_leg() {
+        let swap = swap();
+        assert!(swap.legs_match());
+
+        let txn = TxnProof::from(swap.clone());
+        let slots = txn.slots();
+        assert_eq!(
+            slots,
+            [
+                TxnProof::SwapLeg(swap.legs[0].clone()),
+                TxnProof::SwapLeg(swap.legs[1].clone()),
+            ]
+        );
+
+        // The swap inserts the leaves of its legs, in slot order
+        assert_eq!(
+            txn.leaves(),
+            slots.iter().flat_map(TxnProof::leaves).collect::<Vec<_>>()
+        );
+        assert_eq!(txn.nullifiers().len(), 4);
+        assert_eq!(txn.kind(), UtxoKind::Send);
+        assert_eq!(TxnProof::default().slots(), [TxnProof::default()]);
+
+        let mut greedy = swap;
+        greedy.legs[1].public_inputs.0.messages[4] = Element::new(4);
+        assert!(!greedy.legs_match());
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt::Debug;
 
 use crate::impl_serde_for_element_array;
 use crate::traits::ToBytes;
-use crate::{InputNote, Note, bytes_to_elements};
+use crate::{InputNote, MerklePath, Note, bytes_to_elements};
 use borsh::{BorshDeserialize, BorshSerialize};
 use element::{Base, Element};
 use hash::hash_merge;
@@ -12,7 +12,7 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 /// Number of public input fields for utxo proof
-pub const UTXO_PUBLIC_INPUTS_COUNT: usize = 9;
+pub const UTXO_PUBLIC_INPUTS_COUNT: usize = 10;
 /// Number of fields in the proof
 pub const UTXO_PROOF_SIZE: usize = 508;
 
@@ -27,6 +27,12 @@ pub struct Utxo {
     pub output_notes: [Note; 2],
     /// The burn address
     pub burn_address: Option<Element>,
+    /// The root of the tree the input notes are spent from
+    #[serde(default)]
+    pub root: Element,
+    /// The merkle paths of the input notes in the tree at `root`, unused for padding notes
+    #[serde(default)]
+    pub input_merkle_paths: [MerklePath<161>; 2],
 }
 
 impl Utxo {
@@ -43,6 +49,8 @@ impl Utxo {
             input_notes,
             output_notes,
             burn_address,
+            root: Element::ZERO,
+            input_merkle_paths: Default::default(),
         }
     }
 
@@ -54,6 +62,8 @@ impl Utxo {
             input_notes,
             output_notes,
             burn_address: None,
+            root: Element::ZERO,
+            input_merkle_paths: Default::default(),
         }
     }
 
@@ -65,6 +75,8 @@ impl Utxo {
             input_notes,
             output_notes: [Note::padding_note(), Note::padding_note()],
             burn_address: Some(evm_address),
+            root: Element::ZERO,
+            input_merkle_paths: Default::default(),
         }
     }
 
@@ -76,16 +88,31 @@ impl Utxo {
             burn_address: None,
             input_notes: [InputNote::padding_note(), InputNote::padding_note()],
             output_notes,
+            root: Element::ZERO,
+            input_merkle_paths: Default::default(),
         }
     }
 
-    /// Get the leaf elements for the Utxo transaction, these will be inserted
-    /// or removed from the tree
+    /// Spend the input notes from the tree at `root`, where `input_merkle_paths` are their
+    /// paths. The root must be one the tree has had, see [`root_leaf`](crate::root_leaf)
+    #[must_use]
+    pub fn with_merkle_paths(
+        mut self,
+        root: Element,
+        input_merkle_paths: [MerklePath<161>; 2],
+    ) -> Self {
+        self.root = root;
+        self.input_merkle_paths = input_merkle_paths;
+        self
+    }
+
+    /// Get the leaf elements for the Utxo transaction, the nullifiers and output commitments
+    /// that will be inserted into the tree
     #[must_use]
     pub fn leaf_elements(&self) -> [Element; 4] {
         [
-            self.input_notes[0].note.commitment(),
-            self.input_notes[1].note.commitment(),
+            self.input_notes[0].nullifier(),
+            self.input_notes[1].nullifier(),
             self.output_notes[0].commitment(),
             self.output_notes[1].commitment(),
         ]
@@ -132,10 +159,10 @@ impl Utxo {
         hash_merge([self.output_notes[0].psi, self.output_notes[1].psi])
     }
 
-    /// Get the burn hash
+    /// Get the burn hash, the nullifier of the first input
     #[must_use]
     pub fn burn_hash(&self) -> Element {
-        self.input_notes[0].note.commitment()
+        self.input_notes[0].nullifier()
     }
 
     /// Get the input value for the Utxo transaction
@@ -154,9 +181,10 @@ impl Utxo {
     #[must_use]
     pub fn public_inputs(&self) -> UtxoPublicInput {
         UtxoPublicInput {
-            input_commitments: [
-                self.input_notes[0].note.commitment(),
-                self.input_notes[1].note.commitment(),
+            root: self.root,
+            nullifiers: [
+                self.input_notes[0].nullifier(),
+                self.input_notes[1].nullifier(),
             ],
             output_commitments: [
                 self.output_notes[0].commitment(),
@@ -174,6 +202,8 @@ impl Utxo {
             input_notes: [InputNote::padding_note(), InputNote::padding_note()],
             output_notes: [Note::padding_note(), Note::padding_note()],
             burn_address: None,
+            root: Element::ZERO,
+            input_merkle_paths: Default::default(),
         }
     }
 
@@ -277,9 +307,12 @@ impl UtxoProofBytes {
 #[cfg_attr(feature = "ts-rs", derive(TS))]
 #[cfg_attr(feature = "ts-rs", ts(export))]
 pub struct UtxoPublicInput {
-    /// The input commitments
+    /// The root of the tree the input notes are in, zero if there are no inputs
+    #[cfg_attr(feature = "ts-rs", ts(as = "String"))]
+    pub root: Element,
+    /// The nullifiers of the input notes, zero for padding notes
     #[cfg_attr(feature = "ts-rs", ts(as = "[String; 2]"))]
-    pub input_commitments: [Element; 2],
+    pub nullifiers: [Element; 2],
     /// The output commitments
     #[cfg_attr(feature = "ts-rs", ts(as = "[String; 2]"))]
     pub output_commitments: [Element; 2],
@@ -293,8 +326,9 @@ impl UtxoPublicInput {
     #[must_use]
     pub fn to_bytes(&self) -> Vec<u8> {
         [
-            self.input_commitments[0].to_be_bytes(),
-            self.input_commitments[1].to_be_bytes(),
+            self.root.to_be_bytes(),
+            self.nullifiers[0].to_be_bytes(),
+            self.nullifiers[1].to_be_bytes(),
             self.output_commitments[0].to_be_bytes(),
             self.output_commitments[1].to_be_bytes(),
             self.messages[0].to_be_bytes(),
@@ -308,10 +342,11 @@ impl UtxoPublicInput {
 
     /// Fields
     #[must_use]
-    pub fn fields(&self) -> [Element; 9] {
+    pub fn fields(&self) -> [Element; UTXO_PUBLIC_INPUTS_COUNT] {
         [
-            self.input_commitments[0],
-            self.input_commitments[1],
+            self.root,
+            self.nullifiers[0],
+            self.nullifiers[1],
             self.output_commitments[0],
             self.output_commitments[1],
             self.messages[0],
@@ -322,21 +357,28 @@ impl UtxoPublicInput {
         ]
     }
 
-    /// Get the commitments for the UtxoPublicInput
+    /// Get the leaves the Utxo proof inserts into the tree, its nullifiers and output
+    /// commitments. Zero leaves are padding and aren't inserted
     #[must_use]
-    pub fn commitments(&self) -> [Element; 4] {
+    pub fn leaves(&self) -> [Element; 4] {
         [
-            self.input_commitments[0],
-            self.input_commitments[1],
+            self.nullifiers[0],
+            self.nullifiers[1],
             self.output_commitments[0],
             self.output_commitments[1],
         ]
     }
 
+    /// Whether the Utxo proof spends any input notes, and so depends on `root`
+    #[must_use]
+    pub fn has_inputs(&self) -> bool {
+        self.nullifiers.iter().any(|n| *n != Element::ZERO)
+    }
+
     /// Get the commit hash for the Utxo proof
     #[must_use]
     pub fn commit_hash(&self) -> Element {
-        hash_merge(self.commitments())
+        hash_merge(self.leaves())
     }
 
     /// Hash the UtxoProof
@@ -0,0 +1,258 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+use hash::hash_merge;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ts-rs")]
+use ts_rs::TS;
+
+use crate::traits::ToBytes;
+use crate::{InputNote, MerklePath, Note, UtxoKind, UtxoProofBytes};
+
+/// Number of public input fields for utxo4 proof, the same as for a utxo proof
+pub const UTXO4_PUBLIC_INPUTS_COUNT: usize = 10;
+
+/// Domain separator for [`Utxo4PublicInput::hash`], so it can't collide with a
+/// [`UtxoPublicInput`](crate::UtxoPublicInput) hash
+const UTXO4_HASH_DOMAIN: u64 = 4;
+
+/// Utxo4 is the data required to prove a send with up to four inputs and four outputs, so notes
+/// can be consolidated in fewer transactions than with [`Utxo`](crate::Utxo)
+///
+/// Only sends are supported, mints and burns use [`Utxo`](crate::Utxo)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Utxo4 {
+    /// The input notes (being spent)
+    pub input_notes: [InputNote; 4],
+    /// The output notes (being created)
+    pub output_notes: [Note; 4],
+    /// The root of the tree the input notes are spent from
+    #[serde(default)]
+    pub root: Element,
+    /// The merkle paths of the input notes in the tree at `root`, unused for padding notes
+    #[serde(default)]
+    pub input_merkle_paths: [MerklePath<161>; 4],
+}
+
+impl Utxo4 {
+    /// Create a new send transaction
+    #[must_use]
+    pub fn new_send(input_notes: [InputNote; 4], output_notes: [Note; 4]) -> Self {
+        Self {
+            input_notes,
+            output_notes,
+            root: Element::ZERO,
+            input_merkle_paths: Default::default(),
+        }
+    }
+
+    /// Spend the input notes from the tree at `root`, where `input_merkle_paths` are their
+    /// paths, see [`Utxo::with_merkle_paths`](crate::Utxo::with_merkle_paths)
+    #[must_use]
+    pub fn with_merkle_paths(
+        mut self,
+        root: Element,
+        input_merkle_paths: [MerklePath<161>; 4],
+    ) -> Self {
+        self.root = root;
+        self.input_merkle_paths = input_merkle_paths;
+        self
+    }
+
+    /// Get the leaf elements for the Utxo4 transaction, the nullifiers and output commitments
+    /// that will be inserted into the tree
+    #[must_use]
+    pub fn leaf_elements(&self) -> [Element; 8] {
+        self.public_inputs().leaves()
+    }
+
+    /// Get the input value for the Utxo4 transaction
+    #[must_use]
+    pub fn input_value(&self) -> Element {
+        self.input_notes.iter().map(|input| input.note.value).sum()
+    }
+
+    /// Get the output value for the Utxo4 transaction
+    #[must_use]
+    pub fn output_value(&self) -> Element {
+        self.output_notes.iter().map(|note| note.value).sum()
+    }
+
+    /// Get the public inputs for the Utxo4 transaction
+    #[must_use]
+    pub fn public_inputs(&self) -> Utxo4PublicInput {
+        Utxo4PublicInput {
+            root: self.root,
+            nullifiers: self.input_notes.each_ref().map(InputNote::nullifier),
+            output_commitments: self.output_notes.each_ref().map(Note::commitment),
+            kind: UtxoKind::Send.to_element(),
+        }
+    }
+}
+
+/// The public input for a Utxo4 transaction
+#[derive(
+    Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct Utxo4PublicInput {
+    /// The root of the tree the input notes are in, zero if there are no inputs
+    #[cfg_attr(feature = "ts-rs", ts(as = "String"))]
+    pub root: Element,
+    /// The nullifiers of the input notes, zero for padding notes
+    #[cfg_attr(feature = "ts-rs", ts(as = "[String; 4]"))]
+    pub nullifiers: [Element; 4],
+    /// The output commitments
+    #[cfg_attr(feature = "ts-rs", ts(as = "[String; 4]"))]
+    pub output_commitments: [Element; 4],
+    /// The kind of the transaction, always a send. A send's other messages are zero, so the
+    /// circuit leaves them out
+    #[cfg_attr(feature = "ts-rs", ts(as = "String"))]
+    pub kind: Element,
+}
+
+impl Utxo4PublicInput {
+    /// Convert the Utxo4PublicInput to bytes
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.fields()
+            .into_iter()
+            .flat_map(Element::to_be_bytes)
+            .collect()
+    }
+
+    /// Fields, in the order of the circuit's public inputs
+    #[must_use]
+    pub fn fields(&self) -> [Element; UTXO4_PUBLIC_INPUTS_COUNT] {
+        let mut fields = [Element::ZERO; UTXO4_PUBLIC_INPUTS_COUNT];
+        fields[0] = self.root;
+        fields[1..9].copy_from_slice(&self.leaves());
+        fields[9] = self.kind;
+        fields
+    }
+
+    /// Get the leaves the Utxo4 proof inserts into the tree, its nullifiers and output
+    /// commitments. Zero leaves are padding and aren't inserted
+    #[must_use]
+    pub fn leaves(&self) -> [Element; 8] {
+        let mut leaves = [Element::ZERO; 8];
+        leaves[..4].copy_from_slice(&self.nullifiers);
+        leaves[4..].copy_from_slice(&self.output_commitments);
+        leaves
+    }
+
+    /// Whether the Utxo4 proof spends any input notes, and so depends on `root`
+    #[must_use]
+    pub fn has_inputs(&self) -> bool {
+        self.nullifiers.iter().any(|n| *n != Element::ZERO)
+    }
+
+    /// Get the commit hash for the Utxo4 proof
+    #[must_use]
+    pub fn commit_hash(&self) -> Element {
+        hash_merge(self.leaves())
+    }
+
+    /// Hash the Utxo4PublicInput
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        let mut elements = [Element::new(UTXO4_HASH_DOMAIN); UTXO4_PUBLIC_INPUTS_COUNT + 1];
+        elements[1..].copy_from_slice(&self.fields());
+        hash_merge(elements)
+    }
+
+    /// Get the kind of the Utxo4 transaction
+    #[must_use]
+    pub fn kind(&self) -> UtxoKind {
+        UtxoKind::from(self.kind)
+    }
+}
+
+/// Utxo4Proof is the proof of a Utxo4 transaction
+#[derive(Default, Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct Utxo4Proof {
+    /// The proof for the Utxo4 transaction
+    #[cfg_attr(feature = "ts-rs", ts(type = "string"))]
+    pub proof: UtxoProofBytes,
+    /// The public input for the Utxo4 transaction
+    pub public_inputs: Utxo4PublicInput,
+}
+
+impl PartialEq for Utxo4Proof {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_inputs == other.public_inputs
+    }
+}
+
+impl Eq for Utxo4Proof {}
+
+impl ToBytes for Utxo4Proof {
+    fn to_bytes(&self) -> Vec<u8> {
+        let pi = self.public_inputs.to_bytes();
+        [pi.as_slice(), self.proof.0.as_slice()].concat()
+    }
+}
+
+impl Utxo4Proof {
+    /// Hash the Utxo4Proof, can be used to uniquely identify the Utxo4Proof
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        self.public_inputs.hash()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{UtxoPublicInput, bridged_polygon_usdc_note_kind, get_address_for_private_key};
+
+    #[test]
+    fn public_inputs_layout() {
+        let secret_key = Element::new(101);
+        let address = get_address_for_private_key(secret_key);
+        let kind = bridged_polygon_usdc_note_kind();
+
+        let input_notes = [1, 2, 3, 4].map(|psi| {
+            InputNote::new(
+                Note::new_with_psi(address, Element::new(5), Element::new(psi), kind),
+                secret_key,
+            )
+        });
+        let output_notes = [
+            Note::new_with_psi(address, Element::new(20), Element::new(5), kind),
+            Note::padding_note(),
+            Note::padding_note(),
+            Note::padding_note(),
+        ];
+        let utxo = Utxo4::new_send(input_notes.clone(), output_notes.clone())
+            .with_merkle_paths(Element::new(7), Default::default());
+
+        assert_eq!(utxo.input_value(), utxo.output_value());
+
+        let public_inputs = utxo.public_inputs();
+        let fields = public_inputs.fields();
+        assert_eq!(fields[0], Element::new(7));
+        assert_eq!(fields[1], input_notes[0].nullifier());
+        assert_eq!(fields[4], input_notes[3].nullifier());
+        assert_eq!(fields[5], output_notes[0].commitment());
+        assert_eq!(fields[6], Element::ZERO);
+        assert_eq!(fields[9], Element::new(1));
+        assert_eq!(public_inputs.kind(), UtxoKind::Send);
+        assert!(public_inputs.has_inputs());
+        assert_eq!(
+            public_inputs.to_bytes().len(),
+            UTXO4_PUBLIC_INPUTS_COUNT * 32
+        );
+
+        // The same fields as a utxo proof don't give the same hash
+        let utxo_public_inputs = UtxoPublicInput {
+            root: fields[0],
+            nullifiers: [fields[1], fields[2]],
+            output_commitments: [fields[3], fields[4]],
+            messages: [fields[5], fields[6], fields[7], fields[8], fields[9]],
+        };
+        assert_ne!(public_inputs.hash(), utxo_public_inputs.hash());
+    }
+}
@@ -0,0 +1,173 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use element::Element;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ts-rs")]
+use ts_rs::TS;
+
+use crate::traits::ToBytes;
+use crate::{UtxoKind, UtxoKindMessages, UtxoProofBytes, UtxoPublicInput};
+
+/// The public input of a utxo proof from before notes were spent by nullifier, when spending a
+/// note removed its commitment from the tree. Only used to read old blocks and transactions,
+/// see [`UtxoProofV1`]
+#[derive(
+    Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct UtxoPublicInputV1 {
+    /// The input commitments, removed from the tree by the transaction
+    #[cfg_attr(feature = "ts-rs", ts(as = "[String; 2]"))]
+    pub input_commitments: [Element; 2],
+    /// The output commitments
+    #[cfg_attr(feature = "ts-rs", ts(as = "[String; 2]"))]
+    pub output_commitments: [Element; 2],
+    /// The message of the transaction
+    #[cfg_attr(feature = "ts-rs", ts(as = "[String; 5]"))]
+    pub messages: [Element; 5],
+}
+
+impl UtxoPublicInputV1 {
+    /// Convert the UtxoPublicInputV1 to bytes
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.fields()
+            .iter()
+            .flat_map(Element::to_be_bytes)
+            .collect()
+    }
+
+    /// Fields
+    #[must_use]
+    pub fn fields(&self) -> [Element; 9] {
+        [
+            self.input_commitments[0],
+            self.input_commitments[1],
+            self.output_commitments[0],
+            self.output_commitments[1],
+            self.messages[0],
+            self.messages[1],
+            self.messages[2],
+            self.messages[3],
+            self.messages[4],
+        ]
+    }
+
+    /// Get the input and output commitments
+    #[must_use]
+    pub fn commitments(&self) -> [Element; 4] {
+        [
+            self.input_commitments[0],
+            self.input_commitments[1],
+            self.output_commitments[0],
+            self.output_commitments[1],
+        ]
+    }
+
+    /// Get the commit hash for the Utxo proof
+    #[must_use]
+    pub fn commit_hash(&self) -> Element {
+        hash::hash_merge(self.commitments())
+    }
+
+    /// Hash the public input, the hash the transaction was stored under
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        hash::hash_merge(self.fields())
+    }
+
+    /// The messages are laid out as in [`UtxoPublicInput`], so they are read the same way
+    fn with_messages(&self) -> UtxoPublicInput {
+        UtxoPublicInput {
+            messages: self.messages,
+            ..Default::default()
+        }
+    }
+
+    /// Get the kind of the Utxo proof
+    #[must_use]
+    pub fn kind(&self) -> UtxoKind {
+        self.with_messages().kind()
+    }
+
+    /// Get the kind messages associated with the kind
+    #[must_use]
+    pub fn kind_messages(&self) -> UtxoKindMessages {
+        self.with_messages().kind_messages()
+    }
+
+    /// Gets the hash of the mint/burn, otherwise None
+    #[must_use]
+    pub fn mint_burn_hash(&self) -> Option<Element> {
+        self.with_messages().mint_burn_hash()
+    }
+}
+
+/// A utxo proof from before notes were spent by nullifier
+///
+/// Old blocks and transactions keep these proofs in their original layout, so they still decode
+/// and keep their hashes. The current circuits can't verify or aggregate them
+#[derive(Default, Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "ts-rs", derive(TS))]
+#[cfg_attr(feature = "ts-rs", ts(export))]
+pub struct UtxoProofV1 {
+    /// The proof for the Utxo transaction
+    #[cfg_attr(feature = "ts-rs", ts(type = "string"))]
+    pub proof: UtxoProofBytes,
+    /// The public input for the Utxo transaction
+    pub public_inputs: UtxoPublicInputV1,
+}
+
+impl PartialEq for UtxoProofV1 {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_inputs == other.public_inputs
+    }
+}
+
+impl Eq for UtxoProofV1 {}
+
+impl ToBytes for UtxoProofV1 {
+    fn to_bytes(&self) -> Vec<u8> {
+        let pi = self.public_inputs.to_bytes();
+        [pi.as_slice(), self.proof.0.as_slice()].concat()
+    }
+}
+
+impl UtxoProofV1 {
+    /// Hash the UtxoProofV1, can be used to uniquely identify the UtxoProofV1
+    #[must_use]
+    pub fn hash(&self) -> Element {
+        self.public_inputs.hash()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UtxoProof;
+
+    /// Proofs stored before nullifiers have 9 public inputs, so they don't decode as the current
+    /// [`UtxoProof`]
+    #[test]
+    fn decodes_old_layout() {
+        let proof = UtxoProofV1 {
+            proof: UtxoProofBytes(vec![1, 2, 3]),
+            public_inputs: UtxoPublicInputV1 {
+                input_commitments: [Element::new(1), Element::ZERO],
+                output_commitments: [Element::new(2), Element::new(3)],
+                messages: [
+                    Element::new(1),
+                    Element::ZERO,
+                    Element::ZERO,
+                    Element::ZERO,
+                    Element::ZERO,
+                ],
+            },
+        };
+
+        let bytes = borsh::to_vec(&proof).unwrap();
+        assert_eq!(borsh::from_slice::<UtxoProofV1>(&bytes).unwrap(), proof);
+        assert!(borsh::from_slice::<UtxoProof>(&bytes).is_err());
+        assert_eq!(proof.public_inputs.kind(), UtxoKind::Send);
+    }
+}